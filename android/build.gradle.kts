@@ -96,10 +96,7 @@ tasks.register<Exec>("buildJniLibs") {
         "cargo",
         "build",
         "-p",
-        "ina",
-        "--no-default-features",
-        "--features",
-        "java-ffi,patch,sandbox",
+        "ina-android",
         "--target",
         "aarch64-linux-android",
         "--target",
@@ -110,11 +107,11 @@ tasks.register<Exec>("buildJniLibs") {
 
     doLast {
         copy {
-            from("$rootDir/target/aarch64-linux-android/cdylib-release/libina.so")
+            from("$rootDir/target/aarch64-linux-android/cdylib-release/libina_android.so")
             into("$projectDir/src/main/jniLibs/arm64-v8a")
         }
         copy {
-            from("$rootDir/target/x86_64-linux-android/cdylib-release/libina.so")
+            from("$rootDir/target/x86_64-linux-android/cdylib-release/libina_android.so")
             into("$projectDir/src/main/jniLibs/x86_64")
         }
     }
@@ -4,6 +4,7 @@
 
 package app.accrescent.ina
 
+import android.content.res.AssetFileDescriptor
 import android.os.Bundle
 import android.os.Handler
 import android.os.Looper
@@ -151,6 +152,50 @@ public fun submitPatchRequest(
     submitPatchRequestImpl(messenger, oldFile, patch, new, onComplete)
 }
 
+/**
+ * Submits a request to reconstruct a new blob from a region of an old file and a region of a patch
+ * file
+ *
+ * This method returns immediately, calling [onComplete] when patching is complete. If called
+ * multiple times in sequence, each patch request is put in a queue and run sequentially.
+ *
+ * Requests are run in a separate service process and sandboxed for increased security. For more
+ * details, see this software's external documentation.
+ *
+ * This overload accepts blobs stored within larger files, such as uncompressed entries of an APK
+ * opened with [android.content.res.AssetManager.openFd]. Each region is read from
+ * [AssetFileDescriptor.getStartOffset] for [AssetFileDescriptor.getDeclaredLength] bytes, or to the
+ * end of the file if the length is [AssetFileDescriptor.UNKNOWN_LENGTH]. The caller remains
+ * responsible for closing [old], [patch], and [new].
+ *
+ * @param messenger a [Messenger] associated with the bound service
+ * @param old the old blob
+ * @param patch the patch to read from
+ * @param new the new blob destination
+ * @param onComplete a lambda which is called when patching completes
+ */
+public fun submitPatchRequest(
+    messenger: Messenger,
+    old: AssetFileDescriptor,
+    patch: AssetFileDescriptor,
+    new: ParcelFileDescriptor,
+    onComplete: (PatchResult) -> Unit,
+) {
+    val message = Message.obtain(null, MSG_PATCH).apply {
+        data = Bundle().apply {
+            putParcelable("oldFileFd", old.parcelFileDescriptor)
+            putLong("oldOffset", old.startOffset)
+            putLong("oldLength", old.declaredLength)
+            putParcelable("patchFd", patch.parcelFileDescriptor)
+            putLong("patchOffset", patch.startOffset)
+            putLong("patchLength", patch.declaredLength)
+            putParcelable("newFd", new)
+        }
+        replyTo = Messenger(ResponseHandler(onComplete))
+    }
+    messenger.send(message)
+}
+
 private fun submitPatchRequestImpl(
     messenger: Messenger,
     oldFile: File,
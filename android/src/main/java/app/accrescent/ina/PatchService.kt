@@ -6,6 +6,7 @@ package app.accrescent.ina
 
 import android.app.Service
 import android.content.Intent
+import android.content.res.AssetFileDescriptor
 import android.os.Build
 import android.os.Bundle
 import android.os.Handler
@@ -14,10 +15,7 @@ import android.os.Looper
 import android.os.Message
 import android.os.Messenger
 import android.os.ParcelFileDescriptor
-import android.os.ParcelFileDescriptor.AutoCloseInputStream
-import android.os.ParcelFileDescriptor.AutoCloseOutputStream
 import android.util.Log
-import java.io.IOException
 import java.security.GeneralSecurityException
 
 /**
@@ -51,30 +49,42 @@ public class PatchService : Service() {
                 MSG_PATCH -> {
                     val oldFileFd =
                         msg.data.getParcelableCompat("oldFileFd", ParcelFileDescriptor::class.java)
-                            ?.detachFd() ?: return
+                            ?: return
                     val patchFd =
                         msg.data.getParcelableCompat("patchFd", ParcelFileDescriptor::class.java)
+                            ?: return
                     val newFd =
                         msg.data.getParcelableCompat("newFd", ParcelFileDescriptor::class.java)
+                            ?: return
+                    val old = AssetFileDescriptor(
+                        oldFileFd,
+                        msg.data.getLong("oldOffset", 0),
+                        msg.data.getLong("oldLength", AssetFileDescriptor.UNKNOWN_LENGTH),
+                    )
+                    val patch = AssetFileDescriptor(
+                        patchFd,
+                        msg.data.getLong("patchOffset", 0),
+                        msg.data.getLong("patchLength", AssetFileDescriptor.UNKNOWN_LENGTH),
+                    )
                     val clientHandle = msg.replyTo
 
-                    AutoCloseInputStream(patchFd).use { patch ->
-                        AutoCloseOutputStream(newFd).use { new ->
-                            val response = Message.obtain().apply {
-                                try {
-                                    val bytesWritten = Patcher.patch(oldFileFd, patch, new)
+                    old.use {
+                        patch.use {
+                            newFd.use {
+                                // Reading from the file descriptors directly rather than through
+                                // streams lets the patcher use positioned reads on the old file
+                                val bytesWritten = Patcher.patch(old, patch, newFd)
 
+                                val response = Message.obtain().apply {
                                     if (bytesWritten != -1L) {
                                         what = RESP_PATCH_SUCCESS
                                         data.putLong("bytesWritten", bytesWritten)
                                     } else {
                                         what = RESP_PATCH_FAILURE
                                     }
-                                } catch (e: IOException) {
-                                    what = RESP_PATCH_FAILURE
                                 }
+                                clientHandle.send(response)
                             }
-                            clientHandle.send(response)
                         }
                     }
                 }
@@ -99,7 +109,7 @@ public class PatchService : Service() {
         // Prevent the service from starting if sandbox initialization fails so we never process
         // untrusted data outside of a sandbox
         when (Patcher.enableSandbox()) {
-            1 -> Log.i(TAG, "Successfully enabled seccomp sandbox")
+            1 -> Log.i(TAG, "Successfully enabled seccomp sandbox (status ${Patcher.sandboxStatus()})")
             0 -> throw GeneralSecurityException("Seccomp sandbox unavailable. This should never happen.")
             -1 -> throw GeneralSecurityException("Seccomp sandbox initialization failed")
             else -> throw GeneralSecurityException("Unknown seccomp sandbox error occurred. This should never happen.")
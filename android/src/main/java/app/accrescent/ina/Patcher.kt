@@ -4,6 +4,8 @@
 
 package app.accrescent.ina
 
+import android.content.res.AssetFileDescriptor
+import android.os.ParcelFileDescriptor
 import java.io.IOException
 import java.io.InputStream
 import java.io.OutputStream
@@ -11,7 +13,7 @@ import java.io.OutputStream
 internal class Patcher {
     companion object {
         init {
-            System.loadLibrary("ina")
+            System.loadLibrary("ina_android")
         }
 
         /**
@@ -25,6 +27,77 @@ internal class Patcher {
         @Throws(IOException::class)
         external fun patch(oldFileFd: Int, patch: InputStream, new: OutputStream): Long
 
+        /**
+         * Patches an old file given an Ina patch file, reading both directly from their file
+         * descriptors
+         *
+         * Each of [old] and [patch] is read from [AssetFileDescriptor.getStartOffset] for
+         * [AssetFileDescriptor.getDeclaredLength] bytes, or to the end of the file if the length is
+         * [AssetFileDescriptor.UNKNOWN_LENGTH]. The new blob is written to [new] at its current
+         * position.
+         *
+         * The file descriptors remain owned by the caller and aren't closed.
+         *
+         * @return the number of bytes written to [new], or -1 if patching fails
+         */
+        fun patch(
+            old: AssetFileDescriptor,
+            patch: AssetFileDescriptor,
+            new: ParcelFileDescriptor,
+        ): Long {
+            return patchFds(
+                old.parcelFileDescriptor.fd,
+                old.startOffset,
+                old.declaredLength,
+                patch.parcelFileDescriptor.fd,
+                patch.startOffset,
+                patch.declaredLength,
+                new.fd,
+            )
+        }
+
+        /**
+         * Patches an old file given an Ina patch file, reading both in their entirety directly from
+         * their file descriptors
+         *
+         * The file descriptors remain owned by the caller and aren't closed. [patch] may be a pipe.
+         *
+         * @return the number of bytes written to [new], or -1 if patching fails
+         */
+        fun patch(
+            old: ParcelFileDescriptor,
+            patch: ParcelFileDescriptor,
+            new: ParcelFileDescriptor,
+        ): Long {
+            return patch(
+                AssetFileDescriptor(old, 0, AssetFileDescriptor.UNKNOWN_LENGTH),
+                AssetFileDescriptor(patch, 0, AssetFileDescriptor.UNKNOWN_LENGTH),
+                new,
+            )
+        }
+
+        /**
+         * Patches an old file region given an Ina patch file region
+         *
+         * A negative length means the region extends to the end of the file. The file descriptors
+         * are duplicated rather than taken over, so they remain owned by the caller.
+         *
+         * # Safety
+         *
+         * [oldFd], [patchFd], and [newFd] must be open file descriptors for the duration of the
+         * call
+         */
+        @JvmStatic
+        private external fun patchFds(
+            oldFd: Int,
+            oldOffset: Long,
+            oldLength: Long,
+            patchFd: Int,
+            patchOffset: Long,
+            patchLength: Long,
+            newFd: Int,
+        ): Long
+
         /**
          * Enables the platform sandbox for patching operations
          *
@@ -33,5 +106,15 @@ internal class Patcher {
          */
         @JvmStatic
         external fun enableSandbox(): Int
+
+        /**
+         * Returns the state of the sandbox in the current process
+         *
+         * @return 0 when no sandbox is enabled, 1 when a sandbox restricting filesystem access is
+         * enabled, 2 when a sandbox forcing patching to run single-threaded is enabled, and -1 if
+         * the state is unknown
+         */
+        @JvmStatic
+        external fun sandboxStatus(): Int
     }
 }
@@ -0,0 +1,588 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+    process,
+};
+
+use anyhow::Context;
+use ina::{
+    BundleEntry, BundleEntryKind, BundleReader, BundleWriter, DIGEST_LEN, DiffConfig,
+    DigestAlgorithm, FileMetadata, PatchMetadata,
+};
+
+use crate::{
+    output::{CategorizedError, ErrorCategory},
+    patch::PatchOptions,
+};
+
+/// The numbers of files in a bundle by how they changed
+#[derive(Default)]
+pub struct BundleSummary {
+    pub added: u64,
+    pub modified: u64,
+    pub removed: u64,
+    pub unchanged: u64,
+    pub symlinks: u64,
+    /// The total size of the new files
+    pub new_len: u64,
+}
+
+impl BundleSummary {
+    fn count(&mut self, entry: &BundleEntry) {
+        match entry.kind() {
+            BundleEntryKind::Added => self.added += 1,
+            BundleEntryKind::Modified => self.modified += 1,
+            BundleEntryKind::Removed => self.removed += 1,
+            BundleEntryKind::Unchanged => self.unchanged += 1,
+            BundleEntryKind::Symlink => self.symlinks += 1,
+        }
+        self.new_len += entry.new_len();
+    }
+}
+
+/// Writes a bundle which updates the directory `old` to the directory `new` to `bundle`
+///
+/// Every regular file in either directory gets an entry, and the patches of added and modified
+/// files are created with `config`. If `preserve_metadata` is set, the metadata of each new file
+/// is recorded in its patch, and symbolic links in `new` are recorded with their targets. Symbolic
+/// links in `old` are treated as missing files. New files are hashed with `digest_algorithm`.
+pub fn diff_dirs(
+    old: &Path,
+    new: &Path,
+    bundle: File,
+    config: &DiffConfig,
+    preserve_metadata: bool,
+    digest_algorithm: DigestAlgorithm,
+) -> anyhow::Result<BundleSummary> {
+    let old_files = list_files(old)?;
+    let new_files = list_files(new)?;
+    let mut writer = BundleWriter::with_digest_algorithm(BufWriter::new(bundle), digest_algorithm)?;
+    let mut summary = BundleSummary::default();
+
+    let paths = old_files
+        .keys()
+        .chain(new_files.keys())
+        .collect::<BTreeSet<_>>();
+    for path in paths {
+        let new_path = new.join(path);
+        match new_files.get(path) {
+            None => {
+                writer.remove(path)?;
+                summary.removed += 1;
+                continue;
+            }
+            Some(ListedFile::Symlink(_)) if !preserve_metadata => anyhow::bail!(
+                "'{}' is a symbolic link, which bundles only contain with --preserve-metadata",
+                new_path.display(),
+            ),
+            Some(ListedFile::Symlink(target)) => {
+                writer
+                    .add_symlink(path, target)
+                    .with_context(|| format!("Failed to add '{path}' to the bundle"))?;
+                summary.symlinks += 1;
+                continue;
+            }
+            Some(ListedFile::Regular) => {}
+        }
+
+        let new_data = fs::read(&new_path)
+            .with_context(|| format!("Failed to read new file '{}'", new_path.display()))?;
+        let old_data = matches!(old_files.get(path), Some(ListedFile::Regular))
+            .then(|| {
+                let old_path = old.join(path);
+                fs::read(&old_path)
+                    .with_context(|| format!("Failed to read old file '{}'", old_path.display()))
+            })
+            .transpose()?;
+
+        let mut entry_config = config.clone();
+        if preserve_metadata {
+            let metadata = fs::metadata(&new_path).with_context(|| {
+                format!(
+                    "Failed to read metadata of new file '{}'",
+                    new_path.display()
+                )
+            })?;
+            entry_config.file_metadata(Some(FileMetadata::from_metadata(&metadata)));
+        }
+
+        let stats = writer
+            .add(path, old_data.as_deref(), &new_data, &entry_config)
+            .with_context(|| format!("Failed to add '{path}' to the bundle"))?;
+        match (stats, &old_data) {
+            (None, _) => summary.unchanged += 1,
+            (Some(_), Some(_)) => summary.modified += 1,
+            (Some(_), None) => summary.added += 1,
+        }
+        summary.new_len += new_data.len() as u64;
+    }
+
+    writer
+        .finish()?
+        .into_inner()
+        .map_err(io::IntoInnerError::into_error)?;
+
+    Ok(summary)
+}
+
+/// A file found by `list_files()`
+enum ListedFile {
+    Regular,
+    /// A symbolic link with the given target, using `/` as the separator
+    Symlink(String),
+}
+
+/// Returns the paths of the regular files and symbolic links in `dir` relative to it, using `/` as
+/// the separator
+fn list_files(dir: &Path) -> anyhow::Result<BTreeMap<String, ListedFile>> {
+    let mut files = BTreeMap::new();
+    let mut pending = vec![PathBuf::new()];
+    while let Some(relative) = pending.pop() {
+        let path = dir.join(&relative);
+        let entries = fs::read_dir(&path)
+            .with_context(|| format!("Failed to read directory '{}'", path.display()))?;
+        for entry in entries {
+            let entry =
+                entry.with_context(|| format!("Failed to read directory '{}'", path.display()))?;
+            let relative = relative.join(entry.file_name());
+            let file_type = entry.file_type().with_context(|| {
+                format!("Failed to read metadata of '{}'", entry.path().display())
+            })?;
+
+            if file_type.is_dir() {
+                pending.push(relative);
+            } else if file_type.is_file() {
+                files.insert(bundle_path(&relative)?, ListedFile::Regular);
+            } else if file_type.is_symlink() {
+                let target = fs::read_link(entry.path()).with_context(|| {
+                    format!("Failed to read symbolic link '{}'", entry.path().display())
+                })?;
+                files.insert(
+                    bundle_path(&relative)?,
+                    ListedFile::Symlink(bundle_path(&target)?),
+                );
+            } else {
+                anyhow::bail!(
+                    "'{}' isn't a regular file, directory or symbolic link, which bundles can't \
+                     contain",
+                    entry.path().display(),
+                );
+            }
+        }
+    }
+
+    Ok(files)
+}
+
+/// Converts a relative path into the form used in bundles
+fn bundle_path(relative: &Path) -> anyhow::Result<String> {
+    let components = relative
+        .iter()
+        .map(|component| {
+            component
+                .to_str()
+                .with_context(|| format!("Path '{}' isn't valid UTF-8", relative.display()))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    Ok(components.join("/"))
+}
+
+/// The entries of a bundle, read without applying their patches
+pub struct BundleListing {
+    pub digest_algorithm: DigestAlgorithm,
+    pub summary: BundleSummary,
+    /// The total size of the patches of added and modified files
+    pub patch_len: u64,
+    /// Each entry along with the header of its patch, if it has one
+    pub entries: Vec<(BundleEntry, Option<PatchMetadata>)>,
+}
+
+/// Reads the entries of `bundle` and the headers of their patches
+pub fn list_bundle<R>(bundle: R) -> anyhow::Result<BundleListing>
+where
+    R: Read,
+{
+    let mut reader = BundleReader::new(bundle).context("Failed to read bundle")?;
+    let mut summary = BundleSummary::default();
+    let mut patch_len = 0;
+    let mut entries = Vec::new();
+
+    while let Some(entry) = reader.next_entry().context("Failed to read bundle entry")? {
+        let metadata = reader.patch_metadata().with_context(|| {
+            format!(
+                "Failed to read patch header of bundle entry '{}'",
+                entry.path()
+            )
+        })?;
+        summary.count(&entry);
+        patch_len += entry.patch_len();
+        entries.push((entry, metadata));
+    }
+
+    Ok(BundleListing {
+        digest_algorithm: reader.digest_algorithm(),
+        summary,
+        patch_len,
+        entries,
+    })
+}
+
+/// Applies `bundle` to the directory `old`, updating the directory `output_dir` all at once.
+///
+/// Every new file is first written to a staging directory inside `output_dir` and checked against
+/// the length and hash recorded in the bundle. Unchanged files are checked as well, and copied to
+/// the staging directory if `output_dir` isn't `old`. Only once every file has been checked are
+/// the staged files renamed into place, moving the files they replace and removed files to a
+/// backup directory. If renaming fails, the files renamed so far are moved back, so `output_dir`
+/// is left as it was. The backup is deleted once the update is complete.
+pub fn apply_bundle(
+    old: &Path,
+    bundle: File,
+    output_dir: &Path,
+    options: &PatchOptions,
+    restore_metadata: bool,
+) -> anyhow::Result<BundleSummary> {
+    fs::create_dir_all(output_dir).with_context(|| {
+        format!(
+            "Failed to create output directory '{}'",
+            output_dir.display()
+        )
+    })?;
+    let in_place = same_dir(old, output_dir)?;
+
+    let mut transaction = Transaction::new(output_dir)?;
+    let staged = stage_bundle(
+        old,
+        bundle,
+        &mut transaction,
+        in_place,
+        options,
+        restore_metadata,
+    );
+    let summary = match staged {
+        Ok(summary) => summary,
+        Err(e) => {
+            transaction.abort();
+            return Err(e);
+        }
+    };
+
+    transaction.commit()?;
+
+    Ok(summary)
+}
+
+/// Applies every entry of `bundle` into the staging directory of `transaction`
+fn stage_bundle(
+    old: &Path,
+    bundle: File,
+    transaction: &mut Transaction,
+    in_place: bool,
+    options: &PatchOptions,
+    restore_metadata: bool,
+) -> anyhow::Result<BundleSummary> {
+    let mut reader = BundleReader::new(BufReader::new(bundle)).context("Failed to read bundle")?;
+    let algorithm = reader.digest_algorithm();
+    let mut summary = BundleSummary::default();
+
+    while let Some(entry) = reader.next_entry().context("Failed to read bundle entry")? {
+        let path = entry.path();
+        let old_path = old.join(path);
+        match entry.kind() {
+            BundleEntryKind::Added | BundleEntryKind::Modified => {
+                let staged_path = transaction.stage(path)?;
+                let mut staged_file = File::create(&staged_path).with_context(|| {
+                    format!("Failed to create staged file '{}'", staged_path.display())
+                })?;
+
+                let (len, digest, metadata) = if entry.kind() == BundleEntryKind::Modified {
+                    let old_file = File::open(&old_path).with_context(|| {
+                        format!("Failed to open old file '{}'", old_path.display())
+                    })?;
+                    let mut patcher = options.patcher(old_file, reader.patch())?;
+                    let (len, digest) = copy_hashed(&mut patcher, &mut staged_file, algorithm)?;
+                    (len, digest, patcher.metadata().clone())
+                } else {
+                    let mut patcher = options.patcher(io::Cursor::new([]), reader.patch())?;
+                    let (len, digest) = copy_hashed(&mut patcher, &mut staged_file, algorithm)?;
+                    (len, digest, patcher.metadata().clone())
+                };
+                check_new_file(&entry, len, &digest)?;
+
+                if restore_metadata {
+                    ina::restore_file_metadata(&metadata, &mut staged_file)
+                        .with_context(|| format!("Failed to restore metadata of '{path}'"))?;
+                }
+            }
+            BundleEntryKind::Unchanged => {
+                let mut old_file = File::open(&old_path)
+                    .with_context(|| format!("Failed to open old file '{}'", old_path.display()))?;
+                let (len, digest) = if in_place {
+                    copy_hashed(&mut old_file, &mut io::sink(), algorithm)
+                } else {
+                    let staged_path = transaction.stage(path)?;
+                    let mut staged_file = File::create(&staged_path).with_context(|| {
+                        format!("Failed to create staged file '{}'", staged_path.display())
+                    })?;
+                    copy_hashed(&mut old_file, &mut staged_file, algorithm)
+                }
+                .with_context(|| format!("Failed to read old file '{}'", old_path.display()))?;
+                check_new_file(&entry, len, &digest)?;
+            }
+            BundleEntryKind::Removed => transaction.remove(path)?,
+            BundleEntryKind::Symlink => {
+                let staged_path = transaction.stage(path)?;
+                // The reader returns a target for every symbolic link entry
+                let target = entry.symlink_target().unwrap();
+                create_symlink(target, &staged_path).with_context(|| {
+                    format!("Failed to create symbolic link '{}'", staged_path.display())
+                })?;
+            }
+        }
+
+        summary.count(&entry);
+    }
+
+    Ok(summary)
+}
+
+/// Creates a symbolic link at `link` which points to `target`
+#[cfg(unix)]
+fn create_symlink(target: &str, link: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(target, link)
+}
+
+/// Creates a symbolic link at `link` which points to `target`
+#[cfg(not(unix))]
+fn create_symlink(_target: &str, _link: &Path) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "symbolic links can't be created on this platform",
+    ))
+}
+
+/// Returns an error if a new file of length `len` and the given digest doesn't match `entry`
+fn check_new_file(entry: &BundleEntry, len: u64, digest: &[u8; DIGEST_LEN]) -> anyhow::Result<()> {
+    let matches = len == entry.new_len() && entry.new_digest() == Some(digest);
+    if !matches {
+        return Err(CategorizedError::new(
+            ErrorCategory::Mismatch,
+            format!(
+                "New file '{}' doesn't match the hash recorded in the bundle",
+                entry.path(),
+            ),
+        )
+        .into());
+    }
+
+    Ok(())
+}
+
+/// Copies `reader` to `writer`, returning the number of bytes copied and their digest computed with
+/// `algorithm`
+fn copy_hashed<R, W>(
+    reader: &mut R,
+    writer: &mut W,
+    algorithm: DigestAlgorithm,
+) -> io::Result<(u64, [u8; DIGEST_LEN])>
+where
+    R: Read + ?Sized,
+    W: Write + ?Sized,
+{
+    let mut hasher = algorithm.hasher();
+    let mut buf = vec![0; 64 * 1024];
+    let mut len = 0;
+    loop {
+        let read = match reader.read(&mut buf) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        hasher.update(&buf[..read]);
+        writer.write_all(&buf[..read])?;
+        len += read as u64;
+    }
+    writer.flush()?;
+
+    Ok((len, hasher.finalize()))
+}
+
+/// Returns whether `a` and `b` are the same directory
+fn same_dir(a: &Path, b: &Path) -> anyhow::Result<bool> {
+    let canonicalize = |path: &Path| {
+        fs::canonicalize(path)
+            .with_context(|| format!("Failed to resolve directory '{}'", path.display()))
+    };
+
+    Ok(canonicalize(a)? == canonicalize(b)?)
+}
+
+/// An all-or-nothing update of the files in a directory
+struct Transaction {
+    dir: PathBuf,
+    staging: PathBuf,
+    backup: PathBuf,
+    /// The files to update, along with whether they have a staged replacement
+    changes: Vec<(String, bool)>,
+    paths: BTreeSet<String>,
+}
+
+impl Transaction {
+    /// Starts an update of `dir`, creating its staging directory
+    fn new(dir: &Path) -> anyhow::Result<Self> {
+        // The staging and backup directories are inside `dir` so that renaming files between them
+        // never crosses file systems
+        let id = process::id();
+        let staging = dir.join(format!(".ina-staging-{id}"));
+        let backup = dir.join(format!(".ina-backup-{id}"));
+        fs::create_dir(&staging).with_context(|| {
+            format!("Failed to create staging directory '{}'", staging.display())
+        })?;
+
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            staging,
+            backup,
+            changes: Vec::new(),
+            paths: BTreeSet::new(),
+        })
+    }
+
+    /// Returns the path to stage the new version of the file at `path` at
+    fn stage(&mut self, path: &str) -> anyhow::Result<PathBuf> {
+        self.record(path, true)?;
+        let staged_path = self.staging.join(path);
+        create_parent(&staged_path)?;
+
+        Ok(staged_path)
+    }
+
+    /// Records that the file at `path` is removed
+    fn remove(&mut self, path: &str) -> anyhow::Result<()> {
+        self.record(path, false)
+    }
+
+    /// Records a change of the file at `path`, which must not have been changed already
+    fn record(&mut self, path: &str, staged: bool) -> anyhow::Result<()> {
+        // A second change of the same file would overwrite the backup of the first
+        if !self.paths.insert(path.into()) {
+            return Err(CategorizedError::new(
+                ErrorCategory::InvalidPatch,
+                format!("Bundle contains '{path}' more than once"),
+            )
+            .into());
+        }
+        self.changes.push((path.into(), staged));
+
+        Ok(())
+    }
+
+    /// Moves the staged files into place, rolling back if any of them can't be
+    fn commit(self) -> anyhow::Result<()> {
+        // The changes made so far, along with whether they replaced an existing file
+        let mut done = Vec::new();
+        for (path, staged) in &self.changes {
+            match self.apply(path, *staged, &mut done) {
+                Ok(()) => {}
+                Err(e) => {
+                    let rollback = self.roll_back(&done);
+                    self.abort();
+
+                    return match rollback {
+                        Ok(()) => {
+                            // Only empty directories are left in the backup directory
+                            let _ = fs::remove_dir_all(&self.backup);
+                            Err(e.context(format!(
+                                "Failed to update '{}', so it was left unchanged",
+                                self.dir.display(),
+                            )))
+                        }
+                        Err(rollback_error) => Err(e.context(format!(
+                            "Failed to update '{}' and to roll back the update \
+                             ({rollback_error:#}); the previous files are in '{}'",
+                            self.dir.display(),
+                            self.backup.display(),
+                        ))),
+                    };
+                }
+            }
+        }
+
+        // The update is complete, so failing to clean up doesn't affect its result
+        let _ = fs::remove_dir_all(&self.backup);
+        let _ = fs::remove_dir_all(&self.staging);
+
+        Ok(())
+    }
+
+    /// Moves the file at `path` to the backup directory if it exists and its staged replacement,
+    /// if any, into place, recording what was done in `done`
+    fn apply<'a>(
+        &self,
+        path: &'a str,
+        staged: bool,
+        done: &mut Vec<(&'a str, bool, bool)>,
+    ) -> anyhow::Result<()> {
+        let target = self.dir.join(path);
+        let backed_up = if fs::symlink_metadata(&target).is_ok() {
+            let backup = self.backup.join(path);
+            create_parent(&backup)?;
+            fs::rename(&target, &backup)
+                .with_context(|| format!("Failed to back up '{}'", target.display()))?;
+            true
+        } else {
+            false
+        };
+        done.push((path, backed_up, false));
+
+        if staged {
+            create_parent(&target)?;
+            fs::rename(self.staging.join(path), &target)
+                .with_context(|| format!("Failed to move new file into '{}'", target.display()))?;
+            // The entry was pushed above
+            done.last_mut().unwrap().2 = true;
+        }
+
+        Ok(())
+    }
+
+    /// Undoes the changes in `done`, most recent first
+    fn roll_back(&self, done: &[(&str, bool, bool)]) -> anyhow::Result<()> {
+        for &(path, backed_up, placed) in done.iter().rev() {
+            let target = self.dir.join(path);
+            if placed {
+                fs::remove_file(&target)
+                    .with_context(|| format!("Failed to remove '{}'", target.display()))?;
+            }
+            if backed_up {
+                fs::rename(self.backup.join(path), &target)
+                    .with_context(|| format!("Failed to restore '{}'", target.display()))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Deletes the staging directory
+    fn abort(&self) {
+        let _ = fs::remove_dir_all(&self.staging);
+    }
+}
+
+/// Creates the parent directories of `path`
+fn create_parent(path: &Path) -> anyhow::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create directory '{}'", parent.display()))?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,129 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use serde::Deserialize;
+
+use crate::{BaseCheckMode, DigestKind, ProfileKind, TreatAs};
+
+/// The config file discovered in the current directory when `--config` isn't given
+const DEFAULT_CONFIG_PATH: &str = "ina.toml";
+
+/// Settings loaded from a config file
+///
+/// Every setting is optional and is overridden by its respective command-line flag when present.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub diff: DiffSettings,
+    pub patch: PatchSettings,
+    pub report: ReportSettings,
+}
+
+/// Settings for the `diff` subcommand
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DiffSettings {
+    pub profile: Option<ProfileKind>,
+    pub compression_threads: Option<u32>,
+    pub compression_level: Option<i32>,
+    pub window_log: Option<u32>,
+    pub match_threshold: Option<usize>,
+    pub max_backward_seek: Option<usize>,
+    pub preserve_metadata: Option<bool>,
+    pub max_ratio: Option<f64>,
+    pub provenance: Option<bool>,
+    pub provenance_paths: Option<bool>,
+    pub record_settings: Option<bool>,
+    pub record_new_len: Option<bool>,
+    pub payload_checksum: Option<bool>,
+    pub base_check: Option<BaseCheckMode>,
+    pub embed_digests: Option<bool>,
+    pub diff_window: Option<u64>,
+    pub digest: Option<DigestKind>,
+    pub text: Option<TreatAs>,
+}
+
+/// Settings for the `patch` subcommand
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PatchSettings {
+    pub decompression_buffer_size: Option<usize>,
+    pub max_memory: Option<u64>,
+    pub max_expansion: Option<u32>,
+    pub max_new_len: Option<u64>,
+    pub restore_metadata: Option<bool>,
+    pub isolate: Option<bool>,
+    pub state_interval: Option<usize>,
+}
+
+/// Settings for the `info` and `stats` subcommands
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ReportSettings {
+    pub machine: Option<bool>,
+    pub apply_throughput: Option<usize>,
+}
+
+impl DiffSettings {
+    /// Returns these settings with every unset setting taken from `fallback`
+    pub fn or(self, fallback: Self) -> Self {
+        Self {
+            profile: self.profile.or(fallback.profile),
+            compression_threads: self.compression_threads.or(fallback.compression_threads),
+            compression_level: self.compression_level.or(fallback.compression_level),
+            window_log: self.window_log.or(fallback.window_log),
+            match_threshold: self.match_threshold.or(fallback.match_threshold),
+            max_backward_seek: self.max_backward_seek.or(fallback.max_backward_seek),
+            preserve_metadata: self.preserve_metadata.or(fallback.preserve_metadata),
+            max_ratio: self.max_ratio.or(fallback.max_ratio),
+            provenance: self.provenance.or(fallback.provenance),
+            provenance_paths: self.provenance_paths.or(fallback.provenance_paths),
+            record_settings: self.record_settings.or(fallback.record_settings),
+            record_new_len: self.record_new_len.or(fallback.record_new_len),
+            payload_checksum: self.payload_checksum.or(fallback.payload_checksum),
+            base_check: self.base_check.or(fallback.base_check),
+            embed_digests: self.embed_digests.or(fallback.embed_digests),
+            diff_window: self.diff_window.or(fallback.diff_window),
+            digest: self.digest.or(fallback.digest),
+            text: self.text.or(fallback.text),
+        }
+    }
+}
+
+impl Config {
+    /// Loads the config file at `path`, or from the default location if `path` is `None`
+    ///
+    /// Config files are parsed as JSON if their extension is `.json` and as TOML otherwise. A
+    /// missing config file at the default location is not an error and results in an empty
+    /// config.
+    pub fn load(path: Option<&Path>) -> anyhow::Result<Self> {
+        let path = match path {
+            Some(path) => path.to_path_buf(),
+            None => {
+                let path = PathBuf::from(DEFAULT_CONFIG_PATH);
+                if !path.is_file() {
+                    return Ok(Self::default());
+                }
+                path
+            }
+        };
+
+        let contents = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read config file '{}'", path.display()))?;
+
+        if path.extension().is_some_and(|ext| ext == "json") {
+            serde_json::from_str(&contents)
+                .with_context(|| format!("Failed to parse config file '{}'", path.display()))
+        } else {
+            toml::from_str(&contents)
+                .with_context(|| format!("Failed to parse config file '{}'", path.display()))
+        }
+    }
+}
@@ -0,0 +1,342 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use std::{
+    fmt::{self, Display, Formatter},
+    path::Path,
+};
+
+use anyhow::Context;
+use ina::{DiffConfig, OldBlob, format::CODECS};
+use serde_json::json;
+
+use crate::{config::Config, output::Output, units::Units};
+
+/// The largest decompression window a patch can request, which bounds the memory patching needs
+/// without a limit
+const MAX_WINDOW_LEN: u64 = 1 << 27;
+
+/// The length of the files written to test reflink support, which spans many filesystem blocks
+#[cfg(target_os = "linux")]
+const REFLINK_PROBE_LEN: usize = 1 << 20;
+
+/// The outcome of a single check
+#[derive(Clone, Copy, Eq, PartialEq)]
+enum Status {
+    Ok,
+    Warning,
+    Failed,
+}
+
+impl Display for Status {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Ok => "ok",
+            Self::Warning => "warning",
+            Self::Failed => "FAILED",
+        })
+    }
+}
+
+/// A finding about the environment ina runs in
+struct Check {
+    name: String,
+    status: Status,
+    detail: String,
+}
+
+impl Check {
+    fn new(name: impl Into<String>, status: Status, detail: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            status,
+            detail: detail.into(),
+        }
+    }
+}
+
+/// Runs every check and prints its outcome, failing if any check failed
+///
+/// Files testing reflink support are written to `dir`.
+pub fn run(dir: &Path, config: &Config, output: &Output) -> anyhow::Result<()> {
+    let mut checks = vec![build()];
+    checks.extend(codecs());
+    checks.push(sandbox());
+    checks.push(Check::new(
+        "mmap",
+        Status::Ok,
+        "not used by this build; old files are read with ordinary reads",
+    ));
+    checks.push(reflink(dir));
+    checks.push(memory(config.patch.max_memory));
+    checks.push(selftest());
+
+    if output.json() {
+        let checks: Vec<_> = checks
+            .iter()
+            .map(|check| {
+                json!({
+                    "name": check.name,
+                    "status": check.status.to_string().to_lowercase(),
+                    "detail": check.detail,
+                })
+            })
+            .collect();
+        let report = serde_json::to_string_pretty(&json!({ "checks": checks }))
+            .context("Failed to serialize checks")?;
+        println!("{report}");
+    } else {
+        for check in &checks {
+            if check.status != Status::Ok || output.normal() {
+                println!("{}: {} ({})", check.name, check.status, check.detail);
+            }
+        }
+    }
+
+    let failures = checks
+        .iter()
+        .filter(|check| check.status == Status::Failed)
+        .count();
+    if failures > 0 {
+        anyhow::bail!("{failures} check(s) failed");
+    }
+
+    Ok(())
+}
+
+/// Describes this build of ina
+fn build() -> Check {
+    let linkage = if cfg!(target_feature = "crt-static") {
+        "static"
+    } else {
+        "dynamic"
+    };
+
+    Check::new(
+        "build",
+        Status::Ok,
+        format!(
+            "ina {} for {}-{}, libzstd {}, {linkage} linkage",
+            env!("CARGO_PKG_VERSION"),
+            std::env::consts::ARCH,
+            std::env::consts::OS,
+            zstd::zstd_safe::version_string(),
+        ),
+    )
+}
+
+/// Checks that a small patch round-trips with every codec
+fn codecs() -> Vec<Check> {
+    let old = probe_data(64 << 10);
+    let mut new = old.clone();
+    new[1000..1100].fill(0);
+    new.extend_from_slice(b"appended by ina doctor");
+
+    CODECS
+        .iter()
+        .map(|&codec| {
+            let mut config = DiffConfig::new();
+            config.codec(codec);
+            let result = ina::diff_to_vec(&OldBlob::from_slice(&old), &new, &config)
+                .map_err(anyhow::Error::from)
+                .and_then(|patch| Ok(ina::patch_to_vec(&old, &patch)?));
+
+            match result {
+                Ok(patched) if patched == new => {
+                    Check::new(format!("codec/{codec}"), Status::Ok, "patches round-trip")
+                }
+                Ok(_) => Check::new(
+                    format!("codec/{codec}"),
+                    Status::Failed,
+                    "patching produced the wrong output",
+                ),
+                Err(e) => Check::new(format!("codec/{codec}"), Status::Failed, format!("{e:#}")),
+            }
+        })
+        .collect()
+}
+
+/// Checks whether patching can be sandboxed, and why not if it can't
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn sandbox() -> Check {
+    use ina::sandbox::SandboxSupport;
+
+    match ina::sandbox::support() {
+        SandboxSupport::Seccomp => Check::new("sandbox", Status::Ok, "seccomp"),
+        SandboxSupport::Landlock(abi) => {
+            Check::new("sandbox", Status::Ok, format!("Landlock ABI {abi}"))
+        }
+        SandboxSupport::LandlockUnavailable => Check::new(
+            "sandbox",
+            Status::Warning,
+            "the kernel doesn't support Landlock, so patching isn't sandboxed; it must be built \
+            with CONFIG_SECURITY_LANDLOCK and have landlock in its lsm= boot parameter",
+        ),
+        SandboxSupport::LandlockError(e) => Check::new(
+            "sandbox",
+            Status::Warning,
+            format!("failed to query Landlock support, so patching may not be sandboxed: {e}"),
+        ),
+        _ => Check::new(
+            "sandbox",
+            Status::Warning,
+            "unsupported on this platform, so patching isn't sandboxed",
+        ),
+    }
+}
+
+/// Checks whether patching can be sandboxed, and why not if it can't
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn sandbox() -> Check {
+    Check::new(
+        "sandbox",
+        Status::Warning,
+        "unsupported on this platform, so patching isn't sandboxed",
+    )
+}
+
+/// Checks whether `patch --reflink` can share unchanged blocks on the filesystem of `dir`
+#[cfg(target_os = "linux")]
+fn reflink(dir: &Path) -> Check {
+    use std::{fs, process};
+
+    let old_path = dir.join(format!(".ina-doctor-old-{}", process::id()));
+    let new_path = dir.join(format!(".ina-doctor-new-{}", process::id()));
+    let result = (|| -> anyhow::Result<u64> {
+        let old = probe_data(REFLINK_PROBE_LEN);
+        fs::write(&old_path, &old)?;
+        let patch = ina::diff_to_vec(&OldBlob::from_slice(&old), &old, &DiffConfig::new())?;
+        let old_file = fs::File::open(&old_path)?;
+        let new_file = fs::File::create(&new_path)?;
+
+        Ok(ina::patch_reflink(&old_file, patch.as_slice(), &new_file)?.cloned_len())
+    })();
+    let _ = fs::remove_file(&old_path);
+    let _ = fs::remove_file(&new_path);
+
+    match result {
+        Ok(0) => Check::new(
+            "reflink",
+            Status::Warning,
+            format!(
+                "unsupported on the filesystem of '{}', so `patch --reflink` writes files in full",
+                dir.display(),
+            ),
+        ),
+        Ok(_) => Check::new(
+            "reflink",
+            Status::Ok,
+            format!("supported on the filesystem of '{}'", dir.display()),
+        ),
+        Err(e) => Check::new(
+            "reflink",
+            Status::Warning,
+            format!("couldn't be tested in '{}': {e:#}", dir.display()),
+        ),
+    }
+}
+
+/// Checks whether `patch --reflink` can share unchanged blocks on the filesystem of `dir`
+#[cfg(not(target_os = "linux"))]
+fn reflink(_dir: &Path) -> Check {
+    Check::new("reflink", Status::Ok, "unsupported on this platform")
+}
+
+/// Compares the memory available to patching with the configured limit
+fn memory(max_memory: Option<u64>) -> Check {
+    let units = Units::new(false);
+    let available = available_memory();
+    let available_text = available.map_or("unknown".into(), |bytes| units.size(bytes));
+
+    match (max_memory, available) {
+        (Some(limit), Some(available)) if limit > available => Check::new(
+            "memory",
+            Status::Warning,
+            format!(
+                "{available_text} available, but patching may use up to the configured limit of {}",
+                units.size(limit),
+            ),
+        ),
+        (Some(limit), _) => Check::new(
+            "memory",
+            Status::Ok,
+            format!(
+                "{available_text} available, limit of {} configured",
+                units.size(limit),
+            ),
+        ),
+        (None, Some(available)) if available < MAX_WINDOW_LEN => Check::new(
+            "memory",
+            Status::Warning,
+            format!(
+                "{available_text} available, but without a configured limit, patches may use more \
+                than {}; set `max_memory` in the config file",
+                units.size(MAX_WINDOW_LEN),
+            ),
+        ),
+        (None, _) => Check::new(
+            "memory",
+            Status::Ok,
+            format!("{available_text} available, no limit configured"),
+        ),
+    }
+}
+
+/// Returns the memory available to new processes in bytes, if known
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn available_memory() -> Option<u64> {
+    let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+    let kib = meminfo
+        .lines()
+        .find_map(|line| line.strip_prefix("MemAvailable:"))?
+        .trim()
+        .strip_suffix("kB")?
+        .trim()
+        .parse::<u64>()
+        .ok()?;
+
+    Some(kib * 1024)
+}
+
+/// Returns the memory available to new processes in bytes, if known
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn available_memory() -> Option<u64> {
+    None
+}
+
+/// Applies the golden patches of `ina selftest --format`
+fn selftest() -> Check {
+    let results = ina::selftest::run_format();
+    let failed: Vec<_> = results
+        .iter()
+        .filter(|(_, result)| result.is_err())
+        .map(|(name, _)| *name)
+        .collect();
+
+    if failed.is_empty() {
+        Check::new(
+            "selftest",
+            Status::Ok,
+            format!("{} golden patches applied", results.len()),
+        )
+    } else {
+        Check::new(
+            "selftest",
+            Status::Failed,
+            format!(
+                "{} of {} golden patches failed: {}; run `ina selftest` for details",
+                failed.len(),
+                results.len(),
+                failed.join(", "),
+            ),
+        )
+    }
+}
+
+/// Generates `len` bytes of deterministic data which compresses poorly
+fn probe_data(len: usize) -> Vec<u8> {
+    (0..len as u32)
+        .map(|i| (i.wrapping_mul(0x9e3779b9) >> 24) as u8)
+        .collect()
+}
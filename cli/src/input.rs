@@ -0,0 +1,156 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use std::{
+    fmt::{self, Display, Formatter},
+    fs::File,
+    io::{self, BufReader, Read},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use anyhow::Context;
+use ina::{FileMetadata, ZipEntryReader};
+use zip::ZipArchive;
+
+/// An entry of a zip archive, such as a native library inside an APK, addressed as
+/// `ARCHIVE:ENTRY`
+///
+/// The entry name is everything after the last `:`, so archive paths may contain colons, e.g., in
+/// Windows drive prefixes.
+#[derive(Clone, Debug)]
+pub struct ZipEntry {
+    archive: PathBuf,
+    name: String,
+}
+
+impl ZipEntry {
+    /// Reads the entry into memory, reserving `extra` bytes of capacity after it
+    ///
+    /// Only the archive's central directory and the entry itself are read, so this is cheap even
+    /// for large archives.
+    pub fn read(&self, extra: usize) -> anyhow::Result<Vec<u8>> {
+        let mut archive = self.open()?;
+        let mut entry = archive
+            .by_name(&self.name)
+            .with_context(|| format!("Failed to find zip entry '{self}'"))?;
+
+        let len = usize::try_from(entry.size())
+            .with_context(|| format!("Zip entry '{self}' is too large to read into memory"))?;
+        let mut data = Vec::with_capacity(len.saturating_add(extra));
+        entry
+            .read_to_end(&mut data)
+            .with_context(|| format!("Failed to extract zip entry '{self}'"))?;
+
+        Ok(data)
+    }
+
+    /// Returns the metadata of the entry recorded in the archive
+    ///
+    /// Only the permissions are taken from the archive since zip modification times lack a time
+    /// zone.
+    pub fn file_metadata(&self) -> anyhow::Result<FileMetadata> {
+        let mut archive = self.open()?;
+        let entry = archive
+            .by_name(&self.name)
+            .with_context(|| format!("Failed to find zip entry '{self}'"))?;
+
+        Ok(FileMetadata::new(entry.unix_mode(), None))
+    }
+
+    fn open(&self) -> anyhow::Result<ZipArchive<BufReader<File>>> {
+        let file = File::open(&self.archive)
+            .with_context(|| format!("Failed to open zip archive '{}'", self.archive.display()))?;
+
+        ZipArchive::new(BufReader::new(file))
+            .with_context(|| format!("Failed to read zip archive '{}'", self.archive.display()))
+    }
+}
+
+impl FromStr for ZipEntry {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.rsplit_once(':') {
+            Some((archive, name)) if !archive.is_empty() && !name.is_empty() => Ok(Self {
+                archive: archive.into(),
+                name: name.into(),
+            }),
+            _ => Err("expected an archive path and entry name separated by ':'".into()),
+        }
+    }
+}
+
+impl Display for ZipEntry {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.archive.display(), self.name)
+    }
+}
+
+/// A patch given to `patch`, which is either a file or a stored entry of a zip archive
+pub enum PatchInput {
+    File(File),
+    ZipEntry(ZipEntryReader<BufReader<File>>),
+}
+
+impl PatchInput {
+    /// Opens the patch at `path`
+    ///
+    /// A path which doesn't exist but is of the form `ARCHIVE:ENTRY` for an existing archive is
+    /// read from the archive's entry without extracting it.
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        let entry = path
+            .to_str()
+            .filter(|_| !path.exists())
+            .and_then(|address| address.parse::<ZipEntry>().ok())
+            .filter(|entry| entry.archive.is_file());
+        match entry {
+            Some(entry) => ZipEntryReader::open(&entry.to_string())
+                .map(Self::ZipEntry)
+                .with_context(|| format!("Failed to open zip entry '{entry}'")),
+            None => File::open(path)
+                .map(Self::File)
+                .with_context(|| format!("Failed to open patch file '{}'", path.display())),
+        }
+    }
+
+    /// Returns whether the patch is read from a zip entry
+    pub fn is_zip_entry(&self) -> bool {
+        matches!(self, Self::ZipEntry(_))
+    }
+
+    /// Returns the patch file, or an error naming `option` if the patch is read from a zip entry
+    pub fn into_file(self, option: &str) -> anyhow::Result<File> {
+        match self {
+            Self::File(file) => Ok(file),
+            Self::ZipEntry(_) => {
+                anyhow::bail!("{option} can't be used with patches read from zip entries")
+            }
+        }
+    }
+}
+
+impl Read for PatchInput {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Self::File(file) => file.read(buf),
+            Self::ZipEntry(entry) => entry.read(buf),
+        }
+    }
+}
+
+/// A file given to `diff`, which is either a path or an entry of a zip archive
+pub enum Input {
+    Path(PathBuf),
+    ZipEntry(ZipEntry),
+}
+
+impl Display for Input {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::Path(path) => path.display().fmt(f),
+            Self::ZipEntry(entry) => entry.fmt(f),
+        }
+    }
+}
@@ -2,34 +2,128 @@
 //
 // SPDX-License-Identifier: Apache-2.0
 
+mod bundle;
+mod config;
+mod doctor;
+mod input;
+mod output;
+mod patch;
+mod pipeline;
+#[cfg(feature = "dev")]
+mod serve;
+mod state;
+mod units;
+
 use std::{
+    env,
+    fmt::Display,
     fs::{self, File},
-    io::{self, BufReader, Read},
-    path::PathBuf,
+    io::{self, BufRead, BufReader, Read},
+    ops::Range,
+    path::{Path, PathBuf},
+    process::ExitCode,
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
 use anyhow::Context;
-use clap::{Parser, Subcommand};
-use ina::{DiffConfig, Patcher};
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum, error::ErrorKind};
+use ina::{
+    BaseCheck, Comparison, DiffConfig, DiffStats, DigestAlgorithm, FileMetadata, OldBlob,
+    OldCoverage, PatchMetadata, Profile, Provenance, RediffCheck, RediffReason, SeekHistogram,
+    SeekStats, Target, TextMode, TuneMatrix, unstable::v0::ControlReader,
+};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::{
+    bundle::BundleListing,
+    config::{Config, DiffSettings},
+    input::{Input, PatchInput, ZipEntry},
+    output::{CategorizedError, EXIT_STATUS_HELP, ErrorCategory, Output, Verbosity},
+    patch::{ISOLATED_PATCH_COMMAND, PatchOptions},
+    state::{DEFAULT_STATE_INTERVAL, StateFile},
+    units::{DEFAULT_APPLY_THROUGHPUT, Units},
+};
 
 /// Binary diffing and patching designed for executables
 #[derive(Parser)]
-#[command(display_name("ina"), version)]
+#[command(
+    display_name("ina"),
+    bin_name("ina"),
+    version,
+    after_long_help = EXIT_STATUS_HELP
+)]
 struct Args {
+    /// The path of a config file to read settings from
+    ///
+    /// Settings in the config file are overridden by their respective command-line flags. If this
+    /// option isn't given, settings are read from `ina.toml` in the current directory if it
+    /// exists. Config files with a `.json` extension are parsed as JSON, such as those produced by
+    /// `ina tune`. All others are parsed as TOML.
+    #[arg(long, global = true, verbatim_doc_comment)]
+    config: Option<PathBuf>,
+    /// Only print output requested by the command and errors
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    quiet: bool,
+    /// Print additional details about what the command did
+    #[arg(short, long, global = true)]
+    verbose: bool,
+    /// Print machine-readable JSON instead of human-oriented text where supported
+    ///
+    /// Errors are printed to standard error as one JSON object per line with the fields
+    /// `category`, `exit_code`, `message`, and `causes`.
+    #[arg(long, global = true, verbatim_doc_comment)]
+    json: bool,
+    /// Print the codecs and features supported by this build and exit
+    #[arg(long, exclusive = true)]
+    capabilities: bool,
     #[command(subcommand)]
-    command: Command,
+    command: Option<Command>,
 }
 
 #[derive(Subcommand)]
 enum Command {
     /// Generate a patch between two files
+    ///
+    /// If the old and new paths are both directories, a bundle is generated instead. A bundle
+    /// contains a patch for every added or modified file along with the hash of every new file,
+    /// and can be applied with `ina patch --output-dir`.
+    ///
+    /// Either file can instead be read from an entry of a zip archive such as an APK with
+    /// `--old-zip` or `--new-zip`, in which case its path is omitted:
+    ///
+    ///   ina diff --old-zip v1.apk:lib/arm64-v8a/libfoo.so \
+    ///       --new-zip v2.apk:lib/arm64-v8a/libfoo.so libfoo.ina
+    #[command(verbatim_doc_comment)]
     Diff {
-        /// The path of the old file
-        old: PathBuf,
-        /// The path of the new file
-        new: PathBuf,
-        /// The path of the output patch file
-        patch: PathBuf,
+        /// The path of the old file or directory, unless `--old-zip` is given
+        old: Option<PathBuf>,
+        /// The path of the new file or directory, unless `--new-zip` is given
+        new: Option<PathBuf>,
+        /// The path of the output patch or bundle file
+        patch: Option<PathBuf>,
+        /// Read the old file from an entry of a zip archive, given as ARCHIVE:ENTRY
+        ///
+        /// Only the named entry is extracted, directly into memory, so the archive isn't unpacked.
+        #[arg(long, value_name = "ARCHIVE:ENTRY", verbatim_doc_comment)]
+        old_zip: Option<ZipEntry>,
+        /// Read the new file from an entry of a zip archive, given as ARCHIVE:ENTRY
+        ///
+        /// With `--preserve-metadata`, the permissions recorded for the entry are preserved.
+        #[arg(long, value_name = "ARCHIVE:ENTRY", verbatim_doc_comment)]
+        new_zip: Option<ZipEntry>,
+        /// A preset of settings suited to the kind of files being diffed
+        ///
+        /// The preset sets the compression level, window log, match threshold, and text mode,
+        /// each of which can still be overridden by its own option:
+        ///
+        ///   executable: level 19, window log 24, match threshold 8, binary
+        ///   archive:    level 9, window log 27, match threshold 16, binary
+        ///   text:       level 19, default window log, match threshold 4, text
+        ///
+        /// Default: none
+        #[arg(long, value_enum, verbatim_doc_comment)]
+        profile: Option<ProfileKind>,
         /// The number of threads to use for compression
         ///
         /// Setting this to a value more than 0 allows compression to run on a separate thread than
@@ -52,18 +146,217 @@ enum Command {
         ///
         /// Levels 20-22 result in significantly higher memory usage.
         ///
-        /// Default: 19
+        /// Default: 19, or the level of the profile
         #[arg(long, verbatim_doc_comment)]
         compression_level: Option<i32>,
+        /// The base-2 logarithm of the compression window size
+        ///
+        /// Larger windows allow the compressor to find redundancy further back in the patch at
+        /// the cost of memory usage during both diffing and patching. Values are clamped to the
+        /// range 10-27 inclusive.
+        ///
+        /// Default: chosen by the compression level, or the window log of the profile
+        #[arg(long, verbatim_doc_comment)]
+        window_log: Option<u32>,
+        /// The number of mismatching bytes the matcher tolerates before starting a new match
+        ///
+        /// Default: 8, or the match threshold of the profile
+        #[arg(long, verbatim_doc_comment)]
+        match_threshold: Option<usize>,
+        /// The maximum distance applying the patch may seek backward in the old file (with an
+        /// optional K, M, or G suffix)
+        ///
+        /// Regions of the new file which could only be matched further back are stored
+        /// verbatim, which allows applying the patch to an old file that can only be streamed
+        /// forward (0) or that only retains a limited amount of already read data.
+        ///
+        /// Default: unlimited
+        #[arg(long, value_parser = parse_size, verbatim_doc_comment)]
+        max_backward_seek: Option<usize>,
+        /// Record the permissions and modification time of the new file in the patch
+        ///
+        /// The recorded metadata can be restored when patching with `--restore-metadata`. When
+        /// diffing directories, symbolic links in the new directory are recorded as well.
+        #[arg(long, verbatim_doc_comment)]
+        preserve_metadata: bool,
+        /// Print statistics about the generated patch
+        ///
+        /// The statistics include which ranges of the old file are referenced by the patch.
+        /// Regions of the old file outside of these ranges don't affect the result of patching.
+        #[arg(long, verbatim_doc_comment)]
+        stats: bool,
+        /// The maximum size of the patch as a fraction of the size of the new file
+        ///
+        /// If the patch is larger than this, it's still written, but statistics about it are
+        /// printed and the command exits with status 3. This allows scripts to fall back to
+        /// shipping the full new file when a patch doesn't save enough space.
+        ///
+        /// Default: no limit
+        #[arg(long, verbatim_doc_comment)]
+        max_ratio: Option<f64>,
+        /// The platform the new file is built for, such as `android`, to record in the patch
+        #[arg(long)]
+        target_platform: Option<String>,
+        /// The ABI the new file is built for, such as `arm64-v8a`, to record in the patch
+        #[arg(long)]
+        target_abi: Option<String>,
+        /// The version code of the new file to record in the patch
+        #[arg(long)]
+        target_version_code: Option<u64>,
+        /// Record the version of ina and the time the patch was created in the patch
+        ///
+        /// The creation time is taken from the SOURCE_DATE_EPOCH environment variable if it's
+        /// set. Without this flag, the patch only depends on its inputs and settings, so creating
+        /// it again produces a byte-identical patch.
+        #[arg(long, verbatim_doc_comment)]
+        provenance: bool,
+        /// A logical identifier of the old file to record in the patch, such as `app-v1-arm64`
+        #[arg(long)]
+        old_id: Option<String>,
+        /// A logical identifier of the new file to record in the patch, such as `app-v2-arm64`
+        #[arg(long)]
+        new_id: Option<String>,
+        /// Record the paths of the old and new files in the patch if no identifiers are given
+        ///
+        /// Paths may reveal details of the machine creating the patch, so they're only recorded
+        /// when requested.
+        #[arg(long, verbatim_doc_comment)]
+        provenance_paths: bool,
+        /// A UTF-8 text file, such as release notes, to record in the patch as its annotation
+        ///
+        /// The annotation is stored compressed in the patch header and can be printed with
+        /// `ina info --annotation`. It may be at most 1 MiB long.
+        #[arg(long, verbatim_doc_comment)]
+        annotation_file: Option<PathBuf>,
+        /// Record which blocks of the new file differ from the old file, in blocks of the given
+        /// size (with an optional K or M suffix)
+        ///
+        /// The block map allows `ina patch --changed-blocks` to write only the changed blocks over
+        /// a copy of the old file, such as a partition on a block device. The block size must be
+        /// a power of two between 512 bytes and 1 MiB.
+        ///
+        /// Default block size: 4K
+        #[arg(
+            long,
+            value_name = "BLOCK_SIZE",
+            num_args = 0..=1,
+            default_missing_value = "4K",
+            value_parser = parse_size,
+            verbatim_doc_comment,
+        )]
+        block_map: Option<usize>,
+        /// Record the compression and matcher settings in the patch
+        ///
+        /// Recorded settings allow `ina check-rediff` to tell whether the patch was created with
+        /// outdated settings.
+        #[arg(long, verbatim_doc_comment)]
+        record_settings: bool,
+        /// Record the length of the new file in the patch
+        ///
+        /// The recorded length allows patchers to size the new file before writing it.
+        #[arg(long, verbatim_doc_comment)]
+        record_new_len: bool,
+        /// Record a checksum of the compressed patch data in the patch
+        ///
+        /// The checksum is checked while patching, so corruption of the patch in storage or
+        /// transit is reported as an error. Recording it requires the compressed patch to be
+        /// buffered in memory.
+        #[arg(long, verbatim_doc_comment)]
+        payload_checksum: bool,
+        /// How much of the old file to record in the patch
+        ///
+        /// Patching checks the recorded parts of the old file before writing any output, so
+        /// patching the wrong old file fails with exit status 7. With `full`, the entire old file
+        /// is checksummed. With `sampled`, only 64 evenly spaced regions of 4 KiB are, which
+        /// bounds the time the check takes for large old files but may miss small differences.
+        ///
+        /// Default: none
+        #[arg(long, value_enum, verbatim_doc_comment)]
+        base_check: Option<BaseCheckMode>,
+        /// Record digests of the old and new files in the patch
+        ///
+        /// Patching checks the old file against its digest before writing any output and the new
+        /// file against its digest once it's complete, so patching the wrong old file or producing
+        /// a corrupted new file fails with exit status 7. The digests are computed with the
+        /// algorithm given by `--digest`.
+        #[arg(long, verbatim_doc_comment)]
+        embed_digests: bool,
+        /// Diff the files in windows of this many bytes of the new file
+        ///
+        /// Neither file is read into memory in its entirety, so files larger than the address
+        /// space, such as files over 4 GiB on 32-bit systems, can be diffed. Memory usage is
+        /// roughly 11 times the window length, but matches are only found between corresponding
+        /// regions of the files, so the patch may be larger. Not supported with `--text`.
+        ///
+        /// Default: the files are diffed as a whole
+        #[arg(long, verbatim_doc_comment)]
+        diff_window: Option<u64>,
+        /// The hash algorithm of the digests of new files recorded in a bundle or of the digests
+        /// recorded by `--embed-digests`
+        ///
+        /// Bundles hashed with `sha256` can only be applied by versions of ina which support the
+        /// choice of algorithm.
+        ///
+        /// Default: blake3
+        #[arg(long, value_enum, verbatim_doc_comment)]
+        digest: Option<DigestKind>,
+        /// How to treat the old and new files
+        ///
+        /// In text mode, matches are aligned to line boundaries where possible and the patch
+        /// records which lines changed, which `ina info` prints as a summary. With `auto`, the
+        /// files are treated as text if both of them are valid UTF-8 without control characters
+        /// other than whitespace.
+        ///
+        /// Default: binary, or the text mode of the profile
+        #[arg(long, value_enum, verbatim_doc_comment)]
+        text: Option<TreatAs>,
     },
     /// Reconstruct a new file from and old file and a patch
+    ///
+    /// If the patch file contains several concatenated patches, each is applied to the output of
+    /// the previous one.
     Patch {
         /// The path of the old file
         old: PathBuf,
         /// The path of the patch file
+        ///
+        /// A patch stored without compression in a zip archive, such as an OTA package, can be
+        /// read from the archive without extracting it by giving its path as ARCHIVE:ENTRY:
+        ///
+        ///   ina patch libfoo.so ota.zip:patches/libfoo.ina libfoo-new.so
+        #[arg(verbatim_doc_comment)]
         patch: PathBuf,
         /// The path of the output new file
-        new: PathBuf,
+        #[arg(required_unless_present_any = ["expect", "output_dir"])]
+        new: Option<PathBuf>,
+        /// Apply a bundle to the old directory, updating the given directory all at once
+        ///
+        /// The old path must be the directory the bundle was created from and the patch must be a
+        /// bundle created by `ina diff` from two directories. The given directory may be the old
+        /// directory itself to update it in place.
+        ///
+        /// Every new file is first written to a staging directory inside the output directory and
+        /// checked against the hash recorded in the bundle, as is every unchanged file. Only once
+        /// all files match are they moved into place. If that fails partway through, the files
+        /// moved so far are moved back, so the output directory never contains a mix of old and
+        /// new files. The command exits with status 7 if a file doesn't match its hash.
+        #[arg(
+            long,
+            conflicts_with_all = ["new", "expect", "isolate"],
+            verbatim_doc_comment,
+        )]
+        output_dir: Option<PathBuf>,
+        /// Compare the patch output against an existing file instead of writing it
+        ///
+        /// The patch is applied in memory and its output is compared with the given file. If they
+        /// differ, the offset of the first differing byte is reported and the command exits with
+        /// an error.
+        #[arg(
+            long,
+            conflicts_with_all = ["new", "isolate", "restore_metadata"],
+            verbatim_doc_comment,
+        )]
+        expect: Option<PathBuf>,
         /// The size in bytes of the buffer to use for decompression
         ///
         /// By default, the patching process creates an internal read buffer whose size is
@@ -74,101 +367,2436 @@ enum Command {
         /// Default: varies
         #[arg(long, verbatim_doc_comment)]
         decompression_buffer_size: Option<usize>,
+        /// The approximate maximum number of bytes of memory to use for patching
+        ///
+        /// Patches which can't be applied within this limit are rejected. When set, internal
+        /// buffers are sized to fit within the limit unless `--decompression-buffer-size` is
+        /// given, in which case that buffer counts toward the limit.
+        ///
+        /// Default: no limit
+        #[arg(long, verbatim_doc_comment)]
+        max_memory: Option<u64>,
+        /// The maximum size of the decompressed patch data as a multiple of the new file's size
+        ///
+        /// Patches which decompress to much more data than they output, such as crafted
+        /// decompression bombs, are rejected. Patches created by this tool stay well within a
+        /// multiplier of 2.
+        ///
+        /// Default: no limit
+        #[arg(long, verbatim_doc_comment)]
+        max_expansion: Option<u32>,
+        /// The maximum size in bytes of the new file
+        ///
+        /// Patches which would produce a larger file are rejected. Together with
+        /// `--max-expansion`, this also rejects patches whose data declares a decompressed size
+        /// too large for the new file before decompressing it.
+        ///
+        /// Default: no limit
+        #[arg(long, verbatim_doc_comment)]
+        max_new_len: Option<u64>,
+        /// Restore the file metadata recorded in the patch to the new file
+        ///
+        /// This has no effect if the patch was created without `--preserve-metadata`.
+        #[arg(long, verbatim_doc_comment)]
+        restore_metadata: bool,
+        /// Reject the patch unless it was created for the given platform
+        #[arg(long)]
+        expect_platform: Option<String>,
+        /// Reject the patch unless it was created for the given ABI
+        #[arg(long)]
+        expect_abi: Option<String>,
+        /// Reject the patch unless it was created for the given version code
+        #[arg(long)]
+        expect_version_code: Option<u64>,
+        /// Apply the patch in a separate, sandboxed process
+        ///
+        /// The child process opens the old file, enables the strictest sandbox available on this
+        /// platform, and then reads the patch and sends the new file back over pipes. This isolates
+        /// patching of untrusted patches from the rest of the system even where sandboxing the
+        /// calling process itself isn't possible. On platforms without a supported sandbox, the
+        /// patch is still applied in a separate process.
+        ///
+        /// When combined with `--restore-metadata`, the patch header is read a second time in this
+        /// process to restore the metadata.
+        #[arg(long, verbatim_doc_comment)]
+        isolate: bool,
+        /// Record the progress of writing the new file in the given state file
+        ///
+        /// The progress is saved atomically every `--state-interval` bytes, after the new file's
+        /// data has been flushed to disk. If the command is interrupted and rerun with the same
+        /// arguments, it resumes where it left off as long as the new file still starts with the
+        /// data recorded as written. The state file is removed once the new file is complete.
+        #[arg(
+            long,
+            conflicts_with_all = ["output_dir", "expect", "isolate"],
+            verbatim_doc_comment,
+        )]
+        state_file: Option<PathBuf>,
+        /// The number of bytes to write between saving progress to the state file (with an
+        /// optional K, M, or G suffix)
+        ///
+        /// Default: 8M
+        #[arg(
+            long,
+            requires = "state_file",
+            value_parser = parse_size,
+            verbatim_doc_comment,
+        )]
+        state_interval: Option<usize>,
+        /// Only write the blocks of the new file which differ from the old file
+        ///
+        /// The new file must already exist and hold the old file, e.g., as a partition on a block
+        /// device which the old file was copied to. Each block the patch's block map records as
+        /// changed is written at its offset, and the others are left as they are. A regular new
+        /// file is then truncated or extended to the length of the new file. The patch must have
+        /// been created with `diff --block-map`. Concatenated patches aren't supported.
+        #[arg(
+            long,
+            conflicts_with_all = ["output_dir", "expect", "isolate", "state_file"],
+            verbatim_doc_comment,
+        )]
+        changed_blocks: bool,
+        /// Share unchanged blocks of the old file with the new file instead of writing them
+        ///
+        /// On filesystems supporting reflinks, such as Btrfs and XFS, whole blocks of the new file
+        /// which are unchanged from the old file are cloned rather than written, which greatly
+        /// reduces the amount of data written for large, mostly unchanged files. Elsewhere, the new
+        /// file is written as usual. Patch limits aren't supported in this mode.
+        #[cfg(target_os = "linux")]
+        #[arg(
+            long,
+            conflicts_with_all = [
+                "output_dir",
+                "expect",
+                "isolate",
+                "max_memory",
+                "max_expansion",
+                "max_new_len",
+                "expect_platform",
+                "expect_abi",
+                "expect_version_code",
+                "state_file",
+                "changed_blocks",
+            ],
+            verbatim_doc_comment,
+        )]
+        reflink: bool,
+        /// Write the new file with direct I/O, bypassing the page cache
+        ///
+        /// The new file is opened with O_DIRECT and written in page-aligned blocks of 1 MiB, the
+        /// last of which is padded with zeros and then truncated away. This avoids evicting other
+        /// data from the page cache when reconstructing large files at high throughput. The
+        /// filesystem of the new file must support direct I/O. Patches concatenated after the first
+        /// one are applied without direct I/O.
+        #[cfg(target_os = "linux")]
+        #[arg(
+            long,
+            conflicts_with_all = [
+                "output_dir",
+                "expect",
+                "isolate",
+                "reflink",
+                "state_file",
+                "changed_blocks",
+            ],
+            verbatim_doc_comment,
+        )]
+        direct_io: bool,
+    },
+    /// Apply a patch read from standard input, writing the new file to standard output
+    ///
+    /// This is the child process of `patch --isolate` and isn't intended to be run directly.
+    #[command(name = ISOLATED_PATCH_COMMAND, hide = true)]
+    IsolatedPatch {
+        /// The path of the old file
+        old: PathBuf,
+        #[arg(long)]
+        decompression_buffer_size: Option<usize>,
+        #[arg(long)]
+        max_memory: Option<u64>,
+        #[arg(long)]
+        max_expansion: Option<u32>,
+        #[arg(long)]
+        max_new_len: Option<u64>,
+        #[arg(long)]
+        expect_platform: Option<String>,
+        #[arg(long)]
+        expect_abi: Option<String>,
+        #[arg(long)]
+        expect_version_code: Option<u64>,
+    },
+    /// Recompress a patch with new settings without diffing again
+    ///
+    /// The control records of the input patch are decoded and compressed again, so the output
+    /// patch reconstructs the same new file from the same old file. The file metadata, target,
+    /// and provenance recorded in the input patch are carried over.
+    #[command(verbatim_doc_comment)]
+    Transcode {
+        /// The path of the input patch file
+        input: PathBuf,
+        /// The path of the output patch file
+        output: PathBuf,
+        /// The number of threads to use for compression
+        ///
+        /// See `ina diff --help` for details.
+        ///
+        /// Default: 1
+        #[arg(long, verbatim_doc_comment)]
+        compression_threads: Option<u32>,
+        /// The compression level to use for compressing the output patch file
+        ///
+        /// See `ina diff --help` for details.
+        ///
+        /// Default: 19
+        #[arg(long, verbatim_doc_comment)]
+        compression_level: Option<i32>,
+        /// The base-2 logarithm of the compression window size
+        ///
+        /// See `ina diff --help` for details.
+        ///
+        /// Default: chosen by the compression level
+        #[arg(long, verbatim_doc_comment)]
+        window_log: Option<u32>,
+        /// The codec to compress the output patch file with
+        #[arg(long, value_enum, default_value_t = Codec::Zstd)]
+        codec: Codec,
+        /// Record a checksum of the compressed patch data in the output patch
+        ///
+        /// See `ina diff --help` for details.
+        #[arg(long, verbatim_doc_comment)]
+        payload_checksum: bool,
+    },
+    /// Split a patch or bundle into segments for transports with a size limit per file
+    ///
+    /// Each segment records its index, the number of segments, and checksums of itself and of the
+    /// whole patch, so missing, reordered, or corrupted segments are detected when they're joined.
+    /// Segments are written next to the patch, or to `--output-dir`, and named after the patch
+    /// with their zero-padded index appended, e.g., `app.ina.000`, `app.ina.001`, etc.
+    #[command(verbatim_doc_comment)]
+    Split {
+        /// The path of the patch or bundle file
+        patch: PathBuf,
+        /// The maximum size of each segment, including its 36-byte header
+        ///
+        /// The size is in bytes and may have a K, M, or G suffix for KiB, MiB, or GiB.
+        #[arg(long, value_parser = parse_size, verbatim_doc_comment)]
+        chunk_size: usize,
+        /// The directory to write the segments to
+        #[arg(long)]
+        output_dir: Option<PathBuf>,
+    },
+    /// Join segments created by `ina split` back into a patch or bundle
+    ///
+    /// The segments must be given in order, which shell globs such as `app.ina.*` do.
+    #[command(verbatim_doc_comment)]
+    Join {
+        /// The paths of the segments
+        #[arg(required = true)]
+        segments: Vec<PathBuf>,
+        /// The path of the output patch or bundle file
+        output: PathBuf,
+    },
+    /// Check whether regenerating patches with the current settings would make them smaller
+    ///
+    /// Each patch is recompressed with the current compression settings to measure the savings,
+    /// which is much cheaper than diffing again. Regenerating a patch is recommended if this
+    /// saves at least `--min-savings`, if it uses an outdated patch format, or if it was matched
+    /// with different matcher settings, which is also assumed for patches created without
+    /// `--record-settings`. The current settings are taken from the flags below and the `diff`
+    /// section of the config file, e.g., one written by `ina tune`.
+    ///
+    /// With `--json`, the results are printed as a JSON array.
+    #[command(verbatim_doc_comment)]
+    CheckRediff {
+        /// The paths of the patch files
+        #[arg(required = true)]
+        patches: Vec<PathBuf>,
+        /// The minimum savings from recompression, as a percentage of the patch size, worth
+        /// regenerating a patch for
+        #[arg(long, default_value_t = 1.0)]
+        min_savings: f64,
+        /// The number of threads to use for compression
+        ///
+        /// See `ina diff --help` for details.
+        ///
+        /// Default: 1
+        #[arg(long, verbatim_doc_comment)]
+        compression_threads: Option<u32>,
+        /// The current compression level
+        ///
+        /// Default: 19
+        #[arg(long, verbatim_doc_comment)]
+        compression_level: Option<i32>,
+        /// The current base-2 logarithm of the compression window size
+        ///
+        /// Default: chosen by the compression level
+        #[arg(long, verbatim_doc_comment)]
+        window_log: Option<u32>,
+        /// The current matcher mismatch threshold
+        ///
+        /// Default: 8
+        #[arg(long, verbatim_doc_comment)]
+        match_threshold: Option<usize>,
     },
     /// Display patch metadata
+    ///
+    /// Bundles are recognized automatically, in which case a summary of their entries is printed
+    /// instead. If the size of the new file is known, the size of the patch relative to it and an
+    /// estimate of how long applying the patch takes are printed as well. With `--json`, the
+    /// metadata is printed as a JSON object.
+    #[command(verbatim_doc_comment)]
     Info {
+        /// The path of the patch file or bundle
+        patch: PathBuf,
+        /// List every entry of a bundle with its new file's size and hash and its patch's size and
+        /// format version
+        #[arg(long)]
+        list: bool,
+        /// Print exact sizes in bytes and unrounded percentages and durations, for scripts
+        #[arg(long)]
+        machine: bool,
+        /// The rate at which applying a patch is assumed to produce the new file when estimating
+        /// how long it takes, in bytes per second (with an optional K, M, or G suffix)
+        ///
+        /// Defaults to 128M.
+        #[arg(long, value_parser = parse_size, verbatim_doc_comment)]
+        apply_throughput: Option<usize>,
+        /// Print only the annotation recorded in the patch, such as release notes
+        ///
+        /// The command exits with an error if the patch has no annotation.
+        #[arg(long, conflicts_with = "list", verbatim_doc_comment)]
+        annotation: bool,
+    },
+    /// Dump the control records of a patch, flagging anomalies
+    ///
+    /// Each record is printed along with the offsets in the old and new files at which it
+    /// begins. Records which can't be applied or which a well-formed patch wouldn't contain are
+    /// flagged. If any anomalies are found or the control stream can't be decoded, the command
+    /// exits with an error.
+    #[command(verbatim_doc_comment)]
+    DebugControls {
+        /// The path of the patch file
+        patch: PathBuf,
+        /// The path of the old file the patch was created against
+        ///
+        /// If given, records reading past the end of the old file are flagged as well.
+        #[arg(long, verbatim_doc_comment)]
+        old: Option<PathBuf>,
+    },
+    /// Print statistics about a patch, including how it seeks in the old file
+    ///
+    /// The old file is read in the order of the patch's control records. A seek is any read which
+    /// doesn't start where the previous one ended, and seeks are grouped by their distance
+    /// rounded down to a power of two. The size of the patch relative to the new file and an
+    /// estimate of how long applying the patch takes are printed as well. With `--json`, the
+    /// statistics are printed as a JSON object.
+    #[command(verbatim_doc_comment)]
+    Stats {
+        /// The path of the patch file
+        patch: PathBuf,
+        /// Print exact sizes in bytes and unrounded percentages and durations, for scripts
+        #[arg(long)]
+        machine: bool,
+        /// The rate at which applying a patch is assumed to produce the new file when estimating
+        /// how long it takes, in bytes per second (with an optional K, M, or G suffix)
+        ///
+        /// Defaults to 128M.
+        #[arg(long, value_parser = parse_size, verbatim_doc_comment)]
+        apply_throughput: Option<usize>,
+        /// Fail with exit status 6 if the patch seeks backward in the old file by more than this
+        /// many bytes (with an optional K, M, or G suffix)
+        ///
+        /// This checks whether the patch can be applied to an old file streamed from a source
+        /// which only retains this many bytes before the current position.
+        #[arg(long, value_parser = parse_size, verbatim_doc_comment)]
+        max_backward_seek: Option<usize>,
+    },
+    /// Report the byte ranges two files have in common without producing a patch
+    ///
+    /// The files are matched as when diffing them, using the match threshold from the config
+    /// file. Each match pairs a region of the new file with a region of the same length of the
+    /// old file, and its similarity is the percentage of positions at which their bytes are equal.
+    /// The regions of either file outside of any match are listed as unmatched. With `--json`,
+    /// the report is printed as a JSON object.
+    #[command(verbatim_doc_comment)]
+    Compare {
+        /// The path of the old file
+        old: PathBuf,
+        /// The path of the new file
+        new: PathBuf,
+        /// Print exact sizes in bytes and unrounded percentages, for scripts
+        #[arg(long)]
+        machine: bool,
+    },
+    /// Check that a patch conforms to the patch format without applying it
+    ///
+    /// The header and control stream are validated, including that every varint is encoded in
+    /// as few bytes as possible and that the data section matches its recorded checksum and
+    /// size. Each problem is printed along with its severity and a stable code. If any errors are
+    /// found, the command exits with status 5; warnings don't affect the exit status.
+    ///
+    /// With `--json`, the report is printed as a JSON object.
+    #[command(verbatim_doc_comment)]
+    Lint {
         /// The path of the patch file
         patch: PathBuf,
     },
+    /// Find Pareto-optimal diff settings for a corpus of file pairs
+    ///
+    /// Every combination of the given settings is used to diff every file pair in the corpus. The
+    /// settings producing the smallest patches are printed as JSON suitable for use as a config
+    /// file, along with every other setting combination on the Pareto front of patch size and
+    /// diffing time.
+    #[command(verbatim_doc_comment)]
+    Tune {
+        /// The corpus directory
+        ///
+        /// Each subdirectory of the corpus directory represents one file pair and must contain
+        /// files named `old` and `new`.
+        #[arg(long, verbatim_doc_comment)]
+        corpus: PathBuf,
+        /// Comma-separated compression levels to evaluate
+        ///
+        /// Default: 3,9,19
+        #[arg(long, value_delimiter = ',', verbatim_doc_comment)]
+        compression_levels: Vec<i32>,
+        /// Comma-separated base-2 logarithms of compression window sizes to evaluate
+        ///
+        /// Default: chosen by the compression level
+        #[arg(long, value_delimiter = ',', verbatim_doc_comment)]
+        window_logs: Vec<u32>,
+        /// Comma-separated matcher mismatch thresholds to evaluate
+        ///
+        /// Default: 4,8,16
+        #[arg(long, value_delimiter = ',', verbatim_doc_comment)]
+        match_thresholds: Vec<usize>,
+        /// The number of threads to use for compression
+        ///
+        /// Default: 1
+        #[arg(long, verbatim_doc_comment)]
+        compression_threads: Option<u32>,
+        /// The path to write the resulting JSON to instead of standard output
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+    /// Run self-tests to check that this build of ina works correctly on this device
+    ///
+    /// Each test is printed along with whether it passed. If any test fails, the command exits
+    /// with an error. If no test suites are selected, all of them are run.
+    #[command(verbatim_doc_comment)]
+    Selftest {
+        /// Apply a suite of golden patches to check that the patch format is decoded correctly
+        #[arg(long)]
+        format: bool,
+    },
+    /// Diagnose the environment ina runs in, e.g., when applying updates fails
+    ///
+    /// Reports this build's version and codecs, whether patching can be sandboxed and why not,
+    /// whether `patch --reflink` is supported, the available memory compared to the configured
+    /// `max_memory` limit, and the result of applying the golden patches of `ina selftest`. Each
+    /// check is printed as ok, warning, or FAILED. Only failed checks make the command exit with
+    /// an error.
+    #[command(verbatim_doc_comment)]
+    Doctor {
+        /// The directory to test reflink support in, which should be on the filesystem updated
+        /// files are written to
+        ///
+        /// Default: the current directory
+        #[arg(long)]
+        dir: Option<PathBuf>,
+    },
+    /// Run the steps of a pipeline file, e.g., to generate and verify the patches of a release
+    ///
+    /// The pipeline file is TOML, or JSON if its name ends in `.json`, and lists `[[step]]` tables
+    /// whose `kind` is one of:
+    ///
+    ///   diff     Generate `output` from `old` to `new`, as a bundle if both are directories
+    ///   patch    Apply `patch` to `old`, writing `output`
+    ///   verify   Check that applying `patch` to `old` produces `new`
+    ///   command  Run `argv`, e.g., to sign files, declaring the paths it reads and writes as
+    ///            `inputs` and `outputs`
+    ///
+    /// Relative paths are resolved against the directory of the pipeline file. Diff, patch, and
+    /// verify steps use the settings of the config file given by their `config` key, or else the
+    /// global one. Steps run in parallel, except that a step reading a path another step writes
+    /// runs after it, and each old file is read and indexed once for all the diffs against it.
+    /// Steps depending on a failed step are skipped, and the command fails if any step failed.
+    #[command(verbatim_doc_comment)]
+    Run {
+        /// The pipeline file to run
+        pipeline: PathBuf,
+
+        /// The maximum number of steps to run at once
+        ///
+        /// Default: `jobs` in the pipeline file, or else the number of available CPUs
+        #[arg(long)]
+        jobs: Option<usize>,
+    },
+    /// Serve the files in a directory over HTTP, for testing patch downloads
+    ///
+    /// Only available in builds with the `dev` feature. Files are served with the content type of
+    /// patches if their names end in `.ina`, and support single-range requests, so downloads can be
+    /// resumed. The URL being served is printed once the server is listening, and the server runs
+    /// until it's killed. It isn't hardened for use outside of tests.
+    #[cfg(feature = "dev")]
+    #[command(verbatim_doc_comment)]
+    ServePatches {
+        /// The directory whose files to serve
+        #[arg(long)]
+        dir: PathBuf,
+
+        /// The address to listen on, with port 0 choosing any free port
+        #[arg(long, default_value = "127.0.0.1:0")]
+        addr: String,
+    },
+}
+
+/// A compression codec for patch files
+#[derive(Clone, Copy, ValueEnum)]
+enum Codec {
+    Zstd,
+    /// Brotli, for delivery through infrastructure which supports it but not zstd
+    Brotli,
+}
+
+/// The hash algorithm of the digests `diff` records in bundles
+#[derive(Clone, Copy, Debug, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+enum DigestKind {
+    Blake3,
+    Sha256,
+}
+
+impl From<DigestKind> for DigestAlgorithm {
+    fn from(value: DigestKind) -> Self {
+        match value {
+            DigestKind::Blake3 => DigestAlgorithm::Blake3,
+            DigestKind::Sha256 => DigestAlgorithm::Sha256,
+        }
+    }
+}
+
+/// A preset of `diff` settings suited to a kind of files
+#[derive(Clone, Copy, Debug, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+enum ProfileKind {
+    Executable,
+    Archive,
+    Text,
+}
+
+impl From<ProfileKind> for Profile {
+    fn from(value: ProfileKind) -> Self {
+        match value {
+            ProfileKind::Executable => Profile::Executable,
+            ProfileKind::Archive => Profile::Archive,
+            ProfileKind::Text => Profile::Text,
+        }
+    }
+}
+
+/// How `diff` treats its input files
+#[derive(Clone, Copy, Debug, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+enum TreatAs {
+    Binary,
+    Auto,
+    Text,
+}
+
+/// How much of the old file `diff` records in the patch
+#[derive(Clone, Copy, Debug, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+enum BaseCheckMode {
+    None,
+    Full,
+    Sampled,
+}
+
+impl From<BaseCheckMode> for BaseCheck {
+    fn from(value: BaseCheckMode) -> Self {
+        match value {
+            BaseCheckMode::None => BaseCheck::None,
+            BaseCheckMode::Full => BaseCheck::Full,
+            BaseCheckMode::Sampled => BaseCheck::sampled(),
+        }
+    }
+}
+
+impl From<TreatAs> for TextMode {
+    fn from(value: TreatAs) -> Self {
+        match value {
+            TreatAs::Binary => TextMode::Binary,
+            TreatAs::Auto => TextMode::Auto,
+            TreatAs::Text => TextMode::Text,
+        }
+    }
 }
 
-fn main() -> anyhow::Result<()> {
+fn main() -> ExitCode {
     let args = Args::parse();
+    let verbosity = match (args.quiet, args.verbose) {
+        (true, _) => Verbosity::Quiet,
+        (false, true) => Verbosity::Verbose,
+        (false, false) => Verbosity::Normal,
+    };
+    let output = Output::new(verbosity, args.json);
+
+    let command = match (args.command, args.capabilities) {
+        (Some(command), false) => command,
+        (None, true) => {
+            print_capabilities();
+            return ExitCode::SUCCESS;
+        }
+        (Some(_), true) => Args::command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                "the argument '--capabilities' cannot be used with a subcommand",
+            )
+            .exit(),
+        (None, false) => Args::command()
+            .error(ErrorKind::MissingSubcommand, "a subcommand is required")
+            .exit(),
+    };
+
+    match run(command, args.config.as_deref(), &output) {
+        Ok(code) => code,
+        Err(e) => ExitCode::from(output.error(&e)),
+    }
+}
 
-    match args.command {
+/// Runs `command`, returning the exit status to report on success
+fn run(command: Command, config: Option<&Path>, output: &Output) -> anyhow::Result<ExitCode> {
+    let config = Config::load(config)?;
+
+    match command {
         Command::Diff {
             old,
             new,
             patch,
+            old_zip,
+            new_zip,
+            profile,
             compression_threads,
             compression_level,
+            window_log,
+            match_threshold,
+            max_backward_seek,
+            preserve_metadata,
+            stats,
+            max_ratio,
+            target_platform,
+            target_abi,
+            target_version_code,
+            provenance,
+            old_id,
+            new_id,
+            provenance_paths,
+            annotation_file,
+            block_map,
+            record_settings,
+            record_new_len,
+            payload_checksum,
+            base_check,
+            embed_digests,
+            diff_window,
+            digest,
+            text,
         } => {
-            let mut old_file = File::open(&old)
-                .with_context(|| format!("Failed to open old file '{}'", old.display()))?;
-            let len: usize = old_file
-                .metadata()
-                .with_context(|| {
-                    format!("Failed to read metadata of old file '{}'", old.display())
-                })?
-                .len()
-                .try_into()
-                .with_context(|| {
+            let (old, new, patch) = diff_inputs(old, new, patch, old_zip, new_zip)?;
+
+            // Flags which aren't given are taken from the config file
+            let settings = DiffSettings {
+                profile,
+                compression_threads,
+                compression_level,
+                window_log,
+                match_threshold,
+                max_backward_seek,
+                preserve_metadata: preserve_metadata.then_some(true),
+                max_ratio,
+                provenance: provenance.then_some(true),
+                provenance_paths: provenance_paths.then_some(true),
+                record_settings: record_settings.then_some(true),
+                record_new_len: record_new_len.then_some(true),
+                payload_checksum: payload_checksum.then_some(true),
+                base_check,
+                embed_digests: embed_digests.then_some(true),
+                diff_window,
+                digest,
+                text,
+            }
+            .or(config.diff);
+
+            let mut diff_config = diff_config(&settings);
+            diff_config.target(target(target_platform, target_abi, target_version_code));
+            diff_config.provenance(provenance_of(&settings, &old, &new, old_id, new_id)?);
+            if let Some(annotation_file) = annotation_file {
+                let annotation = fs::read_to_string(&annotation_file).with_context(|| {
                     format!(
-                        "Old file '{}' is too large to read into memory",
-                        old.display(),
+                        "Failed to read annotation file '{}'",
+                        annotation_file.display()
                     )
                 })?;
-            // Reserve a byte of extra space for the sentinel
-            let mut old_data = Vec::with_capacity(len + 1);
-            old_file
-                .read_to_end(&mut old_data)
-                .context("Failure occurred while reading old file")?;
-            // Last byte must be 0
-            old_data.push(0);
+                diff_config.annotation(Some(&annotation));
+            }
+            if let Some(block_size) = block_map {
+                diff_config.block_map(Some(u32::try_from(block_size).unwrap_or(u32::MAX)));
+            }
+            let preserve_metadata = settings.preserve_metadata.unwrap_or(false);
+            let max_ratio = settings.max_ratio;
 
-            let new_data = fs::read(&new)
-                .with_context(|| format!("Failed to read new file '{}'", new.display()))?;
+            if let (Input::Path(old), Input::Path(new)) = (&old, &new)
+                && old.is_dir()
+                && new.is_dir()
+            {
+                let bundle_file = File::create(&patch).with_context(|| {
+                    format!("Failed to create bundle file '{}'", patch.display())
+                })?;
+                let summary = bundle::diff_dirs(
+                    old,
+                    new,
+                    bundle_file,
+                    &diff_config,
+                    preserve_metadata,
+                    settings.digest.unwrap_or(DigestKind::Blake3).into(),
+                )?;
+                let bundle_len = fs::metadata(&patch)
+                    .with_context(|| {
+                        format!("Failed to read metadata of bundle '{}'", patch.display())
+                    })?
+                    .len();
+
+                let ratio = bundle_len as f64 / summary.new_len as f64;
+                let ratio_exceeded = max_ratio.is_some_and(|max_ratio| ratio > max_ratio);
+                if stats || (ratio_exceeded && output.normal()) {
+                    println!("Bundle size: {bundle_len} bytes");
+                    print_bundle_summary(&summary);
+                }
+                if ratio_exceeded {
+                    let message = format!(
+                        "Bundle is {:.2}% of the size of the new files, exceeding the maximum \
+                         ratio",
+                        ratio * 100.0,
+                    );
+                    return Ok(ExitCode::from(
+                        output.failure(ErrorCategory::RatioExceeded, &message),
+                    ));
+                }
+                output.detail(format_args!(
+                    "Wrote bundle '{}' ({bundle_len} bytes, {:.2}% of the new files)",
+                    patch.display(),
+                    ratio * 100.0,
+                ));
+                return Ok(ExitCode::SUCCESS);
+            }
+
+            if preserve_metadata {
+                let file_metadata = match &new {
+                    Input::Path(new) => {
+                        let new_metadata = fs::metadata(new).with_context(|| {
+                            format!("Failed to read metadata of new file '{}'", new.display())
+                        })?;
+                        FileMetadata::from_metadata(&new_metadata)
+                    }
+                    Input::ZipEntry(entry) => entry.file_metadata()?,
+                };
+                diff_config.file_metadata(Some(file_metadata));
+            }
 
             let mut patch_file = File::create(&patch)
                 .with_context(|| format!("Failed to create patch file '{}'", patch.display()))?;
 
-            let mut diff_config = DiffConfig::default();
-            if let Some(threads) = compression_threads {
-                diff_config.compression_threads(threads);
-            }
-            if let Some(level) = compression_level {
-                diff_config.compression_level(level);
+            let diff_stats = match settings.diff_window {
+                Some(window_len) => {
+                    // The window may also be set in the config file, so it can't be ruled out by
+                    // argument conflicts
+                    let (Input::Path(old), Input::Path(new)) = (&old, &new) else {
+                        anyhow::bail!(
+                            "A diff window can't be used with files read from zip entries"
+                        );
+                    };
+                    diff_config.diff_window_len(window_len);
+                    let old_file = File::open(old)
+                        .with_context(|| format!("Failed to open old file '{}'", old.display()))?;
+                    let new_file = File::open(new)
+                        .with_context(|| format!("Failed to open new file '{}'", new.display()))?;
+                    ina::diff_windowed(
+                        BufReader::new(old_file),
+                        BufReader::new(new_file),
+                        &mut patch_file,
+                        &diff_config,
+                    )
+                }
+                None => {
+                    let old_data = match &old {
+                        Input::Path(old) => read_old(old)?,
+                        // Reserve a byte of extra space for the sentinel
+                        Input::ZipEntry(entry) => OldBlob::from_vec(entry.read(1)?),
+                    };
+                    let new_data = match &new {
+                        Input::Path(new) => fs::read(new).with_context(|| {
+                            format!("Failed to read new file '{}'", new.display())
+                        })?,
+                        Input::ZipEntry(entry) => entry.read(0)?,
+                    };
+                    ina::diff_with_config(&old_data, &new_data, &mut patch_file, &diff_config)
+                }
             }
+            .context("I/O error occurred while generating patch file")?;
 
-            ina::diff_with_config(&old_data, &new_data, &mut patch_file, &diff_config)
-                .context("I/O error occurred while generating patch file")?;
+            let ratio = diff_stats.patch_len() as f64 / diff_stats.new_len() as f64;
+            let ratio_exceeded = max_ratio.is_some_and(|max_ratio| ratio > max_ratio);
+            if stats || (ratio_exceeded && output.normal()) {
+                print_stats(&diff_stats);
+            }
+            if ratio_exceeded {
+                let message = format!(
+                    "Patch is {:.2}% of the size of the new file, exceeding the maximum ratio",
+                    ratio * 100.0,
+                );
+                return Ok(ExitCode::from(
+                    output.failure(ErrorCategory::RatioExceeded, &message),
+                ));
+            }
+            output.detail(format_args!(
+                "Wrote patch file '{}' ({} bytes, {:.2}% of the new file)",
+                patch.display(),
+                diff_stats.patch_len(),
+                ratio * 100.0,
+            ));
         }
         Command::Patch {
             old,
             patch,
             new,
+            output_dir,
+            expect,
             decompression_buffer_size,
+            max_memory,
+            max_expansion,
+            max_new_len,
+            restore_metadata,
+            expect_platform,
+            expect_abi,
+            expect_version_code,
+            isolate,
+            state_file,
+            state_interval,
+            changed_blocks,
+            #[cfg(target_os = "linux")]
+            reflink,
+            #[cfg(target_os = "linux")]
+            direct_io,
         } => {
-            let old_file = File::open(&old)
-                .with_context(|| format!("Failed to open old file '{}'", old.display()))?;
-            let patch_file = File::open(&patch)
-                .with_context(|| format!("Failed to open patch file '{}'", patch.display()))?;
-            let mut new_file = File::create(&new)
-                .with_context(|| format!("Failed to create new file '{}'", new.display()))?;
+            let patch_file = PatchInput::open(&patch)?;
+
+            let options = PatchOptions {
+                decompression_buffer_size: decompression_buffer_size
+                    .or(config.patch.decompression_buffer_size),
+                max_memory: max_memory.or(config.patch.max_memory),
+                max_expansion: max_expansion.or(config.patch.max_expansion),
+                max_new_len: max_new_len.or(config.patch.max_new_len),
+                expected_target: target(expect_platform, expect_abi, expect_version_code),
+            };
+
+            let restore_metadata =
+                restore_metadata || config.patch.restore_metadata.unwrap_or(false);
 
-            let mut patcher = match decompression_buffer_size {
-                Some(size) => {
-                    Patcher::with_buffer(old_file, BufReader::with_capacity(size, patch_file))?
+            if let Some(output_dir) = output_dir {
+                let bundle_file = patch_file.into_file("--output-dir")?;
+                let summary = bundle::apply_bundle(
+                    &old,
+                    bundle_file,
+                    &output_dir,
+                    &options,
+                    restore_metadata,
+                )
+                .with_context(|| format!("Failed to apply bundle '{}'", patch.display()))?;
+
+                output.detail(format_args!(
+                    "Updated '{}' ({} added, {} modified, {} removed, {} unchanged, {} symlinks)",
+                    output_dir.display(),
+                    summary.added,
+                    summary.modified,
+                    summary.removed,
+                    summary.unchanged,
+                    summary.symlinks,
+                ));
+                return Ok(ExitCode::SUCCESS);
+            }
+
+            if let Some(expect) = expect {
+                let old_file = File::open(&old)
+                    .with_context(|| format!("Failed to open old file '{}'", old.display()))?;
+                let expect_file = File::open(&expect).with_context(|| {
+                    format!("Failed to open expected file '{}'", expect.display())
+                })?;
+                let patcher = options
+                    .patcher(old_file, patch_file)
+                    .with_context(|| format!("Failed to apply '{}'", patch.display()))?;
+
+                match ina::verify_against(patcher, BufReader::new(expect_file))
+                    .context("Failed to apply patch file")?
+                {
+                    Some(offset) => {
+                        return Err(CategorizedError::new(
+                            ErrorCategory::Mismatch,
+                            format!(
+                                "Patch output differs from '{}' at offset {offset}",
+                                expect.display(),
+                            ),
+                        )
+                        .into());
+                    }
+                    None if output.normal() => {
+                        println!("Patch output matches '{}'", expect.display());
+                    }
+                    None => {}
+                }
+                return Ok(ExitCode::SUCCESS);
+            }
+
+            // Clap requires the new file unless the expected file or an output directory is given,
+            // both of which returned above
+            let new = new.expect("the new file is required without --expect or --output-dir");
+
+            #[cfg(not(target_os = "linux"))]
+            let reflink = false;
+            #[cfg(not(target_os = "linux"))]
+            let direct_io = false;
+            let isolate = isolate || config.patch.isolate.unwrap_or(false);
+            // Isolation may also be enabled in the config file, so it can't be ruled out by
+            // argument conflicts
+            if isolate && state_file.is_some() {
+                anyhow::bail!("Progress can't be recorded when patching in an isolated process");
+            }
+            if isolate && reflink {
+                anyhow::bail!("Files can't be reflinked when patching in an isolated process");
+            }
+            if isolate && direct_io {
+                anyhow::bail!("Direct I/O can't be used when patching in an isolated process");
+            }
+            if isolate && changed_blocks {
+                anyhow::bail!(
+                    "Changed blocks can't be written in place when patching in an isolated process"
+                );
+            }
+            if patch_file.is_zip_entry() && state_file.is_some() {
+                anyhow::bail!("Progress can't be recorded for patches read from zip entries");
+            }
+
+            let state = state_file
+                .map(|path| StateFile::new(&path, &old, &patch, &new))
+                .transpose()?;
+            let (mut new_file, resume) = match &state {
+                Some(state) => {
+                    let (new_file, resume) = state.open_new(&new, output)?;
+                    (new_file, Some(resume))
+                }
+                #[cfg(target_os = "linux")]
+                None if direct_io => (patch::create_direct(&new)?, None),
+                None if changed_blocks => {
+                    let new_file = File::options()
+                        .write(true)
+                        .open(&new)
+                        .with_context(|| format!("Failed to open new file '{}'", new.display()))?;
+                    (new_file, None)
+                }
+                None => {
+                    let new_file = File::create(&new).with_context(|| {
+                        format!("Failed to create new file '{}'", new.display())
+                    })?;
+                    (new_file, None)
                 }
-                None => Patcher::new(old_file, patch_file)?,
             };
-            io::copy(&mut patcher, &mut new_file).context("Failed to apply patch file")?;
-        }
-        Command::Info { patch } => {
-            let mut patch_file = File::open(&patch)
-                .with_context(|| format!("Failed to open patch file '{}'", patch.display()))?;
 
-            let patch_format_version = ina::read_header(&mut patch_file)
-                .with_context(|| format!("Failed to read patch header of '{}'", patch.display()))?
-                .version();
+            let metadata = if isolate || reflink {
+                if isolate {
+                    let patch_file = patch_file.into_file("--isolate")?;
+                    patch::patch_isolated(&old, patch_file, &mut new_file, &options, output)?;
+                } else {
+                    #[cfg(target_os = "linux")]
+                    patch::patch_reflink(
+                        &old,
+                        patch_file.into_file("--reflink")?,
+                        &new_file,
+                        &options,
+                        output,
+                    )?;
+                }
 
-            println!(
-                "Ina patch file, format version {}.{}",
-                patch_format_version.major(),
-                patch_format_version.minor(),
-            );
-        }
-    }
+                restore_metadata
+                    .then(|| {
+                        let mut patch_file = File::open(&patch).with_context(|| {
+                            format!("Failed to open patch file '{}'", patch.display())
+                        })?;
+                        ina::read_header(&mut patch_file).with_context(|| {
+                            format!("Failed to read patch header of '{}'", patch.display())
+                        })
+                    })
+                    .transpose()?
+            } else {
+                let old_file = File::open(&old)
+                    .with_context(|| format!("Failed to open old file '{}'", old.display()))?;
+                let mut patcher = options
+                    .patcher(old_file, patch_file)
+                    .with_context(|| format!("Failed to apply '{}'", patch.display()))?;
+                match (&state, resume) {
+                    (Some(state), Some(resume)) => {
+                        let interval = state_interval
+                            .or(config.patch.state_interval)
+                            .unwrap_or(DEFAULT_STATE_INTERVAL);
+                        state.write_new(&mut patcher, &mut new_file, resume, interval)?;
+                    }
+                    #[cfg(target_os = "linux")]
+                    _ if direct_io => {
+                        patcher = patch::write_direct(patcher, &new, &mut new_file)?;
+                    }
+                    _ if changed_blocks => {
+                        patcher = patch::write_changed_blocks(patcher, &mut new_file, output)?;
+                    }
+                    _ => {
+                        io::copy(&mut patcher, &mut new_file)
+                            .context("Failed to apply patch file")?;
+                    }
+                }
+                let metadata = patch::apply_concatenated(patcher, &new, &mut new_file, output)?;
 
-    Ok(())
+                restore_metadata.then_some(metadata)
+            };
+
+            if let Some(metadata) = metadata {
+                ina::restore_file_metadata(&metadata, &mut new_file).with_context(|| {
+                    format!("Failed to restore metadata of new file '{}'", new.display())
+                })?;
+                output.detail("Restored file metadata recorded in the patch");
+            }
+            if let Some(state) = state {
+                state.remove()?;
+            }
+            output.detail(format_args!("Wrote new file '{}'", new.display()));
+        }
+        Command::IsolatedPatch {
+            old,
+            decompression_buffer_size,
+            max_memory,
+            max_expansion,
+            max_new_len,
+            expect_platform,
+            expect_abi,
+            expect_version_code,
+        } => {
+            let options = PatchOptions {
+                decompression_buffer_size,
+                max_memory,
+                max_expansion,
+                max_new_len,
+                expected_target: target(expect_platform, expect_abi, expect_version_code),
+            };
+
+            patch::run_isolated(&old, &options)?;
+        }
+        Command::Transcode {
+            input,
+            output: output_path,
+            compression_threads,
+            compression_level,
+            window_log,
+            codec,
+            payload_checksum,
+        } => {
+            let input_file = File::open(&input)
+                .with_context(|| format!("Failed to open patch file '{}'", input.display()))?;
+            let output_file = File::create(&output_path).with_context(|| {
+                format!("Failed to create patch file '{}'", output_path.display())
+            })?;
+
+            let mut diff_config = DiffConfig::default();
+            if let Some(threads) = compression_threads.or(config.diff.compression_threads) {
+                diff_config.compression_threads(threads);
+            }
+            if let Some(level) = compression_level.or(config.diff.compression_level) {
+                diff_config.compression_level(level);
+            }
+            if let Some(window_log) = window_log.or(config.diff.window_log) {
+                diff_config.window_log(Some(window_log));
+            }
+            diff_config.payload_checksum(
+                payload_checksum || config.diff.payload_checksum.unwrap_or(false),
+            );
+            diff_config.codec(match codec {
+                Codec::Zstd => ina::Codec::Zstd,
+                Codec::Brotli => ina::Codec::Brotli,
+            });
+
+            let stats = ina::transcode(input_file, output_file, &diff_config)
+                .with_context(|| format!("Failed to transcode patch file '{}'", input.display()))?;
+            output.detail(format_args!(
+                "Wrote patch file '{}' ({} bytes)",
+                output_path.display(),
+                stats.patch_len(),
+            ));
+        }
+        Command::Split {
+            patch,
+            chunk_size,
+            output_dir,
+        } => {
+            if chunk_size <= ina::SEGMENT_HEADER_LEN {
+                Args::command()
+                    .error(
+                        ErrorKind::ValueValidation,
+                        format!(
+                            "--chunk-size must be more than {} bytes",
+                            ina::SEGMENT_HEADER_LEN,
+                        ),
+                    )
+                    .exit();
+            }
+
+            let patch_data = fs::read(&patch)
+                .with_context(|| format!("Failed to read patch file '{}'", patch.display()))?;
+            let segments = ina::split_patch(&patch_data, chunk_size)
+                .with_context(|| format!("Failed to split patch file '{}'", patch.display()))?;
+
+            let file_name = patch
+                .file_name()
+                .with_context(|| format!("Patch file '{}' has no file name", patch.display()))?;
+            let output_dir = output_dir
+                .or_else(|| patch.parent().map(Path::to_path_buf))
+                .unwrap_or_default();
+            // Pad the index so that the segments sort in order
+            let width = (segments.len() - 1).to_string().len().max(3);
+            for (index, segment) in segments.iter().enumerate() {
+                let mut segment_name = file_name.to_owned();
+                segment_name.push(format!(".{index:0width$}"));
+                let segment_path = output_dir.join(segment_name);
+                fs::write(&segment_path, segment).with_context(|| {
+                    format!("Failed to write segment '{}'", segment_path.display())
+                })?;
+                output.detail(format_args!(
+                    "Wrote segment '{}' ({} bytes)",
+                    segment_path.display(),
+                    segment.len(),
+                ));
+            }
+        }
+        Command::Join {
+            segments,
+            output: output_path,
+        } => {
+            let mut segment_files = Vec::with_capacity(segments.len());
+            for segment in &segments {
+                let file = File::open(segment)
+                    .with_context(|| format!("Failed to open segment '{}'", segment.display()))?;
+                segment_files.push(BufReader::new(file));
+            }
+            let concatenated = segment_files
+                .into_iter()
+                .fold(Box::new(io::empty()) as Box<dyn Read>, |segments, file| {
+                    Box::new(segments.chain(file))
+                });
+
+            // Join into memory first so that no output is written if a segment is invalid
+            let mut patch_data = Vec::new();
+            ina::join_segments(concatenated, &mut patch_data).context("Failed to join segments")?;
+            fs::write(&output_path, &patch_data).with_context(|| {
+                format!("Failed to write patch file '{}'", output_path.display())
+            })?;
+            output.detail(format_args!(
+                "Wrote patch file '{}' ({} bytes)",
+                output_path.display(),
+                patch_data.len(),
+            ));
+        }
+        Command::CheckRediff {
+            patches,
+            min_savings,
+            compression_threads,
+            compression_level,
+            window_log,
+            match_threshold,
+        } => {
+            let mut diff_config = DiffConfig::default();
+            if let Some(threads) = compression_threads.or(config.diff.compression_threads) {
+                diff_config.compression_threads(threads);
+            }
+            if let Some(level) = compression_level.or(config.diff.compression_level) {
+                diff_config.compression_level(level);
+            }
+            if let Some(window_log) = window_log.or(config.diff.window_log) {
+                diff_config.window_log(Some(window_log));
+            }
+            if let Some(threshold) = match_threshold.or(config.diff.match_threshold) {
+                diff_config.match_threshold(threshold);
+            }
+
+            let mut results = Vec::with_capacity(patches.len());
+            for patch in &patches {
+                let patch_data = fs::read(patch)
+                    .with_context(|| format!("Failed to read patch file '{}'", patch.display()))?;
+                let check = ina::check_rediff(&patch_data, &diff_config, min_savings / 100.0)
+                    .with_context(|| format!("Failed to check patch file '{}'", patch.display()))?;
+                if !output.json() {
+                    print_rediff_check(patch, &check);
+                }
+                results.push(rediff_check_json(patch, &check));
+            }
+
+            if output.json() {
+                let results = serde_json::to_string_pretty(&results)
+                    .context("Failed to serialize rediff checks")?;
+                println!("{results}");
+            }
+        }
+        Command::Info {
+            patch,
+            list,
+            machine,
+            apply_throughput,
+            annotation,
+        } => {
+            let mut patch_file = File::open(&patch)
+                .map(BufReader::new)
+                .with_context(|| format!("Failed to open patch file '{}'", patch.display()))?;
+            let patch_len = patch_file
+                .get_ref()
+                .metadata()
+                .with_context(|| format!("Failed to read metadata of '{}'", patch.display()))?
+                .len();
+            let (units, throughput) = report_units(machine, apply_throughput, &config);
+
+            let prefix = patch_file
+                .fill_buf()
+                .with_context(|| format!("Failed to read patch file '{}'", patch.display()))?;
+            if ina::is_bundle(prefix) {
+                if annotation {
+                    Args::command()
+                        .error(
+                            ErrorKind::ArgumentConflict,
+                            format!(
+                                "--annotation requires a patch, but '{}' is a bundle",
+                                patch.display()
+                            ),
+                        )
+                        .exit();
+                }
+                let listing = bundle::list_bundle(patch_file)
+                    .with_context(|| format!("Failed to read bundle '{}'", patch.display()))?;
+
+                if output.json() {
+                    let info =
+                        serde_json::to_string_pretty(&bundle_info_json(&listing, list, throughput))
+                            .context("Failed to serialize bundle metadata")?;
+                    println!("{info}");
+                } else {
+                    print_bundle_info(&listing, list, units, throughput);
+                }
+                return Ok(ExitCode::SUCCESS);
+            }
+            if list {
+                Args::command()
+                    .error(
+                        ErrorKind::ArgumentConflict,
+                        format!(
+                            "--list requires a bundle, but '{}' isn't one",
+                            patch.display()
+                        ),
+                    )
+                    .exit();
+            }
+            // Patches of unsupported versions are left to the header parser to report
+            if ina::format::sniff(prefix).is_none() && !prefix.starts_with(&ina::format::MAGIC) {
+                let message = format!(
+                    "'{}' isn't an ina patch or bundle{}",
+                    patch.display(),
+                    text_hint(prefix),
+                );
+                return Ok(ExitCode::from(
+                    output.failure(ErrorCategory::InvalidPatch, &message),
+                ));
+            }
+
+            let metadata = ina::read_header(&mut patch_file)
+                .with_context(|| format!("Failed to read patch header of '{}'", patch.display()))?;
+
+            if annotation {
+                let Some(annotation) = metadata.annotation() else {
+                    let message = format!("'{}' has no annotation", patch.display());
+                    return Ok(ExitCode::from(
+                        output.failure(ErrorCategory::Other, &message),
+                    ));
+                };
+                if output.json() {
+                    println!("{}", json!({ "annotation": annotation }));
+                } else if annotation.ends_with('\n') {
+                    print!("{annotation}");
+                } else {
+                    println!("{annotation}");
+                }
+            } else if output.json() {
+                let mut info = info_json(&metadata);
+                info["patch_len"] = patch_len.into();
+                if let Some(new_len) = metadata.new_len() {
+                    info["ratio"] = (patch_len as f64 / new_len as f64).into();
+                    info["estimated_apply_secs"] =
+                        units::apply_time(new_len, throughput).as_secs_f64().into();
+                }
+                let info = serde_json::to_string_pretty(&info)
+                    .context("Failed to serialize patch metadata")?;
+                println!("{info}");
+            } else {
+                print_info(&metadata, patch_len, units, throughput);
+            }
+        }
+        Command::DebugControls { patch, old } => {
+            let old_len = old
+                .map(|old| {
+                    fs::metadata(&old)
+                        .map(|metadata| metadata.len())
+                        .with_context(|| format!("Failed to read old file '{}'", old.display()))
+                })
+                .transpose()?;
+            let patch_file = File::open(&patch)
+                .with_context(|| format!("Failed to open patch file '{}'", patch.display()))?;
+
+            debug_controls(patch_file, old_len)
+                .with_context(|| format!("Invalid control stream in '{}'", patch.display()))?;
+        }
+        Command::Stats {
+            patch,
+            machine,
+            apply_throughput,
+            max_backward_seek,
+        } => {
+            let patch_file = File::open(&patch)
+                .with_context(|| format!("Failed to open patch file '{}'", patch.display()))?;
+            let patch_len = patch_file
+                .metadata()
+                .with_context(|| format!("Failed to read metadata of '{}'", patch.display()))?
+                .len();
+            let stats = patch_stats(BufReader::new(patch_file))
+                .with_context(|| format!("Invalid control stream in '{}'", patch.display()))?;
+            let (units, throughput) = report_units(machine, apply_throughput, &config);
+
+            if output.json() {
+                let stats =
+                    serde_json::to_string_pretty(&patch_stats_json(&stats, patch_len, throughput))
+                        .context("Failed to serialize patch statistics")?;
+                println!("{stats}");
+            } else if output.normal() {
+                print_patch_stats(&stats, patch_len, units, throughput);
+            }
+
+            if let Some(limit) = max_backward_seek
+                && stats.seeks.max_backward() > limit as u64
+            {
+                let message = format!(
+                    "Patch '{}' seeks backward by {} bytes, more than the limit of {limit} bytes",
+                    patch.display(),
+                    stats.seeks.max_backward(),
+                );
+                return Ok(ExitCode::from(
+                    output.failure(ErrorCategory::Rejected, &message),
+                ));
+            }
+        }
+        Command::Compare { old, new, machine } => {
+            let old_data = read_old(&old)?;
+            let new_data = fs::read(&new)
+                .with_context(|| format!("Failed to read new file '{}'", new.display()))?;
+            let comparison = ina::compare(&old_data, &new_data, &diff_config(&config.diff));
+
+            if output.json() {
+                let report = serde_json::to_string_pretty(&comparison_json(&comparison))
+                    .context("Failed to serialize comparison")?;
+                println!("{report}");
+            } else if output.normal() {
+                print_comparison(&comparison, Units::new(machine));
+            }
+        }
+        Command::Lint { patch } => {
+            let patch_file = File::open(&patch)
+                .with_context(|| format!("Failed to open patch file '{}'", patch.display()))?;
+            let report = ina::lint(BufReader::new(patch_file))
+                .with_context(|| format!("Failed to read patch file '{}'", patch.display()))?;
+
+            if output.json() {
+                let report = serde_json::to_string_pretty(&lint_json(&report))
+                    .context("Failed to serialize lint report")?;
+                println!("{report}");
+            } else if output.normal() || !report.is_conformant() {
+                print_lint(&report);
+            }
+
+            if !report.is_conformant() {
+                let message = format!(
+                    "Patch '{}' doesn't conform to the patch format: {} error(s)",
+                    patch.display(),
+                    report.errors(),
+                );
+                return Ok(ExitCode::from(
+                    output.failure(ErrorCategory::InvalidPatch, &message),
+                ));
+            }
+        }
+        Command::Tune {
+            corpus,
+            compression_levels,
+            window_logs,
+            match_thresholds,
+            compression_threads,
+            output: output_path,
+        } => {
+            let compression_threads = compression_threads.or(config.diff.compression_threads);
+
+            let mut pairs = Vec::new();
+            let mut entries = fs::read_dir(&corpus)
+                .with_context(|| format!("Failed to read corpus directory '{}'", corpus.display()))?
+                .collect::<Result<Vec<_>, _>>()
+                .with_context(|| {
+                    format!("Failed to read corpus directory '{}'", corpus.display())
+                })?;
+            entries.sort_by_key(|entry| entry.path());
+            for entry in entries {
+                let pair_dir = entry.path();
+                if !pair_dir.is_dir() {
+                    continue;
+                }
+
+                let old_data = read_old(&pair_dir.join("old"))?;
+                let new_path = pair_dir.join("new");
+                let new_data = fs::read(&new_path)
+                    .with_context(|| format!("Failed to read new file '{}'", new_path.display()))?;
+                pairs.push((old_data, new_data));
+            }
+            if pairs.is_empty() {
+                anyhow::bail!(
+                    "Corpus directory '{}' contains no file pairs",
+                    corpus.display()
+                );
+            }
+
+            let mut matrix = TuneMatrix::new();
+            if !compression_levels.is_empty() {
+                matrix.compression_levels(compression_levels);
+            }
+            if !window_logs.is_empty() {
+                matrix.window_logs(window_logs.into_iter().map(Some).collect::<Vec<_>>());
+            }
+            if !match_thresholds.is_empty() {
+                matrix.match_thresholds(match_thresholds);
+            }
+            if let Some(threads) = compression_threads {
+                matrix.compression_threads(threads);
+            }
+
+            let corpus_refs: Vec<_> = pairs
+                .iter()
+                .map(|(old, new)| (old, new.as_slice()))
+                .collect();
+            let results = ina::tune(&corpus_refs, &matrix)
+                .context("I/O error occurred while generating patch file")?;
+
+            let diff_settings = |result: &ina::TuneResult| {
+                json!({
+                    "compression_threads": compression_threads
+                        .unwrap_or(DiffConfig::DEFAULT_COMPRESSION_THREADS),
+                    "compression_level": result.compression_level(),
+                    "window_log": result.window_log(),
+                    "match_threshold": result.match_threshold(),
+                })
+            };
+            let pareto: Vec<_> = results
+                .iter()
+                .map(|result| {
+                    let mut settings = diff_settings(result);
+                    settings["patch_size"] = result.patch_size().into();
+                    settings["elapsed_ms"] = (result.elapsed().as_millis() as u64).into();
+                    settings
+                })
+                .collect();
+            let report = json!({
+                "diff": diff_settings(&results[0]),
+                "pareto": pareto,
+            });
+            let report =
+                serde_json::to_string_pretty(&report).context("Failed to serialize results")?;
+
+            match output_path {
+                Some(path) => fs::write(&path, report + "\n")
+                    .with_context(|| format!("Failed to write output file '{}'", path.display()))?,
+                None => println!("{report}"),
+            }
+        }
+        Command::Selftest { format } => {
+            let all = !format;
+
+            let mut failures = 0;
+            if format || all {
+                for (name, result) in ina::selftest::run_format() {
+                    match result {
+                        Ok(()) if output.normal() => println!("format/{name}: ok"),
+                        Ok(()) => {}
+                        Err(e) => {
+                            println!("format/{name}: FAILED: {e}");
+                            failures += 1;
+                        }
+                    }
+                }
+            }
+            if failures > 0 {
+                anyhow::bail!("{failures} self-test(s) failed");
+            }
+        }
+        Command::Doctor { dir } => {
+            doctor::run(dir.as_deref().unwrap_or(Path::new(".")), &config, output)?;
+        }
+        Command::Run { pipeline, jobs } => pipeline::run(&pipeline, jobs, &config, output)?,
+        #[cfg(feature = "dev")]
+        Command::ServePatches { dir, addr } => serve::run(&dir, &addr, output)?,
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Prints the codecs and features supported by this build of the CLI
+fn print_capabilities() {
+    let linkage = if cfg!(target_feature = "crt-static") {
+        "static"
+    } else {
+        "dynamic"
+    };
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    let sandbox = if !ina::sandbox::is_supported() {
+        "unsupported on this platform"
+    } else if cfg!(target_os = "linux") {
+        "landlock"
+    } else {
+        "seccomp"
+    };
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    let sandbox = "unsupported on this platform";
+
+    println!("ina {}", env!("CARGO_PKG_VERSION"));
+    println!(
+        "Codecs: zstd (libzstd {}), brotli",
+        zstd::zstd_safe::version_string(),
+    );
+    println!("Features: bundle, diff, lint, patch, selftest, stats, transcode, tune, verify");
+    println!("Digests: blake3, sha256");
+    println!("Linkage: {linkage}");
+    println!("Sandbox: {sandbox}");
+}
+
+/// Describes the beginning of a file which isn't a patch if it looks like text, e.g., an HTML
+/// error page saved instead of a downloaded patch, or returns an empty string otherwise
+fn text_hint(prefix: &[u8]) -> String {
+    let prefix = &prefix[..prefix.len().min(32)];
+    let is_text = !prefix.is_empty()
+        && prefix
+            .iter()
+            .all(|b| b.is_ascii_graphic() || b.is_ascii_whitespace());
+    if !is_text {
+        return String::new();
+    }
+
+    let line = String::from_utf8_lossy(prefix);
+    let line = line.lines().next().unwrap_or_default();
+    format!("; it starts with the text {line:?}, such as an HTML error page would")
+}
+
+/// Resolves how `info` and `stats` format their output and the throughput they estimate apply
+/// times with from their flags and the config file
+fn report_units(machine: bool, apply_throughput: Option<usize>, config: &Config) -> (Units, usize) {
+    let machine = machine || config.report.machine.unwrap_or(false);
+    let throughput = apply_throughput
+        .or(config.report.apply_throughput)
+        .unwrap_or(DEFAULT_APPLY_THROUGHPUT);
+
+    (Units::new(machine), throughput)
+}
+
+/// Prints the size of a patch relative to the new file it produces and an estimate of how long
+/// applying it takes
+fn print_patch_size(label: &str, patch_len: u64, new_len: u64, units: Units, throughput: usize) {
+    println!(
+        "{label}: {} ({} of the new size)",
+        units.size(patch_len),
+        units.percent(patch_len, new_len),
+    );
+    println!(
+        "Estimated apply time: {} at {}/s",
+        units.duration(units::apply_time(new_len, throughput)),
+        units.size(throughput as u64),
+    );
+}
+
+/// Prints the metadata of a patch in a human-readable format
+fn print_info(metadata: &PatchMetadata, patch_len: u64, units: Units, throughput: usize) {
+    let patch_format_version = metadata.version();
+
+    println!(
+        "Ina patch file, format version {}.{}",
+        patch_format_version.major(),
+        patch_format_version.minor(),
+    );
+    let capabilities = metadata.format_capabilities();
+    let features: Vec<_> = capabilities.features().map(|f| f.to_string()).collect();
+    if !features.is_empty() {
+        println!("Format features: {}", features.join(", "));
+    }
+    let unknown_tags: Vec<_> = capabilities.unknown_tags().map(|t| t.to_string()).collect();
+    if !unknown_tags.is_empty() {
+        println!("Unknown header fields: {}", unknown_tags.join(", "));
+    }
+    println!("Codec: {}", metadata.codec());
+    if let Some(requirements) = metadata.requirements() {
+        let features: Vec<_> = requirements.features().collect();
+        println!(
+            "Requires: format version {}.{}, {}",
+            patch_format_version.major(),
+            requirements.min_minor_version(),
+            features.join(", "),
+        );
+    }
+    if let Some(new_len) = metadata.new_len() {
+        println!("New length: {}", units.size(new_len));
+        print_patch_size("Patch size", patch_len, new_len, units, throughput);
+    } else {
+        println!("Patch size: {}", units.size(patch_len));
+    }
+    if let Some(file_metadata) = metadata.file_metadata() {
+        if let Some(mode) = file_metadata.mode() {
+            println!("File mode: {mode:o}");
+        }
+        if let Some(modified) = file_metadata.modified().and_then(secs_since_epoch) {
+            println!("File modified: {modified} seconds since epoch");
+        }
+    }
+    if let Some(target) = metadata.target() {
+        if let Some(platform) = target.platform() {
+            println!("Target platform: {platform}");
+        }
+        if let Some(abi) = target.abi() {
+            println!("Target ABI: {abi}");
+        }
+        if let Some(version_code) = target.version_code() {
+            println!("Target version code: {version_code}");
+        }
+    }
+    if metadata.has_payload_checksum() {
+        println!("Payload checksum: CRC-32");
+    }
+    if let Some(settings) = metadata.diff_settings() {
+        let window_log = settings
+            .window_log()
+            .map_or_else(|| "auto".into(), |log| log.to_string());
+        println!(
+            "Diff settings: compression level {}, window log {window_log}, match threshold {}",
+            settings.compression_level(),
+            settings.match_threshold(),
+        );
+    }
+    if let Some(digest) = metadata.base_digest() {
+        println!(
+            "Base check: {} regions covering {} of {}",
+            digest.regions().count(),
+            units.size(digest.covered_len()),
+            units.size(digest.old_len()),
+        );
+    }
+    if let Some(digests) = metadata.blob_digests() {
+        println!("Blob digests: {}", digests.algorithm());
+    }
+    if let Some(block_map) = metadata.block_map() {
+        println!(
+            "Block map: {} of {} blocks of {} changed",
+            block_map.changed_count(),
+            block_map.block_count(),
+            units.size(u64::from(block_map.block_size())),
+        );
+    }
+    if let Some(hints) = metadata.text_hints() {
+        println!("Changed lines:");
+        for change in hints.changes() {
+            println!(
+                "  @@ -{} +{} @@",
+                hunk_range(change.old_lines()),
+                hunk_range(change.new_lines()),
+            );
+        }
+        if hints.is_truncated() {
+            println!("  (further changes omitted)");
+        }
+    }
+    if let Some(provenance) = metadata.provenance() {
+        if let Some(tool) = provenance.tool() {
+            println!("Created by: {tool}");
+        }
+        if let Some(created) = provenance.created().and_then(secs_since_epoch) {
+            println!("Created: {created} seconds since epoch");
+        }
+        if let Some(old_id) = provenance.old_id() {
+            println!("Old file: {old_id}");
+        }
+        if let Some(new_id) = provenance.new_id() {
+            println!("New file: {new_id}");
+        }
+    }
+    if let Some(annotation) = metadata.annotation() {
+        println!(
+            "Annotation: {} (print it with --annotation)",
+            units.size(annotation.len() as u64),
+        );
+    }
+}
+
+/// Prints whether regenerating a patch is recommended and why
+fn print_rediff_check(patch: &Path, check: &RediffCheck) {
+    if !check.is_recommended() {
+        println!("{}: up to date", patch.display());
+        return;
+    }
+
+    let reasons: Vec<_> = check
+        .reasons()
+        .iter()
+        .map(|reason| match reason {
+            RediffReason::OutdatedFormat => format!(
+                "outdated format version {}.{}",
+                check.version().major(),
+                check.version().minor(),
+            ),
+            RediffReason::UnrecordedSettings => "settings not recorded".into(),
+            RediffReason::MatcherSettingsChanged => "matcher settings changed".into(),
+            RediffReason::Recompression => format!(
+                "recompression saves {} bytes ({:.2}%)",
+                check.savings(),
+                check.savings() as f64 / check.patch_len() as f64 * 100.0,
+            ),
+        })
+        .collect();
+    println!("{}: regenerate ({})", patch.display(), reasons.join("; "));
+}
+
+/// Returns the result of checking whether to regenerate a patch as a JSON object
+fn rediff_check_json(patch: &Path, check: &RediffCheck) -> serde_json::Value {
+    json!({
+        "patch": patch.display().to_string(),
+        "recommended": check.is_recommended(),
+        "reasons": check.reasons().iter().map(|reason| match reason {
+            RediffReason::OutdatedFormat => "outdated_format",
+            RediffReason::UnrecordedSettings => "unrecorded_settings",
+            RediffReason::MatcherSettingsChanged => "matcher_settings_changed",
+            RediffReason::Recompression => "recompression",
+        }).collect::<Vec<_>>(),
+        "patch_len": check.patch_len(),
+        "recompressed_len": check.recompressed_len(),
+    })
+}
+
+/// Formats a range of lines counted from 0 like the ranges of a unified diff hunk header
+///
+/// Lines are counted from 1. An empty range is given as the line before it followed by a count of
+/// 0.
+fn hunk_range(lines: Range<u64>) -> String {
+    match lines.end - lines.start {
+        0 => format!("{},0", lines.start),
+        1 => format!("{}", lines.start + 1),
+        len => format!("{},{len}", lines.start + 1),
+    }
+}
+
+/// Returns the metadata of a patch as a JSON object
+///
+/// Fields which aren't recorded in the patch are null.
+fn info_json(metadata: &PatchMetadata) -> serde_json::Value {
+    let file_metadata = metadata.file_metadata();
+    let target = metadata.target();
+    let provenance = metadata.provenance();
+
+    json!({
+        "version": {
+            "major": metadata.version().major(),
+            "minor": metadata.version().minor(),
+        },
+        "format_features": metadata
+            .format_capabilities()
+            .features()
+            .map(|feature| feature.to_string())
+            .collect::<Vec<_>>(),
+        "unknown_header_fields": metadata.format_capabilities().unknown_tags().collect::<Vec<_>>(),
+        "codec": metadata.codec().to_string(),
+        "requirements": metadata.requirements().map(|requirements| json!({
+            "min_minor_version": requirements.min_minor_version(),
+            "features": requirements.features().collect::<Vec<_>>(),
+        })),
+        "new_len": metadata.new_len(),
+        "file_metadata": file_metadata.map(|file_metadata| json!({
+            "mode": file_metadata.mode(),
+            "modified": file_metadata.modified().and_then(secs_since_epoch),
+        })),
+        "target": target.map(|target| json!({
+            "platform": target.platform(),
+            "abi": target.abi(),
+            "version_code": target.version_code(),
+        })),
+        "provenance": provenance.map(|provenance| json!({
+            "tool": provenance.tool(),
+            "created": provenance.created().and_then(secs_since_epoch),
+            "old_id": provenance.old_id(),
+            "new_id": provenance.new_id(),
+        })),
+        "payload_checksum": metadata.has_payload_checksum(),
+        "annotation": metadata.annotation(),
+        "diff_settings": metadata.diff_settings().map(|settings| json!({
+            "compression_level": settings.compression_level(),
+            "window_log": settings.window_log(),
+            "match_threshold": settings.match_threshold(),
+        })),
+        "base_check": metadata.base_digest().map(|digest| json!({
+            "old_len": digest.old_len(),
+            "regions": digest
+                .regions()
+                .map(|region| [region.start, region.end])
+                .collect::<Vec<_>>(),
+        })),
+        "blob_digests": metadata.blob_digests().map(|digests| json!({
+            "algorithm": digests.algorithm().to_string(),
+            "old_digest": hex(digests.old_digest()),
+            "new_digest": hex(digests.new_digest()),
+        })),
+        "block_map": metadata.block_map().map(|block_map| json!({
+            "block_size": block_map.block_size(),
+            "new_len": block_map.new_len(),
+            "block_count": block_map.block_count(),
+            "changed": block_map
+                .changed()
+                .map(|range| [range.start, range.end])
+                .collect::<Vec<_>>(),
+        })),
+        "text_hints": metadata.text_hints().map(|hints| json!({
+            "changes": hints.changes().iter().map(|change| json!({
+                "old_lines": [change.old_lines().start, change.old_lines().end],
+                "new_lines": [change.new_lines().start, change.new_lines().end],
+            })).collect::<Vec<_>>(),
+            "truncated": hints.is_truncated(),
+        })),
+    })
+}
+
+fn bundle_info_json(listing: &BundleListing, list: bool, throughput: usize) -> serde_json::Value {
+    let summary = &listing.summary;
+    let mut info = json!({
+        "digest_algorithm": listing.digest_algorithm.to_string(),
+        "added": summary.added,
+        "modified": summary.modified,
+        "removed": summary.removed,
+        "unchanged": summary.unchanged,
+        "symlinks": summary.symlinks,
+        "new_len": summary.new_len,
+        "patch_len": listing.patch_len,
+        "ratio": listing.patch_len as f64 / summary.new_len as f64,
+        "estimated_apply_secs": units::apply_time(summary.new_len, throughput).as_secs_f64(),
+    });
+    if list {
+        info["entries"] = listing
+            .entries
+            .iter()
+            .map(|(entry, metadata)| {
+                json!({
+                    "path": entry.path(),
+                    "kind": entry.kind().to_string(),
+                    "symlink_target": entry.symlink_target(),
+                    "new_len": entry.new_len(),
+                    "new_digest": entry.new_digest().map(|digest| hex(digest)),
+                    "patch_len": entry.patch_len(),
+                    "patch": metadata.as_ref().map(info_json),
+                })
+            })
+            .collect();
+    }
+
+    json!({ "bundle": info })
+}
+
+/// Prints a summary of a bundle, and its entries if `list` is set, in a human-readable format
+fn print_bundle_info(listing: &BundleListing, list: bool, units: Units, throughput: usize) {
+    let summary = &listing.summary;
+
+    println!("Ina bundle, digests {}", listing.digest_algorithm);
+    println!(
+        "Entries: {} added, {} modified, {} removed, {} unchanged, {} symlinks",
+        summary.added, summary.modified, summary.removed, summary.unchanged, summary.symlinks,
+    );
+    println!("New files: {}", units.size(summary.new_len));
+    print_patch_size(
+        "Patches",
+        listing.patch_len,
+        summary.new_len,
+        units,
+        throughput,
+    );
+
+    if list {
+        println!();
+        for (entry, metadata) in &listing.entries {
+            let digest = entry
+                .new_digest()
+                .map_or_else(|| "-".into(), |digest| hex(digest));
+            let patch = metadata.as_ref().map_or_else(
+                || "-".into(),
+                |metadata| {
+                    format!(
+                        "{}, format {}.{}",
+                        units.size(entry.patch_len()),
+                        metadata.version().major(),
+                        metadata.version().minor(),
+                    )
+                },
+            );
+            let target = entry
+                .symlink_target()
+                .map_or_else(String::new, |target| format!(" -> {target}"));
+            println!(
+                "{:<9}  {:>12}  {digest}  {patch}  {}{target}",
+                entry.kind().to_string(),
+                units.size(entry.new_len()),
+                entry.path(),
+            );
+        }
+    }
+}
+
+/// Formats `bytes` as lowercase hexadecimal
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// Returns the number of whole seconds from the epoch to `time`, or `None` if it's before the
+/// epoch
+fn secs_since_epoch(time: SystemTime) -> Option<u64> {
+    time.duration_since(UNIX_EPOCH)
+        .ok()
+        .map(|since| since.as_secs())
+}
+
+/// Returns the time to record as the creation time of a patch
+///
+/// This is the time given by the SOURCE_DATE_EPOCH environment variable if it's set, following the
+/// reproducible builds convention, and the current time otherwise.
+fn creation_time() -> anyhow::Result<SystemTime> {
+    match env::var("SOURCE_DATE_EPOCH") {
+        Ok(epoch) => {
+            let secs = epoch
+                .parse()
+                .with_context(|| format!("Invalid SOURCE_DATE_EPOCH '{epoch}'"))?;
+            Ok(UNIX_EPOCH + Duration::from_secs(secs))
+        }
+        Err(_) => Ok(SystemTime::now()),
+    }
+}
+
+/// Returns a target made up of the given parts, or `None` if no parts are given
+fn target(
+    platform: Option<String>,
+    abi: Option<String>,
+    version_code: Option<u64>,
+) -> Option<Target> {
+    (platform.is_some() || abi.is_some() || version_code.is_some())
+        .then(|| Target::new(platform, abi, version_code))
+}
+
+/// Prints the findings of a lint report followed by a summary
+fn print_lint(report: &ina::LintReport) {
+    for finding in report.findings() {
+        match finding.record() {
+            Some(record) => println!(
+                "{}[{}]: record {record}: {}",
+                finding.severity(),
+                finding.code(),
+                finding.message(),
+            ),
+            None => println!(
+                "{}[{}]: {}",
+                finding.severity(),
+                finding.code(),
+                finding.message(),
+            ),
+        }
+    }
+    if report.is_truncated() {
+        println!("Further findings were omitted");
+    }
+
+    println!(
+        "{} error(s), {} warning(s); {} records, {} bytes of output",
+        report.errors(),
+        report.warnings(),
+        report.records(),
+        report.new_len(),
+    );
+}
+
+/// Returns a lint report as JSON
+fn lint_json(report: &ina::LintReport) -> serde_json::Value {
+    json!({
+        "conformant": report.is_conformant(),
+        "version": report.version().map(|version| json!({
+            "major": version.major(),
+            "minor": version.minor(),
+        })),
+        "errors": report.errors(),
+        "warnings": report.warnings(),
+        "records": report.records(),
+        "new_len": report.new_len(),
+        "data_len": report.data_len(),
+        "truncated": report.is_truncated(),
+        "findings": report.findings().iter().map(|finding| json!({
+            "severity": finding.severity().to_string(),
+            "code": finding.code(),
+            "message": finding.message(),
+            "record": finding.record(),
+        })).collect::<Vec<_>>(),
+    })
+}
+
+/// Prints every control record of `patch`, returning an error if any anomalies are found
+fn debug_controls(patch: File, old_len: Option<u64>) -> anyhow::Result<()> {
+    let controls = ControlReader::new(patch)?;
+
+    let mut old_pos: i128 = 0;
+    let mut new_pos: u64 = 0;
+    let mut records = 0;
+    let mut anomalies = 0;
+
+    println!(
+        "{:>8} {:>12} {:>12} {:>12} {:>12} {:>12}",
+        "record", "old offset", "new offset", "add len", "copy len", "seek",
+    );
+    for (i, record) in controls.enumerate() {
+        let record = record.with_context(|| format!("Failed to decode control record {i}"))?;
+        let add_len = record.add().len() as u64;
+        let copy_len = record.copy().len() as u64;
+
+        print!(
+            "{i:>8} {old_pos:>12} {new_pos:>12} {add_len:>12} {copy_len:>12} {:>12}",
+            record.seek(),
+        );
+
+        let mut flags = Vec::new();
+        if add_len == 0 && copy_len == 0 {
+            flags.push("empty record");
+        }
+        if old_len.is_some_and(|old_len| old_pos + i128::from(add_len) > i128::from(old_len)) {
+            flags.push("add reads past end of old file");
+        }
+        old_pos += i128::from(add_len) + i128::from(record.seek());
+        if old_pos < 0 {
+            flags.push("seek before start of old file");
+        } else if old_len.is_some_and(|old_len| old_pos > i128::from(old_len)) {
+            flags.push("seek past end of old file");
+        }
+        new_pos += add_len + copy_len;
+
+        if flags.is_empty() {
+            println!();
+        } else {
+            println!("  <- {}", flags.join(", "));
+        }
+        records += 1;
+        anomalies += flags.len();
+    }
+
+    println!("{records} records, {new_pos} bytes of output, {anomalies} anomalies");
+    if anomalies > 0 {
+        anyhow::bail!("Found {anomalies} anomalies");
+    }
+
+    Ok(())
+}
+
+/// Statistics about an existing patch's control records
+struct PatchStats {
+    records: u64,
+    add_bytes: u64,
+    copy_bytes: u64,
+    old_coverage: OldCoverage,
+    seeks: SeekStats,
+}
+
+/// Computes statistics about the control records of `patch` without applying it
+fn patch_stats<R: Read>(patch: R) -> anyhow::Result<PatchStats> {
+    let mut stats = PatchStats {
+        records: 0,
+        add_bytes: 0,
+        copy_bytes: 0,
+        old_coverage: OldCoverage::new(),
+        seeks: SeekStats::new(),
+    };
+
+    let mut old_pos: u64 = 0;
+    for (i, record) in ControlReader::new(patch)?.enumerate() {
+        let record = record.with_context(|| format!("Failed to decode control record {i}"))?;
+        let add_len = record.add().len() as u64;
+
+        stats.old_coverage.insert(old_pos..old_pos + add_len);
+        stats.seeks.record_read(old_pos..old_pos + add_len);
+        old_pos = (old_pos + add_len)
+            .checked_add_signed(record.seek())
+            .with_context(|| format!("Control record {i} seeks before start of old file"))?;
+
+        stats.records += 1;
+        stats.add_bytes += add_len;
+        stats.copy_bytes += record.copy().len() as u64;
+    }
+
+    Ok(stats)
+}
+
+fn print_patch_stats(stats: &PatchStats, patch_len: u64, units: Units, throughput: usize) {
+    let new_len = stats.add_bytes + stats.copy_bytes;
+
+    print_patch_size("Patch size", patch_len, new_len, units, throughput);
+    println!("Control records: {}", stats.records);
+    println!(
+        "New file: {} ({} added, {} copied)",
+        units.size(new_len),
+        units.size(stats.add_bytes),
+        units.size(stats.copy_bytes),
+    );
+    println!(
+        "Old file: {} referenced",
+        units.size(stats.old_coverage.covered_len()),
+    );
+    print_seek_stats(&stats.seeks, units);
+}
+
+fn patch_stats_json(stats: &PatchStats, patch_len: u64, throughput: usize) -> serde_json::Value {
+    let new_len = stats.add_bytes + stats.copy_bytes;
+
+    json!({
+        "patch_len": patch_len,
+        "ratio": patch_len as f64 / new_len as f64,
+        "estimated_apply_secs": units::apply_time(new_len, throughput).as_secs_f64(),
+        "control_records": stats.records,
+        "new_len": new_len,
+        "add_bytes": stats.add_bytes,
+        "copy_bytes": stats.copy_bytes,
+        "old_referenced": stats.old_coverage.covered_len(),
+        "seeks": seek_stats_json(&stats.seeks),
+    })
+}
+
+fn print_comparison(comparison: &Comparison, units: Units) {
+    let old_unmatched = comparison.old_unmatched();
+    let new_unmatched = comparison.new_unmatched();
+    let old_matched_len = comparison.old_matched().covered_len();
+
+    println!(
+        "Old file: {} ({} matched)",
+        units.size(comparison.old_len()),
+        units.percent(old_matched_len, comparison.old_len()),
+    );
+    println!(
+        "New file: {} ({} matched)",
+        units.size(comparison.new_len()),
+        units.percent(comparison.matched_len(), comparison.new_len()),
+    );
+    println!(
+        "Similarity: {}",
+        units.percent(
+            comparison.matches().iter().map(|m| m.equal_len()).sum(),
+            comparison.new_len(),
+        ),
+    );
+    println!("Matches: {}", comparison.matches().len());
+    for m in comparison.matches() {
+        let (old_range, new_range) = (m.old_range(), m.new_range());
+        println!(
+            "  new {}..{} = old {}..{} ({} similar)",
+            new_range.start,
+            new_range.end,
+            old_range.start,
+            old_range.end,
+            units.percent(m.equal_len(), new_range.end - new_range.start),
+        );
+    }
+    for (file, unmatched) in [("new", new_unmatched), ("old", old_unmatched)] {
+        println!("Unmatched in {file} file: {}", unmatched.len());
+        for range in unmatched {
+            println!(
+                "  {}..{} ({})",
+                range.start,
+                range.end,
+                units.size(range.end - range.start),
+            );
+        }
+    }
+}
+
+fn comparison_json(comparison: &Comparison) -> serde_json::Value {
+    let ranges_json = |ranges: &[Range<u64>]| -> Vec<serde_json::Value> {
+        ranges
+            .iter()
+            .map(|range| json!({ "offset": range.start, "len": range.end - range.start }))
+            .collect()
+    };
+    let matches: Vec<_> = comparison
+        .matches()
+        .iter()
+        .map(|m| {
+            json!({
+                "old_offset": m.old_range().start,
+                "new_offset": m.new_range().start,
+                "len": m.new_range().end - m.new_range().start,
+                "similarity_percent": m.similarity() * 100.0,
+            })
+        })
+        .collect();
+
+    json!({
+        "old_len": comparison.old_len(),
+        "new_len": comparison.new_len(),
+        "old_matched_len": comparison.old_matched().covered_len(),
+        "new_matched_len": comparison.matched_len(),
+        "similarity_percent": comparison.similarity() * 100.0,
+        "matches": matches,
+        "old_unmatched": ranges_json(&comparison.old_unmatched()),
+        "new_unmatched": ranges_json(&comparison.new_unmatched()),
+    })
+}
+
+/// Prints how a patch seeks in the old file, including the distribution of seek distances
+///
+/// The seek distance histograms are always given in exact bytes so that their buckets line up.
+fn print_seek_stats(seeks: &SeekStats, units: Units) {
+    println!(
+        "Old file seeks: {} of {} reads, {} in total",
+        seeks.seeks(),
+        seeks.reads(),
+        units.size(seeks.total_distance()),
+    );
+    println!(
+        "Longest seeks: {} backward, {} forward",
+        units.size(seeks.max_backward()),
+        units.size(seeks.max_forward()),
+    );
+    for (direction, histogram) in [("Backward", seeks.backward()), ("Forward", seeks.forward())] {
+        if histogram.count() == 0 {
+            continue;
+        }
+        println!("{direction} seek distances:");
+        for (range, count) in histogram.buckets() {
+            println!("  {}..={} bytes: {count}", range.start(), range.end());
+        }
+    }
+}
+
+fn seek_stats_json(seeks: &SeekStats) -> serde_json::Value {
+    let histogram = |histogram: &SeekHistogram| {
+        histogram
+            .buckets()
+            .map(|(range, count)| {
+                json!({
+                    "min": range.start(),
+                    "max": range.end(),
+                    "count": count,
+                })
+            })
+            .collect::<Vec<_>>()
+    };
+
+    json!({
+        "reads": seeks.reads(),
+        "seeks": seeks.seeks(),
+        "total_distance": seeks.total_distance(),
+        "max_backward": seeks.max_backward(),
+        "max_forward": seeks.max_forward(),
+        "backward": histogram(seeks.backward()),
+        "forward": histogram(seeks.forward()),
+    })
+}
+
+/// Prints how many files of each kind a bundle contains
+fn print_bundle_summary(summary: &bundle::BundleSummary) {
+    println!("New files: {} bytes", summary.new_len);
+    println!(
+        "Files: {} added, {} modified, {} removed, {} unchanged, {} symlinks",
+        summary.added, summary.modified, summary.removed, summary.unchanged, summary.symlinks,
+    );
+}
+
+/// Prints human-readable patch statistics
+fn print_stats(stats: &DiffStats) {
+    let coverage = stats.old_coverage();
+
+    println!("Patch size: {} bytes", stats.patch_len());
+    println!("Control records: {}", stats.control_records());
+    if let Some(fast_path) = stats.fast_path() {
+        println!("Fast path: {fast_path}");
+    }
+    println!(
+        "New file: {} bytes ({} added, {} copied)",
+        stats.new_len(),
+        stats.add_bytes(),
+        stats.copy_bytes(),
+    );
+    println!(
+        "Old file: {} bytes, {} referenced ({:.2}%)",
+        stats.old_len(),
+        coverage.covered_len(),
+        coverage.fraction_of(stats.old_len()) * 100.0,
+    );
+    println!(
+        "Time: {:.3}s indexing, {:.3}s matching, {:.3}s compressing",
+        stats.suffix_array_time().as_secs_f64(),
+        stats.match_time().as_secs_f64(),
+        stats.compression_time().as_secs_f64(),
+    );
+    if let Some(peak_memory) = stats.peak_memory() {
+        println!("Peak memory: {peak_memory} bytes");
+    }
+    print_seek_stats(stats.seeks(), Units::new(true));
+    println!("Referenced old file ranges:");
+    for range in coverage.ranges() {
+        println!("  {:#x}..{:#x}", range.start, range.end);
+    }
+}
+
+/// Resolves the positional paths of `diff`, which omit the old or new file if it's read from a zip
+/// entry, into its inputs and the path of the output patch
+///
+/// Fails if the wrong number of paths is given.
+fn diff_inputs(
+    old: Option<PathBuf>,
+    new: Option<PathBuf>,
+    patch: Option<PathBuf>,
+    old_zip: Option<ZipEntry>,
+    new_zip: Option<ZipEntry>,
+) -> anyhow::Result<(Input, Input, PathBuf)> {
+    // Clap fills positional arguments in order, so the given paths are always a prefix
+    let paths: Vec<_> = [old, new, patch].into_iter().flatten().collect();
+    let expected = 3 - usize::from(old_zip.is_some()) - usize::from(new_zip.is_some());
+    if paths.len() != expected {
+        anyhow::bail!(
+            "Expected {expected} paths, got {}; the paths of files read from zip entries must be \
+             omitted",
+            paths.len(),
+        );
+    }
+
+    let mut paths = paths.into_iter();
+    let old = old_zip.map_or_else(|| Input::Path(paths.next().unwrap()), Input::ZipEntry);
+    let new = new_zip.map_or_else(|| Input::Path(paths.next().unwrap()), Input::ZipEntry);
+
+    Ok((old, new, paths.next().unwrap()))
+}
+
+/// Creates the config for diffing from the settings of the `diff` subcommand, except for the
+/// target and provenance
+fn diff_config(settings: &DiffSettings) -> DiffConfig {
+    let mut diff_config = DiffConfig::default();
+    // The profile is applied first so that individual settings override it
+    if let Some(profile) = settings.profile {
+        diff_config.profile(profile.into());
+    }
+    if let Some(threads) = settings.compression_threads {
+        diff_config.compression_threads(threads);
+    }
+    if let Some(level) = settings.compression_level {
+        diff_config.compression_level(level);
+    }
+    if let Some(window_log) = settings.window_log {
+        diff_config.window_log(Some(window_log));
+    }
+    if let Some(threshold) = settings.match_threshold {
+        diff_config.match_threshold(threshold);
+    }
+    diff_config.max_backward_seek(settings.max_backward_seek.map(|max| max as u64));
+    diff_config
+        .record_settings(settings.record_settings.unwrap_or(false))
+        .record_new_len(settings.record_new_len.unwrap_or(false));
+    diff_config.payload_checksum(settings.payload_checksum.unwrap_or(false));
+    if let Some(base_check) = settings.base_check {
+        diff_config.base_check(base_check.into());
+    }
+    if settings.embed_digests.unwrap_or(false) {
+        diff_config.embed_digests(Some(settings.digest.unwrap_or(DigestKind::Blake3).into()));
+    }
+    if let Some(text) = settings.text {
+        diff_config.text_mode(text.into());
+    }
+
+    diff_config
+}
+
+/// Returns the provenance to record in a patch from `old` to `new` according to the settings of
+/// the `diff` subcommand, if any
+///
+/// Explicit version identifiers take precedence over the paths recorded with `provenance_paths`.
+fn provenance_of(
+    settings: &DiffSettings,
+    old: &impl Display,
+    new: &impl Display,
+    old_id: Option<String>,
+    new_id: Option<String>,
+) -> anyhow::Result<Option<Provenance>> {
+    let provenance = settings.provenance.unwrap_or(false);
+    let provenance_paths = settings.provenance_paths.unwrap_or(false);
+    let old_id = old_id.or_else(|| provenance_paths.then(|| old.to_string()));
+    let new_id = new_id.or_else(|| provenance_paths.then(|| new.to_string()));
+    if !provenance && old_id.is_none() && new_id.is_none() {
+        return Ok(None);
+    }
+
+    Ok(Some(Provenance::new(
+        provenance.then(|| format!("ina {}", env!("CARGO_PKG_VERSION"))),
+        provenance.then(creation_time).transpose()?,
+        old_id,
+        new_id,
+    )))
+}
+
+/// Parses a size in bytes with an optional K, M, or G suffix for KiB, MiB, or GiB
+fn parse_size(s: &str) -> Result<usize, String> {
+    let (digits, shift) = [('K', 10), ('M', 20), ('G', 30)]
+        .into_iter()
+        .find_map(|(suffix, shift)| {
+            s.strip_suffix([suffix, suffix.to_ascii_lowercase()])
+                .map(|digits| (digits, shift))
+        })
+        .unwrap_or((s, 0));
+    let size = digits
+        .parse::<usize>()
+        .map_err(|e| format!("invalid size: {e}"))?;
+
+    size.checked_mul(1 << shift)
+        .ok_or_else(|| "size is too large".into())
+}
+
+/// Reads an old file into memory, appending the sentinel required for diffing
+fn read_old(path: &Path) -> anyhow::Result<OldBlob> {
+    let mut old_file = File::open(path)
+        .with_context(|| format!("Failed to open old file '{}'", path.display()))?;
+    let len: usize = old_file
+        .metadata()
+        .with_context(|| format!("Failed to read metadata of old file '{}'", path.display()))?
+        .len()
+        .try_into()
+        .with_context(|| {
+            format!(
+                "Old file '{}' is too large to read into memory",
+                path.display(),
+            )
+        })?;
+    // Reserve a byte of extra space for the sentinel
+    let mut old_data = Vec::with_capacity(len + 1);
+    old_file
+        .read_to_end(&mut old_data)
+        .context("Failure occurred while reading old file")?;
+
+    Ok(OldBlob::from_vec(old_data))
 }
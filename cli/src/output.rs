@@ -0,0 +1,260 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use std::{
+    error::Error,
+    ffi::OsString,
+    fmt::{self, Display, Formatter},
+    io,
+};
+
+use ina::PatchError;
+use serde_json::json;
+
+/// The exit statuses of the CLI, as shown in the long help
+pub const EXIT_STATUS_HELP: &str = "\
+Exit status:
+  0  Success
+  1  Any other error
+  2  Invalid command-line usage
+  3  The patch exceeded the ratio given by `diff --max-ratio`
+  4  An I/O error occurred, such as a missing file or a full disk
+  5  The patch file is malformed or corrupt
+  6  The patch was rejected by `--expect-*` or a `--max-*` limit
+  7  The patch output differs from the file given by `patch --expect` or
+     from a hash recorded in a bundle, or the old file differs from the
+     one recorded by `diff --base-check`";
+
+/// The category of an error, which determines the exit status it's reported with
+///
+/// The exit statuses are stable so that scripts can react to failures without parsing messages.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ErrorCategory {
+    Other,
+    RatioExceeded,
+    Io,
+    InvalidPatch,
+    Rejected,
+    Mismatch,
+}
+
+impl ErrorCategory {
+    /// Determines the category of `error` from the errors in its chain
+    pub fn of(error: &anyhow::Error) -> Self {
+        for cause in error.chain() {
+            if let Some(e) = cause.downcast_ref::<CategorizedError>() {
+                return e.category;
+            }
+            if let Some(e) = cause.downcast_ref::<PatchError>() {
+                return Self::of_patch(e);
+            }
+            if let Some(e) = cause.downcast_ref::<io::Error>() {
+                return Self::of_io(e);
+            }
+        }
+
+        Self::Other
+    }
+
+    /// Determines the category of a patch error
+    fn of_patch(error: &PatchError) -> Self {
+        match error {
+            PatchError::Io(e) => Self::of_io(e),
+            PatchError::Header(e) => Self::of_io(e.io_error()),
+            PatchError::Sink(e) => Self::of_io(e.io_error()),
+            PatchError::Stalled(_) => Self::Io,
+            PatchError::BadMagic(_)
+            | PatchError::UnsupportedVersion(_)
+            | PatchError::InvalidHeaderField(_)
+            | PatchError::Truncated(_)
+            | PatchError::TrailingData
+            | PatchError::SeekBeforeStart(_)
+            | PatchError::SeekPastEnd(_)
+            | PatchError::UnsupportedFeature(_) => Self::InvalidPatch,
+            PatchError::MemoryLimitExceeded(_)
+            | PatchError::ExpansionLimitExceeded(_)
+            | PatchError::NewLenLimitExceeded(_)
+            | PatchError::TargetMismatch(_)
+            | PatchError::SeekViolation(_) => Self::Rejected,
+            PatchError::OldMismatch(_) | PatchError::NewMismatch => Self::Mismatch,
+        }
+    }
+
+    /// Determines the category of an I/O error
+    ///
+    /// Malformed patches surface as I/O errors while they're being applied, as do patches which
+    /// exceed a limit, which wrap a patch error. Errors from the operating system always have a
+    /// more specific kind than `Other`, which is only used by the decompressor.
+    fn of_io(error: &io::Error) -> Self {
+        if let Some(e) = error.get_ref().and_then(|e| e.downcast_ref::<PatchError>()) {
+            return Self::of_patch(e);
+        }
+
+        match error.kind() {
+            io::ErrorKind::InvalidData | io::ErrorKind::UnexpectedEof | io::ErrorKind::Other => {
+                Self::InvalidPatch
+            }
+            _ => Self::Io,
+        }
+    }
+
+    /// Returns the category with the given exit status, if any
+    pub fn from_exit_code(code: i32) -> Option<Self> {
+        [
+            Self::Other,
+            Self::RatioExceeded,
+            Self::Io,
+            Self::InvalidPatch,
+            Self::Rejected,
+            Self::Mismatch,
+        ]
+        .into_iter()
+        .find(|category| i32::from(category.exit_code()) == code)
+    }
+
+    /// Returns the exit status errors of this category are reported with
+    pub fn exit_code(self) -> u8 {
+        match self {
+            Self::Other => 1,
+            Self::RatioExceeded => 3,
+            Self::Io => 4,
+            Self::InvalidPatch => 5,
+            Self::Rejected => 6,
+            Self::Mismatch => 7,
+        }
+    }
+
+    /// Returns the name of this category as reported in JSON errors
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Other => "other",
+            Self::RatioExceeded => "ratio_exceeded",
+            Self::Io => "io",
+            Self::InvalidPatch => "invalid_patch",
+            Self::Rejected => "rejected",
+            Self::Mismatch => "mismatch",
+        }
+    }
+}
+
+/// An error whose category is known where it's created
+#[derive(Debug)]
+pub struct CategorizedError {
+    category: ErrorCategory,
+    message: String,
+}
+
+impl CategorizedError {
+    pub fn new(category: ErrorCategory, message: String) -> Self {
+        Self { category, message }
+    }
+}
+
+impl Display for CategorizedError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl Error for CategorizedError {}
+
+/// How much human-oriented output to print
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub enum Verbosity {
+    Quiet,
+    Normal,
+    Verbose,
+}
+
+/// The destination of human-oriented output and errors, resolved from the global flags
+pub struct Output {
+    verbosity: Verbosity,
+    json: bool,
+}
+
+impl Output {
+    pub fn new(verbosity: Verbosity, json: bool) -> Self {
+        Self { verbosity, json }
+    }
+
+    /// Returns whether machine-readable JSON output was requested
+    pub fn json(&self) -> bool {
+        self.json
+    }
+
+    /// Returns whether human-oriented output at the normal verbosity should be printed
+    pub fn normal(&self) -> bool {
+        self.verbosity >= Verbosity::Normal
+    }
+
+    /// Prints a message to standard error at the verbose verbosity
+    pub fn detail(&self, message: impl Display) {
+        if self.verbosity >= Verbosity::Verbose {
+            eprintln!("{message}");
+        }
+    }
+
+    /// Reports `error` on standard error, returning the exit status to report it with
+    ///
+    /// In JSON mode, the error is printed as a single-line JSON object. Otherwise, the verbose
+    /// verbosity prints each cause on its own line along with a backtrace if one was captured.
+    pub fn error(&self, error: &anyhow::Error) -> u8 {
+        let category = ErrorCategory::of(error);
+        self.report(category, &error.to_string(), error.chain().skip(1));
+
+        if !self.json {
+            if self.verbosity >= Verbosity::Verbose {
+                eprintln!("Error: {error:?}");
+            } else {
+                eprintln!("Error: {error:#}");
+            }
+        }
+
+        category.exit_code()
+    }
+
+    /// Reports a failure which isn't represented by an error, returning its exit status
+    pub fn failure(&self, category: ErrorCategory, message: &str) -> u8 {
+        self.report(category, message, [].into_iter());
+        if !self.json && self.normal() {
+            eprintln!("{message}");
+        }
+
+        category.exit_code()
+    }
+
+    /// Prints the JSON object for a failure if JSON mode is on
+    fn report<'a>(
+        &self,
+        category: ErrorCategory,
+        message: &str,
+        causes: impl Iterator<Item = &'a (dyn Error + 'static)>,
+    ) {
+        if self.json {
+            let causes: Vec<_> = causes.map(|cause| cause.to_string()).collect();
+            let error = json!({
+                "category": category.name(),
+                "exit_code": category.exit_code(),
+                "message": message,
+                "causes": causes,
+            });
+            eprintln!("{error}");
+        }
+    }
+
+    /// Returns the global flags which reproduce this output mode in a child process
+    pub fn to_args(&self) -> Vec<OsString> {
+        let mut args = Vec::new();
+        match self.verbosity {
+            Verbosity::Quiet => args.push("--quiet".into()),
+            Verbosity::Normal => {}
+            Verbosity::Verbose => args.push("--verbose".into()),
+        }
+        if self.json {
+            args.push("--json".into());
+        }
+
+        args
+    }
+}
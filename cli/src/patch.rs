@@ -0,0 +1,437 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use std::{
+    env,
+    ffi::OsString,
+    fs::{self, File},
+    io::{self, BufReader, Cursor, Read, Seek, SeekFrom, Write},
+    mem,
+    path::Path,
+    process::{Command, Stdio},
+};
+
+use anyhow::Context;
+use ina::{PatchLimits, PatchMetadata, Patcher, Target};
+
+use crate::output::{CategorizedError, ErrorCategory, Output};
+
+/// The name of the hidden subcommand run by the child process of `patch --isolate`
+pub const ISOLATED_PATCH_COMMAND: &str = "isolated-patch";
+
+/// The maximum length of a frame of new file data sent by an isolated patch process
+///
+/// The parent doesn't trust the child, so this bounds the memory it allocates for a single frame.
+const MAX_FRAME_LEN: usize = 64 * 1024;
+
+/// The size of the blocks written by `patch --direct-io`
+#[cfg(target_os = "linux")]
+const DIRECT_IO_BLOCK_SIZE: usize = 1 << 20;
+
+/// The alignment of the buffers and write lengths of `patch --direct-io`, which is at least the
+/// logical block size of common storage devices
+#[cfg(target_os = "linux")]
+const DIRECT_IO_ALIGNMENT: usize = 4096;
+
+/// Options for creating a `Patcher`, resolved from the command line and config file
+#[derive(Default)]
+pub struct PatchOptions {
+    pub decompression_buffer_size: Option<usize>,
+    pub max_memory: Option<u64>,
+    pub max_expansion: Option<u32>,
+    pub max_new_len: Option<u64>,
+    pub expected_target: Option<Target>,
+}
+
+impl PatchOptions {
+    /// Creates a `Patcher` for `old` and `patch` according to these options
+    pub fn patcher<O, P>(
+        &self,
+        old: O,
+        patch: P,
+    ) -> anyhow::Result<Patcher<'static, O, BufReader<P>>>
+    where
+        O: Read + Seek,
+        P: Read,
+    {
+        let patcher = match (self.limits(), self.decompression_buffer_size) {
+            (Some(limits), _) => Patcher::with_limits(old, patch, &limits)?,
+            (None, Some(size)) => Patcher::with_buffer(old, BufReader::with_capacity(size, patch))?,
+            (None, None) => Patcher::new(old, patch)?,
+        };
+
+        match &self.expected_target {
+            Some(expected) => Ok(patcher
+                .expect_target(expected)
+                .context("Patch file can't be applied here")?),
+            None => Ok(patcher),
+        }
+    }
+
+    /// Returns the limits to apply the patch within, if any are set
+    fn limits(&self) -> Option<PatchLimits> {
+        if self.max_memory.is_none() && self.max_expansion.is_none() && self.max_new_len.is_none() {
+            return None;
+        }
+
+        let mut limits = PatchLimits::new();
+        if let Some(max_memory) = self.max_memory {
+            limits.max_memory(max_memory);
+        }
+        if let Some(max_expansion) = self.max_expansion {
+            limits.max_expansion(max_expansion);
+        }
+        if let Some(max_new_len) = self.max_new_len {
+            limits.max_new_len(max_new_len);
+        }
+        if let Some(size) = self.decompression_buffer_size {
+            limits.read_buffer_size(size);
+        }
+
+        Some(limits)
+    }
+
+    /// Returns the command-line flags which reproduce these options for `isolated-patch`
+    fn to_args(&self) -> Vec<OsString> {
+        let mut args = Vec::new();
+        let mut push = |flag: &str, value: String| {
+            args.push(flag.into());
+            args.push(value.into());
+        };
+
+        if let Some(size) = self.decompression_buffer_size {
+            push("--decompression-buffer-size", size.to_string());
+        }
+        if let Some(max_memory) = self.max_memory {
+            push("--max-memory", max_memory.to_string());
+        }
+        if let Some(max_expansion) = self.max_expansion {
+            push("--max-expansion", max_expansion.to_string());
+        }
+        if let Some(max_new_len) = self.max_new_len {
+            push("--max-new-len", max_new_len.to_string());
+        }
+        if let Some(target) = &self.expected_target {
+            if let Some(platform) = target.platform() {
+                push("--expect-platform", platform.to_string());
+            }
+            if let Some(abi) = target.abi() {
+                push("--expect-abi", abi.to_string());
+            }
+            if let Some(version_code) = target.version_code() {
+                push("--expect-version-code", version_code.to_string());
+            }
+        }
+
+        args
+    }
+}
+
+/// Applies the patches concatenated after the one `patcher` applied to the file at `new_path`,
+/// each to the output of the previous one
+///
+/// `new` must be the file at `new_path`, which `patcher` has finished writing. The intermediate
+/// outputs are kept in memory, and `new` is only rewritten once the last patch has been applied.
+/// Returns the metadata of the last patch applied.
+pub fn apply_concatenated<O, P>(
+    mut patcher: Patcher<'static, O, BufReader<P>>,
+    new_path: &Path,
+    new: &mut File,
+    output: &Output,
+) -> anyhow::Result<PatchMetadata>
+where
+    O: Read + Seek,
+    P: Read,
+{
+    if !patcher.has_next_patch()? {
+        return Ok(patcher.metadata().clone());
+    }
+
+    let old = fs::read(new_path)
+        .with_context(|| format!("Failed to read new file '{}'", new_path.display()))?;
+    let mut metadata = patcher.metadata().clone();
+    let mut next = patcher.next_patch(Cursor::new(old))?;
+    let mut blob = Vec::new();
+    let mut applied = 1;
+    while let Some(mut patcher) = next {
+        io::copy(&mut patcher, &mut blob).context("Failed to apply concatenated patch")?;
+        applied += 1;
+        metadata = patcher.metadata().clone();
+        next = if patcher.has_next_patch()? {
+            patcher.next_patch(Cursor::new(mem::take(&mut blob)))?
+        } else {
+            None
+        };
+    }
+
+    new.set_len(0)?;
+    new.rewind()?;
+    new.write_all(&blob).context("Failed to write new file")?;
+    output.detail(format_args!("Applied {applied} concatenated patches"));
+
+    Ok(metadata)
+}
+
+/// Creates the new file at `path` for writing with direct I/O, which bypasses the page cache
+#[cfg(target_os = "linux")]
+pub fn create_direct(path: &Path) -> anyhow::Result<File> {
+    use std::os::unix::fs::OpenOptionsExt;
+
+    File::options()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .custom_flags(libc::O_DIRECT)
+        .open(path)
+        .with_context(|| {
+            format!(
+                "Failed to create new file '{}' for direct I/O; its filesystem may not support it",
+                path.display(),
+            )
+        })
+}
+
+/// Writes the new blob reconstructed by `patcher` to `new`, which was created by `create_direct()`
+/// at `new_path`, returning `patcher` once it's done
+///
+/// The new blob is written in aligned blocks, the last of which is padded, so `new` is truncated to
+/// the length of the new blob afterwards. `new` is then reopened without direct I/O, so that
+/// concatenated patches and metadata can be applied to it as usual.
+#[cfg(target_os = "linux")]
+pub fn write_direct<'a, O, B>(
+    patcher: Patcher<'a, O, B>,
+    new_path: &Path,
+    new: &mut File,
+) -> anyhow::Result<Patcher<'a, O, B>>
+where
+    O: Read + Seek,
+    B: io::BufRead,
+{
+    let mut blocks = patcher.aligned_blocks(DIRECT_IO_BLOCK_SIZE, DIRECT_IO_ALIGNMENT);
+    let mut len = 0;
+    while let Some(block) = blocks.next_block().context("Failed to apply patch file")? {
+        new.write_all(block.padded())
+            .context("Failed to write new file")?;
+        len += block.data().len() as u64;
+    }
+    new.set_len(len).context("Failed to truncate new file")?;
+
+    *new = File::options()
+        .write(true)
+        .open(new_path)
+        .with_context(|| format!("Failed to reopen new file '{}'", new_path.display()))?;
+
+    Ok(blocks.into_patcher())
+}
+
+/// Writes the blocks of the new blob reconstructed by `patcher` which its patch records as changed
+/// to `new` at their offsets, returning `patcher` once it's done
+///
+/// `new` must already hold the old blob, so the other blocks are left as they are. If `new` is a
+/// regular file, it's truncated or extended to the length of the new blob afterwards.
+pub fn write_changed_blocks<'a, O, B>(
+    patcher: Patcher<'a, O, B>,
+    new: &mut File,
+    output: &Output,
+) -> anyhow::Result<Patcher<'a, O, B>>
+where
+    O: Read + Seek,
+    B: io::BufRead,
+{
+    let Some(mut updates) = patcher.block_updates() else {
+        anyhow::bail!("The patch has no block map; create it with `ina diff --block-map`");
+    };
+    let block_map = updates.block_map().clone();
+    let mut written = 0;
+    while let Some(update) = updates
+        .next_update()
+        .context("Failed to apply patch file")?
+    {
+        new.seek(SeekFrom::Start(update.offset()))
+            .and_then(|_| new.write_all(update.data()))
+            .context("Failed to write new file")?;
+        written += 1;
+    }
+
+    let mut patcher = updates.into_patcher();
+    if patcher.has_next_patch()? {
+        anyhow::bail!("Concatenated patches can't be applied with --changed-blocks");
+    }
+    if new.metadata()?.is_file() {
+        new.set_len(block_map.new_len())
+            .context("Failed to truncate new file")?;
+    }
+    output.detail(format_args!(
+        "Wrote {written} of {} blocks of {} bytes",
+        block_map.block_count(),
+        block_map.block_size(),
+    ));
+
+    Ok(patcher)
+}
+
+/// Applies `patch` to the file at `old` in a sandboxed child process, writing the result to `new`
+///
+/// The child is this executable running the hidden `isolated-patch` subcommand. It opens the old
+/// file itself, then enables the strictest sandbox available before reading the patch from its
+/// standard input and sending the new file back over a pipe. Only data framed by the child is
+/// written to `new`, and the result is only accepted if the child reports that it finished.
+///
+/// The child reports its own errors according to `output`. If it fails, the returned error has the
+/// same category as the child's.
+pub fn patch_isolated(
+    old: &Path,
+    patch: File,
+    new: &mut File,
+    options: &PatchOptions,
+    output: &Output,
+) -> anyhow::Result<()> {
+    let exe = env::current_exe().context("Failed to locate the ina executable")?;
+    let mut child = Command::new(exe)
+        .args(output.to_args())
+        .arg(ISOLATED_PATCH_COMMAND)
+        .arg(old)
+        .args(options.to_args())
+        .stdin(patch)
+        .stdout(Stdio::piped())
+        .spawn()
+        .context("Failed to start isolated patch process")?;
+
+    // Stdout was requested as piped above, so it's always present
+    let output = child.stdout.take().unwrap();
+    let finished = receive_frames(output, new);
+    let status = child
+        .wait()
+        .context("Failed to wait for isolated patch process")?;
+
+    let message = format!("Isolated patch process failed ({status})");
+    match (
+        finished,
+        status.code().and_then(ErrorCategory::from_exit_code),
+    ) {
+        (Ok(true), _) => Ok(()),
+        (Ok(false), Some(category)) => Err(CategorizedError::new(category, message).into()),
+        (Ok(false), None) => Err(anyhow::Error::msg(message)),
+        (Err(e), _) => Err(e).context(message),
+    }
+}
+
+/// Applies `patch` to the file at `old`, cloning unchanged blocks of the old file into `new` where
+/// the filesystem supports it
+#[cfg(target_os = "linux")]
+pub fn patch_reflink(
+    old: &Path,
+    patch: File,
+    new: &File,
+    options: &PatchOptions,
+    output: &Output,
+) -> anyhow::Result<()> {
+    // Limits may also be set in the config file, so they can't be ruled out by argument conflicts
+    if options.limits().is_some() {
+        anyhow::bail!("Patch limits can't be enforced when patching with --reflink");
+    }
+
+    let old_file =
+        File::open(old).with_context(|| format!("Failed to open old file '{}'", old.display()))?;
+    let stats = ina::patch_reflink(&old_file, patch, new).context("Failed to apply patch file")?;
+    output.detail(format_args!(
+        "Cloned {} of {} bytes from the old file",
+        stats.cloned_len(),
+        stats.new_len(),
+    ));
+
+    Ok(())
+}
+
+/// Runs the child side of `patch --isolate`, writing the new file to standard output
+pub fn run_isolated(old: &Path, options: &PatchOptions) -> anyhow::Result<()> {
+    let old_file =
+        File::open(old).with_context(|| format!("Failed to open old file '{}'", old.display()))?;
+    let patch = io::stdin().lock();
+    let mut output = FrameWriter {
+        inner: io::stdout().lock(),
+    };
+
+    // Every file this process needs is open, so sandbox it before touching the patch
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    ina::sandbox::enable_for_patching().context("Failed to enable sandbox")?;
+
+    let mut patcher = options.patcher(old_file, patch)?;
+    io::copy(&mut patcher, &mut output).context("Failed to apply patch file")?;
+    if patcher.has_next_patch()? {
+        anyhow::bail!("Concatenated patches can't be applied with --isolate");
+    }
+    output.finish().context("Failed to send new file")
+}
+
+/// Copies the frames sent by an isolated patch process to `new`
+///
+/// Returns whether the process sent the final empty frame indicating that it finished.
+fn receive_frames<R>(output: R, new: &mut File) -> anyhow::Result<bool>
+where
+    R: Read,
+{
+    let mut output = BufReader::new(output);
+    let mut frame = vec![0; MAX_FRAME_LEN];
+
+    loop {
+        let mut len = [0; 4];
+        match output.read_exact(&mut len) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(false),
+            Err(e) => return Err(e.into()),
+        }
+
+        let len = u32::from_le_bytes(len) as usize;
+        if len == 0 {
+            return Ok(true);
+        }
+        if len > MAX_FRAME_LEN {
+            anyhow::bail!("received frame of {len} bytes, exceeding the maximum");
+        }
+
+        output.read_exact(&mut frame[..len])?;
+        new.write_all(&frame[..len])
+            .context("Failed to write new file")?;
+    }
+}
+
+/// A writer which sends data as length-prefixed frames
+struct FrameWriter<W>
+where
+    W: Write,
+{
+    inner: W,
+}
+
+impl<W> FrameWriter<W>
+where
+    W: Write,
+{
+    /// Sends the empty frame indicating that all data was sent successfully
+    fn finish(mut self) -> io::Result<()> {
+        self.inner.write_all(&0u32.to_le_bytes())?;
+        self.inner.flush()
+    }
+}
+
+impl<W> Write for FrameWriter<W>
+where
+    W: Write,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = buf.len().min(MAX_FRAME_LEN);
+        if len == 0 {
+            return Ok(0);
+        }
+
+        self.inner.write_all(&(len as u32).to_le_bytes())?;
+        self.inner.write_all(&buf[..len])?;
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
@@ -0,0 +1,654 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{self, File},
+    io::{self, BufReader},
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+    process,
+    sync::{Arc, Condvar, Mutex},
+    thread,
+};
+
+use anyhow::Context;
+use ina::{FileMetadata, OldBlob};
+use serde::Deserialize;
+
+use crate::{
+    DigestKind,
+    config::Config,
+    output::{CategorizedError, ErrorCategory, Output},
+    patch::PatchOptions,
+};
+
+/// A pipeline file describing the steps of a release
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Pipeline {
+    /// The maximum number of steps run at once
+    jobs: Option<usize>,
+    #[serde(default, rename = "step")]
+    steps: Vec<Step>,
+}
+
+/// A single step of a pipeline
+///
+/// Relative paths are resolved against the directory of the pipeline file. Steps which don't name
+/// a config file use the one given by `--config` or found in the current directory.
+#[derive(Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case", deny_unknown_fields)]
+enum Step {
+    /// Generates a patch between two files, or a bundle between two directories
+    Diff {
+        name: Option<String>,
+        config: Option<PathBuf>,
+        old: PathBuf,
+        new: PathBuf,
+        output: PathBuf,
+    },
+    /// Applies a patch to an old file
+    Patch {
+        name: Option<String>,
+        config: Option<PathBuf>,
+        old: PathBuf,
+        patch: PathBuf,
+        output: PathBuf,
+    },
+    /// Checks that applying a patch to an old file produces a new file
+    Verify {
+        name: Option<String>,
+        config: Option<PathBuf>,
+        old: PathBuf,
+        patch: PathBuf,
+        new: PathBuf,
+    },
+    /// Runs an external program, e.g., to sign the outputs of other steps
+    Command {
+        name: Option<String>,
+        argv: Vec<String>,
+        #[serde(default)]
+        inputs: Vec<PathBuf>,
+        #[serde(default)]
+        outputs: Vec<PathBuf>,
+    },
+}
+
+impl Step {
+    /// Returns the name of this step, which defaults to its kind and main output
+    fn name(&self) -> String {
+        match self {
+            Self::Diff {
+                name: Some(name), ..
+            }
+            | Self::Patch {
+                name: Some(name), ..
+            }
+            | Self::Verify {
+                name: Some(name), ..
+            }
+            | Self::Command {
+                name: Some(name), ..
+            } => name.clone(),
+            Self::Diff { output, .. } => format!("diff {}", output.display()),
+            Self::Patch { output, .. } => format!("patch {}", output.display()),
+            Self::Verify { patch, .. } => format!("verify {}", patch.display()),
+            Self::Command { argv, .. } => format!("command {}", argv.join(" ")),
+        }
+    }
+
+    /// Returns the paths this step reads
+    fn inputs(&self) -> Vec<&Path> {
+        match self {
+            Self::Diff {
+                config, old, new, ..
+            } => [Some(old), Some(new), config.as_ref()]
+                .into_iter()
+                .flatten()
+                .map(PathBuf::as_path)
+                .collect(),
+            Self::Patch {
+                config, old, patch, ..
+            } => [Some(old), Some(patch), config.as_ref()]
+                .into_iter()
+                .flatten()
+                .map(PathBuf::as_path)
+                .collect(),
+            Self::Verify {
+                config,
+                old,
+                patch,
+                new,
+                ..
+            } => [Some(old), Some(patch), Some(new), config.as_ref()]
+                .into_iter()
+                .flatten()
+                .map(PathBuf::as_path)
+                .collect(),
+            Self::Command { inputs, .. } => inputs.iter().map(PathBuf::as_path).collect(),
+        }
+    }
+
+    /// Returns the paths this step writes
+    fn outputs(&self) -> Vec<&Path> {
+        match self {
+            Self::Diff { output, .. } | Self::Patch { output, .. } => vec![output],
+            Self::Verify { .. } => Vec::new(),
+            Self::Command { outputs, .. } => outputs.iter().map(PathBuf::as_path).collect(),
+        }
+    }
+
+    /// Resolves every relative path of this step against `base`
+    fn resolve(&mut self, base: &Path) {
+        let paths: Vec<&mut PathBuf> = match self {
+            Self::Diff {
+                config,
+                old,
+                new,
+                output,
+                ..
+            } => [Some(old), Some(new), Some(output), config.as_mut()]
+                .into_iter()
+                .flatten()
+                .collect(),
+            Self::Patch {
+                config,
+                old,
+                patch,
+                output,
+                ..
+            } => [Some(old), Some(patch), Some(output), config.as_mut()]
+                .into_iter()
+                .flatten()
+                .collect(),
+            Self::Verify {
+                config,
+                old,
+                patch,
+                new,
+                ..
+            } => [Some(old), Some(patch), Some(new), config.as_mut()]
+                .into_iter()
+                .flatten()
+                .collect(),
+            Self::Command {
+                inputs, outputs, ..
+            } => inputs.iter_mut().chain(outputs).collect(),
+        };
+        for path in paths {
+            *path = base.join(&*path);
+        }
+    }
+}
+
+/// The state of a step while the pipeline runs
+#[derive(Clone, Copy, Eq, PartialEq)]
+enum State {
+    Pending,
+    Running,
+    Succeeded,
+    /// The step failed or was skipped because a step it depends on failed
+    Failed,
+}
+
+/// The progress of a running pipeline, shared between its workers
+struct Progress {
+    states: Vec<State>,
+    /// The number of remaining steps reading each old file which may be cached
+    old_readers: HashMap<PathBuf, usize>,
+}
+
+/// A cached old file, which is `None` until the first step needing it has read it
+type OldSlot = Arc<Mutex<Option<Arc<OldBlob>>>>;
+
+/// Old files shared between the steps diffing against them, so that each is read and indexed only
+/// once
+#[derive(Default)]
+struct OldCache {
+    blobs: Mutex<HashMap<PathBuf, OldSlot>>,
+}
+
+impl OldCache {
+    /// Returns the old blob at `path`, reading it if no other step has
+    fn get(&self, path: &Path) -> anyhow::Result<Arc<OldBlob>> {
+        let slot = Arc::clone(
+            self.blobs
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .entry(path.to_path_buf())
+                .or_default(),
+        );
+        // Other steps needing the same old file wait until it has been read
+        let mut slot = slot.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(blob) = &*slot {
+            return Ok(Arc::clone(blob));
+        }
+
+        let blob = Arc::new(crate::read_old(path)?);
+        *slot = Some(Arc::clone(&blob));
+        Ok(blob)
+    }
+
+    /// Drops the old blob at `path` from the cache
+    fn remove(&self, path: &Path) {
+        self.blobs
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(path);
+    }
+}
+
+/// Runs the pipeline described by the file at `path`, failing if any step failed
+///
+/// Steps run in parallel, up to `jobs` or the number set in the pipeline file at once, except that
+/// a step reading a path another step writes runs after it. Steps depending on a failed step are
+/// skipped.
+pub fn run(
+    path: &Path,
+    jobs: Option<usize>,
+    default_config: &Config,
+    output: &Output,
+) -> anyhow::Result<()> {
+    let contents = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read pipeline file '{}'", path.display()))?;
+    let mut pipeline: Pipeline = if path.extension().is_some_and(|ext| ext == "json") {
+        serde_json::from_str(&contents)
+            .with_context(|| format!("Failed to parse pipeline file '{}'", path.display()))?
+    } else {
+        toml::from_str(&contents)
+            .with_context(|| format!("Failed to parse pipeline file '{}'", path.display()))?
+    };
+
+    let base = path.parent().unwrap_or(Path::new(""));
+    for step in &mut pipeline.steps {
+        step.resolve(base);
+    }
+    let steps = &pipeline.steps;
+    let dependencies = dependencies(steps)?;
+
+    let mut old_readers = HashMap::new();
+    for step in steps {
+        if let Step::Diff { old, .. } = step {
+            *old_readers.entry(old.clone()).or_insert(0) += 1;
+        }
+    }
+    let progress = Mutex::new(Progress {
+        states: vec![State::Pending; steps.len()],
+        old_readers,
+    });
+    let step_done = Condvar::new();
+    let cache = OldCache::default();
+
+    let jobs = jobs
+        .or(pipeline.jobs)
+        .or_else(|| thread::available_parallelism().ok().map(NonZeroUsize::get))
+        .unwrap_or(1)
+        .clamp(1, steps.len().max(1));
+    thread::scope(|scope| {
+        for _ in 0..jobs {
+            scope.spawn(|| {
+                while let Some(index) = next_step(&progress, &step_done, &dependencies, steps) {
+                    let step = &steps[index];
+                    let result = run_step(step, default_config, &cache, base);
+                    match &result {
+                        Ok(()) if output.normal() => println!("{}: ok", step.name()),
+                        Ok(()) => {}
+                        Err(e) => println!("{}: FAILED: {e:#}", step.name()),
+                    }
+
+                    let mut progress = progress.lock().unwrap_or_else(|e| e.into_inner());
+                    progress.states[index] = if result.is_ok() {
+                        State::Succeeded
+                    } else {
+                        State::Failed
+                    };
+                    if let Step::Diff { old, .. } = step
+                        && let Some(readers) = progress.old_readers.get_mut(old)
+                    {
+                        *readers -= 1;
+                        if *readers == 0 {
+                            cache.remove(old);
+                        }
+                    }
+                    step_done.notify_all();
+                }
+            });
+        }
+    });
+
+    let states = progress
+        .into_inner()
+        .unwrap_or_else(|e| e.into_inner())
+        .states;
+    let failures = states
+        .iter()
+        .filter(|&&state| state == State::Failed)
+        .count();
+    if failures > 0 {
+        anyhow::bail!(
+            "{failures} of {} step(s) failed or were skipped",
+            steps.len()
+        );
+    }
+
+    Ok(())
+}
+
+/// Returns the indices of the steps each step depends on, i.e., which write a path it reads
+///
+/// Fails if two steps write the same path or steps depend on each other in a cycle.
+fn dependencies(steps: &[Step]) -> anyhow::Result<Vec<Vec<usize>>> {
+    let mut writers = HashMap::new();
+    for (index, step) in steps.iter().enumerate() {
+        for path in step.outputs() {
+            if let Some(other) = writers.insert(path, index) {
+                anyhow::bail!(
+                    "Steps '{}' and '{}' both write '{}'",
+                    steps[other].name(),
+                    step.name(),
+                    path.display(),
+                );
+            }
+        }
+    }
+
+    let dependencies: Vec<Vec<usize>> = steps
+        .iter()
+        .map(|step| {
+            let dependencies: HashSet<_> = step
+                .inputs()
+                .into_iter()
+                .filter_map(|path| writers.get(path).copied())
+                .collect();
+            dependencies.into_iter().collect()
+        })
+        .collect();
+
+    // Repeatedly remove steps whose dependencies have all been removed; any left form a cycle
+    let mut remaining: HashSet<_> = (0..steps.len()).collect();
+    loop {
+        let ready: Vec<_> = remaining
+            .iter()
+            .copied()
+            .filter(|&index| {
+                dependencies[index]
+                    .iter()
+                    .all(|dep| !remaining.contains(dep))
+            })
+            .collect();
+        if ready.is_empty() {
+            break;
+        }
+        for index in ready {
+            remaining.remove(&index);
+        }
+    }
+    if let Some(&index) = remaining.iter().min() {
+        anyhow::bail!(
+            "Step '{}' depends on its own output through other steps",
+            steps[index].name(),
+        );
+    }
+
+    Ok(dependencies)
+}
+
+/// Waits until a step is ready to run and claims it, returning `None` once every step has finished
+///
+/// Steps depending on a failed step are marked as failed without running.
+fn next_step(
+    progress: &Mutex<Progress>,
+    step_done: &Condvar,
+    dependencies: &[Vec<usize>],
+    steps: &[Step],
+) -> Option<usize> {
+    let mut progress = progress.lock().unwrap_or_else(|e| e.into_inner());
+    loop {
+        let mut running = false;
+        let mut skipped = false;
+        for index in 0..steps.len() {
+            match progress.states[index] {
+                State::Pending => {}
+                State::Running => {
+                    running = true;
+                    continue;
+                }
+                State::Succeeded | State::Failed => continue,
+            }
+
+            let states = &progress.states;
+            if let Some(&failed) = dependencies[index]
+                .iter()
+                .find(|&&dep| states[dep] == State::Failed)
+            {
+                println!(
+                    "{}: skipped because step '{}' failed",
+                    steps[index].name(),
+                    steps[failed].name(),
+                );
+                progress.states[index] = State::Failed;
+                skipped = true;
+            } else if dependencies[index]
+                .iter()
+                .all(|&dep| states[dep] == State::Succeeded)
+            {
+                progress.states[index] = State::Running;
+                return Some(index);
+            }
+        }
+
+        if skipped {
+            // Skipping a step may allow skipping the steps depending on it
+            step_done.notify_all();
+        } else if running || progress.states.contains(&State::Pending) {
+            progress = step_done.wait(progress).unwrap_or_else(|e| e.into_inner());
+        } else {
+            return None;
+        }
+    }
+}
+
+/// Runs a single step of a pipeline
+fn run_step(
+    step: &Step,
+    default_config: &Config,
+    cache: &OldCache,
+    base: &Path,
+) -> anyhow::Result<()> {
+    let loaded_config;
+    let config = match step {
+        Step::Diff {
+            config: Some(path), ..
+        }
+        | Step::Patch {
+            config: Some(path), ..
+        }
+        | Step::Verify {
+            config: Some(path), ..
+        } => {
+            loaded_config = Config::load(Some(path))?;
+            &loaded_config
+        }
+        _ => default_config,
+    };
+
+    match step {
+        Step::Diff {
+            old, new, output, ..
+        } => diff(old, new, output, config, cache),
+        Step::Patch {
+            old, patch, output, ..
+        } => {
+            let old_file = File::open(old)
+                .with_context(|| format!("Failed to open old file '{}'", old.display()))?;
+            let patch_file = File::open(patch)
+                .with_context(|| format!("Failed to open patch file '{}'", patch.display()))?;
+            let mut new_file = File::create(output)
+                .with_context(|| format!("Failed to create new file '{}'", output.display()))?;
+            let mut patcher = patch_options(config)
+                .patcher(old_file, patch_file)
+                .with_context(|| format!("Failed to apply '{}'", patch.display()))?;
+            io::copy(&mut patcher, &mut new_file).context("Failed to apply patch file")?;
+
+            if config.patch.restore_metadata.unwrap_or(false) {
+                ina::restore_file_metadata(patcher.metadata(), &mut new_file).with_context(
+                    || {
+                        format!(
+                            "Failed to restore metadata of new file '{}'",
+                            output.display()
+                        )
+                    },
+                )?;
+            }
+            Ok(())
+        }
+        Step::Verify {
+            old, patch, new, ..
+        } => {
+            let old_file = File::open(old)
+                .with_context(|| format!("Failed to open old file '{}'", old.display()))?;
+            let patch_file = File::open(patch)
+                .with_context(|| format!("Failed to open patch file '{}'", patch.display()))?;
+            let new_file = File::open(new)
+                .with_context(|| format!("Failed to open new file '{}'", new.display()))?;
+            let patcher = patch_options(config)
+                .patcher(old_file, patch_file)
+                .with_context(|| format!("Failed to apply '{}'", patch.display()))?;
+
+            match ina::verify_against(patcher, BufReader::new(new_file))
+                .context("Failed to apply patch file")?
+            {
+                Some(offset) => Err(CategorizedError::new(
+                    ErrorCategory::Mismatch,
+                    format!(
+                        "Patch output differs from '{}' at offset {offset}",
+                        new.display(),
+                    ),
+                )
+                .into()),
+                None => Ok(()),
+            }
+        }
+        Step::Command { argv, .. } => {
+            let (program, args) = argv
+                .split_first()
+                .context("Command steps need a program to run")?;
+            let status = process::Command::new(program)
+                .args(args)
+                .current_dir(if base.as_os_str().is_empty() {
+                    Path::new(".")
+                } else {
+                    base
+                })
+                .status()
+                .with_context(|| format!("Failed to run '{program}'"))?;
+            if !status.success() {
+                anyhow::bail!("'{program}' exited unsuccessfully ({status})");
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Generates a patch from `old` to `new`, or a bundle if both are directories, according to the
+/// diff settings of `config`
+fn diff(
+    old: &Path,
+    new: &Path,
+    output: &Path,
+    config: &Config,
+    cache: &OldCache,
+) -> anyhow::Result<()> {
+    let settings = &config.diff;
+    let mut diff_config = crate::diff_config(settings);
+    diff_config.provenance(crate::provenance_of(
+        settings,
+        &old.display(),
+        &new.display(),
+        None,
+        None,
+    )?);
+    let preserve_metadata = settings.preserve_metadata.unwrap_or(false);
+
+    let output_file = File::create(output)
+        .with_context(|| format!("Failed to create output file '{}'", output.display()))?;
+    let (output_len, new_len) = if old.is_dir() && new.is_dir() {
+        let summary = crate::bundle::diff_dirs(
+            old,
+            new,
+            output_file,
+            &diff_config,
+            preserve_metadata,
+            settings.digest.unwrap_or(DigestKind::Blake3).into(),
+        )?;
+        let bundle_len = fs::metadata(output)
+            .with_context(|| format!("Failed to read metadata of bundle '{}'", output.display()))?
+            .len();
+
+        (bundle_len, summary.new_len)
+    } else {
+        if preserve_metadata {
+            let new_metadata = fs::metadata(new).with_context(|| {
+                format!("Failed to read metadata of new file '{}'", new.display())
+            })?;
+            diff_config.file_metadata(Some(FileMetadata::from_metadata(&new_metadata)));
+        }
+
+        let mut output_file = output_file;
+        let stats = match settings.diff_window {
+            Some(window_len) => {
+                diff_config.diff_window_len(window_len);
+                let old_file = File::open(old)
+                    .with_context(|| format!("Failed to open old file '{}'", old.display()))?;
+                let new_file = File::open(new)
+                    .with_context(|| format!("Failed to open new file '{}'", new.display()))?;
+                ina::diff_windowed(
+                    BufReader::new(old_file),
+                    BufReader::new(new_file),
+                    &mut output_file,
+                    &diff_config,
+                )
+            }
+            None => {
+                let old_data = cache.get(old)?;
+                let new_data = fs::read(new)
+                    .with_context(|| format!("Failed to read new file '{}'", new.display()))?;
+                ina::diff_with_config(&old_data, &new_data, &mut output_file, &diff_config)
+            }
+        }
+        .context("I/O error occurred while generating patch file")?;
+
+        (stats.patch_len(), stats.new_len())
+    };
+
+    let ratio = output_len as f64 / new_len as f64;
+    if settings
+        .max_ratio
+        .is_some_and(|max_ratio| ratio > max_ratio)
+    {
+        return Err(CategorizedError::new(
+            ErrorCategory::RatioExceeded,
+            format!(
+                "'{}' is {:.2}% of the size of the new file(s), exceeding the maximum ratio",
+                output.display(),
+                ratio * 100.0,
+            ),
+        )
+        .into());
+    }
+
+    Ok(())
+}
+
+/// Returns the options for creating a `Patcher` from the patch settings of `config`
+fn patch_options(config: &Config) -> PatchOptions {
+    PatchOptions {
+        decompression_buffer_size: config.patch.decompression_buffer_size,
+        max_memory: config.patch.max_memory,
+        max_expansion: config.patch.max_expansion,
+        max_new_len: config.patch.max_new_len,
+        expected_target: None,
+    }
+}
@@ -0,0 +1,337 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write},
+    net::{TcpListener, TcpStream},
+    path::{Component, Path, PathBuf},
+    thread,
+};
+
+use anyhow::Context;
+use serde_json::json;
+
+use crate::output::Output;
+
+/// The longest request line or header accepted, which is far longer than any a client of this
+/// server sends
+const MAX_LINE_LEN: u64 = 8 << 10;
+
+/// The most headers accepted in a request
+const MAX_HEADERS: usize = 100;
+
+/// A response status
+#[derive(Clone, Copy)]
+enum Status {
+    Ok,
+    PartialContent,
+    BadRequest,
+    NotFound,
+    MethodNotAllowed,
+    RangeNotSatisfiable,
+}
+
+impl Status {
+    fn code(self) -> u16 {
+        match self {
+            Self::Ok => 200,
+            Self::PartialContent => 206,
+            Self::BadRequest => 400,
+            Self::NotFound => 404,
+            Self::MethodNotAllowed => 405,
+            Self::RangeNotSatisfiable => 416,
+        }
+    }
+
+    fn reason(self) -> &'static str {
+        match self {
+            Self::Ok => "OK",
+            Self::PartialContent => "Partial Content",
+            Self::BadRequest => "Bad Request",
+            Self::NotFound => "Not Found",
+            Self::MethodNotAllowed => "Method Not Allowed",
+            Self::RangeNotSatisfiable => "Range Not Satisfiable",
+        }
+    }
+}
+
+/// The parts of a request this server acts on
+struct Request {
+    method: String,
+    path: String,
+    range: Option<String>,
+}
+
+/// Serves the files in `dir` over HTTP on `addr` until the process is killed
+///
+/// Once listening, the address is printed to standard output, which is how callers learn the port
+/// when `addr` lets the operating system choose one.
+pub fn run(dir: &Path, addr: &str, output: &Output) -> anyhow::Result<()> {
+    let dir = dir
+        .canonicalize()
+        .with_context(|| format!("failed to open {}", dir.display()))?;
+    let listener =
+        TcpListener::bind(addr).with_context(|| format!("failed to listen on {addr}"))?;
+    let addr = listener.local_addr()?;
+
+    // The address is printed regardless of verbosity since it may not be known beforehand
+    if output.json() {
+        println!(
+            "{}",
+            json!({ "dir": dir, "url": format!("http://{addr}/") })
+        );
+    } else {
+        println!("Serving {} at http://{addr}/", dir.display());
+    }
+    io::stdout().flush()?;
+
+    thread::scope(|scope| {
+        for stream in listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(e) => {
+                    output.detail(format_args!("Failed to accept a connection: {e}"));
+                    continue;
+                }
+            };
+            let dir = &dir;
+            scope.spawn(move || {
+                if let Err(e) = handle(dir, stream, output) {
+                    output.detail(format_args!("Failed to answer a request: {e}"));
+                }
+            });
+        }
+    });
+
+    Ok(())
+}
+
+/// Answers a single request on `stream` with a file in `dir`, closing the connection afterward
+fn handle(dir: &Path, stream: TcpStream, output: &Output) -> io::Result<()> {
+    let mut writer = &stream;
+    let request = match read_request(&mut BufReader::new(&stream))? {
+        Some(request) => request,
+        None => return respond(writer, Status::BadRequest, &[], None),
+    };
+
+    let status = answer(dir, &request, &mut writer)?;
+    output.detail(format_args!(
+        "{} {} {}",
+        request.method,
+        request.path,
+        status.code(),
+    ));
+
+    Ok(())
+}
+
+/// Answers `request` on `writer`, returning the status of the response
+fn answer<W>(dir: &Path, request: &Request, writer: &mut W) -> io::Result<Status>
+where
+    W: Write,
+{
+    let head = match request.method.as_str() {
+        "GET" => false,
+        "HEAD" => true,
+        _ => {
+            let status = Status::MethodNotAllowed;
+            respond(writer, status, &[("Allow", "GET, HEAD".to_owned())], None)?;
+            return Ok(status);
+        }
+    };
+
+    let file = resolve(dir, &request.path).and_then(|path| File::open(path).ok());
+    let Some((mut file, len)) = file.and_then(|file| {
+        let len = file.metadata().ok()?.len();
+        Some((file, len))
+    }) else {
+        respond(writer, Status::NotFound, &[], None)?;
+        return Ok(Status::NotFound);
+    };
+
+    let content_type = match Path::new(&request.path).extension() {
+        Some(extension) if extension == "ina" => ina::format::MIME_TYPE,
+        _ => "application/octet-stream",
+    };
+    let mut headers = vec![
+        ("Content-Type", content_type.to_owned()),
+        ("Accept-Ranges", "bytes".to_owned()),
+    ];
+
+    let (status, start, end) = match request.range.as_deref().and_then(parse_range) {
+        None => (Status::Ok, 0, len),
+        Some(range) => match range.resolve(len) {
+            Some((start, end)) => {
+                headers.push(("Content-Range", format!("bytes {start}-{}/{len}", end - 1)));
+                (Status::PartialContent, start, end)
+            }
+            None => {
+                let status = Status::RangeNotSatisfiable;
+                headers.push(("Content-Range", format!("bytes */{len}")));
+                respond(writer, status, &headers, None)?;
+                return Ok(status);
+            }
+        },
+    };
+
+    file.seek(SeekFrom::Start(start))?;
+    let body = (!head).then_some((&mut file as &mut dyn Read, end - start));
+    headers.push(("Content-Length", (end - start).to_string()));
+    respond(writer, status, &headers, body)?;
+
+    Ok(status)
+}
+
+/// Reads the request line and headers of a request, returning `None` if they're malformed
+fn read_request<R>(reader: &mut R) -> io::Result<Option<Request>>
+where
+    R: BufRead,
+{
+    let Some(line) = read_line(reader)? else {
+        return Ok(None);
+    };
+    let mut parts = line.split(' ');
+    let (Some(method), Some(target), Some(version), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return Ok(None);
+    };
+    if !version.starts_with("HTTP/1.") || !target.starts_with('/') {
+        return Ok(None);
+    }
+    let path = target
+        .split(['?', '#'])
+        .next()
+        .unwrap_or_default()
+        .to_owned();
+
+    let mut range = None;
+    for _ in 0..MAX_HEADERS {
+        let Some(line) = read_line(reader)? else {
+            return Ok(None);
+        };
+        if line.is_empty() {
+            return Ok(Some(Request {
+                method: method.to_owned(),
+                path,
+                range,
+            }));
+        }
+        if let Some((name, value)) = line.split_once(':')
+            && name.eq_ignore_ascii_case("range")
+        {
+            range = Some(value.trim().to_owned());
+        }
+    }
+
+    Ok(None)
+}
+
+/// Reads a line terminated by CRLF or LF, returning `None` if it's too long, isn't UTF-8, or the
+/// connection closes first
+fn read_line<R>(reader: &mut R) -> io::Result<Option<String>>
+where
+    R: BufRead,
+{
+    let mut line = Vec::new();
+    reader.take(MAX_LINE_LEN).read_until(b'\n', &mut line)?;
+    if line.pop() != Some(b'\n') {
+        return Ok(None);
+    }
+    if line.last() == Some(&b'\r') {
+        line.pop();
+    }
+
+    Ok(String::from_utf8(line).ok())
+}
+
+/// Returns the path of the file in `dir` which a request path names, or `None` if it names a
+/// directory or anything outside `dir`
+fn resolve(dir: &Path, request_path: &str) -> Option<PathBuf> {
+    let relative = Path::new(request_path.trim_start_matches('/'));
+    if relative.as_os_str().is_empty()
+        || request_path.contains('\\')
+        || !relative
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+    {
+        return None;
+    }
+
+    let path = dir.join(relative);
+    // Symbolic links are resolved, so they may not lead out of the directory either
+    let path = path.canonicalize().ok()?;
+    (path.starts_with(dir) && path.is_file()).then_some(path)
+}
+
+/// A single byte range of a `Range` header
+enum ByteRange {
+    /// The bytes from a position to an optional inclusive end
+    From(u64, Option<u64>),
+    /// The given number of bytes at the end
+    Suffix(u64),
+}
+
+impl ByteRange {
+    /// Returns the start and exclusive end of this range in a file of `len` bytes, or `None` if
+    /// the range doesn't overlap the file
+    fn resolve(&self, len: u64) -> Option<(u64, u64)> {
+        match *self {
+            Self::From(start, end) if start < len => {
+                let end = end.map_or(len, |end| end.saturating_add(1).min(len));
+                Some((start, end))
+            }
+            Self::Suffix(suffix_len) if suffix_len > 0 && len > 0 => {
+                Some((len.saturating_sub(suffix_len), len))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Parses the value of a `Range` header, returning `None` if it's malformed or requests several
+/// ranges, in which case it's ignored and the whole file is served
+fn parse_range(value: &str) -> Option<ByteRange> {
+    let spec = value.strip_prefix("bytes=")?.trim();
+    if spec.contains(',') {
+        return None;
+    }
+    let (start, end) = spec.split_once('-')?;
+
+    match (start.trim(), end.trim()) {
+        ("", suffix_len) => Some(ByteRange::Suffix(suffix_len.parse().ok()?)),
+        (start, "") => Some(ByteRange::From(start.parse().ok()?, None)),
+        (start, end) => {
+            let (start, end) = (start.parse().ok()?, end.parse().ok()?);
+            (start <= end).then_some(ByteRange::From(start, Some(end)))
+        }
+    }
+}
+
+/// Writes a response with `headers` and a body of the given number of bytes read from a reader
+fn respond<W>(
+    mut writer: W,
+    status: Status,
+    headers: &[(&str, String)],
+    body: Option<(&mut dyn Read, u64)>,
+) -> io::Result<()>
+where
+    W: Write,
+{
+    let mut head = format!("HTTP/1.1 {} {}\r\n", status.code(), status.reason());
+    for (name, value) in headers {
+        head.push_str(&format!("{name}: {value}\r\n"));
+    }
+    if !headers.iter().any(|(name, _)| *name == "Content-Length") {
+        head.push_str("Content-Length: 0\r\n");
+    }
+    head.push_str("Connection: close\r\n\r\n");
+    writer.write_all(head.as_bytes())?;
+
+    if let Some((body, len)) = body {
+        io::copy(&mut body.take(len), &mut writer)?;
+    }
+    writer.flush()
+}
@@ -0,0 +1,252 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use std::{
+    ffi::OsString,
+    fs::{self, File, OpenOptions},
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::{self, Path, PathBuf},
+};
+
+use anyhow::Context;
+use ina::{Digest, DigestAlgorithm};
+use serde::{Deserialize, Serialize};
+
+use crate::{hex, output::Output, secs_since_epoch};
+
+/// The default number of bytes written to the new file between checkpoints
+pub const DEFAULT_STATE_INTERVAL: usize = 8 << 20;
+
+/// The version of the state file format, which is increased whenever it changes incompatibly
+const STATE_VERSION: u32 = 1;
+
+/// The progress of `ina patch` as recorded in a state file
+#[derive(Deserialize, Serialize)]
+struct PatchState {
+    version: u32,
+    /// Identifies the arguments and input files the progress applies to
+    fingerprint: String,
+    /// The number of bytes of the new file which were durably written
+    written: u64,
+    /// The BLAKE3 digest of those bytes
+    digest: String,
+}
+
+/// A state file recording the progress of writing a new file, so that an interrupted `ina patch`
+/// can resume where it left off when rerun with the same arguments
+pub struct StateFile {
+    path: PathBuf,
+    fingerprint: String,
+}
+
+/// The point at which writing the new file resumes
+pub struct Resume {
+    /// The number of bytes of the new file already written
+    written: u64,
+    /// The hasher of those bytes
+    hasher: Box<dyn Digest>,
+}
+
+impl StateFile {
+    /// Creates a state file at `path` for patching `old` with `patch` into `new`
+    ///
+    /// The state only applies to the same paths and to old and patch files of the same size and
+    /// modification time.
+    pub fn new(path: &Path, old: &Path, patch: &Path, new: &Path) -> anyhow::Result<Self> {
+        let mut hasher = DigestAlgorithm::Blake3.hasher();
+        for input in [old, patch] {
+            let metadata = fs::metadata(input)
+                .with_context(|| format!("Failed to read metadata of '{}'", input.display()))?;
+            let modified = metadata
+                .modified()
+                .ok()
+                .and_then(secs_since_epoch)
+                .unwrap_or(0);
+
+            hash_path(&mut *hasher, input)?;
+            hasher.update(&metadata.len().to_le_bytes());
+            hasher.update(&modified.to_le_bytes());
+        }
+        hash_path(&mut *hasher, new)?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            fingerprint: hex(&hasher.finalize()),
+        })
+    }
+
+    /// Opens the new file at `new` for writing, resuming from the recorded progress if there is
+    /// any and the new file still starts with the bytes written so far
+    ///
+    /// Otherwise, the new file is created or truncated as usual.
+    pub fn open_new(&self, new: &Path, output: &Output) -> anyhow::Result<(File, Resume)> {
+        if let Some(state) = self.load()? {
+            match OpenOptions::new().read(true).write(true).open(new) {
+                Ok(mut new_file) => {
+                    let mut hasher = DigestAlgorithm::Blake3.hasher();
+                    let hashed = io::copy(&mut (&mut new_file).take(state.written), &mut hasher)
+                        .with_context(|| format!("Failed to read new file '{}'", new.display()))?;
+
+                    if hashed == state.written && hex(&hasher.finalize()) == state.digest {
+                        new_file.set_len(state.written)?;
+                        new_file.seek(SeekFrom::Start(state.written))?;
+                        output.detail(format_args!(
+                            "Resuming after {} bytes recorded in '{}'",
+                            state.written,
+                            self.path.display(),
+                        ));
+
+                        return Ok((
+                            new_file,
+                            Resume {
+                                written: state.written,
+                                hasher,
+                            },
+                        ));
+                    }
+                    output.detail("New file doesn't match the state file, starting over");
+                }
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => {
+                    return Err(e)
+                        .with_context(|| format!("Failed to open new file '{}'", new.display()));
+                }
+            }
+        }
+
+        let new_file = File::create(new)
+            .with_context(|| format!("Failed to create new file '{}'", new.display()))?;
+        Ok((
+            new_file,
+            Resume {
+                written: 0,
+                hasher: DigestAlgorithm::Blake3.hasher(),
+            },
+        ))
+    }
+
+    /// Reads the rest of the new blob from `patcher` into `new`, recording the progress every
+    /// `interval` bytes
+    ///
+    /// `new` and `resume` must have been returned by [`StateFile::open_new()`]. The bytes of the
+    /// new blob which were already written are skipped.
+    pub fn write_new<R>(
+        &self,
+        mut patcher: R,
+        new: &mut File,
+        resume: Resume,
+        interval: usize,
+    ) -> anyhow::Result<()>
+    where
+        R: Read,
+    {
+        let skipped = io::copy(&mut (&mut patcher).take(resume.written), &mut io::sink())
+            .context("Failed to apply patch file")?;
+        if skipped < resume.written {
+            anyhow::bail!("Patch output is shorter than the progress recorded in the state file");
+        }
+
+        let interval = interval.max(1) as u64;
+        let mut writer = CheckpointWriter {
+            new,
+            state: self,
+            hasher: resume.hasher,
+            written: resume.written,
+            interval,
+            next_checkpoint: resume.written + interval,
+        };
+        io::copy(&mut patcher, &mut writer).context("Failed to apply patch file")?;
+
+        Ok(())
+    }
+
+    /// Removes the state file once the new file is complete
+    pub fn remove(&self) -> anyhow::Result<()> {
+        match fs::remove_file(&self.path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e)
+                .with_context(|| format!("Failed to remove state file '{}'", self.path.display())),
+            _ => Ok(()),
+        }
+    }
+
+    /// Reads the recorded progress if it applies to this run
+    fn load(&self) -> anyhow::Result<Option<PatchState>> {
+        let data = match fs::read(&self.path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => {
+                return Err(e).with_context(|| {
+                    format!("Failed to read state file '{}'", self.path.display())
+                });
+            }
+        };
+
+        // State files of other versions or for other arguments are ignored rather than rejected so
+        // that rerunning with changed arguments simply starts over
+        Ok(serde_json::from_slice::<PatchState>(&data)
+            .ok()
+            .filter(|state| {
+                state.version == STATE_VERSION && state.fingerprint == self.fingerprint
+            }))
+    }
+
+    /// Atomically replaces the state file with the given progress
+    fn save(&self, written: u64, digest: &[u8]) -> io::Result<()> {
+        let state = PatchState {
+            version: STATE_VERSION,
+            fingerprint: self.fingerprint.clone(),
+            written,
+            digest: hex(digest),
+        };
+
+        let mut temp_path = OsString::from(&self.path);
+        temp_path.push(".tmp");
+        let mut temp = File::create(&temp_path)?;
+        serde_json::to_writer(&mut temp, &state)?;
+        temp.sync_all()?;
+        fs::rename(&temp_path, &self.path)
+    }
+}
+
+/// A writer to the new file which records its progress in a state file every `interval` bytes
+struct CheckpointWriter<'a> {
+    new: &'a mut File,
+    state: &'a StateFile,
+    hasher: Box<dyn Digest>,
+    written: u64,
+    interval: u64,
+    next_checkpoint: u64,
+}
+
+impl Write for CheckpointWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.new.write(buf)?;
+        self.hasher.update(&buf[..written]);
+        self.written += written as u64;
+
+        if self.written >= self.next_checkpoint {
+            // The data must be durable before the state file claims it was written
+            self.new.sync_data()?;
+            self.state.save(self.written, &self.hasher.finalize())?;
+            self.next_checkpoint = self.written + self.interval;
+        }
+
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.new.flush()
+    }
+}
+
+/// Hashes the absolute form of `path` along with its length so that paths can't run together
+fn hash_path(hasher: &mut dyn Digest, path: &Path) -> anyhow::Result<()> {
+    let path = path::absolute(path)
+        .with_context(|| format!("Failed to resolve path '{}'", path.display()))?;
+    let bytes = path.as_os_str().as_encoded_bytes();
+    hasher.update(&(bytes.len() as u64).to_le_bytes());
+    hasher.update(bytes);
+
+    Ok(())
+}
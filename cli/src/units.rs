@@ -0,0 +1,94 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use std::time::Duration;
+
+/// The default rate at which a patch is assumed to produce the new file when estimating how long
+/// applying it takes, in bytes per second
+pub const DEFAULT_APPLY_THROUGHPUT: usize = 128 << 20;
+
+/// The binary units sizes are formatted in, in increasing order
+const SIZE_UNITS: [&str; 6] = ["KiB", "MiB", "GiB", "TiB", "PiB", "EiB"];
+
+/// How sizes, ratios, and durations are formatted in human-oriented output
+#[derive(Clone, Copy)]
+pub struct Units {
+    /// Whether to print exact values for scripts instead of rounded ones for people
+    machine: bool,
+}
+
+impl Units {
+    /// Returns units which are formatted for people, or exactly if `machine` is set
+    pub fn new(machine: bool) -> Self {
+        Self { machine }
+    }
+
+    /// Formats a size in bytes
+    ///
+    /// Sizes of at least 1 KiB are given to three significant digits in the largest binary unit
+    /// they're at least 1 of, unless the units are for machines.
+    pub fn size(self, bytes: u64) -> String {
+        if self.machine || bytes < 1024 {
+            return format!("{bytes} bytes");
+        }
+
+        let mut value = bytes as f64 / 1024.0;
+        let mut unit = 0;
+        while value >= 1024.0 && unit < SIZE_UNITS.len() - 1 {
+            value /= 1024.0;
+            unit += 1;
+        }
+        let precision = match value {
+            ..9.995 => 2,
+            ..99.95 => 1,
+            _ => 0,
+        };
+
+        format!("{value:.precision$} {}", SIZE_UNITS[unit])
+    }
+
+    /// Formats `part` as a percentage of `whole`
+    ///
+    /// An empty whole is 0%.
+    pub fn percent(self, part: u64, whole: u64) -> String {
+        let fraction = if whole == 0 {
+            0.0
+        } else {
+            part as f64 / whole as f64
+        };
+
+        if self.machine {
+            format!("{:.6}%", fraction * 100.0)
+        } else {
+            format!("{:.2}%", fraction * 100.0)
+        }
+    }
+
+    /// Formats a duration
+    ///
+    /// Durations are given in seconds for machines, and in the largest unit among milliseconds,
+    /// seconds, and minutes that keeps them readable for people.
+    pub fn duration(self, duration: Duration) -> String {
+        let secs = duration.as_secs_f64();
+        if self.machine {
+            return format!("{secs:.3}s");
+        }
+
+        match secs {
+            ..0.0005 => "<1 ms".into(),
+            ..1.0 => format!("{:.0} ms", secs * 1000.0),
+            ..60.0 => format!("{secs:.1} s"),
+            _ => format!("{} min {} s", secs as u64 / 60, secs as u64 % 60),
+        }
+    }
+}
+
+/// Estimates how long applying a patch takes from the size of the new file it produces and the
+/// rate in bytes per second at which patches are assumed to produce it
+///
+/// This is a rough model for comparing patches. The actual time also depends on the storage of
+/// the old and new files and on how compressible the patch's data is.
+pub fn apply_time(new_len: u64, throughput: usize) -> Duration {
+    Duration::from_secs_f64(new_len as f64 / throughput.max(1) as f64)
+}
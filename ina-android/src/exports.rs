@@ -0,0 +1,152 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! The functions called by the Kotlin `Patcher` class, which take ownership of or duplicate the
+//! file descriptors passed to them
+
+use std::{
+    ffi::c_void,
+    fs::File,
+    io,
+    os::fd::{BorrowedFd, FromRawFd},
+    sync::Arc,
+};
+
+#[cfg(feature = "sandbox")]
+use ina::sandbox::SandboxStatus;
+use jni::{
+    Executor, JNIEnv, JavaVM,
+    objects::{JClass, JObject},
+    sys::{JNI_ERR, JNI_VERSION_1_6, jint, jlong},
+};
+
+use crate::{
+    FileRegion, Methods, apply, log_failure,
+    streams::{InputStream, OutputStream},
+    written_or_failure,
+};
+
+// SAFETY: There is no other global function with this name
+#[unsafe(no_mangle)]
+extern "system" fn JNI_OnLoad(vm: JavaVM, _reserved: *mut c_void) -> jint {
+    let cached = vm
+        .get_env()
+        .and_then(|mut env| Methods::get(&mut env).map(|_| ()));
+
+    match cached {
+        Ok(()) => JNI_VERSION_1_6,
+        Err(_) => JNI_ERR,
+    }
+}
+
+// SAFETY: There is no other global function with this name
+#[unsafe(no_mangle)]
+unsafe extern "system" fn Java_app_accrescent_ina_Patcher_patch(
+    mut env: JNIEnv,
+    _class: JClass,
+    old_file_fd: jint,
+    patch: JObject,
+    new: JObject,
+) -> jlong {
+    // SAFETY: The caller guarantees that `old_file_fd` is an owned, open file descriptor
+    let old_file = unsafe { File::from_raw_fd(old_file_fd) };
+
+    let methods = match Methods::get(&mut env) {
+        Ok(methods) => methods,
+        Err(e) => {
+            log_failure(format_args!("failed to look up stream methods: {e}"));
+            return -1;
+        }
+    };
+    let vm = match env.get_java_vm() {
+        Ok(vm) => Arc::new(vm),
+        Err(e) => {
+            log_failure(format_args!("failed to get the Java VM: {e}"));
+            return -1;
+        }
+    };
+    let patch_stream = InputStream::new(Executor::new(Arc::clone(&vm)), patch, methods);
+    let new_stream = OutputStream::new(Executor::new(vm), new, methods);
+
+    written_or_failure(apply(old_file, patch_stream, new_stream))
+}
+
+// SAFETY: There is no other global function with this name
+#[unsafe(no_mangle)]
+#[allow(clippy::too_many_arguments)]
+unsafe extern "system" fn Java_app_accrescent_ina_Patcher_patchFds(
+    _env: JNIEnv,
+    _class: JClass,
+    old_fd: jint,
+    old_offset: jlong,
+    old_len: jlong,
+    patch_fd: jint,
+    patch_offset: jlong,
+    patch_len: jlong,
+    new_fd: jint,
+) -> jlong {
+    // SAFETY: The caller guarantees that all file descriptors are open for the duration of this
+    // call. They remain owned by the caller, so we only use duplicates of them.
+    let (old, patch, new) = unsafe { (dup(old_fd), dup(patch_fd), dup(new_fd)) };
+    let old = old.and_then(|file| FileRegion::new(file, old_offset, old_len));
+    let patch = patch.and_then(|file| FileRegion::new(file, patch_offset, patch_len));
+    let (old, patch, new) = match (old, patch, new) {
+        (Ok(old), Ok(patch), Ok(new)) => (old, patch, new),
+        (Err(e), _, _) | (_, Err(e), _) | (_, _, Err(e)) => {
+            log_failure(format_args!("failed to duplicate file descriptors: {e}"));
+            return -1;
+        }
+    };
+
+    // A patch without an offset or length may be a pipe, which can only be read sequentially
+    let result = if patch.start == 0 && patch.len.is_none() {
+        apply(old, patch.file, new)
+    } else {
+        apply(old, patch, new)
+    };
+    written_or_failure(result)
+}
+
+/// Duplicates `fd`, leaving it owned by the caller
+///
+/// # Safety
+///
+/// `fd` must be an open file descriptor for the duration of this call.
+unsafe fn dup(fd: jint) -> io::Result<File> {
+    // SAFETY: The caller guarantees that `fd` is open
+    let fd = unsafe { BorrowedFd::borrow_raw(fd) };
+
+    fd.try_clone_to_owned().map(File::from)
+}
+
+// SAFETY: There is no other global function with this name
+#[unsafe(no_mangle)]
+#[cfg(feature = "sandbox")]
+extern "system" fn Java_app_accrescent_ina_Patcher_enableSandbox(
+    _env: JNIEnv,
+    _class: JClass,
+) -> jint {
+    match ina::sandbox::enable_for_patching() {
+        Ok(enabled) => jint::from(enabled),
+        Err(e) => {
+            log_failure(format_args!("failed to enable the sandbox: {e}"));
+            -1
+        }
+    }
+}
+
+// SAFETY: There is no other global function with this name
+#[unsafe(no_mangle)]
+#[cfg(feature = "sandbox")]
+extern "system" fn Java_app_accrescent_ina_Patcher_sandboxStatus(
+    _env: JNIEnv,
+    _class: JClass,
+) -> jint {
+    match ina::sandbox::status() {
+        SandboxStatus::Disabled => 0,
+        SandboxStatus::FilesystemRestricted => 1,
+        SandboxStatus::SingleThreaded => 2,
+        _ => -1,
+    }
+}
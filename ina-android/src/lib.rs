@@ -0,0 +1,226 @@
+// SPDX-FileCopyrightText: © 2024 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! JNI bindings backing the Ina Android library.
+//!
+//! This crate builds the native library loaded by the Kotlin `Patcher` class. It isn't part of the
+//! public API of this repository, so its entry points may change along with the Kotlin code calling
+//! them. Keeping the bindings out of the `ina` crate spares other consumers of it the JNI
+//! dependencies and leaves room for Android-specific behavior, which is selected with these
+//! features, all of which are enabled by default:
+//!
+//! - `logcat`: Failures of the entry points are written to logcat with the tag `Ina`, since they
+//!   only return -1 to the Kotlin code.
+//! - `sandbox`: The sandbox can be enabled and its status queried from Kotlin.
+//! - `strict-mode`: The new blob is written in large chunks, so that a Java `OutputStream` isn't
+//!   flagged by `StrictMode`'s detection of unbuffered I/O and JNI calls are kept to a minimum.
+//!
+//! Unsafe code is confined to the leaf modules which need it: `exports` takes file descriptors from
+//! the Kotlin code, `streams` calls Java methods through cached method IDs, and `logcat` calls into
+//! `liblog`. It's denied everywhere else.
+
+// The bindings take file descriptors, so they're only built for Unix-like targets such as Android
+#![cfg(unix)]
+#![deny(unsafe_code)]
+
+#[allow(unsafe_code)]
+mod exports;
+#[cfg(feature = "logcat")]
+#[allow(unsafe_code)]
+mod logcat;
+#[allow(unsafe_code)]
+mod streams;
+
+#[cfg(feature = "strict-mode")]
+use std::io::BufWriter;
+use std::{
+    fmt,
+    fs::File,
+    io::{self, Error as IoError, ErrorKind, Read, Seek, SeekFrom, Write},
+    os::unix::fs::FileExt,
+    sync::OnceLock,
+};
+
+use ina::PatchError;
+use jni::{
+    JNIEnv,
+    errors::Error as JniError,
+    objects::{GlobalRef, JMethodID},
+    sys::jlong,
+};
+
+/// The capacity of the buffer the new blob is written through with the `strict-mode` feature
+///
+/// This is the size of the pipe buffer on Linux, so writes to pipes, which Kotlin streams are
+/// commonly backed by, aren't split any further.
+#[cfg(feature = "strict-mode")]
+const WRITE_BUF_LEN: usize = 64 << 10;
+
+/// Class references and method IDs looked up once per process
+static METHODS: OnceLock<Methods> = OnceLock::new();
+
+/// The Java stream methods called by the stream adapters
+///
+/// Looking up a method by name and signature on every call is a significant fraction of patching
+/// time for streams which read or write small buffers, so the method IDs are cached instead.
+struct Methods {
+    // Method IDs are only valid as long as their class is loaded, so we hold references to the
+    // classes for as long as the IDs are cached
+    _input_stream: GlobalRef,
+    _output_stream: GlobalRef,
+    /// `InputStream.read(byte[], int, int)`
+    ///
+    /// <https://docs.oracle.com/javase/8/docs/api/java/io/InputStream.html#read-byte:A-int-int->
+    read: JMethodID,
+    /// `OutputStream.write(byte[])`
+    ///
+    /// <https://docs.oracle.com/javase/8/docs/api/java/io/OutputStream.html#write-byte:A->
+    write: JMethodID,
+    /// `OutputStream.flush()`
+    ///
+    /// <https://docs.oracle.com/javase/8/docs/api/java/io/OutputStream.html#flush-->
+    flush: JMethodID,
+}
+
+impl Methods {
+    /// Returns the cached methods, looking them up if they haven't been cached yet
+    ///
+    /// The methods are normally cached by `JNI_OnLoad`, but are looked up here as well in case the
+    /// library was loaded without it being called.
+    fn get(env: &mut JNIEnv) -> Result<&'static Self, JniError> {
+        if let Some(methods) = METHODS.get() {
+            return Ok(methods);
+        }
+
+        // If another thread caches the methods first, ours are dropped and theirs are used
+        let methods = Self::lookup(env)?;
+        Ok(METHODS.get_or_init(|| methods))
+    }
+
+    fn lookup(env: &mut JNIEnv) -> Result<Self, JniError> {
+        let input_stream = env.find_class("java/io/InputStream")?;
+        let output_stream = env.find_class("java/io/OutputStream")?;
+
+        Ok(Self {
+            read: env.get_method_id(&input_stream, "read", "([BII)I")?,
+            write: env.get_method_id(&output_stream, "write", "([B)V")?,
+            flush: env.get_method_id(&output_stream, "flush", "()V")?,
+            _input_stream: env.new_global_ref(input_stream)?,
+            _output_stream: env.new_global_ref(output_stream)?,
+        })
+    }
+}
+
+/// Applies `patch` to `old`, writing the new blob to `new` and returning its length
+///
+/// With the `strict-mode` feature, `new` is written through a buffer, which is flushed before
+/// returning along with `new` itself.
+fn apply<O, P, W>(old: O, patch: P, new: W) -> Result<u64, PatchError>
+where
+    O: Read + Seek,
+    P: Read,
+    W: Write,
+{
+    #[cfg(feature = "strict-mode")]
+    let mut new = BufWriter::with_capacity(WRITE_BUF_LEN, new);
+    #[cfg(not(feature = "strict-mode"))]
+    let mut new = new;
+
+    let written = ina::patch(old, patch, &mut new)?;
+    new.flush()?;
+
+    Ok(written)
+}
+
+/// Converts the result of patching into the return value of an entry point, which is the length of
+/// the new blob or -1 if patching failed
+fn written_or_failure(result: Result<u64, PatchError>) -> jlong {
+    match result {
+        Ok(written) => written as jlong,
+        Err(e) => {
+            log_failure(format_args!("patching failed: {e}"));
+            -1
+        }
+    }
+}
+
+/// Logs the failure of an entry point to logcat with the `logcat` feature
+fn log_failure(message: fmt::Arguments) {
+    #[cfg(feature = "logcat")]
+    logcat::error(message);
+    #[cfg(not(feature = "logcat"))]
+    let _ = message;
+}
+
+/// A region of a file read with positioned reads, as described by an Android
+/// `AssetFileDescriptor`
+///
+/// Reading at an explicit position leaves the file offset untouched. This matters because the file
+/// descriptor is a duplicate of one the caller still owns, so they share a single file offset.
+struct FileRegion {
+    file: File,
+    start: u64,
+    /// The length of the region, or `None` if it extends to the end of the file
+    len: Option<u64>,
+    pos: u64,
+}
+
+impl FileRegion {
+    /// Wraps `file` in a region beginning at `offset` which is `len` bytes long, or extends to the
+    /// end of the file if `len` is negative
+    fn new(file: File, offset: jlong, len: jlong) -> io::Result<Self> {
+        let start = u64::try_from(offset)
+            .map_err(|_| IoError::new(ErrorKind::InvalidInput, "negative file offset"))?;
+
+        Ok(Self {
+            file,
+            start,
+            len: u64::try_from(len).ok(),
+            pos: 0,
+        })
+    }
+}
+
+impl Read for FileRegion {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let remaining = self
+            .len
+            .map_or(u64::MAX, |len| len.saturating_sub(self.pos));
+        let buf_len = buf
+            .len()
+            .min(usize::try_from(remaining).unwrap_or(usize::MAX));
+        let offset = self
+            .start
+            .checked_add(self.pos)
+            .ok_or_else(|| IoError::new(ErrorKind::InvalidInput, "file offset overflow"))?;
+
+        let read = self.file.read_at(&mut buf[..buf_len], offset)?;
+        self.pos += read as u64;
+        Ok(read)
+    }
+}
+
+impl Seek for FileRegion {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let pos = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::Current(offset) => self.pos.checked_add_signed(offset),
+            SeekFrom::End(offset) => {
+                let len = match self.len {
+                    Some(len) => len,
+                    None => self.file.metadata()?.len().saturating_sub(self.start),
+                };
+                len.checked_add_signed(offset)
+            }
+        };
+
+        self.pos = pos.ok_or_else(|| {
+            IoError::new(
+                ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )
+        })?;
+        Ok(self.pos)
+    }
+}
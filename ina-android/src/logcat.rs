@@ -0,0 +1,42 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+#[cfg(target_os = "android")]
+use std::ffi::{CStr, CString, c_char, c_int};
+use std::fmt;
+
+/// The tag of messages written to logcat, which matches that of the Kotlin library
+#[cfg(target_os = "android")]
+const TAG: &CStr = c"Ina";
+
+/// The priority of errors, `ANDROID_LOG_ERROR` in `<android/log.h>`
+#[cfg(target_os = "android")]
+const ANDROID_LOG_ERROR: c_int = 6;
+
+#[cfg(target_os = "android")]
+#[link(name = "log")]
+unsafe extern "C" {
+    /// Writes a message to the main log buffer
+    ///
+    /// <https://developer.android.com/ndk/reference/group/logging#__android_log_write>
+    fn __android_log_write(prio: c_int, tag: *const c_char, text: *const c_char) -> c_int;
+}
+
+/// Writes `message` to logcat as an error
+///
+/// Other targets have no logcat, so the message is written to standard error there instead.
+pub(crate) fn error(message: fmt::Arguments) {
+    #[cfg(target_os = "android")]
+    {
+        // Messages are passed as C strings, which can't contain NUL bytes
+        let message = message.to_string().replace('\0', "\u{fffd}");
+        let message = CString::new(message).expect("NUL bytes were replaced");
+        // SAFETY: Both strings are valid C strings which outlive the call
+        unsafe {
+            __android_log_write(ANDROID_LOG_ERROR, TAG.as_ptr(), message.as_ptr());
+        }
+    }
+    #[cfg(not(target_os = "android"))]
+    eprintln!("Ina: {message}");
+}
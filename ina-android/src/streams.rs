@@ -0,0 +1,144 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Adapters exposing Java streams as Rust readers and writers, which call the stream methods
+//! through their cached method IDs
+
+use std::io::{self, Error as IoError, Read, Write};
+
+use jni::{
+    Executor,
+    errors::Error as JniError,
+    objects::{JObject, JValue},
+    signature::{Primitive, ReturnType},
+    sys::{jint, jsize},
+};
+
+use crate::Methods;
+
+pub(crate) struct InputStream<'a> {
+    executor: Executor,
+    input_stream: JObject<'a>,
+    methods: &'static Methods,
+}
+
+impl<'a> InputStream<'a> {
+    pub(crate) fn new(
+        executor: Executor,
+        input_stream: JObject<'a>,
+        methods: &'static Methods,
+    ) -> Self {
+        Self {
+            executor,
+            input_stream,
+            methods,
+        }
+    }
+}
+
+impl<'a> Read for InputStream<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.executor
+            .with_attached(|env| {
+                // A Java array's length is represented by a jsize, and jsize::MAX may be smaller
+                // than buf.len(). Therefore, clamp the maximum size of the temporary buffer we
+                // create to jsize::MAX.
+                let java_buf_len: jsize = buf.len().try_into().unwrap_or(jsize::MAX);
+
+                // Create a temporary Java buffer to read our bytes into
+                let java_buf = env.new_byte_array(java_buf_len)?;
+
+                // Read at most java_buf_len bytes from the Java InputStream into our Java byte
+                // array
+                //
+                // SAFETY: `read` is the method ID of `InputStream.read(byte[], int, int)`, whose
+                // return type and arguments match those given
+                let read: jint = unsafe {
+                    env.call_method_unchecked(
+                        &self.input_stream,
+                        self.methods.read,
+                        ReturnType::Primitive(Primitive::Int),
+                        &[
+                            JValue::Object(&java_buf).as_jni(),
+                            JValue::Int(0).as_jni(),
+                            JValue::Int(java_buf_len).as_jni(),
+                        ],
+                    )
+                }?
+                .try_into()?;
+
+                // Copy our Java byte array into buf
+                env.get_byte_array_region(java_buf, 0, bytemuck::cast_slice_mut::<u8, i8>(buf))?;
+
+                Ok(read)
+            })
+            // If `read` doesn't fit into a usize, then the InputStream API dictates it must be -1
+            // and that the stream is at EOF. The equivalent in Rust's Read API is returning 0, so
+            // map the value.
+            .map(|read| read.try_into().unwrap_or(0))
+            .map_err(|e: JniError| IoError::other(e))
+    }
+}
+
+pub(crate) struct OutputStream<'a> {
+    executor: Executor,
+    output_stream: JObject<'a>,
+    methods: &'static Methods,
+}
+
+impl<'a> OutputStream<'a> {
+    pub(crate) fn new(
+        executor: Executor,
+        output_stream: JObject<'a>,
+        methods: &'static Methods,
+    ) -> Self {
+        Self {
+            executor,
+            output_stream,
+            methods,
+        }
+    }
+}
+
+impl<'a> Write for OutputStream<'a> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.executor
+            .with_attached(|env| {
+                // Write buf to the Java OutputStream
+                let java_buf = env.byte_array_from_slice(buf)?;
+                // SAFETY: `write` is the method ID of `OutputStream.write(byte[])`, whose return
+                // type and arguments match those given
+                unsafe {
+                    env.call_method_unchecked(
+                        &self.output_stream,
+                        self.methods.write,
+                        ReturnType::Primitive(Primitive::Void),
+                        &[JValue::Object(&java_buf).as_jni()],
+                    )
+                }?;
+                Ok(buf.len())
+            })
+            .map_err(|e: JniError| IoError::other(e))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.executor
+            .with_attached(|env| {
+                // Flush the Java OutputStream
+                //
+                // SAFETY: `flush` is the method ID of `OutputStream.flush()`, whose return type and
+                // arguments match those given
+                unsafe {
+                    env.call_method_unchecked(
+                        &self.output_stream,
+                        self.methods.flush,
+                        ReturnType::Primitive(Primitive::Void),
+                        &[],
+                    )
+                }?;
+                Ok(())
+            })
+            .map_err(|e: JniError| IoError::other(e))
+    }
+}
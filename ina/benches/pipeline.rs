@@ -0,0 +1,73 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+#![allow(missing_docs)]
+
+#[path = "../tests/common/mod.rs"]
+mod common;
+
+use std::io::{self, BufReader, Cursor};
+
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use ina::{DiffConfig, OldBlob, Patcher};
+
+const SEED: u64 = 0;
+const LEN: usize = 1 << 20;
+const COMPRESSION_LEVELS: [i32; 3] = [1, 3, 19];
+const COMPRESSION_THREADS: [u32; 2] = [0, 4];
+const BUFFER_SIZES: [usize; 4] = [4 << 10, 32 << 10, 128 << 10, 1 << 20];
+
+fn diff(c: &mut Criterion) {
+    let mut group = c.benchmark_group("diff");
+    group.sample_size(10);
+
+    let (old, new) = common::binary_pair(SEED, LEN);
+    let old = OldBlob::from_vec(old);
+    group.throughput(Throughput::Bytes(new.len() as u64));
+
+    for level in COMPRESSION_LEVELS {
+        for threads in COMPRESSION_THREADS {
+            let mut config = DiffConfig::new();
+            config.compression_level(level).compression_threads(threads);
+
+            group.bench_with_input(
+                BenchmarkId::new(format!("level-{level}"), format!("threads-{threads}")),
+                &config,
+                |b, config| {
+                    b.iter(|| {
+                        let mut patch = Vec::with_capacity(new.len());
+                        ina::diff_with_config(&old, &new, &mut patch, config).unwrap();
+                        patch
+                    });
+                },
+            );
+        }
+    }
+
+    group.finish();
+}
+
+fn patch(c: &mut Criterion) {
+    let mut group = c.benchmark_group("patch");
+
+    let (old, new) = common::binary_pair(SEED, LEN);
+    let mut patch = Vec::new();
+    ina::diff(&OldBlob::from_slice(&old), &new, &mut patch).unwrap();
+    group.throughput(Throughput::Bytes(new.len() as u64));
+
+    for size in BUFFER_SIZES {
+        group.bench_with_input(BenchmarkId::from_parameter(size), &size, |b, &size| {
+            b.iter(|| {
+                let patch = BufReader::with_capacity(size, patch.as_slice());
+                let mut patcher = Patcher::with_buffer(Cursor::new(&old), patch).unwrap();
+                io::copy(&mut patcher, &mut io::sink()).unwrap()
+            });
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, diff, patch);
+criterion_main!(benches);
@@ -0,0 +1,92 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Applies a patch to files handed over as file descriptors.
+//!
+//! On Android, an app store's privileged service typically can't open the files it updates by
+//! path. Instead, it receives file descriptors for the installed APK, the downloaded patch, and the
+//! staged output, e.g., as `ParcelFileDescriptor`s detached over Binder and passed to native code.
+//! This example mimics that split: `main` plays the Java side and opens the files, while
+//! [`apply_from_fds()`] plays the native side and only ever sees descriptors.
+
+#[cfg(unix)]
+use std::{
+    error::Error,
+    fs::{self, File},
+    io::BufReader,
+    os::fd::OwnedFd,
+    process,
+};
+
+#[cfg(unix)]
+use ina::PatchError;
+
+#[cfg(unix)]
+fn main() -> Result<(), Box<dyn Error>> {
+    let dir = std::env::temp_dir().join(format!("ina-fd-example-{}", process::id()));
+    fs::create_dir_all(&dir)?;
+    let result = run(&dir);
+    fs::remove_dir_all(&dir)?;
+
+    result
+}
+
+#[cfg(not(unix))]
+fn main() {
+    println!("This example passes file descriptors, which are only available on Unix");
+}
+
+#[cfg(unix)]
+fn run(dir: &std::path::Path) -> Result<(), Box<dyn Error>> {
+    let old: Vec<u8> = (0..1u32 << 16).map(|i| (i * 13 % 241) as u8).collect();
+    let mut new = old[1024..].to_vec();
+    new.extend_from_slice(&old[..1024]);
+
+    let patch = ina::diff_to_vec(
+        &ina::OldBlob::from_slice(&old),
+        &new,
+        ina::DiffConfig::new().record_new_len(true),
+    )?;
+
+    fs::write(dir.join("base.apk"), &old)?;
+    fs::write(dir.join("update.ina"), &patch)?;
+
+    // Descriptors are plain integers across the Binder boundary. Converting the files into owned
+    // descriptors here stands in for detaching them from `ParcelFileDescriptor`s.
+    let old_fd = OwnedFd::from(File::open(dir.join("base.apk"))?);
+    let patch_fd = OwnedFd::from(File::open(dir.join("update.ina"))?);
+    let new_fd = OwnedFd::from(File::create(dir.join("staged.apk"))?);
+
+    let written = apply_from_fds(old_fd, patch_fd, new_fd)?;
+    assert_eq!(written, new.len() as u64);
+    assert_eq!(fs::read(dir.join("staged.apk"))?, new);
+    println!("Wrote {written} bytes through file descriptors");
+
+    Ok(())
+}
+
+/// Reconstructs the new file into `new_fd` from the old file and patch behind `old_fd` and
+/// `patch_fd`, taking ownership of all three descriptors
+///
+/// The new file is sized up front when the patch records its length and synced before returning,
+/// so the caller can rename it into place as soon as this succeeds.
+#[cfg(unix)]
+fn apply_from_fds(old_fd: OwnedFd, patch_fd: OwnedFd, new_fd: OwnedFd) -> Result<u64, PatchError> {
+    let old = File::from(old_fd);
+    let patch = BufReader::new(File::from(patch_fd));
+    let new = File::from(new_fd);
+
+    let written = ina::patch_to_file(old, patch, &new)?;
+    new.sync_all()?;
+
+    Ok(written)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    #[test]
+    fn example_runs() -> Result<(), Box<dyn std::error::Error>> {
+        super::main()
+    }
+}
@@ -0,0 +1,174 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Updates a directory tree with a single bundle.
+//!
+//! The bundle is built by walking the old and new trees, and applied into a staging directory
+//! whose files are checked against the lengths and digests the bundle records before it would be
+//! swapped into place.
+
+use std::{
+    collections::BTreeMap,
+    error::Error,
+    fs,
+    io::Cursor,
+    path::{Path, PathBuf},
+    process,
+};
+
+use ina::{BundleEntryKind, BundleReader, BundleWriter, DiffConfig};
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let root = std::env::temp_dir().join(format!("ina-bundle-example-{}", process::id()));
+    fs::create_dir_all(&root)?;
+    let result = run(&root);
+    fs::remove_dir_all(&root)?;
+
+    result
+}
+
+fn run(root: &Path) -> Result<(), Box<dyn Error>> {
+    let (old_dir, new_dir, staging_dir) = (root.join("v1"), root.join("v2"), root.join("staging"));
+    let library: Vec<u8> = (0..1u32 << 15).map(|i| (i * 17 % 239) as u8).collect();
+    let mut updated_library = library.clone();
+    updated_library[100..200].fill(0);
+    write_tree(
+        &old_dir,
+        &[
+            ("bin/app", b"app version 1".as_slice()),
+            ("lib/libfoo.so", &library),
+            ("share/README", b"Read me"),
+            ("share/obsolete.txt", b"Going away"),
+        ],
+    )?;
+    write_tree(
+        &new_dir,
+        &[
+            ("bin/app", b"app version 2".as_slice()),
+            ("lib/libfoo.so", &updated_library),
+            ("share/README", b"Read me"),
+            ("share/new.txt", b"Brand new"),
+        ],
+    )?;
+
+    let bundle = build_bundle(&old_dir, &new_dir)?;
+    println!("Built a bundle of {} bytes", bundle.len());
+
+    apply_bundle(&bundle, &old_dir, &staging_dir)?;
+    assert_eq!(read_tree(&staging_dir)?, read_tree(&new_dir)?);
+    println!("Staged tree matches the new tree");
+
+    Ok(())
+}
+
+/// Creates a bundle which updates the tree at `old_dir` to the tree at `new_dir`
+fn build_bundle(old_dir: &Path, new_dir: &Path) -> Result<Vec<u8>, Box<dyn Error>> {
+    let old = read_tree(old_dir)?;
+    let new = read_tree(new_dir)?;
+
+    let mut writer = BundleWriter::new(Vec::new())?;
+    for (path, contents) in &new {
+        if let Some(stats) = writer.add(
+            path,
+            old.get(path).map(Vec::as_slice),
+            contents,
+            &DiffConfig::new(),
+        )? {
+            println!("{path}: {} byte patch", stats.patch_len());
+        }
+    }
+    for path in old.keys().filter(|path| !new.contains_key(*path)) {
+        writer.remove(path)?;
+    }
+
+    Ok(writer.finish()?)
+}
+
+/// Applies `bundle` to the tree at `old_dir`, writing the new tree to `staging_dir`
+fn apply_bundle(bundle: &[u8], old_dir: &Path, staging_dir: &Path) -> Result<(), Box<dyn Error>> {
+    let mut reader = BundleReader::new(bundle)?;
+    let algorithm = reader.digest_algorithm();
+
+    while let Some(entry) = reader.next_entry()? {
+        let old_path = old_dir.join(entry.path());
+        let new = match entry.kind() {
+            BundleEntryKind::Removed => {
+                println!("{}: removed", entry.path());
+                continue;
+            }
+            BundleEntryKind::Unchanged => fs::read(&old_path)?,
+            BundleEntryKind::Added => patch_entry(&[], &mut reader)?,
+            BundleEntryKind::Modified => patch_entry(&fs::read(&old_path)?, &mut reader)?,
+            // The trees built by this example only contain regular files
+            BundleEntryKind::Symlink => {
+                return Err(format!("{}: unexpected symbolic link", entry.path()).into());
+            }
+        };
+
+        // Check the result before it's staged, as a real updater would before swapping trees
+        if new.len() as u64 != entry.new_len() || entry.new_digest() != Some(&algorithm.hash(&new))
+        {
+            return Err(format!("{}: reconstructed file doesn't match", entry.path()).into());
+        }
+        let new_path = staging_dir.join(entry.path());
+        if let Some(parent) = new_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(new_path, new)?;
+        println!("{}: {}", entry.path(), entry.kind());
+    }
+
+    Ok(())
+}
+
+/// Applies the patch of the current entry of `reader` to `old`
+fn patch_entry(old: &[u8], reader: &mut BundleReader<&[u8]>) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut new = Vec::new();
+    ina::patch(Cursor::new(old), reader.patch(), &mut new)?;
+
+    Ok(new)
+}
+
+/// Writes files with the given relative paths and contents under `dir`
+fn write_tree(dir: &Path, files: &[(&str, &[u8])]) -> Result<(), Box<dyn Error>> {
+    for (path, contents) in files {
+        let path = dir.join(path);
+        fs::create_dir_all(path.parent().unwrap_or(dir))?;
+        fs::write(path, contents)?;
+    }
+
+    Ok(())
+}
+
+/// Reads the files under `dir`, keyed by their paths relative to it with `/` as the separator
+fn read_tree(dir: &Path) -> Result<BTreeMap<String, Vec<u8>>, Box<dyn Error>> {
+    let mut files = BTreeMap::new();
+    let mut pending = vec![PathBuf::new()];
+    while let Some(relative) = pending.pop() {
+        for entry in fs::read_dir(dir.join(&relative))? {
+            let entry = entry?;
+            let path = relative.join(entry.file_name());
+            if entry.file_type()?.is_dir() {
+                pending.push(path);
+            } else {
+                let key = path
+                    .components()
+                    .map(|c| c.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/");
+                files.insert(key, fs::read(entry.path())?);
+            }
+        }
+    }
+
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn example_runs() -> Result<(), Box<dyn std::error::Error>> {
+        super::main()
+    }
+}
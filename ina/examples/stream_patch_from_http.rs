@@ -0,0 +1,126 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Applies a patch while it's being downloaded over HTTP.
+//!
+//! A server thread stands in for a CDN serving a patch. The client checks the response, identifies
+//! the patch from its first bytes without buffering it, and feeds the body straight into a
+//! [`Patcher`] whose limits bound the resources an untrusted patch may use. A [`WatchdogReader`]
+//! aborts the download if the server stalls.
+
+use std::{
+    error::Error,
+    io::{self, BufRead, BufReader, Cursor, Write},
+    net::{TcpListener, TcpStream},
+    thread,
+    time::Duration,
+};
+
+use ina::{DiffConfig, OldBlob, PatchLimits, Patcher, WatchdogReader};
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let old: Vec<u8> = (0..1u32 << 16).map(|i| (i * 7 % 251) as u8).collect();
+    let mut new = old.clone();
+    new[4096..4160].fill(0xaa);
+    new.extend_from_slice(b"new trailing data");
+
+    // Record a checksum so that a patch corrupted or truncated in transit is detected
+    let mut config = DiffConfig::new();
+    config.payload_checksum(true).record_new_len(true);
+    let patch = ina::diff_to_vec(&OldBlob::from_slice(&old), &new, &config)?;
+
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let addr = listener.local_addr()?;
+    let server = thread::spawn(move || serve_once(&listener, &patch));
+
+    let mut stream = TcpStream::connect(addr)?;
+    write!(
+        stream,
+        "GET /app-v1-to-v2.ina HTTP/1.1\r\nHost: {addr}\r\nConnection: close\r\n\r\n",
+    )?;
+    let mut body = BufReader::new(stream);
+    let content_type = read_response_head(&mut body)?;
+    println!("Downloading patch ({content_type})");
+
+    // A misconfigured server may answer with an error page instead of the patch, which is
+    // detected before any of it is applied
+    let Some(version) = ina::format::sniff(body.fill_buf()?) else {
+        return Err("response isn't a patch".into());
+    };
+    println!(
+        "Patch format version {}.{}",
+        version.major(),
+        version.minor()
+    );
+
+    // A server which stops sending fails the download rather than hanging it
+    let body = WatchdogReader::new(body, Duration::from_secs(30))?;
+    let mut limits = PatchLimits::new();
+    limits.max_memory(64 << 20).max_new_len(1 << 20);
+    let mut patcher = Patcher::with_limits(Cursor::new(&old), body, &limits)?;
+    let mut reconstructed = Vec::new();
+    io::copy(&mut patcher, &mut reconstructed)?;
+
+    server.join().expect("server thread panicked")?;
+    assert_eq!(reconstructed, new);
+    println!("Reconstructed {} bytes", reconstructed.len());
+
+    Ok(())
+}
+
+/// Answers a single request on `listener` with `patch`
+fn serve_once(listener: &TcpListener, patch: &[u8]) -> io::Result<()> {
+    let (stream, _) = listener.accept()?;
+    let mut request = BufReader::new(&stream);
+    let mut line = String::new();
+    while request.read_line(&mut line)? > 2 {
+        line.clear();
+    }
+
+    let mut stream = &stream;
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        ina::format::MIME_TYPE,
+        patch.len(),
+    )?;
+    stream.write_all(patch)
+}
+
+/// Reads the status line and headers of a response, returning its content type
+fn read_response_head<R>(response: &mut R) -> Result<String, Box<dyn Error>>
+where
+    R: BufRead,
+{
+    let mut status = String::new();
+    response.read_line(&mut status)?;
+    if status.split_whitespace().nth(1) != Some("200") {
+        return Err(format!("unexpected response: {}", status.trim_end()).into());
+    }
+
+    let mut content_type = String::new();
+    loop {
+        let mut line = String::new();
+        response.read_line(&mut line)?;
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':')
+            && name.eq_ignore_ascii_case("content-type")
+        {
+            content_type = value.trim().to_owned();
+        }
+    }
+
+    Ok(content_type)
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn example_runs() -> Result<(), Box<dyn std::error::Error>> {
+        super::main()
+    }
+}
@@ -0,0 +1,53 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Diffs arbitrary blobs and checks that applying the patch reproduces the new blob
+//!
+//! The input is an options byte and a little-endian `u16` giving the length of the old blob,
+//! followed by the old blob and then the new blob. Diffing must neither panic nor fail, since
+//! servers diff untrusted uploads, and every patch must round-trip exactly.
+
+#![no_main]
+
+use std::io::Cursor;
+
+use ina::{DiffConfig, OldBlob, TextMode};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Some((&options, data)) = data.split_first() else {
+        return;
+    };
+    let Some((old_len, data)) = data.split_first_chunk() else {
+        return;
+    };
+    let old_len = usize::from(u16::from_le_bytes(*old_len)).min(data.len());
+    let (old, new) = data.split_at(old_len);
+
+    let mut config = DiffConfig::new();
+    config
+        .compression_level(1)
+        .match_threshold(usize::from(options & 0x0f) + 1);
+    if options & 0x10 != 0 {
+        config.max_backward_seek(Some(u64::from(options >> 7) * 64));
+    }
+
+    // Windowed diffs don't support text mode, so the two are mutually exclusive
+    let mut patch = Vec::new();
+    if options & 0x20 != 0 {
+        config.diff_window_len(u64::from(options & 0x0f) * 128 + 1);
+        ina::diff_windowed(Cursor::new(old), Cursor::new(new), &mut patch, &config)
+            .expect("diffing failed");
+    } else {
+        if options & 0x40 != 0 {
+            config.text_mode(TextMode::Text);
+        }
+        ina::diff_with_config(&OldBlob::from_slice(old), new, &mut patch, &config)
+            .expect("diffing failed");
+    }
+
+    let mut patched = Vec::new();
+    ina::patch(Cursor::new(old), patch.as_slice(), &mut patched).expect("patching failed");
+    assert!(patched == new, "patch doesn't reproduce the new blob");
+});
@@ -0,0 +1,27 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+/// The maximum length in bytes of an annotation
+///
+/// Readers decompress the annotation while parsing the header, so its length is bounded to keep a
+/// malicious header from using excessive memory.
+pub(crate) const MAX_ANNOTATION_LEN: usize = 1 << 20;
+
+/// The zstd compression level annotations are compressed with
+#[cfg(feature = "diff")]
+const COMPRESSION_LEVEL: i32 = 19;
+
+/// Encodes `annotation` as the value of a header field
+#[cfg(feature = "diff")]
+pub(crate) fn encode_field(annotation: &str) -> Vec<u8> {
+    // Compressing into a Vec never fails
+    zstd::bulk::compress(annotation.as_bytes(), COMPRESSION_LEVEL).unwrap()
+}
+
+/// Decodes an annotation from the value of a header field
+#[cfg(feature = "patch")]
+pub(crate) fn decode_field(field: &[u8]) -> Option<String> {
+    let annotation = zstd::bulk::decompress(field, MAX_ANNOTATION_LEN).ok()?;
+    String::from_utf8(annotation).ok()
+}
@@ -0,0 +1,291 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use std::io::{self, ErrorKind, Read, Seek, SeekFrom};
+use std::ops::Range;
+
+use crc32fast::Hasher;
+#[cfg(feature = "patch")]
+use integer_encoding::VarInt;
+#[cfg(feature = "diff")]
+use integer_encoding::VarIntWriter;
+
+#[cfg(feature = "patch")]
+use crate::PatchError;
+
+/// The size of the buffer used to read regions of the old blob
+const BUF_SIZE: usize = 8192;
+
+/// How a patch records the old blob it was created against.
+///
+/// A patch applied to a different old blob than the one it was created against produces garbage.
+/// A base check records the length of the old blob and checksums of some of its regions in the
+/// patch, which [`Patcher`](crate::Patcher) verifies before producing any output, so applying a
+/// patch to the wrong old blob fails up front instead. The more of the old blob is covered, the
+/// more mistakes are caught, but the longer the check takes.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq, PartialOrd, Ord)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum BaseCheck {
+    /// The old blob isn't recorded
+    #[default]
+    None,
+    /// The entire old blob is checksummed, which catches any difference but requires reading all
+    /// of it before patching
+    Full,
+    /// `samples` evenly spaced regions of `sample_len` bytes each are checksummed, including the
+    /// first and last bytes of the old blob.
+    ///
+    /// This bounds the time the check takes regardless of the size of the old blob, which makes it
+    /// suitable for multi-gigabyte old blobs on slow storage. It catches wrong old blobs of the
+    /// same length only if they differ within a sampled region, which is the case for most
+    /// mistakes, such as picking a different version of a file, but not for small corruptions.
+    Sampled {
+        /// The number of regions to checksum
+        samples: u32,
+        /// The length of each region in bytes
+        sample_len: u32,
+    },
+}
+
+impl BaseCheck {
+    /// Returns a sampled base check with the default number and length of samples
+    pub const fn sampled() -> Self {
+        Self::Sampled {
+            samples: Self::DEFAULT_SAMPLES,
+            sample_len: Self::DEFAULT_SAMPLE_LEN,
+        }
+    }
+
+    /// The default number of regions checksummed by a sampled base check
+    pub const DEFAULT_SAMPLES: u32 = 64;
+
+    /// The default length of the regions checksummed by a sampled base check
+    pub const DEFAULT_SAMPLE_LEN: u32 = 4096;
+}
+
+/// The length and region checksums of the old blob recorded in a patch by a [`BaseCheck`]
+#[derive(Clone, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BaseDigest {
+    old_len: u64,
+    regions: Vec<Region>,
+}
+
+/// A region of the old blob and its CRC-32
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct Region {
+    offset: u64,
+    len: u64,
+    crc: u32,
+}
+
+impl BaseDigest {
+    /// Returns the length of the old blob
+    pub fn old_len(&self) -> u64 {
+        self.old_len
+    }
+
+    /// Returns the checksummed regions of the old blob in ascending order
+    pub fn regions(&self) -> impl Iterator<Item = Range<u64>> + '_ {
+        self.regions
+            .iter()
+            .map(|region| region.offset..region.offset + region.len)
+    }
+
+    /// Returns the number of bytes of the old blob covered by the checksummed regions
+    pub fn covered_len(&self) -> u64 {
+        let mut covered = 0;
+        let mut end = 0;
+        for region in self.regions() {
+            covered += region.end.saturating_sub(region.start.max(end));
+            end = end.max(region.end);
+        }
+
+        covered
+    }
+
+    /// Computes the digest of `old`, which must not include a sentinel, as described by `check`
+    #[cfg(feature = "diff")]
+    pub(crate) fn of(old: &[u8], check: BaseCheck) -> Option<Self> {
+        let old_len = old.len() as u64;
+        let regions = spans(old_len, check)?
+            .into_iter()
+            .map(|(offset, len)| Region {
+                offset,
+                len,
+                crc: crc32fast::hash(&old[offset as usize..(offset + len) as usize]),
+            })
+            .collect();
+
+        Some(Self { old_len, regions })
+    }
+
+    /// Computes the digest of `old` from its current position to its end as described by `check`,
+    /// leaving `old` at that position
+    ///
+    /// Unlike [`BaseDigest::of()`], this only reads the checksummed regions of `old`, so it works
+    /// with old blobs which don't fit in memory.
+    #[cfg(feature = "diff")]
+    pub(crate) fn of_reader<O>(old: &mut O, check: BaseCheck) -> io::Result<Option<Self>>
+    where
+        O: Read + Seek,
+    {
+        let start = old.stream_position()?;
+        let old_len = old.seek(SeekFrom::End(0))?.saturating_sub(start);
+        let Some(spans) = spans(old_len, check) else {
+            old.seek(SeekFrom::Start(start))?;
+            return Ok(None);
+        };
+
+        let mut regions = Vec::with_capacity(spans.len());
+        for (offset, len) in spans {
+            old.seek(SeekFrom::Start(start + offset))?;
+            let crc = read_crc(old, len)?;
+            regions.push(Region { offset, len, crc });
+        }
+        old.seek(SeekFrom::Start(start))?;
+
+        Ok(Some(Self { old_len, regions }))
+    }
+
+    /// Encodes the digest as a header field
+    ///
+    /// The field consists of the varint length of the old blob followed by the varint offset,
+    /// varint length, and little-endian CRC-32 of each region.
+    #[cfg(feature = "diff")]
+    pub(crate) fn encode_field(&self) -> Vec<u8> {
+        let mut field = Vec::new();
+        // Writing to a Vec never fails
+        field.write_varint(self.old_len).unwrap();
+        for region in &self.regions {
+            field.write_varint(region.offset).unwrap();
+            field.write_varint(region.len).unwrap();
+            field.extend_from_slice(&region.crc.to_le_bytes());
+        }
+
+        field
+    }
+
+    /// Decodes the digest from a header field, returning `None` if the field is invalid
+    #[cfg(feature = "patch")]
+    pub(crate) fn decode_field(mut field: &[u8]) -> Option<Self> {
+        let old_len = read_varint(&mut field)?;
+        let mut regions = Vec::new();
+        let mut end = 0;
+        while !field.is_empty() {
+            let offset = read_varint(&mut field)?;
+            let len = read_varint(&mut field)?;
+            let crc = u32::from_le_bytes(field.get(..4)?.try_into().ok()?);
+            field = &field[4..];
+
+            // Regions must be ascending and lie within the old blob
+            let region_end = offset.checked_add(len)?;
+            if offset < end || region_end > old_len {
+                return None;
+            }
+            end = region_end;
+            regions.push(Region { offset, len, crc });
+        }
+
+        Some(Self { old_len, regions })
+    }
+
+    /// Checks that `old`, starting at its current position, matches the digest, leaving `old` at
+    /// that position
+    #[cfg(feature = "patch")]
+    pub(crate) fn verify<O>(&self, old: &mut O) -> Result<(), PatchError>
+    where
+        O: Read + Seek,
+    {
+        let start = old.stream_position()?;
+        let old_len = old.seek(SeekFrom::End(0))?.saturating_sub(start);
+        if old_len != self.old_len {
+            old.seek(SeekFrom::Start(start))?;
+            return Err(PatchError::OldMismatch(None));
+        }
+
+        for region in &self.regions {
+            old.seek(SeekFrom::Start(start + region.offset))?;
+            if read_crc(old, region.len)? != region.crc {
+                old.seek(SeekFrom::Start(start))?;
+                return Err(PatchError::OldMismatch(Some(
+                    region.offset..region.offset + region.len,
+                )));
+            }
+        }
+        old.seek(SeekFrom::Start(start))?;
+
+        Ok(())
+    }
+}
+
+/// Returns the offset and length of each region of an old blob of `old_len` bytes checksummed by
+/// `check`, or `None` if it doesn't checksum any
+#[cfg(feature = "diff")]
+fn spans(old_len: u64, check: BaseCheck) -> Option<Vec<(u64, u64)>> {
+    let spans = match check {
+        BaseCheck::None => return None,
+        BaseCheck::Full => vec![(0, old_len)],
+        BaseCheck::Sampled {
+            samples,
+            sample_len,
+        } => {
+            let (samples, sample_len) = (u64::from(samples), u64::from(sample_len));
+            if samples.saturating_mul(sample_len) >= old_len {
+                vec![(0, old_len)]
+            } else if samples == 1 {
+                vec![(0, sample_len)]
+            } else {
+                let last_start = old_len - sample_len;
+                (0..samples)
+                    .map(|i| {
+                        let start = (u128::from(last_start) * u128::from(i)
+                            / u128::from(samples - 1)) as u64;
+                        (start, sample_len)
+                    })
+                    .collect()
+            }
+        }
+    };
+
+    Some(spans.into_iter().filter(|&(_, len)| len > 0).collect())
+}
+
+/// Computes the CRC-32 of the next `len` bytes of `reader`
+fn read_crc<R>(reader: &mut R, len: u64) -> io::Result<u32>
+where
+    R: Read,
+{
+    let mut buf = [0; BUF_SIZE];
+    let mut hasher = Hasher::new();
+    let mut left = len;
+    while left > 0 {
+        let len = left.min(BUF_SIZE as u64) as usize;
+        match reader.read(&mut buf[..len]) {
+            Ok(0) => return Err(ErrorKind::UnexpectedEof.into()),
+            Ok(read) => {
+                hasher.update(&buf[..read]);
+                left -= read as u64;
+            }
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(hasher.finalize())
+}
+
+/// Reads a varint from the beginning of `field`, advancing it past the varint
+#[cfg(feature = "patch")]
+fn read_varint(field: &mut &[u8]) -> Option<u64> {
+    let (value, len) = u64::decode_var(field)?;
+    *field = &field[len..];
+
+    Some(value)
+}
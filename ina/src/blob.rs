@@ -0,0 +1,402 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use std::{
+    cmp,
+    fs::File,
+    io::{self, ErrorKind, Read, Seek, SeekFrom},
+    ops::Range,
+};
+
+use integer_encoding::VarIntReader;
+
+use crate::{PatchError, PatchMetadata, payload, read_header};
+
+/// The longest run of zero difference bytes stored inline rather than starting a new run
+///
+/// Storing short runs of zeros is cheaper than the bookkeeping for an extra run.
+const MAX_ZERO_GAP: u64 = 16;
+
+/// The size of the buffer used to read add fields while indexing a patch
+const ADD_BUF_SIZE: usize = 8192;
+
+/// A source of bytes which supports reading at arbitrary positions.
+///
+/// Unlike [`Read`], reading at a position doesn't require mutable access, so a single `ReadAt` can
+/// be shared between multiple readers.
+pub trait ReadAt {
+    /// Reads bytes starting at `pos` into `buf`, returning the number of bytes read
+    ///
+    /// A return value of 0 indicates that `pos` is at or past the end of the source or that `buf`
+    /// is empty.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if reading fails.
+    fn read_at(&self, pos: u64, buf: &mut [u8]) -> io::Result<usize>;
+
+    /// Reads exactly enough bytes starting at `pos` to fill `buf`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error of kind [`ErrorKind::UnexpectedEof`] if the source ends before `buf` is
+    /// filled, or any other error returned by [`read_at()`](ReadAt::read_at).
+    fn read_exact_at(&self, mut pos: u64, mut buf: &mut [u8]) -> io::Result<()> {
+        while !buf.is_empty() {
+            match self.read_at(pos, buf) {
+                Ok(0) => return Err(ErrorKind::UnexpectedEof.into()),
+                Ok(read) => {
+                    pos += read as u64;
+                    buf = &mut buf[read..];
+                }
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<T> ReadAt for &T
+where
+    T: ReadAt + ?Sized,
+{
+    fn read_at(&self, pos: u64, buf: &mut [u8]) -> io::Result<usize> {
+        (**self).read_at(pos, buf)
+    }
+}
+
+impl ReadAt for [u8] {
+    fn read_at(&self, pos: u64, buf: &mut [u8]) -> io::Result<usize> {
+        let start = cmp::min(pos, self.len() as u64) as usize;
+        let src = &self[start..];
+        let len = cmp::min(src.len(), buf.len());
+        buf[..len].copy_from_slice(&src[..len]);
+
+        Ok(len)
+    }
+}
+
+impl ReadAt for Vec<u8> {
+    fn read_at(&self, pos: u64, buf: &mut [u8]) -> io::Result<usize> {
+        self.as_slice().read_at(pos, buf)
+    }
+}
+
+#[cfg(unix)]
+impl ReadAt for File {
+    fn read_at(&self, pos: u64, buf: &mut [u8]) -> io::Result<usize> {
+        std::os::unix::fs::FileExt::read_at(self, buf, pos)
+    }
+}
+
+#[cfg(windows)]
+impl ReadAt for File {
+    fn read_at(&self, pos: u64, buf: &mut [u8]) -> io::Result<usize> {
+        std::os::windows::fs::FileExt::seek_read(self, buf, pos)
+    }
+}
+
+/// The new blob described by a patch, read on demand from the old blob and the patch.
+///
+/// Creating a `PatchedBlob` indexes the control records of the patch once. Afterward, any region
+/// of the new blob can be read through [`ReadAt`] or through [`Read`] and [`Seek`] without
+/// reconstructing the regions before it. This is useful for reading small parts of a large new
+/// blob, such as the central directory at the end of a ZIP archive.
+///
+/// The index holds the patch's copy fields and the nonzero bytes of its add fields in memory, but
+/// bytes taken from the old blob are only read when requested.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use ina::{OldBlob, PatchedBlob, ReadAt};
+///
+/// let old = b"Hello, world!".to_vec();
+/// let new = b"Hello, patched world!";
+///
+/// let mut patch = Vec::new();
+/// ina::diff(&OldBlob::from_slice(&old), new, &mut patch)?;
+///
+/// let blob = PatchedBlob::new(old, patch.as_slice())?;
+/// assert_eq!(blob.len(), new.len() as u64);
+///
+/// let mut word = [0; 7];
+/// blob.read_exact_at(7, &mut word)?;
+/// assert_eq!(&word, b"patched");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct PatchedBlob<O>
+where
+    O: ReadAt,
+{
+    old: O,
+    metadata: PatchMetadata,
+    segments: Vec<Segment>,
+    runs: Vec<DiffRun>,
+    data: Vec<u8>,
+    len: u64,
+    pos: u64,
+}
+
+/// A contiguous region of the new blob produced by a single add or copy field
+#[derive(Debug)]
+struct Segment {
+    new_start: u64,
+    len: u64,
+    source: Source,
+}
+
+#[derive(Debug)]
+enum Source {
+    /// Bytes from the old blob starting at `old_pos` with the difference runs `runs` added to them
+    Old { old_pos: u64, runs: Range<usize> },
+    /// Bytes stored verbatim in the index data starting at `data_start`
+    Literal { data_start: usize },
+}
+
+/// A run of difference bytes in an add field, stored in the index data starting at `data_start`
+#[derive(Debug)]
+struct DiffRun {
+    offset: u64,
+    data_start: usize,
+    len: u64,
+}
+
+impl<O> PatchedBlob<O>
+where
+    O: ReadAt,
+{
+    /// Creates a new `PatchedBlob` by indexing `patch`, which must have been created against `old`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an I/O error occurs while reading the patch or if the patch is invalid.
+    pub fn new<P>(old: O, mut patch: P) -> Result<Self, PatchError>
+    where
+        P: Read,
+    {
+        let metadata = read_header(&mut patch)?;
+        let mut payload = payload::decoder(patch, &metadata)?;
+
+        let mut blob = Self {
+            old,
+            metadata,
+            segments: Vec::new(),
+            runs: Vec::new(),
+            data: Vec::new(),
+            len: 0,
+            pos: 0,
+        };
+        let mut old_pos = 0u64;
+
+        loop {
+            let add_len: u64 = match payload.read_varint() {
+                Ok(add_len) => add_len,
+                Err(e) if e.kind() == ErrorKind::UnexpectedEof => break,
+                Err(e) => return Err(e.into()),
+            };
+            blob.index_add(&mut payload, old_pos, add_len)?;
+            old_pos = old_pos.checked_add(add_len).ok_or_else(invalid_control)?;
+
+            let copy_len: u64 = payload.read_varint()?;
+            blob.index_copy(&mut payload, copy_len)?;
+
+            let seek: i64 = payload.read_varint()?;
+            old_pos = old_pos
+                .checked_add_signed(seek)
+                .ok_or_else(invalid_control)?;
+        }
+
+        Ok(blob)
+    }
+
+    /// Returns the metadata of the patch
+    pub fn metadata(&self) -> &PatchMetadata {
+        &self.metadata
+    }
+
+    /// Returns the length of the new blob
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Returns `true` if the new blob is empty
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Consumes the `PatchedBlob`, returning the old blob
+    pub fn into_inner(self) -> O {
+        self.old
+    }
+
+    fn index_add<R>(&mut self, payload: &mut R, old_pos: u64, len: u64) -> io::Result<()>
+    where
+        R: Read,
+    {
+        let runs_start = self.runs.len();
+        let mut buf = [0; ADD_BUF_SIZE];
+        let mut offset = 0;
+
+        while offset < len {
+            let chunk_len = cmp::min(len - offset, buf.len() as u64) as usize;
+            let chunk = &mut buf[..chunk_len];
+            payload.read_exact(chunk)?;
+
+            for (i, &byte) in chunk.iter().enumerate() {
+                if byte == 0 {
+                    continue;
+                }
+
+                let byte_offset = offset + i as u64;
+                match self.runs[runs_start..].last_mut() {
+                    // Extend the previous run if this byte is close enough to it. Runs are always
+                    // appended to the end of the index data, so the last run ends there.
+                    Some(run) if byte_offset - (run.offset + run.len) <= MAX_ZERO_GAP => {
+                        let gap = byte_offset - (run.offset + run.len);
+                        self.data.resize(self.data.len() + gap as usize, 0);
+                        self.data.push(byte);
+                        run.len += gap + 1;
+                    }
+                    _ => {
+                        self.runs.push(DiffRun {
+                            offset: byte_offset,
+                            data_start: self.data.len(),
+                            len: 1,
+                        });
+                        self.data.push(byte);
+                    }
+                }
+            }
+
+            offset += chunk_len as u64;
+        }
+
+        self.push_segment(
+            len,
+            Source::Old {
+                old_pos,
+                runs: runs_start..self.runs.len(),
+            },
+        )
+    }
+
+    fn index_copy<R>(&mut self, payload: &mut R, len: u64) -> io::Result<()>
+    where
+        R: Read,
+    {
+        let data_start = self.data.len();
+        // Don't trust `len` for the allocation size
+        payload.take(len).read_to_end(&mut self.data)?;
+        if ((self.data.len() - data_start) as u64) < len {
+            return Err(ErrorKind::UnexpectedEof.into());
+        }
+
+        self.push_segment(len, Source::Literal { data_start })
+    }
+
+    fn push_segment(&mut self, len: u64, source: Source) -> io::Result<()> {
+        if len == 0 {
+            return Ok(());
+        }
+
+        self.segments.push(Segment {
+            new_start: self.len,
+            len,
+            source,
+        });
+        self.len = self.len.checked_add(len).ok_or_else(invalid_control)?;
+
+        Ok(())
+    }
+}
+
+impl<O> ReadAt for PatchedBlob<O>
+where
+    O: ReadAt,
+{
+    fn read_at(&self, pos: u64, buf: &mut [u8]) -> io::Result<usize> {
+        if pos >= self.len || buf.is_empty() {
+            return Ok(0);
+        }
+
+        let segment = &self.segments[self
+            .segments
+            .partition_point(|s| s.new_start + s.len <= pos)];
+        let offset = pos - segment.new_start;
+        let len = cmp::min(buf.len() as u64, segment.len - offset);
+        let out = &mut buf[..len as usize];
+
+        match &segment.source {
+            Source::Literal { data_start } => {
+                let start = data_start + offset as usize;
+                out.copy_from_slice(&self.data[start..start + out.len()]);
+            }
+            Source::Old { old_pos, runs } => {
+                self.old.read_exact_at(old_pos + offset, out)?;
+
+                let runs = &self.runs[runs.clone()];
+                let first = runs.partition_point(|r| r.offset + r.len <= offset);
+                for run in runs[first..].iter().take_while(|r| r.offset < offset + len) {
+                    let start = cmp::max(run.offset, offset);
+                    let end = cmp::min(run.offset + run.len, offset + len);
+                    let diff_start = run.data_start + (start - run.offset) as usize;
+                    let diff = &self.data[diff_start..diff_start + (end - start) as usize];
+                    let out = &mut out[(start - offset) as usize..(end - offset) as usize];
+
+                    out.iter_mut()
+                        .zip(diff)
+                        .for_each(|(o, d)| *o = o.wrapping_add(*d));
+                }
+            }
+        }
+
+        Ok(out.len())
+    }
+}
+
+impl<O> Read for PatchedBlob<O>
+where
+    O: ReadAt,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.read_at(self.pos, buf)?;
+        self.pos += read as u64;
+
+        Ok(read)
+    }
+}
+
+impl<O> Seek for PatchedBlob<O>
+where
+    O: ReadAt,
+{
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let new_pos = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => self.len.checked_add_signed(offset),
+            SeekFrom::Current(offset) => self.pos.checked_add_signed(offset),
+        };
+
+        match new_pos {
+            Some(new_pos) => {
+                self.pos = new_pos;
+                Ok(new_pos)
+            }
+            None => Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )),
+        }
+    }
+}
+
+fn invalid_control() -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, "control record is out of range")
+}
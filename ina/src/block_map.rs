@@ -0,0 +1,243 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+#[cfg(feature = "diff")]
+use std::io::{self, ErrorKind, Read, Seek, SeekFrom};
+use std::ops::Range;
+
+#[cfg(feature = "patch")]
+use integer_encoding::VarInt;
+#[cfg(feature = "diff")]
+use integer_encoding::VarIntWriter;
+
+/// The blocks of a new blob which differ from the old blob, as recorded in a patch.
+///
+/// Updaters which operate on block devices rather than files, such as A/B updaters writing a new
+/// partition image over a copy of the old one, can skip writing blocks which the patch leaves
+/// unchanged. A block map divides the new blob into blocks of a fixed size, the last of which may
+/// be shorter, and records which of them differ from the bytes at the same offset of the old blob.
+/// A block also counts as changed if the old blob ends within it. Blocks of the old blob past the
+/// end of the new blob aren't described.
+///
+/// Block maps are recorded with [`DiffConfig::block_map()`] and read with
+/// [`PatchMetadata::block_map()`]. [`Patcher::block_updates()`] uses them to produce only the
+/// changed blocks of the new blob.
+///
+/// [`DiffConfig::block_map()`]: crate::DiffConfig::block_map
+/// [`PatchMetadata::block_map()`]: crate::PatchMetadata::block_map
+/// [`Patcher::block_updates()`]: crate::Patcher::block_updates
+#[derive(Clone, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BlockMap {
+    block_size: u32,
+    new_len: u64,
+    /// The start and end indices of the ranges of changed blocks in ascending order, none of them
+    /// empty or adjacent
+    changed: Vec<(u64, u64)>,
+}
+
+impl BlockMap {
+    /// The block size of block maps if none is given, which is the block size of most filesystems
+    /// and flash storage
+    pub const DEFAULT_BLOCK_SIZE: u32 = 4096;
+
+    /// The smallest block size of block maps, which is the sector size of most block devices
+    pub const MIN_BLOCK_SIZE: u32 = 512;
+
+    /// The largest block size of block maps
+    pub const MAX_BLOCK_SIZE: u32 = 1 << 20;
+
+    /// Returns the size of the blocks in bytes
+    pub fn block_size(&self) -> u32 {
+        self.block_size
+    }
+
+    /// Returns the length of the new blob
+    pub fn new_len(&self) -> u64 {
+        self.new_len
+    }
+
+    /// Returns the number of blocks of the new blob
+    pub fn block_count(&self) -> u64 {
+        self.new_len.div_ceil(u64::from(self.block_size))
+    }
+
+    /// Returns the ranges of indices of the changed blocks in ascending order
+    ///
+    /// Adjacent changed blocks are merged into a single range, so no two ranges touch.
+    pub fn changed(&self) -> impl Iterator<Item = Range<u64>> + '_ {
+        self.changed.iter().map(|&(start, end)| start..end)
+    }
+
+    /// Returns the number of changed blocks
+    pub fn changed_count(&self) -> u64 {
+        self.changed().map(|range| range.end - range.start).sum()
+    }
+
+    /// Returns whether the block with the given index is changed
+    pub fn is_changed(&self, block: u64) -> bool {
+        let candidate = self.changed.partition_point(|&(_, end)| end <= block);
+        self.changed
+            .get(candidate)
+            .is_some_and(|&(start, _)| start <= block)
+    }
+
+    /// Returns whether `block_size` is supported, i.e., a power of two between
+    /// [`Self::MIN_BLOCK_SIZE`] and [`Self::MAX_BLOCK_SIZE`]
+    pub(crate) fn is_valid_block_size(block_size: u32) -> bool {
+        block_size.is_power_of_two()
+            && (Self::MIN_BLOCK_SIZE..=Self::MAX_BLOCK_SIZE).contains(&block_size)
+    }
+
+    /// Computes the block map of `new` against `old`, which must not include a sentinel
+    #[cfg(feature = "diff")]
+    pub(crate) fn of(old: &[u8], new: &[u8], block_size: u32) -> Self {
+        let mut changed = Vec::new();
+        for (index, block) in new.chunks(block_size as usize).enumerate() {
+            let start = index * block_size as usize;
+            if old.get(start..start + block.len()) != Some(block) {
+                push_changed(&mut changed, index as u64);
+            }
+        }
+
+        Self {
+            block_size,
+            new_len: new.len() as u64,
+            changed,
+        }
+    }
+
+    /// Computes the block map of `new` against `old` from their current positions to their ends,
+    /// leaving both at those positions
+    ///
+    /// Unlike [`BlockMap::of()`], this only holds one block of each blob in memory at a time.
+    #[cfg(feature = "diff")]
+    pub(crate) fn of_readers<O, N>(old: &mut O, new: &mut N, block_size: u32) -> io::Result<Self>
+    where
+        O: Read + Seek,
+        N: Read + Seek,
+    {
+        let old_start = old.stream_position()?;
+        let new_start = new.stream_position()?;
+
+        let mut changed = Vec::new();
+        let mut new_block = vec![0; block_size as usize];
+        let mut old_block = vec![0; block_size as usize];
+        let mut new_len = 0;
+        for index in 0.. {
+            let len = read_block(new, &mut new_block)?;
+            if len == 0 {
+                break;
+            }
+            new_len += len as u64;
+
+            let old_len = read_block(old, &mut old_block[..len])?;
+            if old_len < len || old_block[..len] != new_block[..len] {
+                push_changed(&mut changed, index);
+            }
+            if len < new_block.len() {
+                break;
+            }
+        }
+
+        old.seek(SeekFrom::Start(old_start))?;
+        new.seek(SeekFrom::Start(new_start))?;
+
+        Ok(Self {
+            block_size,
+            new_len,
+            changed,
+        })
+    }
+
+    /// Encodes the block map as a header field
+    ///
+    /// The field consists of the varint block size and the varint length of the new blob, followed
+    /// by the varint number of unchanged blocks preceding each range of changed blocks and the
+    /// varint number of blocks in that range.
+    #[cfg(feature = "diff")]
+    pub(crate) fn encode_field(&self) -> Vec<u8> {
+        let mut field = Vec::new();
+        // Writing to a Vec never fails
+        field.write_varint(self.block_size).unwrap();
+        field.write_varint(self.new_len).unwrap();
+        let mut end = 0;
+        for &(start, range_end) in &self.changed {
+            field.write_varint(start - end).unwrap();
+            field.write_varint(range_end - start).unwrap();
+            end = range_end;
+        }
+
+        field
+    }
+
+    /// Decodes the block map from a header field, returning `None` if the field is invalid
+    #[cfg(feature = "patch")]
+    pub(crate) fn decode_field(mut field: &[u8]) -> Option<Self> {
+        let block_size = u32::try_from(read_varint(&mut field)?).ok()?;
+        let new_len = read_varint(&mut field)?;
+        if !Self::is_valid_block_size(block_size) {
+            return None;
+        }
+
+        let mut map = Self {
+            block_size,
+            new_len,
+            changed: Vec::new(),
+        };
+        let mut end: u64 = 0;
+        while !field.is_empty() {
+            let gap = read_varint(&mut field)?;
+            let len = read_varint(&mut field)?;
+
+            // Ranges must be nonempty, separated, and lie within the new blob
+            let start = end.checked_add(gap)?;
+            let range_end = start.checked_add(len)?;
+            if len == 0 || (gap == 0 && end > 0) || range_end > map.block_count() {
+                return None;
+            }
+            map.changed.push((start, range_end));
+            end = range_end;
+        }
+
+        Some(map)
+    }
+}
+
+/// Marks the block with index `block`, which follows every block already marked, as changed
+#[cfg(feature = "diff")]
+fn push_changed(changed: &mut Vec<(u64, u64)>, block: u64) {
+    match changed.last_mut() {
+        Some((_, end)) if *end == block => *end += 1,
+        _ => changed.push((block, block + 1)),
+    }
+}
+
+/// Fills `block` from `reader` as far as possible, returning the number of bytes read, which is
+/// only less than the length of `block` at the end of `reader`
+#[cfg(feature = "diff")]
+fn read_block<R>(reader: &mut R, block: &mut [u8]) -> io::Result<usize>
+where
+    R: Read,
+{
+    let mut len = 0;
+    while len < block.len() {
+        match reader.read(&mut block[len..]) {
+            Ok(0) => break,
+            Ok(read) => len += read,
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(len)
+}
+
+#[cfg(feature = "patch")]
+fn read_varint(field: &mut &[u8]) -> Option<u64> {
+    let (value, len) = u64::decode_var(field)?;
+    *field = &field[len..];
+
+    Some(value)
+}
@@ -2,18 +2,125 @@
 //
 // SPDX-License-Identifier: Apache-2.0
 
+use std::borrow::Cow;
+
 use sufsort::SuffixArray;
 
-const NON_MATCHING_BYTES_THRESHOLD: usize = 8;
+#[cfg(feature = "unstable")]
+use crate::OldBlob;
+
+/// The length of old blobs from which on their index is built with a prefilter, below which the
+/// fixed cost of building it outweighs the cache misses it saves
+const PREFILTER_MIN_LEN: usize = 4 << 20;
+
+/// Builds the index of `old`, which must end with the sentinel
+pub(crate) fn index(old: &[u8]) -> SuffixArray<'_> {
+    with_prefilter_if_large(SuffixArray::new(old))
+}
+
+/// Adds a prefilter to `index` if its old blob is large enough to benefit from one
+pub(crate) fn with_prefilter_if_large(index: SuffixArray<'_>) -> SuffixArray<'_> {
+    if index.len() >= PREFILTER_MIN_LEN {
+        index.with_prefilter()
+    } else {
+        index
+    }
+}
 
-#[derive(Clone, Copy)]
-pub(crate) struct Match {
+/// A match between regions of an old and new blob.
+///
+/// A match describes how to reconstruct the region of the new blob from `add_new_pos` up to
+/// `copy_end`: its first `add_len` bytes are approximately equal to the bytes of the old blob
+/// starting at `add_old_pos` and are stored as differences against them, while the remaining bytes
+/// have no counterpart in the old blob and are stored verbatim.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
+pub struct Match {
     add_old_pos: usize,
     add_new_pos: usize,
     add_len: usize,
     copy_end: usize,
 }
 
+impl Match {
+    /// Creates a new match from its parts
+    pub fn new(add_old_pos: usize, add_new_pos: usize, add_len: usize, copy_end: usize) -> Self {
+        Self {
+            add_old_pos,
+            add_new_pos,
+            add_len,
+            copy_end,
+        }
+    }
+
+    /// Returns the position in the old blob of the approximately matching region
+    pub fn add_old_pos(&self) -> usize {
+        self.add_old_pos
+    }
+
+    /// Returns the position in the new blob of the approximately matching region
+    pub fn add_new_pos(&self) -> usize {
+        self.add_new_pos
+    }
+
+    /// Returns the length of the approximately matching region
+    pub fn add_len(&self) -> usize {
+        self.add_len
+    }
+
+    /// Returns the position in the new blob where the verbatim region following the approximately
+    /// matching region ends
+    pub fn copy_end(&self) -> usize {
+        self.copy_end
+    }
+}
+
+/// An algorithm which finds the matches a patch is encoded from.
+///
+/// Implementing this trait allows plugging alternative matching algorithms into
+/// [`diff_with_matcher()`](crate::unstable::v0::diff_with_matcher).
+#[cfg(feature = "unstable")]
+pub trait Matcher {
+    /// Returns the matches between `old` and `new`
+    ///
+    /// The matches must be ordered and contiguous in the new blob and cover all of it, as described
+    /// by [`diff_with_matches()`](crate::unstable::v0::diff_with_matches).
+    fn find_matches(&mut self, old: &OldBlob, new: &[u8]) -> Vec<Match>;
+}
+
+/// The matching algorithm of bsdiff, which is used by [`diff()`](crate::diff) and friends.
+#[cfg(feature = "unstable")]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct BsdiffMatcher {
+    match_threshold: usize,
+}
+
+#[cfg(feature = "unstable")]
+impl BsdiffMatcher {
+    /// Creates a matcher with the given mismatch threshold
+    ///
+    /// See [`DiffConfig::match_threshold()`](crate::DiffConfig::match_threshold) for the meaning
+    /// of the threshold.
+    pub fn new(match_threshold: usize) -> Self {
+        Self {
+            match_threshold: match_threshold.max(1),
+        }
+    }
+}
+
+#[cfg(feature = "unstable")]
+impl Default for BsdiffMatcher {
+    fn default() -> Self {
+        Self::new(crate::DiffConfig::DEFAULT_MATCH_THRESHOLD)
+    }
+}
+
+#[cfg(feature = "unstable")]
+impl Matcher for BsdiffMatcher {
+    fn find_matches(&mut self, old: &OldBlob, new: &[u8]) -> Vec<Match> {
+        MatchMaker::new(old.with_sentinel(), new, self.match_threshold).collect()
+    }
+}
+
 impl Match {
     fn copy_pos(&self) -> usize {
         self.add_new_pos + self.add_len
@@ -29,12 +136,31 @@ pub(crate) struct MatchMaker<'a> {
     last_offset: isize,
     old: &'a [u8],
     new: &'a [u8],
-    old_index: SuffixArray<'a>,
+    old_index: Cow<'a, SuffixArray<'a>>,
+    match_threshold: usize,
+    anchors: &'a [(usize, usize)],
 }
 
 impl<'a> MatchMaker<'a> {
-    fn new(old: &'a [u8], new: &'a [u8]) -> Self {
-        let old_index = SuffixArray::new(old);
+    pub(crate) fn new(old: &'a [u8], new: &'a [u8], match_threshold: usize) -> Self {
+        Self::from_index(Cow::Owned(index(old)), new, match_threshold)
+    }
+
+    /// Creates a `MatchMaker` which searches for matches using an existing index of the old blob
+    pub(crate) fn with_index(
+        old_index: &'a SuffixArray<'a>,
+        new: &'a [u8],
+        match_threshold: usize,
+    ) -> Self {
+        Self::from_index(Cow::Borrowed(old_index), new, match_threshold)
+    }
+
+    fn from_index(
+        old_index: Cow<'a, SuffixArray<'a>>,
+        new: &'a [u8],
+        match_threshold: usize,
+    ) -> Self {
+        let old = old_index.data();
 
         Self {
             scan: 0,
@@ -46,8 +172,36 @@ impl<'a> MatchMaker<'a> {
             old,
             new,
             old_index,
+            match_threshold,
+            anchors: &[],
         }
     }
+
+    /// Seeds the offset between the old and new blob at each of `anchors`, which are pairs of
+    /// corresponding old and new positions ordered by their new position
+    pub(crate) fn with_anchors(mut self, anchors: &'a [(usize, usize)]) -> Self {
+        self.anchors = anchors;
+        self
+    }
+
+    /// Consumes the anchors the scan has reached, returning the old position corresponding to the
+    /// scan position according to the last of them if it's in bounds and differs from the current
+    /// offset
+    fn reached_anchor(&mut self) -> Option<usize> {
+        let mut reached = None;
+        while let Some((&(old_pos, new_pos), rest)) = self.anchors.split_first()
+            && new_pos <= self.scan
+        {
+            self.anchors = rest;
+            // Exclude the sentinel
+            reached = old_pos
+                .checked_add(self.scan - new_pos)
+                .filter(|&pos| pos + 1 < self.old.len())
+                .filter(|&pos| pos as isize - self.scan as isize != self.last_offset);
+        }
+
+        reached
+    }
 }
 
 impl<'a> Iterator for MatchMaker<'a> {
@@ -58,7 +212,15 @@ impl<'a> Iterator for MatchMaker<'a> {
             let mut old_score = 0;
             self.scan += self.len;
             let mut scsc = self.scan;
+            let mut anchored = false;
             while self.scan < self.new.len() {
+                if let Some(pos) = self.reached_anchor() {
+                    // End the current match here so the next one starts at the anchor's offset
+                    (self.pos, self.len) = (pos, 0);
+                    anchored = true;
+                    break;
+                }
+
                 (self.pos, self.len) = self
                     .old_index
                     .longest_match(&self.new[self.scan..])
@@ -75,7 +237,7 @@ impl<'a> Iterator for MatchMaker<'a> {
                 }
 
                 if (self.len == old_score && self.len != 0)
-                    || self.len >= old_score + NON_MATCHING_BYTES_THRESHOLD
+                    || self.len >= old_score + self.match_threshold
                 {
                     break;
                 }
@@ -90,12 +252,13 @@ impl<'a> Iterator for MatchMaker<'a> {
                 self.scan += 1;
             }
 
-            if self.len != old_score || self.scan == self.new.len() {
+            if anchored || self.len != old_score || self.scan == self.new.len() {
                 let mut s = 0;
                 let mut s_f = 0;
                 let mut len_forward: usize = 0;
                 let mut i = 0;
-                while self.last_scan + i < self.scan && self.last_pos + i < self.old.len() {
+                // Exclude the sentinel, which isn't part of the old blob the patch is applied to
+                while self.last_scan + i < self.scan && self.last_pos + i + 1 < self.old.len() {
                     if self.old[self.last_pos + i] == self.new[self.last_scan + i] {
                         s += 1;
                     }
@@ -202,10 +365,11 @@ where
     new: &'a [u8],
 }
 
-impl<'a> ControlProducer<'a, MatchMaker<'a>> {
-    pub(crate) fn new(old: &'a [u8], new: &'a [u8]) -> Self {
-        let match_iter = MatchMaker::new(old, new);
-
+impl<'a, I> ControlProducer<'a, I>
+where
+    I: Iterator<Item = Match>,
+{
+    pub(crate) fn from_matches(old: &'a [u8], new: &'a [u8], match_iter: I) -> Self {
         Self {
             match_iter,
             prev_match: None,
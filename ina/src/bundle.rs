@@ -0,0 +1,553 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+#[cfg(feature = "diff")]
+use std::io::Write;
+use std::{
+    fmt::{self, Display, Formatter},
+    io::{self, ErrorKind, Read, Take},
+};
+
+#[cfg(feature = "diff")]
+use byteorder::WriteBytesExt;
+use byteorder::{LittleEndian, ReadBytesExt};
+use integer_encoding::VarIntReader;
+#[cfg(feature = "diff")]
+use integer_encoding::VarIntWriter;
+
+use crate::{DIGEST_LEN, DigestAlgorithm, PatchError, PatchMetadata, patch, read_header};
+#[cfg(feature = "diff")]
+use crate::{DiffConfig, DiffStats, OldBlob};
+
+/// The magic number bundles begin with, which is distinct from that of patches
+const BUNDLE_MAGIC: u32 = 0x62616e69;
+/// The major version of bundles whose digests are BLAKE3 digests
+const BUNDLE_VERSION_MAJOR: u16 = 1;
+/// The major version of bundles whose header records the algorithm of their digests, which is only
+/// used for algorithms other than BLAKE3 so that older readers can still read BLAKE3 bundles
+const BUNDLE_VERSION_MAJOR_DIGEST: u16 = 2;
+#[cfg(feature = "diff")]
+const BUNDLE_VERSION_MINOR: u16 = 0;
+
+// Tags of the entries of a bundle
+const TAG_END: u8 = 0;
+const TAG_ADDED: u8 = 1;
+const TAG_MODIFIED: u8 = 2;
+const TAG_REMOVED: u8 = 3;
+const TAG_UNCHANGED: u8 = 4;
+const TAG_SYMLINK: u8 = 5;
+
+/// The maximum length of an entry path in bytes
+const MAX_PATH_LEN: u64 = 4096;
+
+/// Returns whether `data`, the beginning of a file, is the beginning of a bundle rather than of a
+/// patch
+///
+/// Only the magic number is checked, so `data` needs to be at least 4 bytes long.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> std::io::Result<()> {
+/// use ina::BundleWriter;
+///
+/// let bundle = BundleWriter::new(Vec::new())?.finish()?;
+/// assert!(ina::is_bundle(&bundle));
+///
+/// let mut patch = Vec::new();
+/// ina::diff(&ina::OldBlob::from_slice(b"Hello"), b"Hero", &mut patch)?;
+/// assert!(!ina::is_bundle(&patch));
+/// # Ok(())
+/// # }
+/// ```
+pub fn is_bundle(data: &[u8]) -> bool {
+    data.get(..4) == Some(&BUNDLE_MAGIC.to_le_bytes())
+}
+
+/// How a file changed between the old and new directory of a bundle
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
+pub enum BundleEntryKind {
+    /// The file only exists in the new directory. Its patch applies to an empty old blob.
+    Added,
+    /// The file exists in both directories with different contents. Its patch applies to the old
+    /// file.
+    Modified,
+    /// The file only exists in the old directory
+    Removed,
+    /// The file exists in both directories with the same contents. It has no patch.
+    Unchanged,
+    /// The file is a symbolic link in the new directory, whose target is given by
+    /// [`BundleEntry::symlink_target()`]. It has no patch.
+    Symlink,
+}
+
+impl Display for BundleEntryKind {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let name = match self {
+            Self::Added => "added",
+            Self::Modified => "modified",
+            Self::Removed => "removed",
+            Self::Unchanged => "unchanged",
+            Self::Symlink => "symlink",
+        };
+
+        f.write_str(name)
+    }
+}
+
+/// A file described by a bundle.
+///
+/// Paths are relative to the old and new directories, use `/` as the separator, and consist only
+/// of normal components, i.e., they can't escape the directories they're resolved against.
+#[derive(Clone, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
+pub struct BundleEntry {
+    path: String,
+    kind: BundleEntryKind,
+    new_len: u64,
+    new_digest: Option<[u8; DIGEST_LEN]>,
+    patch_len: u64,
+    symlink_target: Option<String>,
+}
+
+impl BundleEntry {
+    /// Returns the path of the file
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Returns how the file changed
+    pub fn kind(&self) -> BundleEntryKind {
+        self.kind
+    }
+
+    /// Returns the length of the new file, which is 0 for removed files and symbolic links
+    pub fn new_len(&self) -> u64 {
+        self.new_len
+    }
+
+    /// Returns the digest of the new file, unless the file was removed or is a symbolic link
+    ///
+    /// The digest is computed with the algorithm given by [`BundleReader::digest_algorithm()`].
+    pub fn new_digest(&self) -> Option<&[u8; DIGEST_LEN]> {
+        self.new_digest.as_ref()
+    }
+
+    /// Returns the length of the entry's patch, which is 0 for removed and unchanged files and
+    /// symbolic links
+    pub fn patch_len(&self) -> u64 {
+        self.patch_len
+    }
+
+    /// Returns the target of the symbolic link, if the file is one
+    ///
+    /// Targets are relative, `/`-separated paths which resolve to a path inside the new directory
+    /// when followed from the directory containing the link.
+    pub fn symlink_target(&self) -> Option<&str> {
+        self.symlink_target.as_deref()
+    }
+}
+
+/// A reader for bundles, which describe how to update a directory of files.
+///
+/// A bundle is a sequence of entries, one for each file in the old or new directory. Entries of
+/// added and modified files carry a regular patch, which can be applied with a
+/// [`Patcher`](crate::Patcher) reading from [`BundleReader::patch()`], and the length and digest of
+/// the new file so that the result can be checked before it's put in place.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use std::io::Cursor;
+/// use ina::{BundleEntryKind, BundleReader, BundleWriter, DiffConfig};
+///
+/// let mut writer = BundleWriter::new(Vec::new())?;
+/// writer.add("bin/app", Some(b"version 1"), b"version 2", &DiffConfig::new())?;
+/// writer.add("README", None, b"Hello", &DiffConfig::new())?;
+/// let bundle = writer.finish()?;
+///
+/// let mut reader = BundleReader::new(bundle.as_slice())?;
+/// let old = b"version 1";
+/// while let Some(entry) = reader.next_entry()? {
+///     if entry.path() == "bin/app" {
+///         assert_eq!(entry.kind(), BundleEntryKind::Modified);
+///         let mut new = Vec::new();
+///         ina::patch(Cursor::new(old), reader.patch(), &mut new)?;
+///         assert_eq!(new, b"version 2");
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct BundleReader<R>
+where
+    R: Read,
+{
+    inner: Take<R>,
+    digest_algorithm: DigestAlgorithm,
+    finished: bool,
+}
+
+impl<R> BundleReader<R>
+where
+    R: Read,
+{
+    /// Creates a new `BundleReader` for `bundle`, reading its header
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an I/O error occurs while reading the header, if `bundle` isn't a
+    /// bundle, or if its version or digest algorithm is unsupported.
+    pub fn new(mut bundle: R) -> Result<Self, PatchError> {
+        let magic = bundle.read_u32::<LittleEndian>()?;
+        if magic != BUNDLE_MAGIC {
+            return Err(patch::bad_magic(magic, &mut bundle));
+        }
+
+        let version_major = bundle.read_u16::<LittleEndian>()?;
+        // Minor versions only add information readers may ignore
+        let _version_minor = bundle.read_u16::<LittleEndian>()?;
+        let digest_algorithm = match version_major {
+            BUNDLE_VERSION_MAJOR => DigestAlgorithm::Blake3,
+            BUNDLE_VERSION_MAJOR_DIGEST => DigestAlgorithm::from_id(bundle.read_u8()?)
+                .ok_or_else(|| invalid("unsupported bundle digest algorithm"))?,
+            _ => return Err(PatchError::UnsupportedVersion(version_major)),
+        };
+
+        Ok(Self {
+            inner: bundle.take(0),
+            digest_algorithm,
+            finished: false,
+        })
+    }
+
+    /// Returns the algorithm of the digests of new files in the bundle
+    pub fn digest_algorithm(&self) -> DigestAlgorithm {
+        self.digest_algorithm
+    }
+
+    /// Reads the next entry, skipping the unread part of the current entry's patch.
+    ///
+    /// Returns `None` once all entries have been read.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an I/O error occurs or if the entry is malformed, e.g., if its path
+    /// isn't a valid relative path.
+    pub fn next_entry(&mut self) -> Result<Option<BundleEntry>, PatchError> {
+        if self.finished {
+            return Ok(None);
+        }
+
+        io::copy(&mut self.inner, &mut io::sink())?;
+        let bundle = self.inner.get_mut();
+
+        let kind = match bundle.read_u8()? {
+            TAG_END => {
+                self.finished = true;
+                return Ok(None);
+            }
+            TAG_ADDED => BundleEntryKind::Added,
+            TAG_MODIFIED => BundleEntryKind::Modified,
+            TAG_REMOVED => BundleEntryKind::Removed,
+            TAG_UNCHANGED => BundleEntryKind::Unchanged,
+            TAG_SYMLINK => BundleEntryKind::Symlink,
+            _ => return Err(invalid("unknown bundle entry kind").into()),
+        };
+
+        let path = read_path(bundle)?
+            .filter(|path| is_valid_path(path))
+            .ok_or_else(|| invalid("invalid bundle entry path"))?;
+        let symlink_target = match kind {
+            BundleEntryKind::Symlink => Some(
+                read_path(bundle)?
+                    .filter(|target| is_valid_symlink_target(&path, target))
+                    .ok_or_else(|| invalid("invalid bundle symbolic link target"))?,
+            ),
+            _ => None,
+        };
+
+        let (new_len, new_digest) = match kind {
+            BundleEntryKind::Removed | BundleEntryKind::Symlink => (0, None),
+            _ => {
+                let new_len = bundle.read_varint()?;
+                let mut digest = [0; DIGEST_LEN];
+                bundle.read_exact(&mut digest)?;
+                (new_len, Some(digest))
+            }
+        };
+        let patch_len = match kind {
+            BundleEntryKind::Added | BundleEntryKind::Modified => bundle.read_varint()?,
+            BundleEntryKind::Removed | BundleEntryKind::Unchanged | BundleEntryKind::Symlink => 0,
+        };
+        self.inner.set_limit(patch_len);
+
+        Ok(Some(BundleEntry {
+            path,
+            kind,
+            new_len,
+            new_digest,
+            patch_len,
+            symlink_target,
+        }))
+    }
+
+    /// Returns a reader for the unread part of the current entry's patch
+    pub fn patch(&mut self) -> &mut Take<R> {
+        &mut self.inner
+    }
+
+    /// Reads the header of the current entry's patch, returning `None` if the entry has no patch.
+    ///
+    /// This allows indexing the patches in a bundle without applying them. It must be called
+    /// before anything else is read from [`BundleReader::patch()`], after which the rest of the
+    /// patch can still be applied with [`Patcher::from_parts()`](crate::Patcher::from_parts).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an I/O error occurs or if the patch header is invalid.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use ina::{BundleReader, BundleWriter, DiffConfig};
+    ///
+    /// let mut writer = BundleWriter::new(Vec::new())?;
+    /// writer.add("bin/app", Some(b"version 1"), b"version 2", &DiffConfig::new())?;
+    /// writer.remove("README")?;
+    /// let bundle = writer.finish()?;
+    ///
+    /// let mut reader = BundleReader::new(bundle.as_slice())?;
+    /// while let Some(entry) = reader.next_entry()? {
+    ///     match reader.patch_metadata()? {
+    ///         Some(metadata) => println!("{} ({}): {:?}", entry.path(), entry.kind(), metadata),
+    ///         None => println!("{} ({})", entry.path(), entry.kind()),
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn patch_metadata(&mut self) -> Result<Option<PatchMetadata>, PatchError> {
+        if self.inner.limit() == 0 {
+            return Ok(None);
+        }
+
+        read_header(&mut self.inner).map(Some)
+    }
+}
+
+/// A writer which encodes entries into a bundle.
+///
+/// See [`BundleReader`] for an example.
+#[cfg(feature = "diff")]
+pub struct BundleWriter<W>
+where
+    W: Write,
+{
+    out: W,
+    digest_algorithm: DigestAlgorithm,
+}
+
+#[cfg(feature = "diff")]
+impl<W> BundleWriter<W>
+where
+    W: Write,
+{
+    /// Creates a new `BundleWriter` which writes a bundle with BLAKE3 digests to `out`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an I/O error occurs while writing the bundle header.
+    pub fn new(out: W) -> io::Result<Self> {
+        Self::with_digest_algorithm(out, DigestAlgorithm::Blake3)
+    }
+
+    /// Creates a new `BundleWriter` which writes a bundle to `out`, computing the digests of new
+    /// files with `digest_algorithm`
+    ///
+    /// Bundles with digest algorithms other than BLAKE3 can't be read by versions of this crate
+    /// which predate the choice of algorithm.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an I/O error occurs while writing the bundle header.
+    pub fn with_digest_algorithm(
+        mut out: W,
+        digest_algorithm: DigestAlgorithm,
+    ) -> io::Result<Self> {
+        out.write_u32::<LittleEndian>(BUNDLE_MAGIC)?;
+        if digest_algorithm == DigestAlgorithm::Blake3 {
+            out.write_u16::<LittleEndian>(BUNDLE_VERSION_MAJOR)?;
+            out.write_u16::<LittleEndian>(BUNDLE_VERSION_MINOR)?;
+        } else {
+            out.write_u16::<LittleEndian>(BUNDLE_VERSION_MAJOR_DIGEST)?;
+            out.write_u16::<LittleEndian>(BUNDLE_VERSION_MINOR)?;
+            out.write_u8(digest_algorithm.id())?;
+        }
+
+        Ok(Self {
+            out,
+            digest_algorithm,
+        })
+    }
+
+    /// Appends an entry for the file at `path` whose old contents are `old`, if it existed, and
+    /// whose new contents are `new`.
+    ///
+    /// The entry's patch is created with `options` and held in memory until it's written. Returns
+    /// statistics about the patch, or `None` if the file is unchanged.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `path` isn't a valid relative path or if an I/O error occurs.
+    pub fn add(
+        &mut self,
+        path: &str,
+        old: Option<&[u8]>,
+        new: &[u8],
+        options: &DiffConfig,
+    ) -> io::Result<Option<DiffStats>> {
+        if old == Some(new) {
+            self.write_entry_header(TAG_UNCHANGED, path)?;
+            self.write_new_blob(new)?;
+            return Ok(None);
+        }
+
+        let mut patch = Vec::new();
+        let stats = crate::diff_with_config(
+            &OldBlob::from_slice(old.unwrap_or_default()),
+            new,
+            &mut patch,
+            options,
+        )?;
+
+        let tag = if old.is_some() {
+            TAG_MODIFIED
+        } else {
+            TAG_ADDED
+        };
+        self.write_entry_header(tag, path)?;
+        self.write_new_blob(new)?;
+        self.out.write_varint(patch.len() as u64)?;
+        self.out.write_all(&patch)?;
+
+        Ok(Some(stats))
+    }
+
+    /// Appends an entry for the file at `path` which only exists in the old directory
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `path` isn't a valid relative path or if an I/O error occurs.
+    pub fn remove(&mut self, path: &str) -> io::Result<()> {
+        self.write_entry_header(TAG_REMOVED, path)
+    }
+
+    /// Appends an entry for the symbolic link at `path` which points to `target`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `path` isn't a valid relative path, if `target` is absolute or leads out
+    /// of the directory, or if an I/O error occurs.
+    pub fn add_symlink(&mut self, path: &str, target: &str) -> io::Result<()> {
+        if !is_valid_symlink_target(path, target) || target.len() as u64 > MAX_PATH_LEN {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                "symbolic link target must be a relative path inside the directory",
+            ));
+        }
+
+        self.write_entry_header(TAG_SYMLINK, path)?;
+        self.out.write_varint(target.len() as u64)?;
+        self.out.write_all(target.as_bytes())
+    }
+
+    /// Finishes the bundle, returning the underlying writer
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an I/O error occurs.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.out.write_u8(TAG_END)?;
+        self.out.flush()?;
+
+        Ok(self.out)
+    }
+
+    fn write_entry_header(&mut self, tag: u8, path: &str) -> io::Result<()> {
+        if !is_valid_path(path) || path.len() as u64 > MAX_PATH_LEN {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                "bundle entry path must be a relative path of normal components",
+            ));
+        }
+
+        self.out.write_u8(tag)?;
+        self.out.write_varint(path.len() as u64)?;
+        self.out.write_all(path.as_bytes())
+    }
+
+    fn write_new_blob(&mut self, new: &[u8]) -> io::Result<()> {
+        self.out.write_varint(new.len() as u64)?;
+        self.out.write_all(&self.digest_algorithm.hash(new))
+    }
+}
+
+/// Reads a length-prefixed path, returning `None` if it isn't valid UTF-8
+fn read_path<R>(bundle: &mut R) -> Result<Option<String>, PatchError>
+where
+    R: Read,
+{
+    let len: u64 = bundle.read_varint()?;
+    if len > MAX_PATH_LEN {
+        return Err(invalid("bundle entry path is too long").into());
+    }
+    let mut path = Vec::new();
+    bundle.take(len).read_to_end(&mut path)?;
+    if (path.len() as u64) < len {
+        return Err(io::Error::from(ErrorKind::UnexpectedEof).into());
+    }
+
+    Ok(String::from_utf8(path).ok())
+}
+
+/// Returns whether `target` is a relative, `/`-separated path which stays inside the directory
+/// when followed from the directory containing the link at `path`
+fn is_valid_symlink_target(path: &str, target: &str) -> bool {
+    if target.is_empty() || target.starts_with('/') {
+        return false;
+    }
+
+    // The number of directories the link is nested in
+    let mut depth = path.matches('/').count();
+    for component in target.split('/') {
+        match component {
+            "" | "." => {}
+            ".." => match depth.checked_sub(1) {
+                Some(parent) => depth = parent,
+                None => return false,
+            },
+            _ if component.contains(['\\', '\0', ':']) => return false,
+            _ => depth += 1,
+        }
+    }
+
+    true
+}
+
+/// Returns whether `path` is a relative, `/`-separated path consisting only of normal components
+fn is_valid_path(path: &str) -> bool {
+    !path.is_empty()
+        && path.split('/').all(|component| {
+            !matches!(component, "" | "." | "..")
+                && !component.contains(['\\', '\0'])
+                // Reject drive prefixes such as `C:`
+                && !component.contains(':')
+        })
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, message)
+}
@@ -0,0 +1,125 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+#[cfg(feature = "patch")]
+use std::{
+    error::Error,
+    fmt::{self, Display, Formatter},
+    io::{self, ErrorKind},
+};
+
+#[cfg(feature = "patch")]
+use crc32fast::Hasher;
+#[cfg(feature = "patch")]
+use integer_encoding::VarInt;
+#[cfg(feature = "diff")]
+use integer_encoding::VarIntWriter;
+
+/// The length and CRC-32 of the compressed data section of a patch
+///
+/// The checksum covers the data section as produced by the compressor, i.e., before forward error
+/// correction is applied, and is recorded in the header as the varint length followed by the
+/// little-endian CRC-32.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub(crate) struct PayloadChecksum {
+    len: u64,
+    crc: u32,
+}
+
+impl PayloadChecksum {
+    /// Computes the checksum of `payload`
+    #[cfg(feature = "diff")]
+    pub(crate) fn of(payload: &[u8]) -> Self {
+        Self {
+            len: payload.len() as u64,
+            crc: crc32fast::hash(payload),
+        }
+    }
+
+    /// Encodes the checksum as a header field
+    #[cfg(feature = "diff")]
+    pub(crate) fn encode_field(&self) -> Vec<u8> {
+        let mut field = Vec::new();
+        // Writing to a Vec never fails
+        field.write_varint(self.len).unwrap();
+        field.extend_from_slice(&self.crc.to_le_bytes());
+
+        field
+    }
+
+    /// Decodes the checksum from a header field, returning `None` if the field is invalid
+    #[cfg(feature = "patch")]
+    pub(crate) fn decode_field(field: &[u8]) -> Option<Self> {
+        let (len, len_len) = u64::decode_var(field)?;
+        let crc = u32::from_le_bytes(field[len_len..].try_into().ok()?);
+
+        Some(Self { len, crc })
+    }
+}
+
+/// Checks the data section of a patch against its recorded checksum as it's read
+#[cfg(feature = "patch")]
+pub(crate) struct ChecksumVerifier {
+    expected: PayloadChecksum,
+    hasher: Hasher,
+    len: u64,
+}
+
+#[cfg(feature = "patch")]
+impl ChecksumVerifier {
+    pub(crate) fn new(expected: PayloadChecksum) -> Self {
+        Self {
+            expected,
+            hasher: Hasher::new(),
+            len: 0,
+        }
+    }
+
+    /// Adds data read from the data section to the checksum
+    pub(crate) fn update(&mut self, data: &[u8]) {
+        self.hasher.update(data);
+        self.len += data.len() as u64;
+    }
+
+    /// Returns an error if more data has been read than the checksum covers
+    pub(crate) fn check_len(&self) -> io::Result<()> {
+        if self.len > self.expected.len {
+            return Err(mismatch());
+        }
+
+        Ok(())
+    }
+
+    /// Returns an error if the data read so far, which must be the entire data section, doesn't
+    /// match the checksum
+    pub(crate) fn check_end(&self) -> io::Result<()> {
+        let crc = self.hasher.clone().finalize();
+        if self.len != self.expected.len || crc != self.expected.crc {
+            return Err(mismatch());
+        }
+
+        Ok(())
+    }
+}
+
+/// The error wrapped by the I/O errors returned when patch data doesn't match its checksum
+#[cfg(feature = "patch")]
+#[derive(Debug)]
+pub(crate) struct ChecksumMismatch;
+
+#[cfg(feature = "patch")]
+impl Display for ChecksumMismatch {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.write_str("patch data doesn't match its checksum")
+    }
+}
+
+#[cfg(feature = "patch")]
+impl Error for ChecksumMismatch {}
+
+#[cfg(feature = "patch")]
+fn mismatch() -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, ChecksumMismatch)
+}
@@ -0,0 +1,384 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use std::io::{self, BufRead, ErrorKind, Read, Seek};
+
+#[cfg(feature = "bytes")]
+use bytes::Bytes;
+
+use crate::{BlockMap, Patcher};
+
+/// An iterator over the new blob reconstructed by a [`Patcher`] in owned chunks.
+///
+/// This struct is created by [`Patcher::chunks()`]. Every chunk except the last one is exactly as
+/// long as the chunk size given there, and the last one is never empty. After an error, the
+/// iterator yields no more chunks.
+pub struct Chunks<'a, O, B>
+where
+    O: Read + Seek,
+    B: BufRead,
+{
+    patcher: Patcher<'a, O, B>,
+    chunk_size: usize,
+    done: bool,
+}
+
+impl<'a, O, B> Chunks<'a, O, B>
+where
+    O: Read + Seek,
+    B: BufRead,
+{
+    pub(crate) fn new(patcher: Patcher<'a, O, B>, chunk_size: usize) -> Self {
+        assert!(chunk_size > 0, "chunk size must be nonzero");
+
+        Self {
+            patcher,
+            chunk_size,
+            done: false,
+        }
+    }
+
+    /// Returns the `Patcher` reconstructing the new blob
+    pub fn patcher(&self) -> &Patcher<'a, O, B> {
+        &self.patcher
+    }
+
+    /// Converts this iterator into one yielding [`Bytes`] without copying the chunks
+    #[cfg(feature = "bytes")]
+    pub fn into_bytes(self) -> ByteChunks<'a, O, B> {
+        ByteChunks { chunks: self }
+    }
+
+    fn read_chunk(&mut self) -> io::Result<Vec<u8>> {
+        let mut chunk = vec![0; self.chunk_size];
+        let mut len = 0;
+        while len < chunk.len() {
+            match self.patcher.read(&mut chunk[len..]) {
+                Ok(0) => break,
+                Ok(read) => len += read,
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        chunk.truncate(len);
+
+        Ok(chunk)
+    }
+}
+
+impl<O, B> Iterator for Chunks<'_, O, B>
+where
+    O: Read + Seek,
+    B: BufRead,
+{
+    type Item = io::Result<Vec<u8>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let chunk = self.read_chunk();
+        match &chunk {
+            Ok(chunk) if chunk.len() < self.chunk_size => self.done = true,
+            Err(_) => self.done = true,
+            Ok(_) => {}
+        }
+
+        match chunk {
+            Ok(chunk) if chunk.is_empty() => None,
+            chunk => Some(chunk),
+        }
+    }
+}
+
+/// A reader of the new blob reconstructed by a [`Patcher`] into fixed-size, aligned blocks.
+///
+/// This struct is created by [`Patcher::aligned_blocks()`]. Blocks are read into a single buffer
+/// whose start is aligned as requested there, which is reused for every block, so each block must
+/// be consumed before reading the next. Every block except the last one is exactly as long as the
+/// block size, and the last one is never empty. After an error, no more blocks are read.
+pub struct AlignedBlocks<'a, O, B>
+where
+    O: Read + Seek,
+    B: BufRead,
+{
+    patcher: Patcher<'a, O, B>,
+    /// The buffer blocks are read into, which has room for an aligned block wherever it starts
+    buffer: Vec<u8>,
+    /// The offset of the first aligned byte of `buffer`
+    start: usize,
+    block_size: usize,
+    alignment: usize,
+    done: bool,
+}
+
+impl<'a, O, B> AlignedBlocks<'a, O, B>
+where
+    O: Read + Seek,
+    B: BufRead,
+{
+    pub(crate) fn new(patcher: Patcher<'a, O, B>, block_size: usize, alignment: usize) -> Self {
+        assert!(
+            alignment.is_power_of_two(),
+            "alignment must be a power of two"
+        );
+        assert!(
+            block_size > 0 && block_size.is_multiple_of(alignment),
+            "block size must be a nonzero multiple of the alignment",
+        );
+
+        let buffer = vec![0; block_size + alignment - 1];
+        let start = buffer.as_ptr().align_offset(alignment);
+
+        Self {
+            patcher,
+            buffer,
+            start,
+            block_size,
+            alignment,
+            done: false,
+        }
+    }
+
+    /// Returns the `Patcher` reconstructing the new blob
+    pub fn patcher(&self) -> &Patcher<'a, O, B> {
+        &self.patcher
+    }
+
+    /// Returns the `Patcher` reconstructing the new blob, e.g., to apply patches concatenated
+    /// after it once every block has been read
+    pub fn into_patcher(self) -> Patcher<'a, O, B> {
+        self.patcher
+    }
+
+    /// Reads the next block of the new blob, returning `None` once it has been read entirely
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if reading from the `Patcher` fails, after which this method returns
+    /// `Ok(None)`.
+    pub fn next_block(&mut self) -> io::Result<Option<AlignedBlock<'_>>> {
+        if self.done {
+            return Ok(None);
+        }
+
+        let block = &mut self.buffer[self.start..self.start + self.block_size];
+        let mut len = 0;
+        while len < block.len() {
+            match self.patcher.read(&mut block[len..]) {
+                Ok(0) => break,
+                Ok(read) => len += read,
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => {
+                    self.done = true;
+                    return Err(e);
+                }
+            }
+        }
+        if len < block.len() {
+            self.done = true;
+            if len == 0 {
+                return Ok(None);
+            }
+        }
+
+        // The last block is padded with zeros to the alignment
+        let padded_len = len.next_multiple_of(self.alignment);
+        block[len..padded_len].fill(0);
+
+        Ok(Some(AlignedBlock {
+            padded: &block[..padded_len],
+            len,
+        }))
+    }
+}
+
+/// A block of the new blob read by [`AlignedBlocks`].
+///
+/// The block starts at an aligned address. Its padded form additionally extends to a multiple of
+/// the alignment, which only differs from the block itself for the last block of the new blob,
+/// whose padding consists of zeros. This suits direct I/O, e.g., writes to files opened with
+/// `O_DIRECT`, which require aligned buffers and lengths: writing every padded block and then
+/// truncating the file to the length of the new blob reproduces it exactly.
+#[derive(Clone, Copy, Debug)]
+pub struct AlignedBlock<'b> {
+    padded: &'b [u8],
+    len: usize,
+}
+
+impl<'b> AlignedBlock<'b> {
+    /// Returns the bytes of the new blob in this block
+    pub fn data(&self) -> &'b [u8] {
+        &self.padded[..self.len]
+    }
+
+    /// Returns the bytes of the new blob in this block followed by its padding
+    pub fn padded(&self) -> &'b [u8] {
+        self.padded
+    }
+}
+
+/// A reader of the blocks of the new blob reconstructed by a [`Patcher`] which differ from the old
+/// blob.
+///
+/// This struct is created by [`Patcher::block_updates()`] from the [`BlockMap`] recorded in the
+/// patch. The patch still reconstructs the entire new blob, but only the blocks the map records as
+/// changed are returned, along with their offsets in the new blob, so the unchanged ones needn't
+/// be written. Blocks past the end of the new blob described by the map, which a conforming patch
+/// doesn't produce, are returned as changed. Blocks are read into a single buffer, which is reused
+/// for every block, so each block must be consumed before reading the next. After an error, no
+/// more blocks are read.
+pub struct BlockUpdates<'a, O, B>
+where
+    O: Read + Seek,
+    B: BufRead,
+{
+    patcher: Patcher<'a, O, B>,
+    block_map: BlockMap,
+    buffer: Vec<u8>,
+    /// The index of the next block to read
+    index: u64,
+    done: bool,
+}
+
+impl<'a, O, B> BlockUpdates<'a, O, B>
+where
+    O: Read + Seek,
+    B: BufRead,
+{
+    pub(crate) fn new(patcher: Patcher<'a, O, B>, block_map: BlockMap) -> Self {
+        Self {
+            patcher,
+            buffer: vec![0; block_map.block_size() as usize],
+            block_map,
+            index: 0,
+            done: false,
+        }
+    }
+
+    /// Returns the block map which determines the blocks to return
+    pub fn block_map(&self) -> &BlockMap {
+        &self.block_map
+    }
+
+    /// Returns the `Patcher` reconstructing the new blob
+    pub fn patcher(&self) -> &Patcher<'a, O, B> {
+        &self.patcher
+    }
+
+    /// Returns the `Patcher` reconstructing the new blob, e.g., to apply patches concatenated
+    /// after it once every block has been read
+    pub fn into_patcher(self) -> Patcher<'a, O, B> {
+        self.patcher
+    }
+
+    /// Reads the next changed block of the new blob, returning `None` once the new blob has been
+    /// read entirely
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if reading from the `Patcher` fails, after which this method returns
+    /// `Ok(None)`.
+    pub fn next_update(&mut self) -> io::Result<Option<BlockUpdate<'_>>> {
+        while !self.done {
+            let mut len = 0;
+            while len < self.buffer.len() {
+                match self.patcher.read(&mut self.buffer[len..]) {
+                    Ok(0) => break,
+                    Ok(read) => len += read,
+                    Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                    Err(e) => {
+                        self.done = true;
+                        return Err(e);
+                    }
+                }
+            }
+            if len < self.buffer.len() {
+                self.done = true;
+                if len == 0 {
+                    break;
+                }
+            }
+
+            let index = self.index;
+            self.index += 1;
+            if index >= self.block_map.block_count() || self.block_map.is_changed(index) {
+                return Ok(Some(BlockUpdate {
+                    index,
+                    offset: index * u64::from(self.block_map.block_size()),
+                    data: &self.buffer[..len],
+                }));
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+/// A changed block of the new blob read by [`BlockUpdates`].
+///
+/// Every block except the last one of the new blob is as long as the block size of the
+/// [`BlockMap`].
+#[derive(Clone, Copy, Debug)]
+pub struct BlockUpdate<'b> {
+    index: u64,
+    offset: u64,
+    data: &'b [u8],
+}
+
+impl<'b> BlockUpdate<'b> {
+    /// Returns the index of the block in the new blob
+    pub fn index(&self) -> u64 {
+        self.index
+    }
+
+    /// Returns the offset in bytes of the block in the new blob
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Returns the bytes of the new blob in this block
+    pub fn data(&self) -> &'b [u8] {
+        self.data
+    }
+}
+
+/// An iterator over the new blob reconstructed by a [`Patcher`] in [`Bytes`] chunks.
+///
+/// This struct is created by [`Chunks::into_bytes()`] and yields the same chunks as the
+/// [`Chunks`] it was created from.
+#[cfg(feature = "bytes")]
+pub struct ByteChunks<'a, O, B>
+where
+    O: Read + Seek,
+    B: BufRead,
+{
+    chunks: Chunks<'a, O, B>,
+}
+
+#[cfg(feature = "bytes")]
+impl<'a, O, B> ByteChunks<'a, O, B>
+where
+    O: Read + Seek,
+    B: BufRead,
+{
+    /// Returns the `Patcher` reconstructing the new blob
+    pub fn patcher(&self) -> &Patcher<'a, O, B> {
+        self.chunks.patcher()
+    }
+}
+
+#[cfg(feature = "bytes")]
+impl<O, B> Iterator for ByteChunks<'_, O, B>
+where
+    O: Read + Seek,
+    B: BufRead,
+{
+    type Item = io::Result<Bytes>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.chunks.next().map(|chunk| chunk.map(Bytes::from))
+    }
+}
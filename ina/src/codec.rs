@@ -0,0 +1,116 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use std::fmt::{self, Display, Formatter};
+
+#[cfg(any(feature = "brotli", feature = "patch"))]
+use integer_encoding::VarInt;
+
+/// The identifier of zstd in the codec header field, which is implied when the field is absent
+const ZSTD_ID: u64 = 0;
+/// The identifier of Brotli in the codec header field
+#[cfg(feature = "brotli")]
+const BROTLI_ID: u64 = 1;
+
+/// The codec the data section of a patch is compressed with.
+///
+/// Patches are compressed with zstd unless [`DiffConfig::codec()`] selects another codec, in which
+/// case the codec is recorded in the header. It can later be retrieved via
+/// [`PatchMetadata::codec()`]. Applying a patch requires support for its codec, so patches
+/// compressed with a codec other than zstd can't be applied by patchers which predate it.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "brotli")]
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use std::io::Cursor;
+/// use ina::{Codec, DiffConfig};
+///
+/// let mut patch = Vec::new();
+/// let old = ina::OldBlob::from_slice(b"Hello");
+/// ina::diff_with_config(&old, b"Hero", &mut patch, DiffConfig::new().codec(Codec::Brotli))?;
+/// assert_eq!(ina::read_header(&mut patch.as_slice())?.codec(), Codec::Brotli);
+///
+/// let mut new = Vec::new();
+/// ina::patch(Cursor::new(b"Hello"), patch.as_slice(), &mut new)?;
+/// assert_eq!(new, b"Hero");
+/// # Ok(())
+/// # }
+/// # #[cfg(not(feature = "brotli"))]
+/// # fn main() {}
+/// ```
+///
+/// [`DiffConfig::codec()`]: crate::DiffConfig::codec
+/// [`PatchMetadata::codec()`]: crate::PatchMetadata::codec
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq, PartialOrd, Ord)]
+#[non_exhaustive]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum Codec {
+    /// Zstandard, which is used unless another codec is selected
+    #[default]
+    Zstd,
+    /// Brotli, for delivery through infrastructure which supports it but not zstd
+    ///
+    /// The compression level is used as the Brotli quality, clamped to 0-11, and the window log is
+    /// clamped to 10-24.
+    #[cfg(feature = "brotli")]
+    Brotli,
+}
+
+impl Codec {
+    /// Returns the identifier of this codec in the codec header field
+    pub fn id(self) -> u64 {
+        match self {
+            Self::Zstd => ZSTD_ID,
+            #[cfg(feature = "brotli")]
+            Self::Brotli => BROTLI_ID,
+        }
+    }
+
+    /// Encodes the value of the header field recording this codec, or returns `None` if it's the
+    /// default and isn't recorded
+    #[cfg(feature = "diff")]
+    pub(crate) fn encode_field(self) -> Option<Vec<u8>> {
+        match self {
+            Self::Zstd => None,
+            #[cfg(feature = "brotli")]
+            Self::Brotli => Some(self.id().encode_var_vec()),
+        }
+    }
+
+    /// Decodes the value of the header field recording a codec
+    ///
+    /// Returns `None` if the value is malformed or names a codec this build doesn't support.
+    #[cfg(feature = "patch")]
+    pub(crate) fn decode_field(value: &[u8]) -> Option<Self> {
+        let (id, len) = u64::decode_var(value)?;
+        if len != value.len() {
+            return None;
+        }
+
+        match id {
+            ZSTD_ID => Some(Self::Zstd),
+            #[cfg(feature = "brotli")]
+            BROTLI_ID => Some(Self::Brotli),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Codec {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let name = match self {
+            Self::Zstd => "zstd",
+            #[cfg(feature = "brotli")]
+            Self::Brotli => "brotli",
+        };
+
+        f.write_str(name)
+    }
+}
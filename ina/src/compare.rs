@@ -0,0 +1,184 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use std::ops::Range;
+
+use crate::{DiffConfig, OldBlob, OldCoverage, bsdiff::MatchMaker};
+
+/// Finds the regions two blobs have in common without producing a patch
+///
+/// The blobs are matched exactly as when diffing them with `options`, of which only the match
+/// threshold and anchors are used. Each match is an approximate one, i.e., a region of the new blob
+/// which mostly equals a region of the old blob of the same length, and the parts of both blobs
+/// outside of any match are unmatched. This is useful for analyzing how much two files share and
+/// where, e.g., to choose a chunking strategy for deduplicating storage.
+///
+/// # Examples
+///
+/// ```
+/// use ina::{DiffConfig, OldBlob};
+///
+/// let old: Vec<u8> = (0..8192u32).map(|i| (i.wrapping_mul(0x9e3779b9) >> 24) as u8).collect();
+/// let mut new = old[..4096].to_vec();
+/// new.extend_from_slice(&[0; 1024]);
+///
+/// let comparison = ina::compare(&OldBlob::from_slice(&old), &new, &DiffConfig::new());
+/// assert_eq!(comparison.matches()[0].new_range(), 0..4096);
+/// assert_eq!(comparison.matches()[0].similarity(), 1.0);
+/// assert_eq!(comparison.old_unmatched(), [4096..8192]);
+/// ```
+pub fn compare(old: &OldBlob, new: &[u8], options: &DiffConfig) -> Comparison {
+    let old = old.with_sentinel();
+    // Exclude the sentinel
+    let old_len = old.len() - 1;
+    let matches = MatchMaker::new(old, new, options.match_threshold)
+        .with_anchors(&options.anchors)
+        .filter(|m| m.add_len() > 0)
+        .map(|m| {
+            let old_region = &old[m.add_old_pos()..m.add_old_pos() + m.add_len()];
+            let new_region = &new[m.add_new_pos()..m.add_new_pos() + m.add_len()];
+            let equal_len = old_region
+                .iter()
+                .zip(new_region)
+                .filter(|(old_byte, new_byte)| old_byte == new_byte)
+                .count();
+
+            RegionMatch {
+                old_start: m.add_old_pos() as u64,
+                new_start: m.add_new_pos() as u64,
+                len: m.add_len() as u64,
+                equal_len: equal_len as u64,
+            }
+        })
+        .collect();
+
+    Comparison {
+        old_len: old_len as u64,
+        new_len: new.len() as u64,
+        matches,
+    }
+}
+
+/// The regions two blobs have in common, as found by [`compare()`].
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Comparison {
+    old_len: u64,
+    new_len: u64,
+    matches: Vec<RegionMatch>,
+}
+
+impl Comparison {
+    /// Returns the length of the old blob
+    pub fn old_len(&self) -> u64 {
+        self.old_len
+    }
+
+    /// Returns the length of the new blob
+    pub fn new_len(&self) -> u64 {
+        self.new_len
+    }
+
+    /// Returns the matches in ascending order of their position in the new blob
+    ///
+    /// Matches don't overlap in the new blob, but may overlap in the old blob, e.g., where a
+    /// region of the old blob is duplicated in the new blob.
+    pub fn matches(&self) -> &[RegionMatch] {
+        &self.matches
+    }
+
+    /// Returns the regions of the new blob outside of any match in ascending order
+    pub fn new_unmatched(&self) -> Vec<Range<u64>> {
+        let mut unmatched = Vec::new();
+        let mut pos = 0;
+        for m in &self.matches {
+            if m.new_start > pos {
+                unmatched.push(pos..m.new_start);
+            }
+            pos = m.new_start + m.len;
+        }
+        if pos < self.new_len {
+            unmatched.push(pos..self.new_len);
+        }
+
+        unmatched
+    }
+
+    /// Returns the regions of the old blob covered by matches in ascending order, with
+    /// overlapping regions merged
+    pub fn old_matched(&self) -> OldCoverage {
+        let mut coverage = OldCoverage::new();
+        for m in &self.matches {
+            coverage.insert(m.old_range());
+        }
+
+        coverage
+    }
+
+    /// Returns the regions of the old blob outside of any match in ascending order
+    pub fn old_unmatched(&self) -> Vec<Range<u64>> {
+        let mut unmatched = Vec::new();
+        let mut pos = 0;
+        for range in self.old_matched().ranges() {
+            if range.start > pos {
+                unmatched.push(pos..range.start);
+            }
+            pos = range.end;
+        }
+        if pos < self.old_len {
+            unmatched.push(pos..self.old_len);
+        }
+
+        unmatched
+    }
+
+    /// Returns the number of bytes of the new blob within matches
+    pub fn matched_len(&self) -> u64 {
+        self.matches.iter().map(|m| m.len).sum()
+    }
+
+    /// Returns the fraction of the new blob whose bytes equal those of the old blob they're
+    /// matched with
+    ///
+    /// Returns 1 if the new blob is empty.
+    pub fn similarity(&self) -> f64 {
+        if self.new_len == 0 {
+            1.0
+        } else {
+            self.matches.iter().map(|m| m.equal_len).sum::<u64>() as f64 / self.new_len as f64
+        }
+    }
+}
+
+/// A region of a new blob matched with a region of the same length of an old blob.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RegionMatch {
+    old_start: u64,
+    new_start: u64,
+    len: u64,
+    equal_len: u64,
+}
+
+impl RegionMatch {
+    /// Returns the matched region of the old blob
+    pub fn old_range(&self) -> Range<u64> {
+        self.old_start..self.old_start + self.len
+    }
+
+    /// Returns the matched region of the new blob
+    pub fn new_range(&self) -> Range<u64> {
+        self.new_start..self.new_start + self.len
+    }
+
+    /// Returns the number of positions at which the matched regions have equal bytes
+    pub fn equal_len(&self) -> u64 {
+        self.equal_len
+    }
+
+    /// Returns the fraction of positions at which the matched regions have equal bytes
+    pub fn similarity(&self) -> f64 {
+        self.equal_len as f64 / self.len as f64
+    }
+}
@@ -0,0 +1,493 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use std::{
+    collections::HashMap,
+    fmt::{self, Debug, Formatter},
+    io::{self, BufRead, BufReader, ErrorKind, Read, Seek, SeekFrom},
+};
+
+use crc32fast::Hasher;
+use flate2::{Decompress, FlushDecompress, Status};
+use zstd::stream::raw::{Decoder as ZstdDecoder, InBuffer, Operation, OutBuffer};
+
+/// The number of decompressed bytes cached together
+const WINDOW_LEN: usize = 256 << 10;
+
+/// The default maximum number of decompressed bytes cached
+const DEFAULT_CACHE_LIMIT: usize = 16 << 20;
+
+/// The magic number starting every zstd frame
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// The magic number starting every gzip member
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// The compression method of gzip members compressed with deflate, the only one defined
+const GZIP_DEFLATE: u8 = 8;
+
+/// gzip header flags
+const FHCRC: u8 = 1 << 1;
+const FEXTRA: u8 = 1 << 2;
+const FNAME: u8 = 1 << 3;
+const FCOMMENT: u8 = 1 << 4;
+const RESERVED_FLAGS: u8 = 0b1110_0000;
+
+/// The compression format of an old blob read via [`CompressedOld`]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum OldCompression {
+    /// Zstandard, as written by the `zstd` command
+    Zstd,
+    /// gzip, as written by the `gzip` command
+    Gzip,
+}
+
+impl OldCompression {
+    /// Detects the compression format from the first bytes of a compressed blob, returning `None`
+    /// if it isn't recognized
+    pub fn detect(prefix: &[u8]) -> Option<Self> {
+        if prefix.starts_with(&ZSTD_MAGIC) {
+            Some(Self::Zstd)
+        } else if prefix.starts_with(&GZIP_MAGIC) {
+            Some(Self::Gzip)
+        } else {
+            None
+        }
+    }
+}
+
+/// An old blob stored compressed.
+///
+/// A `CompressedOld` implements [`Read`] and [`Seek`] over the decompressed contents of a zstd or
+/// gzip file, so it can be passed to a [`Patcher`](crate::Patcher) in place of the decompressed
+/// old blob. This allows patching on devices which store old blobs compressed without
+/// decompressing them to disk first.
+///
+/// Compressed data can only be decompressed from the start of a zstd frame or gzip member, so
+/// the offsets of frames are recorded as seek points as they're first decompressed. Decompressed
+/// data is cached in windows of 256 KiB, up to 16 MiB by default, evicting the least recently
+/// used windows first. Reading data which isn't cached decompresses it starting from the closest
+/// preceding seek point. Since a file compressed as a single frame only has a seek point at its
+/// start, old blobs should be compressed as several frames, e.g., with `pzstd` or by
+/// concatenating separately compressed parts, or the cache should be large enough to hold them.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::fs::File;
+/// use ina::{CompressedOld, OldCompression};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let old = CompressedOld::new(File::open("app-v1.exe.zst")?, OldCompression::Zstd)?;
+/// let patch = File::open("app-v1-to-v2.ina")?;
+/// let mut new = File::create("app-v2.exe")?;
+///
+/// ina::patch(old, patch, &mut new)?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct CompressedOld<R> {
+    compressed: BufReader<R>,
+    compressed_pos: u64,
+    compression: OldCompression,
+    decoder: Decoder,
+    in_frame: bool,
+    decoder_failed: bool,
+    /// The decompressed offset of the decoder
+    decoded: u64,
+    /// Seek points sorted by both offsets, starting with the start of the compressed blob
+    points: Vec<SeekPoint>,
+    len: Option<u64>,
+    pos: u64,
+    windows: HashMap<u64, Window>,
+    cached_len: usize,
+    cache_limit: usize,
+    clock: u64,
+}
+
+/// A decoder of a single zstd frame or gzip member
+enum Decoder {
+    Zstd(ZstdDecoder<'static>),
+    Gzip {
+        inflate: Decompress,
+        crc: Hasher,
+        len: u32,
+    },
+}
+
+/// The start of a zstd frame or gzip member
+#[derive(Clone, Copy)]
+struct SeekPoint {
+    compressed: u64,
+    decompressed: u64,
+}
+
+/// A cached window of decompressed data
+struct Window {
+    data: Vec<u8>,
+    last_used: u64,
+}
+
+impl<R> CompressedOld<R>
+where
+    R: Read + Seek,
+{
+    /// Creates a new `CompressedOld` reading the blob compressed with `compression` from `inner`,
+    /// starting at its current position
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if creating the decoder or querying the position of `inner` fails.
+    pub fn new(mut inner: R, compression: OldCompression) -> io::Result<Self> {
+        let start = inner.stream_position()?;
+        let decoder = match compression {
+            OldCompression::Zstd => Decoder::Zstd(ZstdDecoder::new()?),
+            OldCompression::Gzip => Decoder::Gzip {
+                inflate: Decompress::new(false),
+                crc: Hasher::new(),
+                len: 0,
+            },
+        };
+
+        Ok(Self {
+            compressed: BufReader::new(inner),
+            compressed_pos: start,
+            compression,
+            decoder,
+            in_frame: false,
+            decoder_failed: false,
+            decoded: 0,
+            points: vec![SeekPoint {
+                compressed: start,
+                decompressed: 0,
+            }],
+            len: None,
+            pos: 0,
+            windows: HashMap::new(),
+            cached_len: 0,
+            cache_limit: DEFAULT_CACHE_LIMIT,
+            clock: 0,
+        })
+    }
+
+    /// Sets the maximum number of decompressed bytes to cache
+    ///
+    /// At least one window of 256 KiB is cached regardless. The default is 16 MiB.
+    pub fn cache_limit(&mut self, limit: usize) -> &mut Self {
+        self.cache_limit = limit;
+        self.evict(0);
+        self
+    }
+
+    /// Returns the window of decompressed data with the given index, decompressing it if it isn't
+    /// cached
+    fn window(&mut self, index: u64) -> io::Result<&[u8]> {
+        self.clock += 1;
+        if !self.windows.contains_key(&index) {
+            let data = self
+                .decompress_window(index)
+                .inspect_err(|_| self.decoder_failed = true)?;
+            self.evict(data.len());
+            self.cached_len += data.len();
+            self.windows.insert(index, Window { data, last_used: 0 });
+        }
+
+        let window = self
+            .windows
+            .get_mut(&index)
+            .expect("window was just cached");
+        window.last_used = self.clock;
+
+        Ok(&window.data)
+    }
+
+    /// Evicts the least recently used windows until `len` more bytes can be cached
+    fn evict(&mut self, len: usize) {
+        while self.cached_len + len > self.cache_limit {
+            let Some(oldest) = self
+                .windows
+                .iter()
+                .min_by_key(|(_, window)| window.last_used)
+                .map(|(&index, _)| index)
+            else {
+                break;
+            };
+            if let Some(window) = self.windows.remove(&oldest) {
+                self.cached_len -= window.data.len();
+            }
+        }
+    }
+
+    /// Decompresses the window with the given index, which is empty if it's past the end
+    fn decompress_window(&mut self, index: u64) -> io::Result<Vec<u8>> {
+        let start = index * WINDOW_LEN as u64;
+        if self.len.is_some_and(|len| start >= len) {
+            return Ok(Vec::new());
+        }
+        self.rewind(start)?;
+
+        let mut data = vec![0; WINDOW_LEN];
+        while self.decoded < start {
+            let skip = (start - self.decoded).min(WINDOW_LEN as u64) as usize;
+            if self.read_decompressed(&mut data[..skip])? == 0 {
+                return Ok(Vec::new());
+            }
+        }
+
+        let mut len = 0;
+        while len < data.len() {
+            match self.read_decompressed(&mut data[len..])? {
+                0 => break,
+                read => len += read,
+            }
+        }
+        data.truncate(len);
+
+        Ok(data)
+    }
+
+    /// Returns the length of the decompressed blob, decompressing the rest of it if it's unknown
+    fn len(&mut self) -> io::Result<u64> {
+        if let Some(len) = self.len {
+            return Ok(len);
+        }
+
+        self.decompress_rest()
+            .inspect_err(|_| self.decoder_failed = true)?;
+
+        Ok(self.decoded)
+    }
+
+    /// Decompresses the rest of the blob without caching it
+    fn decompress_rest(&mut self) -> io::Result<()> {
+        self.rewind(u64::MAX)?;
+        let mut scratch = vec![0; WINDOW_LEN];
+        while self.read_decompressed(&mut scratch)? > 0 {}
+
+        Ok(())
+    }
+
+    /// Moves the decoder to the closest seek point preceding `offset` unless it can already reach
+    /// `offset` from a closer position
+    fn rewind(&mut self, offset: u64) -> io::Result<()> {
+        let point = self.points[self.points.partition_point(|p| p.decompressed <= offset) - 1];
+        if self.decoder_failed || self.decoded > offset || point.decompressed > self.decoded {
+            self.compressed.seek(SeekFrom::Start(point.compressed))?;
+            self.compressed_pos = point.compressed;
+            self.decoded = point.decompressed;
+            self.in_frame = false;
+            self.decoder_failed = false;
+        }
+
+        Ok(())
+    }
+
+    /// Decompresses data at the decoder's position, moving on to the next frame at the end of each
+    /// one, and returns 0 only at the end of the blob
+    fn read_decompressed(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            if !self.in_frame && !self.start_frame()? {
+                return Ok(0);
+            }
+
+            let len = self.decode(buf)?;
+            if len > 0 {
+                self.decoded += len as u64;
+                return Ok(len);
+            }
+        }
+    }
+
+    /// Starts decoding the next frame, recording its start as a seek point, and returns `false` at
+    /// the end of the compressed blob
+    fn start_frame(&mut self) -> io::Result<bool> {
+        if self.compressed.fill_buf()?.is_empty() {
+            self.len = Some(self.decoded);
+            return Ok(false);
+        }
+        if self
+            .points
+            .last()
+            .is_some_and(|point| point.compressed < self.compressed_pos)
+        {
+            self.points.push(SeekPoint {
+                compressed: self.compressed_pos,
+                decompressed: self.decoded,
+            });
+        }
+
+        if self.compression == OldCompression::Gzip {
+            self.read_gzip_header()?;
+        }
+        match &mut self.decoder {
+            Decoder::Zstd(decoder) => decoder.reinit()?,
+            Decoder::Gzip { inflate, crc, len } => {
+                inflate.reset(false);
+                *crc = Hasher::new();
+                *len = 0;
+            }
+        }
+        self.in_frame = true;
+
+        Ok(true)
+    }
+
+    /// Decompresses data from the current frame, returning 0 once the frame ends
+    fn decode(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let input = self.compressed.fill_buf()?;
+            let exhausted = input.is_empty();
+            let (consumed, produced, finished) = match &mut self.decoder {
+                Decoder::Zstd(decoder) => {
+                    let mut src = InBuffer::around(input);
+                    let mut dst = OutBuffer::around(&mut *buf);
+                    let hint = decoder.run(&mut src, &mut dst)?;
+                    (src.pos(), dst.pos(), hint == 0)
+                }
+                Decoder::Gzip { inflate, crc, len } => {
+                    let (total_in, total_out) = (inflate.total_in(), inflate.total_out());
+                    let status = inflate
+                        .decompress(input, buf, FlushDecompress::None)
+                        .map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?;
+                    let produced = (inflate.total_out() - total_out) as usize;
+                    crc.update(&buf[..produced]);
+                    *len = len.wrapping_add(produced as u32);
+                    let consumed = (inflate.total_in() - total_in) as usize;
+                    (consumed, produced, status == Status::StreamEnd)
+                }
+            };
+            self.compressed.consume(consumed);
+            self.compressed_pos += consumed as u64;
+
+            if finished {
+                self.finish_frame()?;
+                return Ok(produced);
+            } else if produced > 0 {
+                return Ok(produced);
+            } else if exhausted {
+                return Err(io::Error::new(
+                    ErrorKind::UnexpectedEof,
+                    "compressed old blob is truncated",
+                ));
+            }
+        }
+    }
+
+    /// Ends the current frame, checking the trailer of gzip members
+    fn finish_frame(&mut self) -> io::Result<()> {
+        self.in_frame = false;
+
+        if let Decoder::Gzip { crc, len, .. } = &self.decoder {
+            let (crc, len) = (crc.clone().finalize(), *len);
+            let mut trailer = [0; 8];
+            self.read_compressed(&mut trailer)?;
+            if trailer[..4] != crc.to_le_bytes() || trailer[4..] != len.to_le_bytes() {
+                return Err(io::Error::new(
+                    ErrorKind::InvalidData,
+                    "gzip member doesn't match its checksum",
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Reads the header of a gzip member, leaving the compressed reader at the start of its
+    /// deflate stream
+    fn read_gzip_header(&mut self) -> io::Result<()> {
+        let mut header = [0; 10];
+        self.read_compressed(&mut header)?;
+        let flags = header[3];
+        if header[..2] != GZIP_MAGIC || header[2] != GZIP_DEFLATE || flags & RESERVED_FLAGS != 0 {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                "invalid gzip member header",
+            ));
+        }
+
+        if flags & FEXTRA != 0 {
+            let mut len = [0; 2];
+            self.read_compressed(&mut len)?;
+            self.read_compressed(&mut vec![0; u16::from_le_bytes(len).into()])?;
+        }
+        for flag in [FNAME, FCOMMENT] {
+            if flags & flag != 0 {
+                let mut byte = [1];
+                while byte[0] != 0 {
+                    self.read_compressed(&mut byte)?;
+                }
+            }
+        }
+        if flags & FHCRC != 0 {
+            self.read_compressed(&mut [0; 2])?;
+        }
+
+        Ok(())
+    }
+
+    /// Fills `buf` from the compressed blob
+    fn read_compressed(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        self.compressed.read_exact(buf)?;
+        self.compressed_pos += buf.len() as u64;
+
+        Ok(())
+    }
+}
+
+impl<R> Read for CompressedOld<R>
+where
+    R: Read + Seek,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() || self.len.is_some_and(|len| self.pos >= len) {
+            return Ok(0);
+        }
+
+        let index = self.pos / WINDOW_LEN as u64;
+        let offset = (self.pos % WINDOW_LEN as u64) as usize;
+        let window = self.window(index)?;
+        let len = buf.len().min(window.len().saturating_sub(offset));
+        buf[..len].copy_from_slice(&window[offset..offset + len]);
+        self.pos += len as u64;
+
+        Ok(len)
+    }
+}
+
+impl<R> Seek for CompressedOld<R>
+where
+    R: Read + Seek,
+{
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let pos = match pos {
+            SeekFrom::Start(pos) => Some(pos),
+            SeekFrom::Current(offset) => self.pos.checked_add_signed(offset),
+            SeekFrom::End(offset) => self.len()?.checked_add_signed(offset),
+        };
+        self.pos = pos.ok_or_else(|| {
+            io::Error::new(
+                ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )
+        })?;
+
+        Ok(self.pos)
+    }
+
+    fn stream_position(&mut self) -> io::Result<u64> {
+        Ok(self.pos)
+    }
+}
+
+impl<R> Debug for CompressedOld<R> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CompressedOld")
+            .field("compression", &self.compression)
+            .field("pos", &self.pos)
+            .field("len", &self.len)
+            .field("seek_points", &self.points.len())
+            .field("cached_len", &self.cached_len)
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,145 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use std::io::{self, BufRead, BufReader, ErrorKind, Read};
+
+use integer_encoding::VarIntReader;
+
+use crate::{
+    PatchError, PatchMetadata,
+    payload::{self, PartialVarint, PayloadDecoder},
+    read_header,
+};
+
+/// A single record of a patch's control stream.
+///
+/// A patch's data section is a sequence of control records. Applying a record first adds the
+/// record's add bytes to the bytes at the current position in the old blob, then appends its copy
+/// bytes verbatim, and finally moves the current position in the old blob by its seek value.
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq, PartialOrd, Ord)]
+pub struct ControlRecord {
+    add: Vec<u8>,
+    copy: Vec<u8>,
+    seek: i64,
+}
+
+impl ControlRecord {
+    /// Creates a new control record from its parts
+    #[cfg(feature = "unstable")]
+    pub fn new(add: Vec<u8>, copy: Vec<u8>, seek: i64) -> Self {
+        Self { add, copy, seek }
+    }
+
+    /// Returns the bytes to add to the old blob
+    pub fn add(&self) -> &[u8] {
+        &self.add
+    }
+
+    /// Returns the bytes to copy into the new blob verbatim
+    pub fn copy(&self) -> &[u8] {
+        &self.copy
+    }
+
+    /// Returns the distance to move the position in the old blob after this record
+    pub fn seek(&self) -> i64 {
+        self.seek
+    }
+}
+
+/// A reader which decodes the control records of a patch.
+///
+/// Unlike [`Patcher`](crate::Patcher), a `ControlReader` doesn't need the old blob, making it
+/// useful for inspecting the structure of a patch.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use ina::unstable::v0::ControlReader;
+///
+/// let mut patch = Vec::new();
+/// ina::diff(&ina::OldBlob::from_slice(b"Hello"), b"Hero", &mut patch)?;
+///
+/// let new_len: usize = ControlReader::new(patch.as_slice())?
+///     .map(|record| record.map(|r| r.add().len() + r.copy().len()))
+///     .sum::<std::io::Result<_>>()?;
+///
+/// assert_eq!(new_len, 4);
+/// # Ok(())
+/// # }
+/// ```
+pub struct ControlReader<'a, B>
+where
+    B: BufRead,
+{
+    payload: PayloadDecoder<'a, B>,
+    metadata: PatchMetadata,
+}
+
+impl<'a, P> ControlReader<'a, BufReader<P>>
+where
+    P: Read,
+{
+    /// Creates a new `ControlReader` for `patch`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an I/O error occurs while reading the patch metadata or if the patch
+    /// metadata is invalid.
+    pub fn new(mut patch: P) -> Result<Self, PatchError> {
+        let metadata = read_header(&mut patch)?;
+
+        Ok(Self {
+            payload: payload::decoder(patch, &metadata)?,
+            metadata,
+        })
+    }
+}
+
+impl<'a, B> ControlReader<'a, B>
+where
+    B: BufRead,
+{
+    /// Returns the metadata of the patch being read
+    pub fn metadata(&self) -> &PatchMetadata {
+        &self.metadata
+    }
+
+    fn read_record(&mut self, add_len: u64) -> io::Result<ControlRecord> {
+        let add = read_field(&mut self.payload, add_len)?;
+        let copy_len = self.payload.read_varint()?;
+        let copy = read_field(&mut self.payload, copy_len)?;
+        let seek = self.payload.read_varint()?;
+
+        Ok(ControlRecord { add, copy, seek })
+    }
+}
+
+impl<'a, B> Iterator for ControlReader<'a, B>
+where
+    B: BufRead,
+{
+    type Item = io::Result<ControlRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        PartialVarint::default()
+            .read(&mut self.payload)
+            .transpose()
+            .map(|add_len| add_len.and_then(|add_len| self.read_record(add_len)))
+    }
+}
+
+/// Reads exactly `len` bytes from `reader` without trusting `len` for the allocation size
+fn read_field<R>(reader: &mut R, len: u64) -> io::Result<Vec<u8>>
+where
+    R: Read,
+{
+    let mut field = Vec::new();
+    reader.take(len).read_to_end(&mut field)?;
+    if (field.len() as u64) < len {
+        return Err(ErrorKind::UnexpectedEof.into());
+    }
+
+    Ok(field)
+}
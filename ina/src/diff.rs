@@ -2,22 +2,42 @@
 //
 // SPDX-License-Identifier: Apache-2.0
 
-use std::io::{self, Write};
+#[cfg(feature = "stats")]
+use std::time::{Duration, Instant};
+use std::{
+    cmp,
+    io::{self, Read, Seek, SeekFrom, Write},
+    ops::Range,
+};
 
-use byteorder::{LittleEndian, WriteBytesExt};
-use integer_encoding::VarIntWriter;
-use zstd::Encoder;
+use sufsort::SuffixArray;
 
+#[cfg(feature = "fec")]
+use crate::FecConfig;
+#[cfg(feature = "stats")]
+use crate::stats::{TimedIter, peak_memory};
 use crate::{
-    bsdiff::ControlProducer,
-    header::{DATA_OFFSET, MAGIC, VERSION_MAJOR, VERSION_MINOR},
+    BaseCheck, BaseDigest, BlockMap, Codec, DiffSettings, FastPath, FileMetadata, OldBlob, Profile,
+    Provenance, Requirements, Target,
+    annotation::{self, MAX_ANNOTATION_LEN},
+    bsdiff::{Control, ControlProducer, Match, MatchMaker},
+    header::{
+        FieldsWriter, TAG_ANNOTATION, TAG_CODEC, TAG_DIFF_SETTINGS, TAG_FILE_MODE,
+        TAG_FILE_MODIFIED, TAG_PROVENANCE_CREATED, TAG_PROVENANCE_NEW_ID, TAG_PROVENANCE_OLD_ID,
+        TAG_PROVENANCE_TOOL, TAG_REQUIREMENTS, TAG_TARGET_ABI, TAG_TARGET_PLATFORM,
+        TAG_TARGET_VERSION_CODE,
+    },
+    mask::{Mask, MaskedMatches},
+    seek_bound::SeekBoundedMatches,
+    stats::DiffStats,
+    text::{TextHints, TextMode, align_to_lines, looks_like_text},
+    writer::{PatchWriter, Recorded},
 };
+#[cfg(feature = "verify")]
+use crate::{BlobDigests, DigestAlgorithm};
 
 /// Constructs a patch between two blobs with default options
 ///
-/// Note that `old` MUST have a `0` appended to the end of the actual old blob for the algorithm to
-/// work properly.
-///
 /// The diffing algorithm used works on arbitrary blobs, but is designed for and particularly
 /// well-suited for creating small patch files between native executables.
 ///
@@ -31,24 +51,22 @@ use crate::{
 ///
 /// Returns an error if an I/O error occurs while writing the patch.
 ///
-/// # Panics
-///
-/// Panics if the last element of `old` is not 0.
-///
 /// # Examples
 ///
 /// ```
 /// # fn main() -> std::io::Result<()> {
-/// let old = b"Hello\0";
+/// use ina::OldBlob;
+///
+/// let old = OldBlob::from_slice(b"Hello");
 /// let new = b"Hero";
 /// let mut patch = Vec::new();
 ///
-/// ina::diff(old, new, &mut patch)?;
+/// ina::diff(&old, new, &mut patch)?;
 ///
 /// # Ok(())
 /// # }
 /// ```
-pub fn diff<W>(old: &[u8], new: &[u8], patch: &mut W) -> io::Result<()>
+pub fn diff<W>(old: &OldBlob, new: &[u8], patch: &mut W) -> io::Result<DiffStats>
 where
     W: Write + ?Sized,
 {
@@ -57,9 +75,6 @@ where
 
 /// Constructs a patch between two blobs
 ///
-/// Note that `old` MUST have a `0` appended to the end of the actual old blob for the algorithm to
-/// work properly.
-///
 /// The diffing algorithm used works on arbitrary blobs, but is designed for and particularly
 /// well-suited for creating small patch files between native executables.
 ///
@@ -70,9 +85,122 @@ where
 ///
 /// Returns an error if an I/O error occurs while writing the patch.
 ///
-/// # Panics
+/// # Examples
+///
+/// ```
+/// # fn main() -> std::io::Result<()> {
+/// use ina::{DiffConfig, OldBlob};
+///
+/// let old = OldBlob::from_slice(b"Hello");
+/// let new = b"Hero";
+/// let mut patch = Vec::new();
 ///
-/// Panics if the last element of `old` is not 0.
+/// ina::diff_with_config(&old, new, &mut patch, &DiffConfig::new().compression_threads(0))?;
+///
+/// # Ok(())
+/// # }
+/// ```
+pub fn diff_with_config<W>(
+    old: &OldBlob,
+    new: &[u8],
+    patch: &mut W,
+    options: &DiffConfig,
+) -> io::Result<DiffStats>
+where
+    W: Write + ?Sized,
+{
+    diff_with_optional_index(old.with_sentinel(), None, new, patch, options)
+}
+
+/// Constructs a patch between two blobs in memory
+///
+/// This function behaves like [`diff_with_config()`], except that the patch is returned rather
+/// than written to a writer.
+///
+/// # Errors
+///
+/// Returns an error if compressing the patch fails.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> std::io::Result<()> {
+/// use ina::{DiffConfig, OldBlob};
+///
+/// let patch = ina::diff_to_vec(&OldBlob::from_slice(b"Hello"), b"Hero", &DiffConfig::new())?;
+///
+/// # Ok(())
+/// # }
+/// ```
+pub fn diff_to_vec(old: &OldBlob, new: &[u8], options: &DiffConfig) -> io::Result<Vec<u8>> {
+    let mut patch = Vec::new();
+    diff_with_optional_index(old.with_sentinel(), None, new, &mut patch, options)?;
+
+    Ok(patch)
+}
+
+/// Constructs a patch between the blob indexed by `index` and another blob
+///
+/// This function behaves like [`diff_with_config()`], except that instead of building a suffix
+/// array of the old blob itself, it uses `index`, which is built by [`OldBlob::index()`]. Since
+/// building the suffix array is usually the most expensive part
+/// of diffing, this allows reusing a single index for many diffs against the same old blob. With
+/// the `index-mapped` feature, the index can also be stored in a file and memory-mapped by several
+/// processes, which then share one copy of it.
+///
+/// # Errors
+///
+/// Returns an error if `options` requests matching against a zeroed copy of the old blob through
+/// [`DiffConfig::zero_masked()`] and [`DiffConfig::mask_ranges()`], since `index` can't reflect the
+/// zeroing, or if an I/O error occurs while writing the patch.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> std::io::Result<()> {
+/// use ina::{DiffConfig, OldBlob};
+///
+/// let old = OldBlob::from_slice(b"Hello");
+/// let index = old.index();
+///
+/// let mut first_patch = Vec::new();
+/// let mut second_patch = Vec::new();
+/// ina::diff_with_index(&index, b"Hero", &mut first_patch, &DiffConfig::new())?;
+/// ina::diff_with_index(&index, b"Help", &mut second_patch, &DiffConfig::new())?;
+///
+/// # Ok(())
+/// # }
+/// ```
+pub fn diff_with_index<W>(
+    index: &SuffixArray<'_>,
+    new: &[u8],
+    patch: &mut W,
+    options: &DiffConfig,
+) -> io::Result<DiffStats>
+where
+    W: Write + ?Sized,
+{
+    if options.zero_masked && !options.old_mask.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "can't zero masked regions of an indexed old blob",
+        ));
+    }
+
+    diff_with_optional_index(index.data(), Some(index), new, patch, options)
+}
+
+/// Constructs a patch between two blobs read from readers
+///
+/// This function behaves like [`diff_with_config()`], except that it reads `old` and `new` to
+/// their ends first. Both blobs are held in memory while diffing.
+///
+/// Combined with [`DiffConfig::anchors()`], this is convenient for build systems which produce
+/// both blobs as streams and know how their sections correspond.
+///
+/// # Errors
+///
+/// Returns an error if an I/O error occurs while reading either blob or writing the patch.
 ///
 /// # Examples
 ///
@@ -80,51 +208,507 @@ where
 /// # fn main() -> std::io::Result<()> {
 /// use ina::DiffConfig;
 ///
-/// let old = b"Hello\0";
-/// let new = b"Hero";
+/// let old = b"Hello".as_slice();
+/// let new = b"Hero".as_slice();
 /// let mut patch = Vec::new();
 ///
-/// ina::diff_with_config(old, new, &mut patch, &DiffConfig::new().compression_threads(0))?;
+/// ina::diff_readers(old, new, &mut patch, &DiffConfig::new())?;
 ///
 /// # Ok(())
 /// # }
 /// ```
-pub fn diff_with_config<W>(
+pub fn diff_readers<O, N, W>(
+    mut old: O,
+    mut new: N,
+    patch: &mut W,
+    options: &DiffConfig,
+) -> io::Result<DiffStats>
+where
+    O: Read,
+    N: Read,
+    W: Write + ?Sized,
+{
+    let mut old_blob = Vec::new();
+    old.read_to_end(&mut old_blob)?;
+    let mut new_blob = Vec::new();
+    new.read_to_end(&mut new_blob)?;
+
+    diff_with_config(&OldBlob::from_vec(old_blob), &new_blob, patch, options)
+}
+
+/// Constructs a patch between two blobs one window at a time
+///
+/// Unlike the other diff functions, this function never holds either blob in memory in its
+/// entirety. Instead, it splits `new` into windows of [`DiffConfig::diff_window_len()`] bytes and
+/// diffs each of them against a window of `old` around the corresponding position, which is up to
+/// twice as long. Both blobs are read from their current positions to their ends. Positions are
+/// tracked as `u64`, so this function can diff blobs larger
+/// than the address space, such as files over 4 GiB on 32-bit targets.
+///
+/// Memory usage is bounded by roughly 11 times the window length regardless of the size of the
+/// blobs. In exchange, matches are only found between corresponding regions of the blobs, so the
+/// patch may be larger than one created by [`diff_with_config()`] if data moved further than the
+/// window length. Anchors, masks, and text mode aren't supported, and patches are always created
+/// in binary mode.
+///
+/// # Errors
+///
+/// Returns an error if the configuration contains anchors, masks, or [`TextMode::Text`], or if an
+/// I/O error occurs while reading either blob or writing the patch.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use std::io::Cursor;
+/// use ina::DiffConfig;
+///
+/// let old = Cursor::new(b"Hello, world!");
+/// let new = Cursor::new(b"Hello, there!");
+/// let mut patch = Vec::new();
+///
+/// let options = DiffConfig::new().diff_window_len(4).clone();
+/// ina::diff_windowed(old, new, &mut patch, &options)?;
+///
+/// let mut patched = Vec::new();
+/// ina::patch(Cursor::new(b"Hello, world!"), patch.as_slice(), &mut patched)?;
+/// assert_eq!(patched, b"Hello, there!");
+/// # Ok(())
+/// # }
+/// ```
+pub fn diff_windowed<O, N, W>(
+    mut old: O,
+    mut new: N,
+    patch: &mut W,
+    options: &DiffConfig,
+) -> io::Result<DiffStats>
+where
+    O: Read + Seek,
+    N: Read + Seek,
+    W: Write + ?Sized,
+{
+    if !options.anchors.is_empty()
+        || !options.old_mask.is_empty()
+        || !options.new_mask.is_empty()
+        || options.text_mode == TextMode::Text
+    {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "anchors, masks, and text mode aren't supported by windowed diffs",
+        ));
+    }
+
+    let old_start = old.stream_position()?;
+    let old_len = old.seek(SeekFrom::End(0))?.saturating_sub(old_start);
+    old.seek(SeekFrom::Start(old_start))?;
+    let new_start = new.stream_position()?;
+    let new_len = new.seek(SeekFrom::End(0))?.saturating_sub(new_start);
+    new.seek(SeekFrom::Start(new_start))?;
+
+    let base_digest = BaseDigest::of_reader(&mut old, options.base_check)?;
+    let block_map = options
+        .block_map_size()?
+        .map(|block_size| BlockMap::of_readers(&mut old, &mut new, block_size))
+        .transpose()?;
+    #[cfg(feature = "verify")]
+    let blob_digests = options
+        .embed_digests
+        .map(|algorithm| BlobDigests::of_readers(algorithm, &mut old, &mut new))
+        .transpose()?;
+    let recorded = Recorded {
+        new_len: Some(new_len),
+        text_hints: None,
+        base_digest: base_digest.as_ref(),
+        block_map: block_map.as_ref(),
+        #[cfg(feature = "verify")]
+        blob_digests: blob_digests.as_ref(),
+    };
+    let mut writer = PatchWriter::with_digests(patch, options, &recorded)?;
+
+    let window_len = options.diff_window_len;
+    let windows = new_len.div_ceil(window_len);
+    let old_window = |index: u64| {
+        let new_offset = index * window_len;
+        let new_window_len = cmp::min(window_len, new_len - new_offset);
+        // The position in the old blob proportional to the window's position in the new blob
+        let center = (u128::from(new_offset) * u128::from(old_len) / u128::from(new_len)) as u64;
+        let start = center.saturating_sub(window_len / 2);
+        let end = cmp::min(old_len, center + new_window_len + window_len / 2);
+
+        (cmp::min(start, end), end)
+    };
+
+    let (mut old_buf, mut new_buf) = (Vec::new(), Vec::new());
+    // The position in the old blob at which the last read ended
+    let mut read_end: u64 = 0;
+    for index in 0..windows {
+        let new_offset = index * window_len;
+        let new_window_len = cmp::min(window_len, new_len - new_offset);
+        read_window(
+            &mut new,
+            new_start + new_offset,
+            new_window_len,
+            &mut new_buf,
+        )?;
+        let (old_offset, old_end) = old_window(index);
+        read_window(
+            &mut old,
+            old_start + old_offset,
+            old_end - old_offset,
+            &mut old_buf,
+        )?;
+        old_buf.push(0);
+
+        // The position in the old blob the next window's matches are relative to, which the last
+        // record of this window seeks to
+        let next_old_offset = (index + 1 < windows).then(|| old_window(index + 1).0);
+        let matches = SeekBoundedMatches::new(
+            MatchMaker::new(&old_buf, &new_buf, options.match_threshold),
+            options.max_backward_seek.unwrap_or(u64::MAX),
+            read_end.saturating_sub(old_offset) as usize,
+        );
+        let mut controls = ControlProducer::from_matches(&old_buf, &new_buf, matches).peekable();
+        let mut old_pos = old_offset;
+        while let Some(control) = controls.next() {
+            old_pos += control.add().len() as u64;
+            if !control.add().is_empty() {
+                read_end = old_pos;
+            }
+            let seek = match (controls.peek(), next_old_offset) {
+                (None, Some(next_old_offset)) => next_old_offset as i64 - old_pos as i64,
+                _ => control.seek(),
+            };
+            old_pos = old_pos.wrapping_add_signed(seek);
+            writer.write_record(control.add(), control.copy(), seek)?;
+        }
+    }
+
+    writer
+        .finish_with_stats()
+        .map(|(_, stats)| DiffStats { old_len, ..stats })
+}
+
+/// Reads `len` bytes of `reader` starting at `offset` into `buf`, replacing its contents
+fn read_window<R>(reader: &mut R, offset: u64, len: u64, buf: &mut Vec<u8>) -> io::Result<()>
+where
+    R: Read + Seek,
+{
+    buf.clear();
+    reader.seek(SeekFrom::Start(offset))?;
+    reader.take(len).read_to_end(buf)?;
+    if (buf.len() as u64) < len {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+
+    Ok(())
+}
+
+/// Constructs a patch between `old` and `new`, searching for matches with `index` if given or a
+/// newly built index of `old` otherwise
+fn diff_with_optional_index<W>(
     old: &[u8],
+    index: Option<&SuffixArray<'_>>,
     new: &[u8],
-    mut patch: &mut W,
+    patch: &mut W,
     options: &DiffConfig,
-) -> io::Result<()>
+) -> io::Result<DiffStats>
 where
     W: Write + ?Sized,
 {
-    // Write the header
-    patch.write_u32::<LittleEndian>(MAGIC)?;
-    patch.write_u16::<LittleEndian>(VERSION_MAJOR)?;
-    patch.write_u16::<LittleEndian>(VERSION_MINOR)?;
-    patch.write_varint(DATA_OFFSET)?;
+    // Match against copies of the blobs with their masked regions zeroed if requested. The old blob
+    // has no masked regions if an index is given.
+    let (masked_old, masked_new);
+    let (match_old, match_new) =
+        if options.zero_masked && !(options.old_mask.is_empty() && options.new_mask.is_empty()) {
+            masked_old = index.is_none().then(|| options.old_mask.zeroed(old));
+            masked_new = options.new_mask.zeroed(new);
+            (masked_old.as_deref().unwrap_or(old), masked_new.as_slice())
+        } else {
+            (old, new)
+        };
+
+    // Exclude the sentinel
+    let text_old = &old[..old.len().saturating_sub(1)];
+    let fast_path = fast_path(text_old, new, options);
 
-    // Create a compressor for the inner patch data
-    let mut patch_encoder = Encoder::new(patch, options.compression_level)?;
-    patch_encoder.multithread(options.compression_threads)?;
+    #[cfg(feature = "stats")]
+    let mut suffix_array_time = Duration::ZERO;
+    #[cfg(feature = "stats")]
+    let mut match_time = Duration::ZERO;
+    let matches: Box<dyn Iterator<Item = Match>> = match fast_path {
+        // The old blob is the start of the new blob, so a single match covers all of it
+        Some(_) => Box::new(
+            (!new.is_empty())
+                .then(|| Match::new(0, 0, text_old.len(), new.len()))
+                .into_iter(),
+        ),
+        None => {
+            #[cfg(feature = "stats")]
+            let index_start = Instant::now();
+            let matches = match index {
+                Some(index) => MatchMaker::with_index(index, match_new, options.match_threshold),
+                None => MatchMaker::new(match_old, match_new, options.match_threshold),
+            }
+            .with_anchors(&options.anchors);
+            #[cfg(feature = "stats")]
+            {
+                suffix_array_time = index_start.elapsed();
+            }
+            #[cfg(feature = "stats")]
+            let matches = TimedIter::new(matches, &mut match_time);
+
+            Box::new(matches)
+        }
+    };
 
-    // Iterate over bsdiff control values, writing them to the patch stream
-    for control in ControlProducer::new(old, new) {
-        // Write add section
-        patch_encoder.write_varint(control.add().len())?;
-        patch_encoder.write_all(control.add())?;
+    let matches = MaskedMatches::new(matches, &options.old_mask, &options.new_mask);
+    let max_backward_seek = options.max_backward_seek.unwrap_or(u64::MAX);
+    let base_digest = BaseDigest::of(text_old, options.base_check);
+    let block_map = options
+        .block_map_size()?
+        .map(|block_size| BlockMap::of(text_old, new, block_size));
+    #[cfg(feature = "verify")]
+    let blob_digests = options
+        .embed_digests
+        .map(|algorithm| BlobDigests::of(algorithm, text_old, new));
+    let recorded = Recorded {
+        new_len: Some(new.len() as u64),
+        text_hints: None,
+        base_digest: base_digest.as_ref(),
+        block_map: block_map.as_ref(),
+        #[cfg(feature = "verify")]
+        blob_digests: blob_digests.as_ref(),
+    };
+    let text = match options.text_mode {
+        TextMode::Binary => false,
+        TextMode::Auto => looks_like_text(text_old) && looks_like_text(new),
+        TextMode::Text => true,
+    };
+    let stats = if text {
+        // The hints are written to the header, so all matches must be found before writing
+        let aligned = matches.map(|m| align_to_lines(m, text_old.len(), new));
+        let matches: Vec<_> = SeekBoundedMatches::new(aligned, max_backward_seek, 0).collect();
+        let hints = TextHints::from_matches(text_old, new, &matches);
+        let recorded = Recorded {
+            text_hints: Some(&hints),
+            ..recorded
+        };
+        let writer = PatchWriter::with_digests(patch, options, &recorded)?;
+        write_records(
+            ControlProducer::from_matches(old, new, matches.into_iter()),
+            writer,
+        )?
+    } else {
+        write_patch(
+            ControlProducer::from_matches(
+                old,
+                new,
+                SeekBoundedMatches::new(matches, max_backward_seek, 0),
+            ),
+            patch,
+            options,
+            &recorded,
+        )?
+    };
 
-        // Write copy section
-        patch_encoder.write_varint(control.copy().len())?;
-        patch_encoder.write_all(control.copy())?;
+    Ok(DiffStats {
+        // Exclude the sentinel
+        old_len: old.len().saturating_sub(1) as u64,
+        fast_path,
+        #[cfg(feature = "stats")]
+        suffix_array_time,
+        #[cfg(feature = "stats")]
+        match_time,
+        #[cfg(feature = "stats")]
+        peak_memory: peak_memory(),
+        ..stats
+    })
+}
 
-        // Write seek value
-        patch_encoder.write_varint(control.seek())?;
+/// Returns the shortcut diffing `old`, which excludes the sentinel, and `new` may take instead of
+/// searching for matches, if any
+///
+/// Anchors and masks influence which matches are found, so no shortcut is taken if any are given.
+fn fast_path(old: &[u8], new: &[u8], options: &DiffConfig) -> Option<FastPath> {
+    if !options.anchors.is_empty() || !options.old_mask.is_empty() || !options.new_mask.is_empty() {
+        return None;
     }
 
-    patch_encoder.finish()?;
+    match new.strip_prefix(old)? {
+        [] => Some(FastPath::Identical),
+        // Everything would be appended to nothing, which the search handles just as quickly
+        _ if old.is_empty() => None,
+        _ => Some(FastPath::Append),
+    }
+}
 
-    Ok(())
+/// Constructs a patch between two blobs from externally computed matches
+///
+/// This function behaves like [`diff_with_config()`], except that instead of searching for matches
+/// between `old` and `new` itself, it uses those given by `matches`. This allows experimenting with
+/// alternative matching algorithms while retaining the patch encoding.
+///
+/// The matches must be ordered and contiguous in the new blob, i.e., the first match must begin at
+/// position 0 of `new`, every subsequent match must begin where the previous one's copy region
+/// ends, and the last match must end at the end of `new`.
+///
+/// # Errors
+///
+/// Returns an error if any match lies outside of `old` or `new`, if the matches aren't contiguous
+/// or don't cover all of `new`, or if an I/O error occurs while writing the patch.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> std::io::Result<()> {
+/// use ina::{DiffConfig, OldBlob, unstable::v0::{Match, diff_with_matches}};
+///
+/// let old = OldBlob::from_slice(b"Hello");
+/// let new = b"Hero";
+/// let mut patch = Vec::new();
+///
+/// // Store "He" as a difference against the old blob and "ro" verbatim
+/// let matches = [Match::new(0, 0, 2, 4)];
+/// diff_with_matches(&old, new, matches, &mut patch, &DiffConfig::new())?;
+///
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "unstable")]
+pub fn diff_with_matches<I, W>(
+    old: &OldBlob,
+    new: &[u8],
+    matches: I,
+    patch: &mut W,
+    options: &DiffConfig,
+) -> io::Result<DiffStats>
+where
+    I: IntoIterator<Item = crate::bsdiff::Match>,
+    W: Write + ?Sized,
+{
+    let old = old.as_bytes();
+    let matches: Vec<_> = matches.into_iter().collect();
+
+    let mut new_pos = 0;
+    for m in &matches {
+        let in_bounds = m.add_old_pos().checked_add(m.add_len()) <= Some(old.len())
+            && m.add_new_pos().checked_add(m.add_len()) <= Some(m.copy_end())
+            && m.copy_end() <= new.len();
+        if !in_bounds || m.add_new_pos() != new_pos {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "match is out of bounds or not contiguous",
+            ));
+        }
+        new_pos = m.copy_end();
+    }
+    if new_pos != new.len() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "matches end before the end of the new blob",
+        ));
+    }
+    let base_digest = BaseDigest::of(old, options.base_check);
+    let block_map = options
+        .block_map_size()?
+        .map(|block_size| BlockMap::of(old, new, block_size));
+    #[cfg(feature = "verify")]
+    let blob_digests = options
+        .embed_digests
+        .map(|algorithm| BlobDigests::of(algorithm, old, new));
+    let recorded = Recorded {
+        new_len: Some(new.len() as u64),
+        text_hints: None,
+        base_digest: base_digest.as_ref(),
+        block_map: block_map.as_ref(),
+        #[cfg(feature = "verify")]
+        blob_digests: blob_digests.as_ref(),
+    };
+
+    write_patch(
+        ControlProducer::from_matches(old, new, matches.into_iter()),
+        patch,
+        options,
+        &recorded,
+    )
+    .map(|stats| DiffStats {
+        old_len: old.len() as u64,
+        ..stats
+    })
+}
+
+/// Constructs a patch between two blobs from the matches found by `matcher`
+///
+/// This function behaves like [`diff_with_config()`], except that the matches between `old` and
+/// `new` are found by `matcher` rather than the built-in matching algorithm. The matcher settings
+/// of `options` are ignored.
+///
+/// # Errors
+///
+/// Returns an error if the matches found by `matcher` are invalid as described by
+/// [`diff_with_matches()`], or if an I/O error occurs while writing the patch.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> std::io::Result<()> {
+/// use ina::{DiffConfig, OldBlob, unstable::v0::{BsdiffMatcher, diff_with_matcher}};
+///
+/// let old = OldBlob::from_slice(b"Hello");
+/// let mut patch = Vec::new();
+/// diff_with_matcher(&old, b"Hero", &mut BsdiffMatcher::new(4), &mut patch, &DiffConfig::new())?;
+///
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "unstable")]
+pub fn diff_with_matcher<M, W>(
+    old: &OldBlob,
+    new: &[u8],
+    matcher: &mut M,
+    patch: &mut W,
+    options: &DiffConfig,
+) -> io::Result<DiffStats>
+where
+    M: crate::bsdiff::Matcher + ?Sized,
+    W: Write + ?Sized,
+{
+    let matches = matcher.find_matches(old, new);
+
+    diff_with_matches(old, new, matches, patch, options)
+}
+
+/// Writes a patch consisting of `controls` to `patch` whose header records `recorded`, returning
+/// statistics about it
+///
+/// The length of the old blob is left for the caller to fill in.
+fn write_patch<'a, C, W>(
+    controls: C,
+    patch: &mut W,
+    options: &DiffConfig,
+    recorded: &Recorded,
+) -> io::Result<DiffStats>
+where
+    C: Iterator<Item = Control<'a>>,
+    W: Write + ?Sized,
+{
+    write_records(
+        controls,
+        PatchWriter::with_digests(patch, options, recorded)?,
+    )
+}
+
+/// Writes `controls` to `writer` and finishes it, returning statistics about the patch
+fn write_records<'a, C, W>(controls: C, mut writer: PatchWriter<W>) -> io::Result<DiffStats>
+where
+    C: Iterator<Item = Control<'a>>,
+    W: Write,
+{
+    for control in controls {
+        writer.write_record(control.add(), control.copy(), control.seek())?;
+    }
+
+    writer.finish_with_stats().map(|(_, stats)| stats)
 }
 
 /// Configuration for a diff operation.
@@ -132,10 +716,38 @@ where
 /// This struct can be used to fine-tune parameters to the diffing algorithm. The defaults should
 /// be optimal for most use cases, but you may wish to change them in especially
 /// resource-constrained or powerful computing environments for better performance.
-#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
+#[derive(Clone, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(remote = "Self", default)
+)]
 pub struct DiffConfig {
-    compression_threads: u32,
-    compression_level: i32,
+    pub(crate) compression_threads: u32,
+    pub(crate) compression_level: i32,
+    pub(crate) window_log: Option<u32>,
+    pub(crate) codec: Codec,
+    pub(crate) match_threshold: usize,
+    max_backward_seek: Option<u64>,
+    pub(crate) anchors: Vec<(usize, usize)>,
+    old_mask: Mask,
+    new_mask: Mask,
+    zero_masked: bool,
+    text_mode: TextMode,
+    file_metadata: Option<FileMetadata>,
+    target: Option<Target>,
+    provenance: Option<Provenance>,
+    annotation: Option<String>,
+    record_settings: bool,
+    pub(crate) record_new_len: bool,
+    pub(crate) payload_checksum: bool,
+    base_check: BaseCheck,
+    block_map: Option<u32>,
+    diff_window_len: u64,
+    #[cfg(feature = "fec")]
+    pub(crate) fec: Option<FecConfig>,
+    #[cfg(feature = "verify")]
+    embed_digests: Option<DigestAlgorithm>,
 }
 
 impl DiffConfig {
@@ -146,9 +758,42 @@ impl DiffConfig {
         Self {
             compression_threads: Self::DEFAULT_COMPRESSION_THREADS,
             compression_level: Self::DEFAULT_COMPRESSION_LEVEL,
+            window_log: None,
+            codec: Codec::Zstd,
+            match_threshold: Self::DEFAULT_MATCH_THRESHOLD,
+            max_backward_seek: None,
+            anchors: Vec::new(),
+            old_mask: Mask::new(),
+            new_mask: Mask::new(),
+            zero_masked: false,
+            text_mode: TextMode::Binary,
+            file_metadata: None,
+            target: None,
+            provenance: None,
+            annotation: None,
+            record_settings: false,
+            record_new_len: false,
+            payload_checksum: false,
+            base_check: BaseCheck::None,
+            block_map: None,
+            diff_window_len: Self::DEFAULT_DIFF_WINDOW_LEN,
+            #[cfg(feature = "fec")]
+            fec: None,
+            #[cfg(feature = "verify")]
+            embed_digests: None,
         }
     }
 
+    /// Applies a preset of settings suited to a class of artifacts.
+    ///
+    /// The profile sets the settings it covers, as described by its variant of [`Profile`], and
+    /// leaves the others as they are. Settings changed afterward override the profile's values, so
+    /// apply a profile before changing individual settings.
+    pub fn profile(&mut self, profile: Profile) -> &mut Self {
+        profile.apply(self);
+        self
+    }
+
     /// Sets the number of threads to use for compressing the patch file.
     ///
     /// Setting this to a value more than 0 allows compression to run on a separate thread than
@@ -158,6 +803,9 @@ impl DiffConfig {
     ///
     /// A value of 0 means that compression will run on the same thread as I/O, reducing diffing
     /// speed but slightly lowering memory usage.
+    ///
+    /// If a sandbox has been enabled via the `sandbox` module, compression always runs on the same
+    /// thread as I/O since the sandbox forbids creating threads.
     pub fn compression_threads(&mut self, threads: u32) -> &mut Self {
         self.compression_threads = threads;
         self
@@ -176,6 +824,428 @@ impl DiffConfig {
         self
     }
 
+    /// Sets the base-2 logarithm of the compression window size.
+    ///
+    /// Larger windows allow the compressor to find redundancy further back in the patch stream at
+    /// the cost of memory usage during both diffing and patching. Values are clamped to the range
+    /// 10-27 inclusive so that patches never require a window larger than a [`Patcher`] accepts by
+    /// default.
+    ///
+    /// If unset, the window size is chosen by the compressor based on the compression level.
+    ///
+    /// [`Patcher`]: crate::Patcher
+    pub fn window_log(&mut self, window_log: Option<u32>) -> &mut Self {
+        self.window_log =
+            window_log.map(|log| log.clamp(Self::MIN_WINDOW_LOG, Self::MAX_WINDOW_LOG));
+        self
+    }
+
+    /// Sets the codec to compress the patch file with.
+    ///
+    /// Patches compressed with a codec other than the default, zstd, record it in their header and
+    /// can only be applied by patchers which support it. The compression level and window log are
+    /// translated for other codecs as described by their variants of [`Codec`]. The number of
+    /// compression threads only applies to zstd.
+    pub fn codec(&mut self, codec: Codec) -> &mut Self {
+        self.codec = codec;
+        self
+    }
+
+    /// Sets the number of mismatching bytes the matcher tolerates before starting a new match.
+    ///
+    /// When scanning the new blob, the matcher keeps extending the current approximate match until
+    /// it finds an exact match in the old blob that is at least this many bytes longer than the
+    /// approximate one. Lower values produce more, shorter control records, while higher values
+    /// produce fewer, longer ones. The best value depends on the inputs, so this is mainly useful
+    /// for tuning against a representative corpus.
+    ///
+    /// A value of 0 is treated as 1.
+    pub fn match_threshold(&mut self, threshold: usize) -> &mut Self {
+        self.match_threshold = threshold.max(1);
+        self
+    }
+
+    /// Sets the maximum distance in bytes applying the patch may seek backward in the old blob.
+    ///
+    /// Matches which would require seeking further back than this from the end of the previous
+    /// read of the old blob are discarded, and the regions of the new blob they cover are stored
+    /// verbatim instead. This allows applying patches to old blobs which can only be read forward,
+    /// with a value of 0, or which only retain a limited amount of already read data, e.g., ones
+    /// streamed from the network, at the cost of larger patches. [`DiffStats::seeks()`] reports
+    /// the seeks a patch actually requires.
+    ///
+    /// If unset, matches may lie anywhere in the old blob. This setting is ignored when diffing
+    /// from externally computed matches.
+    pub fn max_backward_seek(&mut self, max_backward_seek: Option<u64>) -> &mut Self {
+        self.max_backward_seek = max_backward_seek;
+        self
+    }
+
+    /// Sets known corresponding positions of the old and new blobs to guide matching.
+    ///
+    /// Each anchor is a pair of an old and a new position whose contents are expected to
+    /// correspond, e.g., the start of a section in both versions of a linked binary as known by the
+    /// build system. When scanning the new blob reaches an anchor's new position, the matcher
+    /// assumes the same offset between the blobs as the anchor until it finds a better match. This
+    /// keeps matching local in very large blobs whose layout shifts, where the scan may otherwise
+    /// drift to unrelated regions. Anchors are hints, so wrong ones only cost patch size. By
+    /// default, there are no anchors.
+    ///
+    /// Anchors only apply to [`diff_with_config()`], [`diff_with_index()`], and
+    /// [`diff_readers()`], and positions are given in the blobs passed to them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ina::DiffConfig;
+    ///
+    /// // The .data section moved from 0x4000 to 0x5200 between versions
+    /// let mut config = DiffConfig::new();
+    /// config.anchors([(0, 0), (0x4000, 0x5200)]);
+    /// ```
+    pub fn anchors<I>(&mut self, anchors: I) -> &mut Self
+    where
+        I: IntoIterator<Item = (usize, usize)>,
+    {
+        let mut anchors: Vec<_> = anchors.into_iter().collect();
+        anchors.sort_by_key(|&(old_pos, new_pos)| (new_pos, old_pos));
+        self.anchors = anchors;
+        self
+    }
+
+    /// Sets the regions of the old and new blobs to exclude from matching.
+    ///
+    /// Bytes in masked regions of the new blob are always stored verbatim in the patch, and bytes
+    /// in masked regions of the old blob are never referenced by it. This is useful for regions
+    /// which are known to differ between versions and would otherwise be matched poorly, such as
+    /// embedded signatures or timestamps. Ranges may overlap and are given as positions in the
+    /// blobs passed to [`diff_with_config()`]. By default, no regions are masked.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// use ina::{DiffConfig, OldBlob};
+    ///
+    /// let old = OldBlob::from_slice(b"header SIGNATURE-1 body");
+    /// let new = b"header SIGNATURE-2 body";
+    /// let mut config = DiffConfig::new();
+    /// config.mask_ranges([7..18], [7..18]).zero_masked(true);
+    ///
+    /// let mut patch = Vec::new();
+    /// let stats = ina::diff_with_config(&old, new, &mut patch, &config)?;
+    /// assert!(stats.copy_bytes() >= 11);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn mask_ranges<O, N>(&mut self, old_ranges: O, new_ranges: N) -> &mut Self
+    where
+        O: IntoIterator<Item = Range<usize>>,
+        N: IntoIterator<Item = Range<usize>>,
+    {
+        self.old_mask = Mask::from_ranges(old_ranges);
+        self.new_mask = Mask::from_ranges(new_ranges);
+        self
+    }
+
+    /// Sets whether to treat masked regions as zeroed while matching.
+    ///
+    /// By default, the matcher sees the actual contents of masked regions, so matches tend to end
+    /// at their boundaries when the contents differ. Zeroing them allows matches to span across
+    /// masked regions, improving matching around them at the cost of copying both blobs. Masked
+    /// bytes are stored verbatim either way.
+    pub fn zero_masked(&mut self, zero: bool) -> &mut Self {
+        self.zero_masked = zero;
+        self
+    }
+
+    /// Sets how the inputs are treated.
+    ///
+    /// When the inputs are treated as text, matches are aligned to line boundaries where possible
+    /// and the patch records [`TextHints`] summarizing which lines changed, which can be used to
+    /// review a patch without the old or new blob. Aligning matches may make patches slightly
+    /// larger. See [`TextMode`] for details. By default, the inputs are treated as binary.
+    ///
+    /// Text mode only applies to [`diff_with_config()`] and [`diff_with_index()`].
+    pub fn text_mode(&mut self, mode: TextMode) -> &mut Self {
+        self.text_mode = mode;
+        self
+    }
+
+    /// Sets the metadata of the new file to record in the patch.
+    ///
+    /// The recorded metadata can be restored to the reconstructed file after patching, e.g., to
+    /// ensure a reconstructed executable is marked as executable. See [`FileMetadata`] for
+    /// details. By default, no file metadata is recorded.
+    pub fn file_metadata(&mut self, metadata: Option<FileMetadata>) -> &mut Self {
+        self.file_metadata = metadata;
+        self
+    }
+
+    /// Sets the target of the new file to record in the patch.
+    ///
+    /// The recorded target can be checked before patching to ensure the patch isn't applied on a
+    /// device the new file wasn't built for. See [`Target`] for details. By default, no target is
+    /// recorded.
+    pub fn target(&mut self, target: Option<Target>) -> &mut Self {
+        self.target = target;
+        self
+    }
+
+    /// Sets the provenance to record in the patch.
+    ///
+    /// Recording provenance makes patches depend on more than their inputs and settings, e.g., on
+    /// the time they were created, so it's opt-in to keep patch creation reproducible. See
+    /// [`Provenance`] for details. By default, no provenance is recorded.
+    pub fn provenance(&mut self, provenance: Option<Provenance>) -> &mut Self {
+        self.provenance = provenance;
+        self
+    }
+
+    /// Sets a free-form annotation to record in the patch, such as release notes.
+    ///
+    /// The annotation is stored compressed in the patch header, so it travels with the patch and
+    /// can be read with [`PatchMetadata::annotation()`] without applying it. Annotations may be at
+    /// most 1 MiB long; diffing fails with an error of kind
+    /// [`InvalidInput`](io::ErrorKind::InvalidInput) for longer ones. By default, no annotation is
+    /// recorded.
+    ///
+    /// [`PatchMetadata::annotation()`]: crate::PatchMetadata::annotation
+    pub fn annotation(&mut self, annotation: Option<&str>) -> &mut Self {
+        self.annotation = annotation.map(str::to_owned);
+        self
+    }
+
+    /// Sets whether to record the compression and matcher settings in the patch.
+    ///
+    /// Recorded settings allow [`check_rediff()`](crate::check_rediff) to tell whether a patch was
+    /// created with different settings than the current ones without the old and new blobs. See
+    /// [`DiffSettings`] for details. By default, no settings are recorded.
+    pub fn record_settings(&mut self, record_settings: bool) -> &mut Self {
+        self.record_settings = record_settings;
+        self
+    }
+
+    /// Sets whether to record the length of the new blob in the patch.
+    ///
+    /// The recorded length allows [`patch_to_file()`](crate::patch_to_file) to size the new file
+    /// before writing it, and is returned by [`PatchMetadata::new_len()`]. It isn't recorded in
+    /// patches written record by record with a [`PatchWriter`](crate::PatchWriter), since their
+    /// length isn't known in advance. By default, the length isn't recorded.
+    ///
+    /// [`PatchMetadata::new_len()`]: crate::PatchMetadata::new_len
+    pub fn record_new_len(&mut self, record_new_len: bool) -> &mut Self {
+        self.record_new_len = record_new_len;
+        self
+    }
+
+    /// Sets whether to record a checksum of the compressed patch data in the patch.
+    ///
+    /// The checksum covers the compressed data rather than the new blob, so it detects corruption
+    /// of the patch, e.g., by a CDN or storage, without the cost of verifying the output and even
+    /// for patches which aren't signed. A [`Patcher`](crate::Patcher) checks the data against the
+    /// checksum as it's read and returns an error of kind
+    /// [`InvalidData`](std::io::ErrorKind::InvalidData) on a mismatch, which also detects
+    /// truncated patches. Since the mismatch can only be detected once all of the data has been
+    /// read, the output of a `Patcher` must not be used unless it finishes without an error.
+    ///
+    /// Note that recording a checksum requires the entire compressed patch to be buffered in
+    /// memory while diffing. By default, no checksum is recorded.
+    pub fn payload_checksum(&mut self, payload_checksum: bool) -> &mut Self {
+        self.payload_checksum = payload_checksum;
+        self
+    }
+
+    /// Sets how much of the old blob to record in the patch so that applying it to the wrong old
+    /// blob is detected up front.
+    ///
+    /// See [`BaseCheck`] for the trade-off between the modes. [`PatchWriter`] doesn't see the old
+    /// blob, so it ignores this setting. By default, the old blob isn't recorded.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use std::io::Cursor;
+    /// use ina::{BaseCheck, DiffConfig, OldBlob, PatchError, Patcher};
+    ///
+    /// let mut patch = Vec::new();
+    /// let options = DiffConfig::new().base_check(BaseCheck::sampled()).clone();
+    /// ina::diff_with_config(&OldBlob::from_slice(b"Hello"), b"Hero", &mut patch, &options)?;
+    ///
+    /// let result = Patcher::new(Cursor::new(b"Jello"), patch.as_slice());
+    /// assert!(matches!(result, Err(PatchError::OldMismatch(_))));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn base_check(&mut self, base_check: BaseCheck) -> &mut Self {
+        self.base_check = base_check;
+        self
+    }
+
+    /// Sets the size in bytes of the blocks of the new blob to record a [`BlockMap`] of in the
+    /// patch.
+    ///
+    /// The block map records which blocks of the new blob differ from the old blob, so that
+    /// updaters operating on block devices only need to write those, e.g., with
+    /// [`Patcher::block_updates()`]. Computing it compares the new blob with the old blob in its
+    /// entirety. The block size must be a power of two between [`BlockMap::MIN_BLOCK_SIZE`] and
+    /// [`BlockMap::MAX_BLOCK_SIZE`], and the diff functions return
+    /// [`InvalidInput`](io::ErrorKind::InvalidInput) for other sizes. [`PatchWriter`] doesn't see
+    /// either blob, so it ignores this setting. By default, no block map is recorded.
+    ///
+    /// [`Patcher::block_updates()`]: crate::Patcher::block_updates
+    pub fn block_map(&mut self, block_size: Option<u32>) -> &mut Self {
+        self.block_map = block_size;
+        self
+    }
+
+    /// Returns the block size of the block map to record, if any
+    ///
+    /// Returns an error if the block size isn't supported.
+    fn block_map_size(&self) -> io::Result<Option<u32>> {
+        match self.block_map {
+            Some(block_size) if !BlockMap::is_valid_block_size(block_size) => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "block map block size {block_size} isn't a power of two between {} and {}",
+                    BlockMap::MIN_BLOCK_SIZE,
+                    BlockMap::MAX_BLOCK_SIZE,
+                ),
+            )),
+            block_size => Ok(block_size),
+        }
+    }
+
+    /// Sets the algorithm to record digests of the old and new blobs with, or `None` not to record
+    /// them.
+    ///
+    /// [`Patcher`](crate::Patcher) checks the old blob against its digest before producing any
+    /// output and the new blob against its digest once it has produced all of it. See
+    /// [`BlobDigests`] for details. Computing the digests reads both blobs in their entirety.
+    /// [`PatchWriter`] doesn't see either blob, so it ignores this setting. By default, no digests
+    /// are recorded.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use std::io::{self, Cursor};
+    /// use ina::{DiffConfig, DigestAlgorithm, OldBlob, PatchError, Patcher};
+    ///
+    /// let mut patch = Vec::new();
+    /// let options = DiffConfig::new()
+    ///     .embed_digests(Some(DigestAlgorithm::Blake3))
+    ///     .clone();
+    /// ina::diff_with_config(&OldBlob::from_slice(b"Hello"), b"Hero", &mut patch, &options)?;
+    ///
+    /// let result = Patcher::new(Cursor::new(b"Hellp"), patch.as_slice());
+    /// assert!(matches!(result, Err(PatchError::OldMismatch(_))));
+    ///
+    /// let mut patcher = Patcher::new(Cursor::new(b"Hello"), patch.as_slice())?;
+    /// io::copy(&mut patcher, &mut io::sink())?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "verify")]
+    pub fn embed_digests(&mut self, algorithm: Option<DigestAlgorithm>) -> &mut Self {
+        self.embed_digests = algorithm;
+        self
+    }
+
+    /// Sets the length in bytes of the windows of the new blob diffed by [`diff_windowed()`].
+    ///
+    /// Longer windows find more matches between data that moved between the old and new blobs,
+    /// but use proportionally more memory. The other diff functions ignore this setting. A length
+    /// of 0 is treated as 1.
+    pub fn diff_window_len(&mut self, len: u64) -> &mut Self {
+        self.diff_window_len = cmp::max(len, 1);
+        self
+    }
+
+    /// Sets the forward error correction parameters to protect the patch with.
+    ///
+    /// Forward error correction allows a [`Patcher`](crate::Patcher) to correct a bounded amount
+    /// of corruption in the patch, which is useful when patches are delivered over channels that
+    /// can't retransmit data. See [`FecConfig`] for details. By default, no forward error
+    /// correction is used.
+    ///
+    /// Note that enabling forward error correction requires the entire compressed patch to be
+    /// buffered in memory while diffing.
+    #[cfg(feature = "fec")]
+    pub fn fec(&mut self, fec: Option<FecConfig>) -> &mut Self {
+        self.fec = fec;
+        self
+    }
+
+    /// Encodes the header extension fields described by this configuration
+    ///
+    /// Returns an error if the annotation is too long to be recorded.
+    pub(crate) fn header_fields(&self) -> io::Result<FieldsWriter> {
+        let mut fields = FieldsWriter::default();
+
+        // Requirements come first so that patchers lacking them fail before parsing anything else
+        if let Some(requirements) = Requirements::of(self) {
+            fields.push(TAG_REQUIREMENTS, &requirements.encode_field());
+        }
+
+        if let Some(metadata) = self.file_metadata {
+            if let Some(mode) = metadata.encode_mode() {
+                fields.push(TAG_FILE_MODE, &mode);
+            }
+            if let Some(modified) = metadata.encode_modified() {
+                fields.push(TAG_FILE_MODIFIED, &modified);
+            }
+        }
+
+        if let Some(target) = &self.target {
+            if let Some(platform) = target.encode_platform() {
+                fields.push(TAG_TARGET_PLATFORM, &platform);
+            }
+            if let Some(abi) = target.encode_abi() {
+                fields.push(TAG_TARGET_ABI, &abi);
+            }
+            if let Some(version_code) = target.encode_version_code() {
+                fields.push(TAG_TARGET_VERSION_CODE, &version_code);
+            }
+        }
+
+        if let Some(provenance) = &self.provenance {
+            if let Some(tool) = provenance.encode_tool() {
+                fields.push(TAG_PROVENANCE_TOOL, &tool);
+            }
+            if let Some(created) = provenance.encode_created() {
+                fields.push(TAG_PROVENANCE_CREATED, &created);
+            }
+            if let Some(old_id) = provenance.encode_old_id() {
+                fields.push(TAG_PROVENANCE_OLD_ID, &old_id);
+            }
+            if let Some(new_id) = provenance.encode_new_id() {
+                fields.push(TAG_PROVENANCE_NEW_ID, &new_id);
+            }
+        }
+
+        if self.record_settings {
+            fields.push(TAG_DIFF_SETTINGS, &DiffSettings::of(self).encode_field());
+        }
+
+        if let Some(codec) = self.codec.encode_field() {
+            fields.push(TAG_CODEC, &codec);
+        }
+
+        if let Some(annotation) = &self.annotation {
+            if annotation.len() > MAX_ANNOTATION_LEN {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("annotation is longer than {MAX_ANNOTATION_LEN} bytes"),
+                ));
+            }
+            fields.push(TAG_ANNOTATION, &annotation::encode_field(annotation));
+        }
+
+        Ok(fields)
+    }
+
     /// The default number of compression threads to create
     ///
     /// We set this to 1 to ensure I/O and compression can run concurrently.
@@ -186,6 +1256,20 @@ impl DiffConfig {
     /// We set this to 19 because it obtains the highest compression ratio without incurring the
     /// significant memory costs of higher levels.
     pub const DEFAULT_COMPRESSION_LEVEL: i32 = 19;
+
+    /// The default matcher mismatch threshold
+    ///
+    /// This is the value used by the original bsdiff algorithm.
+    pub const DEFAULT_MATCH_THRESHOLD: usize = 8;
+
+    /// The default length of the windows diffed by [`diff_windowed()`]
+    ///
+    /// We set this to 16 MiB so that windowed diffs use less than 200 MiB of memory, which fits
+    /// comfortably in the address space of 32-bit targets.
+    pub const DEFAULT_DIFF_WINDOW_LEN: u64 = 1 << 24;
+
+    const MIN_WINDOW_LOG: u32 = 10;
+    const MAX_WINDOW_LOG: u32 = 27;
 }
 
 impl Default for DiffConfig {
@@ -193,3 +1277,34 @@ impl Default for DiffConfig {
         Self::new()
     }
 }
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for DiffConfig {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        Self::serialize(self, serializer)
+    }
+}
+
+/// Deserializes a configuration in which unset settings have their default values
+///
+/// Settings are clamped and ordered like their setters do.
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for DiffConfig {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let mut config = Self::deserialize(deserializer)?;
+        let fields = config.clone();
+        config
+            .window_log(fields.window_log)
+            .match_threshold(fields.match_threshold)
+            .anchors(fields.anchors)
+            .diff_window_len(fields.diff_window_len);
+
+        Ok(config)
+    }
+}
@@ -0,0 +1,286 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+#[cfg(feature = "verify")]
+use std::io::{self, Read, Seek, SeekFrom};
+use std::{
+    fmt::{self, Display, Formatter},
+    io::Write,
+};
+
+#[cfg(feature = "verify")]
+use integer_encoding::VarInt;
+
+#[cfg(feature = "verify")]
+use crate::{PatchError, header::TAG_DIGESTS};
+
+/// The length in bytes of the digests used for integrity checks
+///
+/// Every supported algorithm produces digests of this length.
+pub const DIGEST_LEN: usize = 32;
+
+/// A cryptographic hash algorithm used to check the integrity of new blobs.
+///
+/// BLAKE3 is the default. SHA-256 is available with the `sha256` feature for environments which
+/// mandate FIPS-approved hash algorithms. Each algorithm has an ID which identifies it in the
+/// formats that record digests, such as bundles.
+///
+/// The CRC-32 checksums of payloads, FEC blocks, and base checks detect accidental corruption
+/// rather than tampering, so they aren't affected by the choice of algorithm.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq, PartialOrd, Ord)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum DigestAlgorithm {
+    /// BLAKE3 with 256-bit output
+    #[default]
+    Blake3,
+    /// SHA-256
+    #[cfg(feature = "sha256")]
+    Sha256,
+}
+
+impl DigestAlgorithm {
+    /// Returns the ID of the algorithm in the formats that record digests
+    pub const fn id(self) -> u8 {
+        match self {
+            Self::Blake3 => 1,
+            #[cfg(feature = "sha256")]
+            Self::Sha256 => 2,
+        }
+    }
+
+    /// Returns the algorithm with the given ID, or `None` if it's unknown or wasn't enabled at
+    /// compile time
+    pub const fn from_id(id: u8) -> Option<Self> {
+        match id {
+            1 => Some(Self::Blake3),
+            #[cfg(feature = "sha256")]
+            2 => Some(Self::Sha256),
+            _ => None,
+        }
+    }
+
+    /// Returns a new hasher for the algorithm
+    pub fn hasher(self) -> Box<dyn Digest> {
+        match self {
+            Self::Blake3 => Box::new(blake3::Hasher::new()),
+            #[cfg(feature = "sha256")]
+            Self::Sha256 => Box::new(<sha2::Sha256 as sha2::Digest>::new()),
+        }
+    }
+
+    /// Computes the digest of `data`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ina::DigestAlgorithm;
+    ///
+    /// let digest = DigestAlgorithm::Blake3.hash(b"Hero");
+    /// assert_eq!(&digest, blake3::hash(b"Hero").as_bytes());
+    /// ```
+    pub fn hash(self, data: &[u8]) -> [u8; DIGEST_LEN] {
+        let mut hasher = self.hasher();
+        hasher.update(data);
+        hasher.finalize()
+    }
+}
+
+impl Display for DigestAlgorithm {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let name = match self {
+            Self::Blake3 => "BLAKE3",
+            #[cfg(feature = "sha256")]
+            Self::Sha256 => "SHA-256",
+        };
+
+        f.write_str(name)
+    }
+}
+
+/// An incremental hasher for a [`DigestAlgorithm`].
+///
+/// Data can be hashed with [`Digest::update()`] or by writing it to the hasher, e.g., with
+/// [`io::copy()`](std::io::copy).
+pub trait Digest: Write + Send {
+    /// Returns the algorithm of the hasher
+    fn algorithm(&self) -> DigestAlgorithm;
+
+    /// Hashes `data`
+    fn update(&mut self, data: &[u8]);
+
+    /// Returns the digest of the data hashed so far
+    fn finalize(&self) -> [u8; DIGEST_LEN];
+}
+
+impl Digest for blake3::Hasher {
+    fn algorithm(&self) -> DigestAlgorithm {
+        DigestAlgorithm::Blake3
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        blake3::Hasher::update(self, data);
+    }
+
+    fn finalize(&self) -> [u8; DIGEST_LEN] {
+        *blake3::Hasher::finalize(self).as_bytes()
+    }
+}
+
+#[cfg(feature = "sha256")]
+impl Digest for sha2::Sha256 {
+    fn algorithm(&self) -> DigestAlgorithm {
+        DigestAlgorithm::Sha256
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        sha2::Digest::update(self, data);
+    }
+
+    fn finalize(&self) -> [u8; DIGEST_LEN] {
+        sha2::Digest::finalize(self.clone()).into()
+    }
+}
+
+/// The digests of the old and new blobs of a patch, as recorded in the patch.
+///
+/// [`DiffConfig::embed_digests()`] records them. [`Patcher`] then checks the old blob against its
+/// digest before producing any output, which requires reading all of it, and the new blob against
+/// its digest once it has produced all of it. Unlike a [`BaseCheck`], this detects any corruption
+/// or mix-up of either blob, such as a corrupted download or a patch applied to the wrong version,
+/// without callers maintaining digests of their own. Patchers built without the `verify` feature
+/// skip the digests.
+///
+/// A new blob which fails its check has already been produced by the time it's detected, so the
+/// output of a `Patcher` must not be used unless it finishes without an error.
+///
+/// [`BaseCheck`]: crate::BaseCheck
+/// [`DiffConfig::embed_digests()`]: crate::DiffConfig::embed_digests
+/// [`Patcher`]: crate::Patcher
+#[cfg(feature = "verify")]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BlobDigests {
+    algorithm: DigestAlgorithm,
+    old: [u8; DIGEST_LEN],
+    new: [u8; DIGEST_LEN],
+}
+
+#[cfg(feature = "verify")]
+impl BlobDigests {
+    /// Returns the algorithm the digests were computed with
+    pub fn algorithm(&self) -> DigestAlgorithm {
+        self.algorithm
+    }
+
+    /// Returns the digest of the old blob
+    pub fn old_digest(&self) -> &[u8; DIGEST_LEN] {
+        &self.old
+    }
+
+    /// Returns the digest of the new blob
+    pub fn new_digest(&self) -> &[u8; DIGEST_LEN] {
+        &self.new
+    }
+
+    /// Computes the digests of `old`, which must not include a sentinel, and `new`
+    #[cfg(feature = "diff")]
+    pub(crate) fn of(algorithm: DigestAlgorithm, old: &[u8], new: &[u8]) -> Self {
+        Self {
+            algorithm,
+            old: algorithm.hash(old),
+            new: algorithm.hash(new),
+        }
+    }
+
+    /// Computes the digests of `old` and `new` from their current positions to their ends,
+    /// leaving both at those positions
+    #[cfg(feature = "diff")]
+    pub(crate) fn of_readers<O, N>(
+        algorithm: DigestAlgorithm,
+        old: &mut O,
+        new: &mut N,
+    ) -> io::Result<Self>
+    where
+        O: Read + Seek,
+        N: Read + Seek,
+    {
+        Ok(Self {
+            algorithm,
+            old: hash_reader(algorithm, old)?.0,
+            new: hash_reader(algorithm, new)?.0,
+        })
+    }
+
+    /// Encodes the digests as a header field
+    ///
+    /// The field consists of the varint ID of the algorithm followed by the digests of the old and
+    /// new blobs.
+    #[cfg(feature = "diff")]
+    pub(crate) fn encode_field(&self) -> Vec<u8> {
+        let mut field = u64::from(self.algorithm.id()).encode_var_vec();
+        field.extend_from_slice(&self.old);
+        field.extend_from_slice(&self.new);
+
+        field
+    }
+
+    /// Decodes the digests from a header field
+    ///
+    /// Returns [`PatchError::UnsupportedFeature`] if they were computed with an algorithm this
+    /// build doesn't support, since the blobs couldn't be checked against them.
+    pub(crate) fn decode_field(field: &[u8]) -> Result<Self, PatchError> {
+        let invalid = || PatchError::InvalidHeaderField(TAG_DIGESTS);
+        let (id, len) = u64::decode_var(field).ok_or_else(invalid)?;
+        let (old, rest) = field[len..]
+            .split_first_chunk::<DIGEST_LEN>()
+            .ok_or_else(invalid)?;
+        let (new, rest) = rest.split_first_chunk::<DIGEST_LEN>().ok_or_else(invalid)?;
+        if !rest.is_empty() {
+            return Err(invalid());
+        }
+        let algorithm = u8::try_from(id)
+            .ok()
+            .and_then(DigestAlgorithm::from_id)
+            .ok_or_else(|| PatchError::UnsupportedFeature(format!("digest-algorithm={id}")))?;
+
+        Ok(Self {
+            algorithm,
+            old: *old,
+            new: *new,
+        })
+    }
+
+    /// Checks `old` from its current position to its end against the digest of the old blob,
+    /// leaving `old` at that position
+    pub(crate) fn verify_old<O>(&self, old: &mut O) -> Result<(), PatchError>
+    where
+        O: Read + Seek,
+    {
+        let (digest, old_len) = hash_reader(self.algorithm, old)?;
+        if digest != self.old {
+            return Err(PatchError::OldMismatch(Some(0..old_len)));
+        }
+
+        Ok(())
+    }
+}
+
+/// Hashes `reader` from its current position to its end with `algorithm`, returning the digest and
+/// the number of bytes hashed and leaving `reader` at that position
+#[cfg(feature = "verify")]
+fn hash_reader<R>(algorithm: DigestAlgorithm, reader: &mut R) -> io::Result<([u8; DIGEST_LEN], u64)>
+where
+    R: Read + Seek,
+{
+    let start = reader.stream_position()?;
+    let mut hasher = algorithm.hasher();
+    let len = io::copy(reader, &mut hasher);
+    reader.seek(SeekFrom::Start(start))?;
+
+    Ok((hasher.finalize(), len?))
+}
@@ -0,0 +1,575 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use std::{
+    collections::HashMap,
+    fmt::{self, Debug, Formatter},
+    future::Future,
+    io,
+    num::NonZeroUsize,
+    panic::{self, AssertUnwindSafe},
+    pin::Pin,
+    sync::{
+        Arc, Condvar, Mutex, MutexGuard, OnceLock, PoisonError,
+        mpsc::{self, Receiver, Sender},
+    },
+    task::{Context, Poll, Waker},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use sufsort::SuffixArray;
+
+use crate::{DiffConfig, DiffStats, OldBlob, bsdiff, diff::diff_with_index};
+
+/// The default maximum number of bytes of old blobs and their indexes to cache
+const DEFAULT_INDEX_CACHE_LIMIT: u64 = 1 << 30;
+
+/// An old blob submitted to a [`DiffEngine`], identified by a key.
+///
+/// The engine caches the index of each old blob under its key, so every old blob must have a
+/// unique key and submitting the same key again reuses the cached index. Cloning an `OldRef` is
+/// cheap and shares the old blob.
+#[derive(Clone, Debug)]
+pub struct OldRef {
+    key: [u8; 32],
+    blob: Arc<OldBlob>,
+}
+
+impl OldRef {
+    /// Creates a reference to `blob` keyed by the BLAKE3 digest of its contents
+    pub fn new(blob: OldBlob) -> Self {
+        Self {
+            key: *blake3::hash(blob.as_bytes()).as_bytes(),
+            blob: Arc::new(blob),
+        }
+    }
+
+    /// Creates a reference to `blob` with a key chosen by the caller, such as a digest of it which
+    /// is already known, to avoid hashing it
+    ///
+    /// Diffing against an old blob whose key was previously used for a different old blob
+    /// produces a patch which can't be applied to either of them.
+    pub fn with_key(key: [u8; 32], blob: Arc<OldBlob>) -> Self {
+        Self { key, blob }
+    }
+
+    /// Returns the key of the old blob
+    pub fn key(&self) -> &[u8; 32] {
+        &self.key
+    }
+
+    /// Returns the old blob
+    pub fn blob(&self) -> &OldBlob {
+        &self.blob
+    }
+}
+
+/// Options for creating a [`DiffEngine`].
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct EngineOptions {
+    threads: usize,
+    index_cache_limit: u64,
+    config: DiffConfig,
+}
+
+impl EngineOptions {
+    /// Creates a new set of engine options
+    pub fn new() -> Self {
+        Self {
+            threads: thread::available_parallelism().map_or(1, NonZeroUsize::get),
+            index_cache_limit: DEFAULT_INDEX_CACHE_LIMIT,
+            config: DiffConfig::new(),
+        }
+    }
+
+    /// Sets the number of jobs diffed at once, each on its own thread.
+    ///
+    /// Jobs submitted while every thread is busy wait in a queue. At least one thread is always
+    /// used. The default is the available parallelism of the machine. Each job may additionally
+    /// use the compression threads set in its [`DiffConfig`].
+    pub fn threads(&mut self, threads: usize) -> &mut Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    /// Sets the maximum number of bytes of old blobs and their indexes to cache.
+    ///
+    /// The index of an old blob takes 4 bytes per byte of the old blob, so each cached old blob
+    /// takes about 5 times its length. The least recently used old blobs are evicted once the
+    /// limit is exceeded, though those still being diffed against are only freed once their jobs
+    /// finish. The default is 1 GiB.
+    pub fn index_cache_limit(&mut self, limit: u64) -> &mut Self {
+        self.index_cache_limit = limit;
+        self
+    }
+
+    /// Sets the configuration of jobs submitted without one.
+    ///
+    /// The default is [`DiffConfig::new()`].
+    pub fn config(&mut self, config: &DiffConfig) -> &mut Self {
+        self.config = config.clone();
+        self
+    }
+}
+
+impl Default for EngineOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Hooks notified of the work done by a [`DiffEngine`], e.g., to export it as metrics.
+///
+/// Every method does nothing by default. Methods are called from the threads submitting and
+/// diffing jobs, so they should return quickly.
+#[allow(unused_variables)]
+pub trait EngineMetrics: Send + Sync {
+    /// Called when a job is submitted
+    fn job_submitted(&self) {}
+
+    /// Called when a thread starts diffing a job which waited in the queue for `queued`
+    fn job_started(&self, queued: Duration) {}
+
+    /// Called when a job which took `elapsed` to diff finishes with `result`
+    fn job_finished(&self, elapsed: Duration, result: Result<&DiffStats, &io::Error>) {}
+
+    /// Called when a job finds the index of its old blob in the cache
+    fn index_cache_hit(&self) {}
+
+    /// Called when a job builds the index of its old blob, which took `elapsed`
+    fn index_cache_miss(&self, elapsed: Duration) {}
+
+    /// Called when an old blob and its index taking `len` bytes are evicted from the cache
+    fn index_cache_eviction(&self, len: u64) {}
+
+    /// Called whenever the number of bytes of old blobs and their indexes in the cache changes
+    fn index_cache_len(&self, len: u64) {}
+}
+
+/// The metrics hooks of an engine created without any
+struct NoMetrics;
+
+impl EngineMetrics for NoMetrics {}
+
+/// A pool of threads which diff new blobs against cached indexes of old blobs.
+///
+/// Services generating patches on demand usually diff many new blobs against few old blobs, e.g.,
+/// the releases installed by their users. Building the index of the old blob is usually the most
+/// expensive part of diffing, so a `DiffEngine` caches the index of each old blob in memory,
+/// evicting the least recently used ones beyond the limit set by
+/// [`EngineOptions::index_cache_limit()`]. Jobs are diffed on a fixed number of threads, so the
+/// engine can be shared by any number of callers without overcommitting the machine.
+///
+/// Submitting a job returns a [`DiffJob`], which is a [`Future`] resolving to the patch, so it can
+/// be awaited from any async runtime. Callers without one can block on it with
+/// [`DiffJob::wait()`].
+///
+/// Dropping the engine waits for the jobs already submitted to finish. Since it creates threads, a
+/// `DiffEngine` can't be created once a sandbox has been enabled via the `sandbox` module.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> std::io::Result<()> {
+/// use ina::{DiffEngine, EngineOptions, OldBlob, OldRef};
+///
+/// let engine = DiffEngine::new(EngineOptions::new().threads(2))?;
+/// let old = OldRef::new(OldBlob::from_slice(b"Hello"));
+///
+/// // Only one of the jobs builds the index of the old blob, which the other one reuses
+/// let first = engine.submit(&old, b"Hero".to_vec());
+/// let second = engine.submit(&old, b"Help".to_vec());
+///
+/// let first = first.wait()?;
+/// let second = second.wait()?;
+/// assert_ne!(first.index_cached(), second.index_cached());
+/// # Ok(())
+/// # }
+/// ```
+pub struct DiffEngine {
+    shared: Arc<Shared>,
+    jobs: Option<Sender<Job>>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl DiffEngine {
+    /// Creates an engine with the given options
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a thread can't be created.
+    pub fn new(options: &EngineOptions) -> io::Result<Self> {
+        Self::with_metrics(options, Arc::new(NoMetrics))
+    }
+
+    /// Creates an engine with the given options which notifies `metrics` of its work
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a thread can't be created.
+    pub fn with_metrics(
+        options: &EngineOptions,
+        metrics: Arc<dyn EngineMetrics>,
+    ) -> io::Result<Self> {
+        let shared = Arc::new(Shared {
+            cache: Mutex::new(IndexCache::new(options.index_cache_limit)),
+            config: options.config.clone(),
+            metrics,
+        });
+        let (sender, receiver) = mpsc::channel();
+        let receiver = Arc::new(Mutex::new(receiver));
+
+        let mut engine = Self {
+            shared,
+            jobs: Some(sender),
+            workers: Vec::with_capacity(options.threads),
+        };
+        for _ in 0..options.threads {
+            let shared = Arc::clone(&engine.shared);
+            let receiver = Arc::clone(&receiver);
+            let worker = thread::Builder::new()
+                .name("ina-diff".into())
+                .spawn(move || run_jobs(&shared, &receiver))?;
+            engine.workers.push(worker);
+        }
+
+        Ok(engine)
+    }
+
+    /// Submits a job diffing `new` against `old` with the configuration of the engine
+    pub fn submit(&self, old: &OldRef, new: Vec<u8>) -> DiffJob {
+        self.submit_job(old, new, None)
+    }
+
+    /// Submits a job diffing `new` against `old` with `config` instead of the configuration of the
+    /// engine
+    ///
+    /// The job fails if `config` requests matching against a zeroed copy of the old blob, since the
+    /// cached index can't reflect the zeroing. See [`diff_with_index()`](crate::diff_with_index).
+    pub fn submit_with_config(&self, old: &OldRef, new: Vec<u8>, config: &DiffConfig) -> DiffJob {
+        self.submit_job(old, new, Some(config.clone()))
+    }
+
+    /// Returns the number of bytes of old blobs and their indexes in the cache
+    pub fn index_cache_len(&self) -> u64 {
+        lock(&self.shared.cache).len
+    }
+
+    fn submit_job(&self, old: &OldRef, new: Vec<u8>, config: Option<DiffConfig>) -> DiffJob {
+        let slot = Arc::new(Slot::default());
+        let job = Job {
+            old: old.clone(),
+            new,
+            config,
+            slot: Arc::clone(&slot),
+            submitted: Instant::now(),
+        };
+
+        self.shared.metrics.job_submitted();
+        // The channel is only disconnected if every thread has exited, e.g., because a metrics
+        // hook panicked
+        if let Some(jobs) = &self.jobs
+            && let Err(mpsc::SendError(job)) = jobs.send(job)
+        {
+            job.slot
+                .finish(Err(io::Error::other("diff engine threads exited")));
+        }
+
+        DiffJob { slot }
+    }
+}
+
+impl Debug for DiffEngine {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_struct("DiffEngine")
+            .field("threads", &self.workers.len())
+            .field("index_cache_len", &self.index_cache_len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for DiffEngine {
+    fn drop(&mut self) {
+        // Disconnecting the channel stops each worker once the queue is empty
+        self.jobs = None;
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+/// A job submitted to a [`DiffEngine`].
+///
+/// A `DiffJob` is a [`Future`] resolving to the result of the job once a thread of the engine has
+/// diffed it. Dropping it doesn't cancel the job.
+#[derive(Debug)]
+#[must_use = "the result of a job can only be retrieved through its `DiffJob`"]
+pub struct DiffJob {
+    slot: Arc<Slot>,
+}
+
+impl DiffJob {
+    /// Blocks until the job finishes, returning its result
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if diffing fails, e.g., because the job's configuration is invalid.
+    pub fn wait(self) -> io::Result<DiffResult> {
+        let mut state = lock(&self.slot.state);
+        loop {
+            if let Some(result) = state.result.take() {
+                return result;
+            }
+            state = self
+                .slot
+                .done
+                .wait(state)
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+    }
+
+    /// Returns whether the job has finished
+    pub fn is_finished(&self) -> bool {
+        lock(&self.slot.state).result.is_some()
+    }
+}
+
+impl Future for DiffJob {
+    type Output = io::Result<DiffResult>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = lock(&self.slot.state);
+        match state.result.take() {
+            Some(result) => Poll::Ready(result),
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// The result of a job submitted to a [`DiffEngine`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DiffResult {
+    patch: Vec<u8>,
+    stats: DiffStats,
+    index_cached: bool,
+}
+
+impl DiffResult {
+    /// Returns the patch
+    pub fn patch(&self) -> &[u8] {
+        &self.patch
+    }
+
+    /// Returns the patch, consuming the result
+    pub fn into_patch(self) -> Vec<u8> {
+        self.patch
+    }
+
+    /// Returns statistics about the patch
+    pub fn stats(&self) -> &DiffStats {
+        &self.stats
+    }
+
+    /// Returns whether the index of the old blob was found in the cache rather than built for the
+    /// job
+    pub fn index_cached(&self) -> bool {
+        self.index_cached
+    }
+}
+
+/// The state shared by an engine and its threads
+struct Shared {
+    cache: Mutex<IndexCache>,
+    config: DiffConfig,
+    metrics: Arc<dyn EngineMetrics>,
+}
+
+/// A job waiting in the queue of an engine
+struct Job {
+    old: OldRef,
+    new: Vec<u8>,
+    config: Option<DiffConfig>,
+    slot: Arc<Slot>,
+    submitted: Instant,
+}
+
+/// Where the result of a job is handed from the thread diffing it to its `DiffJob`
+#[derive(Debug, Default)]
+struct Slot {
+    state: Mutex<SlotState>,
+    done: Condvar,
+}
+
+#[derive(Debug, Default)]
+struct SlotState {
+    result: Option<io::Result<DiffResult>>,
+    waker: Option<Waker>,
+}
+
+impl Slot {
+    /// Stores `result` and wakes whoever is waiting for it
+    fn finish(&self, result: io::Result<DiffResult>) {
+        let waker = {
+            let mut state = lock(&self.state);
+            state.result = Some(result);
+            state.waker.take()
+        };
+        self.done.notify_all();
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+/// An old blob and its index, which is built by the first job needing it
+struct PreparedOld {
+    blob: Arc<OldBlob>,
+    suffixes: OnceLock<Vec<u32>>,
+}
+
+impl PreparedOld {
+    /// Returns the number of bytes taken by the old blob and its index once built
+    fn len(&self) -> u64 {
+        let suffixes = self.suffixes.get().map_or(0, Vec::len);
+        (self.blob.with_sentinel().len() + suffixes * size_of::<u32>()) as u64
+    }
+}
+
+/// A cached old blob
+struct CacheEntry {
+    old: Arc<PreparedOld>,
+    /// The number of bytes accounted for the entry, or `None` while its index is being built
+    len: Option<u64>,
+    last_used: u64,
+}
+
+/// The old blobs of an engine and their indexes, evicting the least recently used ones beyond a
+/// limit
+struct IndexCache {
+    entries: HashMap<[u8; 32], CacheEntry>,
+    len: u64,
+    limit: u64,
+    clock: u64,
+}
+
+impl IndexCache {
+    fn new(limit: u64) -> Self {
+        Self {
+            entries: HashMap::new(),
+            len: 0,
+            limit,
+            clock: 0,
+        }
+    }
+
+    /// Returns the cached entry for `old`, adding one whose index isn't built yet if there's none
+    fn get(&mut self, old: &OldRef) -> Arc<PreparedOld> {
+        self.clock += 1;
+        let entry = self.entries.entry(old.key).or_insert_with(|| CacheEntry {
+            old: Arc::new(PreparedOld {
+                blob: Arc::clone(&old.blob),
+                suffixes: OnceLock::new(),
+            }),
+            len: None,
+            last_used: 0,
+        });
+        entry.last_used = self.clock;
+
+        Arc::clone(&entry.old)
+    }
+
+    /// Accounts for the index of the entry with the given key having been built, evicting the
+    /// least recently used entries if the limit is exceeded
+    fn built(&mut self, key: &[u8; 32], metrics: &dyn EngineMetrics) {
+        if let Some(entry) = self.entries.get_mut(key)
+            && entry.len.is_none()
+        {
+            let len = entry.old.len();
+            entry.len = Some(len);
+            self.len += len;
+        }
+
+        while self.len > self.limit {
+            // Entries whose index is being built don't count toward the limit yet
+            let Some(oldest) = self
+                .entries
+                .iter()
+                .filter(|(_, entry)| entry.len.is_some())
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(&key, _)| key)
+            else {
+                break;
+            };
+            if let Some(CacheEntry { len: Some(len), .. }) = self.entries.remove(&oldest) {
+                self.len -= len;
+                metrics.index_cache_eviction(len);
+            }
+        }
+        metrics.index_cache_len(self.len);
+    }
+}
+
+/// Diffs jobs from `receiver` until the engine is dropped
+fn run_jobs(shared: &Shared, receiver: &Mutex<Receiver<Job>>) {
+    loop {
+        // The lock must be released before diffing the job so that other threads can receive the
+        // next one, so it's only held for this statement
+        let job = lock(receiver).recv();
+        let Ok(job) = job else {
+            break;
+        };
+        shared.metrics.job_started(job.submitted.elapsed());
+        let start = Instant::now();
+        let result = panic::catch_unwind(AssertUnwindSafe(|| run_job(shared, &job)))
+            .unwrap_or_else(|_| Err(io::Error::other("diff job panicked")));
+        shared
+            .metrics
+            .job_finished(start.elapsed(), result.as_ref().map(DiffResult::stats));
+        job.slot.finish(result);
+    }
+}
+
+/// Diffs `job` against the cached index of its old blob, building the index if necessary
+fn run_job(shared: &Shared, job: &Job) -> io::Result<DiffResult> {
+    let old = lock(&shared.cache).get(&job.old);
+
+    // Jobs needing an index which another thread is building wait for it rather than building it
+    // again
+    let mut build_time = None;
+    let suffixes = old.suffixes.get_or_init(|| {
+        let start = Instant::now();
+        let suffixes = SuffixArray::new(old.blob.with_sentinel()).into_suffixes();
+        build_time = Some(start.elapsed());
+        suffixes
+    });
+    match build_time {
+        Some(elapsed) => {
+            shared.metrics.index_cache_miss(elapsed);
+            lock(&shared.cache).built(&job.old.key, &*shared.metrics);
+        }
+        None => shared.metrics.index_cache_hit(),
+    }
+
+    let index = SuffixArray::from_suffix_slice(old.blob.with_sentinel(), suffixes)
+        .expect("suffixes were built for the old blob");
+    let index = bsdiff::with_prefilter_if_large(index);
+    let config = job.config.as_ref().unwrap_or(&shared.config);
+    let mut patch = Vec::new();
+    let stats = diff_with_index(&index, &job.new, &mut patch, config)?;
+
+    Ok(DiffResult {
+        patch,
+        stats,
+        index_cached: build_time.is_none(),
+    })
+}
+
+/// Locks `mutex`, ignoring poisoning since jobs which panic don't hold any locks
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
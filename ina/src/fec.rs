@@ -0,0 +1,401 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use std::io;
+#[cfg(feature = "diff")]
+use std::io::Write;
+#[cfg(feature = "patch")]
+use std::io::{BufRead, ErrorKind, Read};
+
+#[cfg(feature = "patch")]
+use integer_encoding::VarInt;
+#[cfg(feature = "diff")]
+use integer_encoding::VarIntWriter;
+use reed_solomon_erasure::galois_8::ReedSolomon;
+
+/// The length of the checksum following each block
+#[cfg(feature = "patch")]
+const CHECKSUM_LEN: usize = 4;
+
+/// Forward error correction parameters for a patch.
+///
+/// When a [`DiffConfig`](crate::DiffConfig) has forward error correction enabled, the compressed
+/// patch data is split into groups of fixed-size data blocks, and Reed-Solomon parity blocks are
+/// appended to each group. Every block carries a checksum, so a [`Patcher`](crate::Patcher) can
+/// tell which blocks were corrupted in transit and reconstruct them from the rest of their group,
+/// provided no more blocks than the number of parity blocks were corrupted in any one group.
+///
+/// The patch header itself isn't protected.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use ina::{DiffConfig, FecConfig};
+///
+/// let mut fec = FecConfig::new();
+/// fec.block_size(64).data_blocks(4).parity_blocks(2);
+/// let mut patch = Vec::new();
+/// let old = ina::OldBlob::from_slice(b"Hello");
+/// ina::diff_with_config(&old, b"Hero", &mut patch, DiffConfig::new().fec(Some(fec)))?;
+///
+/// // Corrupt the first byte of the patch data, which is stored in one data block followed by two
+/// // parity blocks, each with a 4-byte checksum
+/// let data_start = patch.len() - 3 * (64 + 4);
+/// patch[data_start] ^= 0xff;
+///
+/// let mut new = Vec::new();
+/// ina::patch(std::io::Cursor::new(b"Hello"), patch.as_slice(), &mut new)?;
+/// assert_eq!(new, b"Hero");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(remote = "Self")
+)]
+pub struct FecConfig {
+    block_size: u32,
+    data_blocks: u16,
+    parity_blocks: u16,
+}
+
+impl FecConfig {
+    /// Creates a new forward error correction configuration
+    pub const fn new() -> Self {
+        Self {
+            block_size: Self::DEFAULT_BLOCK_SIZE,
+            data_blocks: Self::DEFAULT_DATA_BLOCKS,
+            parity_blocks: Self::DEFAULT_PARITY_BLOCKS,
+        }
+    }
+
+    /// Sets the size in bytes of each block.
+    ///
+    /// Smaller blocks localize corruption more precisely at the cost of more checksum overhead.
+    /// Values are clamped to the range 1-1048576 inclusive.
+    pub fn block_size(&mut self, size: u32) -> &mut Self {
+        self.block_size = size.clamp(1, Self::MAX_BLOCK_SIZE);
+        self
+    }
+
+    /// Sets the number of data blocks in each group.
+    ///
+    /// Values are clamped so that the total number of blocks in a group is at most 256, and at
+    /// least 1 data block is used.
+    pub fn data_blocks(&mut self, blocks: u16) -> &mut Self {
+        self.data_blocks = blocks.clamp(1, Self::MAX_GROUP_BLOCKS - self.parity_blocks);
+        self
+    }
+
+    /// Sets the number of parity blocks in each group.
+    ///
+    /// This is the number of corrupted blocks that can be corrected in each group. Values are
+    /// clamped so that the total number of blocks in a group is at most 256, and at least 1 parity
+    /// block is used.
+    pub fn parity_blocks(&mut self, blocks: u16) -> &mut Self {
+        self.parity_blocks = blocks.clamp(1, Self::MAX_GROUP_BLOCKS - self.data_blocks);
+        self
+    }
+
+    /// The default block size
+    ///
+    /// We set this to 4 KiB so that checksums add less than 0.1% overhead.
+    pub const DEFAULT_BLOCK_SIZE: u32 = 4096;
+
+    /// The default number of data blocks per group
+    pub const DEFAULT_DATA_BLOCKS: u16 = 32;
+
+    /// The default number of parity blocks per group
+    ///
+    /// Together with the default number of data blocks, this adds 12.5% overhead.
+    pub const DEFAULT_PARITY_BLOCKS: u16 = 4;
+
+    const MAX_BLOCK_SIZE: u32 = 1 << 20;
+    const MAX_GROUP_BLOCKS: u16 = 256;
+
+    /// Encodes the header field describing these parameters for a payload of `payload_len` bytes
+    #[cfg(feature = "diff")]
+    pub(crate) fn encode_field(&self, payload_len: u64) -> Vec<u8> {
+        let mut field = Vec::new();
+        // Writing to a Vec never fails
+        field.write_varint(self.block_size).unwrap();
+        field.write_varint(self.data_blocks).unwrap();
+        field.write_varint(self.parity_blocks).unwrap();
+        field.write_varint(payload_len).unwrap();
+
+        field
+    }
+
+    /// Writes `payload` to `out` with parity blocks and checksums
+    #[cfg(feature = "diff")]
+    pub(crate) fn encode<W>(&self, payload: &[u8], out: &mut W) -> io::Result<()>
+    where
+        W: Write + ?Sized,
+    {
+        let block_size = self.block_size as usize;
+        let group_len = block_size * usize::from(self.data_blocks);
+        let parity_blocks = usize::from(self.parity_blocks);
+
+        for group_data in payload.chunks(group_len) {
+            let data_blocks = group_data.len().div_ceil(block_size);
+            let mut group = vec![0; (data_blocks + parity_blocks) * block_size];
+            group[..group_data.len()].copy_from_slice(group_data);
+
+            let mut blocks: Vec<_> = group.chunks_mut(block_size).collect();
+            codec(data_blocks, parity_blocks)?
+                .encode(&mut blocks)
+                .map_err(io::Error::other)?;
+
+            for block in blocks {
+                out.write_all(block)?;
+                out.write_all(&crc32fast::hash(block).to_le_bytes())?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for FecConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for FecConfig {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        Self::serialize(self, serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for FecConfig {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        // Clamp the values like the setters do. The parity blocks are reduced first so that any
+        // valid number of data blocks is kept.
+        let fields = Self::deserialize(deserializer)?;
+        let mut config = Self::new();
+        config
+            .block_size(fields.block_size)
+            .parity_blocks(1)
+            .data_blocks(fields.data_blocks)
+            .parity_blocks(fields.parity_blocks);
+
+        Ok(config)
+    }
+}
+
+/// The parameters of an error-corrected patch payload
+#[cfg(feature = "patch")]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub(crate) struct FecParams {
+    config: FecConfig,
+    payload_len: u64,
+}
+
+#[cfg(feature = "patch")]
+impl FecParams {
+    pub(crate) fn config(&self) -> FecConfig {
+        self.config
+    }
+
+    /// Returns the size of the buffer needed to decode a group of blocks
+    pub(crate) fn group_len(&self) -> u64 {
+        let blocks = u64::from(self.config.data_blocks) + u64::from(self.config.parity_blocks);
+
+        blocks * (u64::from(self.config.block_size) + CHECKSUM_LEN as u64)
+    }
+
+    /// Decodes the parameters from a header field, returning `None` if the field is invalid
+    pub(crate) fn decode_field(mut field: &[u8]) -> Option<Self> {
+        fn next<V: VarInt>(field: &mut &[u8]) -> Option<V> {
+            let (value, len) = V::decode_var(field)?;
+            *field = &field[len..];
+            Some(value)
+        }
+
+        let block_size: u32 = next(&mut field)?;
+        let data_blocks: u16 = next(&mut field)?;
+        let parity_blocks: u16 = next(&mut field)?;
+        let payload_len = next(&mut field)?;
+
+        // Reject parameters which `FecConfig` would never produce, bounding the memory used for
+        // decoding
+        let valid = (1..=FecConfig::MAX_BLOCK_SIZE).contains(&block_size)
+            && data_blocks >= 1
+            && parity_blocks >= 1
+            && u32::from(data_blocks) + u32::from(parity_blocks)
+                <= u32::from(FecConfig::MAX_GROUP_BLOCKS);
+        valid.then_some(Self {
+            config: FecConfig {
+                block_size,
+                data_blocks,
+                parity_blocks,
+            },
+            payload_len,
+        })
+    }
+}
+
+/// A reader which corrects and strips the parity blocks and checksums of a payload
+#[cfg(feature = "patch")]
+pub(crate) struct FecReader<R>
+where
+    R: Read,
+{
+    inner: R,
+    params: FecParams,
+    /// The group currently being read, stored as blocks followed by their checksums
+    group: Vec<u8>,
+    /// The range of the current group's data which hasn't been consumed yet
+    pos: usize,
+    end: usize,
+    /// The number of bytes of the next group read so far, which are kept if reading the rest fails
+    filled: usize,
+    /// The number of payload bytes which haven't been loaded into a group yet
+    remaining: u64,
+}
+
+#[cfg(feature = "patch")]
+impl<R> FecReader<R>
+where
+    R: Read,
+{
+    pub(crate) fn new(inner: R, params: FecParams) -> Self {
+        Self {
+            inner,
+            params,
+            group: Vec::new(),
+            pos: 0,
+            end: 0,
+            filled: 0,
+            remaining: params.payload_len,
+        }
+    }
+
+    /// Returns whether the whole payload has been consumed, in which case `inner` is positioned
+    /// right after it
+    pub(crate) fn is_finished(&self) -> bool {
+        self.pos == self.end && self.remaining == 0
+    }
+
+    /// Returns the reader of the patch
+    pub(crate) fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Unwraps the reader of the patch
+    pub(crate) fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Reads, checks, and corrects the next group of blocks
+    fn load_group(&mut self) -> io::Result<()> {
+        let block_size = self.params.config.block_size as usize;
+        let stored_block_len = block_size + CHECKSUM_LEN;
+        let max_group_len = block_size * usize::from(self.params.config.data_blocks);
+        let parity_blocks = usize::from(self.params.config.parity_blocks);
+
+        let data_len = self.remaining.min(max_group_len as u64) as usize;
+        let data_blocks = data_len.div_ceil(block_size);
+
+        if self.filled == 0 {
+            self.group
+                .resize((data_blocks + parity_blocks) * stored_block_len, 0);
+        }
+        while self.filled < self.group.len() {
+            match self.inner.read(&mut self.group[self.filled..]) {
+                Ok(0) => return Err(ErrorKind::UnexpectedEof.into()),
+                Ok(read) => self.filled += read,
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        self.filled = 0;
+
+        let mut blocks: Vec<_> = self
+            .group
+            .chunks_mut(stored_block_len)
+            .map(|stored_block| {
+                let (block, checksum) = stored_block.split_at_mut(block_size);
+                let intact = crc32fast::hash(block).to_le_bytes() == *checksum;
+                (block, intact)
+            })
+            .collect();
+
+        let corrupted = blocks.iter().filter(|(_, intact)| !intact).count();
+        if corrupted > parity_blocks {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                "too many corrupted blocks to correct",
+            ));
+        }
+        if blocks[..data_blocks].iter().any(|(_, intact)| !intact) {
+            codec(data_blocks, parity_blocks)?
+                .reconstruct_data(&mut blocks)
+                .map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?;
+        }
+
+        // Compact the data blocks to the front of the buffer so that they can be read contiguously
+        for i in 1..data_blocks {
+            self.group.copy_within(
+                i * stored_block_len..i * stored_block_len + block_size,
+                i * block_size,
+            );
+        }
+        self.pos = 0;
+        self.end = data_len;
+        self.remaining -= data_len as u64;
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "patch")]
+impl<R> Read for FecReader<R>
+where
+    R: Read,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let len = available.len().min(buf.len());
+        buf[..len].copy_from_slice(&available[..len]);
+        self.consume(len);
+
+        Ok(len)
+    }
+}
+
+#[cfg(feature = "patch")]
+impl<R> BufRead for FecReader<R>
+where
+    R: Read,
+{
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.pos == self.end && self.remaining > 0 {
+            self.load_group()?;
+        }
+
+        Ok(&self.group[self.pos..self.end])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos = (self.pos + amt).min(self.end);
+    }
+}
+
+fn codec(data_blocks: usize, parity_blocks: usize) -> io::Result<ReedSolomon> {
+    ReedSolomon::new(data_blocks, parity_blocks).map_err(io::Error::other)
+}
@@ -0,0 +1,177 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+#[cfg(feature = "patch")]
+use std::time::Duration;
+use std::{
+    fs::{self, File},
+    io,
+    time::SystemTime,
+};
+
+use integer_encoding::VarInt;
+
+/// The permission bits which are recorded and restored
+///
+/// The setuid, setgid, and sticky bits are left out so that applying a patch from an untrusted
+/// source can't produce a file which runs with the privileges of its owner.
+const PERMISSION_BITS: u32 = 0o777;
+
+/// Metadata of a file which can be recorded in a patch and restored after patching.
+///
+/// Patches don't record file metadata by default. To record it, pass a `FileMetadata` to
+/// [`DiffConfig::file_metadata()`]. It can later be retrieved via
+/// [`PatchMetadata::file_metadata()`] and restored with [`FileMetadata::apply()`].
+///
+/// [`DiffConfig::file_metadata()`]: crate::DiffConfig::file_metadata
+/// [`PatchMetadata::file_metadata()`]: crate::PatchMetadata::file_metadata
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FileMetadata {
+    mode: Option<u32>,
+    modified: Option<SystemTime>,
+}
+
+impl FileMetadata {
+    /// Creates a new `FileMetadata` from its parts.
+    ///
+    /// `mode` holds Unix permission bits such as `0o755` and is ignored on other platforms. Only
+    /// the read, write, and execute bits are kept, i.e., the setuid, setgid, and sticky bits are
+    /// cleared. `modified` is the last modification time of the file.
+    pub fn new(mode: Option<u32>, modified: Option<SystemTime>) -> Self {
+        Self {
+            mode: mode.map(|mode| mode & PERMISSION_BITS),
+            modified,
+        }
+    }
+
+    /// Creates a new `FileMetadata` describing an existing file.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::fs;
+    /// use ina::{DiffConfig, FileMetadata};
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// let mut config = DiffConfig::new();
+    /// config.file_metadata(Some(FileMetadata::from_metadata(&fs::metadata("app-v2.exe")?)));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_metadata(metadata: &fs::Metadata) -> Self {
+        #[cfg(unix)]
+        let mode = {
+            use std::os::unix::fs::PermissionsExt;
+
+            Some(metadata.permissions().mode() & PERMISSION_BITS)
+        };
+        #[cfg(not(unix))]
+        let mode = None;
+
+        Self {
+            mode,
+            modified: metadata.modified().ok(),
+        }
+    }
+
+    /// Returns the Unix permission bits of the file, if recorded
+    pub fn mode(&self) -> Option<u32> {
+        self.mode
+    }
+
+    /// Returns the last modification time of the file, if recorded
+    pub fn modified(&self) -> Option<SystemTime> {
+        self.modified
+    }
+
+    /// Applies the recorded metadata to `file`.
+    ///
+    /// Fields which weren't recorded are left untouched. Permission bits are only applied on Unix
+    /// platforms, and never include the setuid, setgid, or sticky bits.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an I/O error occurs while updating the file's metadata.
+    pub fn apply(&self, file: &File) -> io::Result<()> {
+        #[cfg(unix)]
+        if let Some(mode) = self.mode {
+            use std::os::unix::fs::PermissionsExt;
+
+            file.set_permissions(fs::Permissions::from_mode(mode & PERMISSION_BITS))?;
+        }
+        if let Some(modified) = self.modified {
+            file.set_modified(modified)?;
+        }
+
+        Ok(())
+    }
+
+    #[cfg(feature = "diff")]
+    pub(crate) fn encode_mode(&self) -> Option<Vec<u8>> {
+        self.mode.map(|mode| mode.encode_var_vec())
+    }
+
+    #[cfg(feature = "diff")]
+    pub(crate) fn encode_modified(&self) -> Option<Vec<u8>> {
+        self.modified.map(encode_time)
+    }
+
+    #[cfg(feature = "patch")]
+    pub(crate) fn decode_mode(&mut self, value: &[u8]) -> Option<()> {
+        let (mode, _) = u32::decode_var(value)?;
+        // Patches from untrusted sources may set any bits
+        self.mode = Some(mode & PERMISSION_BITS);
+
+        Some(())
+    }
+
+    #[cfg(feature = "patch")]
+    pub(crate) fn decode_modified(&mut self, value: &[u8]) -> Option<()> {
+        self.modified = Some(decode_time(value)?);
+
+        Some(())
+    }
+}
+
+/// Encodes `time` as a zigzag-encoded number of seconds since the epoch followed by a number of
+/// nanoseconds
+#[cfg(feature = "diff")]
+pub(crate) fn encode_time(time: SystemTime) -> Vec<u8> {
+    let (secs, nanos) = match time.duration_since(SystemTime::UNIX_EPOCH) {
+        Ok(since) => (since.as_secs() as i64, since.subsec_nanos()),
+        Err(e) => {
+            // Represent times before the epoch as a negative number of seconds plus a positive
+            // number of nanoseconds
+            let before = e.duration();
+            let secs = -(before.as_secs() as i64);
+            match before.subsec_nanos() {
+                0 => (secs, 0),
+                nanos => (secs - 1, 1_000_000_000 - nanos),
+            }
+        }
+    };
+
+    let mut value = secs.encode_var_vec();
+    value.extend_from_slice(&nanos.encode_var_vec());
+    value
+}
+
+/// Decodes a time encoded by `encode_time()`
+#[cfg(feature = "patch")]
+pub(crate) fn decode_time(value: &[u8]) -> Option<SystemTime> {
+    let (secs, len) = i64::decode_var(value)?;
+    let (nanos, _) = u32::decode_var(&value[len..])?;
+    if nanos >= 1_000_000_000 {
+        return None;
+    }
+
+    if secs >= 0 {
+        SystemTime::UNIX_EPOCH.checked_add(Duration::new(secs as u64, nanos))
+    } else {
+        SystemTime::UNIX_EPOCH
+            .checked_sub(Duration::from_secs(secs.unsigned_abs()))?
+            .checked_add(Duration::from_nanos(nanos.into()))
+    }
+}
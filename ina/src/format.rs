@@ -0,0 +1,501 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Identification of patches and the features of the patch format they use.
+//!
+//! [`sniff()`] cheaply tells whether a file is a patch from its first few bytes, e.g., for servers
+//! choosing a `Content-Type` or file managers choosing an icon, without parsing its header. The
+//! features a patch uses are described by [`FormatCapabilities`], which is also available at the
+//! crate root.
+//!
+//! The remaining items describe the layout of the format itself, such as [`HEADER`] and
+//! [`TAGGED_FIELDS`]. They're the constants this crate reads and writes patches with, so
+//! descriptions of the format generated from them, e.g., for implementations in other languages,
+//! can't fall out of sync with it.
+//!
+//! # Layout notation
+//!
+//! Layouts are given as comma-separated `name: type` items in the order they're encoded. The types
+//! are:
+//!
+//! - `u16le` and `u32le`: little-endian unsigned integers of 2 and 4 bytes
+//! - `varint`: an unsigned LEB128 integer
+//! - `zigzag`: a signed integer mapped to an unsigned one by zigzag encoding, then encoded as a
+//!   `varint`
+//! - `bytes[n]`: `n` raw bytes, where `n` names an earlier item
+//! - `bytes` and `utf8`: the remaining bytes of the enclosing value, the latter being valid UTF-8
+//!
+//! `*(...)` means that the items in parentheses repeat until the enclosing value ends.
+
+use std::{
+    collections::BTreeSet,
+    fmt::{self, Display, Formatter},
+    io::{self, ErrorKind, Read},
+};
+
+use integer_encoding::VarIntReader;
+
+use crate::{
+    Codec, HeaderField, PatchError, PatchMetadata, PatchVersion,
+    header::{
+        self, Fields, TAG_ANNOTATION, TAG_BASE_CHECK, TAG_BLOCK_MAP, TAG_CODEC, TAG_DIFF_SETTINGS,
+        TAG_DIGESTS, TAG_FEC, TAG_FILE_MODE, TAG_FILE_MODIFIED, TAG_NEW_LEN, TAG_PAYLOAD_CHECKSUM,
+        TAG_PROVENANCE_CREATED, TAG_PROVENANCE_NEW_ID, TAG_PROVENANCE_OLD_ID, TAG_PROVENANCE_TOOL,
+        TAG_REQUIREMENTS, TAG_TARGET_ABI, TAG_TARGET_PLATFORM, TAG_TARGET_VERSION_CODE,
+        TAG_TEXT_HINTS,
+    },
+    patch::{CountingReader, MajorVersion, header_error, read_header_field},
+};
+
+/// The bytes every patch starts with
+///
+/// Patches of every version start with these bytes. Bundles start with different bytes.
+pub const MAGIC: [u8; 4] = header::MAGIC.to_le_bytes();
+
+/// The suggested MIME type of patch files
+///
+/// This type isn't registered with IANA. Patch files conventionally use the extension `.ina`.
+pub const MIME_TYPE: &str = "application/x-ina";
+
+/// The major version of the patch format created by this version of the crate
+pub const VERSION_MAJOR: u16 = header::VERSION_MAJOR;
+
+/// The minor version of the patch format created by this version of the crate
+///
+/// Minor versions only add tagged fields, which older patchers skip.
+pub const VERSION_MINOR: u16 = header::VERSION_MINOR;
+
+/// The parts of the patch header in the order they're encoded
+///
+/// The header is followed by the data section, a stream of [`RECORD_LAYOUT`] records compressed
+/// with the patch's [`Codec`].
+pub const HEADER: [HeaderField; 5] = [
+    HeaderField::Magic,
+    HeaderField::MajorVersion,
+    HeaderField::MinorVersion,
+    HeaderField::DataOffset,
+    HeaderField::Extension,
+];
+
+/// The layout of a control record in the decompressed data section
+///
+/// The `add` bytes are added to the bytes of the old blob at its current position, the `copy`
+/// bytes are copied to the new blob as they are, and `seek` then moves the position in the old
+/// blob.
+pub const RECORD_LAYOUT: &str =
+    "add_len: varint, add: bytes[add_len], copy_len: varint, copy: bytes[copy_len], seek: zigzag";
+
+/// The codecs the data section may be compressed with, in ascending order of their identifiers
+pub const CODECS: &[Codec] = &[
+    Codec::Zstd,
+    #[cfg(feature = "brotli")]
+    Codec::Brotli,
+];
+
+/// The tagged fields defined for the extension area, in ascending order of their tags
+pub const TAGGED_FIELDS: &[TaggedField] = &[
+    TaggedField::new(
+        TAG_FILE_MODE,
+        "file-mode",
+        FormatFeature::FileMetadata,
+        "mode: varint",
+    ),
+    TaggedField::new(
+        TAG_FILE_MODIFIED,
+        "file-modified",
+        FormatFeature::FileMetadata,
+        TIME_LAYOUT,
+    ),
+    TaggedField::new(
+        TAG_FEC,
+        "fec",
+        FormatFeature::Fec,
+        "block_size: varint, data_blocks: varint, parity_blocks: varint, payload_len: varint",
+    ),
+    TaggedField::new(
+        TAG_TARGET_PLATFORM,
+        "target-platform",
+        FormatFeature::Target,
+        "platform: utf8",
+    ),
+    TaggedField::new(
+        TAG_TARGET_ABI,
+        "target-abi",
+        FormatFeature::Target,
+        "abi: utf8",
+    ),
+    TaggedField::new(
+        TAG_TARGET_VERSION_CODE,
+        "target-version-code",
+        FormatFeature::Target,
+        "version_code: varint",
+    ),
+    TaggedField::new(
+        TAG_PROVENANCE_TOOL,
+        "provenance-tool",
+        FormatFeature::Provenance,
+        "tool: utf8",
+    ),
+    TaggedField::new(
+        TAG_PROVENANCE_CREATED,
+        "provenance-created",
+        FormatFeature::Provenance,
+        TIME_LAYOUT,
+    ),
+    TaggedField::new(
+        TAG_PROVENANCE_OLD_ID,
+        "provenance-old-id",
+        FormatFeature::Provenance,
+        "old_id: utf8",
+    ),
+    TaggedField::new(
+        TAG_PROVENANCE_NEW_ID,
+        "provenance-new-id",
+        FormatFeature::Provenance,
+        "new_id: utf8",
+    ),
+    TaggedField::new(
+        TAG_PAYLOAD_CHECKSUM,
+        "payload-checksum",
+        FormatFeature::PayloadChecksum,
+        "len: varint, crc32: u32le",
+    ),
+    TaggedField::new(
+        TAG_TEXT_HINTS,
+        "text-hints",
+        FormatFeature::TextHints,
+        "truncated: varint, *(new_gap: varint, new_lines: varint, old_start: varint, \
+        old_lines: varint)",
+    ),
+    TaggedField::new(
+        TAG_BASE_CHECK,
+        "base-check",
+        FormatFeature::BaseCheck,
+        "old_len: varint, *(offset: varint, len: varint, crc32: u32le)",
+    ),
+    TaggedField::new(
+        TAG_DIFF_SETTINGS,
+        "diff-settings",
+        FormatFeature::DiffSettings,
+        "compression_level: zigzag, window_log: varint, match_threshold: varint",
+    ),
+    TaggedField::new(
+        TAG_NEW_LEN,
+        "new-len",
+        FormatFeature::NewLen,
+        "new_len: varint",
+    ),
+    TaggedField::new(TAG_CODEC, "codec", FormatFeature::Codec, "codec: varint"),
+    TaggedField::new(
+        TAG_ANNOTATION,
+        "annotation",
+        FormatFeature::Annotation,
+        "annotation: zstd(utf8)",
+    ),
+    TaggedField::new(
+        TAG_BLOCK_MAP,
+        "block-map",
+        FormatFeature::BlockMap,
+        "block_size: varint, new_len: varint, *(unchanged_blocks: varint, changed_blocks: varint)",
+    ),
+    TaggedField::new(
+        TAG_REQUIREMENTS,
+        "requirements",
+        FormatFeature::Requirements,
+        "min_minor_version: varint, *(name_len: varint, name: bytes[name_len])",
+    ),
+    TaggedField::new(
+        TAG_DIGESTS,
+        "digests",
+        FormatFeature::Digests,
+        "algorithm: varint, old_digest: bytes[32], new_digest: bytes[32]",
+    ),
+];
+
+/// The layout of a point in time, given relative to the Unix epoch
+const TIME_LAYOUT: &str = "secs: zigzag, nanos: varint";
+
+/// Identifies a patch from its first bytes, returning the version of the patch format it uses
+///
+/// Only the magic number and version are checked, so `bytes` needs to be at least 8 bytes long,
+/// and the rest of the patch may still be invalid. Returns `None` if `bytes` isn't the beginning
+/// of a patch or if the patch's major version isn't supported by this version of the crate.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> std::io::Result<()> {
+/// let mut patch = Vec::new();
+/// ina::diff(&ina::OldBlob::from_slice(b"Hello"), b"Hero", &mut patch)?;
+///
+/// let version = ina::format::sniff(&patch).expect("patch isn't recognized");
+/// assert_eq!(version.major(), 1);
+/// assert_eq!(ina::format::sniff(b"<!DOCTYPE html>"), None);
+/// # Ok(())
+/// # }
+/// ```
+pub fn sniff(bytes: &[u8]) -> Option<PatchVersion> {
+    let (magic, rest) = bytes.split_first_chunk::<4>()?;
+    if *magic != MAGIC {
+        return None;
+    }
+    let (major, rest) = rest.split_first_chunk::<2>()?;
+    let (minor, _) = rest.split_first_chunk::<2>()?;
+
+    PatchVersion::from_values(u16::from_le_bytes(*major), u16::from_le_bytes(*minor)).ok()
+}
+
+/// A strategy for reading the parts of a patch whose encoding depends on its major version
+///
+/// The magic number and version which start every patch are read before a reader is selected, so
+/// a reader starts reading right after them. Each major version has its own reader so that patches
+/// of older versions keep being read exactly as before when a new major version changes the
+/// encoding.
+pub(crate) trait FormatReader {
+    /// Reads the rest of the header of a patch of the given version, leaving `patch` at the start
+    /// of the data section
+    fn read_metadata(
+        &self,
+        version: PatchVersion,
+        patch: &mut CountingReader<&mut dyn Read>,
+    ) -> Result<PatchMetadata, PatchError>;
+}
+
+/// Returns the reader for patches of the given version
+pub(crate) fn reader(version: PatchVersion) -> &'static dyn FormatReader {
+    match version.major_version() {
+        MajorVersion::One => &V1,
+    }
+}
+
+/// The reader for version 1.x patches
+///
+/// The fixed portion of the header is followed by the varint length of the extension area and the
+/// extension area itself, which consists of tagged fields as described in `header.rs`.
+struct V1;
+
+impl FormatReader for V1 {
+    fn read_metadata(
+        &self,
+        version: PatchVersion,
+        patch: &mut CountingReader<&mut dyn Read>,
+    ) -> Result<PatchMetadata, PatchError> {
+        let data_offset = read_header_field(patch, HeaderField::DataOffset, |patch| {
+            patch.read_varint::<u64>()
+        })?;
+
+        let extension_start = patch.count();
+        let mut extension = Vec::new();
+        read_header_field(patch, HeaderField::Extension, |patch| {
+            patch.take(data_offset).read_to_end(&mut extension)?;
+            if (extension.len() as u64) < data_offset {
+                return Err(io::Error::new(
+                    ErrorKind::UnexpectedEof,
+                    format!(
+                        "extension area is {data_offset} bytes long, but the patch ends after {} \
+                        bytes of it",
+                        extension.len(),
+                    ),
+                ));
+            }
+
+            Ok(())
+        })?;
+
+        let mut metadata = PatchMetadata::new(version);
+        let mut fields = Fields::new(&extension);
+        while let Some(field) = fields.next() {
+            let (tag, value) = field.map_err(|e| {
+                header_error(
+                    HeaderField::ExtensionField,
+                    extension_start + fields.pos() as u64,
+                    e,
+                )
+            })?;
+            metadata.parse_field(tag, value)?;
+        }
+
+        Ok(metadata)
+    }
+}
+
+/// An optional feature of the patch format.
+///
+/// See [`FormatCapabilities`] for details.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
+#[non_exhaustive]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum FormatFeature {
+    /// The length of the new blob is recorded
+    NewLen,
+    /// File metadata of the new blob is recorded
+    FileMetadata,
+    /// The target of the new blob is recorded
+    Target,
+    /// The provenance of the patch is recorded
+    Provenance,
+    /// A checksum of the patch data is recorded
+    PayloadChecksum,
+    /// Hints describing the changed lines of text blobs are recorded
+    TextHints,
+    /// A digest of the old blob is recorded to check it before patching
+    BaseCheck,
+    /// The settings the patch was created with are recorded
+    DiffSettings,
+    /// The patch data is protected by forward error correction
+    Fec,
+    /// The patch data is compressed with a codec other than zstd
+    Codec,
+    /// A free-form annotation, such as release notes, is recorded
+    Annotation,
+    /// The blocks of the new blob which differ from the old blob are recorded
+    BlockMap,
+    /// What a patcher must support to apply the patch is recorded
+    Requirements,
+    /// Digests of the old and new blobs are recorded to check them
+    Digests,
+}
+
+impl FormatFeature {
+    /// Returns the feature a header field with the given tag belongs to, if known
+    fn of_tag(tag: u64) -> Option<Self> {
+        TAGGED_FIELDS
+            .iter()
+            .find(|field| field.tag == tag)
+            .map(|field| field.feature)
+    }
+}
+
+impl Display for FormatFeature {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let name = match self {
+            Self::NewLen => "new-len",
+            Self::FileMetadata => "file-metadata",
+            Self::Target => "target",
+            Self::Provenance => "provenance",
+            Self::PayloadChecksum => "payload-checksum",
+            Self::TextHints => "text-hints",
+            Self::BaseCheck => "base-check",
+            Self::DiffSettings => "diff-settings",
+            Self::Fec => "fec",
+            Self::Codec => "codec",
+            Self::Annotation => "annotation",
+            Self::BlockMap => "block-map",
+            Self::Requirements => "requirements",
+            Self::Digests => "digests",
+        };
+
+        f.write_str(name)
+    }
+}
+
+/// A field defined for the extension area of the patch header.
+///
+/// Each field is encoded as its varint tag, the varint length of its value, and the value, which
+/// is laid out as described by [`layout()`](TaggedField::layout). See [`TAGGED_FIELDS`] for the
+/// defined fields.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct TaggedField {
+    tag: u64,
+    name: &'static str,
+    feature: FormatFeature,
+    layout: &'static str,
+}
+
+impl TaggedField {
+    const fn new(
+        tag: u64,
+        name: &'static str,
+        feature: FormatFeature,
+        layout: &'static str,
+    ) -> Self {
+        Self {
+            tag,
+            name,
+            feature,
+            layout,
+        }
+    }
+
+    /// Returns the tag identifying the field
+    pub fn tag(&self) -> u64 {
+        self.tag
+    }
+
+    /// Returns the name of the field, e.g., `new-len`
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Returns the feature the field belongs to
+    pub fn feature(&self) -> FormatFeature {
+        self.feature
+    }
+
+    /// Returns the layout of the field's value in the notation described in the
+    /// [module documentation](self)
+    pub fn layout(&self) -> &'static str {
+        self.layout
+    }
+}
+
+/// The optional features of the patch format a patch uses.
+///
+/// Returned by [`PatchMetadata::format_capabilities()`], this allows telling which features a
+/// patcher must support to make full use of a patch, e.g., before distributing it to devices with
+/// older patchers. Header fields this version of the crate doesn't understand are reported by
+/// their tags, since they may belong to features added in newer minor versions of the format.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use ina::{DiffConfig, FormatFeature};
+///
+/// let mut patch = Vec::new();
+/// let old = ina::OldBlob::from_slice(b"Hello");
+/// ina::diff_with_config(&old, b"Hero", &mut patch, DiffConfig::new().payload_checksum(true))?;
+///
+/// let capabilities = ina::read_header(&mut patch.as_slice())?.format_capabilities().clone();
+/// assert!(capabilities.uses(FormatFeature::PayloadChecksum));
+/// assert!(!capabilities.uses(FormatFeature::Fec));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FormatCapabilities {
+    features: BTreeSet<FormatFeature>,
+    unknown_tags: BTreeSet<u64>,
+}
+
+impl FormatCapabilities {
+    /// Returns whether the patch uses `feature`
+    pub fn uses(&self, feature: FormatFeature) -> bool {
+        self.features.contains(&feature)
+    }
+
+    /// Returns the features the patch uses in ascending order
+    pub fn features(&self) -> impl Iterator<Item = FormatFeature> + '_ {
+        self.features.iter().copied()
+    }
+
+    /// Returns the tags of the header fields this version of the crate doesn't understand in
+    /// ascending order
+    pub fn unknown_tags(&self) -> impl Iterator<Item = u64> + '_ {
+        self.unknown_tags.iter().copied()
+    }
+
+    /// Records that the patch has a header field with the given tag
+    pub(crate) fn insert_tag(&mut self, tag: u64) {
+        match FormatFeature::of_tag(tag) {
+            Some(feature) => self.features.insert(feature),
+            None => self.unknown_tags.insert(tag),
+        };
+    }
+}
@@ -2,9 +2,128 @@
 //
 // SPDX-License-Identifier: Apache-2.0
 
+#[cfg(feature = "patch")]
+use std::io;
+
+#[cfg(feature = "patch")]
+use integer_encoding::VarInt;
+#[cfg(feature = "diff")]
+use integer_encoding::VarIntWriter;
+
 pub(crate) const MAGIC: u32 = 0x5c956c7c;
 pub(crate) const VERSION_MAJOR: u16 = 1;
+pub(crate) const VERSION_MINOR: u16 = 2;
+
+// Tags of the fields which may be present in the header extension area
+pub(crate) const TAG_FILE_MODE: u64 = 1;
+pub(crate) const TAG_FILE_MODIFIED: u64 = 2;
+#[cfg(any(feature = "patch", feature = "fec"))]
+pub(crate) const TAG_FEC: u64 = 3;
+pub(crate) const TAG_TARGET_PLATFORM: u64 = 4;
+pub(crate) const TAG_TARGET_ABI: u64 = 5;
+pub(crate) const TAG_TARGET_VERSION_CODE: u64 = 6;
+pub(crate) const TAG_PROVENANCE_TOOL: u64 = 7;
+pub(crate) const TAG_PROVENANCE_CREATED: u64 = 8;
+pub(crate) const TAG_PROVENANCE_OLD_ID: u64 = 9;
+pub(crate) const TAG_PROVENANCE_NEW_ID: u64 = 10;
+pub(crate) const TAG_PAYLOAD_CHECKSUM: u64 = 11;
+pub(crate) const TAG_TEXT_HINTS: u64 = 12;
+pub(crate) const TAG_BASE_CHECK: u64 = 13;
+pub(crate) const TAG_DIFF_SETTINGS: u64 = 14;
+pub(crate) const TAG_NEW_LEN: u64 = 15;
+pub(crate) const TAG_CODEC: u64 = 16;
+pub(crate) const TAG_ANNOTATION: u64 = 17;
+pub(crate) const TAG_BLOCK_MAP: u64 = 18;
+pub(crate) const TAG_REQUIREMENTS: u64 = 19;
+#[cfg(feature = "patch")]
+pub(crate) const TAG_DIGESTS: u64 = 20;
+
+/// A builder for the header extension area
+///
+/// The extension area immediately follows the fixed portion of the header and consists of a
+/// sequence of fields, each encoded as a varint tag, a varint length, and `length` bytes of value.
+/// Readers skip fields with tags they don't understand.
 #[cfg(feature = "diff")]
-pub(crate) const VERSION_MINOR: u16 = 0;
+#[derive(Default)]
+pub(crate) struct FieldsWriter {
+    buf: Vec<u8>,
+}
+
 #[cfg(feature = "diff")]
-pub(crate) const DATA_OFFSET: u16 = 0;
+impl FieldsWriter {
+    pub(crate) fn push(&mut self, tag: u64, value: &[u8]) {
+        // Writing to a Vec never fails
+        self.buf.write_varint(tag).unwrap();
+        self.buf.write_varint(value.len()).unwrap();
+        self.buf.extend_from_slice(value);
+    }
+
+    pub(crate) fn into_bytes(self) -> Vec<u8> {
+        self.buf
+    }
+}
+
+/// An iterator over the fields of a header extension area
+#[cfg(feature = "patch")]
+pub(crate) struct Fields<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+#[cfg(feature = "patch")]
+impl<'a> Fields<'a> {
+    pub(crate) fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    /// Returns the offset of the next field from the start of the extension area, or of the
+    /// malformed field after an error
+    pub(crate) fn pos(&self) -> usize {
+        self.pos
+    }
+
+    fn read_varint<V: VarInt>(&mut self) -> io::Result<V> {
+        let (value, len) = V::decode_var(self.data).ok_or_else(truncated)?;
+        self.data = &self.data[len..];
+
+        Ok(value)
+    }
+}
+
+#[cfg(feature = "patch")]
+impl<'a> Iterator for Fields<'a> {
+    type Item = io::Result<(u64, &'a [u8])>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.data.is_empty() {
+            return None;
+        }
+
+        let remaining = self.data.len();
+        let field = (|| {
+            let tag = self.read_varint::<u64>()?;
+            let len = self.read_varint::<usize>()?;
+            if len > self.data.len() {
+                return Err(truncated());
+            }
+            let (value, rest) = self.data.split_at(len);
+            self.data = rest;
+
+            Ok((tag, value))
+        })();
+
+        // Stop iterating after the first error since field boundaries are lost
+        if field.is_err() {
+            self.data = &[];
+        } else {
+            self.pos += remaining - self.data.len();
+        }
+
+        Some(field)
+    }
+}
+
+#[cfg(feature = "patch")]
+fn truncated() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "truncated header field")
+}
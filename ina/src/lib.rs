@@ -14,11 +14,10 @@
 //!
 //! ```no_run
 //! use std::fs::{self, File};
+//! use ina::OldBlob;
 //!
 //! # fn main() -> std::io::Result<()> {
-//! let mut old = fs::read("app-v1.exe")?;
-//! // Ensure the last byte is a 0
-//! old.push(0);
+//! let old = OldBlob::from_vec(fs::read("app-v1.exe")?);
 //! let new = fs::read("app-v2.exe")?;
 //! let mut patch = File::create("app-v1-to-v2.ina")?;
 //!
@@ -43,21 +42,232 @@
 //! # Ok(())
 //! # }
 //! ```
+//!
+//! # Unsafe code
+//!
+//! Only the `mmap`, `reflink`, and `sandbox` features rely on unsafe code, which is confined to the
+//! module implementing each of them. Any combination of features without these three forbids
+//! unsafe code crate-wide, so security-sensitive consumers can rule it out entirely by disabling
+//! them. Enabling the `safe-only` feature makes that a build error instead of something to keep
+//! track of, since it can't be combined with any of the three.
+
+#![deny(unsafe_code)]
+#![cfg_attr(
+    not(any(feature = "mmap", feature = "reflink", feature = "sandbox")),
+    forbid(unsafe_code)
+)]
+
+#[cfg(all(
+    feature = "safe-only",
+    any(feature = "mmap", feature = "reflink", feature = "sandbox")
+))]
+compile_error!("the `safe-only` feature can't be combined with `mmap`, `reflink`, or `sandbox`");
 
+// Unsafe code in a module allowing it, as every module relying on unsafe code does, which
+// `cargo xtask safe-only` checks fails to compile whenever unsafe code is forbidden
+#[cfg(ina_unsafe_probe)]
+#[allow(dead_code, unsafe_code)]
+fn unsafe_probe() {
+    // SAFETY: The block is empty
+    unsafe {}
+}
+
+#[cfg(any(feature = "diff", feature = "patch"))]
+mod annotation;
+#[cfg(any(feature = "diff", feature = "patch"))]
+mod base_check;
+#[cfg(feature = "random-access")]
+mod blob;
+#[cfg(any(feature = "diff", feature = "patch"))]
+mod block_map;
 #[cfg(feature = "diff")]
 mod bsdiff;
+#[cfg(feature = "bundle")]
+mod bundle;
+#[cfg(any(feature = "diff", feature = "patch"))]
+mod checksum;
+#[cfg(feature = "patch")]
+mod chunks;
+#[cfg(any(feature = "diff", feature = "patch"))]
+mod codec;
+#[cfg(feature = "diff")]
+mod compare;
+#[cfg(feature = "compressed-old")]
+mod compressed_old;
+#[cfg(all(feature = "patch", any(feature = "diff", feature = "unstable")))]
+mod control;
 #[cfg(feature = "diff")]
 mod diff;
+#[cfg(any(feature = "bundle", feature = "verify"))]
+mod digest;
+#[cfg(feature = "engine")]
+mod engine;
+#[cfg(all(feature = "fec", any(feature = "diff", feature = "patch")))]
+mod fec;
+#[cfg(any(feature = "diff", feature = "patch"))]
+mod file_metadata;
+#[cfg(feature = "patch")]
+pub mod format;
 #[cfg(any(feature = "diff", feature = "patch"))]
 mod header;
-#[cfg(feature = "java-ffi")]
-mod jni;
+#[cfg(feature = "patch")]
+mod limits;
+#[cfg(feature = "lint")]
+mod lint;
+#[cfg(feature = "diff")]
+mod mask;
+#[cfg(feature = "mmap")]
+#[allow(unsafe_code)]
+mod mmap;
+#[cfg(feature = "diff")]
+mod old_blob;
 #[cfg(feature = "patch")]
 mod patch;
+#[cfg(feature = "patch")]
+mod payload;
+mod plan;
+#[cfg(feature = "diff")]
+mod profile;
+#[cfg(any(feature = "diff", feature = "patch"))]
+mod provenance;
+#[cfg(feature = "patch")]
+mod provider;
+#[cfg(all(feature = "diff", feature = "patch"))]
+mod rediff;
+#[cfg(all(feature = "reflink", target_os = "linux"))]
+#[allow(unsafe_code)]
+mod reflink;
+#[cfg(any(feature = "diff", feature = "patch"))]
+mod requirements;
 #[cfg(feature = "sandbox")]
 pub mod sandbox;
+#[cfg(feature = "diff")]
+mod seek_bound;
+#[cfg(feature = "patch")]
+mod seek_policy;
+mod segment;
+#[cfg(feature = "selftest")]
+pub mod selftest;
+#[cfg(any(feature = "diff", feature = "patch"))]
+mod settings;
+#[cfg(any(feature = "diff", feature = "patch"))]
+mod stats;
+#[cfg(any(feature = "diff", feature = "patch"))]
+mod target;
+#[cfg(feature = "test-util")]
+pub mod test_util;
+#[cfg(any(feature = "diff", feature = "patch"))]
+mod text;
+#[cfg(all(feature = "diff", feature = "patch"))]
+mod transcode;
+#[cfg(feature = "diff")]
+mod tune;
+#[cfg(feature = "unstable")]
+pub mod unstable;
+#[cfg(feature = "verify")]
+mod verify;
+#[cfg(feature = "patch")]
+mod watchdog;
+#[cfg(feature = "diff")]
+mod writer;
+#[cfg(feature = "zip")]
+mod zip_entry;
 
+/// Suffix arrays, the index used to find matches when diffing.
+///
+/// This is a re-export of the [`sufsort`] crate, which is available whenever the `diff` feature is
+/// enabled. Its optional functionality can be enabled through the `index-bwt`, `index-lcp`,
+/// `index-mapped`, and `index-owned` features of this crate, which enable the `bwt`, `lcp`,
+/// `mapped`, and `owned` features of `sufsort` respectively.
+#[cfg(feature = "sufsort")]
+pub use sufsort as index;
+
+#[cfg(any(feature = "diff", feature = "patch"))]
+pub use base_check::{BaseCheck, BaseDigest};
+#[cfg(feature = "random-access")]
+pub use blob::{PatchedBlob, ReadAt};
+#[cfg(any(feature = "diff", feature = "patch"))]
+pub use block_map::BlockMap;
+#[cfg(all(feature = "bundle", feature = "diff"))]
+pub use bundle::BundleWriter;
+#[cfg(feature = "bundle")]
+pub use bundle::{BundleEntry, BundleEntryKind, BundleReader, is_bundle};
+#[cfg(all(feature = "bytes", feature = "patch"))]
+pub use chunks::ByteChunks;
+#[cfg(feature = "patch")]
+pub use chunks::{AlignedBlock, AlignedBlocks, BlockUpdate, BlockUpdates, Chunks};
+#[cfg(any(feature = "diff", feature = "patch"))]
+pub use codec::Codec;
+#[cfg(feature = "diff")]
+pub use compare::{Comparison, RegionMatch, compare};
+#[cfg(feature = "compressed-old")]
+pub use compressed_old::{CompressedOld, OldCompression};
+#[cfg(feature = "diff")]
+pub use diff::{
+    DiffConfig, diff, diff_readers, diff_to_vec, diff_windowed, diff_with_config, diff_with_index,
+};
+#[cfg(feature = "verify")]
+pub use digest::BlobDigests;
+#[cfg(any(feature = "bundle", feature = "verify"))]
+pub use digest::{DIGEST_LEN, Digest, DigestAlgorithm};
+#[cfg(feature = "engine")]
+pub use engine::{DiffEngine, DiffJob, DiffResult, EngineMetrics, EngineOptions, OldRef};
+#[cfg(all(feature = "fec", any(feature = "diff", feature = "patch")))]
+pub use fec::FecConfig;
+#[cfg(any(feature = "diff", feature = "patch"))]
+pub use file_metadata::FileMetadata;
+#[cfg(feature = "patch")]
+pub use format::{FormatCapabilities, FormatFeature};
+#[cfg(feature = "patch")]
+pub use limits::PatchLimits;
+#[cfg(feature = "lint")]
+pub use lint::{LintFinding, LintReport, LintSeverity, lint};
+#[cfg(feature = "mmap")]
+pub use mmap::MappedFile;
 #[cfg(feature = "diff")]
-pub use diff::{DiffConfig, diff, diff_with_config};
+pub use old_blob::OldBlob;
 #[cfg(feature = "patch")]
-pub use patch::{PatchError, PatchMetadata, PatchVersion, Patcher, patch, read_header};
+pub use patch::{
+    HeaderError, HeaderField, PatchError, PatchMetadata, PatchVersion, Patcher, SinkError, patch,
+    patch_multi, patch_to_vec, patch_with_hook, read_header, restore_file_metadata,
+};
+#[cfg(all(feature = "patch", any(unix, windows)))]
+pub use patch::{patch_into, patch_to_file};
+pub use plan::{Catalog, CatalogPatch, ChainPlan, plan_chain};
+#[cfg(feature = "diff")]
+pub use profile::Profile;
+#[cfg(any(feature = "diff", feature = "patch"))]
+pub use provenance::Provenance;
+#[cfg(feature = "patch")]
+pub use provider::{OldProvider, ProvidedOld};
+#[cfg(all(feature = "diff", feature = "patch"))]
+pub use rediff::{RediffCheck, RediffReason, check_rediff};
+#[cfg(all(feature = "reflink", target_os = "linux"))]
+pub use reflink::{ReflinkStats, patch_reflink};
+#[cfg(any(feature = "diff", feature = "patch"))]
+pub use requirements::Requirements;
+#[cfg(feature = "patch")]
+pub use seek_policy::{SeekPolicy, SeekRule, SeekViolation};
+pub use segment::{SEGMENT_HEADER_LEN, SegmentReader, join_segments, split_patch};
+#[cfg(any(feature = "diff", feature = "patch"))]
+pub use settings::DiffSettings;
+#[cfg(any(feature = "diff", feature = "patch"))]
+pub use stats::{DiffStats, FastPath, OldCoverage, SeekHistogram, SeekStats};
+#[cfg(any(feature = "diff", feature = "patch"))]
+pub use target::Target;
+#[cfg(feature = "diff")]
+pub use text::TextMode;
+#[cfg(any(feature = "diff", feature = "patch"))]
+pub use text::{LineChange, TextHints};
+#[cfg(all(feature = "diff", feature = "patch"))]
+pub use transcode::transcode;
+#[cfg(feature = "diff")]
+pub use tune::{TuneMatrix, TuneResult, tune};
+#[cfg(feature = "verify")]
+pub use verify::{VerifyOptions, verify, verify_against};
+#[cfg(feature = "patch")]
+pub use watchdog::WatchdogReader;
+#[cfg(feature = "diff")]
+pub use writer::PatchWriter;
+#[cfg(feature = "zip")]
+pub use zip_entry::ZipEntryReader;
@@ -0,0 +1,296 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use zstd::zstd_safe;
+
+/// The magic number beginning every zstd frame
+const ZSTD_MAGIC: u32 = 0xfd2fb528;
+
+/// The smallest window size zstd supports
+const MIN_WINDOW_LOG: u32 = 10;
+
+/// The smallest buffer a `Patcher` uses when memory-limited or configured with explicit buffer
+/// sizes
+const MIN_BUF_SIZE: usize = 1024;
+
+/// The default size of the buffer holding the difference bytes of add fields
+pub(crate) const DEFAULT_DIFF_BUF_SIZE: usize = 8192;
+
+/// The number of bytes of patch data allowed on top of the expansion limit
+///
+/// This leaves room for the control record fields that precede the first bytes of output.
+const EXPANSION_ALLOWANCE: u64 = 64 << 10;
+
+/// Resource limits for patching.
+///
+/// Patches may request large decompression windows, so applying an untrusted or misconfigured
+/// patch on a memory-constrained device can exhaust its memory. Passing limits to
+/// [`Patcher::with_limits()`](crate::Patcher::with_limits) bounds the memory a `Patcher` uses, and
+/// rejects patches which can't be applied within those bounds before any of their data is
+/// decompressed when possible.
+///
+/// A small patch may also decompress to an enormous amount of data which produces little or no
+/// output, e.g., a long run of empty control records, keeping a `Patcher` busy indefinitely. The
+/// expansion limit and the maximum new blob length guard against such decompression bombs.
+///
+/// The sizes of a `Patcher`'s buffers can be set as well, e.g., to keep them small on embedded
+/// devices or to reduce the number of reads on servers.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::fs::File;
+/// use ina::{PatchLimits, Patcher};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let old = File::open("app-v1.exe")?;
+/// let patch = File::open("app-v1-to-v2.ina")?;
+///
+/// // Never use more than approximately 64 MiB of memory
+/// let patcher = Patcher::with_limits(old, patch, PatchLimits::new().max_memory(64 << 20))?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PatchLimits {
+    max_memory: Option<u64>,
+    max_expansion: Option<u32>,
+    max_new_len: Option<u64>,
+    read_buffer_size: Option<usize>,
+    diff_buffer_size: Option<usize>,
+}
+
+impl PatchLimits {
+    /// Creates a new set of limits which doesn't impose any additional restrictions
+    ///
+    /// Without a memory limit, patches may use decompression windows of up to 128 MiB.
+    pub const fn new() -> Self {
+        Self {
+            max_memory: None,
+            max_expansion: None,
+            max_new_len: None,
+            read_buffer_size: None,
+            diff_buffer_size: None,
+        }
+    }
+
+    /// Sets the approximate maximum number of bytes of memory a `Patcher` may use.
+    ///
+    /// The limit covers the decompression window and a `Patcher`'s internal buffers, which are
+    /// shrunk as needed to fit. Memory used by the old blob and patch readers themselves isn't
+    /// included.
+    pub fn max_memory(&mut self, bytes: u64) -> &mut Self {
+        self.max_memory = Some(bytes);
+        self
+    }
+
+    /// Sets the maximum size of the decompressed patch data relative to the new blob.
+    ///
+    /// The decompressed data of a well-formed patch consists of the new blob's bytes, as
+    /// differences or verbatim, plus a few bytes of framing per control record, so it's rarely
+    /// much larger than the new blob. With this limit, a `Patcher` fails with
+    /// [`PatchError::ExpansionLimitExceeded`] once it has decompressed more than `multiplier` times
+    /// the number of bytes it has output plus a small allowance. If a
+    /// [maximum new blob length](Self::max_new_len) is set as well, patches whose data declares a
+    /// decompressed size exceeding `multiplier` times that length are rejected before any of it is
+    /// decompressed.
+    ///
+    /// A multiplier of 2 accommodates the patches produced by this crate. By default, there is no
+    /// limit.
+    ///
+    /// [`PatchError::ExpansionLimitExceeded`]: crate::PatchError::ExpansionLimitExceeded
+    pub fn max_expansion(&mut self, multiplier: u32) -> &mut Self {
+        self.max_expansion = Some(multiplier);
+        self
+    }
+
+    /// Sets the maximum length of the new blob in bytes, e.g., as declared by an update manifest.
+    ///
+    /// A `Patcher` fails with [`PatchError::NewLenLimitExceeded`] once its output would exceed
+    /// this length. By default, there is no limit.
+    ///
+    /// [`PatchError::NewLenLimitExceeded`]: crate::PatchError::NewLenLimitExceeded
+    pub fn max_new_len(&mut self, bytes: u64) -> &mut Self {
+        self.max_new_len = Some(bytes);
+        self
+    }
+
+    /// Sets the size in bytes of the buffer the patch is read through.
+    ///
+    /// By default, the buffer is sized for the decompression algorithm in use, currently about
+    /// 128 KiB, and shrunk to fit the [memory limit](Self::max_memory) if one is set. Smaller
+    /// buffers save memory at the cost of more reads from the patch. Sizes below 1 KiB are rounded
+    /// up to 1 KiB.
+    pub fn read_buffer_size(&mut self, bytes: usize) -> &mut Self {
+        self.read_buffer_size = Some(bytes);
+        self
+    }
+
+    /// Sets the size in bytes of the buffer holding the difference bytes of the patch while
+    /// they're applied to the old blob.
+    ///
+    /// This bounds how much of the old blob a `Patcher` reads at once. The default is 8 KiB.
+    /// Sizes below 1 KiB are rounded up to 1 KiB.
+    pub fn diff_buffer_size(&mut self, bytes: usize) -> &mut Self {
+        self.diff_buffer_size = Some(bytes);
+        self
+    }
+
+    /// Returns the maximum length of the new blob, if any
+    pub(crate) fn new_len_limit(&self) -> Option<u64> {
+        self.max_new_len
+    }
+
+    /// Returns the maximum number of bytes of patch data which may be decompressed after
+    /// outputting `new_len` bytes, if any
+    pub(crate) fn expansion_limit(&self, new_len: u64) -> Option<u64> {
+        self.max_expansion.map(|multiplier| {
+            new_len
+                .saturating_mul(u64::from(multiplier))
+                .saturating_add(EXPANSION_ALLOWANCE)
+        })
+    }
+
+    /// Returns the maximum number of bytes the patch data may decompress to in total, if known
+    pub(crate) fn total_expansion_limit(&self) -> Option<u64> {
+        self.max_new_len
+            .and_then(|max_new_len| self.expansion_limit(max_new_len))
+    }
+
+    /// Returns the size of the read buffer to use for the patch
+    pub(crate) fn read_buf_size(&self) -> usize {
+        if let Some(size) = self.read_buffer_size {
+            return size.max(MIN_BUF_SIZE);
+        }
+        let default = zstd_safe::DCtx::in_size();
+
+        match self.max_memory {
+            Some(max) => (max / 8)
+                .try_into()
+                .map_or(default, |fraction: usize| fraction.min(default))
+                .max(MIN_BUF_SIZE),
+            None => default,
+        }
+    }
+
+    /// Returns the size of the buffer to use for difference bytes
+    pub(crate) fn diff_buf_size(&self) -> usize {
+        self.diff_buffer_size
+            .map_or(DEFAULT_DIFF_BUF_SIZE, |size| size.max(MIN_BUF_SIZE))
+    }
+
+    /// Returns the base-2 logarithm of the largest decompression window which fits in the memory
+    /// limit alongside `overhead` bytes of other allocations
+    ///
+    /// Returns `Ok(None)` if there is no memory limit, or `Err(required)` with the approximate
+    /// amount of memory required if even the smallest window doesn't fit.
+    pub(crate) fn window_log_max(&self, overhead: u64) -> Result<Option<u32>, u64> {
+        let Some(max) = self.max_memory else {
+            return Ok(None);
+        };
+        let overhead = overhead + decoder_overhead();
+        let min_required = overhead + (1 << MIN_WINDOW_LOG);
+
+        match max.checked_sub(overhead) {
+            Some(budget) if budget >= 1 << MIN_WINDOW_LOG => Ok(Some(budget.ilog2())),
+            _ => Err(min_required),
+        }
+    }
+
+    /// Returns the approximate amount of memory required to decompress with a window of
+    /// `window_size` bytes alongside `overhead` bytes of other allocations, if it exceeds the
+    /// memory limit
+    pub(crate) fn check_window(&self, overhead: u64, window_size: u64) -> Result<(), u64> {
+        let required = overhead
+            .saturating_add(decoder_overhead())
+            .saturating_add(window_size);
+
+        match self.max_memory {
+            Some(max) if required > max => Err(required),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Returns an estimate of the memory used by a zstd decompression context excluding its window
+///
+/// This covers the context itself along with its input and output block buffers.
+fn decoder_overhead() -> u64 {
+    3 * u64::from(zstd_safe::BLOCKSIZE_MAX)
+}
+
+/// Parses the window size declared by the Brotli stream header at the beginning of `data`
+///
+/// Returns `None` if `data` is empty or declares a large window, which isn't standard Brotli and
+/// is rejected while decompressing.
+#[cfg(feature = "brotli")]
+pub(crate) fn declared_brotli_window_size(data: &[u8]) -> Option<u64> {
+    // The window bits are encoded in 1, 4, or 7 bits as described in section 9.1 of RFC 7932
+    let header = *data.first()?;
+    let window_bits = if header & 0x01 == 0 {
+        16
+    } else if (header >> 1) & 0x07 != 0 {
+        17 + u32::from((header >> 1) & 0x07)
+    } else {
+        match (header >> 4) & 0x07 {
+            0 => 17,
+            1 => return None,
+            bits => 8 + u32::from(bits),
+        }
+    };
+
+    Some((1 << window_bits) - 16)
+}
+
+/// Parses the window size declared by the zstd frame header at the beginning of `data`
+///
+/// Returns `None` if `data` doesn't begin with a complete zstd frame header.
+pub(crate) fn declared_window_size(data: &[u8]) -> Option<u64> {
+    let descriptor = frame_descriptor(data)?;
+    let single_segment = descriptor & 0x20 != 0;
+    if !single_segment {
+        let window_descriptor = *data.get(5)?;
+        let exponent = u32::from(window_descriptor >> 3);
+        let mantissa = u64::from(window_descriptor & 0x07);
+        let base = 1u64 << (MIN_WINDOW_LOG + exponent);
+
+        return Some(base + base / 8 * mantissa);
+    }
+
+    // Single-segment frames have no window descriptor, so their window size is their content size
+    declared_content_size(data)
+}
+
+/// Parses the decompressed size declared by the zstd frame header at the beginning of `data`
+///
+/// Returns `None` if `data` doesn't begin with a complete zstd frame header or the frame doesn't
+/// declare its decompressed size.
+pub(crate) fn declared_content_size(data: &[u8]) -> Option<u64> {
+    let descriptor = frame_descriptor(data)?;
+    let single_segment = descriptor & 0x20 != 0;
+    let size_flag = descriptor >> 6;
+    if size_flag == 0 && !single_segment {
+        return None;
+    }
+
+    let window_descriptor_len = usize::from(!single_segment);
+    let dict_id_len = [0, 1, 2, 4][usize::from(descriptor & 0x03)];
+    let content_size = data.get(5 + window_descriptor_len + dict_id_len..)?;
+    match size_flag {
+        0 => content_size.first().map(|&size| u64::from(size)),
+        1 => Some(u64::from(u16::from_le_bytes(content_size.get(..2)?.try_into().ok()?)) + 256),
+        2 => Some(u64::from(u32::from_le_bytes(
+            content_size.get(..4)?.try_into().ok()?,
+        ))),
+        _ => Some(u64::from_le_bytes(content_size.get(..8)?.try_into().ok()?)),
+    }
+}
+
+/// Returns the frame header descriptor of the zstd frame beginning at the start of `data`
+fn frame_descriptor(data: &[u8]) -> Option<u8> {
+    let magic = u32::from_le_bytes(data.get(..4)?.try_into().ok()?);
+
+    (magic == ZSTD_MAGIC).then_some(*data.get(4)?)
+}
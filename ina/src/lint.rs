@@ -0,0 +1,546 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use std::{
+    collections::BTreeSet,
+    fmt::{self, Display, Formatter},
+    io::{self, BufRead, BufReader, ErrorKind, Read},
+};
+
+use byteorder::{LittleEndian, ReadBytesExt};
+use integer_encoding::VarInt;
+use zstd::zstd_safe::DCtx;
+
+use crate::{
+    Codec, PatchMetadata, PatchVersion,
+    checksum::ChecksumMismatch,
+    header::{
+        MAGIC, TAG_ANNOTATION, TAG_BASE_CHECK, TAG_BLOCK_MAP, TAG_CODEC, TAG_DIFF_SETTINGS,
+        TAG_FEC, TAG_FILE_MODE, TAG_FILE_MODIFIED, TAG_NEW_LEN, TAG_PAYLOAD_CHECKSUM,
+        TAG_PROVENANCE_CREATED, TAG_PROVENANCE_NEW_ID, TAG_PROVENANCE_OLD_ID, TAG_PROVENANCE_TOOL,
+        TAG_TARGET_ABI, TAG_TARGET_PLATFORM, TAG_TARGET_VERSION_CODE, TAG_TEXT_HINTS,
+        VERSION_MINOR,
+    },
+    limits,
+    payload::{Payload, PayloadDecoder},
+};
+
+/// The tags of the header fields defined by the newest minor version of the patch format
+const KNOWN_TAGS: [u64; 18] = [
+    TAG_FILE_MODE,
+    TAG_FILE_MODIFIED,
+    TAG_FEC,
+    TAG_TARGET_PLATFORM,
+    TAG_TARGET_ABI,
+    TAG_TARGET_VERSION_CODE,
+    TAG_PROVENANCE_TOOL,
+    TAG_PROVENANCE_CREATED,
+    TAG_PROVENANCE_OLD_ID,
+    TAG_PROVENANCE_NEW_ID,
+    TAG_PAYLOAD_CHECKSUM,
+    TAG_TEXT_HINTS,
+    TAG_BASE_CHECK,
+    TAG_DIFF_SETTINGS,
+    TAG_NEW_LEN,
+    TAG_CODEC,
+    TAG_ANNOTATION,
+    TAG_BLOCK_MAP,
+];
+
+/// The maximum length of a varint encoding a `u64`
+const MAX_VARINT_LEN: usize = 10;
+
+/// How serious a problem found by [`lint()`] is
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
+pub enum LintSeverity {
+    /// The patch can be applied, but it contains something a conforming writer wouldn't produce
+    /// or that a reader may ignore
+    Warning,
+    /// The patch doesn't conform to the patch format and may be rejected by readers
+    Error,
+}
+
+impl Display for LintSeverity {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let name = match self {
+            Self::Warning => "warning",
+            Self::Error => "error",
+        };
+
+        f.write_str(name)
+    }
+}
+
+/// A problem found by [`lint()`]
+#[derive(Clone, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
+pub struct LintFinding {
+    severity: LintSeverity,
+    code: &'static str,
+    message: String,
+    record: Option<u64>,
+}
+
+impl LintFinding {
+    /// Returns how serious the problem is
+    pub fn severity(&self) -> LintSeverity {
+        self.severity
+    }
+
+    /// Returns the stable, kebab-case identifier of the kind of problem, such as
+    /// `non-canonical-varint`
+    pub fn code(&self) -> &'static str {
+        self.code
+    }
+
+    /// Returns a human-readable description of the problem
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Returns the index of the control record the problem was found in, if it's in the control
+    /// stream
+    pub fn record(&self) -> Option<u64> {
+        self.record
+    }
+}
+
+/// The result of checking a patch with [`lint()`]
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct LintReport {
+    version: Option<PatchVersion>,
+    records: u64,
+    new_len: u64,
+    data_len: u64,
+    findings: Vec<LintFinding>,
+    truncated: bool,
+}
+
+impl LintReport {
+    /// The maximum number of findings recorded in a report
+    pub const MAX_FINDINGS: usize = 1000;
+
+    fn new() -> Self {
+        Self {
+            version: None,
+            records: 0,
+            new_len: 0,
+            data_len: 0,
+            findings: Vec::new(),
+            truncated: false,
+        }
+    }
+
+    /// Returns whether no errors were found. Warnings don't affect conformance.
+    pub fn is_conformant(&self) -> bool {
+        self.errors() == 0
+    }
+
+    /// Returns the version of the patch format, unless the header couldn't be read
+    pub fn version(&self) -> Option<PatchVersion> {
+        self.version
+    }
+
+    /// Returns the number of control records which were decoded
+    pub fn records(&self) -> u64 {
+        self.records
+    }
+
+    /// Returns the length of the new blob described by the decoded control records
+    pub fn new_len(&self) -> u64 {
+        self.new_len
+    }
+
+    /// Returns the number of bytes of the control stream which were decoded
+    pub fn data_len(&self) -> u64 {
+        self.data_len
+    }
+
+    /// Returns the problems found, in the order they were found
+    pub fn findings(&self) -> &[LintFinding] {
+        &self.findings
+    }
+
+    /// Returns the number of findings with severity [`LintSeverity::Error`]
+    pub fn errors(&self) -> usize {
+        self.count(LintSeverity::Error)
+    }
+
+    /// Returns the number of findings with severity [`LintSeverity::Warning`]
+    pub fn warnings(&self) -> usize {
+        self.count(LintSeverity::Warning)
+    }
+
+    /// Returns whether more than [`LintReport::MAX_FINDINGS`] problems were found, in which case
+    /// only the first ones are recorded
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    fn count(&self, severity: LintSeverity) -> usize {
+        self.findings
+            .iter()
+            .filter(|finding| finding.severity == severity)
+            .count()
+    }
+
+    fn push(
+        &mut self,
+        severity: LintSeverity,
+        code: &'static str,
+        message: impl Into<String>,
+        record: Option<u64>,
+    ) {
+        if self.findings.len() < Self::MAX_FINDINGS {
+            self.findings.push(LintFinding {
+                severity,
+                code,
+                message: message.into(),
+                record,
+            });
+        } else {
+            self.truncated = true;
+        }
+    }
+
+    fn error(&mut self, code: &'static str, message: impl Into<String>) {
+        self.push(LintSeverity::Error, code, message, None);
+    }
+
+    fn warning(&mut self, code: &'static str, message: impl Into<String>) {
+        self.push(LintSeverity::Warning, code, message, None);
+    }
+
+    /// Records a malformed encoding which ended checking, propagating other errors
+    fn malformed(&mut self, code: &'static str, error: io::Error) -> io::Result<()> {
+        if !is_malformed(&error) {
+            return Err(error);
+        }
+
+        let code = match error.kind() {
+            // Errors the decompressor returns, such as for incomplete frames, carry a message
+            ErrorKind::UnexpectedEof if error.get_ref().is_none() => code,
+            _ if error.get_ref().is_some_and(|e| e.is::<ChecksumMismatch>()) => "checksum-mismatch",
+            _ => "corrupt-data",
+        };
+        self.error(code, error.to_string());
+
+        Ok(())
+    }
+}
+
+/// Checks that a patch conforms to the patch format without applying it
+///
+/// Unlike applying a patch, checking it doesn't need the old blob, so this function is suitable
+/// for validating patches before accepting them into a repository. The entire patch is read and
+/// decompressed, but the new blob is never reconstructed. The following problems are reported,
+/// identified by their [code](LintFinding::code):
+///
+/// | Code                    | Severity | Problem                                                 |
+/// |-------------------------|----------|---------------------------------------------------------|
+/// | `bad-magic`             | Error    | The patch doesn't begin with the patch magic number     |
+/// | `unsupported-version`   | Error    | The major version of the patch format is unsupported    |
+/// | `newer-minor-version`   | Warning  | The minor version is newer than this library knows      |
+/// | `truncated-header`      | Error    | The patch ends within its header                        |
+/// | `non-canonical-varint`  | Error    | A varint isn't encoded in as few bytes as possible      |
+/// | `malformed-field`       | Error    | A header field's length exceeds the extension area      |
+/// | `invalid-field`         | Error    | A known header field has an invalid value               |
+/// | `duplicate-field`       | Error    | A header field appears more than once                   |
+/// | `unknown-field`         | Warning  | A header field has a tag this library doesn't know      |
+/// | `unsupported-field`     | Error    | This build can't decode the framing of the data section |
+/// | `corrupt-data`          | Error    | The data section can't be decompressed or is cut off    |
+/// | `checksum-mismatch`     | Error    | The data section doesn't match its recorded checksum    |
+/// | `content-size-mismatch` | Error    | The decompressed data section isn't its declared length |
+/// | `truncated-record`      | Error    | The control stream ends within a control record         |
+/// | `seek-before-start`     | Error    | A control record moves before the start of the old blob |
+/// | `empty-record`          | Warning  | A control record adds and copies nothing                |
+///
+/// Problems which prevent the rest of the patch from being decoded, such as a bad magic number
+/// or corrupt data, end checking.
+///
+/// # Errors
+///
+/// Returns an error if an I/O error occurs while reading the patch. Malformed patches are
+/// reported as findings rather than errors.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let mut patch = Vec::new();
+/// ina::diff(&ina::OldBlob::from_slice(b"Hello"), b"Hero", &mut patch)?;
+///
+/// let report = ina::lint(patch.as_slice())?;
+/// assert!(report.is_conformant());
+/// assert_eq!(report.new_len(), 4);
+///
+/// let report = ina::lint(&patch[..10])?;
+/// assert!(!report.is_conformant());
+/// # Ok(())
+/// # }
+/// ```
+pub fn lint<P>(mut patch: P) -> io::Result<LintReport>
+where
+    P: Read,
+{
+    let mut report = LintReport::new();
+
+    let metadata = match lint_header(&mut patch, &mut report) {
+        Ok(Some(metadata)) => metadata,
+        Ok(None) => return Ok(report),
+        Err(e) => {
+            report.malformed("truncated-header", e)?;
+            return Ok(report);
+        }
+    };
+    if let Err(e) = lint_data(patch, &metadata, &mut report) {
+        report.malformed("truncated-record", e)?;
+    }
+
+    Ok(report)
+}
+
+/// Checks the header of `patch`, returning its metadata unless the rest of the patch can't be
+/// decoded
+fn lint_header<P>(patch: &mut P, report: &mut LintReport) -> io::Result<Option<PatchMetadata>>
+where
+    P: Read,
+{
+    let magic = patch.read_u32::<LittleEndian>()?;
+    if magic != MAGIC {
+        report.error(
+            "bad-magic",
+            format!("expected magic {MAGIC:x}, found {magic:x}"),
+        );
+        return Ok(None);
+    }
+
+    let version_major = patch.read_u16::<LittleEndian>()?;
+    let version_minor = patch.read_u16::<LittleEndian>()?;
+    let Ok(version) = PatchVersion::from_values(version_major, version_minor) else {
+        report.error(
+            "unsupported-version",
+            format!("patch format version {version_major}.{version_minor} is unsupported"),
+        );
+        return Ok(None);
+    };
+    report.version = Some(version);
+    if version_minor > VERSION_MINOR {
+        report.warning(
+            "newer-minor-version",
+            format!(
+                "patch format version {version_major}.{version_minor} is newer than \
+                {version_major}.{VERSION_MINOR}, so some header fields may not be checked",
+            ),
+        );
+    }
+
+    let data_offset = read_varint(patch, report, "data offset", None)?
+        .ok_or_else(|| io::Error::from(ErrorKind::UnexpectedEof))?;
+    let mut extension = Vec::new();
+    patch.take(data_offset).read_to_end(&mut extension)?;
+    if (extension.len() as u64) < data_offset {
+        return Err(ErrorKind::UnexpectedEof.into());
+    }
+
+    let mut metadata = PatchMetadata::new(version);
+    let mut seen = BTreeSet::new();
+    let mut fields = extension.as_slice();
+    while !fields.is_empty() {
+        let field = (|| {
+            let tag = read_varint(&mut fields, report, "header field tag", None)?;
+            let len = read_varint(&mut fields, report, "header field length", None)?;
+            Ok::<_, io::Error>(tag.zip(len))
+        })();
+        let Ok(Some((tag, len))) = field else {
+            report.error("malformed-field", "truncated header field");
+            return Ok(None);
+        };
+        if len > fields.len() as u64 {
+            report.error(
+                "malformed-field",
+                format!("header field with tag {tag} extends past the end of the header"),
+            );
+            return Ok(None);
+        }
+        let (value, rest) = fields.split_at(len as usize);
+        fields = rest;
+
+        if !seen.insert(tag) {
+            report.error(
+                "duplicate-field",
+                format!("header field with tag {tag} appears more than once"),
+            );
+        }
+        #[cfg(not(feature = "fec"))]
+        if tag == TAG_FEC {
+            report.error(
+                "unsupported-field",
+                "the data section uses forward error correction, which this build doesn't support",
+            );
+            return Ok(None);
+        }
+        if tag == TAG_CODEC && Codec::decode_field(value).is_none() {
+            report.error(
+                "unsupported-field",
+                "the data section is compressed with a codec this build doesn't support",
+            );
+            return Ok(None);
+        }
+        if !KNOWN_TAGS.contains(&tag) {
+            report.warning(
+                "unknown-field",
+                format!("header field with tag {tag} is unknown and will be ignored"),
+            );
+        } else if let Err(e) = metadata.parse_field(tag, value) {
+            report.error("invalid-field", e.to_string());
+        }
+    }
+
+    Ok(Some(metadata))
+}
+
+/// Checks the data section of a patch with the given metadata
+fn lint_data<P>(patch: P, metadata: &PatchMetadata, report: &mut LintReport) -> io::Result<()>
+where
+    P: Read,
+{
+    let mut payload = Payload::new(BufReader::with_capacity(DCtx::in_size(), patch), metadata);
+    let content_size = limits::declared_content_size(payload.fill_buf()?);
+    let mut data = BufReader::new(PayloadDecoder::new(payload, metadata)?);
+
+    let mut old_pos: i128 = 0;
+    loop {
+        // The decompressor reports an incomplete frame as an unexpected end of file, so the end
+        // of the control stream must be detected before trying to read the next record
+        if data.fill_buf()?.is_empty() {
+            break;
+        }
+
+        let record = Some(report.records);
+        let truncated = || io::Error::from(ErrorKind::UnexpectedEof);
+        let add_len =
+            read_varint(&mut data, report, "add length", record)?.ok_or_else(truncated)?;
+        skip(&mut data, add_len, report)?;
+        let copy_len =
+            read_varint(&mut data, report, "copy length", record)?.ok_or_else(truncated)?;
+        skip(&mut data, copy_len, report)?;
+        let seek = read_varint(&mut data, report, "seek", record)?.ok_or_else(truncated)?;
+        // Seeks are zigzag-encoded
+        let seek = (seek >> 1) as i64 ^ -((seek & 1) as i64);
+
+        if add_len == 0 && copy_len == 0 {
+            report.push(
+                LintSeverity::Warning,
+                "empty-record",
+                "record adds and copies nothing",
+                record,
+            );
+        }
+        old_pos += i128::from(add_len) + i128::from(seek);
+        if old_pos < 0 {
+            report.push(
+                LintSeverity::Error,
+                "seek-before-start",
+                format!("record seeks to offset {old_pos} of the old blob"),
+                record,
+            );
+        }
+
+        report.records += 1;
+        report.new_len = report
+            .new_len
+            .saturating_add(add_len)
+            .saturating_add(copy_len);
+    }
+
+    if let Some(content_size) = content_size
+        && content_size != report.data_len
+    {
+        report.error(
+            "content-size-mismatch",
+            format!(
+                "data section declares {content_size} bytes of control stream but decompresses \
+                to {} bytes",
+                report.data_len,
+            ),
+        );
+    }
+
+    Ok(())
+}
+
+/// Reads a varint from `reader`, flagging it if it isn't encoded in as few bytes as possible
+///
+/// Returns `None` if `reader` is at its end.
+fn read_varint<R>(
+    reader: &mut R,
+    report: &mut LintReport,
+    what: &str,
+    record: Option<u64>,
+) -> io::Result<Option<u64>>
+where
+    R: Read,
+{
+    let mut value = 0;
+    for i in 0..MAX_VARINT_LEN {
+        let byte = match reader.read_u8() {
+            Ok(byte) => byte,
+            Err(e) if i == 0 && e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let bits = u64::from(byte & 0x7f);
+        if i == MAX_VARINT_LEN - 1 && bits > 1 {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                format!("{what} overflows 64 bits"),
+            ));
+        }
+        value |= bits << (7 * i);
+
+        if byte & 0x80 == 0 {
+            let len = i + 1;
+            if record.is_some() {
+                report.data_len += len as u64;
+            }
+            if len != value.required_space() {
+                report.push(
+                    LintSeverity::Error,
+                    "non-canonical-varint",
+                    format!("{what} {value} is encoded in {len} bytes"),
+                    record,
+                );
+            }
+            return Ok(Some(value));
+        }
+    }
+    Err(io::Error::new(
+        ErrorKind::InvalidData,
+        format!("{what} is longer than {MAX_VARINT_LEN} bytes"),
+    ))
+}
+
+/// Skips `len` bytes of the control stream, failing if it ends first
+fn skip<R>(data: &mut R, len: u64, report: &mut LintReport) -> io::Result<()>
+where
+    R: Read,
+{
+    let skipped = io::copy(&mut data.take(len), &mut io::sink())?;
+    report.data_len += skipped;
+    if skipped < len {
+        return Err(ErrorKind::UnexpectedEof.into());
+    }
+
+    Ok(())
+}
+
+/// Returns whether `error` was caused by a malformed patch rather than by failing to read it
+///
+/// Errors from the operating system always have a more specific kind than `Other`, which is only
+/// used by the decompressor.
+fn is_malformed(error: &io::Error) -> bool {
+    matches!(
+        error.kind(),
+        ErrorKind::InvalidData | ErrorKind::UnexpectedEof | ErrorKind::Other
+    )
+}
@@ -0,0 +1,197 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use std::{collections::VecDeque, ops::Range};
+
+use crate::bsdiff::Match;
+
+/// A set of masked regions of a blob
+///
+/// The regions are kept sorted and non-overlapping, with adjacent regions merged.
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq, PartialOrd, Ord)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(remote = "Self")
+)]
+pub(crate) struct Mask {
+    ranges: Vec<(usize, usize)>,
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Mask {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        Self::serialize(self, serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Mask {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let ranges = Self::deserialize(deserializer)?.ranges;
+
+        Ok(Self::from_ranges(
+            ranges.into_iter().map(|(start, end)| start..end),
+        ))
+    }
+}
+
+impl Mask {
+    pub(crate) const fn new() -> Self {
+        Self { ranges: Vec::new() }
+    }
+
+    pub(crate) fn from_ranges<I>(ranges: I) -> Self
+    where
+        I: IntoIterator<Item = Range<usize>>,
+    {
+        let mut sorted: Vec<_> = ranges
+            .into_iter()
+            .filter(|range| !range.is_empty())
+            .map(|range| (range.start, range.end))
+            .collect();
+        sorted.sort_unstable();
+
+        let mut ranges: Vec<(usize, usize)> = Vec::with_capacity(sorted.len());
+        for (start, end) in sorted {
+            match ranges.last_mut() {
+                Some((_, last_end)) if start <= *last_end => *last_end = (*last_end).max(end),
+                _ => ranges.push((start, end)),
+            }
+        }
+
+        Self { ranges }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// Returns a copy of `data` with every masked byte set to 0
+    pub(crate) fn zeroed(&self, data: &[u8]) -> Vec<u8> {
+        let mut zeroed = data.to_vec();
+        for &(start, end) in &self.ranges {
+            let end = end.min(zeroed.len());
+            if start < end {
+                zeroed[start..end].fill(0);
+            }
+        }
+
+        zeroed
+    }
+
+    /// Returns the first masked region overlapping `range`, clipped to `range`
+    fn first_overlap(&self, range: Range<usize>) -> Option<Range<usize>> {
+        let i = self.ranges.partition_point(|&(_, end)| end <= range.start);
+        let &(start, end) = self.ranges.get(i)?;
+        let overlap = start.max(range.start)..end.min(range.end);
+
+        (!overlap.is_empty()).then_some(overlap)
+    }
+}
+
+/// An adapter which splits matches so that masked bytes are never stored as differences
+///
+/// Wherever the add region of a match covers a masked byte of the new blob or references a masked
+/// byte of the old blob, the match is split so that the byte is stored verbatim instead.
+pub(crate) struct MaskedMatches<'m, I>
+where
+    I: Iterator<Item = Match>,
+{
+    matches: I,
+    old_mask: &'m Mask,
+    new_mask: &'m Mask,
+    pending: VecDeque<Match>,
+}
+
+impl<'m, I> MaskedMatches<'m, I>
+where
+    I: Iterator<Item = Match>,
+{
+    pub(crate) fn new(matches: I, old_mask: &'m Mask, new_mask: &'m Mask) -> Self {
+        Self {
+            matches,
+            old_mask,
+            new_mask,
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// Returns the first run of the add region beginning at `new_pos` in the new blob and
+    /// `old_pos` in the old blob which is masked in either blob
+    ///
+    /// The run is given in new blob positions.
+    fn masked_run(&self, new_pos: usize, old_pos: usize, len: usize) -> Option<Range<usize>> {
+        let masked_at = |new_start: usize| {
+            let old_start = old_pos + (new_start - new_pos);
+            let new_hit = self.new_mask.first_overlap(new_start..new_pos + len);
+            let old_hit = self
+                .old_mask
+                .first_overlap(old_start..old_pos + len)
+                .map(|hit| hit.start - old_pos + new_pos..hit.end - old_pos + new_pos);
+
+            match (new_hit, old_hit) {
+                (Some(a), Some(b)) => Some(if a.start <= b.start { a } else { b }),
+                (a, b) => a.or(b),
+            }
+        };
+
+        // Extend the run through any masked regions which immediately follow it
+        let mut run = masked_at(new_pos)?;
+        while let Some(next) = masked_at(run.end).filter(|next| next.start == run.end) {
+            run.end = next.end;
+        }
+
+        Some(run)
+    }
+
+    fn split(&mut self, m: Match) {
+        let mut new_pos = m.add_new_pos();
+        let mut old_pos = m.add_old_pos();
+        let mut len = m.add_len();
+
+        while let Some(run) = self.masked_run(new_pos, old_pos, len) {
+            let skipped = run.end - new_pos;
+            let copy_end = if skipped == len {
+                m.copy_end()
+            } else {
+                run.end
+            };
+            self.pending
+                .push_back(Match::new(old_pos, new_pos, run.start - new_pos, copy_end));
+
+            new_pos += skipped;
+            old_pos += skipped;
+            len -= skipped;
+            if len == 0 {
+                return;
+            }
+        }
+
+        self.pending
+            .push_back(Match::new(old_pos, new_pos, len, m.copy_end()));
+    }
+}
+
+impl<'m, I> Iterator for MaskedMatches<'m, I>
+where
+    I: Iterator<Item = Match>,
+{
+    type Item = Match;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.pending.is_empty() {
+            let m = self.matches.next()?;
+            self.split(m);
+        }
+
+        self.pending.pop_front()
+    }
+}
@@ -0,0 +1,106 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use std::{
+    fs::File,
+    io::{self, BufRead, Cursor, Read, Seek, SeekFrom},
+};
+
+use memmap2::Mmap;
+
+/// A memory-mapped old blob.
+///
+/// A `MappedFile` implements [`Read`] and [`Seek`] over the contents of a memory-mapped file, so it
+/// can be passed to a [`Patcher`](crate::Patcher) in place of the [`File`] itself. Reads and seeks
+/// are then served from memory rather than issuing a `read` or `lseek` system call for every
+/// control record, which speeds up patching and removes the need to permit those system calls on
+/// the old file within a sandbox.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::fs::File;
+/// use ina::MappedFile;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let old_file = File::open("app-v1.exe")?;
+/// // SAFETY: app-v1.exe isn't modified while it's mapped
+/// let old = unsafe { MappedFile::map(&old_file)? };
+/// let patch = File::open("app-v1-to-v2.ina")?;
+/// let mut new = File::create("app-v2.exe")?;
+///
+/// ina::patch(old, patch, &mut new)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct MappedFile {
+    map: Cursor<Mmap>,
+}
+
+impl MappedFile {
+    /// Maps the contents of `file` into memory
+    ///
+    /// `file` may be closed once this function returns.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if mapping the file fails.
+    ///
+    /// # Safety
+    ///
+    /// The file must not be modified or truncated while the returned `MappedFile` exists, whether
+    /// by this process or any other. Doing so is undefined behavior and may cause the process to
+    /// crash.
+    pub unsafe fn map(file: &File) -> io::Result<Self> {
+        // SAFETY: The caller guarantees that the file isn't modified while it's mapped
+        let map = unsafe { Mmap::map(file)? };
+
+        Ok(Self {
+            map: Cursor::new(map),
+        })
+    }
+
+    /// Returns the contents of the mapped file
+    pub fn as_slice(&self) -> &[u8] {
+        self.map.get_ref()
+    }
+}
+
+impl Read for MappedFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.map.read(buf)
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        self.map.read_exact(buf)
+    }
+}
+
+impl BufRead for MappedFile {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.map.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.map.consume(amt)
+    }
+}
+
+impl Seek for MappedFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.map.seek(pos)
+    }
+
+    fn stream_position(&mut self) -> io::Result<u64> {
+        self.map.stream_position()
+    }
+}
+
+#[cfg(feature = "random-access")]
+impl crate::ReadAt for MappedFile {
+    fn read_at(&self, pos: u64, buf: &mut [u8]) -> io::Result<usize> {
+        self.as_slice().read_at(pos, buf)
+    }
+}
@@ -0,0 +1,91 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use sufsort::SuffixArray;
+
+/// An old blob prepared for diffing.
+///
+/// The diffing algorithm requires the old blob to be followed by a `0` sentinel, which isn't part
+/// of the blob itself. `OldBlob` appends the sentinel exactly once when it's created, so the diff
+/// functions never see an old blob without one and the sentinel never ends up in the blob
+/// reconstructed by patching.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> std::io::Result<()> {
+/// use ina::OldBlob;
+///
+/// let old = OldBlob::from_slice(b"Hello");
+/// let mut patch = Vec::new();
+/// ina::diff(&old, b"Hero", &mut patch)?;
+///
+/// assert_eq!(old.as_bytes(), b"Hello");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
+pub struct OldBlob {
+    data: Vec<u8>,
+}
+
+impl OldBlob {
+    /// Prepares `data` for diffing, taking ownership of it
+    ///
+    /// The sentinel is appended to `data` in place, which only reallocates it if it has no spare
+    /// capacity.
+    pub fn from_vec(mut data: Vec<u8>) -> Self {
+        data.push(0);
+        Self { data }
+    }
+
+    /// Prepares a copy of `data` for diffing
+    ///
+    /// Prefer [`OldBlob::from_vec()`] when the old blob is already owned to avoid the copy.
+    pub fn from_slice(data: &[u8]) -> Self {
+        let mut owned = Vec::with_capacity(data.len() + 1);
+        owned.extend_from_slice(data);
+        Self::from_vec(owned)
+    }
+
+    /// Returns the contents of the old blob, excluding the sentinel
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data[..self.data.len() - 1]
+    }
+
+    /// Returns the length of the old blob in bytes, excluding the sentinel
+    pub fn len(&self) -> usize {
+        self.data.len() - 1
+    }
+
+    /// Returns whether the old blob is empty
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Builds an index of the old blob for [`diff_with_index()`](crate::diff_with_index)
+    ///
+    /// Building the index is usually the most expensive part of diffing, so reuse it when diffing
+    /// several new blobs against the same old blob.
+    pub fn index(&self) -> SuffixArray<'_> {
+        crate::bsdiff::index(&self.data)
+    }
+
+    /// Returns the contents of the old blob, excluding the sentinel
+    pub fn into_vec(mut self) -> Vec<u8> {
+        self.data.pop();
+        self.data
+    }
+
+    /// Returns the contents of the old blob followed by the sentinel
+    pub(crate) fn with_sentinel(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+impl Default for OldBlob {
+    fn default() -> Self {
+        Self::from_vec(Vec::new())
+    }
+}
@@ -6,37 +6,102 @@ use std::{
     cmp,
     error::Error,
     fmt::{self, Display, Formatter},
+    fs::File,
     io::{self, BufRead, BufReader, ErrorKind, Read, Seek, SeekFrom, Write},
+    ops::Range,
+    time::Duration,
 };
 
 use byteorder::{LittleEndian, ReadBytesExt};
-use integer_encoding::VarIntReader;
-use zstd::Decoder;
+use integer_encoding::VarInt;
 
-use crate::header::{MAGIC, VERSION_MAJOR};
-
-const DEFAULT_BUF_SIZE: usize = 8192;
+use crate::{
+    AlignedBlocks, BaseDigest, BlockMap, BlockUpdates, Chunks, Codec, DiffSettings, FileMetadata,
+    FormatCapabilities, OldProvider, PatchLimits, Provenance, ProvidedOld, Requirements,
+    SeekPolicy, SeekViolation, Target, TextHints, annotation,
+    checksum::PayloadChecksum,
+    format,
+    header::{
+        MAGIC, TAG_ANNOTATION, TAG_BASE_CHECK, TAG_BLOCK_MAP, TAG_CODEC, TAG_DIFF_SETTINGS,
+        TAG_FEC, TAG_FILE_MODE, TAG_FILE_MODIFIED, TAG_NEW_LEN, TAG_PAYLOAD_CHECKSUM,
+        TAG_PROVENANCE_CREATED, TAG_PROVENANCE_NEW_ID, TAG_PROVENANCE_OLD_ID, TAG_PROVENANCE_TOOL,
+        TAG_REQUIREMENTS, TAG_TARGET_ABI, TAG_TARGET_PLATFORM, TAG_TARGET_VERSION_CODE,
+        TAG_TEXT_HINTS, VERSION_MAJOR,
+    },
+    limits,
+    payload::{self, PartialVarint, Payload, PayloadDecoder},
+    seek_policy::SeekTracker,
+};
+#[cfg(feature = "verify")]
+use crate::{BlobDigests, DIGEST_LEN, Digest, DigestAlgorithm, header::TAG_DIGESTS};
+#[cfg(feature = "fec")]
+use crate::{FecConfig, fec::FecParams};
 
 /// A patcher that reconstructs a new blob from an old blob and a patch
 ///
 /// Because this struct implements [`Read`], it can be used to apply a patch in a streaming
 /// fashion, e.g., while reading the patch from the network.
+///
+/// A `Patcher` only reports the end of the new blob by reading 0 bytes once the patch data has
+/// been decompressed completely. If the patch data is cut off, even right at the end of a control
+/// record, or the new blob is shorter than the length recorded in the patch, reading fails with
+/// [`PatchError::Truncated`]. If the patch produces more than the recorded length, reading fails
+/// with [`PatchError::TrailingData`]. Both errors are wrapped in an [`io::Error`].
+///
+/// The patch may be read without blocking, e.g., from a non-blocking socket. Reading from a
+/// `Patcher` keeps its position in the control stream when reading the patch fails, so a read which
+/// fails with [`ErrorKind::WouldBlock`] can be retried once more of the patch is available and
+/// continues exactly where it stopped. Reads which are [`ErrorKind::Interrupted`] are retried
+/// internally, and a read which has already produced part of the new blob when the patch blocks
+/// returns that part instead of failing. The old blob, however, is expected to be readable without
+/// blocking: a failed read of it is undone by seeking back, so it's safe to retry, but it's then
+/// repeated from its start.
 pub struct Patcher<'a, O, B>
 where
     O: Read + Seek,
     B: BufRead,
 {
     old: O,
-    patch: Decoder<'a, B>,
+    patch: CountingReader<PayloadDecoder<'a, B>>,
     state: PatcherState,
     buf: Vec<u8>,
+    /// The range of `buf` holding difference bytes of the current add field which have been read
+    /// from the patch but not yet applied
+    diff: Range<usize>,
+    /// The control field currently being read
+    varint: PartialVarint,
     metadata: PatchMetadata,
+    limits: PatchLimits,
+    new_len: u64,
+    /// The position in the old blob relative to its position when patching started
+    old_pos: u64,
+    /// Whether the end of the patch data has been reached and checked
+    finished: bool,
+    on_write: Option<WriteHook<'a>>,
+    seeks: Option<SeekTracker>,
+    #[cfg(feature = "verify")]
+    old_hash: Option<OldHash>,
+    /// The hasher of the new blob, if the patch records its digest
+    #[cfg(feature = "verify")]
+    new_hash: Option<Box<dyn Digest>>,
 }
 
+/// A callback notified of each range of the new blob a `Patcher` produces
+type WriteHook<'a> = Box<dyn FnMut(u64, u64) + Send + 'a>;
+
+/// The state of a `Patcher` within the control stream
+///
+/// Field lengths are kept as `u64` rather than `usize` so that records longer than the address
+/// space, which occur when patching blobs larger than 4 GiB on 32-bit targets, are applied in
+/// chunks like any other.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 enum PatcherState {
     AtNextControl,
-    Add(usize),
-    Copy(usize),
+    Add(u64),
+    CopyLen,
+    Copy(u64),
+    /// The seek ending the current control record, once it has been read
+    Seek(Option<i64>),
 }
 
 impl<'a, O, B> Patcher<'a, O, B>
@@ -54,8 +119,8 @@ where
     ///
     /// # Errors
     ///
-    /// Returns an error if an I/O error occurs while reading the patch metadata or if the patch
-    /// metadata is invalid.
+    /// Returns an error if an I/O error occurs while reading the patch metadata, if the patch
+    /// metadata is invalid, or if `old` fails the patch's base check.
     ///
     /// # Examples
     ///
@@ -74,18 +139,99 @@ where
     /// # Ok(())
     /// # }
     /// ```
-    pub fn with_buffer(old: O, mut patch: B) -> Result<Self, PatchError> {
+    pub fn with_buffer(mut old: O, mut patch: B) -> Result<Self, PatchError> {
         let metadata = read_header(&mut patch)?;
+        metadata.verify_base(&mut old)?;
+
+        let patch_decoder =
+            PayloadDecoder::new(Payload::new(patch, &metadata), &metadata)?.single_frame();
+
+        Ok(Self::from_decoder(
+            old,
+            patch_decoder,
+            metadata,
+            &PatchLimits::new(),
+        ))
+    }
+
+    /// Creates a new `Patcher` for `old` and a patch whose header has already been read which
+    /// abides by `limits`
+    fn with_metadata_and_limits(
+        metadata: PatchMetadata,
+        mut old: O,
+        patch: B,
+        limits: &PatchLimits,
+    ) -> Result<Self, PatchError> {
+        metadata.verify_base(&mut old)?;
+
+        let overhead = (limits.diff_buf_size() + limits.read_buf_size()) as u64
+            + payload::framing_buffer_len(&metadata);
+        let window_log_max = limits
+            .window_log_max(overhead)
+            .map_err(PatchError::MemoryLimitExceeded)?;
+
+        let mut payload = Payload::new(patch, &metadata);
+        // Reject the patch up front if the first frame already declares too large of a window or
+        // decompresses to more data than the new blob may be made of
+        let frame_header = payload.fill_buf()?;
+        let window_size = match metadata.codec() {
+            Codec::Zstd => limits::declared_window_size(frame_header),
+            #[cfg(feature = "brotli")]
+            Codec::Brotli => limits::declared_brotli_window_size(frame_header),
+        };
+        if let Some(window_size) = window_size {
+            limits
+                .check_window(overhead, window_size)
+                .map_err(PatchError::MemoryLimitExceeded)?;
+        }
+        if let Some(content_size) = limits::declared_content_size(frame_header)
+            && limits
+                .total_expansion_limit()
+                .is_some_and(|limit| content_size > limit)
+        {
+            return Err(PatchError::ExpansionLimitExceeded(content_size));
+        }
 
-        let patch_decoder = Decoder::with_buffer(patch)?;
+        let mut patch_decoder = PayloadDecoder::new(payload, &metadata)?.single_frame();
+        if let Some(window_log_max) = window_log_max {
+            patch_decoder.window_log_max(window_log_max)?;
+        }
+
+        Ok(Self::from_decoder(old, patch_decoder, metadata, limits))
+    }
+
+    /// Creates a new `Patcher` for `old` which reads the control stream of a patch with the given
+    /// metadata from `decoder`, once `old` has passed the patch's base check
+    fn from_decoder(
+        old: O,
+        decoder: PayloadDecoder<'a, B>,
+        metadata: PatchMetadata,
+        limits: &PatchLimits,
+    ) -> Self {
+        #[cfg(feature = "verify")]
+        let new_hash = metadata
+            .blob_digests()
+            .map(|digests| digests.algorithm().hasher());
 
-        Ok(Self {
+        Self {
             old,
-            patch: patch_decoder,
+            patch: CountingReader::new(decoder),
             state: PatcherState::AtNextControl,
-            buf: vec![0; DEFAULT_BUF_SIZE],
+            buf: vec![0; limits.diff_buf_size()],
+            diff: 0..0,
+            varint: PartialVarint::default(),
             metadata,
-        })
+            limits: *limits,
+            new_len: 0,
+            old_pos: 0,
+            finished: false,
+            on_write: None,
+            seeks: None,
+            #[cfg(feature = "verify")]
+            old_hash: None,
+            #[cfg(feature = "verify")]
+            new_hash,
+        }
     }
 
     /// Returns the metadata of the patch file associated with this `Patcher`
@@ -99,6 +245,397 @@ where
     pub fn metadata(&self) -> &PatchMetadata {
         &self.metadata
     }
+
+    /// Checks that the patch was created for `expected`, returning the `Patcher` if so.
+    ///
+    /// Every part of `expected` which is set must be recorded in the patch with the same value.
+    /// Parts of `expected` which aren't set aren't checked. Patches which don't record a part that
+    /// is set in `expected` are rejected, so a patch created without a target only satisfies an
+    /// empty expectation.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PatchError::TargetMismatch`] if the patch doesn't satisfy `expected`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::fs::File;
+    /// use ina::{Patcher, Target};
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let old = File::open("app-v1.exe")?;
+    /// let patch = File::open("app-v1-to-v2.ina")?;
+    ///
+    /// let expected = Target::new(None, Some(std::env::consts::ARCH.into()), None);
+    /// let patcher = Patcher::new(old, patch)?.expect_target(&expected)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn expect_target(self, expected: &Target) -> Result<Self, PatchError> {
+        let target = self.metadata.target.clone().unwrap_or_default();
+
+        if target.satisfies(expected) {
+            Ok(self)
+        } else {
+            Err(PatchError::TargetMismatch(self.metadata.target))
+        }
+    }
+
+    /// Converts this `Patcher` into an iterator over the new blob in owned chunks of `chunk_size`
+    /// bytes.
+    ///
+    /// This is useful for handing the new blob to consumers which take ownership of buffers, e.g.,
+    /// channels or async bridges, without implementing the [`Read`] plumbing. With the `bytes`
+    /// feature, `Chunks::into_bytes()` yields the chunks as [`Bytes`] instead.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `chunk_size` is 0.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::{io::Cursor, sync::mpsc};
+    /// use ina::Patcher;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let old = b"Hello, world!";
+    /// let new = b"Hello, patched world!";
+    /// let mut patch = Vec::new();
+    /// ina::diff(&ina::OldBlob::from_slice(old), new, &mut patch)?;
+    ///
+    /// let (sender, receiver) = mpsc::channel();
+    /// for chunk in Patcher::new(Cursor::new(old), patch.as_slice())?.chunks(8) {
+    ///     sender.send(chunk?)?;
+    /// }
+    /// drop(sender);
+    ///
+    /// assert_eq!(receiver.iter().flatten().collect::<Vec<_>>(), new);
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`Bytes`]: https://docs.rs/bytes/latest/bytes/struct.Bytes.html
+    pub fn chunks(self, chunk_size: usize) -> Chunks<'a, O, B> {
+        Chunks::new(self, chunk_size)
+    }
+
+    /// Converts this `Patcher` into a reader of the new blob in blocks of `block_size` bytes whose
+    /// start is aligned to `alignment` bytes in memory.
+    ///
+    /// This is useful for writing the new blob with direct I/O, e.g., to files opened with
+    /// `O_DIRECT` on Linux, which bypasses the page cache when reconstructing large blobs at high
+    /// throughput but requires buffers aligned to the logical block size of the storage device, or
+    /// to be safe, the page size. Blocks are read into a reused buffer, and the last block is
+    /// padded with zeros to a multiple of `alignment`, so the file needs to be truncated to the
+    /// length of the new blob after writing it. See [`AlignedBlock`] for details.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `alignment` isn't a power of two or `block_size` isn't a nonzero multiple of it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::{Cursor, Write};
+    /// use ina::Patcher;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let old = vec![1; 10_000];
+    /// let new = vec![2; 10_000];
+    /// let mut patch = Vec::new();
+    /// ina::diff(&ina::OldBlob::from_slice(&old), &new, &mut patch)?;
+    ///
+    /// let patcher = Patcher::new(Cursor::new(old), patch.as_slice())?;
+    /// let mut blocks = patcher.aligned_blocks(8192, 4096);
+    /// let mut file = Cursor::new(Vec::new());
+    /// let mut len = 0;
+    /// while let Some(block) = blocks.next_block()? {
+    ///     assert_eq!(block.padded().as_ptr() as usize % 4096, 0);
+    ///     file.write_all(block.padded())?;
+    ///     len += block.data().len();
+    /// }
+    /// file.get_mut().truncate(len);
+    ///
+    /// assert_eq!(file.into_inner(), new);
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`AlignedBlock`]: crate::AlignedBlock
+    pub fn aligned_blocks(self, block_size: usize, alignment: usize) -> AlignedBlocks<'a, O, B> {
+        AlignedBlocks::new(self, block_size, alignment)
+    }
+
+    /// Converts this `Patcher` into a reader of the blocks of the new blob which differ from the
+    /// old blob, according to the [`BlockMap`] recorded in the patch.
+    ///
+    /// This suits updaters which operate on block devices, e.g., A/B updaters which write the new
+    /// blob over a copy of the old one: writing each block at its offset and leaving the others
+    /// untouched produces the new blob. Returns `None` if the patch doesn't record a block map, so
+    /// check [`PatchMetadata::block_map()`] first to keep the `Patcher` in that case. See
+    /// [`BlockUpdates`] for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::Cursor;
+    /// use ina::{DiffConfig, OldBlob, Patcher};
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let old = vec![1; 16384];
+    /// let mut new = old.clone();
+    /// new[5000] = 2;
+    /// let options = DiffConfig::new().block_map(Some(4096)).clone();
+    /// let patch = ina::diff_to_vec(&OldBlob::from_slice(&old), &new, &options)?;
+    ///
+    /// let mut device = old.clone();
+    /// let mut updates = Patcher::new(Cursor::new(&old), patch.as_slice())?
+    ///     .block_updates()
+    ///     .unwrap();
+    /// while let Some(update) = updates.next_update()? {
+    ///     assert_eq!(update.index(), 1);
+    ///     let offset = update.offset() as usize;
+    ///     device[offset..offset + update.data().len()].copy_from_slice(update.data());
+    /// }
+    ///
+    /// assert_eq!(device, new);
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`BlockUpdates`]: crate::BlockUpdates
+    pub fn block_updates(self) -> Option<BlockUpdates<'a, O, B>> {
+        let block_map = self.metadata.block_map.clone()?;
+
+        Some(BlockUpdates::new(self, block_map))
+    }
+
+    /// Sets a callback which is notified of each range of the new blob as it's produced,
+    /// returning the `Patcher`.
+    ///
+    /// `callback` is called with the offset and length of the range after every successful read
+    /// which produces data, before the read returns. The ranges are therefore reported in order,
+    /// each starting where the previous one ended, and cover exactly the bytes returned by the
+    /// `Patcher`. This lets consumers such as signature generators or block-level replicators
+    /// track which regions of the new blob have been produced without decoding the control
+    /// stream themselves.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::{
+    ///     io::{self, Cursor},
+    ///     sync::{Arc, Mutex},
+    /// };
+    /// use ina::Patcher;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut patch = Vec::new();
+    /// ina::diff(&ina::OldBlob::from_slice(b"Hello"), b"Hero", &mut patch)?;
+    ///
+    /// let ranges = Arc::new(Mutex::new(Vec::new()));
+    /// let sink = Arc::clone(&ranges);
+    /// let mut patcher = Patcher::new(Cursor::new(b"Hello"), patch.as_slice())?
+    ///     .on_write(move |offset, len| sink.lock().unwrap().push(offset..offset + len));
+    /// io::copy(&mut patcher, &mut io::sink())?;
+    ///
+    /// assert_eq!(ranges.lock().unwrap().last().map(|range| range.end), Some(4));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn on_write<F>(mut self, callback: F) -> Self
+    where
+        F: FnMut(u64, u64) + Send + 'a,
+    {
+        self.on_write = Some(Box::new(callback));
+        self
+    }
+
+    /// Restricts the seeks the patch may perform in the old blob according to `policy`, returning
+    /// the `Patcher`.
+    ///
+    /// Every seek the patch requests is checked before it's performed, and reading fails with
+    /// [`PatchError::SeekViolation`], wrapped in an [`io::Error`], if it violates the policy. The
+    /// policy also applies to the patches following this one which are applied with
+    /// [`Patcher::next_patch()`]. See [`SeekPolicy`] for details and an example.
+    pub fn seek_policy(mut self, policy: SeekPolicy) -> Self {
+        self.seeks = SeekTracker::new(policy);
+        self
+    }
+
+    /// Hashes the old blob with `algorithm` while patching, returning the `Patcher`.
+    ///
+    /// The bytes the patch reads from the old blob are hashed as they're read. Where the patch
+    /// skips ahead in the old blob, the skipped bytes are read and hashed instead of seeking over
+    /// them, and the rest of the old blob is read once patching finishes. Once the new blob has
+    /// been produced, [`Patcher::old_digest()`] returns the digest of the old blob from its
+    /// position when this `Patcher` was created to its end. This lets callers check the integrity
+    /// of the old blob without reading it in a separate pass, which is nearly free when the patch
+    /// reads most of it anyway.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::{self, Cursor};
+    /// use ina::{DigestAlgorithm, Patcher};
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut patch = Vec::new();
+    /// ina::diff(&ina::OldBlob::from_slice(b"Hello"), b"Hero", &mut patch)?;
+    ///
+    /// let mut patcher = Patcher::new(Cursor::new(b"Hello"), patch.as_slice())?
+    ///     .hash_old(DigestAlgorithm::Blake3);
+    /// io::copy(&mut patcher, &mut io::sink())?;
+    ///
+    /// assert_eq!(patcher.old_digest()?, DigestAlgorithm::Blake3.hash(b"Hello"));
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "verify")]
+    pub fn hash_old(mut self, algorithm: DigestAlgorithm) -> Self {
+        self.old_hash = Some(OldHash::new(algorithm));
+        self
+    }
+
+    /// Returns the digest of the old blob computed while patching.
+    ///
+    /// Any part of the old blob the patch didn't read is read and hashed first. See
+    /// [`Patcher::hash_old()`] for details.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if hashing wasn't enabled with [`Patcher::hash_old()`], if this `Patcher`
+    /// hasn't finished producing its new blob, i.e., reading from it hasn't returned `Ok(0)` yet,
+    /// or if an I/O error occurs while reading the rest of the old blob.
+    #[cfg(feature = "verify")]
+    pub fn old_digest(&mut self) -> Result<[u8; DIGEST_LEN], PatchError> {
+        let Some(old_hash) = &mut self.old_hash else {
+            return Err(
+                io::Error::new(ErrorKind::InvalidInput, "the old blob isn't being hashed").into(),
+            );
+        };
+        if !self.finished {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                "the current patch hasn't been applied completely",
+            )
+            .into());
+        }
+
+        old_hash.hash_from_frontier(&mut self.old, None)?;
+        Ok(old_hash.hasher.finalize())
+    }
+
+    /// Returns whether another patch follows the patch of this `Patcher` in the patch stream.
+    ///
+    /// Patches may be concatenated into a single stream, in which case each one is applied to the
+    /// new blob produced by the previous one. See [`Patcher::next_patch()`] for details.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if this `Patcher` hasn't finished producing its new blob, i.e., reading
+    /// from it hasn't returned `Ok(0)` yet, or if an I/O error occurs while reading the patch.
+    pub fn has_next_patch(&mut self) -> Result<bool, PatchError> {
+        if !self.finished {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                "the current patch hasn't been applied completely",
+            )
+            .into());
+        }
+
+        let rest = self.patch.inner.get_mut().get_mut().fill_buf()?;
+        Ok(!rest.is_empty())
+    }
+
+    /// Continues with the patch following the patch of this `Patcher` in the patch stream,
+    /// applying it to `old`.
+    ///
+    /// Patches may be concatenated into a single stream, e.g., to deliver a hotfix alongside the
+    /// update it applies to. Each patch transforms the new blob produced by the previous one, so
+    /// `old` is normally that new blob. Once this `Patcher` has finished producing its new blob,
+    /// this method reads the header of the next patch and returns a `Patcher` for it, or returns
+    /// `None` if the stream ends. The returned `Patcher` abides by the same [`PatchLimits`] as
+    /// this one, but the callback set with [`Patcher::on_write()`] isn't carried over, and neither
+    /// is hashing of the old blob.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if this `Patcher` hasn't finished producing its new blob, if an I/O error
+    /// occurs while reading the next patch's metadata, if that metadata is invalid, or if `old`
+    /// fails the next patch's base check.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::{self, Cursor};
+    /// use ina::Patcher;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut stream = Vec::new();
+    /// ina::diff(&ina::OldBlob::from_slice(b"Hello"), b"Hero", &mut stream)?;
+    /// ina::diff(&ina::OldBlob::from_slice(b"Hero"), b"Heron", &mut stream)?;
+    ///
+    /// let mut blob = b"Hello".to_vec();
+    /// let mut patcher = Patcher::new(Cursor::new(blob.clone()), stream.as_slice())?;
+    /// loop {
+    ///     blob.clear();
+    ///     io::copy(&mut patcher, &mut blob)?;
+    ///     match patcher.next_patch(Cursor::new(blob.clone()))? {
+    ///         Some(next) => patcher = next,
+    ///         None => break,
+    ///     }
+    /// }
+    ///
+    /// assert_eq!(blob, b"Heron");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn next_patch<Q>(mut self, old: Q) -> Result<Option<Patcher<'a, Q, B>>, PatchError>
+    where
+        Q: Read + Seek,
+    {
+        if !self.has_next_patch()? {
+            return Ok(None);
+        }
+
+        let seek_policy = self.seeks.as_ref().map(SeekTracker::policy);
+        let mut patch = self.patch.inner.into_inner().into_inner();
+        let metadata = read_header(&mut patch)?;
+
+        let mut patcher = Patcher::with_metadata_and_limits(metadata, old, patch, &self.limits)?;
+        if let Some(policy) = seek_policy {
+            patcher = patcher.seek_policy(policy);
+        }
+        Ok(Some(patcher))
+    }
+}
+
+impl<'a, T, P> Patcher<'a, ProvidedOld<T>, BufReader<P>>
+where
+    T: OldProvider,
+    P: Read,
+{
+    /// Creates a new `Patcher` which reads the old blob from `provider` and applies `patch`.
+    ///
+    /// This method behaves like [`Patcher::new()`], except that the old blob is read on demand
+    /// through an [`OldProvider`] instead of a [`Read`] + [`Seek`] stream. Patches created with a
+    /// [`BaseCheck`](crate::BaseCheck) can only be applied if the provider knows the length of the
+    /// old blob.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an I/O error occurs while reading the patch metadata, if the patch
+    /// metadata is invalid, or if the old blob fails the patch's base check.
+    ///
+    /// # Examples
+    ///
+    /// See [`OldProvider`].
+    pub fn with_provider(provider: T, patch: P) -> Result<Self, PatchError> {
+        Self::new(ProvidedOld::new(provider), patch)
+    }
 }
 
 impl<'a, O, P> Patcher<'a, O, BufReader<P>>
@@ -116,8 +653,9 @@ where
     ///
     /// # Errors
     ///
-    /// Returns an error if an I/O error occurs while reading the patch metadata or if the patch
-    /// metadata is invalid.
+    /// Returns an error if an I/O error occurs while reading the patch metadata, if the patch
+    /// metadata is invalid, or if `old` fails the patch's base check, which is described by
+    /// [`BaseCheck`](crate::BaseCheck).
     ///
     /// # Examples
     ///
@@ -136,15 +674,95 @@ where
     pub fn new(old: O, mut patch: P) -> Result<Self, PatchError> {
         let metadata = read_header(&mut patch)?;
 
-        let patch_decoder = Decoder::new(patch)?;
+        Self::from_parts(metadata, old, patch)
+    }
+
+    /// Creates a new `Patcher` for `old` and a patch whose header has already been read.
+    ///
+    /// This method behaves like [`Patcher::new()`], except that instead of reading the patch
+    /// header itself, it uses `metadata` previously returned by [`read_header()`]. `patch` must be
+    /// positioned immediately after the header, as `read_header()` leaves it. This avoids reading
+    /// the header twice when it has already been read to route or validate a patch, and doesn't
+    /// require seeking back to the start of the patch.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an I/O error occurs while setting up decompression or if `old` fails the
+    /// patch's base check.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::fs::File;
+    /// use ina::Patcher;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut patch = File::open("app-v1-to-v2.ina")?;
+    /// let metadata = ina::read_header(&mut patch)?;
+    /// let old = match metadata.target().and_then(|target| target.abi()) {
+    ///     Some("arm64-v8a") => File::open("app-v1-arm64.exe")?,
+    ///     _ => File::open("app-v1.exe")?,
+    /// };
+    ///
+    /// let patcher = Patcher::from_parts(metadata, old, patch)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_parts(metadata: PatchMetadata, mut old: O, patch: P) -> Result<Self, PatchError> {
+        metadata.verify_base(&mut old)?;
+        let patch_decoder = payload::decoder(patch, &metadata)?.single_frame();
 
-        Ok(Self {
+        Ok(Self::from_decoder(
             old,
-            patch: patch_decoder,
-            state: PatcherState::AtNextControl,
-            buf: vec![0; DEFAULT_BUF_SIZE],
+            patch_decoder,
             metadata,
-        })
+            &PatchLimits::new(),
+        ))
+    }
+
+    /// Creates a new `Patcher` for `old` and `patch` which abides by `limits`.
+    ///
+    /// This method behaves like [`Patcher::new()`], except that the `Patcher`'s memory usage and
+    /// the amount of data it decompresses and outputs are bounded as described by
+    /// [`PatchLimits`]. Patches whose data declares a decompression window too large for the
+    /// memory limit or a decompressed size too large for the expansion limit are rejected
+    /// immediately, and the limits are otherwise enforced while decompressing, in which case
+    /// reading from the `Patcher` fails with an [`ErrorKind::InvalidData`] error wrapping the
+    /// [`PatchError`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an I/O error occurs while reading the patch metadata, if the patch
+    /// metadata is invalid, if `old` fails the patch's base check, or if the patch can't be applied
+    /// within `limits`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::Cursor;
+    /// use ina::{DiffConfig, OldBlob, PatchError, PatchLimits, Patcher};
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let old = vec![0; 1 << 20];
+    /// let mut new = old.clone();
+    /// new[1000] = 1;
+    ///
+    /// let mut patch = Vec::new();
+    /// let options = DiffConfig::new().window_log(Some(20)).clone();
+    /// ina::diff_with_config(&OldBlob::from_slice(&old), &new, &mut patch, &options)?;
+    ///
+    /// // The patch declares a 1 MiB window, so it can't be applied in 512 KiB of memory
+    /// let limits = PatchLimits::new().max_memory(512 << 10).clone();
+    /// let result = Patcher::with_limits(Cursor::new(&old), patch.as_slice(), &limits);
+    /// assert!(matches!(result, Err(PatchError::MemoryLimitExceeded(_))));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_limits(old: O, mut patch: P, limits: &PatchLimits) -> Result<Self, PatchError> {
+        let metadata = read_header(&mut patch)?;
+        let patch = BufReader::with_capacity(limits.read_buf_size(), patch);
+
+        Self::with_metadata_and_limits(metadata, old, patch, limits)
     }
 }
 
@@ -157,121 +775,573 @@ where
         let mut read_total = 0;
 
         while !buf.is_empty() {
-            let read = match self.state {
-                PatcherState::AtNextControl => {
-                    // Next is a control add field. Read the length of it and continue.
-                    match self.patch.read_varint() {
-                        Ok(add_len) => {
-                            self.state = PatcherState::Add(add_len);
-                            0
-                        }
-                        Err(e) => match e.kind() {
-                            ErrorKind::UnexpectedEof => break,
-                            _ => return Err(e),
-                        },
-                    }
-                }
-                PatcherState::Add(add_len) => {
-                    // We're currently reading an add field, so read `len` bytes from both the old
-                    // file and the patch file, add them together, and write the result to the
-                    // buffer.
-                    //
-                    // Because `buf` may not be large enough to hold everything we need to read, we
-                    // keep track of how many bytes we wrote and jump back to this state if needed.
-                    let max_read_len = cmp::min(cmp::min(add_len, buf.len()), self.buf.len());
-
-                    let out = &mut buf[..max_read_len];
-                    self.old.read_exact(out)?;
-
-                    // Reuse `self.buf` to hold the difference bytes read from the patch file
-                    // without allocating on every `read()`
-                    let diff = &mut self.buf[..max_read_len];
-                    self.patch.read_exact(diff)?;
-
-                    (0..max_read_len).for_each(|i| out[i] = out[i].wrapping_add(diff[i]));
-
-                    if add_len == max_read_len {
-                        // We finished reading all of the add bytes, so read the copy field len and
-                        // transition to the copy reading state
-                        let copy_len = self.patch.read_varint()?;
-                        self.state = PatcherState::Copy(copy_len);
-                    } else {
-                        // We didn't read all of the add bytes, so continue to do so on the next read
-                        // iteration
-                        self.state = PatcherState::Add(add_len - max_read_len);
-                    }
-
-                    max_read_len
-                }
-                PatcherState::Copy(copy_len) => {
-                    // We're currently reading a copy field, so write the next bytes into the buffer
-                    // directly.
-                    //
-                    // Again, `buf` may not be large enough to hold everything we need to read, so we
-                    // keep track of how many bytes we wrote and jump back to this state if needed.
-                    let max_read_len = cmp::min(copy_len, buf.len());
-
-                    let out = &mut buf[..max_read_len];
-                    self.patch.read_exact(out)?;
-
-                    if copy_len == max_read_len {
-                        // We finished reading the copy field, so perform a seek and jump to reading
-                        // the next add field
-                        let seek = self.patch.read_varint()?;
-                        self.old.seek(SeekFrom::Current(seek))?;
-
-                        self.state = PatcherState::AtNextControl;
-                    } else {
-                        self.state = PatcherState::Copy(copy_len - max_read_len);
-                    }
-
-                    max_read_len
-                }
+            let read = match self.read_step(buf) {
+                Ok(Some(read)) => read,
+                Ok(None) => break,
+                // Hand out what has been produced so far rather than dropping it, since the caller
+                // may retry once the patch has more data available
+                Err(e) if read_total > 0 && is_retryable(&e) => break,
+                Err(e) => return Err(e),
             };
 
+            #[cfg(feature = "verify")]
+            if let Some(new_hash) = &mut self.new_hash {
+                new_hash.update(&buf[..read]);
+            }
             read_total += read;
             buf = &mut buf[read..];
+            self.new_len += read as u64;
+            self.check_expansion()?;
+        }
+
+        if read_total > 0
+            && let Some(on_write) = &mut self.on_write
+        {
+            on_write(self.new_len - read_total as u64, read_total as u64);
         }
 
         Ok(read_total)
     }
 }
 
-/// An error indicating that patching a blob failed.
-///
-/// This error is returned by [`Patcher::new()`] when the patch given to it contains invalid
-/// metadata or reading the metadata fails. For more information, see that function's
-/// documentation.
-///
-/// # Examples
-///
-/// ```
-/// use std::io::Cursor;
-/// use ina::{PatchError, Patcher};
-///
-/// let mut old = Cursor::new(&[1, 2, 3, 4]);
-/// // Garbage data
-/// let patch = &[0, 0, 0, 0];
-/// let patcher = Patcher::new(old, patch.as_ref());
-///
-/// assert!(matches!(patcher, Err(PatchError::BadMagic(_))));
-/// ```
-#[derive(Debug)]
-pub enum PatchError {
-    /// An I/O error occurred
-    Io(io::Error),
-    /// The patch magic is invalid
-    BadMagic(u32),
-    /// The patch major version is unsupported
-    UnsupportedVersion(u16),
-}
-
-impl Display for PatchError {
-    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+impl<'a, O, B> Patcher<'a, O, B>
+where
+    O: Read + Seek,
+    B: BufRead,
+{
+    /// Advances through the control stream, writing at most `buf.len()` bytes of the new blob to
+    /// `buf` and returning how many, or `None` once the patch data has ended
+    ///
+    /// Every failure leaves the state such that calling this method again resumes where it failed,
+    /// which is what makes reading from a `Patcher` retryable.
+    fn read_step(&mut self, buf: &mut [u8]) -> io::Result<Option<usize>> {
+        let new_len = self.new_len;
+        let read = match self.state {
+            PatcherState::AtNextControl if self.finished => return Ok(None),
+            PatcherState::AtNextControl => {
+                // Next is a control add field unless the patch data ends. Read the length of it
+                // and continue.
+                match self
+                    .varint
+                    .read(&mut self.patch)
+                    .map_err(|e| payload::truncated(e, new_len))?
+                {
+                    // Empty fields are skipped, since there's nothing to read for them
+                    Some(0) => {
+                        self.state = PatcherState::CopyLen;
+                        0
+                    }
+                    Some(add_len) => {
+                        self.state = PatcherState::Add(add_len);
+                        0
+                    }
+                    None => {
+                        self.patch.inner.get_ref().finish()?;
+                        self.check_complete()?;
+                        #[cfg(feature = "verify")]
+                        self.check_new_digest()?;
+                        self.finished = true;
+                        return Ok(None);
+                    }
+                }
+            }
+            PatcherState::Add(add_len) => {
+                let max_read_len = chunk_len(add_len, buf.len());
+                self.check_new_len(max_read_len)?;
+                // We're currently reading an add field, so read `len` bytes from both the old file
+                // and the patch file, add them together, and write the result to the buffer.
+                //
+                // Because `buf` may not be large enough to hold everything we need to read, we keep
+                // track of how many bytes we wrote and jump back to this state if needed.
+                let max_read_len = cmp::min(max_read_len, self.buf.len());
+
+                // Reuse `self.buf` to hold the difference bytes read from the patch file without
+                // allocating on every `read()`. They're read before the old bytes and kept until
+                // applied, so that a failed read of either consumes nothing the next attempt needs.
+                if self.diff.is_empty() {
+                    let read = read_retrying(&mut self.patch, &mut self.buf[..max_read_len])
+                        .map_err(|e| payload::truncated(e, new_len))?;
+                    if read == 0 {
+                        return Err(payload::truncated(ErrorKind::UnexpectedEof.into(), new_len));
+                    }
+                    self.diff = 0..read;
+                }
+                let read = cmp::min(max_read_len, self.diff.len());
+
+                let out = &mut buf[..read];
+                self.read_old_exact(out)?;
+                self.old_pos += read as u64;
+                if let Some(seeks) = &mut self.seeks {
+                    seeks.record_read(read);
+                }
+                #[cfg(feature = "verify")]
+                if let Some(old_hash) = &mut self.old_hash {
+                    old_hash.record_read(out);
+                }
+
+                let diff = &self.buf[self.diff.start..self.diff.start + read];
+                (0..read).for_each(|i| out[i] = out[i].wrapping_add(diff[i]));
+                self.diff.start += read;
+
+                self.state = if add_len == read as u64 {
+                    // We finished reading all of the add bytes, so read the copy field len next
+                    PatcherState::CopyLen
+                } else {
+                    // We didn't read all of the add bytes, so continue to do so on the next read
+                    // iteration
+                    PatcherState::Add(add_len - read as u64)
+                };
+
+                read
+            }
+            PatcherState::CopyLen => {
+                let copy_len = self
+                    .varint
+                    .read(&mut self.patch)
+                    .and_then(|copy_len| copy_len.ok_or_else(|| ErrorKind::UnexpectedEof.into()))
+                    .map_err(|e| payload::truncated(e, new_len))?;
+                self.state = match copy_len {
+                    0 => PatcherState::Seek(None),
+                    copy_len => PatcherState::Copy(copy_len),
+                };
+
+                0
+            }
+            PatcherState::Copy(copy_len) => {
+                // We're currently reading a copy field, so write the next bytes into the buffer
+                // directly.
+                //
+                // Again, `buf` may not be large enough to hold everything we need to read, so we
+                // keep track of how many bytes we wrote and jump back to this state if needed.
+                let max_read_len = chunk_len(copy_len, buf.len());
+                self.check_new_len(max_read_len)?;
+
+                let read = read_retrying(&mut self.patch, &mut buf[..max_read_len])
+                    .map_err(|e| payload::truncated(e, new_len))?;
+                if read == 0 {
+                    return Err(payload::truncated(ErrorKind::UnexpectedEof.into(), new_len));
+                }
+
+                self.state = if copy_len == read as u64 {
+                    // We finished reading the copy field, so perform a seek next
+                    PatcherState::Seek(None)
+                } else {
+                    PatcherState::Copy(copy_len - read as u64)
+                };
+
+                read
+            }
+            PatcherState::Seek(seek) => {
+                let seek = match seek {
+                    Some(seek) => seek,
+                    None => self
+                        .varint
+                        .read(&mut self.patch)
+                        .and_then(|seek| seek.ok_or_else(|| ErrorKind::UnexpectedEof.into()))
+                        .map_err(|e| payload::truncated(e, new_len))?,
+                };
+                // Keep the seek until it has been performed in case performing it fails
+                self.state = PatcherState::Seek(Some(seek));
+
+                let old_pos = seek_target(self.old_pos, seek, &self.metadata, new_len)
+                    .map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?;
+                if let Some(seeks) = &self.seeks {
+                    seeks.check_seek(seek, new_len).map_err(|e| {
+                        io::Error::new(ErrorKind::InvalidData, PatchError::SeekViolation(e))
+                    })?;
+                }
+                self.seek_old(seek)?;
+                self.old_pos = old_pos;
+                if let Some(seeks) = &mut self.seeks {
+                    seeks.record_seek(seek);
+                }
+
+                // Jump to reading the next add field
+                self.state = PatcherState::AtNextControl;
+
+                0
+            }
+        };
+
+        Ok(Some(read))
+    }
+
+    /// Reads exactly `out.len()` bytes from the old blob
+    ///
+    /// If reading fails for any reason but the old blob ending, the bytes read so far are seeked
+    /// back over so that the read can be retried.
+    fn read_old_exact(&mut self, out: &mut [u8]) -> io::Result<()> {
+        let mut filled = 0;
+        while filled < out.len() {
+            match self.old.read(&mut out[filled..]) {
+                Ok(0) => {
+                    return Err(io::Error::new(
+                        ErrorKind::UnexpectedEof,
+                        "failed to fill whole buffer",
+                    ));
+                }
+                Ok(read) => filled += read,
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => {
+                    if filled > 0 {
+                        self.old.seek(SeekFrom::Current(-(filled as i64)))?;
+                    }
+                    return Err(e);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns an error if outputting another `len` bytes would exceed the maximum new blob length
+    /// or the length of the new blob recorded in the patch
+    fn check_new_len(&self, len: usize) -> io::Result<()> {
+        if let Some(limit) = self.limits.new_len_limit()
+            && self.new_len + len as u64 > limit
+        {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                PatchError::NewLenLimitExceeded(limit),
+            ));
+        }
+        if let Some(recorded) = self.metadata.new_len()
+            && self.new_len + len as u64 > recorded
+        {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                PatchError::TrailingData,
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Returns an error if the new blob is shorter than the length recorded in the patch once the
+    /// patch data has ended
+    fn check_complete(&self) -> io::Result<()> {
+        match self.metadata.new_len() {
+            Some(recorded) if self.new_len < recorded => Err(io::Error::new(
+                ErrorKind::UnexpectedEof,
+                PatchError::Truncated(self.new_len),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns an error if the digest of the new blob differs from the one recorded in the patch
+    /// once the patch data has ended
+    #[cfg(feature = "verify")]
+    fn check_new_digest(&self) -> io::Result<()> {
+        match (&self.new_hash, self.metadata.blob_digests()) {
+            (Some(new_hash), Some(digests)) if new_hash.finalize() != *digests.new_digest() => Err(
+                io::Error::new(ErrorKind::InvalidData, PatchError::NewMismatch),
+            ),
+            _ => Ok(()),
+        }
+    }
+
+    /// Seeks `offset` bytes from the current position in the old blob
+    fn seek_old(&mut self, offset: i64) -> io::Result<()> {
+        #[cfg(feature = "verify")]
+        if let Some(old_hash) = &mut self.old_hash {
+            return old_hash.seek(&mut self.old, offset);
+        }
+
+        self.old.seek(SeekFrom::Current(offset))?;
+        Ok(())
+    }
+
+    /// Returns an error if another patch follows the finished patch of this `Patcher`
+    fn check_no_next_patch(&mut self) -> Result<(), PatchError> {
+        if self.has_next_patch()? {
+            return Err(PatchError::TrailingData);
+        }
+
+        Ok(())
+    }
+
+    /// Returns an error if more patch data has been decompressed than the expansion limit allows
+    /// for the bytes output so far
+    fn check_expansion(&self) -> io::Result<()> {
+        let decompressed = self.patch.count();
+        match self.limits.expansion_limit(self.new_len) {
+            Some(limit) if decompressed > limit => Err(io::Error::new(
+                ErrorKind::InvalidData,
+                PatchError::ExpansionLimitExceeded(decompressed),
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Returns the position in the old blob which a seek of `seek` bytes from `old_pos` moves to, given
+/// the patch's metadata and the number of bytes of the new blob produced before the seek
+///
+/// Seeks are rejected if they move before the start of the old blob, past the end of the old blob
+/// if the patch records its length, or past the largest position seekable relative to the start of
+/// patching, which is `i64::MAX`. This keeps malformed seeks from reaching the old blob, whose
+/// implementation may not handle them gracefully.
+pub(crate) fn seek_target(
+    old_pos: u64,
+    seek: i64,
+    metadata: &PatchMetadata,
+    new_len: u64,
+) -> Result<u64, PatchError> {
+    match old_pos.checked_add_signed(seek) {
+        Some(target)
+            if target > i64::MAX as u64
+                || metadata
+                    .base_digest()
+                    .is_some_and(|digest| target > digest.old_len()) =>
+        {
+            Err(PatchError::SeekPastEnd(new_len))
+        }
+        Some(target) => Ok(target),
+        None if seek < 0 => Err(PatchError::SeekBeforeStart(new_len)),
+        None => Err(PatchError::SeekPastEnd(new_len)),
+    }
+}
+
+/// A hasher of the old blob fed by the reads of a `Patcher`
+///
+/// The old blob is hashed in order up to a frontier, the furthest position read so far. Reads
+/// behind the frontier are already hashed, and forward seeks past it read and hash the bytes they
+/// skip instead, so each byte of the old blob is hashed exactly once.
+#[cfg(feature = "verify")]
+struct OldHash {
+    hasher: Box<dyn Digest>,
+    /// The current position in the old blob relative to where patching started
+    pos: u64,
+    /// The position up to which the old blob has been hashed
+    hashed: u64,
+}
+
+#[cfg(feature = "verify")]
+impl OldHash {
+    fn new(algorithm: DigestAlgorithm) -> Self {
+        Self {
+            hasher: algorithm.hasher(),
+            pos: 0,
+            hashed: 0,
+        }
+    }
+
+    /// Hashes the part of `data`, which was just read from the current position, past the frontier
+    fn record_read(&mut self, data: &[u8]) {
+        let end = self.pos + data.len() as u64;
+        // Reads only start past the frontier if a seek went past the end of the old blob, in which
+        // case the gap is hashed later
+        if self.pos <= self.hashed && end > self.hashed {
+            let unhashed = (self.hashed - self.pos) as usize;
+            self.hasher.update(&data[unhashed..]);
+            self.hashed = end;
+        }
+        self.pos = end;
+    }
+
+    /// Seeks `offset` bytes from the current position in `old`, hashing the bytes skipped past the
+    /// frontier
+    fn seek<O>(&mut self, old: &mut O, offset: i64) -> io::Result<()>
+    where
+        O: Read + Seek,
+    {
+        let target = self.pos.checked_add_signed(offset).ok_or_else(|| {
+            io::Error::new(
+                ErrorKind::InvalidInput,
+                "seek before the start of the old blob",
+            )
+        })?;
+        if target <= self.hashed {
+            old.seek(SeekFrom::Current(offset))?;
+            self.pos = target;
+            return Ok(());
+        }
+
+        self.hash_from_frontier(old, Some(target - self.hashed))?;
+        // The old blob may end before the target, in which case reading from it fails later
+        if self.pos < target {
+            old.seek(SeekFrom::Current((target - self.pos) as i64))?;
+            self.pos = target;
+        }
+
+        Ok(())
+    }
+
+    /// Moves to the frontier and hashes up to `len` bytes beyond it, or the rest of `old` if `len`
+    /// is `None`
+    fn hash_from_frontier<O>(&mut self, old: &mut O, len: Option<u64>) -> io::Result<()>
+    where
+        O: Read + Seek,
+    {
+        if self.pos != self.hashed {
+            old.seek(SeekFrom::Current(self.hashed as i64 - self.pos as i64))?;
+        }
+        let hashed = match len {
+            Some(len) => io::copy(&mut old.take(len), &mut self.hasher)?,
+            None => io::copy(old, &mut self.hasher)?,
+        };
+        self.hashed += hashed;
+        self.pos = self.hashed;
+
+        Ok(())
+    }
+}
+
+/// Returns how many bytes of a field with `remaining` bytes left fit in a buffer of `buf_len` bytes
+fn chunk_len(remaining: u64, buf_len: usize) -> usize {
+    usize::try_from(remaining).map_or(buf_len, |remaining| cmp::min(remaining, buf_len))
+}
+
+/// Reads from `reader` into `buf`, retrying if the read is interrupted
+fn read_retrying<R>(reader: &mut R, buf: &mut [u8]) -> io::Result<usize>
+where
+    R: Read,
+{
+    loop {
+        match reader.read(buf) {
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            result => return result,
+        }
+    }
+}
+
+/// Returns whether `error` signals that a read may succeed if retried later rather than a failure
+fn is_retryable(error: &io::Error) -> bool {
+    matches!(error.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut)
+}
+
+/// A reader which counts the bytes read from it
+pub(crate) struct CountingReader<R> {
+    inner: R,
+    count: u64,
+}
+
+impl<R> CountingReader<R> {
+    fn new(inner: R) -> Self {
+        Self { inner, count: 0 }
+    }
+
+    /// Returns the number of bytes read so far
+    pub(crate) fn count(&self) -> u64 {
+        self.count
+    }
+}
+
+impl<R> Read for CountingReader<R>
+where
+    R: Read,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.count += read as u64;
+
+        Ok(read)
+    }
+}
+
+/// An error indicating that patching a blob failed.
+///
+/// This error is returned by [`Patcher::new()`] when the patch given to it contains invalid
+/// metadata or reading the metadata fails. For more information, see that function's
+/// documentation.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::Cursor;
+/// use ina::{PatchError, Patcher};
+///
+/// let mut old = Cursor::new(&[1, 2, 3, 4]);
+/// // Garbage data
+/// let patch = &[0, 0, 0, 0];
+/// let patcher = Patcher::new(old, patch.as_ref());
+///
+/// assert!(matches!(patcher, Err(PatchError::BadMagic(_))));
+/// ```
+#[derive(Debug)]
+pub enum PatchError {
+    /// An I/O error occurred
+    Io(io::Error),
+    /// The patch magic is invalid. Contains the first bytes read, up to 16, which help identify
+    /// what was read instead of a patch, e.g., an HTML error page served by a CDN.
+    BadMagic(Vec<u8>),
+    /// The patch major version is unsupported
+    UnsupportedVersion(u16),
+    /// Reading a part of the header failed, e.g., because the patch is truncated
+    Header(HeaderError),
+    /// A header field with the given tag is malformed
+    InvalidHeaderField(u64),
+    /// Applying the patch would require approximately the given number of bytes of memory, which
+    /// exceeds the configured limit
+    MemoryLimitExceeded(u64),
+    /// The patch data decompresses to at least the given number of bytes, which exceeds the
+    /// configured expansion limit
+    ExpansionLimitExceeded(u64),
+    /// The new blob would be longer than the given configured maximum length
+    NewLenLimitExceeded(u64),
+    /// The patch was created for a different target than expected. Contains the target recorded
+    /// in the patch, if any.
+    TargetMismatch(Option<Target>),
+    /// The old blob differs from the one the patch was created against according to the patch's
+    /// [base check](crate::BaseCheck). Contains the checksummed region of the old blob which
+    /// differs, or `None` if the old blob's length differs.
+    OldMismatch(Option<Range<u64>>),
+    /// The patch ends before the new blob is complete, either within a control record or before
+    /// the length of the new blob recorded in the patch is reached. Contains the number of bytes of
+    /// the new blob produced before the patch ended.
+    Truncated(u64),
+    /// Data follows the end of the patch, either control records producing more than the length of
+    /// the new blob recorded in the patch or, where a single patch is expected, another patch
+    TrailingData,
+    /// Reading the patch made no progress for the given timeout of a
+    /// [`WatchdogReader`](crate::WatchdogReader)
+    Stalled(Duration),
+    /// Writing the new blob to one of several outputs failed
+    Sink(SinkError),
+    /// The patch requested a seek in the old blob which the `Patcher`'s
+    /// [`SeekPolicy`](crate::SeekPolicy) forbids
+    SeekViolation(SeekViolation),
+    /// The patch requested a seek before the start of the old blob. Contains the number of bytes
+    /// of the new blob produced before the seek.
+    SeekBeforeStart(u64),
+    /// The patch requested a seek past the end of the old blob, as recorded in the patch's
+    /// [base check](crate::BaseCheck), or past the largest seekable position. Contains the number
+    /// of bytes of the new blob produced before the seek.
+    SeekPastEnd(u64),
+    /// The patch requires a feature this patcher doesn't support, such as a codec or forward error
+    /// correction, or a newer minor version of the format. Contains the name of the requirement,
+    /// e.g., `codec=brotli`. See [`Requirements`](crate::Requirements) for details.
+    UnsupportedFeature(String),
+    /// The new blob differs from the one the patch was created for according to the digest
+    /// recorded in the patch. This is only detected once the whole new blob has been produced.
+    NewMismatch,
+}
+
+impl Display for PatchError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         match self {
             PatchError::Io(e) => write!(f, "I/O error: {e}"),
-            PatchError::BadMagic(magic) => {
-                write!(f, "bad magic: expected {MAGIC:x}, found {magic:x}")
+            PatchError::BadMagic(found) => {
+                write!(
+                    f,
+                    "bad magic: expected {}, found {}",
+                    hex(&MAGIC.to_le_bytes()),
+                    hex(found),
+                )?;
+                if found
+                    .iter()
+                    .all(|b| b.is_ascii_graphic() || b.is_ascii_whitespace())
+                {
+                    write!(
+                        f,
+                        " ({:?}), which looks like text, such as an HTML error page, rather than \
+                        a patch",
+                        String::from_utf8_lossy(found),
+                    )?;
+                }
+
+                Ok(())
             }
             PatchError::UnsupportedVersion(version) => {
                 write!(
@@ -280,6 +1350,89 @@ impl Display for PatchError {
                     supported versions are {VERSION_MAJOR}.x",
                 )
             }
+            PatchError::Header(e) => write!(f, "invalid header: {e}"),
+            PatchError::InvalidHeaderField(tag) => write!(f, "invalid header field with tag {tag}"),
+            PatchError::MemoryLimitExceeded(required) => {
+                write!(
+                    f,
+                    "memory limit exceeded: patch requires approximately {required} bytes",
+                )
+            }
+            PatchError::ExpansionLimitExceeded(decompressed) => {
+                write!(
+                    f,
+                    "expansion limit exceeded: patch data decompresses to at least \
+                    {decompressed} bytes",
+                )
+            }
+            PatchError::NewLenLimitExceeded(limit) => {
+                write!(
+                    f,
+                    "new length limit exceeded: new blob is longer than {limit} bytes",
+                )
+            }
+            PatchError::TargetMismatch(Some(target)) => {
+                write!(f, "target mismatch: patch was created for {target}")
+            }
+            PatchError::TargetMismatch(None) => {
+                write!(f, "target mismatch: patch doesn't record a target")
+            }
+            PatchError::OldMismatch(Some(region)) => {
+                write!(
+                    f,
+                    "old blob mismatch: bytes {}..{} differ from the old blob the patch was \
+                    created against",
+                    region.start, region.end,
+                )
+            }
+            PatchError::OldMismatch(None) => {
+                write!(
+                    f,
+                    "old blob mismatch: length differs from the old blob the patch was created \
+                    against",
+                )
+            }
+            PatchError::Truncated(new_len) => {
+                write!(
+                    f,
+                    "truncated patch: patch ends after {new_len} bytes of the new blob",
+                )
+            }
+            PatchError::TrailingData => {
+                write!(f, "trailing data: data follows the end of the patch")
+            }
+            PatchError::Stalled(timeout) => {
+                write!(f, "stalled: no patch data received for {timeout:?}")
+            }
+            PatchError::Sink(e) => write!(f, "output error: {e}"),
+            PatchError::SeekViolation(e) => write!(f, "seek policy violated: {e}"),
+            PatchError::SeekBeforeStart(new_len) => {
+                write!(
+                    f,
+                    "invalid seek: patch seeks before the start of the old blob after {new_len} \
+                    bytes of the new blob",
+                )
+            }
+            PatchError::SeekPastEnd(new_len) => {
+                write!(
+                    f,
+                    "invalid seek: patch seeks past the end of the old blob after {new_len} bytes \
+                    of the new blob",
+                )
+            }
+            PatchError::NewMismatch => {
+                write!(
+                    f,
+                    "new blob mismatch: new blob differs from the one the patch was created for",
+                )
+            }
+            PatchError::UnsupportedFeature(name) => {
+                write!(
+                    f,
+                    "unsupported feature: patch requires {name}, which this patcher doesn't \
+                     support",
+                )
+            }
         }
     }
 }
@@ -288,6 +1441,8 @@ impl Error for PatchError {
     fn source(&self) -> Option<&(dyn Error + 'static)> {
         match self {
             PatchError::Io(e) => e.source(),
+            PatchError::Header(e) => e.source(),
+            PatchError::Sink(e) => e.source(),
             _ => None,
         }
     }
@@ -305,23 +1460,495 @@ impl From<TryFromValueError> for PatchError {
     }
 }
 
+/// Formats `bytes` as lowercase hexadecimal
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// A part of a patch header.
+///
+/// See [`HeaderError`] for details.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
+#[non_exhaustive]
+pub enum HeaderField {
+    /// The magic number identifying the patch format
+    Magic,
+    /// The major version of the patch format
+    MajorVersion,
+    /// The minor version of the patch format
+    MinorVersion,
+    /// The length of the extension area, which gives the offset of the data section
+    DataOffset,
+    /// The extension area holding the tagged header fields
+    Extension,
+    /// A tagged field in the extension area
+    ExtensionField,
+}
+
+impl HeaderField {
+    /// Returns the layout of this part of the header in the notation described in the
+    /// [`format`] module documentation
+    pub fn layout(self) -> &'static str {
+        match self {
+            Self::Magic => "magic: u32le",
+            Self::MajorVersion => "major: u16le",
+            Self::MinorVersion => "minor: u16le",
+            Self::DataOffset => "extension_len: varint",
+            Self::Extension => "*(tag: varint, len: varint, value: bytes[len])",
+            Self::ExtensionField => "tag: varint, len: varint, value: bytes[len]",
+        }
+    }
+}
+
+impl Display for HeaderField {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let name = match self {
+            Self::Magic => "magic",
+            Self::MajorVersion => "major version",
+            Self::MinorVersion => "minor version",
+            Self::DataOffset => "data offset",
+            Self::Extension => "extension area",
+            Self::ExtensionField => "extension field",
+        };
+
+        f.write_str(name)
+    }
+}
+
+/// An error indicating that reading a part of a patch header failed.
+///
+/// This error records which part of the header was being read and where it starts, which helps
+/// diagnose truncated or corrupted patches. Offsets are counted from the start of the patch, so
+/// they're relative to the start of the current patch when patches are concatenated or stored in
+/// a bundle.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use ina::{HeaderField, PatchError};
+///
+/// let mut patch = Vec::new();
+/// ina::diff(&ina::OldBlob::from_slice(b"Hello"), b"Hero", &mut patch)?;
+///
+/// // The patch is cut off in the middle of its minor version
+/// match ina::read_header(&mut &patch[..7]) {
+///     Err(PatchError::Header(e)) => {
+///         assert_eq!(e.field(), HeaderField::MinorVersion);
+///         assert_eq!(e.offset(), 6);
+///     }
+///     _ => panic!("expected a header error"),
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct HeaderError {
+    field: HeaderField,
+    offset: u64,
+    error: io::Error,
+}
+
+impl HeaderError {
+    /// Returns the part of the header which couldn't be read
+    pub fn field(&self) -> HeaderField {
+        self.field
+    }
+
+    /// Returns the offset in bytes of the part of the header which couldn't be read from the
+    /// start of the patch
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Returns the underlying I/O error
+    pub fn io_error(&self) -> &io::Error {
+        &self.error
+    }
+}
+
+impl Display for HeaderError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(
+            f,
+            "failed to read {} at offset {}: {}",
+            self.field, self.offset, self.error,
+        )
+    }
+}
+
+impl Error for HeaderError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.error.source()
+    }
+}
+
+/// An error indicating that writing the new blob to one of several outputs failed.
+///
+/// This error is returned by [`patch_multi()`] and records which output failed, so that, e.g., a
+/// failed upload can be told apart from a full disk.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use std::io::{self, Cursor, Write};
+/// use ina::PatchError;
+///
+/// let mut patch = Vec::new();
+/// ina::diff(&ina::OldBlob::from_slice(b"Hello"), b"Hero", &mut patch)?;
+///
+/// let mut new = Vec::new();
+/// let mut full = [0; 2];
+/// let sinks: &mut [&mut dyn Write] = &mut [&mut new, &mut full.as_mut_slice()];
+///
+/// match ina::patch_multi(Cursor::new(b"Hello"), patch.as_slice(), sinks) {
+///     Err(PatchError::Sink(e)) => {
+///         assert_eq!(e.index(), 1);
+///         assert_eq!(e.io_error().kind(), io::ErrorKind::WriteZero);
+///     }
+///     _ => panic!("expected an output error"),
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct SinkError {
+    index: usize,
+    offset: u64,
+    error: io::Error,
+}
+
+impl SinkError {
+    /// Returns the index of the output which failed in the outputs given to [`patch_multi()`]
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Returns the offset in bytes from the start of the new blob of the write which failed
+    ///
+    /// All bytes before this offset were written to every output. The failed output may have
+    /// received some bytes past it.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Returns the underlying I/O error
+    pub fn io_error(&self) -> &io::Error {
+        &self.error
+    }
+}
+
+impl Display for SinkError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(
+            f,
+            "failed to write to output {} at offset {}: {}",
+            self.index, self.offset, self.error,
+        )
+    }
+}
+
+impl Error for SinkError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.error.source()
+    }
+}
+
+/// Reads a part of a patch header with `read`, attributing any error to `field` at the current
+/// offset of `patch`
+pub(crate) fn read_header_field<R, T>(
+    patch: &mut CountingReader<R>,
+    field: HeaderField,
+    read: impl FnOnce(&mut CountingReader<R>) -> io::Result<T>,
+) -> Result<T, PatchError>
+where
+    R: Read,
+{
+    let offset = patch.count();
+    read(patch).map_err(|error| header_error(field, offset, error))
+}
+
+/// Returns an error for a part of a patch header at `offset` which couldn't be read
+pub(crate) fn header_error(field: HeaderField, offset: u64, error: io::Error) -> PatchError {
+    PatchError::Header(HeaderError {
+        field,
+        offset,
+        error,
+    })
+}
+
+/// Returns an error for the invalid magic number `magic`, reading a few more bytes of `rest` to
+/// help identify what was read instead
+pub(crate) fn bad_magic<R>(magic: u32, rest: R) -> PatchError
+where
+    R: Read,
+{
+    let mut found = magic.to_le_bytes().to_vec();
+    // The bytes are only informational, so failing to read them isn't an error
+    let _ = rest
+        .take((BAD_MAGIC_PREFIX_LEN - found.len()) as u64)
+        .read_to_end(&mut found);
+
+    PatchError::BadMagic(found)
+}
+
+/// The maximum number of bytes recorded in [`PatchError::BadMagic`]
+const BAD_MAGIC_PREFIX_LEN: usize = 16;
+
 /// Metadata of a patch file.
 ///
 /// This struct represents information about a patch file present in its header such the patch
 /// format version.
-#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
+#[derive(Clone, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PatchMetadata {
     version: PatchVersion,
+    new_len: Option<u64>,
+    file_metadata: Option<FileMetadata>,
+    target: Option<Target>,
+    provenance: Option<Provenance>,
+    payload_checksum: Option<PayloadChecksum>,
+    text_hints: Option<TextHints>,
+    base_digest: Option<BaseDigest>,
+    diff_settings: Option<DiffSettings>,
+    annotation: Option<String>,
+    block_map: Option<BlockMap>,
+    #[cfg(feature = "fec")]
+    fec: Option<FecParams>,
+    codec: Codec,
+    requirements: Option<Requirements>,
+    #[cfg(feature = "verify")]
+    blob_digests: Option<BlobDigests>,
+    capabilities: FormatCapabilities,
 }
 
-impl PatchMetadata {
-    fn new(version: PatchVersion) -> Self {
-        Self { version }
+impl PatchMetadata {
+    pub(crate) fn new(version: PatchVersion) -> Self {
+        Self {
+            version,
+            new_len: None,
+            file_metadata: None,
+            target: None,
+            provenance: None,
+            payload_checksum: None,
+            text_hints: None,
+            base_digest: None,
+            diff_settings: None,
+            annotation: None,
+            block_map: None,
+            #[cfg(feature = "fec")]
+            fec: None,
+            codec: Codec::Zstd,
+            requirements: None,
+            #[cfg(feature = "verify")]
+            blob_digests: None,
+            capabilities: FormatCapabilities::default(),
+        }
+    }
+
+    /// Returns the version of the patch file format.
+    pub fn version(&self) -> PatchVersion {
+        self.version
+    }
+
+    /// Returns the optional features of the patch file format the patch uses.
+    ///
+    /// See [`FormatCapabilities`] for details.
+    pub fn format_capabilities(&self) -> &FormatCapabilities {
+        &self.capabilities
+    }
+
+    /// Returns the length of the new blob recorded in the patch, if any.
+    ///
+    /// The length is only recorded if requested when creating the patch. It isn't verified until
+    /// the patch is applied, so it shouldn't be trusted before then.
+    pub fn new_len(&self) -> Option<u64> {
+        self.new_len
+    }
+
+    /// Returns the metadata of the new file recorded in the patch, if any.
+    pub fn file_metadata(&self) -> Option<FileMetadata> {
+        self.file_metadata
+    }
+
+    /// Returns the target of the new file recorded in the patch, if any.
+    pub fn target(&self) -> Option<&Target> {
+        self.target.as_ref()
+    }
+
+    /// Returns the provenance recorded in the patch, if any.
+    pub fn provenance(&self) -> Option<&Provenance> {
+        self.provenance.as_ref()
+    }
+
+    /// Returns whether the patch records a checksum of its compressed data.
+    ///
+    /// If it does, the data is checked against the checksum as it's read, so corruption of the
+    /// patch, e.g., in storage or in transit, is reported as an error instead of causing patching
+    /// to produce an incorrect new blob. See [`DiffConfig::payload_checksum()`] for details.
+    ///
+    /// [`DiffConfig::payload_checksum()`]: crate::DiffConfig::payload_checksum
+    pub fn has_payload_checksum(&self) -> bool {
+        self.payload_checksum.is_some()
+    }
+
+    pub(crate) fn payload_checksum(&self) -> Option<PayloadChecksum> {
+        self.payload_checksum
+    }
+
+    /// Returns the line-oriented summary of the changes made by the patch, if any.
+    ///
+    /// Only patches created in text mode record hints. See [`TextHints`] for details.
+    pub fn text_hints(&self) -> Option<&TextHints> {
+        self.text_hints.as_ref()
+    }
+
+    /// Returns the length and region checksums of the old blob recorded in the patch, if any.
+    ///
+    /// Only patches created with a [`BaseCheck`](crate::BaseCheck) record them. They're verified
+    /// when a [`Patcher`] is created.
+    pub fn base_digest(&self) -> Option<&BaseDigest> {
+        self.base_digest.as_ref()
+    }
+
+    /// Returns the settings the patch was created with, if recorded.
+    ///
+    /// See [`DiffSettings`] for details.
+    pub fn diff_settings(&self) -> Option<&DiffSettings> {
+        self.diff_settings.as_ref()
+    }
+
+    /// Returns the free-form annotation recorded in the patch, such as release notes, if any.
+    pub fn annotation(&self) -> Option<&str> {
+        self.annotation.as_deref()
+    }
+
+    /// Returns the blocks of the new blob which differ from the old blob, if recorded.
+    ///
+    /// See [`BlockMap`] for details.
+    pub fn block_map(&self) -> Option<&BlockMap> {
+        self.block_map.as_ref()
+    }
+
+    /// Returns the digests of the old and new blobs, if recorded.
+    ///
+    /// See [`BlobDigests`] for details.
+    #[cfg(feature = "verify")]
+    pub fn blob_digests(&self) -> Option<&BlobDigests> {
+        self.blob_digests.as_ref()
+    }
+
+    /// Checks `old` against the recorded base digest and digest of the old blob, if any
+    pub(crate) fn verify_base<O>(&self, old: &mut O) -> Result<(), PatchError>
+    where
+        O: Read + Seek,
+    {
+        if let Some(digest) = &self.base_digest {
+            digest.verify(old)?;
+        }
+        #[cfg(feature = "verify")]
+        if let Some(digests) = &self.blob_digests {
+            digests.verify_old(old)?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the forward error correction parameters of the patch, if any.
+    #[cfg(feature = "fec")]
+    pub fn fec(&self) -> Option<FecConfig> {
+        self.fec.map(|params| params.config())
+    }
+
+    #[cfg(feature = "fec")]
+    pub(crate) fn fec_params(&self) -> Option<FecParams> {
+        self.fec
     }
 
-    /// Returns the version of the patch file format.
-    pub fn version(&self) -> PatchVersion {
-        self.version
+    /// Returns the codec the patch data is compressed with.
+    pub fn codec(&self) -> Codec {
+        self.codec
+    }
+
+    /// Returns what a patcher must support to apply the patch, if recorded.
+    ///
+    /// See [`Requirements`] for details.
+    pub fn requirements(&self) -> Option<&Requirements> {
+        self.requirements.as_ref()
+    }
+
+    pub(crate) fn parse_field(&mut self, tag: u64, value: &[u8]) -> Result<(), PatchError> {
+        self.capabilities.insert_tag(tag);
+
+        let parsed = match tag {
+            TAG_NEW_LEN => u64::decode_var(value).map(|(new_len, _)| self.new_len = Some(new_len)),
+            TAG_FILE_MODE => self
+                .file_metadata
+                .get_or_insert_default()
+                .decode_mode(value),
+            TAG_FILE_MODIFIED => self
+                .file_metadata
+                .get_or_insert_default()
+                .decode_modified(value),
+            TAG_TARGET_PLATFORM => self.target.get_or_insert_default().decode_platform(value),
+            TAG_TARGET_ABI => self.target.get_or_insert_default().decode_abi(value),
+            TAG_TARGET_VERSION_CODE => self
+                .target
+                .get_or_insert_default()
+                .decode_version_code(value),
+            TAG_PROVENANCE_TOOL => self.provenance.get_or_insert_default().decode_tool(value),
+            TAG_PROVENANCE_CREATED => self
+                .provenance
+                .get_or_insert_default()
+                .decode_created(value),
+            TAG_PROVENANCE_OLD_ID => self.provenance.get_or_insert_default().decode_old_id(value),
+            TAG_PROVENANCE_NEW_ID => self.provenance.get_or_insert_default().decode_new_id(value),
+            TAG_PAYLOAD_CHECKSUM => PayloadChecksum::decode_field(value)
+                .map(|checksum| self.payload_checksum = Some(checksum)),
+            TAG_TEXT_HINTS => {
+                TextHints::decode_field(value).map(|hints| self.text_hints = Some(hints))
+            }
+            TAG_BASE_CHECK => {
+                BaseDigest::decode_field(value).map(|digest| self.base_digest = Some(digest))
+            }
+            TAG_DIFF_SETTINGS => DiffSettings::decode_field(value)
+                .map(|settings| self.diff_settings = Some(settings)),
+            #[cfg(feature = "fec")]
+            TAG_FEC => FecParams::decode_field(value).map(|params| self.fec = Some(params)),
+            // The data section can't be read without support for forward error correction
+            #[cfg(not(feature = "fec"))]
+            TAG_FEC => {
+                return Err(PatchError::UnsupportedFeature(
+                    format::FormatFeature::Fec.to_string(),
+                ));
+            }
+            // The data section can't be read with a codec this build doesn't support
+            TAG_CODEC => Codec::decode_field(value).map(|codec| self.codec = codec),
+            TAG_ANNOTATION => {
+                annotation::decode_field(value).map(|annotation| self.annotation = Some(annotation))
+            }
+            TAG_BLOCK_MAP => {
+                BlockMap::decode_field(value).map(|block_map| self.block_map = Some(block_map))
+            }
+            #[cfg(feature = "verify")]
+            TAG_DIGESTS => {
+                self.blob_digests = Some(BlobDigests::decode_field(value)?);
+                Some(())
+            }
+            TAG_REQUIREMENTS => match Requirements::decode_field(value) {
+                Some(requirements) => {
+                    requirements.check()?;
+                    self.requirements = Some(requirements);
+                    Some(())
+                }
+                None => None,
+            },
+            // Ignore fields we don't understand
+            _ => Some(()),
+        };
+
+        parsed.ok_or(PatchError::InvalidHeaderField(tag))
     }
 }
 
@@ -329,13 +1956,18 @@ impl PatchMetadata {
 ///
 /// This structure represents an acceptable patch format version which we know how to parse.
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(into = "VersionFields", try_from = "VersionFields")
+)]
 pub struct PatchVersion {
     major: MajorVersion,
     minor: u16,
 }
 
 impl PatchVersion {
-    fn from_values(major: u16, minor: u16) -> Result<Self, TryFromValueError> {
+    pub(crate) fn from_values(major: u16, minor: u16) -> Result<Self, TryFromValueError> {
         let major = major.try_into()?;
 
         Ok(Self { major, minor })
@@ -346,14 +1978,45 @@ impl PatchVersion {
         self.major.into()
     }
 
+    pub(crate) fn major_version(&self) -> MajorVersion {
+        self.major
+    }
+
     /// Returns the minor version of the patch format
     pub fn minor(&self) -> u16 {
         self.minor
     }
 }
 
+/// The (de)serialized form of a `PatchVersion`
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct VersionFields {
+    major: u16,
+    minor: u16,
+}
+
+#[cfg(feature = "serde")]
+impl From<PatchVersion> for VersionFields {
+    fn from(version: PatchVersion) -> Self {
+        Self {
+            major: version.major(),
+            minor: version.minor(),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<VersionFields> for PatchVersion {
+    type Error = PatchError;
+
+    fn try_from(fields: VersionFields) -> Result<Self, Self::Error> {
+        Ok(Self::from_values(fields.major, fields.minor)?)
+    }
+}
+
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
-enum MajorVersion {
+pub(crate) enum MajorVersion {
     One,
 }
 
@@ -377,7 +2040,7 @@ impl From<MajorVersion> for u16 {
 }
 
 #[derive(Debug)]
-struct TryFromValueError(u16);
+pub(crate) struct TryFromValueError(u16);
 
 impl Display for TryFromValueError {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
@@ -394,8 +2057,8 @@ impl Error for TryFromValueError {
 /// Reads the header of `patch` to extract its metadata.
 ///
 /// This function reads the full header of `patch`, including fields the current parser doesn't
-/// understand. This behavior means that the `patch` reader will always point to the beginning of
-/// the patch data section after successful completion of this function.
+/// understand, which are ignored. This behavior means that the `patch` reader will always point to
+/// the beginning of the patch data section after successful completion of this function.
 ///
 /// # Errors
 ///
@@ -405,33 +2068,36 @@ pub fn read_header<P>(mut patch: &mut P) -> Result<PatchMetadata, PatchError>
 where
     P: Read + ?Sized,
 {
-    let magic = patch.read_u32::<LittleEndian>()?;
+    let mut patch = CountingReader::new(&mut patch as &mut dyn Read);
+    let magic = read_header_field(&mut patch, HeaderField::Magic, |patch| {
+        patch.read_u32::<LittleEndian>()
+    })?;
     if magic != MAGIC {
-        return Err(PatchError::BadMagic(magic));
+        return Err(bad_magic(magic, &mut patch));
     }
 
-    let version_major = patch.read_u16::<LittleEndian>()?;
-    let version_minor = patch.read_u16::<LittleEndian>()?;
+    let version_major = read_header_field(&mut patch, HeaderField::MajorVersion, |patch| {
+        patch.read_u16::<LittleEndian>()
+    })?;
+    let version_minor = read_header_field(&mut patch, HeaderField::MinorVersion, |patch| {
+        patch.read_u16::<LittleEndian>()
+    })?;
     let patch_version = PatchVersion::from_values(version_major, version_minor)?;
 
-    let data_offset = patch.read_varint()?;
-
-    // Discard the portion of the patch we don't understand
-    io::copy(&mut patch.take(data_offset), &mut io::sink())?;
-
-    Ok(PatchMetadata::new(patch_version))
+    format::reader(patch_version).read_metadata(patch_version, &mut patch)
 }
 
 /// Reconstructs a new blob from an old blob and a patch
 ///
 ///
 /// This is a convenience method for creating a [`Patcher`] and reading it to completion. If
-/// successful, returns the number of bytes written to `new`.
+/// successful, returns the number of bytes written to `new`. `patch` must contain a single patch;
+/// patches concatenated into one stream are applied with [`Patcher::next_patch()`] instead.
 ///
 /// # Errors
 ///
 /// Returns an error if an I/O occurs while reading the patch metadata of if the patch metadata is
-/// invalid.
+/// invalid, or if more data follows the patch.
 ///
 /// # Examples
 ///
@@ -455,6 +2121,596 @@ where
     W: Write + ?Sized,
 {
     let mut patcher = Patcher::new(old, patch)?;
+    let written = io::copy(&mut patcher, new)?;
+    patcher.check_no_next_patch()?;
+
+    Ok(written)
+}
+
+/// Reconstructs a new blob in memory from an old blob and a patch in memory
+///
+/// This function behaves like [`patch()`], except that the new blob is returned. If the patch
+/// records the length of the new blob, the returned vector is allocated with exactly that capacity
+/// up front rather than growing as the new blob is written. Since the recorded length comes from
+/// the patch, the capacity allocated up front is capped at twice the combined length of `old` and
+/// `patch`, and an allocation that fails is not an error. The vector then grows as usual.
+///
+/// # Errors
+///
+/// Returns an error if the patch metadata is invalid or the patch can't be applied to `old`, or if
+/// more data follows the patch.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let mut patch = Vec::new();
+/// ina::diff(&ina::OldBlob::from_slice(b"Hello"), b"Hero", &mut patch)?;
+///
+/// assert_eq!(ina::patch_to_vec(b"Hello", &patch)?, b"Hero");
+///
+/// # Ok(())
+/// # }
+/// ```
+pub fn patch_to_vec(old: &[u8], patch: &[u8]) -> Result<Vec<u8>, PatchError> {
+    let mut patcher = Patcher::new(io::Cursor::new(old), patch)?;
+    let new_len = patcher.metadata().new_len();
+
+    let mut new = Vec::new();
+    if let Some(new_len) = new_len {
+        let inputs_len = old.len() as u64 + patch.len() as u64;
+        let capacity = preallocation_len(new_len, inputs_len);
+        let _ = new.try_reserve_exact(usize::try_from(capacity).unwrap_or(usize::MAX));
+    }
+    io::copy(&mut patcher, &mut new)?;
+    patcher.check_no_next_patch()?;
+
+    Ok(new)
+}
+
+/// The maximum ratio of the space allocated up front for a new blob to the combined length of the
+/// old blob and patch it's reconstructed from
+const MAX_PREALLOCATION_RATIO: u64 = 2;
+
+/// Returns how many bytes to allocate up front for a new blob whose length is recorded as `new_len`
+/// in a patch, given the combined length of the old blob and patch
+///
+/// The recorded length can't be trusted, so it's capped to keep a malicious patch from making the
+/// caller allocate far more than the inputs take up. A genuine new blob only exceeds the cap if
+/// most of it repeats the old blob or compresses extremely well.
+fn preallocation_len(new_len: u64, inputs_len: u64) -> u64 {
+    new_len.min(inputs_len.saturating_mul(MAX_PREALLOCATION_RATIO))
+}
+
+/// Reconstructs a new blob from an old blob and a patch into a region of an existing file
+///
+/// This function behaves like [`patch()`], except that the new blob is written to `target`
+/// starting at `offset` using positioned writes. This allows reconstructing a new blob directly
+/// into a larger container file, such as a partition image or a preallocated slot in an archive,
+/// without writing it to an intermediate file first. The rest of `target` is left untouched,
+/// though it's extended if the new blob ends past its current end. If successful, returns the
+/// number of bytes written.
+///
+/// On Unix platforms, the file cursor of `target` isn't moved. On Windows, it's left at an
+/// unspecified position.
+///
+/// # Errors
+///
+/// Returns an error if an I/O occurs while reading the patch metadata or writing to `target`, or
+/// if the patch metadata is invalid.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::fs::{File, OpenOptions};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let old = File::open("app-v1.exe")?;
+/// let patch = File::open("app-v1-to-v2.ina")?;
+/// let container = OpenOptions::new().write(true).open("bundle.img")?;
+///
+/// // Reconstruct the new blob into the slot at 1 MiB
+/// ina::patch_into(old, patch, &container, 1 << 20)?;
+///
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(any(unix, windows))]
+pub fn patch_into<O, P>(old: O, patch: P, target: &File, offset: u64) -> Result<u64, PatchError>
+where
+    O: Read + Seek,
+    P: Read,
+{
+    let mut patcher = Patcher::new(old, patch)?;
+    let mut target = PositionedWriter {
+        file: target,
+        pos: offset,
+    };
+
+    let written = io::copy(&mut patcher, &mut target)?;
+    patcher.check_no_next_patch()?;
+
+    Ok(written)
+}
+
+/// Reconstructs a new blob from an old blob and a patch into a file
+///
+/// This function behaves like [`patch()`], except that if the patch records the length of the new
+/// blob, `new` is first truncated or extended to that length so that the filesystem can allocate
+/// it at once rather than as the file grows. Since the recorded length comes from the patch, `new`
+/// is extended to at most twice the length of the rest of `old`. The new blob is then written
+/// from the start of `new` using positioned writes, and `new` is truncated to its length
+/// afterward. If successful, returns the number of bytes written.
+///
+/// On Unix platforms, the file cursor of `new` isn't moved. On Windows, it's left at an unspecified
+/// position.
+///
+/// # Errors
+///
+/// Returns an error if an I/O occurs while reading the patch metadata or writing to `new`, or if
+/// the patch metadata is invalid. If the patch ends before the recorded length of the new blob is
+/// reached, `new` is left with the length it was extended to and the bytes written so far.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::fs::File;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let old = File::open("app-v1.exe")?;
+/// let patch = File::open("app-v1-to-v2.ina")?;
+/// let new = File::create("app-v2.exe")?;
+///
+/// ina::patch_to_file(old, patch, &new)?;
+///
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(any(unix, windows))]
+pub fn patch_to_file<O, P>(mut old: O, patch: P, new: &File) -> Result<u64, PatchError>
+where
+    O: Read + Seek,
+    P: Read,
+{
+    // The length of `patch` can't be determined without consuming it, so only that of `old`
+    // counts towards the preallocation cap
+    let old_start = old.stream_position()?;
+    let old_len = old.seek(SeekFrom::End(0))?.saturating_sub(old_start);
+    old.seek(SeekFrom::Start(old_start))?;
+
+    let mut patcher = Patcher::new(old, patch)?;
+    let new_len = patcher.metadata().new_len();
+    if let Some(new_len) = new_len {
+        new.set_len(preallocation_len(new_len, old_len))?;
+    }
+
+    let mut target = PositionedWriter { file: new, pos: 0 };
+    let written = io::copy(&mut patcher, &mut target)?;
+    patcher.check_no_next_patch()?;
+    new.set_len(written)?;
+
+    Ok(written)
+}
+
+/// A writer which writes to a file at an advancing position without using its file cursor
+#[cfg(any(unix, windows))]
+struct PositionedWriter<'a> {
+    file: &'a File,
+    pos: u64,
+}
+
+#[cfg(any(unix, windows))]
+impl Write for PositionedWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        #[cfg(unix)]
+        let written = std::os::unix::fs::FileExt::write_at(self.file, buf, self.pos)?;
+        #[cfg(windows)]
+        let written = std::os::windows::fs::FileExt::seek_write(self.file, buf, self.pos)?;
+
+        self.pos += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Reconstructs a new blob from an old blob and a patch, then runs a post-processing hook
+///
+/// This function behaves like [`patch()`], except that once the new blob has been fully written,
+/// `hook` is called with the patch metadata and the destination. This is useful for restoring
+/// file metadata recorded in the patch, in which case [`restore_file_metadata()`] can be passed
+/// directly as the hook. If successful, returns the number of bytes written to `new`.
+///
+/// # Errors
+///
+/// Returns an error if an I/O occurs while reading the patch metadata, if the patch metadata is
+/// invalid, or if `hook` returns an error.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::fs::File;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let old = File::open("app-v1.exe")?;
+/// let patch = File::open("app-v1-to-v2.ina")?;
+/// let mut new = File::create("app-v2.exe")?;
+///
+/// ina::patch_with_hook(old, patch, &mut new, ina::restore_file_metadata)?;
+///
+/// # Ok(())
+/// # }
+/// ```
+pub fn patch_with_hook<O, P, W, F>(
+    old: O,
+    patch: P,
+    new: &mut W,
+    hook: F,
+) -> Result<u64, PatchError>
+where
+    O: Read + Seek,
+    P: Read,
+    W: Write + ?Sized,
+    F: FnOnce(&PatchMetadata, &mut W) -> io::Result<()>,
+{
+    let mut patcher = Patcher::new(old, patch)?;
+
+    let written = io::copy(&mut patcher, new)?;
+    patcher.check_no_next_patch()?;
+    new.flush()?;
+    hook(patcher.metadata(), new)?;
+
+    Ok(written)
+}
+
+/// Reconstructs a new blob from an old blob and a patch, writing it to several outputs at once
+///
+/// This function behaves like [`patch()`], except that each part of the new blob is written in
+/// full to every writer in `sinks`, in order, before the next part is reconstructed. This allows,
+/// e.g., writing the new blob to a file while hashing and uploading it without reading it back.
+/// Once the new blob has been written, each writer is flushed. If successful, returns the number
+/// of bytes written to each writer.
+///
+/// # Errors
+///
+/// Returns an error if an I/O occurs while reading the patch metadata, if the patch metadata is
+/// invalid, or if more data follows the patch. If writing to or flushing a writer fails, patching
+/// stops and [`PatchError::Sink`] is returned, which records the index of the writer in `sinks`
+/// and how far the new blob was written.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::{fs::File, io::Write};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let old = File::open("app-v1.exe")?;
+/// let patch = File::open("app-v1-to-v2.ina")?;
+/// let mut new = File::create("app-v2.exe")?;
+/// let mut backup = File::create("/mnt/backup/app-v2.exe")?;
+///
+/// ina::patch_multi(old, patch, &mut [&mut new as &mut dyn Write, &mut backup])?;
+///
+/// # Ok(())
+/// # }
+/// ```
+pub fn patch_multi<O, P>(old: O, patch: P, sinks: &mut [&mut dyn Write]) -> Result<u64, PatchError>
+where
+    O: Read + Seek,
+    P: Read,
+{
+    let mut patcher = Patcher::new(old, patch)?;
+    let mut buf = vec![0; MULTI_BUF_SIZE];
+    let mut written = 0;
+
+    loop {
+        let read = match patcher.read(&mut buf) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e.into()),
+        };
+        for (index, sink) in sinks.iter_mut().enumerate() {
+            sink.write_all(&buf[..read])
+                .map_err(|error| sink_error(index, written, error))?;
+        }
+        written += read as u64;
+    }
+    patcher.check_no_next_patch()?;
+    for (index, sink) in sinks.iter_mut().enumerate() {
+        sink.flush()
+            .map_err(|error| sink_error(index, written, error))?;
+    }
+
+    Ok(written)
+}
+
+/// The size of the buffer parts of the new blob are written to outputs from by [`patch_multi()`]
+const MULTI_BUF_SIZE: usize = 64 << 10;
+
+/// Creates an error attributing `error` to the output at `index` of [`patch_multi()`]
+fn sink_error(index: usize, offset: u64, error: io::Error) -> PatchError {
+    PatchError::Sink(SinkError {
+        index,
+        offset,
+        error,
+    })
+}
+
+/// Restores the file metadata recorded in a patch to `file`
+///
+/// This function is intended to be used as a hook for [`patch_with_hook()`]. If the patch doesn't
+/// contain file metadata, it does nothing. See [`FileMetadata::apply()`] for details.
+///
+/// # Errors
+///
+/// Returns an error if an I/O error occurs while updating the file's metadata.
+pub fn restore_file_metadata(metadata: &PatchMetadata, file: &mut File) -> io::Result<()> {
+    match metadata.file_metadata() {
+        Some(file_metadata) => file_metadata.apply(file),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashSet, io::Cursor, mem};
+
+    use super::*;
+
+    /// A control record of bytes added to the old blob, bytes copied into the new blob, and a seek
+    type Record = (Vec<u8>, Vec<u8>, i64);
+
+    fn bytes(len: usize, seed: u8) -> Vec<u8> {
+        (0..len)
+            .map(|i| (i as u8).wrapping_mul(31).wrapping_add(seed))
+            .collect()
+    }
+
+    fn old() -> Vec<u8> {
+        bytes(1024, 0x5a)
+    }
+
+    /// Returns records with empty fields, single bytes, and lengths around varint boundaries
+    fn records() -> Vec<Record> {
+        vec![
+            (vec![], vec![], 0),
+            (bytes(1, 1), vec![], 0),
+            (vec![], bytes(1, 2), 0),
+            (bytes(127, 3), bytes(128, 4), -100),
+            (bytes(300, 5), vec![], 50),
+            (vec![], bytes(129, 6), -277),
+            (bytes(16, 7), bytes(15, 8), 0),
+            (vec![], vec![], 0),
+        ]
+    }
+
+    /// Encodes `records` as an uncompressed control stream
+    fn controls(records: &[Record]) -> Vec<u8> {
+        let mut controls = Vec::new();
+        for (add, copy, seek) in records {
+            controls.extend_from_slice(&(add.len() as u64).encode_var_vec());
+            controls.extend_from_slice(add);
+            controls.extend_from_slice(&(copy.len() as u64).encode_var_vec());
+            controls.extend_from_slice(copy);
+            controls.extend_from_slice(&seek.encode_var_vec());
+        }
+
+        controls
+    }
+
+    /// Applies `records` to `old` without a `Patcher`
+    fn apply(old: &[u8], records: &[Record]) -> Vec<u8> {
+        let mut new = Vec::new();
+        let mut pos = 0;
+        for (add, copy, seek) in records {
+            let old = &old[pos..pos + add.len()];
+            new.extend(old.iter().zip(add).map(|(o, a)| o.wrapping_add(*a)));
+            new.extend_from_slice(copy);
+            pos = (pos + add.len())
+                .checked_add_signed(*seek as isize)
+                .unwrap();
+        }
+
+        new
+    }
+
+    /// Creates a `Patcher` reading `controls` uncompressed, `patch_buf_len` bytes at a time at
+    /// most, with a difference buffer of `diff_buf_len` bytes
+    fn patcher<'a>(
+        old: &'a [u8],
+        controls: &'a [u8],
+        patch_buf_len: usize,
+        diff_buf_len: usize,
+    ) -> Patcher<'a, Cursor<&'a [u8]>, BufReader<&'a [u8]>> {
+        let metadata = PatchMetadata::new(PatchVersion::from_values(VERSION_MAJOR, 0).unwrap());
+        let patch = BufReader::with_capacity(patch_buf_len, controls);
+        let decoder = PayloadDecoder::Identity(Payload::new(patch, &metadata));
+        let mut patcher =
+            Patcher::from_decoder(Cursor::new(old), decoder, metadata, &PatchLimits::new());
+        patcher.buf = vec![0; diff_buf_len];
+
+        patcher
+    }
+
+    /// Reads `patcher` to its end with reads of at most `chunk` bytes
+    fn read_in_chunks<R: Read>(mut patcher: R, chunk: usize) -> io::Result<Vec<u8>> {
+        let mut new = Vec::new();
+        let mut buf = vec![0; chunk];
+        loop {
+            let read = patcher.read(&mut buf)?;
+            if read == 0 {
+                return Ok(new);
+            }
+            assert!(read <= chunk);
+            new.extend_from_slice(&buf[..read]);
+        }
+    }
+
+    /// Returns whether a `Patcher` may move from state `from` to state `to` in one step
+    fn is_transition(from: PatcherState, to: PatcherState) -> bool {
+        use PatcherState::*;
+
+        match (from, to) {
+            (AtNextControl, Add(len)) => len > 0,
+            (AtNextControl, CopyLen) | (Add(_), CopyLen) => true,
+            (Add(from), Add(to)) | (Copy(from), Copy(to)) => to < from,
+            (CopyLen, Copy(len)) => len > 0,
+            (CopyLen, Seek(None)) | (Copy(_), Seek(None)) => true,
+            (Seek(_), AtNextControl) => true,
+            _ => false,
+        }
+    }
+
+    #[test]
+    fn steps_through_every_transition() -> io::Result<()> {
+        let old = old();
+        let records = records();
+        let controls = controls(&records);
+        let mut patcher = patcher(&old, &controls, 1, 1);
+
+        let mut new = Vec::new();
+        let mut visited = HashSet::new();
+        let mut state = patcher.state;
+        let mut byte = [0];
+        while let Some(read) = patcher.read_step(&mut byte)? {
+            new.extend_from_slice(&byte[..read]);
+            patcher.new_len += read as u64;
 
-    Ok(io::copy(&mut patcher, new)?)
+            assert!(
+                is_transition(state, patcher.state),
+                "invalid transition from {state:?} to {:?}",
+                patcher.state,
+            );
+            state = patcher.state;
+            visited.insert(mem::discriminant(&state));
+        }
+
+        assert_eq!(state, PatcherState::AtNextControl);
+        assert_eq!(visited.len(), 5);
+        assert_eq!(new, apply(&old, &records));
+        // The end of the patch is sticky
+        assert_eq!(patcher.read_step(&mut byte)?, None);
+        assert_eq!(patcher.read(&mut byte)?, 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn output_is_independent_of_buffer_sizes() -> io::Result<()> {
+        let old = old();
+        let records = records();
+        let controls = controls(&records);
+        let expected = apply(&old, &records);
+
+        for patch_buf_len in [1, 2, 5, 8192] {
+            for diff_buf_len in [1, 2, 15, 16, 17, 127, 128, 8192] {
+                for chunk in [1, 2, 3, 16, 128, 4096] {
+                    let patcher = patcher(&old, &controls, patch_buf_len, diff_buf_len);
+                    let new = read_in_chunks(patcher, chunk)?;
+                    assert!(
+                        new == expected,
+                        "patch buffer of {patch_buf_len} bytes, difference buffer of \
+                        {diff_buf_len} bytes, reads of {chunk} bytes",
+                    );
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn fields_at_buffer_boundaries() -> io::Result<()> {
+        let old = old();
+        let diff_buf_len = 16;
+
+        for len in [
+            diff_buf_len - 1,
+            diff_buf_len,
+            diff_buf_len + 1,
+            2 * diff_buf_len,
+        ] {
+            let records = vec![(bytes(len, 1), bytes(len, 2), -(len as i64))];
+            let controls = controls(&records);
+            for chunk in [len - 1, len, len + 1] {
+                let patcher = patcher(&old, &controls, 1, diff_buf_len);
+                assert_eq!(read_in_chunks(patcher, chunk)?, apply(&old, &records));
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn empty_fields_produce_nothing() -> io::Result<()> {
+        let old = old();
+        for records in [vec![], vec![(vec![], vec![], 0); 3]] {
+            let controls = controls(&records);
+            let mut patcher = patcher(&old, &controls, 1, 1);
+            assert!(read_in_chunks(&mut patcher, 1)?.is_empty());
+            assert!(patcher.finished);
+        }
+
+        // An empty add field is skipped straight to the copy field
+        let controls = controls(&[(vec![], bytes(2, 0), 0)]);
+        let mut patcher = patcher(&old, &controls, 1, 1);
+        assert_eq!(patcher.read_step(&mut [0])?, Some(0));
+        assert_eq!(patcher.state, PatcherState::CopyLen);
+
+        Ok(())
+    }
+
+    #[test]
+    fn truncation_within_records_is_detected() {
+        let old = old();
+        let records = records();
+        let controls = controls(&records);
+        let boundaries: HashSet<_> = (0..=records.len())
+            .map(|n| self::controls(&records[..n]).len())
+            .collect();
+
+        for len in 0..controls.len() {
+            let patcher = patcher(&old, &controls[..len], 1, 4);
+            match read_in_chunks(patcher, 7) {
+                Ok(new) => {
+                    assert!(
+                        boundaries.contains(&len),
+                        "truncation to {len} bytes undetected"
+                    );
+                    assert!(apply(&old, &records).starts_with(&new));
+                }
+                Err(e) => {
+                    assert!(
+                        !boundaries.contains(&len),
+                        "valid prefix of {len} bytes failed"
+                    );
+                    assert!(
+                        matches!(
+                            e.get_ref().and_then(|e| e.downcast_ref()),
+                            Some(PatchError::Truncated(_)),
+                        ),
+                        "truncation to {len} bytes wasn't reported as such: {e}",
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn add_past_end_of_old_fails() {
+        let old = bytes(8, 0);
+        let controls = controls(&[(bytes(4, 1), vec![], 0), (bytes(5, 2), vec![], 0)]);
+
+        let mut patcher = patcher(&old, &controls, 1, 16);
+        let mut buf = [0; 16];
+        assert_eq!(patcher.read(&mut buf[..4]).unwrap(), 4);
+        let error = patcher.read(&mut buf).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::UnexpectedEof);
+        // The difference bytes are read one at a time, so the bytes the old blob has left are
+        // applied before it ends
+        assert_eq!(patcher.state, PatcherState::Add(1));
+    }
 }
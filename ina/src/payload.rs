@@ -0,0 +1,438 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use std::io::{self, BufRead, BufReader, ErrorKind, Read};
+
+#[cfg(feature = "brotli")]
+use brotli::{BrotliDecompressStream, BrotliResult, BrotliState, HeapAlloc, HuffmanCode};
+use integer_encoding::VarInt;
+use zstd::{Decoder, zstd_safe::DCtx};
+
+#[cfg(feature = "fec")]
+use crate::fec::FecReader;
+use crate::{Codec, PatchError, PatchMetadata, checksum::ChecksumVerifier};
+
+/// The compressed data section of a patch, with any framing described by the header removed and
+/// checked against its checksum if the header records one
+pub(crate) struct Payload<B>
+where
+    B: BufRead,
+{
+    framing: Framing<B>,
+    verifier: Option<ChecksumVerifier>,
+}
+
+/// The framing of the data section of a patch
+enum Framing<B>
+where
+    B: BufRead,
+{
+    Plain(B),
+    #[cfg(feature = "fec")]
+    Fec(FecReader<B>),
+}
+
+impl<B> Payload<B>
+where
+    B: BufRead,
+{
+    /// Wraps `patch`, which must point to the beginning of the data section of a patch with the
+    /// given metadata
+    pub(crate) fn new(patch: B, metadata: &PatchMetadata) -> Self {
+        #[cfg(feature = "fec")]
+        let framing = match metadata.fec_params() {
+            Some(params) => Framing::Fec(FecReader::new(patch, params)),
+            None => Framing::Plain(patch),
+        };
+        #[cfg(not(feature = "fec"))]
+        let framing = Framing::Plain(patch);
+
+        Self {
+            framing,
+            verifier: metadata.payload_checksum().map(ChecksumVerifier::new),
+        }
+    }
+
+    /// Checks that the data section has been read completely and matches its checksum, which must
+    /// be the case once the compressed data ends
+    pub(crate) fn finish(&self) -> io::Result<()> {
+        #[cfg(feature = "fec")]
+        if let Framing::Fec(patch) = &self.framing
+            && !patch.is_finished()
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "data section continues after the compressed data",
+            ));
+        }
+        if let Some(verifier) = &self.verifier {
+            verifier.check_end()?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the reader of the patch, which is positioned right after the data section once it's
+    /// finished
+    pub(crate) fn get_mut(&mut self) -> &mut B {
+        match &mut self.framing {
+            Framing::Plain(patch) => patch,
+            #[cfg(feature = "fec")]
+            Framing::Fec(patch) => patch.get_mut(),
+        }
+    }
+
+    /// Unwraps the reader of the patch
+    pub(crate) fn into_inner(self) -> B {
+        match self.framing {
+            Framing::Plain(patch) => patch,
+            #[cfg(feature = "fec")]
+            Framing::Fec(patch) => patch.into_inner(),
+        }
+    }
+}
+
+/// Returns the size of the buffers needed to remove the framing of a patch with the given metadata
+pub(crate) fn framing_buffer_len(metadata: &PatchMetadata) -> u64 {
+    #[cfg(feature = "fec")]
+    if let Some(params) = metadata.fec_params() {
+        return params.group_len();
+    }
+    #[cfg(not(feature = "fec"))]
+    let _ = metadata;
+
+    0
+}
+
+impl<B> Read for Payload<B>
+where
+    B: BufRead,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let len = available.len().min(buf.len());
+        buf[..len].copy_from_slice(&available[..len]);
+        self.consume(len);
+
+        Ok(len)
+    }
+}
+
+impl<B> BufRead for Payload<B>
+where
+    B: BufRead,
+{
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        let buf = self.framing.fill_buf()?;
+        if let Some(verifier) = &self.verifier {
+            verifier.check_len()?;
+            if buf.is_empty() {
+                verifier.check_end()?;
+            }
+        }
+
+        Ok(buf)
+    }
+
+    fn consume(&mut self, amt: usize) {
+        if let Some(verifier) = self.verifier.as_mut().filter(|_| amt > 0) {
+            // The data being consumed was returned by the last call to `fill_buf()`, so it's still
+            // buffered and filling the buffer again doesn't perform any I/O
+            if let Ok(buf) = self.framing.fill_buf() {
+                verifier.update(&buf[..amt.min(buf.len())]);
+            }
+        }
+        self.framing.consume(amt);
+    }
+}
+
+impl<B> Framing<B>
+where
+    B: BufRead,
+{
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        match self {
+            Self::Plain(patch) => patch.fill_buf(),
+            #[cfg(feature = "fec")]
+            Self::Fec(patch) => patch.fill_buf(),
+        }
+    }
+
+    fn consume(&mut self, amt: usize) {
+        match self {
+            Self::Plain(patch) => patch.consume(amt),
+            #[cfg(feature = "fec")]
+            Self::Fec(patch) => patch.consume(amt),
+        }
+    }
+}
+
+/// Creates a decompressor for the data section of `patch` with a buffer sized for decompression
+pub(crate) fn decoder<'a, P>(
+    patch: P,
+    metadata: &PatchMetadata,
+) -> io::Result<PayloadDecoder<'a, BufReader<P>>>
+where
+    P: Read,
+{
+    let patch = BufReader::with_capacity(DCtx::in_size(), patch);
+
+    PayloadDecoder::new(Payload::new(patch, metadata), metadata)
+}
+
+/// A varint of the control stream read one byte at a time, which keeps the bytes read so far when
+/// reading fails so that it can be resumed, e.g., after the payload reports
+/// [`ErrorKind::WouldBlock`]
+#[derive(Debug, Default)]
+pub(crate) struct PartialVarint {
+    bytes: [u8; MAX_VARINT_LEN],
+    len: usize,
+}
+
+/// The maximum length of a varint encoding a 64-bit integer
+const MAX_VARINT_LEN: usize = 10;
+
+impl PartialVarint {
+    /// Continues reading the varint from the decompressed `payload`, returning `None` if the
+    /// control stream ends before its first byte
+    ///
+    /// The control stream only ends where the decompressed data does, which decompressors report by
+    /// reading 0 bytes. A compressed stream which is cut off is reported as an unexpected end of
+    /// file instead, so it isn't mistaken for the end of the control stream.
+    pub(crate) fn read<V, R>(&mut self, payload: &mut R) -> io::Result<Option<V>>
+    where
+        V: VarInt,
+        R: Read,
+    {
+        loop {
+            let mut byte = [0];
+            match payload.read(&mut byte) {
+                Ok(0) if self.len == 0 => return Ok(None),
+                Ok(0) => return Err(ErrorKind::UnexpectedEof.into()),
+                Ok(_) => {}
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+
+            if self.len == MAX_VARINT_LEN {
+                return Err(io::Error::new(
+                    ErrorKind::InvalidData,
+                    "unterminated varint",
+                ));
+            }
+            self.bytes[self.len] = byte[0];
+            self.len += 1;
+
+            if byte[0] & 0x80 == 0 {
+                let bytes = &self.bytes[..self.len];
+                self.len = 0;
+                return V::decode_var(bytes)
+                    .map(|(value, _)| Some(value))
+                    .ok_or_else(|| io::Error::new(ErrorKind::InvalidData, "invalid varint"));
+            }
+        }
+    }
+}
+
+/// Converts an unexpected end of file while reading the decompressed data section of a patch into
+/// [`PatchError::Truncated`], given the number of bytes of the new blob produced so far
+pub(crate) fn truncated(error: io::Error, new_len: u64) -> io::Error {
+    if error.kind() == ErrorKind::UnexpectedEof {
+        io::Error::new(ErrorKind::UnexpectedEof, PatchError::Truncated(new_len))
+    } else {
+        error
+    }
+}
+
+/// A decompressor for the data section of a patch using the codec recorded in its header
+pub(crate) enum PayloadDecoder<'a, B>
+where
+    B: BufRead,
+{
+    Zstd(Decoder<'a, Payload<B>>),
+    #[cfg(feature = "brotli")]
+    Brotli(BrotliDecoder<Payload<B>>),
+    /// The data section read as is, so that unit tests can supply control streams uncompressed
+    #[cfg(test)]
+    Identity(Payload<B>),
+}
+
+impl<B> PayloadDecoder<'_, B>
+where
+    B: BufRead,
+{
+    /// Creates a decompressor for `payload`, the data section of a patch with the given metadata
+    pub(crate) fn new(payload: Payload<B>, metadata: &PatchMetadata) -> io::Result<Self> {
+        match metadata.codec() {
+            Codec::Zstd => Ok(Self::Zstd(Decoder::with_buffer(payload)?)),
+            #[cfg(feature = "brotli")]
+            Codec::Brotli => Ok(Self::Brotli(BrotliDecoder::new(payload))),
+        }
+    }
+
+    /// Stops decompressing at the end of the first zstd frame rather than continuing with the next
+    ///
+    /// A Brotli stream always ends where its data does.
+    pub(crate) fn single_frame(self) -> Self {
+        match self {
+            Self::Zstd(decoder) => Self::Zstd(decoder.single_frame()),
+            #[cfg(feature = "brotli")]
+            decoder @ Self::Brotli(_) => decoder,
+            #[cfg(test)]
+            decoder @ Self::Identity(_) => decoder,
+        }
+    }
+
+    /// Limits the size of the zstd decompression window to `2^log_distance` bytes
+    ///
+    /// The window of a Brotli stream is declared by its first byte and is checked before
+    /// decompressing instead.
+    pub(crate) fn window_log_max(&mut self, log_distance: u32) -> io::Result<()> {
+        match self {
+            Self::Zstd(decoder) => decoder.window_log_max(log_distance),
+            #[cfg(feature = "brotli")]
+            Self::Brotli(_) => Ok(()),
+            #[cfg(test)]
+            Self::Identity(_) => Ok(()),
+        }
+    }
+
+    pub(crate) fn get_ref(&self) -> &Payload<B> {
+        match self {
+            Self::Zstd(decoder) => decoder.get_ref(),
+            #[cfg(feature = "brotli")]
+            Self::Brotli(decoder) => &decoder.inner,
+            #[cfg(test)]
+            Self::Identity(payload) => payload,
+        }
+    }
+
+    pub(crate) fn get_mut(&mut self) -> &mut Payload<B> {
+        match self {
+            Self::Zstd(decoder) => decoder.get_mut(),
+            #[cfg(feature = "brotli")]
+            Self::Brotli(decoder) => &mut decoder.inner,
+            #[cfg(test)]
+            Self::Identity(payload) => payload,
+        }
+    }
+
+    pub(crate) fn into_inner(self) -> Payload<B> {
+        match self {
+            Self::Zstd(decoder) => decoder.finish(),
+            #[cfg(feature = "brotli")]
+            Self::Brotli(decoder) => decoder.inner,
+            #[cfg(test)]
+            Self::Identity(payload) => payload,
+        }
+    }
+}
+
+impl<B> Read for PayloadDecoder<'_, B>
+where
+    B: BufRead,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Self::Zstd(decoder) => decoder.read(buf),
+            #[cfg(feature = "brotli")]
+            Self::Brotli(decoder) => decoder.read(buf),
+            #[cfg(test)]
+            Self::Identity(payload) => payload.read(buf),
+        }
+    }
+}
+
+/// A Brotli decompressor which only consumes the input of a single stream
+///
+/// Unlike [`brotli::Decompressor`], this leaves any data following the stream in `inner` so that
+/// concatenated patches can be read one after another.
+#[cfg(feature = "brotli")]
+pub(crate) struct BrotliDecoder<R>
+where
+    R: BufRead,
+{
+    inner: R,
+    state: Box<BrotliState<HeapAlloc<u8>, HeapAlloc<u32>, HeapAlloc<HuffmanCode>>>,
+    finished: bool,
+}
+
+#[cfg(feature = "brotli")]
+impl<R> BrotliDecoder<R>
+where
+    R: BufRead,
+{
+    fn new(inner: R) -> Self {
+        // Large windows are an extension which standard Brotli decoders, such as those of web
+        // browsers, don't support
+        let state = BrotliState::new_strict(
+            HeapAlloc::default(),
+            HeapAlloc::default(),
+            HeapAlloc::default(),
+        );
+
+        Self {
+            inner,
+            state: Box::new(state),
+            finished: false,
+        }
+    }
+}
+
+#[cfg(feature = "brotli")]
+impl<R> Read for BrotliDecoder<R>
+where
+    R: BufRead,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            if self.finished || buf.is_empty() {
+                return Ok(0);
+            }
+
+            let input = self.inner.fill_buf()?;
+            let input_empty = input.is_empty();
+            let mut available_in = input.len();
+            let mut input_offset = 0;
+            let mut available_out = buf.len();
+            let mut output_offset = 0;
+            let mut total_out = 0;
+            let result = BrotliDecompressStream(
+                &mut available_in,
+                &mut input_offset,
+                input,
+                &mut available_out,
+                &mut output_offset,
+                buf,
+                &mut total_out,
+                &mut self.state,
+            );
+            self.inner.consume(input_offset);
+
+            match result {
+                BrotliResult::ResultSuccess => {
+                    self.finished = true;
+                    return Ok(output_offset);
+                }
+                BrotliResult::NeedsMoreOutput => return Ok(output_offset),
+                BrotliResult::NeedsMoreInput if output_offset > 0 => return Ok(output_offset),
+                // A truncated stream must not be mistaken for the end of the control stream, which
+                // is detected by reading 0 bytes
+                BrotliResult::NeedsMoreInput if input_empty => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "Brotli stream is truncated",
+                    ));
+                }
+                BrotliResult::NeedsMoreInput => {}
+                BrotliResult::ResultFailure => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("invalid Brotli stream: {:?}", self.state.error_code),
+                    ));
+                }
+            }
+        }
+    }
+}
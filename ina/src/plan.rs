@@ -0,0 +1,348 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+    path::{Path, PathBuf},
+};
+#[cfg(feature = "patch")]
+use std::{
+    fs::{self, File},
+    io::{self, BufReader, ErrorKind},
+};
+
+#[cfg(feature = "patch")]
+use crate::{PatchError, PatchMetadata};
+
+/// A patch available for download, described only by its metadata.
+///
+/// Versions are identified by free-form strings such as content hashes or logical names like
+/// `app-v1-arm64`. Identifiers are compared exactly.
+#[derive(Clone, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
+pub struct CatalogPatch {
+    old_id: String,
+    new_id: String,
+    len: u64,
+    path: Option<PathBuf>,
+}
+
+impl CatalogPatch {
+    /// Creates a new `CatalogPatch` from the identifiers of the versions it updates between and its
+    /// length in bytes
+    pub fn new(old_id: impl Into<String>, new_id: impl Into<String>, len: u64) -> Self {
+        Self {
+            old_id: old_id.into(),
+            new_id: new_id.into(),
+            len,
+            path: None,
+        }
+    }
+
+    /// Sets the path the patch is stored at
+    pub fn with_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+
+    /// Creates a new `CatalogPatch` from the header of a patch and its length in bytes
+    ///
+    /// The version identifiers are taken from the patch's [`Provenance`](crate::Provenance).
+    /// Returns `None` if the patch doesn't record both identifiers.
+    #[cfg(feature = "patch")]
+    pub fn from_metadata(metadata: &PatchMetadata, len: u64) -> Option<Self> {
+        let provenance = metadata.provenance()?;
+
+        Some(Self::new(provenance.old_id()?, provenance.new_id()?, len))
+    }
+
+    /// Returns the identifier of the version this patch applies to
+    pub fn old_id(&self) -> &str {
+        &self.old_id
+    }
+
+    /// Returns the identifier of the version this patch produces
+    pub fn new_id(&self) -> &str {
+        &self.new_id
+    }
+
+    /// Returns the length of this patch in bytes
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Returns whether this patch is empty
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the path the patch is stored at, if known
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+}
+
+/// A catalog of the patches and full files available for download.
+///
+/// Besides planning updates via [`plan_chain()`], a catalog can be queried for the patches
+/// applying to a given version. A catalog of a directory of patches can be built from their
+/// headers with [`Catalog::from_dir()`].
+///
+/// # Examples
+///
+/// ```
+/// use ina::{Catalog, CatalogPatch};
+///
+/// let mut catalog = Catalog::new();
+/// catalog
+///     .add_patch(CatalogPatch::new("v1", "v2", 1_000))
+///     .add_patch(CatalogPatch::new("v2", "v3", 2_000))
+///     .add_full_file("v3", 50_000);
+///
+/// let plan = ina::plan_chain(&catalog, "v1", "v3").unwrap();
+/// assert_eq!(plan.full_file(), None);
+/// assert_eq!(plan.patches().len(), 2);
+/// assert_eq!(plan.len(), 3_000);
+///
+/// assert_eq!(catalog.patches_from("v2").count(), 1);
+/// assert_eq!(catalog.patch_between("v1", "v2").map(CatalogPatch::len), Some(1_000));
+/// assert_eq!(catalog.patch_between("v1", "v3"), None);
+/// ```
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Catalog {
+    patches: Vec<CatalogPatch>,
+    /// The indices of the patches applying to each version, in the order they were added
+    outgoing: HashMap<String, Vec<usize>>,
+    full_files: HashMap<String, u64>,
+}
+
+impl Catalog {
+    /// Creates a new, empty catalog
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a catalog of the patches in the directory `dir`.
+    ///
+    /// The header of every file in `dir` is read, and each patch recording the identifiers of the
+    /// versions it updates between in its [`Provenance`](crate::Provenance) is added along with
+    /// its path, in the order of their paths. Other files, including patches without both
+    /// identifiers, are skipped, as are subdirectories.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if reading `dir` or one of its files fails.
+    #[cfg(feature = "patch")]
+    pub fn from_dir(dir: impl AsRef<Path>) -> io::Result<Self> {
+        let mut paths = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            if entry.file_type()?.is_file() {
+                paths.push(entry.path());
+            }
+        }
+        paths.sort();
+
+        let mut catalog = Self::new();
+        for path in paths {
+            let file = File::open(&path)?;
+            let len = file.metadata()?.len();
+            let metadata = match crate::read_header(&mut BufReader::new(file)) {
+                Ok(metadata) => metadata,
+                // Files too short to hold a header aren't patches either
+                Err(PatchError::Io(e)) if e.kind() != ErrorKind::UnexpectedEof => return Err(e),
+                Err(_) => continue,
+            };
+            if let Some(patch) = CatalogPatch::from_metadata(&metadata, len) {
+                catalog.add_patch(patch.with_path(path));
+            }
+        }
+
+        Ok(catalog)
+    }
+
+    /// Adds a patch to the catalog
+    pub fn add_patch(&mut self, patch: CatalogPatch) -> &mut Self {
+        self.outgoing
+            .entry(patch.old_id.clone())
+            .or_default()
+            .push(self.patches.len());
+        self.patches.push(patch);
+        self
+    }
+
+    /// Adds a full file of the given version and length in bytes to the catalog.
+    ///
+    /// If a full file of the same version was already added, it's replaced.
+    pub fn add_full_file(&mut self, id: impl Into<String>, len: u64) -> &mut Self {
+        self.full_files.insert(id.into(), len);
+        self
+    }
+
+    /// Returns the patches in the catalog in the order they were added
+    pub fn patches(&self) -> &[CatalogPatch] {
+        &self.patches
+    }
+
+    /// Returns the patches which apply to version `old_id` in the order they were added
+    pub fn patches_from(&self, old_id: &str) -> impl Iterator<Item = &CatalogPatch> {
+        self.outgoing
+            .get(old_id)
+            .into_iter()
+            .flatten()
+            .map(|&index| &self.patches[index])
+    }
+
+    /// Returns the smallest patch updating from version `old_id` to version `new_id`, if any.
+    ///
+    /// If several patches are equally small, the first one added is returned.
+    pub fn patch_between(&self, old_id: &str, new_id: &str) -> Option<&CatalogPatch> {
+        self.patches_from(old_id)
+            .filter(|patch| patch.new_id == new_id)
+            .min_by_key(|patch| patch.len)
+    }
+}
+
+/// The cheapest way to update from one version to another, as found by [`plan_chain()`].
+///
+/// A plan consists of an optional full file to download first followed by a chain of patches to
+/// apply in order. If there's no full file, the chain starts from the device's current version.
+/// Both are empty if the device is already up to date.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ChainPlan<'a> {
+    full_file: Option<&'a str>,
+    patches: Vec<&'a CatalogPatch>,
+    len: u64,
+}
+
+impl<'a> ChainPlan<'a> {
+    /// Returns the identifier of the full file to download first, if any
+    pub fn full_file(&self) -> Option<&'a str> {
+        self.full_file
+    }
+
+    /// Returns the patches to apply in order
+    pub fn patches(&self) -> &[&'a CatalogPatch] {
+        &self.patches
+    }
+
+    /// Returns the total number of bytes to download
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Returns whether nothing needs to be downloaded
+    pub fn is_empty(&self) -> bool {
+        self.full_file.is_none() && self.patches.is_empty()
+    }
+}
+
+/// How the cheapest known route to a version starts or continues
+#[derive(Clone, Copy)]
+enum Step {
+    /// The version is the device's current version
+    Current,
+    /// The version is downloaded as a full file
+    FullFile,
+    /// The version is produced by applying the patch with the given index
+    Patch(usize),
+}
+
+/// Finds the cheapest way to update from version `from` to version `to` using `catalog`
+///
+/// The cost of a plan is the total number of bytes downloaded. Candidate plans include single
+/// patches, chains of patches, a full file of `to`, and a full file of an intermediate version
+/// followed by a chain of patches. If several plans are equally cheap, the one with the fewest
+/// downloads is chosen.
+///
+/// Returns `None` if `to` can't be reached from `from` with the contents of `catalog`.
+///
+/// # Examples
+///
+/// ```
+/// use ina::{Catalog, CatalogPatch};
+///
+/// let mut catalog = Catalog::new();
+/// catalog
+///     .add_patch(CatalogPatch::new("v1", "v2", 40_000))
+///     .add_full_file("v2", 30_000);
+///
+/// // The full file is smaller than the patch
+/// let plan = ina::plan_chain(&catalog, "v1", "v2").unwrap();
+/// assert_eq!(plan.full_file(), Some("v2"));
+/// assert!(plan.patches().is_empty());
+///
+/// assert!(ina::plan_chain(&catalog, "v2", "v3").is_none());
+/// ```
+pub fn plan_chain<'a>(catalog: &'a Catalog, from: &str, to: &str) -> Option<ChainPlan<'a>> {
+    // Dijkstra's algorithm over versions, where costs are (bytes, downloads) and every full file
+    // is an additional starting point
+    let mut best: HashMap<&str, ((u64, usize), Step)> = HashMap::new();
+    let mut queue = BinaryHeap::new();
+    best.insert(from, ((0, 0), Step::Current));
+    queue.push(Reverse(((0, 0), from)));
+    for (id, &len) in &catalog.full_files {
+        let cost = (len, 1);
+        if best
+            .get(id.as_str())
+            .is_none_or(|&(best_cost, _)| cost < best_cost)
+        {
+            best.insert(id, (cost, Step::FullFile));
+            queue.push(Reverse((cost, id)));
+        }
+    }
+
+    while let Some(Reverse((cost, id))) = queue.pop() {
+        if best[id].0 < cost {
+            continue;
+        }
+        if id == to {
+            break;
+        }
+
+        for &index in catalog.outgoing.get(id).into_iter().flatten() {
+            let patch = &catalog.patches[index];
+            if patch.new_id == patch.old_id {
+                continue;
+            }
+
+            let next_cost = (cost.0.saturating_add(patch.len), cost.1 + 1);
+            if best
+                .get(patch.new_id.as_str())
+                .is_none_or(|&(best_cost, _)| next_cost < best_cost)
+            {
+                best.insert(&patch.new_id, (next_cost, Step::Patch(index)));
+                queue.push(Reverse((next_cost, &patch.new_id)));
+            }
+        }
+    }
+
+    let &((len, _), _) = best.get(to)?;
+    let mut plan = ChainPlan {
+        full_file: None,
+        patches: Vec::new(),
+        len,
+    };
+    let mut id = to;
+    loop {
+        match best[id].1 {
+            Step::Current => break,
+            Step::FullFile => {
+                plan.full_file = catalog
+                    .full_files
+                    .get_key_value(id)
+                    .map(|(id, _)| id.as_str());
+                break;
+            }
+            Step::Patch(index) => {
+                let patch = &catalog.patches[index];
+                plan.patches.push(patch);
+                id = &patch.old_id;
+            }
+        }
+    }
+    plan.patches.reverse();
+
+    Some(plan)
+}
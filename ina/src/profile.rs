@@ -0,0 +1,85 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{Codec, DiffConfig, TextMode};
+
+/// A preset of diff settings suited to a class of artifacts.
+///
+/// Applying a profile via [`DiffConfig::profile()`] sets the matcher threshold, codec, compression
+/// level, window log, text mode, and treatment of masked regions to values which produce small
+/// patches for that class of artifacts, so they don't have to be tuned individually. Settings
+/// changed after applying a profile override the profile's values, while settings the profile
+/// doesn't cover, e.g., recorded metadata or masked ranges, are left as they are.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use ina::{DiffConfig, Profile};
+///
+/// let mut config = DiffConfig::new();
+/// config.profile(Profile::Text).compression_level(22);
+///
+/// let mut patch = Vec::new();
+/// let old = ina::OldBlob::from_slice(b"one\ntwo\n");
+/// ina::diff_with_config(&old, b"one\nthree\n", &mut patch, &config)?;
+/// assert!(ina::read_header(&mut patch.as_slice())?.text_hints().is_some());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
+#[non_exhaustive]
+pub enum Profile {
+    /// Compiled executables and libraries
+    ///
+    /// Uses the default matcher threshold, which suits code whose displacements change between
+    /// builds, compression level 19 with a 16 MiB window to find data repeated across large
+    /// binaries, and zeroes masked regions so matches span embedded signatures and timestamps.
+    Executable,
+    /// Archives whose members are mostly compressed, such as APKs and other zip files
+    ///
+    /// Uses a higher matcher threshold so matches continue through small differences in stored
+    /// members, compression level 9 since the data stored verbatim is mostly incompressible, and a
+    /// 128 MiB window to find members which moved within large archives. Masked regions are zeroed
+    /// like for executables.
+    Archive,
+    /// Source code, configuration, and other text
+    ///
+    /// Treats the inputs as text, uses a lower matcher threshold since edits are usually short,
+    /// and compression level 19 with a window chosen by the compressor.
+    Text,
+}
+
+impl Profile {
+    /// Sets the settings covered by this profile in `config`
+    pub(crate) fn apply(self, config: &mut DiffConfig) {
+        config.codec(Codec::Zstd);
+        match self {
+            Self::Executable => {
+                config
+                    .match_threshold(DiffConfig::DEFAULT_MATCH_THRESHOLD)
+                    .compression_level(DiffConfig::DEFAULT_COMPRESSION_LEVEL)
+                    .window_log(Some(24))
+                    .text_mode(TextMode::Binary)
+                    .zero_masked(true);
+            }
+            Self::Archive => {
+                config
+                    .match_threshold(16)
+                    .compression_level(9)
+                    .window_log(Some(27))
+                    .text_mode(TextMode::Binary)
+                    .zero_masked(true);
+            }
+            Self::Text => {
+                config
+                    .match_threshold(4)
+                    .compression_level(DiffConfig::DEFAULT_COMPRESSION_LEVEL)
+                    .window_log(None)
+                    .text_mode(TextMode::Text)
+                    .zero_masked(false);
+            }
+        }
+    }
+}
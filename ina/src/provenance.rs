@@ -0,0 +1,144 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use std::time::SystemTime;
+
+#[cfg(feature = "patch")]
+use crate::file_metadata::decode_time;
+#[cfg(feature = "diff")]
+use crate::file_metadata::encode_time;
+
+/// A record of how and from what a patch was created.
+///
+/// Patches don't record provenance by default, so creating the same patch twice produces
+/// byte-identical output. To record it, pass a `Provenance` to [`DiffConfig::provenance()`]. It
+/// can later be retrieved via [`PatchMetadata::provenance()`].
+///
+/// Every part of a `Provenance` is optional and free-form. The artifact identifiers are intended to
+/// hold logical names such as `app-v1-arm64` rather than file paths, which may reveal details of
+/// the machine that created the patch.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use std::{io::Cursor, time::SystemTime};
+/// use ina::{DiffConfig, Patcher, Provenance};
+///
+/// let provenance = Provenance::new(
+///     Some(format!("my-builder {}", env!("CARGO_PKG_VERSION"))),
+///     Some(SystemTime::now()),
+///     Some("app-v1".into()),
+///     Some("app-v2".into()),
+/// );
+/// let mut patch = Vec::new();
+/// let mut config = DiffConfig::new();
+/// config.provenance(Some(provenance.clone()));
+/// ina::diff_with_config(&ina::OldBlob::from_slice(b"Hello"), b"Hero", &mut patch, &config)?;
+///
+/// let patcher = Patcher::new(Cursor::new(b"Hello"), patch.as_slice())?;
+/// assert_eq!(patcher.metadata().provenance(), Some(&provenance));
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`DiffConfig::provenance()`]: crate::DiffConfig::provenance
+/// [`PatchMetadata::provenance()`]: crate::PatchMetadata::provenance
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Provenance {
+    tool: Option<String>,
+    created: Option<SystemTime>,
+    old_id: Option<String>,
+    new_id: Option<String>,
+}
+
+impl Provenance {
+    /// Creates a new `Provenance` from its parts.
+    ///
+    /// `tool` names the tool and version which created the patch, such as `ina 0.1.0`. `created`
+    /// is the time the patch was created. `old_id` and `new_id` identify the old and new artifacts
+    /// the patch was created from.
+    pub fn new(
+        tool: Option<String>,
+        created: Option<SystemTime>,
+        old_id: Option<String>,
+        new_id: Option<String>,
+    ) -> Self {
+        Self {
+            tool,
+            created,
+            old_id,
+            new_id,
+        }
+    }
+
+    /// Returns the tool which created the patch, if recorded
+    pub fn tool(&self) -> Option<&str> {
+        self.tool.as_deref()
+    }
+
+    /// Returns the time the patch was created, if recorded
+    pub fn created(&self) -> Option<SystemTime> {
+        self.created
+    }
+
+    /// Returns the identifier of the old artifact, if recorded
+    pub fn old_id(&self) -> Option<&str> {
+        self.old_id.as_deref()
+    }
+
+    /// Returns the identifier of the new artifact, if recorded
+    pub fn new_id(&self) -> Option<&str> {
+        self.new_id.as_deref()
+    }
+
+    #[cfg(feature = "diff")]
+    pub(crate) fn encode_tool(&self) -> Option<Vec<u8>> {
+        self.tool.as_ref().map(|tool| tool.as_bytes().to_vec())
+    }
+
+    #[cfg(feature = "diff")]
+    pub(crate) fn encode_created(&self) -> Option<Vec<u8>> {
+        self.created.map(encode_time)
+    }
+
+    #[cfg(feature = "diff")]
+    pub(crate) fn encode_old_id(&self) -> Option<Vec<u8>> {
+        self.old_id.as_ref().map(|id| id.as_bytes().to_vec())
+    }
+
+    #[cfg(feature = "diff")]
+    pub(crate) fn encode_new_id(&self) -> Option<Vec<u8>> {
+        self.new_id.as_ref().map(|id| id.as_bytes().to_vec())
+    }
+
+    #[cfg(feature = "patch")]
+    pub(crate) fn decode_tool(&mut self, value: &[u8]) -> Option<()> {
+        self.tool = Some(String::from_utf8(value.to_vec()).ok()?);
+
+        Some(())
+    }
+
+    #[cfg(feature = "patch")]
+    pub(crate) fn decode_created(&mut self, value: &[u8]) -> Option<()> {
+        self.created = Some(decode_time(value)?);
+
+        Some(())
+    }
+
+    #[cfg(feature = "patch")]
+    pub(crate) fn decode_old_id(&mut self, value: &[u8]) -> Option<()> {
+        self.old_id = Some(String::from_utf8(value.to_vec()).ok()?);
+
+        Some(())
+    }
+
+    #[cfg(feature = "patch")]
+    pub(crate) fn decode_new_id(&mut self, value: &[u8]) -> Option<()> {
+        self.new_id = Some(String::from_utf8(value.to_vec()).ok()?);
+
+        Some(())
+    }
+}
@@ -0,0 +1,156 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use std::io::{self, ErrorKind, Read, Seek, SeekFrom};
+
+/// A source of old blob data read on demand by offset.
+///
+/// Implementing this trait allows a [`Patcher`](crate::Patcher) to take old data from sources
+/// which aren't naturally a [`Read`] + [`Seek`] stream, such as content-addressed chunk stores,
+/// compressed archives, or network sources with their own caching. Closures of the form
+/// `FnMut(u64, &mut [u8]) -> io::Result<usize>` implement this trait, so a callback can be used
+/// directly.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use std::io::{self, Read};
+/// use ina::{OldBlob, Patcher};
+///
+/// // The old blob split into fixed-size chunks, as a chunk store might hold it
+/// const CHUNK_LEN: u64 = 4;
+/// let chunks: [&[u8]; 4] = [b"Hell", b"o, w", b"orld", b"!"];
+///
+/// let mut patch = Vec::new();
+/// ina::diff(&OldBlob::from_slice(b"Hello, world!"), b"Hello, there!", &mut patch)?;
+///
+/// let provider = |offset: u64, buf: &mut [u8]| -> io::Result<usize> {
+///     let Some(chunk) = chunks.get((offset / CHUNK_LEN) as usize) else {
+///         return Ok(0);
+///     };
+///     let src = &chunk[(offset % CHUNK_LEN) as usize..];
+///     let len = src.len().min(buf.len());
+///     buf[..len].copy_from_slice(&src[..len]);
+///     Ok(len)
+/// };
+///
+/// let mut new = Vec::new();
+/// Patcher::with_provider(provider, patch.as_slice())?.read_to_end(&mut new)?;
+/// assert_eq!(new, b"Hello, there!");
+/// # Ok(())
+/// # }
+/// ```
+pub trait OldProvider {
+    /// Reads bytes of the old blob starting at `offset` into `buf`, returning the number of bytes
+    /// read
+    ///
+    /// Fewer bytes than `buf` holds may be read, e.g., at the end of a chunk. A return value of 0
+    /// indicates that `offset` is at or past the end of the old blob or that `buf` is empty.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if reading fails.
+    fn read_at(&mut self, offset: u64, buf: &mut [u8]) -> io::Result<usize>;
+
+    /// Returns the length of the old blob, or `None` if it's unknown
+    ///
+    /// The length is only needed to check the old blob against patches created with a
+    /// [`BaseCheck`](crate::BaseCheck). Such patches can't be applied with a provider which doesn't
+    /// know its length. Returns `None` by default.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if determining the length fails.
+    fn total_len(&mut self) -> io::Result<Option<u64>> {
+        Ok(None)
+    }
+}
+
+impl<F> OldProvider for F
+where
+    F: FnMut(u64, &mut [u8]) -> io::Result<usize>,
+{
+    fn read_at(&mut self, offset: u64, buf: &mut [u8]) -> io::Result<usize> {
+        self(offset, buf)
+    }
+}
+
+/// An [`OldProvider`] adapted for reading as a stream
+///
+/// This is the old blob type of `Patcher`s created by
+/// [`Patcher::with_provider()`](crate::Patcher::with_provider). It can also be created directly
+/// to pass a provider to other constructors, e.g., [`Patcher::with_limits()`].
+///
+/// [`Patcher::with_limits()`]: crate::Patcher::with_limits
+#[derive(Debug)]
+pub struct ProvidedOld<T> {
+    provider: T,
+    pos: u64,
+}
+
+impl<T> ProvidedOld<T>
+where
+    T: OldProvider,
+{
+    /// Creates a stream which reads the old blob from `provider`, starting at its beginning
+    pub fn new(provider: T) -> Self {
+        Self { provider, pos: 0 }
+    }
+
+    /// Returns a reference to the underlying provider
+    pub fn get_ref(&self) -> &T {
+        &self.provider
+    }
+
+    /// Unwraps this `ProvidedOld`, returning the underlying provider
+    pub fn into_inner(self) -> T {
+        self.provider
+    }
+}
+
+impl<T> Read for ProvidedOld<T>
+where
+    T: OldProvider,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.provider.read_at(self.pos, buf)?;
+        self.pos += read as u64;
+
+        Ok(read)
+    }
+}
+
+impl<T> Seek for ProvidedOld<T>
+where
+    T: OldProvider,
+{
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let pos = match pos {
+            SeekFrom::Start(pos) => Some(pos),
+            SeekFrom::Current(offset) => self.pos.checked_add_signed(offset),
+            SeekFrom::End(offset) => {
+                let len = self.provider.total_len()?.ok_or_else(|| {
+                    io::Error::new(
+                        ErrorKind::Unsupported,
+                        "the old blob provider doesn't know its length",
+                    )
+                })?;
+                len.checked_add_signed(offset)
+            }
+        };
+        self.pos = pos.ok_or_else(|| {
+            io::Error::new(
+                ErrorKind::InvalidInput,
+                "seek before the start of the old blob",
+            )
+        })?;
+
+        Ok(self.pos)
+    }
+
+    fn stream_position(&mut self) -> io::Result<u64> {
+        Ok(self.pos)
+    }
+}
@@ -0,0 +1,158 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use std::io;
+
+use crate::{
+    DiffConfig, DiffSettings, PatchError, PatchVersion,
+    header::{VERSION_MAJOR, VERSION_MINOR},
+    read_header,
+};
+
+/// A reason regenerating a patch may make it smaller
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
+pub enum RediffReason {
+    /// The patch uses an older version of the patch format
+    OutdatedFormat,
+    /// The patch doesn't record the settings it was created with, so it may have been matched with
+    /// different settings than the current ones
+    UnrecordedSettings,
+    /// The patch was matched with different settings than the current ones
+    MatcherSettingsChanged,
+    /// Compressing the patch with the current settings makes it meaningfully smaller
+    Recompression,
+}
+
+/// The result of [`check_rediff()`]
+#[derive(Clone, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
+pub struct RediffCheck {
+    version: PatchVersion,
+    settings: Option<DiffSettings>,
+    patch_len: u64,
+    recompressed_len: u64,
+    reasons: Vec<RediffReason>,
+}
+
+impl RediffCheck {
+    /// Returns the format version of the patch
+    pub fn version(&self) -> PatchVersion {
+        self.version
+    }
+
+    /// Returns the settings the patch was created with, if recorded
+    pub fn settings(&self) -> Option<&DiffSettings> {
+        self.settings.as_ref()
+    }
+
+    /// Returns the length of the patch
+    pub fn patch_len(&self) -> u64 {
+        self.patch_len
+    }
+
+    /// Returns the length of the patch once compressed with the current settings
+    pub fn recompressed_len(&self) -> u64 {
+        self.recompressed_len
+    }
+
+    /// Returns the number of bytes compressing the patch with the current settings saves, which
+    /// is 0 if it doesn't make the patch smaller
+    pub fn savings(&self) -> u64 {
+        self.patch_len.saturating_sub(self.recompressed_len)
+    }
+
+    /// Returns the reasons regenerating the patch may make it smaller, in no particular order
+    pub fn reasons(&self) -> &[RediffReason] {
+        &self.reasons
+    }
+
+    /// Returns whether regenerating the patch is recommended, i.e., whether there's any reason to
+    /// expect it to make the patch smaller
+    pub fn is_recommended(&self) -> bool {
+        !self.reasons.is_empty()
+    }
+}
+
+/// Checks whether regenerating `patch` with `options` would likely make it meaningfully smaller,
+/// without needing the old and new blobs it was created from.
+///
+/// This is intended for periodically re-optimizing a repository of patches as settings and the
+/// patch format improve, only regenerating the patches which are likely to benefit. The effect of
+/// the compression settings is measured exactly by [transcoding](crate::transcode) the patch with
+/// `options`, which is much cheaper than diffing again. The payload checksum and forward error
+/// correction of the patch are kept rather than taken from `options`. Savings of less than
+/// `min_savings`, a fraction of the length of the patch, aren't considered meaningful. The effect
+/// of the matcher settings can only be measured by diffing again, so the patch is flagged if it was
+/// matched with different settings or doesn't record the settings it was created with. See
+/// [`DiffConfig::record_settings()`].
+///
+/// # Errors
+///
+/// Returns an error if `patch` is invalid.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use ina::{DiffConfig, RediffReason};
+///
+/// let words = ["alpha ", "beta ", "gamma ", "delta "];
+/// let new: String = (0..4000u32)
+///     .map(|i| words[(i.wrapping_mul(0x9e3779b9) >> 30) as usize])
+///     .collect();
+/// let mut config = DiffConfig::new();
+/// config.compression_level(1).record_settings(true);
+/// let mut patch = Vec::new();
+/// ina::diff_with_config(&ina::OldBlob::default(), new.as_bytes(), &mut patch, &config)?;
+///
+/// // The patch is up to date with the settings it was created with
+/// assert!(!ina::check_rediff(&patch, &config, 0.01)?.is_recommended());
+///
+/// // A higher compression level makes it smaller
+/// config.compression_level(19);
+/// let check = ina::check_rediff(&patch, &config, 0.01)?;
+/// assert_eq!(check.reasons(), [RediffReason::Recompression]);
+/// # Ok(())
+/// # }
+/// ```
+pub fn check_rediff(
+    patch: &[u8],
+    options: &DiffConfig,
+    min_savings: f64,
+) -> Result<RediffCheck, PatchError> {
+    let metadata = read_header(&mut &patch[..])?;
+
+    // Keep the integrity checks of the patch so that only the compression is compared
+    let mut options = options.clone();
+    options.payload_checksum(metadata.has_payload_checksum());
+    #[cfg(feature = "fec")]
+    options.fec(metadata.fec());
+    let recompressed_len = crate::transcode(patch, io::sink(), &options)?.patch_len();
+    let patch_len = patch.len() as u64;
+
+    let mut reasons = Vec::new();
+    // The current version is always valid
+    let current_version = PatchVersion::from_values(VERSION_MAJOR, VERSION_MINOR).unwrap();
+    if metadata.version() < current_version {
+        reasons.push(RediffReason::OutdatedFormat);
+    }
+    match metadata.diff_settings() {
+        None => reasons.push(RediffReason::UnrecordedSettings),
+        Some(settings) if settings.match_threshold() != options.match_threshold as u64 => {
+            reasons.push(RediffReason::MatcherSettingsChanged);
+        }
+        Some(_) => {}
+    }
+    let savings = patch_len.saturating_sub(recompressed_len);
+    if savings > 0 && savings as f64 >= min_savings * patch_len as f64 {
+        reasons.push(RediffReason::Recompression);
+    }
+
+    Ok(RediffCheck {
+        version: metadata.version(),
+        settings: metadata.diff_settings().copied(),
+        patch_len,
+        recompressed_len,
+        reasons,
+    })
+}
@@ -0,0 +1,329 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use std::{
+    fs::File,
+    io::{self, ErrorKind, Read},
+    os::{
+        fd::AsRawFd,
+        unix::fs::{FileExt, MetadataExt},
+    },
+};
+
+use integer_encoding::VarIntReader;
+
+use crate::{
+    PatchError,
+    patch::seek_target,
+    payload::{self, PartialVarint},
+    read_header,
+};
+
+/// The approximate number of bytes of the new file processed at once
+const CHUNK_LEN: u64 = 1 << 20;
+
+/// The block size assumed if a file doesn't report a usable one
+const FALLBACK_BLOCK_LEN: u64 = 4096;
+
+/// Statistics about a new file written by [`patch_reflink()`]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct ReflinkStats {
+    new_len: u64,
+    cloned_len: u64,
+}
+
+impl ReflinkStats {
+    /// Returns the length of the new file
+    pub fn new_len(&self) -> u64 {
+        self.new_len
+    }
+
+    /// Returns the number of bytes of the new file which were cloned from the old file rather
+    /// than written
+    pub fn cloned_len(&self) -> u64 {
+        self.cloned_len
+    }
+
+    /// Returns the number of bytes of the new file which were written
+    pub fn written_len(&self) -> u64 {
+        self.new_len - self.cloned_len
+    }
+}
+
+/// Reconstructs a new file from an old file and a patch, sharing unchanged extents with the old
+/// file where the filesystem supports it
+///
+/// This function behaves like [`patch()`](crate::patch), except that whole filesystem blocks of
+/// the new file which are unchanged copies of blocks of the old file are cloned from the old file
+/// with the `FICLONERANGE` ioctl rather than written. On filesystems supporting reflinks, such as
+/// Btrfs and XFS, this shares the storage of the unchanged regions between both files, which
+/// greatly reduces the amount of data written when patching large files that are mostly
+/// unchanged. Only blocks at the same offset within a block in both files can be cloned, so data
+/// which moved by a multiple of the block size is shared too, but data which moved by any other
+/// amount is written.
+///
+/// If the filesystem doesn't support cloning, or `old` and `new` are on different filesystems,
+/// every block is written instead and the result is the same as with `patch()`. `new` must be
+/// open for writing and must not be the same file as `old`. It's written with positioned writes
+/// and truncated to the length of the new file, so its previous contents don't matter. The new
+/// file isn't read back, so it's not checked against a digest of the new blob recorded in the
+/// patch.
+///
+/// # Errors
+///
+/// Returns an error if an I/O error occurs, if the patch is invalid, or if `old` fails the
+/// patch's base check.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::fs::File;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let old = File::open("disk-v1.img")?;
+/// let patch = File::open("disk-v1-to-v2.ina")?;
+/// let new = File::create("disk-v2.img")?;
+///
+/// let stats = ina::patch_reflink(&old, patch, &new)?;
+/// println!("Wrote {} of {} bytes", stats.written_len(), stats.new_len());
+/// # Ok(())
+/// # }
+/// ```
+pub fn patch_reflink<P>(old: &File, mut patch: P, new: &File) -> Result<ReflinkStats, PatchError>
+where
+    P: Read,
+{
+    let metadata = read_header(&mut patch)?;
+    metadata.verify_base(&mut &*old)?;
+    let mut patch = payload::decoder(patch, &metadata)?;
+
+    let block_len = block_len(old)?.max(block_len(new)?);
+    let mut output = Output::new(old, new, block_len);
+
+    let truncated = |e, new_len| PatchError::from(payload::truncated(e, new_len));
+    while let Some(add_len) = PartialVarint::default()
+        .read(&mut patch)
+        .map_err(|e| truncated(e, output.new_pos))?
+    {
+        output.add(&mut patch, add_len)?;
+
+        let copy_len = patch
+            .read_varint::<u64>()
+            .map_err(|e| truncated(e, output.new_pos))?;
+        output.copy(&mut patch, copy_len)?;
+
+        let seek = patch
+            .read_varint::<i64>()
+            .map_err(|e| truncated(e, output.new_pos))?;
+        output.old_pos = seek_target(output.old_pos, seek, &metadata, output.new_pos)
+            .map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?;
+    }
+
+    patch.get_ref().finish()?;
+    match metadata.new_len() {
+        Some(recorded) if output.new_pos < recorded => {
+            return Err(truncated(ErrorKind::UnexpectedEof.into(), output.new_pos));
+        }
+        Some(recorded) if output.new_pos > recorded => {
+            return Err(io::Error::new(ErrorKind::InvalidData, PatchError::TrailingData).into());
+        }
+        _ => {}
+    }
+    new.set_len(output.new_pos)?;
+
+    Ok(ReflinkStats {
+        new_len: output.new_pos,
+        cloned_len: output.cloned_len,
+    })
+}
+
+/// The state of a new file being written by [`patch_reflink()`]
+struct Output<'a> {
+    old: &'a File,
+    new: &'a File,
+    old_pos: u64,
+    new_pos: u64,
+    block_len: u64,
+    cloned_len: u64,
+    /// Whether cloning is still worth trying, i.e., hasn't failed because it's unsupported
+    clone: bool,
+    diff: Vec<u8>,
+    buf: Vec<u8>,
+}
+
+impl<'a> Output<'a> {
+    fn new(old: &'a File, new: &'a File, block_len: u64) -> Self {
+        let chunk_len = block_len * (CHUNK_LEN / block_len).max(1);
+
+        Self {
+            old,
+            new,
+            old_pos: 0,
+            new_pos: 0,
+            block_len,
+            cloned_len: 0,
+            clone: true,
+            diff: vec![0; chunk_len as usize],
+            buf: vec![0; chunk_len as usize],
+        }
+    }
+
+    /// Applies an add field of `len` bytes read from `patch`
+    fn add<R>(&mut self, patch: &mut R, mut len: u64) -> io::Result<()>
+    where
+        R: Read,
+    {
+        while len > 0 {
+            // End chunks on block boundaries of the new file so that whole blocks can be cloned
+            let chunk_len = (self.diff.len() as u64 - self.new_pos % self.block_len).min(len);
+            let mut diff = std::mem::take(&mut self.diff);
+            let result = self.add_chunk(patch, &mut diff[..chunk_len as usize]);
+            self.diff = diff;
+            result?;
+
+            len -= chunk_len;
+        }
+
+        Ok(())
+    }
+
+    /// Applies the next `diff.len()` bytes of an add field, reading them from `patch` into `diff`
+    ///
+    /// `diff` must end at a block boundary of the new file unless it ends the add field.
+    fn add_chunk<R>(&mut self, patch: &mut R, diff: &mut [u8]) -> io::Result<()>
+    where
+        R: Read,
+    {
+        patch
+            .read_exact(diff)
+            .map_err(|e| payload::truncated(e, self.new_pos))?;
+
+        // Split the chunk at block boundaries of the new file into runs of blocks which can be
+        // cloned and runs which must be written
+        let mut start = 0;
+        while start < diff.len() {
+            let cloneable = self.cloneable(&diff[start..], 0);
+            let mut end = start;
+            while end < diff.len() && self.cloneable(&diff[start..], end - start) == cloneable {
+                let block_offset = (self.new_pos + (end - start) as u64) % self.block_len;
+                end = (end + (self.block_len - block_offset) as usize).min(diff.len());
+            }
+
+            let run_len = (end - start) as u64;
+            if !(cloneable && self.clone_range(run_len)?) {
+                let out = &mut self.buf[..end - start];
+                self.old
+                    .read_exact_at(out, self.old_pos)
+                    .map_err(|e| truncated_old(e, self.old_pos))?;
+                for (out, diff) in out.iter_mut().zip(&diff[start..end]) {
+                    *out = out.wrapping_add(*diff);
+                }
+                self.new.write_all_at(out, self.new_pos)?;
+            }
+
+            self.old_pos += run_len;
+            self.new_pos += run_len;
+            start = end;
+        }
+
+        Ok(())
+    }
+
+    /// Returns whether the block of the new file starting `offset` bytes past the current
+    /// position, whose difference bytes begin at `diff[offset]`, can be cloned from the old file
+    ///
+    /// A block can be cloned if it's unchanged, whole, and aligned to a block in both files.
+    fn cloneable(&self, diff: &[u8], offset: usize) -> bool {
+        let block_len = self.block_len as usize;
+
+        self.clone
+            && (self.old_pos + offset as u64) % self.block_len == 0
+            && (self.new_pos + offset as u64) % self.block_len == 0
+            && diff.len() - offset >= block_len
+            && diff[offset..offset + block_len]
+                .iter()
+                .all(|&byte| byte == 0)
+    }
+
+    /// Clones the next `len` bytes of the old file into the new file, returning whether cloning is
+    /// supported
+    fn clone_range(&mut self, len: u64) -> io::Result<bool> {
+        let range = libc::file_clone_range {
+            src_fd: self.old.as_raw_fd().into(),
+            src_offset: self.old_pos,
+            src_length: len,
+            dest_offset: self.new_pos,
+        };
+        // SAFETY: Both file descriptors are valid for the duration of the call since they're
+        // borrowed from open files, and `range` is a valid `file_clone_range` the kernel only
+        // reads from.
+        let result = unsafe { libc::ioctl(self.new.as_raw_fd(), libc::FICLONERANGE, &range) };
+        if result == 0 {
+            self.cloned_len += len;
+            return Ok(true);
+        }
+
+        let error = io::Error::last_os_error();
+        match error.raw_os_error() {
+            // The filesystem doesn't support cloning, the files are on different filesystems, or
+            // the range can't be cloned for a reason that will persist, e.g., the block size of
+            // the filesystem differs from the one reported, so write from now on
+            Some(
+                libc::EOPNOTSUPP
+                | libc::ENOTTY
+                | libc::ENOSYS
+                | libc::EXDEV
+                | libc::EINVAL
+                | libc::EPERM,
+            ) => {
+                self.clone = false;
+                Ok(false)
+            }
+            _ => Err(error),
+        }
+    }
+
+    /// Applies a copy field of `len` bytes read from `patch`
+    fn copy<R>(&mut self, patch: &mut R, mut len: u64) -> io::Result<()>
+    where
+        R: Read,
+    {
+        while len > 0 {
+            let chunk_len = (self.buf.len() as u64).min(len);
+            let out = &mut self.buf[..chunk_len as usize];
+            patch
+                .read_exact(out)
+                .map_err(|e| payload::truncated(e, self.new_pos))?;
+            self.new.write_all_at(out, self.new_pos)?;
+
+            self.new_pos += chunk_len;
+            len -= chunk_len;
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the block size of `file`, which is the granularity extents can be cloned at
+fn block_len(file: &File) -> io::Result<u64> {
+    let block_len = file.metadata()?.blksize();
+
+    Ok(if block_len.is_power_of_two() {
+        block_len
+    } else {
+        FALLBACK_BLOCK_LEN
+    })
+}
+
+/// Converts an unexpected end of file while reading the old file into an error describing it
+fn truncated_old(error: io::Error, pos: u64) -> io::Error {
+    if error.kind() == ErrorKind::UnexpectedEof {
+        io::Error::new(
+            ErrorKind::UnexpectedEof,
+            format!("old file ends before offset {pos} referenced by the patch"),
+        )
+    } else {
+        error
+    }
+}
@@ -0,0 +1,163 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+#[cfg(feature = "patch")]
+use integer_encoding::VarInt;
+#[cfg(feature = "diff")]
+use integer_encoding::VarIntWriter;
+
+#[cfg(feature = "diff")]
+use crate::{Codec, DiffConfig};
+#[cfg(feature = "patch")]
+use crate::{
+    PatchError,
+    format::{CODECS, TAGGED_FIELDS},
+    header::VERSION_MINOR,
+};
+
+/// The prefix of requirements naming the codec the patch data is compressed with
+const CODEC_PREFIX: &str = "codec=";
+
+/// The requirement of support for forward error correction
+#[cfg(any(feature = "fec", feature = "patch"))]
+const FEC: &str = "fec";
+
+/// The prefix under which an unsatisfied minimum minor version is reported
+#[cfg(feature = "patch")]
+const MINOR_VERSION_PREFIX: &str = "minor-version=";
+
+/// The oldest minor version of the format whose patchers support every feature a patch of this
+/// version of the crate may require
+#[cfg(feature = "diff")]
+const MIN_MINOR_VERSION: u16 = 1;
+
+/// What a patcher must support to apply a patch, as recorded in the patch.
+///
+/// Patchers skip header fields they don't understand, which is how new minor versions of the
+/// format stay readable by older patchers. Some features change how the patch data is read,
+/// though, such as a codec other than zstd or forward error correction, and patchers which predate
+/// them would only fail once they start decoding the data. Patches using such features record the
+/// oldest minor version of the format and the features a patcher must support, so that
+/// [`Patcher`] can reject patches it can't apply while reading the header, with
+/// [`PatchError::UnsupportedFeature`] naming what's missing.
+///
+/// Features are named like the [`FormatFeature`]s they belong to, e.g., `fec`, except for the
+/// codec, which is named `codec=` followed by the name of the [`Codec`], e.g., `codec=brotli`.
+/// Requirements are only recorded by patches which some 1.x patchers can't apply, and are
+/// available via [`PatchMetadata::requirements()`].
+///
+/// [`Codec`]: crate::Codec
+/// [`FormatFeature`]: crate::FormatFeature
+/// [`PatchError::UnsupportedFeature`]: crate::PatchError::UnsupportedFeature
+/// [`PatchMetadata::requirements()`]: crate::PatchMetadata::requirements
+/// [`Patcher`]: crate::Patcher
+#[derive(Clone, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Requirements {
+    min_minor_version: u16,
+    features: Vec<String>,
+}
+
+impl Requirements {
+    /// Returns the oldest minor version of the format whose patchers can apply the patch
+    pub fn min_minor_version(&self) -> u16 {
+        self.min_minor_version
+    }
+
+    /// Returns the names of the features a patcher must support in the order they're recorded
+    pub fn features(&self) -> impl Iterator<Item = &str> + '_ {
+        self.features.iter().map(String::as_str)
+    }
+
+    /// Returns the requirements of patches created with `options`, or `None` if every 1.x patcher
+    /// can apply them
+    #[cfg(feature = "diff")]
+    pub(crate) fn of(options: &DiffConfig) -> Option<Self> {
+        let mut features = Vec::new();
+        if options.codec != Codec::Zstd {
+            features.push(format!("{CODEC_PREFIX}{}", options.codec));
+        }
+        #[cfg(feature = "fec")]
+        if options.fec.is_some() {
+            features.push(FEC.to_owned());
+        }
+
+        (!features.is_empty()).then_some(Self {
+            min_minor_version: MIN_MINOR_VERSION,
+            features,
+        })
+    }
+
+    /// Encodes the requirements as a header field
+    ///
+    /// The field consists of the varint minimum minor version followed by the varint length and
+    /// UTF-8 bytes of each feature name.
+    #[cfg(feature = "diff")]
+    pub(crate) fn encode_field(&self) -> Vec<u8> {
+        let mut field = Vec::new();
+        // Writing to a Vec never fails
+        field.write_varint(self.min_minor_version).unwrap();
+        for feature in &self.features {
+            field.write_varint(feature.len()).unwrap();
+            field.extend_from_slice(feature.as_bytes());
+        }
+
+        field
+    }
+
+    /// Decodes the requirements from a header field, returning `None` if the field is invalid
+    #[cfg(feature = "patch")]
+    pub(crate) fn decode_field(mut field: &[u8]) -> Option<Self> {
+        let (min_minor_version, len) = u64::decode_var(field)?;
+        field = &field[len..];
+
+        let mut requirements = Self {
+            min_minor_version: u16::try_from(min_minor_version).ok()?,
+            features: Vec::new(),
+        };
+        while !field.is_empty() {
+            let (name_len, len) = usize::decode_var(field)?;
+            let (name, rest) = field[len..].split_at_checked(name_len)?;
+            requirements
+                .features
+                .push(String::from_utf8(name.to_vec()).ok()?);
+            field = rest;
+        }
+
+        Some(requirements)
+    }
+
+    /// Checks that this build supports everything the requirements name, returning
+    /// [`PatchError::UnsupportedFeature`] for the first thing it doesn't
+    #[cfg(feature = "patch")]
+    pub(crate) fn check(&self) -> Result<(), PatchError> {
+        if self.min_minor_version > VERSION_MINOR {
+            return Err(PatchError::UnsupportedFeature(format!(
+                "{MINOR_VERSION_PREFIX}{}",
+                self.min_minor_version,
+            )));
+        }
+
+        match self.features().find(|feature| !is_supported(feature)) {
+            Some(feature) => Err(PatchError::UnsupportedFeature(feature.to_owned())),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Returns whether this build supports the feature with the given name
+#[cfg(feature = "patch")]
+fn is_supported(name: &str) -> bool {
+    if let Some(codec) = name.strip_prefix(CODEC_PREFIX) {
+        return CODECS
+            .iter()
+            .any(|supported| supported.to_string() == codec);
+    }
+
+    // Every feature of the format is supported except those behind features of this crate
+    let known = TAGGED_FIELDS
+        .iter()
+        .any(|field| field.feature().to_string() == name);
+    known && (cfg!(feature = "fec") || name != FEC)
+}
@@ -0,0 +1,87 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use std::path::{Path, PathBuf};
+
+use super::{common::SandboxError, patch};
+
+/// A builder for a sandbox which allows more than the sandbox enabled by
+/// [`enable_for_patching()`](super::enable_for_patching).
+///
+/// This is useful for updaters which must write logs or lock files while patching, which would
+/// otherwise have to do without a sandbox entirely.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::{fs::File, path::Path};
+/// use ina::sandbox::SandboxBuilder;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let old = File::open("app-v1.exe")?;
+/// let patch = File::open("app-v1-to-v2.ina")?;
+/// let mut new = File::create("app-v2.exe")?;
+///
+/// SandboxBuilder::new()
+///     .allow_dir(Path::new("/var/log/updater"))
+///     .enable_for_patching()?;
+///
+/// // Logs may still be written while patching
+/// ina::patch(old, patch, &mut new)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq, PartialOrd, Ord)]
+pub struct SandboxBuilder {
+    allowed_dirs: Vec<PathBuf>,
+}
+
+impl SandboxBuilder {
+    /// Creates a new `SandboxBuilder` for a sandbox which allows nothing more than the default one
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allows reading, creating, writing, and removing files and directories beneath `dir` once
+    /// the sandbox is enabled.
+    ///
+    /// Nothing beneath `dir` may be executed. `dir` is opened when the sandbox is enabled, so it
+    /// must exist by then. This is only supported by the Landlock sandbox used on Linux other than
+    /// Android, since the seccomp sandbox used on Android forbids opening files altogether.
+    pub fn allow_dir(&mut self, dir: &Path) -> &mut Self {
+        self.allowed_dirs.push(dir.to_path_buf());
+        self
+    }
+
+    /// Enables the platform-specific sandbox for patching with the allowances of this builder.
+    ///
+    /// This function behaves like [`enable_for_patching()`](super::enable_for_patching), except
+    /// that the allowed directories remain accessible.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a supported sandboxing method is detected on the current platform, but
+    /// enabling it fails, or [`SandboxError::AllowedDirsUnsupported`] if directories are allowed
+    /// but the platform's sandbox can't allow access to them.
+    pub fn enable_for_patching(&self) -> Result<bool, SandboxError> {
+        patch::enable_with(true, &self.allowed_dirs)
+    }
+
+    /// Enables the platform-specific sandbox for patching a memory-mapped old blob with the
+    /// allowances of this builder.
+    ///
+    /// This function behaves like
+    /// [`enable_for_mapped_patching()`](super::enable_for_mapped_patching), except that the
+    /// allowed directories remain accessible.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a supported sandboxing method is detected on the current platform, but
+    /// enabling it fails, or [`SandboxError::AllowedDirsUnsupported`] if directories are allowed
+    /// but the platform's sandbox can't allow access to them.
+    #[cfg(feature = "mmap")]
+    pub fn enable_for_mapped_patching(&self) -> Result<bool, SandboxError> {
+        patch::enable_with(false, &self.allowed_dirs)
+    }
+}
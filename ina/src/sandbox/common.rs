@@ -5,6 +5,7 @@
 use std::{
     error::Error,
     fmt::{self, Display, Formatter},
+    io,
 };
 
 /// An error indicating that sandboxing the process failed.
@@ -20,12 +21,22 @@ use std::{
 pub enum SandboxError {
     /// A seccomp error occurred
     Seccomp(seccompiler::Error),
+    /// A Landlock error occurred
+    Landlock(io::Error),
+    /// Access to directories was requested with
+    /// [`SandboxBuilder::allow_dir()`](super::SandboxBuilder::allow_dir), but the platform's
+    /// sandbox can't allow access to specific directories
+    AllowedDirsUnsupported,
 }
 
 impl Display for SandboxError {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         match self {
             SandboxError::Seccomp(e) => write!(f, "seccomp error: {e}"),
+            SandboxError::Landlock(e) => write!(f, "Landlock error: {e}"),
+            SandboxError::AllowedDirsUnsupported => {
+                write!(f, "the sandbox can't allow access to specific directories")
+            }
         }
     }
 }
@@ -34,6 +45,8 @@ impl Error for SandboxError {
     fn source(&self) -> Option<&(dyn Error + 'static)> {
         match self {
             SandboxError::Seccomp(e) => e.source(),
+            SandboxError::Landlock(e) => Some(e),
+            SandboxError::AllowedDirsUnsupported => None,
         }
     }
 }
@@ -0,0 +1,142 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use std::{
+    fs::{File, OpenOptions},
+    io,
+    os::{
+        fd::{AsRawFd, FromRawFd, OwnedFd},
+        unix::fs::OpenOptionsExt,
+    },
+    path::{Path, PathBuf},
+    ptr,
+};
+
+use super::common::SandboxError;
+
+// Definitions from include/uapi/linux/landlock.h
+const LANDLOCK_CREATE_RULESET_VERSION: u32 = 1 << 0;
+const LANDLOCK_RULE_PATH_BENEATH: libc::c_int = 1;
+
+const LANDLOCK_ACCESS_FS_EXECUTE: u64 = 1 << 0;
+const LANDLOCK_ACCESS_FS_MAKE_CHAR: u64 = 1 << 6;
+const LANDLOCK_ACCESS_FS_MAKE_BLOCK: u64 = 1 << 11;
+
+#[repr(C)]
+struct RulesetAttr {
+    handled_access_fs: u64,
+}
+
+#[repr(C, packed)]
+struct PathBeneathAttr {
+    allowed_access: u64,
+    parent_fd: i32,
+}
+
+/// Returns the Landlock ABI version supported by the running kernel, or `None` if it doesn't
+/// support Landlock
+pub(super) fn abi() -> io::Result<Option<i64>> {
+    // SAFETY: Querying the ABI version takes no attribute and has no side effects
+    let abi = unsafe {
+        libc::syscall(
+            libc::SYS_landlock_create_ruleset,
+            ptr::null::<RulesetAttr>(),
+            0,
+            LANDLOCK_CREATE_RULESET_VERSION,
+        )
+    };
+    if abi < 0 {
+        let error = io::Error::last_os_error();
+        return match error.raw_os_error() {
+            // Landlock isn't built into or is disabled in the running kernel
+            Some(libc::ENOSYS | libc::EOPNOTSUPP) => Ok(None),
+            _ => Err(error),
+        };
+    }
+
+    Ok(Some(abi))
+}
+
+/// Restricts the filesystem access of the calling thread to the directories in `allowed_dirs`
+///
+/// Returns `Ok(false)` if the kernel doesn't support Landlock.
+pub(super) fn restrict(allowed_dirs: &[PathBuf]) -> Result<bool, SandboxError> {
+    let Some(abi) = abi().map_err(SandboxError::Landlock)? else {
+        return Ok(false);
+    };
+
+    // Handle every access right the kernel knows of so that none is implicitly allowed
+    let handled_access_fs = match abi {
+        1 => (1 << 13) - 1,
+        2 => (1 << 14) - 1,
+        3 | 4 => (1 << 15) - 1,
+        _ => (1 << 16) - 1,
+    };
+    let ruleset = RulesetAttr { handled_access_fs };
+    // SAFETY: `ruleset` is a valid attribute of the given size which outlives the call
+    let ruleset_fd = check(unsafe {
+        libc::syscall(
+            libc::SYS_landlock_create_ruleset,
+            &ruleset,
+            size_of::<RulesetAttr>(),
+            0,
+        )
+    })?;
+    // SAFETY: The syscall succeeded, so it returned a new file descriptor owned by nobody else
+    let ruleset_fd = unsafe { OwnedFd::from_raw_fd(ruleset_fd as i32) };
+
+    // Directories may be written to, but nothing beneath them may be executed and no devices may be
+    // created
+    let allowed_access = handled_access_fs
+        & !(LANDLOCK_ACCESS_FS_EXECUTE
+            | LANDLOCK_ACCESS_FS_MAKE_CHAR
+            | LANDLOCK_ACCESS_FS_MAKE_BLOCK);
+    for dir in allowed_dirs {
+        let dir_file = open_path(dir).map_err(|e| {
+            SandboxError::Landlock(io::Error::new(
+                e.kind(),
+                format!("failed to open directory '{}': {e}", dir.display()),
+            ))
+        })?;
+        let rule = PathBeneathAttr {
+            allowed_access,
+            parent_fd: dir_file.as_raw_fd(),
+        };
+        // SAFETY: Both file descriptors are valid and `rule` is a valid rule of the given type
+        // which outlives the call
+        check(unsafe {
+            libc::syscall(
+                libc::SYS_landlock_add_rule,
+                ruleset_fd.as_raw_fd(),
+                LANDLOCK_RULE_PATH_BENEATH,
+                &rule,
+                0,
+            )
+        })?;
+    }
+
+    // SAFETY: Setting no_new_privs has no memory safety implications
+    check(unsafe { libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) }.into())?;
+    // SAFETY: The ruleset file descriptor is valid
+    check(unsafe { libc::syscall(libc::SYS_landlock_restrict_self, ruleset_fd.as_raw_fd(), 0) })?;
+
+    Ok(true)
+}
+
+/// Opens the directory at `path` only to refer to it in a rule
+fn open_path(path: &Path) -> io::Result<File> {
+    OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_PATH | libc::O_DIRECTORY)
+        .open(path)
+}
+
+/// Converts the failure of a syscall into an error
+fn check(result: libc::c_long) -> Result<libc::c_long, SandboxError> {
+    if result < 0 {
+        Err(SandboxError::Landlock(io::Error::last_os_error()))
+    } else {
+        Ok(result)
+    }
+}
@@ -13,6 +13,12 @@
 //! The methods are separated by the operation being performed since patching and diffing may use
 //! different platform capabilities.
 //!
+//! On Android, the sandbox is a seccomp filter which only allows the system calls needed for
+//! patching, applied to every thread of the process. On other Linux systems, it's a Landlock
+//! ruleset which forbids accessing the filesystem by path, except for directories allowed with a
+//! [`SandboxBuilder`], and applies to the calling thread and the threads it creates afterward.
+//! Files opened before enabling either sandbox remain usable.
+//!
 //! # Examples
 //!
 //! ```no_run
@@ -36,8 +42,145 @@
 
 pub use seccompiler;
 
+mod builder;
 mod common;
+#[cfg(target_os = "linux")]
+#[allow(unsafe_code)]
+mod landlock;
 mod patch;
 
+use std::{
+    io,
+    sync::atomic::{AtomicU8, Ordering},
+};
+
+pub use builder::SandboxBuilder;
 pub use common::SandboxError;
 pub use patch::enable as enable_for_patching;
+#[cfg(feature = "mmap")]
+pub use patch::enable_mapped as enable_for_mapped_patching;
+
+/// The most restrictive [`SandboxStatus`] enabled in the current process
+static STATUS: AtomicU8 = AtomicU8::new(SandboxStatus::Disabled as u8);
+
+/// The state of the sandbox in the current process.
+///
+/// The seccomp sandbox forbids creating threads, so once it's enabled, Ina automatically runs
+/// operations which would otherwise use multiple threads, such as multithreaded compression
+/// configured with [`DiffConfig::compression_threads()`], on the calling thread instead.
+///
+/// [`DiffConfig::compression_threads()`]: crate::DiffConfig::compression_threads
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
+#[non_exhaustive]
+pub enum SandboxStatus {
+    /// No sandbox has been enabled
+    Disabled,
+    /// A sandbox restricting filesystem access has been enabled, which still allows operations to
+    /// use multiple threads
+    FilesystemRestricted,
+    /// A sandbox has been enabled, so operations run single-threaded
+    SingleThreaded,
+}
+
+/// Returns the state of the sandbox in the current process
+///
+/// # Examples
+///
+/// ```no_run
+/// use ina::sandbox::{self, SandboxStatus};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// if sandbox::enable_for_patching()? {
+///     assert_ne!(sandbox::status(), SandboxStatus::Disabled);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub fn status() -> SandboxStatus {
+    match STATUS.load(Ordering::Acquire) {
+        0 => SandboxStatus::Disabled,
+        1 => SandboxStatus::FilesystemRestricted,
+        _ => SandboxStatus::SingleThreaded,
+    }
+}
+
+/// Returns whether a sandboxing method is supported on the target platform
+///
+/// If this returns `false`, the functions in this module succeed without enabling a sandbox. On
+/// Linux other than Android, the sandbox also requires a kernel with Landlock enabled, so enabling
+/// it may still succeed without enabling a sandbox when this returns `true`.
+///
+/// # Examples
+///
+/// ```
+/// use ina::sandbox;
+///
+/// if !sandbox::is_supported() {
+///     eprintln!("warning: patching without a sandbox");
+/// }
+/// ```
+pub const fn is_supported() -> bool {
+    cfg!(any(
+        all(
+            target_os = "android",
+            target_endian = "little",
+            any(target_arch = "aarch64", target_arch = "x86_64")
+        ),
+        target_os = "linux",
+    ))
+}
+
+/// How a sandbox can be enabled in the current environment, as reported by [`support()`]
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum SandboxSupport {
+    /// The seccomp sandbox used on Android can be enabled
+    Seccomp,
+    /// The Landlock sandbox used on Linux can be enabled, restricting access to the rights of the
+    /// given Landlock ABI version
+    Landlock(i64),
+    /// The running Linux kernel doesn't support Landlock, e.g., because it was built without it or
+    /// Landlock isn't in its list of enabled security modules
+    LandlockUnavailable,
+    /// Querying the running Linux kernel's support for Landlock failed
+    LandlockError(io::Error),
+    /// No sandboxing method is supported on the target platform
+    UnsupportedPlatform,
+}
+
+/// Determines how a sandbox can be enabled in the current environment
+///
+/// Unlike [`is_supported()`], this accounts for the running kernel, so it reports why enabling a
+/// sandbox would succeed without enabling one.
+///
+/// # Examples
+///
+/// ```
+/// use ina::sandbox::{self, SandboxSupport};
+///
+/// if let SandboxSupport::LandlockUnavailable = sandbox::support() {
+///     eprintln!("warning: the kernel doesn't support Landlock, so patching won't be sandboxed");
+/// }
+/// ```
+pub fn support() -> SandboxSupport {
+    #[cfg(target_os = "linux")]
+    return match landlock::abi() {
+        Ok(Some(abi)) => SandboxSupport::Landlock(abi),
+        Ok(None) => SandboxSupport::LandlockUnavailable,
+        Err(e) => SandboxSupport::LandlockError(e),
+    };
+
+    #[cfg(not(target_os = "linux"))]
+    if is_supported() {
+        SandboxSupport::Seccomp
+    } else {
+        SandboxSupport::UnsupportedPlatform
+    }
+}
+
+/// Records that a sandbox was enabled in the current process
+///
+/// Sandboxes can't be disabled, so the status only ever becomes more restrictive.
+fn set_status(status: SandboxStatus) {
+    STATUS.fetch_max(status as u8, Ordering::AcqRel);
+}
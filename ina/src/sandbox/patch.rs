@@ -2,12 +2,16 @@
 //
 // SPDX-License-Identifier: Apache-2.0
 
-use super::common::SandboxError;
+use std::path::PathBuf;
+
+use super::{SandboxStatus, common::SandboxError, set_status};
 
 /// Enables the platform-specific sandbox for patching
 ///
 /// Returns `Ok(true)` if sandboxing was successfully enabled for the current platform and
-/// `Ok(false)` if no supported sandboxing method was detected.
+/// `Ok(false)` if no supported sandboxing method was detected. Once enabled, the restrictions in
+/// effect are reported by [`status()`](super::status). No directories may be accessed by path; use
+/// a [`SandboxBuilder`](super::SandboxBuilder) to allow access to some.
 ///
 /// # Errors
 ///
@@ -35,7 +39,74 @@ use super::common::SandboxError;
 /// # }
 /// ```
 pub fn enable() -> Result<bool, SandboxError> {
-    Ok(enable_platform_sandbox()?)
+    enable_with(true, &[])
+}
+
+/// Enables the platform-specific sandbox for patching a memory-mapped old blob
+///
+/// This function behaves like [`enable_for_patching()`](super::enable_for_patching), but
+/// additionally forbids seeking, which isn't needed when the old blob is a
+/// [`MappedFile`](crate::MappedFile). The old blob must be mapped before the sandbox is enabled.
+///
+/// # Errors
+///
+/// Returns an error if a supported sandboxing method is detected on the current platform, but
+/// enabling it fails.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::fs::File;
+/// use ina::{MappedFile, sandbox};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let old_file = File::open("app-v1.exe")?;
+/// // SAFETY: app-v1.exe isn't modified while it's mapped
+/// let old = unsafe { MappedFile::map(&old_file)? };
+/// let patch = File::open("app-v1-to-v2.ina")?;
+/// let mut new = File::create("app-v2.exe")?;
+///
+/// sandbox::enable_for_mapped_patching()?;
+///
+/// ina::patch(old, patch, &mut new)?;
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "mmap")]
+pub fn enable_mapped() -> Result<bool, SandboxError> {
+    enable_with(false, &[])
+}
+
+/// Enables the platform-specific sandbox for patching, allowing access to the directories in
+/// `allowed_dirs`
+pub(super) fn enable_with(
+    allow_seek: bool,
+    allowed_dirs: &[PathBuf],
+) -> Result<bool, SandboxError> {
+    let status = enable_platform_sandbox(allow_seek, allowed_dirs)?;
+    if let Some(status) = status {
+        set_status(status);
+    }
+
+    Ok(status.is_some())
+}
+
+#[cfg(all(
+    target_os = "android",
+    target_endian = "little",
+    any(target_arch = "aarch64", target_arch = "x86_64")
+))]
+fn enable_platform_sandbox(
+    allow_seek: bool,
+    allowed_dirs: &[PathBuf],
+) -> Result<Option<SandboxStatus>, SandboxError> {
+    // The seccomp filter forbids opening files altogether
+    if !allowed_dirs.is_empty() {
+        return Err(SandboxError::AllowedDirsUnsupported);
+    }
+    enable_seccomp(allow_seek)?;
+
+    Ok(Some(SandboxStatus::SingleThreaded))
 }
 
 #[cfg(all(
@@ -43,75 +114,79 @@ pub fn enable() -> Result<bool, SandboxError> {
     target_endian = "little",
     any(target_arch = "aarch64", target_arch = "x86_64")
 ))]
-fn enable_platform_sandbox() -> seccompiler::Result<bool> {
+fn enable_seccomp(allow_seek: bool) -> seccompiler::Result<()> {
     use seccompiler::{
         BpfProgram, SeccompAction, SeccompCmpArgLen, SeccompCmpOp, SeccompCondition, SeccompFilter,
         SeccompRule,
     };
     use std::env::consts::ARCH;
 
-    // Expanded from
-    // https://android.googlesource.com/platform/bionic/+/fb48ddc/libc/kernel/uapi/linux/android/binder.h#124.
+    // Expanded from line 124 of libc/kernel/uapi/linux/android/binder.h in bionic at commit
+    // fb48ddc (https://android.googlesource.com/platform/bionic/+/fb48ddc).
     // For the sake of the expansion, we assume that BINDER_IPC_32BIT is not defined, which is
     // always the case on 64-bit systems.
     const BINDER_WRITE_READ: u64 = 3224396289;
 
-    let filter: BpfProgram = SeccompFilter::new(
-        vec![
-            (libc::SYS_close, vec![]),
-            (libc::SYS_epoll_pwait, vec![]),
-            (
-                libc::SYS_fcntl,
-                vec![SeccompRule::new(vec![SeccompCondition::new(
-                    1,
+    let mut rules = vec![
+        (libc::SYS_close, vec![]),
+        (libc::SYS_epoll_pwait, vec![]),
+        (
+            libc::SYS_fcntl,
+            vec![SeccompRule::new(vec![SeccompCondition::new(
+                1,
+                SeccompCmpArgLen::Dword,
+                SeccompCmpOp::Eq,
+                libc::F_DUPFD_CLOEXEC as u64,
+            )?])?],
+        ),
+        (libc::SYS_getuid, vec![]),
+        (
+            libc::SYS_ioctl,
+            vec![SeccompRule::new(vec![SeccompCondition::new(
+                1,
+                SeccompCmpArgLen::Dword,
+                SeccompCmpOp::Eq,
+                BINDER_WRITE_READ,
+            )?])?],
+        ),
+        (libc::SYS_lseek, vec![]),
+        (
+            libc::SYS_mmap,
+            vec![
+                SeccompRule::new(vec![SeccompCondition::new(
+                    2,
+                    SeccompCmpArgLen::Dword,
+                    SeccompCmpOp::Eq,
+                    (libc::PROT_READ | libc::PROT_WRITE) as u64,
+                )?])?,
+                SeccompRule::new(vec![SeccompCondition::new(
+                    2,
                     SeccompCmpArgLen::Dword,
                     SeccompCmpOp::Eq,
-                    libc::F_DUPFD_CLOEXEC as u64,
-                )?])?],
-            ),
-            (libc::SYS_getuid, vec![]),
-            (
-                libc::SYS_ioctl,
-                vec![SeccompRule::new(vec![SeccompCondition::new(
-                    1,
+                    libc::PROT_NONE as u64,
+                )?])?,
+                #[cfg(target_arch = "aarch64")]
+                SeccompRule::new(vec![SeccompCondition::new(
+                    2,
                     SeccompCmpArgLen::Dword,
                     SeccompCmpOp::Eq,
-                    BINDER_WRITE_READ,
-                )?])?],
-            ),
-            (libc::SYS_lseek, vec![]),
-            (
-                libc::SYS_mmap,
-                vec![
-                    SeccompRule::new(vec![SeccompCondition::new(
-                        2,
-                        SeccompCmpArgLen::Dword,
-                        SeccompCmpOp::Eq,
-                        (libc::PROT_READ | libc::PROT_WRITE) as u64,
-                    )?])?,
-                    SeccompRule::new(vec![SeccompCondition::new(
-                        2,
-                        SeccompCmpArgLen::Dword,
-                        SeccompCmpOp::Eq,
-                        libc::PROT_NONE as u64,
-                    )?])?,
-                    #[cfg(target_arch = "aarch64")]
-                    SeccompRule::new(vec![SeccompCondition::new(
-                        2,
-                        SeccompCmpArgLen::Dword,
-                        SeccompCmpOp::Eq,
-                        libc::PROT_MTE as u64,
-                    )?])?,
-                ],
-            ),
-            (libc::SYS_munmap, vec![]),
-            (libc::SYS_prctl, vec![]),
-            (libc::SYS_read, vec![]),
-            (libc::SYS_write, vec![]),
-            (libc::SYS_writev, vec![]),
-        ]
-        .into_iter()
-        .collect(),
+                    libc::PROT_MTE as u64,
+                )?])?,
+            ],
+        ),
+        (libc::SYS_munmap, vec![]),
+        (libc::SYS_prctl, vec![]),
+        (libc::SYS_pread64, vec![]),
+        (libc::SYS_read, vec![]),
+        (libc::SYS_write, vec![]),
+        (libc::SYS_writev, vec![]),
+    ];
+    if !allow_seek {
+        rules.retain(|(syscall, _)| *syscall != libc::SYS_lseek);
+    }
+
+    let filter: BpfProgram = SeccompFilter::new(
+        rules.into_iter().collect(),
         SeccompAction::KillProcess,
         SeccompAction::Allow,
         // This should never panic due to conditional compilation
@@ -121,14 +196,30 @@ fn enable_platform_sandbox() -> seccompiler::Result<bool> {
 
     seccompiler::apply_filter_all_threads(&filter)?;
 
-    Ok(true)
+    Ok(())
 }
 
-#[cfg(not(all(
-    target_os = "android",
-    target_endian = "little",
-    any(target_arch = "aarch64", target_arch = "x86_64")
+#[cfg(target_os = "linux")]
+fn enable_platform_sandbox(
+    _allow_seek: bool,
+    allowed_dirs: &[PathBuf],
+) -> Result<Option<SandboxStatus>, SandboxError> {
+    let enabled = super::landlock::restrict(allowed_dirs)?;
+
+    Ok(enabled.then_some(SandboxStatus::FilesystemRestricted))
+}
+
+#[cfg(not(any(
+    all(
+        target_os = "android",
+        target_endian = "little",
+        any(target_arch = "aarch64", target_arch = "x86_64")
+    ),
+    target_os = "linux",
 )))]
-fn enable_platform_sandbox() -> seccompiler::Result<bool> {
-    Ok(false)
+fn enable_platform_sandbox(
+    _allow_seek: bool,
+    _allowed_dirs: &[PathBuf],
+) -> Result<Option<SandboxStatus>, SandboxError> {
+    Ok(None)
 }
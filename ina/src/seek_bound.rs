@@ -0,0 +1,62 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::bsdiff::Match;
+
+/// An adapter which drops matches that would require seeking too far backward in the old blob
+///
+/// The old blob is read in the order of the matches' add regions. Wherever a match's add region
+/// begins more than `max_backward_seek` bytes before the end of the previous read, the match's
+/// region of the new blob is stored verbatim instead, so applying the patch never seeks backward
+/// further than that.
+pub(crate) struct SeekBoundedMatches<I>
+where
+    I: Iterator<Item = Match>,
+{
+    matches: I,
+    max_backward_seek: u64,
+    /// The position in the old blob at which the last read ended
+    read_end: usize,
+    /// The position in the old blob after the previous match, if any
+    cursor: Option<usize>,
+}
+
+impl<I> SeekBoundedMatches<I>
+where
+    I: Iterator<Item = Match>,
+{
+    /// Creates a new adapter for matches following a read of the old blob which ended at
+    /// `read_end`
+    pub(crate) fn new(matches: I, max_backward_seek: u64, read_end: usize) -> Self {
+        Self {
+            matches,
+            max_backward_seek,
+            read_end,
+            cursor: None,
+        }
+    }
+}
+
+impl<I> Iterator for SeekBoundedMatches<I>
+where
+    I: Iterator<Item = Match>,
+{
+    type Item = Match;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut m = self.matches.next()?;
+        let backward = self.read_end.saturating_sub(m.add_old_pos()) as u64;
+        if m.add_len() > 0 && backward > self.max_backward_seek {
+            // Nothing is read from the old blob, so stay where the previous match left off. The
+            // first match must stay where it is since the patcher doesn't seek before it.
+            let old_pos = self.cursor.unwrap_or(m.add_old_pos());
+            m = Match::new(old_pos, m.add_new_pos(), 0, m.copy_end());
+        } else if m.add_len() > 0 {
+            self.read_end = m.add_old_pos() + m.add_len();
+        }
+
+        self.cursor = Some(m.add_old_pos() + m.add_len());
+        Some(m)
+    }
+}
@@ -0,0 +1,251 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use std::{
+    error::Error,
+    fmt::{self, Display, Formatter},
+};
+
+/// Constraints on the seeks a [`Patcher`](crate::Patcher) may perform in the old blob.
+///
+/// Diffs can be limited to patches which are friendly to old blobs read as streams with
+/// [`DiffConfig::max_backward_seek()`], but an applier receiving patches from elsewhere can't rely
+/// on that. Setting a policy with [`Patcher::seek_policy()`] makes a `Patcher` check every seek a
+/// patch requests before performing it and fail with [`PatchError::SeekViolation`] if the seek
+/// breaks one of the constraints, e.g., because the old blob is a pipe which can't seek backward
+/// or a network stream which only buffers a limited amount of already read data.
+///
+/// Positions in the old blob are relative to its position when patching started. By default, no
+/// seeks are restricted.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::{self, Cursor};
+/// use ina::{PatchError, Patcher, SeekPolicy};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// // The second half of the new blob matches the start of the old blob
+/// let old: Vec<u8> = (0..64 << 10)
+///     .map(|i: u32| (i.wrapping_mul(0x9e3779b9) >> 24) as u8)
+///     .collect();
+/// let mut new = old[32 << 10..].to_vec();
+/// new.extend_from_slice(&old[..32 << 10]);
+/// let mut patch = Vec::new();
+/// ina::diff(&ina::OldBlob::from_slice(&old), &new, &mut patch)?;
+///
+/// let mut patcher = Patcher::new(Cursor::new(&old), patch.as_slice())?
+///     .seek_policy(*SeekPolicy::new().max_backward_seek(0));
+/// let error = io::copy(&mut patcher, &mut io::sink()).unwrap_err();
+/// assert!(matches!(
+///     error.get_ref().and_then(|e| e.downcast_ref()),
+///     Some(PatchError::SeekViolation(_)),
+/// ));
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`DiffConfig::max_backward_seek()`]: crate::DiffConfig::max_backward_seek
+/// [`Patcher::seek_policy()`]: crate::Patcher::seek_policy
+/// [`PatchError::SeekViolation`]: crate::PatchError::SeekViolation
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SeekPolicy {
+    max_backward_seek: Option<u64>,
+    max_forward_seek: Option<u64>,
+    window: Option<u64>,
+}
+
+impl SeekPolicy {
+    /// Creates a new policy which doesn't restrict any seeks
+    pub const fn new() -> Self {
+        Self {
+            max_backward_seek: None,
+            max_forward_seek: None,
+            window: None,
+        }
+    }
+
+    /// Sets the maximum distance in bytes a single seek may move backward in the old blob.
+    ///
+    /// A distance of 0 forbids backward seeks, so the old blob is read monotonically, i.e., each
+    /// read starts at or after the end of the previous one. This matches the limit set by
+    /// `DiffConfig::max_backward_seek()` when diffing.
+    pub fn max_backward_seek(&mut self, bytes: u64) -> &mut Self {
+        self.max_backward_seek = Some(bytes);
+        self
+    }
+
+    /// Sets the maximum distance in bytes a single seek may move forward in the old blob.
+    pub fn max_forward_seek(&mut self, bytes: u64) -> &mut Self {
+        self.max_forward_seek = Some(bytes);
+        self
+    }
+
+    /// Sets the number of bytes behind the furthest position read so far in the old blob which
+    /// reads may start at.
+    ///
+    /// Unlike the maximum backward seek, this bounds the total distance of several consecutive
+    /// backward seeks, so it suits old blobs which only retain a window of already read data.
+    pub fn window(&mut self, bytes: u64) -> &mut Self {
+        self.window = Some(bytes);
+        self
+    }
+
+    /// Returns whether this policy restricts any seeks
+    fn is_unrestricted(&self) -> bool {
+        *self == Self::new()
+    }
+}
+
+/// A constraint of a [`SeekPolicy`], along with its configured limit in bytes.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum SeekRule {
+    /// The maximum distance a single seek may move backward
+    MaxBackwardSeek(u64),
+    /// The maximum distance a single seek may move forward
+    MaxForwardSeek(u64),
+    /// The maximum distance behind the furthest position read which reads may start at
+    Window(u64),
+}
+
+impl Display for SeekRule {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::MaxBackwardSeek(0) => write!(f, "backward seeks are forbidden"),
+            Self::MaxBackwardSeek(limit) => {
+                write!(f, "seeks may move at most {limit} bytes backward")
+            }
+            Self::MaxForwardSeek(limit) => {
+                write!(f, "seeks may move at most {limit} bytes forward")
+            }
+            Self::Window(limit) => write!(
+                f,
+                "reads may start at most {limit} bytes behind the furthest position read",
+            ),
+        }
+    }
+}
+
+/// A seek requested by a patch which violates the [`SeekPolicy`] of a `Patcher`.
+///
+/// This is contained in [`PatchError::SeekViolation`], which is wrapped in an [`io::Error`] when
+/// returned from reading a `Patcher`.
+///
+/// [`PatchError::SeekViolation`]: crate::PatchError::SeekViolation
+/// [`io::Error`]: std::io::Error
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct SeekViolation {
+    rule: SeekRule,
+    old_offset: u64,
+    seek: i64,
+    new_offset: u64,
+}
+
+impl SeekViolation {
+    /// Returns the constraint the seek violates
+    pub fn rule(&self) -> SeekRule {
+        self.rule
+    }
+
+    /// Returns the position in the old blob the seek would have started from
+    pub fn old_offset(&self) -> u64 {
+        self.old_offset
+    }
+
+    /// Returns the distance in bytes of the seek, which is negative for backward seeks
+    pub fn seek(&self) -> i64 {
+        self.seek
+    }
+
+    /// Returns the offset in bytes from the start of the new blob at which the patch requested
+    /// the seek
+    pub fn new_offset(&self) -> u64 {
+        self.new_offset
+    }
+}
+
+impl Display for SeekViolation {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(
+            f,
+            "seek of {} bytes from offset {} of the old blob at offset {} of the new blob \
+            violates the seek policy: {}",
+            self.seek, self.old_offset, self.new_offset, self.rule,
+        )
+    }
+}
+
+impl Error for SeekViolation {}
+
+/// The position of a `Patcher` in the old blob, tracked to enforce its `SeekPolicy`
+#[derive(Debug)]
+pub(crate) struct SeekTracker {
+    policy: SeekPolicy,
+    pos: u64,
+    /// The furthest position read so far
+    frontier: u64,
+}
+
+impl SeekTracker {
+    /// Creates a tracker enforcing `policy`, or `None` if it doesn't restrict any seeks
+    pub(crate) fn new(policy: SeekPolicy) -> Option<Self> {
+        (!policy.is_unrestricted()).then_some(Self {
+            policy,
+            pos: 0,
+            frontier: 0,
+        })
+    }
+
+    /// Returns the policy this tracker enforces
+    pub(crate) fn policy(&self) -> SeekPolicy {
+        self.policy
+    }
+
+    /// Records a read of `len` bytes from the old blob
+    pub(crate) fn record_read(&mut self, len: usize) {
+        self.pos += len as u64;
+        self.frontier = self.frontier.max(self.pos);
+    }
+
+    /// Checks whether the policy permits a seek of `seek` bytes requested after producing
+    /// `new_offset` bytes of the new blob
+    pub(crate) fn check_seek(&self, seek: i64, new_offset: u64) -> Result<(), SeekViolation> {
+        let target = i128::from(self.pos) + i128::from(seek);
+        let rule = match self.policy {
+            SeekPolicy {
+                max_backward_seek: Some(limit),
+                ..
+            } if seek < 0 && seek.unsigned_abs() > limit => Some(SeekRule::MaxBackwardSeek(limit)),
+            SeekPolicy {
+                max_forward_seek: Some(limit),
+                ..
+            } if seek > 0 && seek.unsigned_abs() > limit => Some(SeekRule::MaxForwardSeek(limit)),
+            SeekPolicy {
+                window: Some(limit),
+                ..
+            } if target < i128::from(self.frontier) - i128::from(limit) => {
+                Some(SeekRule::Window(limit))
+            }
+            _ => None,
+        };
+
+        match rule {
+            Some(rule) => Err(SeekViolation {
+                rule,
+                old_offset: self.pos,
+                seek,
+                new_offset,
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Records a seek of `seek` bytes in the old blob
+    pub(crate) fn record_seek(&mut self, seek: i64) {
+        // Seeks before the start of the old blob fail when performed
+        self.pos = self.pos.saturating_add_signed(seek);
+    }
+}
@@ -0,0 +1,331 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use std::io::{self, ErrorKind, Read, Write};
+
+use crc32fast::Hasher;
+
+/// The magic number segments begin with, which is distinct from that of patches and bundles
+const SEGMENT_MAGIC: u32 = 0x73616e69;
+const SEGMENT_VERSION_MAJOR: u16 = 1;
+const SEGMENT_VERSION_MINOR: u16 = 0;
+
+/// The length in bytes of the header each segment begins with.
+///
+/// The header consists of the following little-endian fields:
+///
+/// | Field         | Type  | Description                                             |
+/// |---------------|-------|---------------------------------------------------------|
+/// | Magic         | `u32` | `0x73616e69`, i.e., `inas`                              |
+/// | Major version | `u16` | 1                                                       |
+/// | Minor version | `u16` | 0                                                       |
+/// | Patch length  | `u64` | The length of the whole patch                           |
+/// | Patch CRC     | `u32` | The CRC-32 of the whole patch                           |
+/// | Index         | `u32` | The index of the segment, starting from 0               |
+/// | Count         | `u32` | The number of segments the patch was split into         |
+/// | Length        | `u32` | The length of the part of the patch in the segment      |
+/// | CRC           | `u32` | The CRC-32 of the preceding header fields and the part  |
+///
+/// The patch length and CRC identify the patch a segment belongs to, so segments of different
+/// patches can't be mixed up.
+pub const SEGMENT_HEADER_LEN: usize = 36;
+
+/// Splits `patch` into self-describing segments which are each at most `max_segment_len` bytes
+/// long, including their header.
+///
+/// The segments can be joined back into the patch with [`join_segments()`], or applied directly by
+/// passing a [`SegmentReader`] reading their concatenation to a [`Patcher`](crate::Patcher). This
+/// is useful for transports with a limit on the size of each file. Any blob can be split, including
+/// bundles.
+///
+/// # Errors
+///
+/// Returns an error of kind [`ErrorKind::InvalidInput`] if `max_segment_len` is too small to hold
+/// a header and at least one byte, or if the patch would need more than [`u32::MAX`] segments.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> std::io::Result<()> {
+/// let mut patch = Vec::new();
+/// ina::diff(&ina::OldBlob::from_slice(b"Hello"), b"Hero", &mut patch)?;
+///
+/// let segments = ina::split_patch(&patch, ina::SEGMENT_HEADER_LEN + 8)?;
+/// assert!(segments.len() > 1);
+///
+/// let mut joined = Vec::new();
+/// ina::join_segments(segments.concat().as_slice(), &mut joined)?;
+/// assert_eq!(joined, patch);
+/// # Ok(())
+/// # }
+/// ```
+pub fn split_patch(patch: &[u8], max_segment_len: usize) -> io::Result<Vec<Vec<u8>>> {
+    let part_len = max_segment_len
+        .checked_sub(SEGMENT_HEADER_LEN)
+        .filter(|&len| len > 0)
+        .ok_or_else(|| {
+            io::Error::new(
+                ErrorKind::InvalidInput,
+                format!("segments must be longer than {SEGMENT_HEADER_LEN} bytes"),
+            )
+        })?
+        .min(u32::MAX as usize);
+
+    // An empty patch is still split into one (empty) segment so that it can be joined
+    let count = u32::try_from(patch.len().div_ceil(part_len).max(1))
+        .map_err(|_| io::Error::new(ErrorKind::InvalidInput, "too many segments"))?;
+    let patch_len = patch.len() as u64;
+    let patch_crc = crc32fast::hash(patch);
+
+    let parts = patch
+        .chunks(part_len)
+        .chain(patch.is_empty().then_some(&[][..]));
+    let segments = parts
+        .zip(0u32..)
+        .map(|(part, index)| {
+            let mut segment = Vec::with_capacity(SEGMENT_HEADER_LEN + part.len());
+            segment.extend_from_slice(&SEGMENT_MAGIC.to_le_bytes());
+            segment.extend_from_slice(&SEGMENT_VERSION_MAJOR.to_le_bytes());
+            segment.extend_from_slice(&SEGMENT_VERSION_MINOR.to_le_bytes());
+            segment.extend_from_slice(&patch_len.to_le_bytes());
+            segment.extend_from_slice(&patch_crc.to_le_bytes());
+            segment.extend_from_slice(&index.to_le_bytes());
+            segment.extend_from_slice(&count.to_le_bytes());
+            // The part length fits in a u32 since part_len does
+            segment.extend_from_slice(&(part.len() as u32).to_le_bytes());
+
+            let mut hasher = Hasher::new();
+            hasher.update(&segment);
+            hasher.update(part);
+            segment.extend_from_slice(&hasher.finalize().to_le_bytes());
+            segment.extend_from_slice(part);
+
+            segment
+        })
+        .collect();
+
+    Ok(segments)
+}
+
+/// Joins the concatenated `segments` produced by [`split_patch()`] back into a patch, writing it
+/// to `patch` and returning its length
+///
+/// # Errors
+///
+/// Returns an error if an I/O error occurs or if the segments are invalid, as described for
+/// [`SegmentReader`].
+pub fn join_segments<R, W>(segments: R, mut patch: W) -> io::Result<u64>
+where
+    R: Read,
+    W: Write,
+{
+    io::copy(&mut SegmentReader::new(segments), &mut patch)
+}
+
+/// A reader which reads the patch split into the concatenated segments read from an inner reader.
+///
+/// Each segment is read into memory and checked against its CRC before any of its data is returned,
+/// so a [`Patcher`](crate::Patcher) reading from a `SegmentReader` never sees corrupted data. Once
+/// the last segment has been read, the whole patch is checked against the length and CRC recorded
+/// in every segment. Segments must be in order, which is the case when concatenating segment files
+/// named with their zero-padded index in lexicographic order.
+///
+/// All errors are of kind [`ErrorKind::InvalidData`], except for errors returned by the inner
+/// reader. In particular, missing segments are reported as invalid data rather than as an
+/// unexpected end of file, since they indicate an incomplete transfer rather than a truncated
+/// patch.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use std::io::Cursor;
+/// use ina::SegmentReader;
+///
+/// let mut patch = Vec::new();
+/// ina::diff(&ina::OldBlob::from_slice(b"Hello"), b"Hero", &mut patch)?;
+/// let segments = ina::split_patch(&patch, 64)?.concat();
+///
+/// let mut new = Vec::new();
+/// let reader = SegmentReader::new(segments.as_slice());
+/// ina::patch(Cursor::new(b"Hello"), reader, &mut new)?;
+/// assert_eq!(new, b"Hero");
+/// # Ok(())
+/// # }
+/// ```
+pub struct SegmentReader<R>
+where
+    R: Read,
+{
+    inner: R,
+    patch: Option<PatchIdentity>,
+    next_index: u32,
+    part: Vec<u8>,
+    pos: usize,
+    hasher: Hasher,
+    len: u64,
+    finished: bool,
+}
+
+/// The fields of a segment header which are the same for every segment of a patch
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct PatchIdentity {
+    len: u64,
+    crc: u32,
+    count: u32,
+}
+
+impl<R> SegmentReader<R>
+where
+    R: Read,
+{
+    /// Creates a new `SegmentReader` reading segments from `inner`
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            patch: None,
+            next_index: 0,
+            part: Vec::new(),
+            pos: 0,
+            hasher: Hasher::new(),
+            len: 0,
+            finished: false,
+        }
+    }
+
+    /// Returns the number of segments the patch was split into, or `None` if no segment has been
+    /// read yet
+    pub fn segment_count(&self) -> Option<u32> {
+        self.patch.map(|patch| patch.count)
+    }
+
+    /// Unwraps this `SegmentReader`, returning the inner reader
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Reads and checks the next segment, returning `false` once the last segment has been read
+    fn next_segment(&mut self) -> io::Result<bool> {
+        if let Some(patch) = self.patch
+            && self.next_index == patch.count
+        {
+            if self.len != patch.len || self.hasher.clone().finalize() != patch.crc {
+                return Err(invalid("joined patch doesn't match its checksum"));
+            }
+            return Ok(false);
+        }
+
+        let mut header = [0; SEGMENT_HEADER_LEN];
+        self.inner
+            .read_exact(&mut header)
+            .map_err(|error| self.missing(error))?;
+        let field = |offset: usize, len: usize| &header[offset..offset + len];
+        let u16_at = |offset| u16::from_le_bytes(field(offset, 2).try_into().unwrap());
+        let u32_at = |offset| u32::from_le_bytes(field(offset, 4).try_into().unwrap());
+
+        if u32_at(0) != SEGMENT_MAGIC {
+            return Err(invalid("not a patch segment"));
+        }
+        // Minor versions only add information readers may ignore
+        if u16_at(4) != SEGMENT_VERSION_MAJOR {
+            return Err(invalid("unsupported patch segment version"));
+        }
+        let identity = PatchIdentity {
+            len: u64::from_le_bytes(field(8, 8).try_into().unwrap()),
+            crc: u32_at(16),
+            count: u32_at(24),
+        };
+        let index = u32_at(20);
+        let part_len = u32_at(28);
+        let crc = u32_at(32);
+
+        if identity.count == 0 {
+            return Err(invalid("segment count is 0"));
+        }
+        if *self.patch.get_or_insert(identity) != identity {
+            return Err(invalid("segment belongs to a different patch"));
+        }
+        if index != self.next_index {
+            return Err(invalid(&format!(
+                "expected segment {} of {} but found segment {}",
+                self.next_index + 1,
+                identity.count,
+                index.saturating_add(1),
+            )));
+        }
+        if u64::from(part_len) > identity.len - self.len.min(identity.len) {
+            return Err(invalid("segment is longer than the rest of the patch"));
+        }
+
+        self.part.clear();
+        self.pos = 0;
+        (&mut self.inner)
+            .take(part_len.into())
+            .read_to_end(&mut self.part)?;
+        if self.part.len() != part_len as usize {
+            return Err(self.missing(ErrorKind::UnexpectedEof.into()));
+        }
+
+        let mut hasher = Hasher::new();
+        hasher.update(&header[..SEGMENT_HEADER_LEN - 4]);
+        hasher.update(&self.part);
+        if hasher.finalize() != crc {
+            return Err(invalid(&format!(
+                "segment {} of {} doesn't match its checksum",
+                index + 1,
+                identity.count,
+            )));
+        }
+
+        self.hasher.update(&self.part);
+        self.len += self.part.len() as u64;
+        self.next_index += 1;
+
+        Ok(true)
+    }
+
+    /// Converts an unexpected end of file while reading the next segment into an error reporting
+    /// the missing segment
+    fn missing(&self, error: io::Error) -> io::Error {
+        if error.kind() != ErrorKind::UnexpectedEof {
+            return error;
+        }
+
+        match self.patch {
+            Some(patch) => invalid(&format!(
+                "segment {} of {} is missing or incomplete",
+                self.next_index + 1,
+                patch.count,
+            )),
+            None => invalid("no patch segments found"),
+        }
+    }
+}
+
+impl<R> Read for SegmentReader<R>
+where
+    R: Read,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.part.len() {
+            if self.finished || buf.is_empty() {
+                return Ok(0);
+            }
+            if !self.next_segment()? {
+                self.finished = true;
+                return Ok(0);
+            }
+        }
+
+        let len = buf.len().min(self.part.len() - self.pos);
+        buf[..len].copy_from_slice(&self.part[self.pos..self.pos + len]);
+        self.pos += len;
+
+        Ok(len)
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, message)
+}
@@ -0,0 +1,190 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Self-tests for verifying Ina on a target device.
+//!
+//! The patch format is defined independently of the endianness and pointer width of the machine
+//! producing or applying a patch. The self-tests in this module check that this holds for the
+//! current target by applying a suite of golden patches embedded in the library, which were
+//! produced on a little-endian 64-bit machine. They are intended to be run on-device, e.g., on
+//! big-endian or 32-bit targets which can't run the crate's test suite.
+//!
+//! # Examples
+//!
+//! ```
+//! let results = ina::selftest::run_format();
+//!
+//! for (name, result) in &results {
+//!     assert!(result.is_ok(), "golden vector {name} failed");
+//! }
+//! ```
+
+use std::{
+    error::Error,
+    fmt::{self, Display, Formatter},
+    io::Cursor,
+    time::{Duration, UNIX_EPOCH},
+};
+
+use crate::{FileMetadata, PatchError, Patcher};
+
+/// A golden patch along with the blobs it was created from
+struct Vector<'a> {
+    name: &'static str,
+    old: &'a [u8],
+    patch: &'a [u8],
+    new: &'a [u8],
+    file_metadata: Option<FileMetadata>,
+}
+
+macro_rules! vector {
+    ($name: literal, $file_metadata: expr) => {
+        Vector {
+            name: $name,
+            old: include_bytes!(concat!("../golden/", $name, ".old")),
+            patch: include_bytes!(concat!("../golden/", $name, ".ina")),
+            new: include_bytes!(concat!("../golden/", $name, ".new")),
+            file_metadata: $file_metadata,
+        }
+    };
+}
+
+fn vectors() -> [Vector<'static>; 4] {
+    [
+        vector!("basic", None),
+        // No control records
+        vector!("empty", None),
+        // Negative seeks and multi-byte varints
+        vector!("seek", None),
+        // Zigzag-encoded pre-epoch timestamp followed by a field with an unknown tag
+        vector!(
+            "metadata",
+            Some(FileMetadata::new(
+                Some(0o755),
+                Some(UNIX_EPOCH - Duration::new(1, 500_000_000)),
+            ))
+        ),
+    ]
+}
+
+/// Runs the patch format self-tests, returning the name and result of each test
+///
+/// Every golden patch is applied to its old blob and the result is compared to the expected new
+/// blob. If the `diff` feature is enabled, a patch is additionally created and applied to check
+/// that this target encodes patch headers identically.
+pub fn run_format() -> Vec<(&'static str, Result<(), SelftestError>)> {
+    let results = vectors()
+        .iter()
+        .map(|vector| (vector.name, check_vector(vector)))
+        .collect();
+
+    #[cfg(feature = "diff")]
+    let results = {
+        let mut results: Vec<_> = results;
+        results.push(("encode", check_encode()));
+        results
+    };
+
+    results
+}
+
+fn check_vector(vector: &Vector) -> Result<(), SelftestError> {
+    let mut patcher = Patcher::new(Cursor::new(vector.old), vector.patch)?;
+    let mut new = Vec::new();
+    std::io::copy(&mut patcher, &mut new).map_err(PatchError::from)?;
+
+    if let Some(offset) = first_difference(&new, vector.new) {
+        return Err(SelftestError::OutputMismatch(offset));
+    }
+    if patcher.metadata().file_metadata() != vector.file_metadata {
+        return Err(SelftestError::MetadataMismatch);
+    }
+
+    Ok(())
+}
+
+/// Checks that diffing the basic vector reproduces its header and that the result applies
+#[cfg(feature = "diff")]
+fn check_encode() -> Result<(), SelftestError> {
+    let vector = &vectors()[0];
+
+    let mut patch = Vec::new();
+    crate::diff(
+        &crate::OldBlob::from_slice(vector.old),
+        vector.new,
+        &mut patch,
+    )
+    .map_err(PatchError::from)?;
+
+    // Only the headers are compared since the compressed data may legitimately differ between
+    // compressor versions
+    let header_len = header_len(vector.patch)?;
+    if let Some(offset) = first_difference(&patch[..header_len], &vector.patch[..header_len]) {
+        return Err(SelftestError::OutputMismatch(offset));
+    }
+
+    check_vector(&Vector {
+        patch: &patch,
+        ..*vector
+    })
+}
+
+/// Returns the length of the header of `patch`
+#[cfg(feature = "diff")]
+fn header_len(mut patch: &[u8]) -> Result<usize, PatchError> {
+    let len = patch.len();
+    crate::read_header(&mut patch)?;
+
+    Ok(len - patch.len())
+}
+
+/// Returns the offset of the first byte at which `a` and `b` differ, if any
+fn first_difference(a: &[u8], b: &[u8]) -> Option<u64> {
+    a.iter()
+        .zip(b)
+        .position(|(x, y)| x != y)
+        .or_else(|| (a.len() != b.len()).then_some(a.len().min(b.len())))
+        .map(|offset| offset as u64)
+}
+
+/// An error indicating that a self-test failed.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum SelftestError {
+    /// Applying a golden patch failed
+    Patch(PatchError),
+    /// The output differed from the expected output starting at the given offset
+    OutputMismatch(u64),
+    /// The file metadata read from a golden patch differed from the expected metadata
+    MetadataMismatch,
+}
+
+impl Display for SelftestError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            SelftestError::Patch(e) => write!(f, "patch error: {e}"),
+            SelftestError::OutputMismatch(offset) => {
+                write!(f, "output differs from expected output at offset {offset}")
+            }
+            SelftestError::MetadataMismatch => {
+                write!(f, "file metadata differs from expected file metadata")
+            }
+        }
+    }
+}
+
+impl Error for SelftestError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SelftestError::Patch(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<PatchError> for SelftestError {
+    fn from(value: PatchError) -> Self {
+        SelftestError::Patch(value)
+    }
+}
@@ -0,0 +1,90 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+#[cfg(feature = "patch")]
+use integer_encoding::VarInt;
+#[cfg(feature = "diff")]
+use integer_encoding::VarIntWriter;
+
+#[cfg(feature = "diff")]
+use crate::DiffConfig;
+
+/// The settings a patch was created with.
+///
+/// Patches don't record their settings by default. To record them, enable
+/// [`DiffConfig::record_settings()`]. They can later be retrieved via
+/// [`PatchMetadata::diff_settings()`], e.g., to find patches in a repository which were created
+/// with outdated settings and may be worth regenerating.
+///
+/// [`DiffConfig::record_settings()`]: crate::DiffConfig::record_settings
+/// [`PatchMetadata::diff_settings()`]: crate::PatchMetadata::diff_settings
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DiffSettings {
+    compression_level: i32,
+    window_log: Option<u32>,
+    match_threshold: u64,
+}
+
+impl DiffSettings {
+    /// Returns the settings of `config` which are recorded in patches
+    #[cfg(feature = "diff")]
+    pub(crate) fn of(config: &DiffConfig) -> Self {
+        Self {
+            compression_level: config.compression_level,
+            window_log: config.window_log,
+            match_threshold: config.match_threshold as u64,
+        }
+    }
+
+    /// Returns the compression level the patch was compressed with
+    pub fn compression_level(&self) -> i32 {
+        self.compression_level
+    }
+
+    /// Returns the base-2 logarithm of the compression window size, or `None` if it was chosen by
+    /// the compressor
+    pub fn window_log(&self) -> Option<u32> {
+        self.window_log
+    }
+
+    /// Returns the mismatch threshold the patch was matched with
+    pub fn match_threshold(&self) -> u64 {
+        self.match_threshold
+    }
+
+    /// Encodes the settings as a header field
+    ///
+    /// The field consists of the zigzag varint compression level, the varint window log or 0 if it
+    /// was chosen by the compressor, and the varint match threshold.
+    #[cfg(feature = "diff")]
+    pub(crate) fn encode_field(&self) -> Vec<u8> {
+        let mut field = Vec::new();
+        // Writing to a Vec never fails
+        field.write_varint(self.compression_level).unwrap();
+        field.write_varint(self.window_log.unwrap_or(0)).unwrap();
+        field.write_varint(self.match_threshold).unwrap();
+
+        field
+    }
+
+    /// Decodes the settings from a header field, returning `None` if the field is invalid
+    #[cfg(feature = "patch")]
+    pub(crate) fn decode_field(field: &[u8]) -> Option<Self> {
+        let (compression_level, level_len) = i32::decode_var(field)?;
+        let field = &field[level_len..];
+        let (window_log, window_log_len) = u32::decode_var(field)?;
+        let field = &field[window_log_len..];
+        let (match_threshold, threshold_len) = u64::decode_var(field)?;
+        if threshold_len != field.len() {
+            return None;
+        }
+
+        Some(Self {
+            compression_level,
+            window_log: (window_log != 0).then_some(window_log),
+            match_threshold,
+        })
+    }
+}
@@ -0,0 +1,464 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+#[cfg(feature = "stats")]
+use std::time::{Duration, Instant};
+use std::{
+    fmt::{self, Display, Formatter},
+    ops::{Range, RangeInclusive},
+};
+
+/// Statistics about a generated patch.
+///
+/// This struct is returned by the diffing functions and describes the patch they produced.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DiffStats {
+    pub(crate) old_len: u64,
+    pub(crate) new_len: u64,
+    pub(crate) patch_len: u64,
+    pub(crate) control_records: u64,
+    pub(crate) add_bytes: u64,
+    pub(crate) copy_bytes: u64,
+    pub(crate) old_coverage: OldCoverage,
+    pub(crate) seeks: SeekStats,
+    pub(crate) fast_path: Option<FastPath>,
+    #[cfg(feature = "stats")]
+    pub(crate) suffix_array_time: Duration,
+    #[cfg(feature = "stats")]
+    pub(crate) match_time: Duration,
+    #[cfg(feature = "stats")]
+    pub(crate) compression_time: Duration,
+    #[cfg(feature = "stats")]
+    pub(crate) peak_memory: Option<u64>,
+}
+
+impl DiffStats {
+    /// Returns the length of the old blob, excluding the sentinel
+    pub fn old_len(&self) -> u64 {
+        self.old_len
+    }
+
+    /// Returns the length of the new blob
+    pub fn new_len(&self) -> u64 {
+        self.new_len
+    }
+
+    /// Returns the length of the patch, including its header
+    pub fn patch_len(&self) -> u64 {
+        self.patch_len
+    }
+
+    /// Returns the number of control records in the patch
+    pub fn control_records(&self) -> u64 {
+        self.control_records
+    }
+
+    /// Returns the number of bytes of the new blob stored as differences against the old blob
+    pub fn add_bytes(&self) -> u64 {
+        self.add_bytes
+    }
+
+    /// Returns the number of bytes of the new blob stored verbatim
+    pub fn copy_bytes(&self) -> u64 {
+        self.copy_bytes
+    }
+
+    /// Returns the regions of the old blob referenced by the patch
+    ///
+    /// Applying the patch only reads these regions of the old blob, so any other region can be
+    /// altered or removed from the old blob without affecting the result of patching.
+    pub fn old_coverage(&self) -> &OldCoverage {
+        &self.old_coverage
+    }
+
+    /// Returns statistics about the seeks in the old blob made while applying the patch
+    pub fn seeks(&self) -> &SeekStats {
+        &self.seeks
+    }
+
+    /// Returns the shortcut taken instead of searching for matches, if the blobs were found to be
+    /// trivially related
+    pub fn fast_path(&self) -> Option<FastPath> {
+        self.fast_path
+    }
+
+    /// Returns the time spent indexing the old blob
+    #[cfg(feature = "stats")]
+    pub fn suffix_array_time(&self) -> Duration {
+        self.suffix_array_time
+    }
+
+    /// Returns the time spent searching for matches between the old and new blobs
+    #[cfg(feature = "stats")]
+    pub fn match_time(&self) -> Duration {
+        self.match_time
+    }
+
+    /// Returns the time spent compressing the patch
+    ///
+    /// With multithreaded compression, this is the time the diffing thread spent handing data to
+    /// and waiting on the compression threads.
+    #[cfg(feature = "stats")]
+    pub fn compression_time(&self) -> Duration {
+        self.compression_time
+    }
+
+    /// Returns the peak resident memory of the process in bytes as of the end of diffing, if
+    /// available on the current platform
+    ///
+    /// This is measured for the whole process, so it's only an estimate of the memory used for
+    /// diffing if the process did other work before or concurrently. It's currently available on
+    /// Linux and Android.
+    #[cfg(feature = "stats")]
+    pub fn peak_memory(&self) -> Option<u64> {
+        self.peak_memory
+    }
+}
+
+/// A trivial relation between an old and a new blob which lets diffing skip indexing the old blob
+/// and searching for matches.
+///
+/// Diffing detects these cases by comparing the blobs directly and writes a single control record
+/// for them, which is as small a patch as the full search would produce.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum FastPath {
+    /// The blobs are identical
+    Identical,
+    /// The new blob consists of the old blob followed by appended data
+    Append,
+}
+
+impl Display for FastPath {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::Identical => write!(f, "identical"),
+            Self::Append => write!(f, "append"),
+        }
+    }
+}
+
+/// An iterator adapter which accumulates the time spent producing items
+#[cfg(feature = "stats")]
+pub(crate) struct TimedIter<'t, I> {
+    iter: I,
+    elapsed: &'t mut Duration,
+}
+
+#[cfg(feature = "stats")]
+impl<'t, I> TimedIter<'t, I> {
+    pub(crate) fn new(iter: I, elapsed: &'t mut Duration) -> Self {
+        Self { iter, elapsed }
+    }
+}
+
+#[cfg(feature = "stats")]
+impl<I> Iterator for TimedIter<'_, I>
+where
+    I: Iterator,
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let start = Instant::now();
+        let item = self.iter.next();
+        *self.elapsed += start.elapsed();
+
+        item
+    }
+}
+
+/// Returns the peak resident memory of the process in bytes, if available
+#[cfg(all(feature = "stats", any(target_os = "linux", target_os = "android")))]
+pub(crate) fn peak_memory() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let kib = status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmHWM:"))?
+        .trim()
+        .strip_suffix("kB")?
+        .trim()
+        .parse::<u64>()
+        .ok()?;
+
+    Some(kib * 1024)
+}
+
+/// Returns the peak resident memory of the process in bytes, if available
+#[cfg(all(
+    feature = "stats",
+    not(any(target_os = "linux", target_os = "android"))
+))]
+pub(crate) fn peak_memory() -> Option<u64> {
+    None
+}
+
+/// The set of regions of an old blob referenced by a patch.
+///
+/// # Examples
+///
+/// ```
+/// use ina::OldCoverage;
+///
+/// let mut coverage = OldCoverage::new();
+/// coverage.insert(10..20);
+/// coverage.insert(0..5);
+/// coverage.insert(15..30);
+///
+/// assert_eq!(coverage.ranges(), &[0..5, 10..30]);
+/// assert_eq!(coverage.covered_len(), 25);
+/// assert_eq!(coverage.fraction_of(100), 0.25);
+/// ```
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(remote = "Self")
+)]
+pub struct OldCoverage {
+    // Invariant: sorted, non-overlapping, non-adjacent, and non-empty
+    ranges: Vec<Range<u64>>,
+}
+
+impl OldCoverage {
+    /// Creates a new, empty coverage set
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Marks `range` of the old blob as referenced
+    pub fn insert(&mut self, range: Range<u64>) {
+        if range.is_empty() {
+            return;
+        }
+
+        // Merge `range` with every range it overlaps or touches. Since ranges are usually inserted
+        // in ascending order, this is typically an append.
+        let first = self.ranges.partition_point(|r| r.end < range.start);
+        let last = self.ranges.partition_point(|r| r.start <= range.end);
+        let merged = if first < last {
+            self.ranges[first].start.min(range.start)..self.ranges[last - 1].end.max(range.end)
+        } else {
+            range
+        };
+        self.ranges.splice(first..last, [merged]);
+    }
+
+    /// Returns the referenced ranges in ascending order, with overlapping ranges merged
+    pub fn ranges(&self) -> &[Range<u64>] {
+        &self.ranges
+    }
+
+    /// Returns the total number of referenced bytes
+    pub fn covered_len(&self) -> u64 {
+        self.ranges.iter().map(|r| r.end - r.start).sum()
+    }
+
+    /// Returns the fraction of an old blob of length `old_len` which is referenced
+    ///
+    /// Returns 0 if `old_len` is 0.
+    pub fn fraction_of(&self, old_len: u64) -> f64 {
+        if old_len == 0 {
+            0.0
+        } else {
+            self.covered_len() as f64 / old_len as f64
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for OldCoverage {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        Self::serialize(self, serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for OldCoverage {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        // Restore the invariant for ranges which weren't serialized from an `OldCoverage`
+        let mut coverage = Self::new();
+        for range in Self::deserialize(deserializer)?.ranges {
+            coverage.insert(range);
+        }
+
+        Ok(coverage)
+    }
+}
+
+/// Statistics about the seeks in an old blob made while applying a patch.
+///
+/// The old blob is read in the order of the patch's control records, starting at its beginning. A
+/// seek is any read which doesn't start where the previous one ended. This is useful for deciding
+/// whether a patch can be applied to an old blob which can't be seeked freely, e.g., one streamed
+/// from the network which only buffers a bounded amount of already read data for backward seeks.
+///
+/// # Examples
+///
+/// ```
+/// use ina::SeekStats;
+///
+/// let mut seeks = SeekStats::new();
+/// seeks.record_read(0..100);
+/// seeks.record_read(100..200);
+/// seeks.record_read(50..60);
+/// seeks.record_read(1000..1010);
+///
+/// assert_eq!(seeks.reads(), 4);
+/// assert_eq!(seeks.seeks(), 2);
+/// assert_eq!(seeks.max_backward(), 150);
+/// assert_eq!(seeks.max_forward(), 940);
+/// assert_eq!(seeks.total_distance(), 1090);
+/// ```
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SeekStats {
+    pos: u64,
+    reads: u64,
+    total_distance: u64,
+    max_backward: u64,
+    max_forward: u64,
+    backward: SeekHistogram,
+    forward: SeekHistogram,
+}
+
+impl SeekStats {
+    /// Creates a new `SeekStats` for an old blob which hasn't been read yet
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records that `range` of the old blob is read next
+    ///
+    /// Empty ranges are ignored since they don't require reading the old blob.
+    pub fn record_read(&mut self, range: Range<u64>) {
+        if range.is_empty() {
+            return;
+        }
+
+        if range.start < self.pos {
+            let distance = self.pos - range.start;
+            self.backward.insert(distance);
+            self.max_backward = self.max_backward.max(distance);
+            self.total_distance = self.total_distance.saturating_add(distance);
+        } else if range.start > self.pos {
+            let distance = range.start - self.pos;
+            self.forward.insert(distance);
+            self.max_forward = self.max_forward.max(distance);
+            self.total_distance = self.total_distance.saturating_add(distance);
+        }
+        self.reads += 1;
+        self.pos = range.end;
+    }
+
+    /// Returns the number of reads of the old blob
+    pub fn reads(&self) -> u64 {
+        self.reads
+    }
+
+    /// Returns the number of reads which don't start where the previous one ended
+    pub fn seeks(&self) -> u64 {
+        self.backward.count() + self.forward.count()
+    }
+
+    /// Returns the sum of the distances of all seeks in bytes
+    pub fn total_distance(&self) -> u64 {
+        self.total_distance
+    }
+
+    /// Returns the distance of the longest backward seek in bytes, or 0 if there are none
+    ///
+    /// An old blob which retains at least this many bytes before its current position can be
+    /// used to apply the patch without seeking backward in its source.
+    pub fn max_backward(&self) -> u64 {
+        self.max_backward
+    }
+
+    /// Returns the distance of the longest forward seek in bytes, or 0 if there are none
+    pub fn max_forward(&self) -> u64 {
+        self.max_forward
+    }
+
+    /// Returns the distribution of the distances of backward seeks
+    pub fn backward(&self) -> &SeekHistogram {
+        &self.backward
+    }
+
+    /// Returns the distribution of the distances of forward seeks
+    pub fn forward(&self) -> &SeekHistogram {
+        &self.forward
+    }
+}
+
+/// A distribution of seek distances with a bucket for each power of two.
+///
+/// See [`SeekStats`] for details.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SeekHistogram {
+    // Bucket `i` counts the distances in `2^i..2^(i + 1)`
+    #[cfg_attr(feature = "serde", serde(with = "buckets"))]
+    buckets: [u64; 64],
+}
+
+impl SeekHistogram {
+    fn insert(&mut self, distance: u64) {
+        self.buckets[distance.ilog2() as usize] += 1;
+    }
+
+    /// Returns the number of seeks in the distribution
+    pub fn count(&self) -> u64 {
+        self.buckets.iter().sum()
+    }
+
+    /// Returns the non-empty buckets in ascending order of distance, each as the range of
+    /// distances it covers and the number of seeks within it
+    pub fn buckets(&self) -> impl Iterator<Item = (RangeInclusive<u64>, u64)> + '_ {
+        self.buckets
+            .iter()
+            .enumerate()
+            .filter(|&(_, &count)| count > 0)
+            .map(|(i, &count)| {
+                let start = 1 << i;
+                (start..=start + (start - 1), count)
+            })
+    }
+}
+
+impl Default for SeekHistogram {
+    fn default() -> Self {
+        Self { buckets: [0; 64] }
+    }
+}
+
+/// (De)serialization of the buckets of a `SeekHistogram` as a sequence, since serde only supports
+/// arrays of up to 32 elements
+#[cfg(feature = "serde")]
+mod buckets {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer, de::Error};
+
+    pub(super) fn serialize<S>(buckets: &[u64; 64], serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        buckets.as_slice().serialize(serializer)
+    }
+
+    pub(super) fn deserialize<'de, D>(deserializer: D) -> Result<[u64; 64], D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Vec::<u64>::deserialize(deserializer)?
+            .try_into()
+            .map_err(|buckets: Vec<u64>| Error::invalid_length(buckets.len(), &"64 buckets"))
+    }
+}
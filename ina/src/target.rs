@@ -0,0 +1,150 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use std::fmt::{self, Display, Formatter};
+
+use integer_encoding::VarInt;
+
+/// A description of the platform an artifact is built for.
+///
+/// A patch can record the target of the new blob it produces so that it isn't applied on a device
+/// it wasn't built for, e.g., an arm64 build on an x86 device. To record it, pass a `Target` to
+/// [`DiffConfig::target()`]. It can later be retrieved via [`PatchMetadata::target()`] and
+/// enforced with [`Patcher::expect_target()`].
+///
+/// Each part of a `Target` is optional and free-form, so any naming scheme can be used as long as
+/// the diffing and patching sides agree on it.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use std::io::Cursor;
+/// use ina::{DiffConfig, PatchError, Patcher, Target};
+///
+/// let arm64 = Target::new(Some("android".into()), Some("arm64-v8a".into()), Some(42));
+/// let mut patch = Vec::new();
+/// let old = ina::OldBlob::from_slice(b"Hello");
+/// ina::diff_with_config(&old, b"Hero", &mut patch, DiffConfig::new().target(Some(arm64)))?;
+///
+/// let x86_64 = Target::new(Some("android".into()), Some("x86_64".into()), None);
+/// let result = Patcher::new(Cursor::new(b"Hello"), patch.as_slice())?.expect_target(&x86_64);
+/// assert!(matches!(result, Err(PatchError::TargetMismatch(_))));
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`DiffConfig::target()`]: crate::DiffConfig::target
+/// [`PatchMetadata::target()`]: crate::PatchMetadata::target
+/// [`Patcher::expect_target()`]: crate::Patcher::expect_target
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Target {
+    platform: Option<String>,
+    abi: Option<String>,
+    version_code: Option<u64>,
+}
+
+impl Target {
+    /// Creates a new `Target` from its parts.
+    ///
+    /// `platform` names the operating system or platform, such as `android` or `linux`. `abi`
+    /// names the application binary interface, such as `arm64-v8a` or `x86_64-unknown-linux-gnu`.
+    /// `version_code` is the version of the artifact.
+    pub fn new(platform: Option<String>, abi: Option<String>, version_code: Option<u64>) -> Self {
+        Self {
+            platform,
+            abi,
+            version_code,
+        }
+    }
+
+    /// Returns the platform of the target, if recorded
+    pub fn platform(&self) -> Option<&str> {
+        self.platform.as_deref()
+    }
+
+    /// Returns the ABI of the target, if recorded
+    pub fn abi(&self) -> Option<&str> {
+        self.abi.as_deref()
+    }
+
+    /// Returns the version code of the artifact, if recorded
+    pub fn version_code(&self) -> Option<u64> {
+        self.version_code
+    }
+
+    /// Returns whether every part of `expected` which is set is recorded with the same value in
+    /// this target
+    #[cfg(feature = "patch")]
+    pub(crate) fn satisfies(&self, expected: &Target) -> bool {
+        fn part_satisfies<T: PartialEq>(actual: &Option<T>, expected: &Option<T>) -> bool {
+            expected.is_none() || actual == expected
+        }
+
+        part_satisfies(&self.platform, &expected.platform)
+            && part_satisfies(&self.abi, &expected.abi)
+            && part_satisfies(&self.version_code, &expected.version_code)
+    }
+
+    #[cfg(feature = "diff")]
+    pub(crate) fn encode_platform(&self) -> Option<Vec<u8>> {
+        self.platform
+            .as_ref()
+            .map(|platform| platform.as_bytes().to_vec())
+    }
+
+    #[cfg(feature = "diff")]
+    pub(crate) fn encode_abi(&self) -> Option<Vec<u8>> {
+        self.abi.as_ref().map(|abi| abi.as_bytes().to_vec())
+    }
+
+    #[cfg(feature = "diff")]
+    pub(crate) fn encode_version_code(&self) -> Option<Vec<u8>> {
+        self.version_code
+            .map(|version_code| version_code.encode_var_vec())
+    }
+
+    #[cfg(feature = "patch")]
+    pub(crate) fn decode_platform(&mut self, value: &[u8]) -> Option<()> {
+        self.platform = Some(String::from_utf8(value.to_vec()).ok()?);
+
+        Some(())
+    }
+
+    #[cfg(feature = "patch")]
+    pub(crate) fn decode_abi(&mut self, value: &[u8]) -> Option<()> {
+        self.abi = Some(String::from_utf8(value.to_vec()).ok()?);
+
+        Some(())
+    }
+
+    #[cfg(feature = "patch")]
+    pub(crate) fn decode_version_code(&mut self, value: &[u8]) -> Option<()> {
+        let (version_code, _) = u64::decode_var(value)?;
+        self.version_code = Some(version_code);
+
+        Some(())
+    }
+}
+
+impl Display for Target {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let parts = [
+            self.platform
+                .as_ref()
+                .map(|platform| format!("platform {platform}")),
+            self.abi.as_ref().map(|abi| format!("ABI {abi}")),
+            self.version_code
+                .map(|version_code| format!("version code {version_code}")),
+        ];
+        let parts: Vec<_> = parts.into_iter().flatten().collect();
+
+        if parts.is_empty() {
+            write!(f, "unspecified target")
+        } else {
+            write!(f, "{}", parts.join(", "))
+        }
+    }
+}
@@ -0,0 +1,242 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! I/O wrappers which fail at configurable offsets, for testing how code handles I/O errors.
+//!
+//! Embedders of Ina typically read patches from the network and write new blobs to storage, either
+//! of which can fail at any point. [`FaultyIo`] wraps a reader, writer, or seekable stream and
+//! injects an error of a chosen [`ErrorKind`] once a chosen number of bytes has passed through it,
+//! so that the failure paths of diffing and patching, and of the code driving them, can be tested
+//! deterministically.
+//!
+//! # Examples
+//!
+//! ```
+//! use std::io::{self, Cursor, ErrorKind};
+//! use ina::{
+//!     OldBlob, Patcher,
+//!     test_util::{Fault, FaultyIo},
+//! };
+//!
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let old: Vec<u8> = (0..10_000).map(|i: u32| (i.wrapping_mul(0x9e3779b9) >> 24) as u8).collect();
+//! let mut new = old[5000..].to_vec();
+//! new.extend_from_slice(&old[..5000]);
+//! let mut patch = Vec::new();
+//! ina::diff(&OldBlob::from_slice(&old), &new, &mut patch)?;
+//!
+//! // Fail reading the old blob halfway through
+//! let old = FaultyIo::new(Cursor::new(&old)).fail_reads(Fault::at(5000, ErrorKind::BrokenPipe));
+//! let mut patcher = Patcher::new(old, patch.as_slice())?;
+//! let error = io::copy(&mut patcher, &mut io::sink()).unwrap_err();
+//! assert_eq!(error.kind(), ErrorKind::BrokenPipe);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::io::{self, BufRead, ErrorKind, Read, Seek, SeekFrom, Write};
+
+/// An error to inject into the operations of a [`FaultyIo`] at an offset.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Fault {
+    offset: u64,
+    kind: ErrorKind,
+    once: bool,
+}
+
+impl Fault {
+    /// Creates a fault which fails every operation reaching `offset` with an error of `kind`
+    pub const fn at(offset: u64, kind: ErrorKind) -> Self {
+        Self {
+            offset,
+            kind,
+            once: false,
+        }
+    }
+
+    /// Makes the fault fail only the first operation reaching its offset, after which operations
+    /// succeed again, like a transient error
+    pub const fn once(mut self) -> Self {
+        self.once = true;
+        self
+    }
+}
+
+/// A wrapper around a reader, writer, or seekable stream which injects errors at offsets.
+///
+/// The offset of a `FaultyIo` starts at 0 and advances with every byte read or written. Seeks set
+/// it to the position they return, so seekable streams should be wrapped at their start. A read or
+/// write which would cross the offset of its fault is shortened to end there, so the fault is
+/// reached exactly, and the operations reaching the offset fail instead of touching the inner
+/// stream. A seek fails without moving the inner stream if it targets a position at or past the
+/// offset of the seek fault, or if it's relative to the end of the stream, whose position isn't
+/// known in advance.
+#[derive(Debug)]
+pub struct FaultyIo<T> {
+    inner: T,
+    offset: u64,
+    read_fault: Option<Fault>,
+    write_fault: Option<Fault>,
+    seek_fault: Option<Fault>,
+    injected: u32,
+}
+
+impl<T> FaultyIo<T> {
+    /// Wraps `inner` without injecting any errors yet
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner,
+            offset: 0,
+            read_fault: None,
+            write_fault: None,
+            seek_fault: None,
+            injected: 0,
+        }
+    }
+
+    /// Injects `fault` into reads, returning the wrapper
+    pub fn fail_reads(mut self, fault: Fault) -> Self {
+        self.read_fault = Some(fault);
+        self
+    }
+
+    /// Injects `fault` into writes, returning the wrapper
+    pub fn fail_writes(mut self, fault: Fault) -> Self {
+        self.write_fault = Some(fault);
+        self
+    }
+
+    /// Injects `fault` into seeks, returning the wrapper
+    pub fn fail_seeks(mut self, fault: Fault) -> Self {
+        self.seek_fault = Some(fault);
+        self
+    }
+
+    /// Returns the number of bytes read or written so far, adjusted by seeks
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Returns the number of errors injected so far
+    pub fn injected(&self) -> u32 {
+        self.injected
+    }
+
+    /// Returns a reference to the inner stream
+    pub fn get_ref(&self) -> &T {
+        &self.inner
+    }
+
+    /// Returns the inner stream
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    /// Returns the number of bytes a read of `len` bytes may pass through before reaching its
+    /// fault, or the error to fail it with if it has been reached
+    fn read_limit(&mut self, len: usize) -> io::Result<usize> {
+        limit(&mut self.read_fault, self.offset, len, &mut self.injected)
+    }
+}
+
+/// Returns the number of bytes an operation of `len` bytes at `offset` may pass through before
+/// reaching `fault`, or the error to fail it with if it has been reached, counting it in `injected`
+fn limit(
+    fault: &mut Option<Fault>,
+    offset: u64,
+    len: usize,
+    injected: &mut u32,
+) -> io::Result<usize> {
+    let Some(armed) = *fault else {
+        return Ok(len);
+    };
+    if offset < armed.offset {
+        let remaining = usize::try_from(armed.offset - offset).unwrap_or(usize::MAX);
+        return Ok(len.min(remaining));
+    }
+
+    // Empty operations never reach the offset
+    if len == 0 {
+        return Ok(0);
+    }
+    fail(fault, injected)
+}
+
+/// Returns the error of `fault`, counting it in `injected` and disarming `fault` if it fails only
+/// once
+fn fail<V>(fault: &mut Option<Fault>, injected: &mut u32) -> io::Result<V> {
+    let Some(armed) = *fault else {
+        unreachable!("only armed faults fail");
+    };
+    if armed.once {
+        *fault = None;
+    }
+    *injected += 1;
+
+    Err(io::Error::new(armed.kind, "injected fault"))
+}
+
+impl<T: Read> Read for FaultyIo<T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.read_limit(buf.len())?;
+        let read = self.inner.read(&mut buf[..len])?;
+        self.offset += read as u64;
+
+        Ok(read)
+    }
+}
+
+impl<T: BufRead> BufRead for FaultyIo<T> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        // Filling the buffer counts as reading from the offset, but the bytes only pass through
+        // once they're consumed
+        let len = self.read_limit(usize::MAX)?;
+        let buf = self.inner.fill_buf()?;
+
+        Ok(&buf[..buf.len().min(len)])
+    }
+
+    fn consume(&mut self, amount: usize) {
+        self.inner.consume(amount);
+        self.offset += amount as u64;
+    }
+}
+
+impl<T: Write> Write for FaultyIo<T> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = limit(
+            &mut self.write_fault,
+            self.offset,
+            buf.len(),
+            &mut self.injected,
+        )?;
+        let written = self.inner.write(&buf[..len])?;
+        self.offset += written as u64;
+
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<T: Seek> Seek for FaultyIo<T> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        if let Some(fault) = self.seek_fault {
+            let target = match pos {
+                SeekFrom::Start(target) => Some(target),
+                SeekFrom::Current(delta) => Some(self.offset.saturating_add_signed(delta)),
+                SeekFrom::End(_) => None,
+            };
+            if target.is_none_or(|target| target >= fault.offset) {
+                return fail(&mut self.seek_fault, &mut self.injected);
+            }
+        }
+
+        self.offset = self.inner.seek(pos)?;
+
+        Ok(self.offset)
+    }
+}
@@ -0,0 +1,322 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use std::ops::Range;
+
+#[cfg(feature = "patch")]
+use integer_encoding::VarInt;
+#[cfg(feature = "diff")]
+use integer_encoding::VarIntWriter;
+
+#[cfg(feature = "diff")]
+use crate::bsdiff::Match;
+
+/// How the diffing algorithm treats its inputs.
+///
+/// In text mode, regions of the new blob stored as differences are extended to end at line
+/// boundaries where possible, so verbatim regions tend to hold whole lines, and the patch records
+/// [`TextHints`] describing which lines changed. The hints can be read via
+/// [`PatchMetadata::text_hints()`] to summarize a patch for review without the old or new blob.
+///
+/// [`PatchMetadata::text_hints()`]: crate::PatchMetadata::text_hints
+#[cfg(feature = "diff")]
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq, PartialOrd, Ord)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum TextMode {
+    /// Treat the inputs as binary
+    #[default]
+    Binary,
+    /// Treat the inputs as text if both of them look like text, i.e., are valid UTF-8 without
+    /// control characters other than whitespace
+    Auto,
+    /// Always treat the inputs as text
+    Text,
+}
+
+/// The lines of the old and new blob covered by one changed region.
+///
+/// Lines are counted from 0 and are terminated by `\n`. Either range may be empty: an empty old
+/// range marks lines inserted before that line of the old blob, and an empty new range marks lines
+/// of the old blob removed before that line of the new blob.
+#[derive(Clone, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LineChange {
+    // Stored as pairs since ranges don't implement `Ord`
+    old_lines: (u64, u64),
+    new_lines: (u64, u64),
+}
+
+impl LineChange {
+    /// Creates a new `LineChange` from the changed lines of the old and new blob
+    pub fn new(old_lines: Range<u64>, new_lines: Range<u64>) -> Self {
+        Self {
+            old_lines: (old_lines.start, old_lines.end),
+            new_lines: (new_lines.start, new_lines.end),
+        }
+    }
+
+    /// Returns the changed lines of the old blob
+    pub fn old_lines(&self) -> Range<u64> {
+        self.old_lines.0..self.old_lines.1
+    }
+
+    /// Returns the changed lines of the new blob
+    pub fn new_lines(&self) -> Range<u64> {
+        self.new_lines.0..self.new_lines.1
+    }
+}
+
+/// A line-oriented summary of the changes made by a text patch.
+///
+/// Patches created in [`TextMode::Text`] (or [`TextMode::Auto`] with text inputs) record which
+/// lines changed, ordered by their position in the new blob. Changes close to each other may be
+/// merged and the changes are approximate where the diffing algorithm reorders regions of the old
+/// blob, so the hints are intended for display rather than for reconstructing either blob.
+///
+/// To keep the header small, at most [`TextHints::MAX_CHANGES`] changes are recorded.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use ina::{DiffConfig, OldBlob, TextMode};
+///
+/// let old = OldBlob::from_slice(b"name = app\nversion = 1\nchannel = stable\n");
+/// let new = b"name = app\nversion = 2\nchannel = stable\n";
+/// let mut patch = Vec::new();
+/// ina::diff_with_config(&old, new, &mut patch, DiffConfig::new().text_mode(TextMode::Auto))?;
+///
+/// let metadata = ina::read_header(&mut patch.as_slice())?;
+/// let hints = metadata.text_hints().unwrap();
+/// assert_eq!(hints.changes().len(), 1);
+/// assert_eq!(hints.changes()[0].new_lines(), 1..2);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TextHints {
+    changes: Vec<LineChange>,
+    truncated: bool,
+}
+
+impl TextHints {
+    /// The maximum number of changes recorded in a patch
+    pub const MAX_CHANGES: usize = 1024;
+
+    /// Returns the changed regions, ordered by their position in the new blob
+    pub fn changes(&self) -> &[LineChange] {
+        &self.changes
+    }
+
+    /// Returns whether changes were left out because there were more than
+    /// [`TextHints::MAX_CHANGES`]
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    /// Computes the changed lines from the matches used to create a patch
+    ///
+    /// `old` must not include the sentinel.
+    #[cfg(feature = "diff")]
+    pub(crate) fn from_matches(old: &[u8], new: &[u8], matches: &[Match]) -> Self {
+        let old_lines = LineIndex::new(old);
+        let new_lines = LineIndex::new(new);
+        let mut hints = Self::default();
+        let mut push = |old_range: Range<usize>, new_range: Range<usize>| {
+            hints.push(LineChange::new(
+                old_lines.lines(old_range),
+                new_lines.lines(new_range),
+            ));
+        };
+
+        let mut old_pos = 0;
+        for m in matches {
+            let (old_start, new_start) = (m.add_old_pos(), m.add_new_pos());
+            // Skipping forward in the old blob removes the skipped region. If the region doesn't
+            // begin at a line boundary, slide it forward as long as it describes the same change
+            // so that it covers whole lines where possible.
+            if old_start > old_pos {
+                let (mut start, mut end, mut at) = (old_pos, old_start, new_start);
+                while start > 0
+                    && old[start - 1] != b'\n'
+                    && old.get(end) == Some(&old[start])
+                    && at < new.len()
+                {
+                    (start, end, at) = (start + 1, end + 1, at + 1);
+                }
+                push(start..end, at..at);
+            }
+
+            let differs = |i: usize| old.get(old_start + i) != Some(&new[new_start + i]);
+            let mut i = 0;
+            while i < m.add_len() {
+                if differs(i) {
+                    let run_start = i;
+                    while i < m.add_len() && differs(i) {
+                        i += 1;
+                    }
+                    push(
+                        old_start + run_start..old_start + i,
+                        new_start + run_start..new_start + i,
+                    );
+                } else {
+                    i += 1;
+                }
+            }
+
+            old_pos = old_start + m.add_len();
+            let copy_start = new_start + m.add_len();
+            if copy_start < m.copy_end() {
+                push(old_pos..old_pos, copy_start..m.copy_end());
+            }
+        }
+        if old_pos < old.len() {
+            push(old_pos..old.len(), new.len()..new.len());
+        }
+
+        hints
+    }
+
+    /// Adds a change, merging it into the previous one if their new lines overlap or touch
+    #[cfg(feature = "diff")]
+    fn push(&mut self, change: LineChange) {
+        if let Some(last) = self.changes.last_mut()
+            && change.new_lines.0 <= last.new_lines.1
+        {
+            last.old_lines = (
+                last.old_lines.0.min(change.old_lines.0),
+                last.old_lines.1.max(change.old_lines.1),
+            );
+            last.new_lines.1 = last.new_lines.1.max(change.new_lines.1);
+        } else if self.changes.len() < Self::MAX_CHANGES {
+            self.changes.push(change);
+        } else {
+            self.truncated = true;
+        }
+    }
+
+    /// Encodes the hints as a header field
+    ///
+    /// The field consists of a varint which is 1 if the hints are truncated and 0 otherwise,
+    /// followed by four varints for each change: the number of new lines between the end of the
+    /// previous change and its start, the number of new lines it covers, the first old line it
+    /// covers, and the number of old lines it covers.
+    #[cfg(feature = "diff")]
+    pub(crate) fn encode_field(&self) -> Vec<u8> {
+        let mut field = Vec::new();
+        // Writing to a Vec never fails
+        field.write_varint(u64::from(self.truncated)).unwrap();
+        let mut new_pos = 0;
+        for change in &self.changes {
+            let ((old_start, old_end), (new_start, new_end)) = (change.old_lines, change.new_lines);
+            field.write_varint(new_start - new_pos).unwrap();
+            field.write_varint(new_end - new_start).unwrap();
+            field.write_varint(old_start).unwrap();
+            field.write_varint(old_end - old_start).unwrap();
+            new_pos = new_end;
+        }
+
+        field
+    }
+
+    /// Decodes the hints from a header field, returning `None` if the field is invalid
+    #[cfg(feature = "patch")]
+    pub(crate) fn decode_field(mut field: &[u8]) -> Option<Self> {
+        let truncated = match read_varint(&mut field)? {
+            0 => false,
+            1 => true,
+            _ => return None,
+        };
+        let mut changes = Vec::new();
+        let mut new_pos = 0u64;
+        while !field.is_empty() {
+            let new_start = new_pos.checked_add(read_varint(&mut field)?)?;
+            let new_end = new_start.checked_add(read_varint(&mut field)?)?;
+            let old_start = read_varint(&mut field)?;
+            let old_end = old_start.checked_add(read_varint(&mut field)?)?;
+            changes.push(LineChange::new(old_start..old_end, new_start..new_end));
+            new_pos = new_end;
+        }
+
+        Some(Self { changes, truncated })
+    }
+}
+
+/// Reads a varint from the beginning of `field`, advancing it past the varint
+#[cfg(feature = "patch")]
+fn read_varint(field: &mut &[u8]) -> Option<u64> {
+    let (value, len) = u64::decode_var(field)?;
+    *field = &field[len..];
+
+    Some(value)
+}
+
+/// Returns whether `data` looks like text, i.e., is valid UTF-8 without control characters other
+/// than whitespace
+#[cfg(feature = "diff")]
+pub(crate) fn looks_like_text(data: &[u8]) -> bool {
+    std::str::from_utf8(data).is_ok_and(|text| {
+        text.chars()
+            .all(|c| !c.is_control() || matches!(c, '\t' | '\n' | '\r' | '\x0c'))
+    })
+}
+
+/// Extends the add region of `m` to the end of the line it ends in if that line ends within the
+/// match in `new` and the old blob, whose length is `old_len`, is long enough
+#[cfg(feature = "diff")]
+pub(crate) fn align_to_lines(m: Match, old_len: usize, new: &[u8]) -> Match {
+    let add_end = m.add_new_pos() + m.add_len();
+    if m.add_len() == 0 || new[add_end - 1] == b'\n' {
+        return m;
+    }
+
+    new[add_end..m.copy_end()]
+        .iter()
+        .position(|&byte| byte == b'\n')
+        .map(|i| m.add_len() + i + 1)
+        .filter(|&add_len| add_len <= old_len.saturating_sub(m.add_old_pos()))
+        .map_or(m, |add_len| {
+            Match::new(m.add_old_pos(), m.add_new_pos(), add_len, m.copy_end())
+        })
+}
+
+/// The positions of the line breaks in a blob
+#[cfg(feature = "diff")]
+struct LineIndex {
+    breaks: Vec<usize>,
+}
+
+#[cfg(feature = "diff")]
+impl LineIndex {
+    fn new(data: &[u8]) -> Self {
+        Self {
+            breaks: data
+                .iter()
+                .enumerate()
+                .filter_map(|(i, &byte)| (byte == b'\n').then_some(i))
+                .collect(),
+        }
+    }
+
+    /// Returns the line containing the byte at `pos`
+    fn line(&self, pos: usize) -> u64 {
+        self.breaks.partition_point(|&i| i < pos) as u64
+    }
+
+    /// Returns the lines containing the bytes in `range`, or the empty range at the line
+    /// containing `range.start` if `range` is empty
+    fn lines(&self, range: Range<usize>) -> Range<u64> {
+        let start = self.line(range.start);
+        if range.is_empty() {
+            start..start
+        } else {
+            start..self.line(range.end - 1) + 1
+        }
+    }
+}
@@ -0,0 +1,92 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use std::io::{Read, Write};
+
+use crate::{
+    PatchError,
+    control::ControlReader,
+    diff::DiffConfig,
+    stats::DiffStats,
+    writer::{PatchWriter, Recorded},
+};
+
+/// Recompresses a patch with new compression settings without diffing again
+///
+/// The control records of `patch` are decoded and re-encoded into `out` according to the
+/// compression and forward error correction settings of `options`, so the resulting patch
+/// reconstructs exactly the same new blob from the same old blob. This allows producing several
+/// variants of a patch from a single, potentially expensive diff, e.g., one compressed at the
+/// highest level for delivery over metered connections and one which decodes quickly.
+///
+/// The new blob length, file metadata, target, provenance, text hints, and base digest recorded in
+/// `patch` are carried over, overriding those set in `options`. Settings of `options` which only
+/// affect matching are ignored. If `patch` records the settings it was created with, the
+/// transcoded patch records the new compression settings along with the original matcher
+/// settings. Header fields this version of the crate doesn't understand aren't carried over.
+///
+/// The returned statistics describe the transcoded patch. Since the old blob isn't available, its
+/// length is reported as 0.
+///
+/// # Errors
+///
+/// Returns an error if `patch` is invalid or if an I/O error occurs while reading `patch` or
+/// writing `out`.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use std::io::Cursor;
+/// use ina::DiffConfig;
+///
+/// let mut patch = Vec::new();
+/// let old = ina::OldBlob::from_slice(b"Hello");
+/// ina::diff_with_config(&old, b"Hero", &mut patch, DiffConfig::new().compression_level(3))?;
+///
+/// let mut transcoded = Vec::new();
+/// ina::transcode(patch.as_slice(), &mut transcoded, DiffConfig::new().compression_level(22))?;
+///
+/// let mut new = Vec::new();
+/// ina::patch(Cursor::new(b"Hello"), transcoded.as_slice(), &mut new)?;
+/// assert_eq!(new, b"Hero");
+/// # Ok(())
+/// # }
+/// ```
+pub fn transcode<P, W>(patch: P, out: W, options: &DiffConfig) -> Result<DiffStats, PatchError>
+where
+    P: Read,
+    W: Write,
+{
+    let mut records = ControlReader::new(patch)?;
+
+    let mut options = options.clone();
+    options
+        .file_metadata(records.metadata().file_metadata())
+        .target(records.metadata().target().cloned())
+        .provenance(records.metadata().provenance().cloned())
+        .record_settings(records.metadata().diff_settings().is_some())
+        .record_new_len(records.metadata().new_len().is_some());
+    if let Some(settings) = records.metadata().diff_settings() {
+        // The match threshold doesn't affect transcoding, only the recorded settings
+        options.match_threshold(usize::try_from(settings.match_threshold()).unwrap_or(usize::MAX));
+    }
+
+    let metadata = records.metadata();
+    let recorded = Recorded {
+        new_len: metadata.new_len(),
+        text_hints: metadata.text_hints(),
+        base_digest: metadata.base_digest(),
+        block_map: metadata.block_map(),
+        #[cfg(feature = "verify")]
+        blob_digests: metadata.blob_digests(),
+    };
+    let mut writer = PatchWriter::with_digests(out, &options, &recorded)?;
+    for record in &mut records {
+        let record = record?;
+        writer.write_record(record.add(), record.copy(), record.seek())?;
+    }
+
+    Ok(writer.finish_with_stats().map(|(_, stats)| stats)?)
+}
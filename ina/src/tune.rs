@@ -0,0 +1,287 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use std::{
+    io,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    OldBlob,
+    diff::{DiffConfig, diff_with_config},
+};
+
+/// A matrix of diff parameters to evaluate when tuning.
+///
+/// Every combination of the configured compression levels, window sizes, and match thresholds is
+/// tried by [`tune()`], so the number of diff operations performed is the product of the lengths
+/// of each parameter list multiplied by the number of pairs in the corpus.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct TuneMatrix {
+    compression_levels: Vec<i32>,
+    window_logs: Vec<Option<u32>>,
+    match_thresholds: Vec<usize>,
+    compression_threads: u32,
+}
+
+impl TuneMatrix {
+    /// Creates a new tuning matrix with a small set of sensible defaults
+    pub fn new() -> Self {
+        Self {
+            compression_levels: vec![3, 9, DiffConfig::DEFAULT_COMPRESSION_LEVEL],
+            window_logs: vec![None],
+            match_thresholds: vec![4, DiffConfig::DEFAULT_MATCH_THRESHOLD, 16],
+            compression_threads: DiffConfig::DEFAULT_COMPRESSION_THREADS,
+        }
+    }
+
+    /// Sets the compression levels to evaluate.
+    ///
+    /// See [`DiffConfig::compression_level()`] for the meaning of each value.
+    pub fn compression_levels(&mut self, levels: impl Into<Vec<i32>>) -> &mut Self {
+        self.compression_levels = levels.into();
+        self
+    }
+
+    /// Sets the compression window sizes to evaluate.
+    ///
+    /// See [`DiffConfig::window_log()`] for the meaning of each value.
+    pub fn window_logs(&mut self, window_logs: impl Into<Vec<Option<u32>>>) -> &mut Self {
+        self.window_logs = window_logs.into();
+        self
+    }
+
+    /// Sets the matcher mismatch thresholds to evaluate.
+    ///
+    /// See [`DiffConfig::match_threshold()`] for the meaning of each value.
+    pub fn match_thresholds(&mut self, thresholds: impl Into<Vec<usize>>) -> &mut Self {
+        self.match_thresholds = thresholds.into();
+        self
+    }
+
+    /// Sets the number of compression threads used for every evaluated configuration.
+    ///
+    /// This value is held constant rather than tuned because it trades memory for speed without
+    /// affecting patch size.
+    pub fn compression_threads(&mut self, threads: u32) -> &mut Self {
+        self.compression_threads = threads;
+        self
+    }
+
+    fn parameters(&self) -> impl Iterator<Item = (i32, Option<u32>, usize)> + '_ {
+        self.compression_levels.iter().flat_map(move |&level| {
+            self.window_logs.iter().flat_map(move |&window_log| {
+                self.match_thresholds
+                    .iter()
+                    .map(move |&threshold| (level, window_log, threshold))
+            })
+        })
+    }
+}
+
+impl Default for TuneMatrix {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The measured result of diffing a corpus with a single configuration.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct TuneResult {
+    config: DiffConfig,
+    compression_level: i32,
+    window_log: Option<u32>,
+    match_threshold: usize,
+    patch_size: u64,
+    elapsed: Duration,
+}
+
+impl TuneResult {
+    /// Returns the configuration that produced this result
+    pub fn config(&self) -> &DiffConfig {
+        &self.config
+    }
+
+    /// Returns the compression level used
+    pub fn compression_level(&self) -> i32 {
+        self.compression_level
+    }
+
+    /// Returns the compression window size used, if one was set
+    pub fn window_log(&self) -> Option<u32> {
+        self.window_log
+    }
+
+    /// Returns the matcher mismatch threshold used
+    pub fn match_threshold(&self) -> usize {
+        self.match_threshold
+    }
+
+    /// Returns the total size in bytes of all patches generated for the corpus
+    pub fn patch_size(&self) -> u64 {
+        self.patch_size
+    }
+
+    /// Returns the total time spent generating all patches for the corpus
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    fn dominates(&self, other: &Self) -> bool {
+        self.patch_size <= other.patch_size
+            && self.elapsed <= other.elapsed
+            && (self.patch_size < other.patch_size || self.elapsed < other.elapsed)
+    }
+}
+
+/// Finds the Pareto-optimal diff configurations for a corpus
+///
+/// Each element of `corpus` is a pair of old and new blobs representative of the inputs the
+/// resulting configuration will be used with.
+///
+/// Every configuration in `matrix` is used to diff every pair in the corpus, measuring the total
+/// patch size and time taken. The configurations for which no other configuration produces both
+/// smaller patches and runs faster are returned, sorted by ascending patch size. At least one
+/// configuration is always returned, and the first is the one producing the smallest patches.
+///
+/// # Errors
+///
+/// Returns an error of kind [`InvalidInput`](io::ErrorKind::InvalidInput) if any parameter list of
+/// `matrix` is empty, so that it contains no configurations, or if an I/O error occurs while
+/// generating a patch.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> std::io::Result<()> {
+/// use ina::{OldBlob, TuneMatrix};
+///
+/// let (hello, fish) = (OldBlob::from_slice(b"Hello"), OldBlob::from_slice(b"Red fish"));
+/// let corpus: &[(&OldBlob, &[u8])] = &[(&hello, b"Hero"), (&fish, b"Blue fish")];
+/// let mut matrix = TuneMatrix::new();
+/// matrix.compression_levels([1, 3]).compression_threads(0);
+///
+/// let results = ina::tune(corpus, &matrix)?;
+/// let best = results[0].config();
+///
+/// # Ok(())
+/// # }
+/// ```
+pub fn tune(corpus: &[(&OldBlob, &[u8])], matrix: &TuneMatrix) -> io::Result<Vec<TuneResult>> {
+    if matrix.parameters().next().is_none() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "tuning matrix has an empty parameter list",
+        ));
+    }
+
+    let mut results = Vec::new();
+
+    for (compression_level, window_log, match_threshold) in matrix.parameters() {
+        let mut config = DiffConfig::new();
+        config
+            .compression_threads(matrix.compression_threads)
+            .compression_level(compression_level)
+            .window_log(window_log)
+            .match_threshold(match_threshold);
+
+        let mut patch_size = 0;
+        let mut elapsed = Duration::ZERO;
+
+        for (old, new) in corpus {
+            let start = Instant::now();
+            let stats = diff_with_config(old, new, &mut io::sink(), &config)?;
+            elapsed += start.elapsed();
+
+            patch_size += stats.patch_len();
+        }
+
+        results.push(TuneResult {
+            config,
+            compression_level,
+            window_log,
+            match_threshold,
+            patch_size,
+            elapsed,
+        });
+    }
+
+    Ok(pareto_front(&results))
+}
+
+/// Returns the results which no other result dominates, sorted by ascending patch size and then by
+/// time taken
+fn pareto_front(results: &[TuneResult]) -> Vec<TuneResult> {
+    let mut front: Vec<_> = results
+        .iter()
+        .filter(|candidate| !results.iter().any(|other| other.dominates(candidate)))
+        .cloned()
+        .collect();
+    front.sort_by_key(|result| (result.patch_size, result.elapsed));
+
+    front
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(patch_size: u64, elapsed_ms: u64) -> TuneResult {
+        TuneResult {
+            config: DiffConfig::new(),
+            compression_level: 0,
+            window_log: None,
+            match_threshold: 0,
+            patch_size,
+            elapsed: Duration::from_millis(elapsed_ms),
+        }
+    }
+
+    #[test]
+    fn dominance() {
+        let base = result(100, 10);
+        assert!(result(99, 10).dominates(&base));
+        assert!(result(100, 9).dominates(&base));
+        assert!(result(50, 5).dominates(&base));
+        // Results don't dominate themselves, nor results which are better in either respect
+        assert!(!base.dominates(&base));
+        assert!(!result(99, 11).dominates(&base));
+        assert!(!result(101, 9).dominates(&base));
+    }
+
+    #[test]
+    fn front_is_sorted_by_patch_size() {
+        let results = [
+            result(300, 5),
+            result(200, 20),
+            // Dominated by the result of size 200
+            result(250, 25),
+            result(100, 40),
+            // Dominated by the result of size 100, which is as large but faster
+            result(100, 50),
+            result(150, 30),
+        ];
+
+        let front: Vec<_> = pareto_front(&results)
+            .iter()
+            .map(|result| (result.patch_size, result.elapsed.as_millis()))
+            .collect();
+        assert_eq!(front, [(100, 40), (150, 30), (200, 20), (300, 5)]);
+    }
+
+    #[test]
+    fn empty_parameter_lists_are_rejected() {
+        let old = OldBlob::from_slice(b"Hello");
+        let corpus: &[(&OldBlob, &[u8])] = &[(&old, b"Hero")];
+
+        let mut matrices = [TuneMatrix::new(), TuneMatrix::new(), TuneMatrix::new()];
+        matrices[0].compression_levels([]);
+        matrices[1].window_logs([]);
+        matrices[2].match_thresholds([]);
+        for matrix in &matrices {
+            let error = tune(corpus, matrix).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        }
+    }
+}
@@ -0,0 +1,36 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Experimental interfaces.
+//!
+//! This module is only available with the `unstable` feature enabled. The interfaces it contains
+//! are under active development and are NOT covered by this crate's semver guarantees, but are
+//! provided so that they can be evaluated without depending on a git revision of this crate.
+//!
+//! Interfaces are grouped into versioned submodules. When an experimental interface needs to
+//! change incompatibly, the new version is added to a new submodule while the previous one is kept
+//! for a transition period, so that upgrading this crate doesn't immediately break code using
+//! experimental interfaces.
+
+/// The first iteration of experimental interfaces.
+///
+/// This version contains:
+///
+/// - Access to the control stream of a patch via [`ControlReader`](v0::ControlReader)
+/// - Patch encoding of externally computed matches via
+///   [`diff_with_matches()`](v0::diff_with_matches)
+/// - Pluggable matching algorithms via the [`Matcher`](v0::Matcher) trait and
+///   [`diff_with_matcher()`](v0::diff_with_matcher)
+///
+/// A preview of a second version of the patch format isn't part of this version. It's left for a
+/// later one, once there are format changes to preview.
+pub mod v0 {
+    #[cfg(feature = "patch")]
+    pub use crate::control::{ControlReader, ControlRecord};
+    #[cfg(feature = "diff")]
+    pub use crate::{
+        bsdiff::{BsdiffMatcher, Match, Matcher},
+        diff::{diff_with_matcher, diff_with_matches},
+    };
+}
@@ -0,0 +1,215 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use std::{
+    hint,
+    io::{self, BufRead, ErrorKind, Read, Seek},
+};
+
+use crate::{DIGEST_LEN, DigestAlgorithm, PatchError, Patcher};
+
+/// The size of the buffers used for comparing blobs
+const BUF_SIZE: usize = 8192;
+
+/// Options for a verification operation.
+///
+/// The defaults favor speed. Services verifying patches from untrusted sources should consider
+/// enabling [`VerifyOptions::constant_time()`].
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct VerifyOptions {
+    constant_time: bool,
+    digest_algorithm: DigestAlgorithm,
+}
+
+impl VerifyOptions {
+    /// Creates a new set of verification options
+    pub const fn new() -> Self {
+        Self {
+            constant_time: false,
+            digest_algorithm: DigestAlgorithm::Blake3,
+        }
+    }
+
+    /// Sets whether verification should resist timing attacks.
+    ///
+    /// When enabled, digests are compared in constant time and the full patch is always read
+    /// before a result is reported, even if applying the patch fails partway through. This
+    /// prevents a hostile patch from learning how far verification progressed by measuring how
+    /// long it takes to be rejected.
+    pub fn constant_time(&mut self, constant_time: bool) -> &mut Self {
+        self.constant_time = constant_time;
+        self
+    }
+
+    /// Sets the algorithm the expected digest was computed with.
+    ///
+    /// By default, digests are BLAKE3 digests.
+    pub fn digest_algorithm(&mut self, algorithm: DigestAlgorithm) -> &mut Self {
+        self.digest_algorithm = algorithm;
+        self
+    }
+}
+
+/// Verifies that applying `patch` to `old` reproduces a blob with the given digest
+///
+/// The digest is computed with the algorithm set by [`VerifyOptions::digest_algorithm()`], which
+/// defaults to BLAKE3.
+///
+/// The reconstructed blob is hashed as it is produced and is never stored, so this function can be
+/// used to validate patches server-side without writing their output anywhere.
+///
+/// Returns `Ok(true)` if the digest of the reconstructed blob equals `expected` and `Ok(false)`
+/// otherwise.
+///
+/// # Errors
+///
+/// Returns an error if an I/O error occurs while applying the patch or if the patch is invalid.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use std::io::Cursor;
+/// use ina::VerifyOptions;
+///
+/// let mut patch = Vec::new();
+/// ina::diff(&ina::OldBlob::from_slice(b"Hello"), b"Hero", &mut patch)?;
+///
+/// let expected = blake3::hash(b"Hero");
+/// let old = Cursor::new(b"Hello");
+/// let options = VerifyOptions::new().constant_time(true).clone();
+///
+/// assert!(ina::verify(old, patch.as_slice(), expected.as_bytes(), &options)?);
+/// # Ok(())
+/// # }
+/// ```
+pub fn verify<O, P>(
+    old: O,
+    mut patch: P,
+    expected: &[u8; DIGEST_LEN],
+    options: &VerifyOptions,
+) -> Result<bool, PatchError>
+where
+    O: Read + Seek,
+    P: Read,
+{
+    let mut hasher = options.digest_algorithm.hasher();
+    let result = Patcher::new(old, &mut patch)
+        .and_then(|mut patcher| Ok(io::copy(&mut patcher, &mut hasher)?));
+
+    if options.constant_time {
+        // Consume whatever remains of the patch so that rejection time doesn't depend on where
+        // patching stopped
+        io::copy(&mut patch, &mut io::sink())?;
+    }
+    result?;
+
+    let actual = hasher.finalize();
+    if options.constant_time {
+        Ok(constant_time_eq(&actual, expected))
+    } else {
+        Ok(&actual == expected)
+    }
+}
+
+/// Applies a patch and compares the result against an existing candidate blob
+///
+/// The reconstructed blob is compared with `candidate` as it is produced and is never stored. This
+/// is useful for investigating why a patch doesn't reproduce a blob built elsewhere, e.g., by a
+/// CI system. Comparison stops at the first difference.
+///
+/// Returns `Ok(None)` if the reconstructed blob is identical to `candidate` and `Ok(Some(offset))`
+/// with the offset of the first differing byte otherwise. If one blob is a prefix of the other,
+/// the offset is the length of the shorter one.
+///
+/// # Errors
+///
+/// Returns an error if an I/O error occurs while applying the patch or reading `candidate` or if
+/// the patch is invalid.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use std::io::Cursor;
+/// use ina::Patcher;
+///
+/// let mut patch = Vec::new();
+/// ina::diff(&ina::OldBlob::from_slice(b"Hello"), b"Hero", &mut patch)?;
+///
+/// let patcher = Patcher::new(Cursor::new(b"Hello"), patch.as_slice())?;
+/// assert_eq!(ina::verify_against(patcher, b"Herb".as_slice())?, Some(3));
+/// # Ok(())
+/// # }
+/// ```
+pub fn verify_against<O, B, C>(
+    mut patcher: Patcher<'_, O, B>,
+    mut candidate: C,
+) -> Result<Option<u64>, PatchError>
+where
+    O: Read + Seek,
+    B: BufRead,
+    C: Read,
+{
+    let mut new_buf = [0; BUF_SIZE];
+    let mut candidate_buf = [0; BUF_SIZE];
+    let mut offset = 0;
+
+    loop {
+        let len = read_full(&mut patcher, &mut new_buf)?;
+        if len == 0 {
+            // The reconstructed blob ended, so the blobs only match if the candidate did as well
+            let candidate_len = read_full(&mut candidate, &mut candidate_buf[..1])?;
+            return Ok((candidate_len != 0).then_some(offset));
+        }
+
+        let candidate_len = read_full(&mut candidate, &mut candidate_buf[..len])?;
+        let difference = new_buf[..candidate_len]
+            .iter()
+            .zip(&candidate_buf[..candidate_len])
+            .position(|(a, b)| a != b)
+            .or((candidate_len < len).then_some(candidate_len));
+        if let Some(position) = difference {
+            return Ok(Some(offset + position as u64));
+        }
+
+        offset += len as u64;
+    }
+}
+
+/// Reads from `reader` until `buf` is full or the end of the stream is reached, returning the
+/// number of bytes read
+fn read_full<R>(reader: &mut R, buf: &mut [u8]) -> io::Result<usize>
+where
+    R: Read,
+{
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(read) => filled += read,
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(filled)
+}
+
+/// Compares two byte slices in time independent of their contents
+///
+/// The comparison time still depends on the slices' lengths, which are assumed to be public.
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+
+    let difference = a
+        .iter()
+        .zip(b)
+        .fold(0, |acc, (x, y)| hint::black_box(acc | (x ^ y)));
+
+    difference == 0
+}
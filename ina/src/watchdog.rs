@@ -0,0 +1,147 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use std::{
+    io::{self, ErrorKind, Read},
+    sync::mpsc::{self, Receiver, RecvTimeoutError, SyncSender},
+    thread,
+    time::Duration,
+};
+
+use crate::PatchError;
+
+/// The maximum number of bytes read from the inner reader at once
+const CHUNK_LEN: usize = 64 << 10;
+
+/// The number of chunks read ahead of the consumer
+const READ_AHEAD_CHUNKS: usize = 2;
+
+/// A reader which fails if its inner reader makes no progress for too long.
+///
+/// Reading from a stream which hangs, e.g., a network connection whose peer stopped responding or
+/// a Java stream deadlocked behind JNI, blocks forever. A `WatchdogReader` reads from its inner
+/// reader on a separate thread instead and fails with [`PatchError::Stalled`], wrapped in an
+/// [`io::Error`] of kind [`TimedOut`](ErrorKind::TimedOut), if no data arrives within the timeout.
+/// Wrapping the patch stream of a [`Patcher`](crate::Patcher) in one therefore aborts patching
+/// rather than blocking indefinitely. Only waiting on the inner reader counts toward the timeout,
+/// so a slow consumer doesn't trigger it.
+///
+/// Up to 128 KiB are read ahead of the consumer. A read which never returns can't be cancelled, so
+/// after a stall, the reading thread remains blocked until the inner reader returns, at which
+/// point it exits. Since it creates a thread, a `WatchdogReader` can't be created once a sandbox
+/// has been enabled via the `sandbox` module.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use std::{io::{Cursor, Read}, time::Duration};
+/// use ina::{Patcher, WatchdogReader};
+///
+/// let mut patch = Vec::new();
+/// ina::diff(&ina::OldBlob::from_slice(b"Hello"), b"Hero", &mut patch)?;
+///
+/// let patch = WatchdogReader::new(Cursor::new(patch), Duration::from_secs(30))?;
+/// let mut new = Vec::new();
+/// Patcher::new(Cursor::new(b"Hello"), patch)?.read_to_end(&mut new)?;
+/// assert_eq!(new, b"Hero");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct WatchdogReader {
+    chunks: Receiver<io::Result<Vec<u8>>>,
+    chunk: Vec<u8>,
+    pos: usize,
+    timeout: Duration,
+    finished: bool,
+}
+
+impl WatchdogReader {
+    /// Creates a reader which reads from `inner` on a new thread and fails if `inner` provides no
+    /// data for `timeout`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the thread can't be created.
+    pub fn new<R>(inner: R, timeout: Duration) -> io::Result<Self>
+    where
+        R: Read + Send + 'static,
+    {
+        let (sender, chunks) = mpsc::sync_channel(READ_AHEAD_CHUNKS);
+        thread::Builder::new()
+            .name("ina-watchdog".into())
+            .spawn(move || read_chunks(inner, &sender))?;
+
+        Ok(Self {
+            chunks,
+            chunk: Vec::new(),
+            pos: 0,
+            timeout,
+            finished: false,
+        })
+    }
+}
+
+impl Read for WatchdogReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.chunk.len() {
+            if self.finished || buf.is_empty() {
+                return Ok(0);
+            }
+
+            match self.chunks.recv_timeout(self.timeout) {
+                Ok(Ok(chunk)) => {
+                    self.finished = chunk.is_empty();
+                    self.chunk = chunk;
+                    self.pos = 0;
+                }
+                Ok(Err(e)) => {
+                    self.finished = true;
+                    return Err(e);
+                }
+                Err(RecvTimeoutError::Timeout) => {
+                    return Err(io::Error::new(
+                        ErrorKind::TimedOut,
+                        PatchError::Stalled(self.timeout),
+                    ));
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    self.finished = true;
+                    return Err(io::Error::other("watchdog reading thread panicked"));
+                }
+            }
+        }
+
+        let len = buf.len().min(self.chunk.len() - self.pos);
+        buf[..len].copy_from_slice(&self.chunk[self.pos..self.pos + len]);
+        self.pos += len;
+
+        Ok(len)
+    }
+}
+
+/// Reads `inner` to its end in chunks, sending each to `chunks` followed by an empty chunk or an
+/// error, until the receiving `WatchdogReader` is dropped
+fn read_chunks<R>(mut inner: R, chunks: &SyncSender<io::Result<Vec<u8>>>)
+where
+    R: Read,
+{
+    loop {
+        let mut chunk = vec![0; CHUNK_LEN];
+        let result = match inner.read(&mut chunk) {
+            Ok(len) => {
+                chunk.truncate(len);
+                Ok(chunk)
+            }
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => Err(e),
+        };
+
+        let last = result.as_ref().map_or(true, Vec::is_empty);
+        if chunks.send(result).is_err() || last {
+            return;
+        }
+    }
+}
@@ -0,0 +1,494 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use std::io::{self, Write};
+#[cfg(feature = "stats")]
+use std::time::Instant;
+
+#[cfg(feature = "brotli")]
+use brotli::CompressorWriter;
+use byteorder::{LittleEndian, WriteBytesExt};
+use integer_encoding::{VarInt, VarIntWriter};
+use zstd::Encoder;
+
+use crate::{
+    BaseDigest, BlockMap, Codec,
+    checksum::PayloadChecksum,
+    diff::DiffConfig,
+    header::{
+        FieldsWriter, MAGIC, TAG_BASE_CHECK, TAG_BLOCK_MAP, TAG_NEW_LEN, TAG_PAYLOAD_CHECKSUM,
+        TAG_TEXT_HINTS, VERSION_MAJOR, VERSION_MINOR,
+    },
+    stats::DiffStats,
+    text::TextHints,
+};
+#[cfg(feature = "verify")]
+use crate::{BlobDigests, header::TAG_DIGESTS};
+#[cfg(feature = "fec")]
+use crate::{FecConfig, header::TAG_FEC};
+
+/// What a patch records about the blobs it was created from, besides what its [`DiffConfig`]
+/// describes
+#[derive(Default)]
+pub(crate) struct Recorded<'a> {
+    /// The total length of the records' add and copy fields, recorded if enabled in the options
+    pub(crate) new_len: Option<u64>,
+    pub(crate) text_hints: Option<&'a TextHints>,
+    pub(crate) base_digest: Option<&'a BaseDigest>,
+    pub(crate) block_map: Option<&'a BlockMap>,
+    #[cfg(feature = "verify")]
+    pub(crate) blob_digests: Option<&'a BlobDigests>,
+}
+
+/// A writer which encodes control records into a patch.
+///
+/// `PatchWriter` is the encoder used by [`diff()`](crate::diff) and friends, exposed so that
+/// patches can be composed from control records computed elsewhere, e.g., by a different matching
+/// algorithm or by converting a patch from another format. The header and compression are handled
+/// according to the given [`DiffConfig`], whose matcher settings are ignored.
+///
+/// A patch is a sequence of control records. Applying a record first adds each of its add bytes to
+/// the byte at the current position in the old blob using wrapping addition, advancing the
+/// position as it goes. It then appends its copy bytes verbatim and finally moves the position in
+/// the old blob by its seek value. The position starts at 0.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use ina::{DiffConfig, PatchWriter};
+///
+/// let old = b"Hello";
+/// let mut patch = PatchWriter::new(Vec::new(), &DiffConfig::new())?;
+/// // "He" + [0, 0] = "He", then "ro" verbatim
+/// patch.write_record(&[0, 0], b"ro", 0)?;
+/// let patch = patch.finish()?;
+///
+/// let mut new = Vec::new();
+/// ina::patch(std::io::Cursor::new(old), patch.as_slice(), &mut new)?;
+/// assert_eq!(new, b"Hero");
+/// # Ok(())
+/// # }
+/// ```
+pub struct PatchWriter<W>
+where
+    W: Write,
+{
+    encoder: PayloadEncoder<W>,
+    stats: DiffStats,
+    old_pos: u64,
+}
+
+impl<W> PatchWriter<W>
+where
+    W: Write,
+{
+    /// Creates a new `PatchWriter` which writes a patch to `out` using the given options
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an I/O error occurs while writing the patch header or if the
+    /// compressor can't be configured.
+    pub fn new(out: W, options: &DiffConfig) -> io::Result<Self> {
+        Self::with_digests(out, options, &Recorded::default())
+    }
+
+    /// Creates a new `PatchWriter` which additionally records what `recorded` describes in the
+    /// header
+    pub(crate) fn with_digests(
+        out: W,
+        options: &DiffConfig,
+        recorded: &Recorded,
+    ) -> io::Result<Self> {
+        let mut fields = options.header_fields()?;
+        if options.record_new_len
+            && let Some(new_len) = recorded.new_len
+        {
+            fields.push(TAG_NEW_LEN, &new_len.encode_var_vec());
+        }
+        if let Some(hints) = recorded.text_hints {
+            fields.push(TAG_TEXT_HINTS, &hints.encode_field());
+        }
+        if let Some(digest) = recorded.base_digest {
+            fields.push(TAG_BASE_CHECK, &digest.encode_field());
+        }
+        if let Some(block_map) = recorded.block_map {
+            fields.push(TAG_BLOCK_MAP, &block_map.encode_field());
+        }
+        #[cfg(feature = "verify")]
+        if let Some(digests) = recorded.blob_digests {
+            fields.push(TAG_DIGESTS, &digests.encode_field());
+        }
+
+        // The checksum and the parity blocks of forward error correction can only be computed over
+        // the complete compressed data, which must be described in the header, so the header is
+        // written when the patch is finished
+        #[cfg(feature = "fec")]
+        let buffered = options.payload_checksum || options.fec.is_some();
+        #[cfg(not(feature = "fec"))]
+        let buffered = options.payload_checksum;
+        let output = if buffered {
+            Output::Buffered {
+                out,
+                fields,
+                payload_checksum: options.payload_checksum,
+                #[cfg(feature = "fec")]
+                fec: options.fec,
+                payload: Vec::new(),
+            }
+        } else {
+            Output::direct(out, &fields.into_bytes())?
+        };
+
+        // The seccomp sandbox forbids creating threads, so compress on the calling thread once it's
+        // enabled
+        #[cfg(feature = "sandbox")]
+        let compression_threads = match crate::sandbox::status() {
+            crate::sandbox::SandboxStatus::Disabled
+            | crate::sandbox::SandboxStatus::FilesystemRestricted => options.compression_threads,
+            _ => 0,
+        };
+        #[cfg(not(feature = "sandbox"))]
+        let compression_threads = options.compression_threads;
+
+        let encoder = match options.codec {
+            Codec::Zstd => {
+                let mut encoder = Encoder::new(output, options.compression_level)?;
+                encoder.multithread(compression_threads)?;
+                if let Some(window_log) = options.window_log {
+                    encoder.window_log(window_log)?;
+                }
+
+                PayloadEncoder::Zstd(encoder)
+            }
+            #[cfg(feature = "brotli")]
+            Codec::Brotli => PayloadEncoder::Brotli(Box::new(CompressorWriter::new(
+                ErrorCapturingWriter {
+                    inner: output,
+                    error: None,
+                },
+                BROTLI_BUF_SIZE,
+                options.compression_level.clamp(0, BROTLI_MAX_QUALITY) as u32,
+                options.window_log.map_or(BROTLI_DEFAULT_WINDOW_LOG, |log| {
+                    log.clamp(BROTLI_MIN_WINDOW_LOG, BROTLI_MAX_WINDOW_LOG)
+                }),
+            ))),
+        };
+
+        Ok(Self {
+            encoder,
+            stats: DiffStats::default(),
+            old_pos: 0,
+        })
+    }
+
+    /// Appends a control record to the patch
+    ///
+    /// `add` contains the differences to add to the bytes at the current position in the old blob,
+    /// `copy` contains bytes to insert verbatim, and `seek` is the distance to move the position in
+    /// the old blob afterward.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error of kind [`InvalidInput`](io::ErrorKind::InvalidInput) if the record would
+    /// move the position in the old blob before its start, or any error which occurs while writing
+    /// the record.
+    pub fn write_record(&mut self, add: &[u8], copy: &[u8], seek: i64) -> io::Result<&mut Self> {
+        let add_len = add.len() as u64;
+        let next_old_pos = self
+            .old_pos
+            .checked_add(add_len)
+            .and_then(|pos| pos.checked_add_signed(seek))
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "control record seeks before the start of the old blob",
+                )
+            })?;
+
+        #[cfg(feature = "stats")]
+        let start = Instant::now();
+        self.encoder.write_varint(add.len())?;
+        self.encoder.write_all(add)?;
+        self.encoder.write_varint(copy.len())?;
+        self.encoder.write_all(copy)?;
+        self.encoder.write_varint(seek)?;
+        #[cfg(feature = "stats")]
+        {
+            self.stats.compression_time += start.elapsed();
+        }
+
+        // Track which region of the old blob the add field references and how it's reached
+        self.stats
+            .old_coverage
+            .insert(self.old_pos..self.old_pos + add_len);
+        self.stats
+            .seeks
+            .record_read(self.old_pos..self.old_pos + add_len);
+        self.old_pos = next_old_pos;
+
+        self.stats.control_records += 1;
+        self.stats.add_bytes += add_len;
+        self.stats.copy_bytes += copy.len() as u64;
+        self.stats.new_len += add_len + copy.len() as u64;
+
+        Ok(self)
+    }
+
+    /// Finishes writing the patch, returning the underlying writer
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an I/O error occurs while writing the rest of the patch.
+    pub fn finish(self) -> io::Result<W> {
+        self.finish_with_stats().map(|(out, _)| out)
+    }
+
+    /// Finishes writing the patch, returning the underlying writer and statistics about the patch
+    ///
+    /// The length of the old blob in the returned statistics is left for the caller to fill in.
+    pub(crate) fn finish_with_stats(self) -> io::Result<(W, DiffStats)> {
+        #[cfg(feature = "stats")]
+        let start = Instant::now();
+        let (out, patch_len) = self.encoder.finish()?.finish()?;
+
+        Ok((
+            out,
+            DiffStats {
+                patch_len,
+                #[cfg(feature = "stats")]
+                compression_time: self.stats.compression_time + start.elapsed(),
+                ..self.stats
+            },
+        ))
+    }
+}
+
+/// The size of the buffer Brotli compresses into
+#[cfg(feature = "brotli")]
+const BROTLI_BUF_SIZE: usize = 64 << 10;
+/// The highest Brotli quality, which compresses the most
+#[cfg(feature = "brotli")]
+const BROTLI_MAX_QUALITY: i32 = 11;
+/// The Brotli window log used unless one is set, which is the default of the reference encoder
+#[cfg(feature = "brotli")]
+const BROTLI_DEFAULT_WINDOW_LOG: u32 = 22;
+/// The smallest Brotli window log
+#[cfg(feature = "brotli")]
+const BROTLI_MIN_WINDOW_LOG: u32 = 10;
+/// The largest Brotli window log supported without the large window extension
+#[cfg(feature = "brotli")]
+const BROTLI_MAX_WINDOW_LOG: u32 = 24;
+
+/// A compressor for a patch's data section using the configured codec
+enum PayloadEncoder<W>
+where
+    W: Write,
+{
+    Zstd(Encoder<'static, Output<W>>),
+    #[cfg(feature = "brotli")]
+    Brotli(Box<CompressorWriter<ErrorCapturingWriter<Output<W>>>>),
+}
+
+impl<W> PayloadEncoder<W>
+where
+    W: Write,
+{
+    /// Finishes compressing, returning the destination of the compressed data
+    fn finish(self) -> io::Result<Output<W>> {
+        match self {
+            Self::Zstd(encoder) => encoder.finish(),
+            #[cfg(feature = "brotli")]
+            Self::Brotli(encoder) => {
+                let output = encoder.into_inner();
+                match output.error {
+                    Some(e) => Err(e),
+                    None => Ok(output.inner),
+                }
+            }
+        }
+    }
+}
+
+impl<W> Write for PayloadEncoder<W>
+where
+    W: Write,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Self::Zstd(encoder) => encoder.write(buf),
+            #[cfg(feature = "brotli")]
+            Self::Brotli(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::Zstd(encoder) => encoder.flush(),
+            #[cfg(feature = "brotli")]
+            Self::Brotli(encoder) => encoder.flush(),
+        }
+    }
+}
+
+/// A writer which keeps the first error returned by `inner`
+///
+/// The Brotli compressor discards errors which occur while finishing its stream, so they're kept
+/// here to be reported afterward.
+#[cfg(feature = "brotli")]
+struct ErrorCapturingWriter<W>
+where
+    W: Write,
+{
+    inner: W,
+    error: Option<io::Error>,
+}
+
+#[cfg(feature = "brotli")]
+impl<W> Write for ErrorCapturingWriter<W>
+where
+    W: Write,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.inner.write(buf).inspect_err(|e| {
+            self.error
+                .get_or_insert_with(|| io::Error::new(e.kind(), e.to_string()));
+        })
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush().inspect_err(|e| {
+            self.error
+                .get_or_insert_with(|| io::Error::new(e.kind(), e.to_string()));
+        })
+    }
+}
+
+/// The destination of a patch's compressed data section
+enum Output<W>
+where
+    W: Write,
+{
+    /// The header has already been written to `out`, so data is written directly after it
+    Direct(CountingWriter<W>),
+    /// The data is buffered so that the header, which describes it, and any parity blocks can be
+    /// written once it's complete
+    Buffered {
+        out: W,
+        fields: FieldsWriter,
+        payload_checksum: bool,
+        #[cfg(feature = "fec")]
+        fec: Option<FecConfig>,
+        payload: Vec<u8>,
+    },
+}
+
+impl<W> Output<W>
+where
+    W: Write,
+{
+    fn direct(out: W, fields: &[u8]) -> io::Result<Self> {
+        let mut out = CountingWriter {
+            inner: out,
+            count: 0,
+        };
+        write_header(&mut out, fields)?;
+
+        Ok(Self::Direct(out))
+    }
+
+    /// Writes anything remaining, returning the underlying writer and the total number of bytes
+    /// written to it
+    fn finish(self) -> io::Result<(W, u64)> {
+        match self {
+            Self::Direct(out) => Ok((out.inner, out.count)),
+            Self::Buffered {
+                out,
+                mut fields,
+                payload_checksum,
+                #[cfg(feature = "fec")]
+                fec,
+                payload,
+            } => {
+                let mut out = CountingWriter {
+                    inner: out,
+                    count: 0,
+                };
+                if payload_checksum {
+                    fields.push(
+                        TAG_PAYLOAD_CHECKSUM,
+                        &PayloadChecksum::of(&payload).encode_field(),
+                    );
+                }
+                #[cfg(feature = "fec")]
+                if let Some(fec) = fec {
+                    fields.push(TAG_FEC, &fec.encode_field(payload.len() as u64));
+                    write_header(&mut out, &fields.into_bytes())?;
+                    fec.encode(&payload, &mut out)?;
+
+                    return Ok((out.inner, out.count));
+                }
+                write_header(&mut out, &fields.into_bytes())?;
+                out.write_all(&payload)?;
+
+                Ok((out.inner, out.count))
+            }
+        }
+    }
+}
+
+impl<W> Write for Output<W>
+where
+    W: Write,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Self::Direct(out) => out.write(buf),
+            Self::Buffered { payload, .. } => payload.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::Direct(out) => out.flush(),
+            Self::Buffered { .. } => Ok(()),
+        }
+    }
+}
+
+/// Writes the fixed portion of the header followed by the extension area `fields`
+fn write_header<W>(mut patch: &mut W, fields: &[u8]) -> io::Result<()>
+where
+    W: Write + ?Sized,
+{
+    patch.write_u32::<LittleEndian>(MAGIC)?;
+    patch.write_u16::<LittleEndian>(VERSION_MAJOR)?;
+    patch.write_u16::<LittleEndian>(VERSION_MINOR)?;
+    patch.write_varint(fields.len())?;
+    patch.write_all(fields)
+}
+
+/// A writer which counts the number of bytes written through it
+struct CountingWriter<W>
+where
+    W: Write,
+{
+    inner: W,
+    count: u64,
+}
+
+impl<W> Write for CountingWriter<W>
+where
+    W: Write,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.count += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
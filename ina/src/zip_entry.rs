@@ -0,0 +1,353 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use std::{
+    fs::File,
+    io::{self, BufReader, ErrorKind, Read, Seek, SeekFrom, Take},
+};
+
+use byteorder::{LittleEndian, ReadBytesExt};
+use crc32fast::Hasher;
+
+const LOCAL_HEADER_SIGNATURE: u32 = 0x04034b50;
+const CENTRAL_HEADER_SIGNATURE: u32 = 0x02014b50;
+const END_SIGNATURE: u32 = 0x06054b50;
+const ZIP64_END_SIGNATURE: u32 = 0x06064b50;
+const ZIP64_LOCATOR_SIGNATURE: u32 = 0x07064b50;
+/// The header ID of the extra field holding 64-bit sizes and offsets
+const ZIP64_EXTRA_ID: u16 = 0x0001;
+
+/// The length of the end of central directory record without its comment
+const END_LEN: u64 = 22;
+/// The length of the ZIP64 end of central directory locator
+const ZIP64_LOCATOR_LEN: u64 = 20;
+/// The maximum length of the end of central directory record including its comment
+const MAX_END_LEN: u64 = END_LEN + u16::MAX as u64;
+
+/// The compression method of entries stored without compression
+const METHOD_STORED: u16 = 0;
+/// The general purpose flag marking encrypted entries
+const FLAG_ENCRYPTED: u16 = 1;
+
+/// A reader of a single entry of a zip archive, such as a patch shipped inside an OTA package.
+///
+/// Only the archive's central directory and the entry itself are read, so an entry can be streamed
+/// into a [`Patcher`](crate::Patcher) without extracting it. The entry must be stored without
+/// compression, as is usual for entries which are already compressed like patches. ZIP64 archives
+/// are supported.
+///
+/// The CRC-32 recorded for the entry is checked once the entry has been read to its end, so a
+/// mismatch is reported by the read which would otherwise return 0.
+///
+/// # Examples
+///
+/// ```no_run
+/// use ina::{Patcher, ZipEntryReader};
+/// use std::fs::File;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let old = File::open("libfoo-v1.so")?;
+/// let patch = ZipEntryReader::open("ota.zip:patches/libfoo.ina")?;
+/// let mut new = File::create("libfoo-v2.so")?;
+///
+/// ina::patch(old, patch, &mut new)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct ZipEntryReader<R> {
+    inner: Take<R>,
+    len: u64,
+    crc: u32,
+    hasher: Hasher,
+}
+
+impl ZipEntryReader<BufReader<File>> {
+    /// Opens the entry addressed as `ARCHIVE:ENTRY`, e.g., `ota.zip:patches/libfoo.ina`
+    ///
+    /// The entry name is everything after the last `:`, so archive paths may contain colons, e.g.,
+    /// in Windows drive prefixes.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error of kind [`ErrorKind::InvalidInput`] if `address` isn't of the form
+    /// `ARCHIVE:ENTRY`, or any error returned by opening the archive or by
+    /// [`ZipEntryReader::new()`].
+    pub fn open(address: &str) -> io::Result<Self> {
+        let (archive, name) = address
+            .rsplit_once(':')
+            .filter(|(archive, name)| !archive.is_empty() && !name.is_empty())
+            .ok_or_else(|| {
+                io::Error::new(
+                    ErrorKind::InvalidInput,
+                    "expected an archive path and entry name separated by ':'",
+                )
+            })?;
+
+        Self::new(BufReader::new(File::open(archive)?), name)
+    }
+}
+
+impl<R> ZipEntryReader<R>
+where
+    R: Read + Seek,
+{
+    /// Creates a reader of the entry of `archive` named `name`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error of kind [`ErrorKind::NotFound`] if the archive has no entry named `name`,
+    /// of kind [`ErrorKind::Unsupported`] if the entry is compressed or encrypted, of kind
+    /// [`ErrorKind::InvalidData`] if `archive` isn't a valid zip archive, or any error returned by
+    /// reading `archive`.
+    pub fn new(mut archive: R, name: &str) -> io::Result<Self> {
+        let (mut remaining, directory_offset) = find_central_directory(&mut archive)?;
+        archive.seek(SeekFrom::Start(directory_offset))?;
+
+        let entry = loop {
+            if remaining == 0 {
+                return Err(io::Error::new(
+                    ErrorKind::NotFound,
+                    format!("the archive has no entry named '{name}'"),
+                ));
+            }
+            remaining -= 1;
+
+            let entry = CentralEntry::read(&mut archive)?;
+            if entry.name == name.as_bytes() {
+                break entry;
+            }
+        };
+
+        if entry.flags & FLAG_ENCRYPTED != 0 {
+            return Err(io::Error::new(
+                ErrorKind::Unsupported,
+                format!("entry '{name}' is encrypted"),
+            ));
+        }
+        if entry.method != METHOD_STORED {
+            return Err(io::Error::new(
+                ErrorKind::Unsupported,
+                format!(
+                    "entry '{name}' is compressed (method {}), but only entries stored without \
+                    compression can be read",
+                    entry.method,
+                ),
+            ));
+        }
+
+        // The sizes in the local header may be deferred to a data descriptor, so only its name and
+        // extra field lengths are used
+        archive.seek(SeekFrom::Start(entry.local_offset))?;
+        expect_signature(&mut archive, LOCAL_HEADER_SIGNATURE, "local file header")?;
+        archive.seek(SeekFrom::Current(22))?;
+        let name_len = archive.read_u16::<LittleEndian>()?;
+        let extra_len = archive.read_u16::<LittleEndian>()?;
+        archive.seek(SeekFrom::Current(
+            i64::from(name_len) + i64::from(extra_len),
+        ))?;
+
+        Ok(Self {
+            inner: archive.take(entry.compressed_len),
+            len: entry.compressed_len,
+            crc: entry.crc,
+            hasher: Hasher::new(),
+        })
+    }
+
+    /// Returns the length of the entry in bytes
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Returns whether the entry is empty
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Unwraps this `ZipEntryReader`, returning the underlying archive
+    ///
+    /// The archive is positioned after the bytes of the entry read so far.
+    pub fn into_inner(self) -> R {
+        self.inner.into_inner()
+    }
+}
+
+impl<R> Read for ZipEntryReader<R>
+where
+    R: Read,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.hasher.update(&buf[..read]);
+
+        if read == 0 && !buf.is_empty() {
+            if self.inner.limit() > 0 {
+                return Err(io::Error::new(
+                    ErrorKind::UnexpectedEof,
+                    "the archive ends within the entry",
+                ));
+            }
+            if self.hasher.clone().finalize() != self.crc {
+                return Err(io::Error::new(
+                    ErrorKind::InvalidData,
+                    "the entry doesn't match its CRC-32",
+                ));
+            }
+        }
+
+        Ok(read)
+    }
+}
+
+/// The parts of a central directory file header needed to read an entry
+struct CentralEntry {
+    flags: u16,
+    method: u16,
+    crc: u32,
+    compressed_len: u64,
+    local_offset: u64,
+    name: Vec<u8>,
+}
+
+impl CentralEntry {
+    fn read<R: Read>(archive: &mut R) -> io::Result<Self> {
+        expect_signature(
+            archive,
+            CENTRAL_HEADER_SIGNATURE,
+            "central directory header",
+        )?;
+        let mut fixed = [0; 42];
+        archive.read_exact(&mut fixed)?;
+        let mut fixed = fixed.as_slice();
+
+        let _version_made_by = fixed.read_u16::<LittleEndian>()?;
+        let _version_needed = fixed.read_u16::<LittleEndian>()?;
+        let flags = fixed.read_u16::<LittleEndian>()?;
+        let method = fixed.read_u16::<LittleEndian>()?;
+        let _modified = fixed.read_u32::<LittleEndian>()?;
+        let crc = fixed.read_u32::<LittleEndian>()?;
+        let compressed_len = fixed.read_u32::<LittleEndian>()?;
+        let uncompressed_len = fixed.read_u32::<LittleEndian>()?;
+        let name_len = fixed.read_u16::<LittleEndian>()?;
+        let extra_len = fixed.read_u16::<LittleEndian>()?;
+        let comment_len = fixed.read_u16::<LittleEndian>()?;
+        let _disk = fixed.read_u16::<LittleEndian>()?;
+        let _internal_attributes = fixed.read_u16::<LittleEndian>()?;
+        let _external_attributes = fixed.read_u32::<LittleEndian>()?;
+        let local_offset = fixed.read_u32::<LittleEndian>()?;
+
+        let mut name = vec![0; name_len.into()];
+        archive.read_exact(&mut name)?;
+        let mut extra = vec![0; extra_len.into()];
+        archive.read_exact(&mut extra)?;
+        io::copy(&mut archive.take(comment_len.into()), &mut io::sink())?;
+
+        // Values which don't fit in 32 bits are saturated and given in the ZIP64 extra field in
+        // this order instead
+        let mut zip64 = zip64_extra(&extra);
+        let mut widen = |value: u32| match value {
+            u32::MAX => zip64.read_u64::<LittleEndian>().map_err(|_| {
+                io::Error::new(ErrorKind::InvalidData, "ZIP64 extra field is too short")
+            }),
+            value => Ok(u64::from(value)),
+        };
+        widen(uncompressed_len)?;
+        let compressed_len = widen(compressed_len)?;
+        let local_offset = widen(local_offset)?;
+
+        Ok(Self {
+            flags,
+            method,
+            crc,
+            compressed_len,
+            local_offset,
+            name,
+        })
+    }
+}
+
+/// Returns the data of the ZIP64 field in `extra`, or an empty slice if there is none
+fn zip64_extra(mut extra: &[u8]) -> &[u8] {
+    while extra.len() >= 4 {
+        let id = u16::from_le_bytes([extra[0], extra[1]]);
+        let len = usize::from(u16::from_le_bytes([extra[2], extra[3]]));
+        let Some(data) = extra.get(4..4 + len) else {
+            break;
+        };
+        if id == ZIP64_EXTRA_ID {
+            return data;
+        }
+        extra = &extra[4 + len..];
+    }
+
+    &[]
+}
+
+/// Finds the central directory of `archive`, returning its number of entries and its offset
+fn find_central_directory<R: Read + Seek>(archive: &mut R) -> io::Result<(u64, u64)> {
+    let archive_len = archive.seek(SeekFrom::End(0))?;
+    let tail_len = archive_len.min(MAX_END_LEN);
+    archive.seek(SeekFrom::Start(archive_len - tail_len))?;
+    let mut tail = Vec::with_capacity(tail_len as usize);
+    archive.take(tail_len).read_to_end(&mut tail)?;
+
+    // The end of central directory record is searched for backward since its comment may contain
+    // the signature
+    let end_pos = (0..=tail.len().saturating_sub(END_LEN as usize))
+        .rev()
+        .find(|&pos| tail[pos..pos + 4] == END_SIGNATURE.to_le_bytes())
+        .ok_or_else(|| io::Error::new(ErrorKind::InvalidData, "not a zip archive"))?;
+    let mut end = &tail[end_pos + 4..];
+    let _disk = end.read_u16::<LittleEndian>()?;
+    let _directory_disk = end.read_u16::<LittleEndian>()?;
+    let _disk_entries = end.read_u16::<LittleEndian>()?;
+    let entries = end.read_u16::<LittleEndian>()?;
+    let _directory_len = end.read_u32::<LittleEndian>()?;
+    let directory_offset = end.read_u32::<LittleEndian>()?;
+    if entries != u16::MAX && directory_offset != u32::MAX {
+        return Ok((entries.into(), directory_offset.into()));
+    }
+
+    // The counts and offset are saturated, so the ZIP64 end of central directory record, which is
+    // found through the locator immediately preceding the record, holds the actual values
+    let end_offset = archive_len - tail_len + end_pos as u64;
+    let locator_offset = end_offset.checked_sub(ZIP64_LOCATOR_LEN).ok_or_else(|| {
+        io::Error::new(
+            ErrorKind::InvalidData,
+            "ZIP64 end of central directory is missing",
+        )
+    })?;
+    archive.seek(SeekFrom::Start(locator_offset))?;
+    expect_signature(archive, ZIP64_LOCATOR_SIGNATURE, "ZIP64 locator")?;
+    let _directory_disk = archive.read_u32::<LittleEndian>()?;
+    let zip64_end_offset = archive.read_u64::<LittleEndian>()?;
+
+    archive.seek(SeekFrom::Start(zip64_end_offset))?;
+    expect_signature(
+        archive,
+        ZIP64_END_SIGNATURE,
+        "ZIP64 end of central directory",
+    )?;
+    let mut zip64_end = [0; 52];
+    archive.read_exact(&mut zip64_end)?;
+    let mut zip64_end = &zip64_end[28..];
+    let entries = zip64_end.read_u64::<LittleEndian>()?;
+    let _directory_len = zip64_end.read_u64::<LittleEndian>()?;
+    let directory_offset = zip64_end.read_u64::<LittleEndian>()?;
+
+    Ok((entries, directory_offset))
+}
+
+/// Reads a signature from `archive`, failing if it isn't `expected`
+fn expect_signature<R: Read>(archive: &mut R, expected: u32, record: &str) -> io::Result<()> {
+    if archive.read_u32::<LittleEndian>()? != expected {
+        return Err(io::Error::new(
+            ErrorKind::InvalidData,
+            format!("invalid {record} signature"),
+        ));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,88 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+#![allow(missing_docs)]
+
+use std::{error::Error, io::Cursor};
+
+use ina::{DiffConfig, OldBlob};
+
+fn pseudo_random(len: usize, seed: u32) -> Vec<u8> {
+    let mut state = seed;
+    (0..len)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state as u8
+        })
+        .collect()
+}
+
+#[test]
+fn anchors_keep_matching_local() -> Result<(), Box<dyn Error>> {
+    // The old blob holds two near-identical sections, and the new blob is a slightly modified
+    // copy of the second one. Without knowing which section the new blob derives from, the
+    // matcher jumps between them.
+    let first = pseudo_random(1 << 16, 1);
+    let mut second = first.clone();
+    for i in (0..second.len()).step_by(16) {
+        second[i] ^= 0x55;
+    }
+    let old = [first.as_slice(), &second].concat();
+    let mut new = second.clone();
+    for i in (8..new.len()).step_by(64) {
+        new[i] ^= 0x0f;
+    }
+
+    let mut config = DiffConfig::new();
+    config.anchors([
+        (first.len(), 0),
+        // Anchors outside of the blobs are ignored
+        (old.len() * 2, 1000),
+    ]);
+    let mut anchored = Vec::new();
+    ina::diff_readers(old.as_slice(), new.as_slice(), &mut anchored, &config)?;
+    let mut plain = Vec::new();
+    ina::diff_readers(
+        old.as_slice(),
+        new.as_slice(),
+        &mut plain,
+        &DiffConfig::new(),
+    )?;
+    assert!(anchored.len() < plain.len());
+
+    for patch in [anchored, plain] {
+        let mut reconstructed_new = Vec::new();
+        ina::patch(Cursor::new(&old), patch.as_slice(), &mut reconstructed_new)?;
+        assert_eq!(reconstructed_new, new);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn anchors_at_swapped_sections() -> Result<(), Box<dyn Error>> {
+    let (text, data) = (pseudo_random(1 << 16, 1), pseudo_random(1 << 15, 2));
+    let old = [text.as_slice(), &data].concat();
+    let mut new = [data.as_slice(), &text].concat();
+    for i in (0..new.len()).step_by(64) {
+        new[i] = new[i].wrapping_add(1);
+    }
+
+    let old_blob = OldBlob::from_slice(&old);
+    let mut patch = Vec::new();
+    ina::diff_with_config(
+        &old_blob,
+        &new,
+        &mut patch,
+        DiffConfig::new().anchors([(0, data.len()), (text.len(), 0)]),
+    )?;
+
+    let mut reconstructed_new = Vec::new();
+    ina::patch(Cursor::new(&old), patch.as_slice(), &mut reconstructed_new)?;
+    assert_eq!(reconstructed_new, new);
+
+    Ok(())
+}
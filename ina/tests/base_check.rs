@@ -0,0 +1,134 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+#![cfg(all(feature = "diff", feature = "patch"))]
+#![allow(missing_docs)]
+
+mod common;
+
+use std::{error::Error, io::Cursor};
+
+use ina::{BaseCheck, DiffConfig, OldBlob, PatchError, Patcher};
+
+fn old() -> Vec<u8> {
+    common::periodic_blob(100_000, 7)
+}
+
+#[test]
+fn base_checks_round_trip() -> Result<(), Box<dyn Error>> {
+    let old = old();
+    let mut new = old.clone();
+    new[5000..5100].fill(0);
+
+    for base_check in [BaseCheck::None, BaseCheck::Full, BaseCheck::sampled()] {
+        let patch = ina::diff_to_vec(
+            &OldBlob::from_slice(&old),
+            &new,
+            DiffConfig::new().base_check(base_check),
+        )?;
+        assert_eq!(ina::patch_to_vec(&old, &patch)?, new, "{base_check:?}");
+    }
+
+    let sampled = BaseCheck::Sampled {
+        samples: 4,
+        sample_len: 1000,
+    };
+    let patch = ina::diff_to_vec(
+        &OldBlob::from_slice(&old),
+        &new,
+        DiffConfig::new().base_check(sampled),
+    )?;
+    let metadata = ina::read_header(&mut patch.as_slice())?;
+    let digest = metadata.base_digest().unwrap();
+    assert_eq!(digest.old_len(), old.len() as u64);
+    assert_eq!(digest.covered_len(), 4000);
+    assert_eq!(
+        digest.regions().collect::<Vec<_>>(),
+        [0..1000, 33000..34000, 66000..67000, 99000..100_000],
+    );
+
+    // Samples covering the whole old blob check all of it
+    let patch = ina::diff_to_vec(
+        &OldBlob::from_slice(b"short"),
+        b"shorter",
+        DiffConfig::new().base_check(BaseCheck::sampled()),
+    )?;
+    let metadata = ina::read_header(&mut patch.as_slice())?;
+    assert_eq!(metadata.base_digest().unwrap().covered_len(), 5);
+
+    Ok(())
+}
+
+#[test]
+fn wrong_old_blobs_are_rejected() -> Result<(), Box<dyn Error>> {
+    let old = old();
+    let patch = ina::diff_to_vec(
+        &OldBlob::from_slice(&old),
+        b"new",
+        DiffConfig::new().base_check(BaseCheck::sampled()),
+    )?;
+
+    let mut wrong = old.clone();
+    wrong[0] ^= 1;
+    assert!(matches!(
+        ina::patch_to_vec(&wrong, &patch),
+        Err(PatchError::OldMismatch(Some(region))) if region.start == 0,
+    ));
+    assert!(matches!(
+        ina::patch_to_vec(&old[1..], &patch),
+        Err(PatchError::OldMismatch(None)),
+    ));
+
+    // No output is produced before the old blob is checked
+    assert!(Patcher::new(Cursor::new(wrong), patch.as_slice()).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn sampled_checks_miss_changes_between_samples() -> Result<(), Box<dyn Error>> {
+    let old = old();
+    let sampled = BaseCheck::Sampled {
+        samples: 2,
+        sample_len: 100,
+    };
+    let old_blob = OldBlob::from_slice(&old);
+    let sampled_patch = ina::diff_to_vec(&old_blob, b"new", DiffConfig::new().base_check(sampled))?;
+    let full_patch = ina::diff_to_vec(
+        &old_blob,
+        b"new",
+        DiffConfig::new().base_check(BaseCheck::Full),
+    )?;
+    let mut wrong = old.clone();
+    wrong[50_000] ^= 1;
+
+    assert!(ina::patch_to_vec(&wrong, &sampled_patch).is_ok());
+    assert!(matches!(
+        ina::patch_to_vec(&wrong, &full_patch),
+        Err(PatchError::OldMismatch(Some(region))) if region == (0..old.len() as u64),
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn transcoding_keeps_base_digest() -> Result<(), Box<dyn Error>> {
+    let old = old();
+    let patch = ina::diff_to_vec(
+        &OldBlob::from_slice(&old),
+        b"new",
+        DiffConfig::new().base_check(BaseCheck::sampled()),
+    )?;
+
+    let mut transcoded = Vec::new();
+    let options = DiffConfig::new().compression_level(1).clone();
+    ina::transcode(patch.as_slice(), &mut transcoded, &options)?;
+
+    assert_eq!(
+        ina::read_header(&mut transcoded.as_slice())?.base_digest(),
+        ina::read_header(&mut patch.as_slice())?.base_digest(),
+    );
+
+    Ok(())
+}
@@ -0,0 +1,144 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+#![cfg(all(feature = "diff", feature = "verify"))]
+#![allow(missing_docs)]
+
+mod common;
+
+use std::{
+    error::Error,
+    io::{self, Cursor, ErrorKind, Read},
+};
+
+use ina::{DiffConfig, DigestAlgorithm, OldBlob, PatchError, Patcher, TextMode};
+
+fn old() -> Vec<u8> {
+    common::periodic_blob(100_000, 7)
+}
+
+fn new() -> Vec<u8> {
+    common::edited_blob(&old(), 5000..5100)
+}
+
+fn options() -> DiffConfig {
+    DiffConfig::new()
+        .embed_digests(Some(DigestAlgorithm::Blake3))
+        .clone()
+}
+
+#[test]
+fn digests_are_recorded_by_every_diff_function() -> Result<(), Box<dyn Error>> {
+    let old = old();
+    let new = new();
+    let options = options();
+
+    let mut windowed = Vec::new();
+    ina::diff_windowed(
+        Cursor::new(&old),
+        Cursor::new(&new),
+        &mut windowed,
+        options.clone().diff_window_len(4096),
+    )?;
+    let text_old = b"line one\nline two\n";
+    let text_new = b"line one\nline 2\n";
+    let text = ina::diff_to_vec(
+        &OldBlob::from_slice(text_old),
+        text_new,
+        options.clone().text_mode(TextMode::Text),
+    )?;
+
+    for (old, new, patch) in [
+        (
+            old.as_slice(),
+            new.as_slice(),
+            ina::diff_to_vec(&OldBlob::from_slice(&old), &new, &options)?,
+        ),
+        (&old, &new, windowed),
+        (text_old, text_new, text),
+    ] {
+        let metadata = ina::read_header(&mut patch.as_slice())?;
+        let digests = metadata.blob_digests().unwrap();
+        assert_eq!(digests.algorithm(), DigestAlgorithm::Blake3);
+        assert_eq!(digests.old_digest(), &DigestAlgorithm::Blake3.hash(old));
+        assert_eq!(digests.new_digest(), &DigestAlgorithm::Blake3.hash(new));
+        assert_eq!(ina::patch_to_vec(old, &patch)?, new);
+    }
+
+    // No digests are recorded by default
+    let patch = ina::diff_to_vec(&OldBlob::from_slice(&old), &new, &DiffConfig::new())?;
+    assert!(
+        ina::read_header(&mut patch.as_slice())?
+            .blob_digests()
+            .is_none()
+    );
+
+    Ok(())
+}
+
+#[test]
+fn wrong_old_blobs_are_rejected() -> Result<(), Box<dyn Error>> {
+    let old = old();
+    let patch = ina::diff_to_vec(&OldBlob::from_slice(&old), &new(), &options())?;
+
+    let mut wrong = old.clone();
+    wrong[50_000] ^= 1;
+    assert!(matches!(
+        ina::patch_to_vec(&wrong, &patch),
+        Err(PatchError::OldMismatch(Some(region))) if region == (0..old.len() as u64),
+    ));
+    assert!(Patcher::new(Cursor::new(wrong), patch.as_slice()).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn new_blob_mismatches_are_detected_at_the_end() -> Result<(), Box<dyn Error>> {
+    let old = old();
+    let new = new();
+    let mut patch = ina::diff_to_vec(&OldBlob::from_slice(&old), &new, &options())?;
+
+    // Corrupt the recorded digest of the new blob, which the old blob can't be checked against
+    let digest = DigestAlgorithm::Blake3.hash(&new);
+    let pos = patch
+        .windows(digest.len())
+        .position(|window| window == digest)
+        .unwrap();
+    patch[pos] ^= 1;
+
+    let mut patcher = Patcher::new(Cursor::new(&old), patch.as_slice())?;
+    let mut output = Vec::new();
+    let error = patcher.read_to_end(&mut output).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+    assert!(matches!(
+        error
+            .into_inner()
+            .unwrap()
+            .downcast::<PatchError>()
+            .as_deref(),
+        Ok(PatchError::NewMismatch),
+    ));
+    // Only output of the new blob has been produced by then
+    assert!(new.starts_with(&output));
+
+    Ok(())
+}
+
+#[test]
+fn transcoding_keeps_digests() -> Result<(), Box<dyn Error>> {
+    let patch = ina::diff_to_vec(&OldBlob::from_slice(&old()), &new(), &options())?;
+
+    let mut transcoded = Vec::new();
+    let options = DiffConfig::new().compression_level(1).clone();
+    ina::transcode(patch.as_slice(), &mut transcoded, &options)?;
+
+    assert_eq!(
+        ina::read_header(&mut transcoded.as_slice())?.blob_digests(),
+        ina::read_header(&mut patch.as_slice())?.blob_digests(),
+    );
+    let mut patcher = Patcher::new(Cursor::new(old()), transcoded.as_slice())?;
+    io::copy(&mut patcher, &mut io::sink())?;
+
+    Ok(())
+}
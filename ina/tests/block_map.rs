@@ -0,0 +1,66 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+#![allow(missing_docs)]
+
+use std::{error::Error, io};
+
+use ina::{DiffConfig, OldBlob, Patcher};
+
+#[test]
+fn block_updates_reproduce_new_blob() -> Result<(), Box<dyn Error>> {
+    let old: Vec<u8> = (0..40_000u32)
+        .map(|i| (i.wrapping_mul(0x9e3779b9) >> 24) as u8)
+        .collect();
+    let mut new = old[..37_000].to_vec();
+    new[100] ^= 1;
+    new[4096..4200].fill(0);
+    new[20_000] ^= 1;
+    // The last block is partial and extends past the end of the old blob
+    new.extend_from_slice(&[7; 5000]);
+
+    let config = DiffConfig::new().block_map(Some(4096)).clone();
+    let mut patches = vec![ina::diff_to_vec(&OldBlob::from_slice(&old), &new, &config)?];
+    let mut windowed = Vec::new();
+    ina::diff_windowed(
+        io::Cursor::new(&old),
+        io::Cursor::new(&new),
+        &mut windowed,
+        DiffConfig::new()
+            .block_map(Some(4096))
+            .diff_window_len(8192),
+    )?;
+    patches.push(windowed);
+
+    for patch in patches {
+        let metadata = ina::read_header(&mut patch.as_slice())?;
+        let block_map = metadata.block_map().unwrap();
+        assert_eq!(block_map.block_count(), 11);
+        assert_eq!(block_map.changed().collect::<Vec<_>>(), [0..2, 4..5, 9..11]);
+
+        let mut device = old.clone();
+        device.resize(new.len(), 0);
+        let mut updates = Patcher::new(io::Cursor::new(&old), patch.as_slice())?
+            .block_updates()
+            .unwrap();
+        while let Some(update) = updates.next_update()? {
+            assert!(block_map.is_changed(update.index()));
+            let offset = update.offset() as usize;
+            device[offset..offset + update.data().len()].copy_from_slice(update.data());
+        }
+        assert_eq!(device, new);
+    }
+
+    let patch = ina::diff_to_vec(&OldBlob::from_slice(&old), &new, &DiffConfig::new())?;
+    assert!(
+        Patcher::new(io::Cursor::new(&old), patch.as_slice())?
+            .block_updates()
+            .is_none()
+    );
+    let config = DiffConfig::new().block_map(Some(1000)).clone();
+    let error = ina::diff_to_vec(&OldBlob::from_slice(&old), &new, &config).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+
+    Ok(())
+}
@@ -0,0 +1,115 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+#![cfg(feature = "brotli")]
+#![allow(missing_docs)]
+
+mod common;
+
+use std::{
+    error::Error,
+    io::{Cursor, Read},
+};
+
+use ina::{Codec, DiffConfig, FormatFeature, OldBlob, PatchError, PatchLimits, Patcher};
+
+fn diff(old: &[u8], new: &[u8], config: &DiffConfig) -> Result<Vec<u8>, Box<dyn Error>> {
+    Ok(ina::diff_to_vec(&OldBlob::from_slice(old), new, config)?)
+}
+
+fn brotli_config() -> DiffConfig {
+    let mut config = DiffConfig::new();
+    config.codec(Codec::Brotli);
+
+    config
+}
+
+#[test]
+fn round_trip() -> Result<(), Box<dyn Error>> {
+    let (old, new) = common::binary_pair(4, 64 << 10);
+    let patch = diff(&old, &new, &brotli_config())?;
+
+    let metadata = ina::read_header(&mut patch.as_slice())?;
+    assert_eq!(metadata.codec(), Codec::Brotli);
+    assert!(
+        metadata
+            .format_capabilities()
+            .features()
+            .any(|feature| feature == FormatFeature::Codec)
+    );
+    assert_eq!(ina::patch_to_vec(&old, &patch)?, new);
+
+    // Patches compressed with zstd don't record a codec
+    let patch = diff(&old, &new, &DiffConfig::new())?;
+    assert_eq!(
+        ina::read_header(&mut patch.as_slice())?.codec(),
+        Codec::Zstd
+    );
+
+    Ok(())
+}
+
+#[test]
+fn transcode_to_brotli() -> Result<(), Box<dyn Error>> {
+    let (old, new) = common::binary_pair(4, 64 << 10);
+    let patch = diff(&old, &new, &DiffConfig::new())?;
+
+    let mut transcoded = Vec::new();
+    ina::transcode(patch.as_slice(), &mut transcoded, &brotli_config())?;
+    assert_eq!(
+        ina::read_header(&mut transcoded.as_slice())?.codec(),
+        Codec::Brotli,
+    );
+    assert_eq!(ina::patch_to_vec(&old, &transcoded)?, new);
+
+    Ok(())
+}
+
+#[test]
+fn truncated_patch_fails() -> Result<(), Box<dyn Error>> {
+    let (old, new) = common::binary_pair(4, 64 << 10);
+    let patch = diff(&old, &new, &brotli_config())?;
+
+    for len in [patch.len() - 1, patch.len() / 2] {
+        assert!(ina::patch_to_vec(&old, &patch[..len]).is_err());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn memory_limit_rejects_large_window() -> Result<(), Box<dyn Error>> {
+    let (old, new) = common::binary_pair(4, 64 << 10);
+    let mut config = brotli_config();
+    config.window_log(Some(24));
+    let patch = diff(&old, &new, &config)?;
+
+    let mut limits = PatchLimits::new();
+    limits.max_memory(4 << 20);
+    let result = Patcher::with_limits(Cursor::new(&old), patch.as_slice(), &limits);
+    assert!(matches!(result, Err(PatchError::MemoryLimitExceeded(_))));
+
+    Ok(())
+}
+
+#[test]
+fn concatenated_patches() -> Result<(), Box<dyn Error>> {
+    let (old, new) = common::binary_pair(4, 64 << 10);
+    let mut patches = diff(&old, &new, &brotli_config())?;
+    patches.extend(diff(&new, &old, &DiffConfig::new())?);
+
+    let mut patcher = Patcher::new(Cursor::new(&old), patches.as_slice())?;
+    let mut first = Vec::new();
+    patcher.read_to_end(&mut first)?;
+    assert_eq!(first, new);
+
+    let mut patcher = patcher
+        .next_patch(Cursor::new(&new))?
+        .expect("a second patch follows");
+    let mut second = Vec::new();
+    patcher.read_to_end(&mut second)?;
+    assert_eq!(second, old);
+
+    Ok(())
+}
@@ -0,0 +1,198 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+#![cfg(feature = "bundle")]
+#![allow(missing_docs)]
+
+use std::{error::Error, io::Cursor};
+
+use ina::{BundleEntryKind, BundleReader, BundleWriter, DiffConfig, OldBlob, PatchError, Patcher};
+
+#[test]
+fn bundle_round_trip() -> Result<(), Box<dyn Error>> {
+    let old_app: Vec<u8> = (0..4096u32).map(|i| (i % 251) as u8).collect();
+    let mut new_app = old_app.clone();
+    new_app[100..200].fill(0);
+
+    let mut writer = BundleWriter::new(Vec::new())?;
+    writer.add("bin/app", Some(&old_app), &new_app, &DiffConfig::new())?;
+    writer.remove("bin/obsolete")?;
+    writer.add("README", Some(b"readme"), b"readme", &DiffConfig::new())?;
+    writer.add("share/data", None, b"data", &DiffConfig::new())?;
+    let bundle = writer.finish()?;
+
+    let mut reader = BundleReader::new(bundle.as_slice())?;
+    let mut entries = Vec::new();
+    while let Some(entry) = reader.next_entry()? {
+        let new = match entry.kind() {
+            BundleEntryKind::Modified => {
+                let mut new = Vec::new();
+                ina::patch(Cursor::new(&old_app), reader.patch(), &mut new)?;
+                Some(new)
+            }
+            BundleEntryKind::Added => {
+                let mut new = Vec::new();
+                ina::patch(Cursor::new([]), reader.patch(), &mut new)?;
+                Some(new)
+            }
+            BundleEntryKind::Removed | BundleEntryKind::Unchanged | BundleEntryKind::Symlink => {
+                None
+            }
+        };
+        if let Some(new) = &new {
+            assert_eq!(new.len() as u64, entry.new_len());
+            assert_eq!(
+                entry.new_digest(),
+                Some(blake3::hash(new).as_bytes()),
+                "{}",
+                entry.path(),
+            );
+        }
+        entries.push((entry.path().to_string(), entry.kind(), new));
+    }
+    // Reading past the end keeps returning `None`
+    assert!(reader.next_entry()?.is_none());
+
+    assert_eq!(
+        entries,
+        [
+            ("bin/app".into(), BundleEntryKind::Modified, Some(new_app)),
+            ("bin/obsolete".into(), BundleEntryKind::Removed, None),
+            ("README".into(), BundleEntryKind::Unchanged, None),
+            (
+                "share/data".into(),
+                BundleEntryKind::Added,
+                Some(b"data".to_vec())
+            ),
+        ],
+    );
+
+    Ok(())
+}
+
+#[test]
+fn bundle_paths_must_be_relative() -> Result<(), Box<dyn Error>> {
+    for path in ["", "/etc/passwd", "../escape", "a/./b", "a//b", "C:\\x"] {
+        let mut writer = BundleWriter::new(Vec::new())?;
+        assert!(writer.remove(path).is_err(), "{path:?}");
+    }
+
+    // Craft a bundle whose entry escapes the directory it's applied to
+    let mut writer = BundleWriter::new(Vec::new())?;
+    writer.remove("xx/passwd")?;
+    let mut bundle = writer.finish()?;
+    let start = bundle.windows(2).position(|w| w == b"xx").unwrap();
+    bundle[start..start + 2].copy_from_slice(b"..");
+
+    let mut reader = BundleReader::new(bundle.as_slice())?;
+    assert!(matches!(reader.next_entry(), Err(PatchError::Io(_))));
+
+    Ok(())
+}
+
+#[test]
+fn bundle_symlinks_round_trip() -> Result<(), Box<dyn Error>> {
+    let mut writer = BundleWriter::new(Vec::new())?;
+    writer.add_symlink("lib/libapp.so", "libapp.so.2")?;
+    writer.add_symlink("bin/app", "../libexec/./app")?;
+    writer.add("libexec/app", None, b"app", &DiffConfig::new())?;
+    let bundle = writer.finish()?;
+
+    let mut reader = BundleReader::new(bundle.as_slice())?;
+    let mut links = Vec::new();
+    while let Some(entry) = reader.next_entry()? {
+        if entry.kind() == BundleEntryKind::Symlink {
+            assert_eq!((entry.new_len(), entry.new_digest()), (0, None));
+            assert_eq!(entry.patch_len(), 0);
+        }
+        links.push((
+            entry.path().to_owned(),
+            entry.symlink_target().map(str::to_owned),
+        ));
+    }
+
+    assert_eq!(
+        links,
+        [
+            ("lib/libapp.so".into(), Some("libapp.so.2".into())),
+            ("bin/app".into(), Some("../libexec/./app".into())),
+            ("libexec/app".into(), None),
+        ],
+    );
+
+    Ok(())
+}
+
+#[test]
+fn bundle_symlinks_must_stay_inside() -> Result<(), Box<dyn Error>> {
+    for target in ["", "/etc/passwd", "../escape", "a/../../escape", "C:\\x"] {
+        let mut writer = BundleWriter::new(Vec::new())?;
+        assert!(writer.add_symlink("link", target).is_err(), "{target:?}");
+    }
+
+    // Craft a bundle whose link points out of the directory it's applied to
+    let mut writer = BundleWriter::new(Vec::new())?;
+    writer.add_symlink("link", "xx/passwd")?;
+    let mut bundle = writer.finish()?;
+    let start = bundle.windows(2).position(|w| w == b"xx").unwrap();
+    bundle[start..start + 2].copy_from_slice(b"..");
+
+    let mut reader = BundleReader::new(bundle.as_slice())?;
+    assert!(matches!(reader.next_entry(), Err(PatchError::Io(_))));
+
+    Ok(())
+}
+
+#[test]
+fn patches_are_not_bundles() -> Result<(), Box<dyn Error>> {
+    let mut patch = Vec::new();
+    ina::diff(&OldBlob::from_slice(b"old"), b"new", &mut patch)?;
+
+    assert!(matches!(
+        BundleReader::new(patch.as_slice()),
+        Err(PatchError::BadMagic(_)),
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn bundle_patch_metadata() -> Result<(), Box<dyn Error>> {
+    let mut config = DiffConfig::new();
+    config.record_settings(true);
+    let mut writer = BundleWriter::new(Vec::new())?;
+    writer.add("bin/app", Some(b"version 1"), b"version 2", &config)?;
+    writer.remove("bin/obsolete")?;
+    writer.add("README", Some(b"readme"), b"readme", &config)?;
+    let bundle = writer.finish()?;
+    assert!(ina::is_bundle(&bundle));
+
+    let mut reader = BundleReader::new(bundle.as_slice())?;
+    let mut listing = Vec::new();
+    while let Some(entry) = reader.next_entry()? {
+        let metadata = reader.patch_metadata()?;
+        if let Some(metadata) = &metadata {
+            assert!(metadata.diff_settings().is_some());
+
+            // The rest of the patch can still be applied
+            let mut patcher =
+                Patcher::from_parts(metadata.clone(), Cursor::new(b"version 1"), reader.patch())?;
+            let mut new = Vec::new();
+            std::io::copy(&mut patcher, &mut new)?;
+            assert_eq!(new, b"version 2");
+        }
+        listing.push((entry.path().to_owned(), metadata.is_some()));
+    }
+
+    assert_eq!(
+        listing,
+        [
+            ("bin/app".to_owned(), true),
+            ("bin/obsolete".to_owned(), false),
+            ("README".to_owned(), false),
+        ],
+    );
+
+    Ok(())
+}
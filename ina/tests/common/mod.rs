@@ -0,0 +1,181 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Fixtures shared by the integration tests
+//!
+//! Not every test uses every fixture.
+#![allow(dead_code)]
+
+use std::{env, fs, io, ops::Range, path::PathBuf};
+
+/// The environment variable naming a directory containing real binaries to test with
+///
+/// See `tests/testdata/README.md` for the binaries expected.
+pub const REAL_BINARIES_VAR: &str = "INA_TEST_BINARIES";
+
+/// A small deterministic pseudorandom number generator (SplitMix64)
+///
+/// Fixtures are generated with this rather than an external crate so that they're identical on
+/// every platform and across dependency updates.
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a number in `0..n`
+    pub fn below(&mut self, n: u64) -> u64 {
+        self.next_u64() % n
+    }
+
+    /// Returns a number in `range`
+    pub fn between(&mut self, range: std::ops::Range<usize>) -> usize {
+        range.start + self.below((range.end - range.start) as u64) as usize
+    }
+}
+
+/// Generates `len` bytes which repeat every 251 bytes, with consecutive bytes differing by `step`
+pub fn periodic_blob(len: u32, step: u32) -> Vec<u8> {
+    (0..len).map(|i| (i * step % 251) as u8).collect()
+}
+
+/// Returns a new version of `old` with the bytes in `zeroed` cleared and a few bytes appended
+pub fn edited_blob(old: &[u8], zeroed: Range<usize>) -> Vec<u8> {
+    let mut new = old.to_vec();
+    new[zeroed].fill(0);
+    new.extend_from_slice(b"appended");
+
+    new
+}
+
+/// Instruction encodings resembling x86-64 code, each followed by a 4-byte operand
+const OPCODES: [&[u8]; 8] = [
+    &[0xe8],             // call rel32
+    &[0xe9],             // jmp rel32
+    &[0x0f, 0x84],       // je rel32
+    &[0x48, 0x8d, 0x05], // lea rax, [rip + disp32]
+    &[0x48, 0x8b, 0x05], // mov rax, [rip + disp32]
+    &[0x48, 0x81, 0xec], // sub rsp, imm32
+    &[0xb8],             // mov eax, imm32
+    &[0x48, 0x89, 0x44, 0x24],
+];
+
+const WORDS: [&str; 12] = [
+    "error", "failed", "open", "file", "memory", "invalid", "option", "config", "input", "output",
+    "warning", "version",
+];
+
+/// Generates the contents of a binary of about `len` bytes
+fn generate_binary(rng: &mut Rng, len: usize) -> Vec<u8> {
+    let mut old = Vec::with_capacity(len + 4096);
+    while old.len() < len {
+        match rng.below(4) {
+            // Code, the bulk of executables
+            0 | 1 => {
+                for _ in 0..rng.between(64..512) {
+                    old.extend_from_slice(OPCODES[rng.below(OPCODES.len() as u64) as usize]);
+                    // Operands are mostly small displacements
+                    let operand = rng.below(1 << 16) as u32;
+                    old.extend_from_slice(&operand.to_le_bytes());
+                }
+            }
+            // A string table
+            2 => {
+                for _ in 0..rng.between(16..128) {
+                    for _ in 0..rng.between(1..4) {
+                        old.extend_from_slice(
+                            WORDS[rng.below(WORDS.len() as u64) as usize].as_bytes(),
+                        );
+                        old.push(b' ');
+                    }
+                    *old.last_mut().unwrap() = 0;
+                }
+            }
+            // A table of pointers into the binary
+            _ => {
+                let base = 0x40_0000 + rng.below(1 << 20);
+                for i in 0..rng.between(32..256) as u64 {
+                    old.extend_from_slice(&(base + i * 16 + rng.below(16)).to_le_bytes());
+                }
+            }
+        }
+        // Sections are padded to 16 bytes
+        old.resize(old.len().next_multiple_of(16), 0);
+    }
+
+    old
+}
+
+/// Generates a pair of blobs resembling two versions of an executable of about `len` bytes
+///
+/// The new blob shares most of its contents with the old one, but has edited, inserted, and removed
+/// regions, as well as regions whose 32-bit words all changed by the same amount, like code whose
+/// displacements changed because the code around it moved. The same `seed` always produces the
+/// same pair.
+pub fn binary_pair(seed: u64, len: usize) -> (Vec<u8>, Vec<u8>) {
+    let mut rng = Rng::new(seed);
+    let old = generate_binary(&mut rng, len);
+
+    let mut new = Vec::with_capacity(old.len() + old.len() / 8);
+    let mut pos = 0;
+    while pos < old.len() {
+        let region = &old[pos..old.len().min(pos + rng.between(256..8192))];
+        pos += region.len();
+
+        match rng.below(16) {
+            // Displacements shifted by a constant
+            0..=2 => {
+                let shift = rng.between(1..256) as u32;
+                let mut words = region.chunks_exact(4);
+                for word in &mut words {
+                    let word = u32::from_le_bytes(word.try_into().unwrap());
+                    new.extend_from_slice(&word.wrapping_add(shift).to_le_bytes());
+                }
+                new.extend_from_slice(words.remainder());
+            }
+            // Edited bytes
+            3 | 4 => {
+                let start = new.len();
+                new.extend_from_slice(region);
+                for _ in 0..rng.between(1..16) {
+                    let i = start + rng.below(region.len() as u64) as usize;
+                    new[i] = rng.next_u64() as u8;
+                }
+            }
+            // Inserted sections
+            5 => {
+                new.extend_from_slice(region);
+                let len = rng.between(64..1024);
+                new.extend(generate_binary(&mut rng, len));
+            }
+            // Removed region
+            6 => {}
+            _ => new.extend_from_slice(region),
+        }
+    }
+
+    (old, new)
+}
+
+/// Reads the real old and new binaries from the directory named by [`REAL_BINARIES_VAR`], or
+/// returns `None` if it isn't set
+pub fn real_binaries() -> io::Result<Option<(Vec<u8>, Vec<u8>)>> {
+    let Some(dir) = env::var_os(REAL_BINARIES_VAR).map(PathBuf::from) else {
+        return Ok(None);
+    };
+
+    Ok(Some((
+        fs::read(dir.join("gcc-13.1.1"))?,
+        fs::read(dir.join("gcc-13.2.1"))?,
+    )))
+}
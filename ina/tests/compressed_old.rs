@@ -0,0 +1,115 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+#![allow(missing_docs)]
+
+mod common;
+
+use std::{
+    error::Error,
+    io::{Cursor, ErrorKind, Read, Seek, SeekFrom, Write},
+};
+
+use flate2::{Compression, write::GzEncoder};
+use ina::{CompressedOld, OldBlob, OldCompression};
+
+/// Compresses `data` split into `frames` separately compressed parts
+fn compress(data: &[u8], compression: OldCompression, frames: usize) -> Vec<u8> {
+    let mut compressed = Vec::new();
+    for part in data.chunks(data.len().div_ceil(frames)) {
+        match compression {
+            OldCompression::Zstd => {
+                compressed.extend(zstd::encode_all(part, 3).expect("compressing succeeds"));
+            }
+            OldCompression::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(part).expect("compressing succeeds");
+                compressed.extend(encoder.finish().expect("compressing succeeds"));
+            }
+        }
+    }
+
+    compressed
+}
+
+#[test]
+fn patch_against_compressed_old() -> Result<(), Box<dyn Error>> {
+    let (old, new) = common::binary_pair(11, 1 << 20);
+    let mut patch = Vec::new();
+    ina::diff(&OldBlob::from_slice(&old), &new, &mut patch)?;
+
+    for compression in [OldCompression::Zstd, OldCompression::Gzip] {
+        for frames in [1, 7] {
+            let compressed = compress(&old, compression, frames);
+            assert_eq!(OldCompression::detect(&compressed), Some(compression));
+
+            // A cache smaller than the old blob forces decompressing windows repeatedly
+            let mut compressed_old = CompressedOld::new(Cursor::new(compressed), compression)?;
+            compressed_old.cache_limit(256 << 10);
+            let mut patched = Vec::new();
+            ina::patch(compressed_old, patch.as_slice(), &mut patched)?;
+
+            assert!(patched == new, "{compression:?} with {frames} frames");
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn seeks_match_decompressed_old() -> Result<(), Box<dyn Error>> {
+    let (old, _) = common::binary_pair(12, 1 << 20);
+    let mut rng = common::Rng::new(12);
+
+    for compression in [OldCompression::Zstd, OldCompression::Gzip] {
+        let compressed = compress(&old, compression, 5);
+        let mut compressed_old = CompressedOld::new(Cursor::new(compressed), compression)?;
+        compressed_old.cache_limit(0);
+
+        assert_eq!(
+            compressed_old.seek(SeekFrom::End(-10))?,
+            old.len() as u64 - 10
+        );
+        let mut tail = Vec::new();
+        compressed_old.read_to_end(&mut tail)?;
+        assert_eq!(tail, old[old.len() - 10..]);
+
+        for _ in 0..50 {
+            let start = rng.between(0..old.len());
+            let len = rng.between(0..(old.len() - start).min(600 << 10));
+            compressed_old.seek(SeekFrom::Start(start as u64))?;
+            let mut read = vec![0; len];
+            compressed_old.read_exact(&mut read)?;
+            assert!(
+                read == old[start..start + len],
+                "read {len} bytes at {start}"
+            );
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn damaged_compressed_old_fails() -> Result<(), Box<dyn Error>> {
+    let (old, _) = common::binary_pair(13, 64 << 10);
+
+    for compression in [OldCompression::Zstd, OldCompression::Gzip] {
+        let mut compressed = compress(&old, compression, 2);
+        compressed.truncate(compressed.len() - 4);
+        let mut compressed_old = CompressedOld::new(Cursor::new(compressed), compression)?;
+        let error = compressed_old.read_to_end(&mut Vec::new()).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::UnexpectedEof, "{compression:?}");
+    }
+
+    // The checksum in the trailer of a gzip member is checked
+    let mut compressed = compress(&old, OldCompression::Gzip, 1);
+    let crc = compressed.len() - 8;
+    compressed[crc] ^= 1;
+    let mut compressed_old = CompressedOld::new(Cursor::new(compressed), OldCompression::Gzip)?;
+    let error = compressed_old.read_to_end(&mut Vec::new()).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+
+    Ok(())
+}
@@ -4,57 +4,945 @@
 
 #![allow(missing_docs)]
 
+mod common;
+
 use std::{
     error::Error,
     fs::{self, File},
-    io,
+    io::{self, Read, Write},
     path::Path,
 };
 
 use blake3::Hasher;
+use ina::{
+    BaseCheck, DiffConfig, FastPath, FileMetadata, HeaderField, OldBlob, OldProvider, PatchError,
+    Patcher, Profile, Target,
+};
 
-const OLD_FILE_NAME: &str = "gcc-13.1.1";
-const NEW_FILE_NAME: &str = "gcc-13.2.1";
-const PATCH_FILE_NAME: &str = "gcc-13.1.1-13.2.1.ina";
-
-#[test]
-fn gcc() -> Result<(), Box<dyn Error>> {
-    let test_data_dir = Path::new(env!("CARGO_MANIFEST_DIR"))
-        .join("tests")
-        .join("testdata");
+/// Diffs `old` and `new` through files, applies the patch, and checks the result
+fn round_trip_files(name: &str, old: &[u8], new: &[u8]) -> Result<u64, Box<dyn Error>> {
     let workspace_dir = Path::new(env!("CARGO_TARGET_TMPDIR"));
+    let old_path = workspace_dir.join(format!("{name}.old"));
+    let patch_path = workspace_dir.join(format!("{name}.ina"));
+    let new_path = workspace_dir.join(format!("{name}.new"));
+    fs::write(&old_path, old)?;
 
     // Create a patch file
     {
-        let mut old = fs::read(test_data_dir.join(OLD_FILE_NAME))?;
-        // Add a sentinel so the algorithm works properly
-        old.push(0);
-        let new = fs::read(test_data_dir.join(NEW_FILE_NAME))?;
-        let mut patch = File::create(workspace_dir.join(PATCH_FILE_NAME))?;
-        ina::diff(&old, &new, &mut patch)?;
+        let mut patch = File::create(&patch_path)?;
+        ina::diff(&OldBlob::from_slice(old), new, &mut patch)?;
     }
 
     // Reconstruct the new file from the old file and the patch file
     {
-        let old = File::open(test_data_dir.join(OLD_FILE_NAME))?;
-        let patch = File::open(workspace_dir.join(PATCH_FILE_NAME))?;
-        let mut new = File::create(workspace_dir.join(NEW_FILE_NAME))?;
+        let old = File::open(&old_path)?;
+        let patch = File::open(&patch_path)?;
+        let mut new = File::create(&new_path)?;
         ina::patch(old, patch, &mut new)?;
     }
 
     // Verify that patching worked correctly by comparing the hashes of the new and reconstructed
     // new files
-    let mut new = File::open(test_data_dir.join(NEW_FILE_NAME))?;
-    let mut reconstructed_new = File::open(workspace_dir.join(NEW_FILE_NAME))?;
-
-    let mut new_hasher = Hasher::new();
+    let mut reconstructed_new = File::open(&new_path)?;
     let mut reconstructed_new_hasher = Hasher::new();
-    io::copy(&mut new, &mut new_hasher)?;
     io::copy(&mut reconstructed_new, &mut reconstructed_new_hasher)?;
-    let new_hash = new_hasher.finalize();
-    let reconstructed_new_hash = reconstructed_new_hasher.finalize();
 
-    assert_eq!(new_hash, reconstructed_new_hash);
+    assert_eq!(blake3::hash(new), reconstructed_new_hasher.finalize());
+
+    Ok(fs::metadata(&patch_path)?.len())
+}
+
+#[test]
+fn synthetic_binaries() -> Result<(), Box<dyn Error>> {
+    for seed in 0..4 {
+        let (old, new) = common::binary_pair(seed, 256 << 10);
+        let patch_len = round_trip_files(&format!("synthetic-{seed}"), &old, &new)?;
+
+        // Most of the new binary is shared with the old one, so the patch is much smaller
+        assert!(
+            patch_len < new.len() as u64 / 4,
+            "seed {seed}: {patch_len}-byte patch for {}-byte binary",
+            new.len(),
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn real_binaries() -> Result<(), Box<dyn Error>> {
+    let Some((old, new)) = common::real_binaries()? else {
+        eprintln!(
+            "skipping: set {} to test with real binaries",
+            common::REAL_BINARIES_VAR,
+        );
+        return Ok(());
+    };
+
+    round_trip_files("gcc", &old, &new)?;
+
+    Ok(())
+}
+
+/// A writer which accepts at most a few bytes per write and fails after a limit
+struct ShortWriter {
+    written: Vec<u8>,
+    limit: usize,
+}
+
+impl Write for ShortWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.written.len() >= self.limit {
+            return Err(io::Error::other("upload failed"));
+        }
+        let len = buf.len().min(7);
+        self.written.extend_from_slice(&buf[..len]);
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn patch_multi_writes_every_sink() -> Result<(), Box<dyn Error>> {
+    let (old, new) = common::binary_pair(5, 256 << 10);
+    let patch = ina::diff_to_vec(&OldBlob::from_slice(&old), &new, &DiffConfig::new())?;
+
+    let mut file = Vec::new();
+    let mut hasher = Hasher::new();
+    let mut upload = ShortWriter {
+        written: Vec::new(),
+        limit: usize::MAX,
+    };
+    let written = ina::patch_multi(
+        io::Cursor::new(&old),
+        patch.as_slice(),
+        &mut [&mut file, &mut hasher, &mut upload],
+    )?;
+    assert_eq!(written, new.len() as u64);
+    assert_eq!(file, new);
+    assert_eq!(hasher.finalize(), blake3::hash(&new));
+    assert_eq!(upload.written, new);
+
+    // A failing sink is identified by its index
+    let mut file = Vec::new();
+    let mut upload = ShortWriter {
+        written: Vec::new(),
+        limit: 100 << 10,
+    };
+    let result = ina::patch_multi(
+        io::Cursor::new(&old),
+        patch.as_slice(),
+        &mut [&mut file, &mut upload],
+    );
+    let Err(PatchError::Sink(e)) = result else {
+        panic!("expected an output error, got {result:?}");
+    };
+    assert_eq!(e.index(), 1);
+    assert!(e.offset() <= upload.written.len() as u64);
+    assert_eq!(file[..e.offset() as usize], new[..e.offset() as usize]);
+    assert_eq!(e.io_error().to_string(), "upload failed");
+
+    Ok(())
+}
+
+#[test]
+fn patch_to_vec_preallocates() -> Result<(), Box<dyn Error>> {
+    let (old, new) = common::binary_pair(4, 64 << 10);
+    let old_blob = OldBlob::from_slice(&old);
+
+    let patch = ina::diff_to_vec(&old_blob, &new, DiffConfig::new().record_new_len(true))?;
+    let patched = ina::patch_to_vec(&old, &patch)?;
+    assert_eq!(patched, new);
+    assert_eq!(patched.capacity(), new.len());
+
+    // A recorded length far beyond the length of the inputs isn't allocated up front
+    let zeros = vec![0; 1 << 20];
+    let patch = ina::diff_to_vec(
+        &OldBlob::from_slice(&[]),
+        &zeros,
+        DiffConfig::new().record_new_len(true),
+    )?;
+    assert!(patch.len() * 2 < zeros.len());
+    assert_eq!(ina::patch_to_vec(&[], &patch)?, zeros);
+
+    // Without a recorded length, the new blob is still reconstructed
+    let patch = ina::diff_to_vec(&old_blob, &new, &DiffConfig::new())?;
+    assert_eq!(ina::patch_to_vec(&old, &patch)?, new);
+
+    Ok(())
+}
+
+#[test]
+fn profiles_round_trip() -> Result<(), Box<dyn Error>> {
+    let (old, new) = common::binary_pair(5, 64 << 10);
+    let old_blob = OldBlob::from_slice(&old);
+
+    for profile in [Profile::Executable, Profile::Archive, Profile::Text] {
+        let patch = ina::diff_to_vec(&old_blob, &new, DiffConfig::new().profile(profile))?;
+        assert_eq!(ina::patch_to_vec(&old, &patch)?, new, "{profile:?}");
+    }
+
+    // Settings changed after the profile override it
+    let mut config = DiffConfig::new();
+    config.profile(Profile::Archive).compression_level(19);
+    let mut expected = DiffConfig::new();
+    expected.profile(Profile::Archive);
+    assert_ne!(config, expected);
+    expected.compression_level(19);
+    assert_eq!(config, expected);
+
+    Ok(())
+}
+
+#[test]
+fn header_errors_report_offsets() -> Result<(), Box<dyn Error>> {
+    let mut patch = Vec::new();
+    ina::diff_with_config(
+        &OldBlob::from_slice(b"Hello"),
+        b"Hero",
+        &mut patch,
+        DiffConfig::new().record_new_len(true),
+    )?;
+
+    // The fixed header is followed by a one-byte data offset and a single new length field
+    for (len, field, offset) in [
+        (0, HeaderField::Magic, 0),
+        (5, HeaderField::MajorVersion, 4),
+        (6, HeaderField::MinorVersion, 6),
+        (8, HeaderField::DataOffset, 8),
+        (10, HeaderField::Extension, 9),
+    ] {
+        match ina::read_header(&mut &patch[..len]) {
+            Err(PatchError::Header(e)) => {
+                assert_eq!((e.field(), e.offset()), (field, offset), "{len}");
+                assert_eq!(e.io_error().kind(), io::ErrorKind::UnexpectedEof);
+            }
+            result => panic!("unexpected result for {len} bytes: {result:?}"),
+        }
+    }
+
+    // Claim a longer new length field than the extension area holds
+    let mut corrupted = patch.clone();
+    corrupted[10] = 0x7f;
+    match ina::read_header(&mut corrupted.as_slice()) {
+        Err(PatchError::Header(e)) => {
+            assert_eq!((e.field(), e.offset()), (HeaderField::ExtensionField, 9));
+        }
+        result => panic!("unexpected result: {result:?}"),
+    }
+
+    let page = b"<!DOCTYPE html>\n<html><body>502 Bad Gateway</body></html>";
+    let error = ina::read_header(&mut page.as_slice()).unwrap_err();
+    assert!(matches!(&error, PatchError::BadMagic(found) if found == &page[..16]));
+    assert!(error.to_string().contains("<!DOCTYPE html>"));
+
+    Ok(())
+}
+
+#[test]
+fn old_blob_hides_sentinel() -> Result<(), Box<dyn Error>> {
+    let (old, new) = common::binary_pair(5, 16 << 10);
+    let old_blob = OldBlob::from_vec(old.clone());
+    assert_eq!(old_blob.as_bytes(), old);
+    assert_eq!(old_blob.len(), old.len());
+    assert_eq!(OldBlob::from_slice(&old), old_blob);
+    assert!(OldBlob::default().is_empty());
+
+    // Old blobs ending in a 0 byte keep it, rather than it being mistaken for the sentinel
+    let mut zero_ended = old.clone();
+    zero_ended.push(0);
+    let patch = ina::diff_to_vec(&OldBlob::from_slice(&zero_ended), &new, &DiffConfig::new())?;
+    assert_eq!(ina::patch_to_vec(&zero_ended, &patch)?, new);
+    assert_eq!(old_blob.into_vec(), old);
+
+    Ok(())
+}
+
+#[test]
+fn sniff_identifies_patches() -> Result<(), Box<dyn Error>> {
+    let mut patch = Vec::new();
+    ina::diff(&OldBlob::from_slice(b"Hello"), b"Hero", &mut patch)?;
+
+    let version = ina::read_header(&mut patch.as_slice())?.version();
+    assert_eq!(ina::format::sniff(&patch), Some(version));
+    assert_eq!(ina::format::sniff(&patch[..8]), Some(version));
+    assert!(patch.starts_with(&ina::format::MAGIC));
+
+    // The version is needed, and patches of unknown major versions aren't recognized
+    assert_eq!(ina::format::sniff(&patch[..7]), None);
+    let mut future = patch.clone();
+    future[4..6].copy_from_slice(&2u16.to_le_bytes());
+    assert_eq!(ina::format::sniff(&future), None);
+
+    Ok(())
+}
+
+#[test]
+fn transcode_preserves_delta() -> Result<(), Box<dyn Error>> {
+    let old = b"The quick brown fox jumps over the lazy dog";
+    let new = b"The quick red fox leaps over the lazy cat";
+    let old_blob = OldBlob::from_slice(old);
+    let file_metadata = FileMetadata::new(Some(0o755), None);
+    let target = Target::new(Some("android".into()), None, Some(42));
+    let mut patch = Vec::new();
+    ina::diff_with_config(
+        &old_blob,
+        new,
+        &mut patch,
+        DiffConfig::new()
+            .compression_level(1)
+            .file_metadata(Some(file_metadata))
+            .target(Some(target.clone()))
+            .record_new_len(true),
+    )?;
+
+    let mut transcoded = Vec::new();
+    let stats = ina::transcode(
+        patch.as_slice(),
+        &mut transcoded,
+        DiffConfig::new().compression_level(22),
+    )?;
+    assert_eq!(stats.new_len(), new.len() as u64);
+    assert_eq!(stats.patch_len(), transcoded.len() as u64);
+
+    let metadata = ina::read_header(&mut transcoded.as_slice())?;
+    assert_eq!(metadata.file_metadata(), Some(file_metadata));
+    assert_eq!(metadata.target(), Some(&target));
+    assert_eq!(metadata.new_len(), Some(new.len() as u64));
+
+    let mut reconstructed_new = Vec::new();
+    ina::patch(
+        io::Cursor::new(old),
+        transcoded.as_slice(),
+        &mut reconstructed_new,
+    )?;
+    assert_eq!(reconstructed_new, new);
+
+    Ok(())
+}
+
+#[test]
+fn patcher_from_parts() -> Result<(), Box<dyn Error>> {
+    let old = b"Hello, world!";
+    let new = b"Hello, patched world!";
+    let old_blob = OldBlob::from_slice(old);
+    let mut patch = Vec::new();
+    ina::diff(&old_blob, new, &mut patch)?;
+
+    let mut patch_reader = patch.as_slice();
+    let metadata = ina::read_header(&mut patch_reader)?;
+    let mut patcher = Patcher::from_parts(metadata, io::Cursor::new(old), patch_reader)?;
+
+    let mut reconstructed_new = Vec::new();
+    io::copy(&mut patcher, &mut reconstructed_new)?;
+    assert_eq!(reconstructed_new, new);
+
+    Ok(())
+}
+
+/// An old blob provider which serves at most `chunk_len` bytes per read, like a chunk store
+struct ChunkProvider<'a> {
+    data: &'a [u8],
+    chunk_len: usize,
+}
+
+impl OldProvider for ChunkProvider<'_> {
+    fn read_at(&mut self, offset: u64, buf: &mut [u8]) -> io::Result<usize> {
+        let start = (offset as usize).min(self.data.len());
+        let chunk_end = (start / self.chunk_len + 1) * self.chunk_len;
+        let src = &self.data[start..chunk_end.min(self.data.len())];
+        let len = src.len().min(buf.len());
+        buf[..len].copy_from_slice(&src[..len]);
+
+        Ok(len)
+    }
+
+    fn total_len(&mut self) -> io::Result<Option<u64>> {
+        Ok(Some(self.data.len() as u64))
+    }
+}
+
+#[test]
+fn patcher_with_provider() -> Result<(), Box<dyn Error>> {
+    let (old, new) = common::binary_pair(6, 64 << 10);
+    let mut patch = Vec::new();
+    let config = DiffConfig::new().base_check(BaseCheck::sampled()).clone();
+    ina::diff_with_config(&OldBlob::from_slice(&old), &new, &mut patch, &config)?;
+
+    let provider = ChunkProvider {
+        data: &old,
+        chunk_len: 1000,
+    };
+    let mut patcher = Patcher::with_provider(provider, patch.as_slice())?;
+    let mut reconstructed_new = Vec::new();
+    io::copy(&mut patcher, &mut reconstructed_new)?;
+    assert_eq!(reconstructed_new, new);
+
+    // Base checks require the length of the old blob, which a plain callback doesn't provide
+    let callback = |offset: u64, buf: &mut [u8]| {
+        let provider = &mut ChunkProvider {
+            data: &old,
+            chunk_len: 1000,
+        };
+        provider.read_at(offset, buf)
+    };
+    let result = Patcher::with_provider(callback, patch.as_slice());
+    assert!(matches!(
+        result,
+        Err(PatchError::Io(e)) if e.kind() == io::ErrorKind::Unsupported
+    ));
+
+    let mut patch = Vec::new();
+    ina::diff(&OldBlob::from_slice(&old), &new, &mut patch)?;
+    let mut patcher = Patcher::with_provider(callback, patch.as_slice())?;
+    let mut reconstructed_new = Vec::new();
+    io::copy(&mut patcher, &mut reconstructed_new)?;
+    assert_eq!(reconstructed_new, new);
+
+    Ok(())
+}
+
+#[test]
+fn patcher_chunks() -> Result<(), Box<dyn Error>> {
+    let old: Vec<u8> = (0..10_000u32).map(|i| (i % 251) as u8).collect();
+    let mut new = old.clone();
+    new[5000..5100].fill(7);
+    let old_blob = OldBlob::from_slice(&old);
+    let mut patch = Vec::new();
+    ina::diff(&old_blob, &new, &mut patch)?;
+
+    let chunks = Patcher::new(io::Cursor::new(&old), patch.as_slice())?
+        .chunks(4096)
+        .collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(
+        chunks.iter().map(Vec::len).collect::<Vec<_>>(),
+        [4096, 4096, 1808],
+    );
+    assert_eq!(chunks.concat(), new);
+
+    // A chunk size dividing the new blob's length doesn't produce an empty last chunk
+    let chunks = Patcher::new(io::Cursor::new(&old), patch.as_slice())?.chunks(2500);
+    assert_eq!(chunks.count(), 4);
+
+    Ok(())
+}
+
+#[test]
+fn patcher_reports_written_ranges() -> Result<(), Box<dyn Error>> {
+    let old: Vec<u8> = (0..10_000u32).map(|i| (i % 251) as u8).collect();
+    let mut new = old.clone();
+    new[5000..5100].fill(7);
+    let old_blob = OldBlob::from_slice(&old);
+    let mut patch = Vec::new();
+    ina::diff(&old_blob, &new, &mut patch)?;
+
+    let mut ranges = Vec::new();
+    let chunks = Patcher::new(io::Cursor::new(&old), patch.as_slice())?
+        .on_write(|offset, len| ranges.push((offset, len)))
+        .chunks(3000)
+        .collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(chunks.concat(), new);
+
+    // Every byte returned is reported exactly once, in order
+    let mut end = 0;
+    for (offset, len) in ranges {
+        assert_eq!(offset, end);
+        assert!(len > 0);
+        end += len;
+    }
+    assert_eq!(end, new.len() as u64);
+
+    Ok(())
+}
+
+#[cfg(feature = "verify")]
+#[test]
+fn patcher_hashes_old_blob() -> Result<(), Box<dyn Error>> {
+    use ina::DigestAlgorithm;
+
+    let (old, edited) = common::binary_pair(3, 256 << 10);
+    // Sections moved backward and forward, a section dropped, and the end of the old blob unused
+    let (a, rest) = old.split_at(64 << 10);
+    let (b, rest) = rest.split_at(64 << 10);
+    let c = &rest[..64 << 10];
+    let moved = [c, a, &vec![7; 4096], b].concat();
+    let unrelated = (0..100_000u32).map(|i| (i * 7 % 253) as u8).collect();
+
+    for new in [edited, moved, unrelated] {
+        let mut patch = Vec::new();
+        ina::diff(&OldBlob::from_slice(&old), &new, &mut patch)?;
+
+        let mut patcher = Patcher::new(io::Cursor::new(&old), patch.as_slice())?
+            .hash_old(DigestAlgorithm::Blake3);
+        assert!(patcher.old_digest().is_err());
+        let mut patched = Vec::new();
+        io::copy(&mut patcher, &mut patched)?;
+        assert_eq!(patched, new);
+        assert_eq!(patcher.old_digest()?, DigestAlgorithm::Blake3.hash(&old));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn concatenated_patches() -> Result<(), Box<dyn Error>> {
+    let (v1, v2) = common::binary_pair(0, 64 << 10);
+    let mut hotfix = v2.clone();
+    hotfix[1000..1100].fill(7);
+
+    // The main delta records a checksum, which must still be checked although more data follows
+    let mut stream = Vec::new();
+    let config = DiffConfig::new().payload_checksum(true).clone();
+    ina::diff_with_config(&OldBlob::from_slice(&v1), &v2, &mut stream, &config)?;
+    ina::diff(&OldBlob::from_slice(&v2), &hotfix, &mut stream)?;
+
+    let mut patcher = Patcher::new(io::Cursor::new(&v1), stream.as_slice())?;
+    assert!(patcher.has_next_patch().is_err());
+    let mut new = Vec::new();
+    io::copy(&mut patcher, &mut new)?;
+    assert_eq!(new, v2);
+    assert!(patcher.has_next_patch()?);
+
+    let mut patcher = patcher.next_patch(io::Cursor::new(new))?.unwrap();
+    let mut new = Vec::new();
+    io::copy(&mut patcher, &mut new)?;
+    assert_eq!(new, hotfix);
+    assert!(!patcher.has_next_patch()?);
+    assert!(patcher.next_patch(io::Cursor::new(new))?.is_none());
+
+    Ok(())
+}
+
+#[test]
+fn truncated_patches_fail() -> Result<(), Box<dyn Error>> {
+    let (old, new) = common::binary_pair(6, 16 << 10);
+    let patch = ina::diff_to_vec(&OldBlob::from_slice(&old), &new, &DiffConfig::new())?;
+    let mut payload = patch.as_slice();
+    ina::read_header(&mut payload)?;
+    let payload_start = patch.len() - payload.len();
+
+    // Cutting off the patch anywhere in its data, including at the end of a control record, is
+    // reported rather than producing a shorter new blob
+    for len in payload_start..patch.len() {
+        let mut reconstructed_new = Vec::new();
+        let result = ina::patch(io::Cursor::new(&old), &patch[..len], &mut reconstructed_new);
+        let Err(PatchError::Io(e)) = result else {
+            panic!("truncation to {len} bytes went undetected: {result:?}");
+        };
+        assert!(
+            matches!(
+                e.get_ref().and_then(|e| e.downcast_ref()),
+                Some(PatchError::Truncated(_)),
+            ),
+            "truncation to {len} bytes wasn't reported as such: {e}",
+        );
+    }
+
+    // Data following a single patch is rejected
+    let mut trailing = patch.clone();
+    trailing.push(0);
+    let result = ina::patch_to_vec(&old, &trailing);
+    assert!(matches!(result, Err(PatchError::TrailingData)));
+
+    Ok(())
+}
+
+/// A reader which reads at most a few bytes at a time and often fails with errors which may be
+/// retried once past `calm_len` bytes
+struct FlakyReader<R> {
+    inner: R,
+    calm_len: u64,
+    pos: u64,
+    reads: u32,
+}
+
+impl<R> FlakyReader<R> {
+    fn new(inner: R, calm_len: u64) -> Self {
+        Self {
+            inner,
+            calm_len,
+            pos: 0,
+            reads: 0,
+        }
+    }
+}
+
+impl<R: Read> Read for FlakyReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.reads += 1;
+        if self.pos >= self.calm_len {
+            match self.reads % 5 {
+                0 => return Err(io::ErrorKind::WouldBlock.into()),
+                3 => return Err(io::ErrorKind::Interrupted.into()),
+                _ => {}
+            }
+        }
+
+        let len = buf.len().min(7);
+        let read = self.inner.read(&mut buf[..len])?;
+        self.pos += read as u64;
+        Ok(read)
+    }
+}
+
+/// Applies `patch` to `old` through readers which fail with retryable errors, retrying until the
+/// new blob has been read completely
+fn patch_flakily(old: &[u8], patch: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut data = patch;
+    ina::read_header(&mut data)?;
+    let header_len = (patch.len() - data.len()) as u64;
+
+    let patch = FlakyReader::new(patch, header_len);
+    let mut patcher = Patcher::new(io::Cursor::new(old), patch)?;
+    let mut new = Vec::new();
+    let mut buf = [0; 100];
+    let mut would_block = 0;
+    loop {
+        match patcher.read(&mut buf) {
+            Ok(0) => break,
+            Ok(read) => new.extend_from_slice(&buf[..read]),
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => would_block += 1,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    assert!(would_block > 0, "reads never blocked");
+
+    Ok(new)
+}
+
+#[test]
+fn patcher_resumes_after_would_block() -> Result<(), Box<dyn Error>> {
+    let (old, new) = common::binary_pair(13, 64 << 10);
+    let patch = ina::diff_to_vec(&OldBlob::from_slice(&old), &new, &DiffConfig::new())?;
+    assert_eq!(patch_flakily(&old, &patch)?, new);
+
+    #[cfg(feature = "fec")]
+    {
+        let config = DiffConfig::new().fec(Some(ina::FecConfig::new())).clone();
+        let patch = ina::diff_to_vec(&OldBlob::from_slice(&old), &new, &config)?;
+        assert_eq!(patch_flakily(&old, &patch)?, new);
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "bytes")]
+#[test]
+fn patcher_byte_chunks() -> Result<(), Box<dyn Error>> {
+    let old = b"Hello, world!";
+    let new = b"Hello, patched world!";
+    let old_blob = OldBlob::from_slice(old);
+    let mut patch = Vec::new();
+    ina::diff(&old_blob, new, &mut patch)?;
+
+    let chunks = Patcher::new(io::Cursor::new(old), patch.as_slice())?
+        .chunks(16)
+        .into_bytes()
+        .collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(chunks, [&new[..16], &new[16..]]);
+
+    Ok(())
+}
+
+#[cfg(feature = "verify")]
+#[test]
+fn verify_against_candidates() -> Result<(), Box<dyn Error>> {
+    let old = b"Hello, world!";
+    let new = vec![0x5a; 20_000];
+    let old_blob = OldBlob::from_slice(old);
+    let mut patch = Vec::new();
+    ina::diff(&old_blob, &new, &mut patch)?;
+
+    let verify = |candidate: &[u8]| -> Result<Option<u64>, Box<dyn Error>> {
+        let patcher = Patcher::new(io::Cursor::new(old), patch.as_slice())?;
+        Ok(ina::verify_against(patcher, candidate)?)
+    };
+
+    let mut modified = new.clone();
+    modified[12_345] = 0;
+    let mut longer = new.clone();
+    longer.push(0);
+
+    assert_eq!(verify(&new)?, None);
+    assert_eq!(verify(&modified)?, Some(12_345));
+    assert_eq!(verify(&new[..10_000])?, Some(10_000));
+    assert_eq!(verify(&longer)?, Some(20_000));
+
+    Ok(())
+}
+
+#[test]
+fn payload_checksum_detects_corruption() -> Result<(), Box<dyn Error>> {
+    let old = b"Hello, world!";
+    let new = b"Hello, checksummed world!";
+    let old_blob = OldBlob::from_slice(old);
+    let mut patch = Vec::new();
+    let config = DiffConfig::new().payload_checksum(true).clone();
+    ina::diff_with_config(&old_blob, new, &mut patch, &config)?;
+
+    let apply = |patch: &[u8]| -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut reconstructed_new = Vec::new();
+        ina::patch(io::Cursor::new(old), patch, &mut reconstructed_new)?;
+        Ok(reconstructed_new)
+    };
+    assert_eq!(apply(&patch)?, new);
+
+    let mut payload = patch.as_slice();
+    assert!(ina::read_header(&mut payload)?.has_payload_checksum());
+    let payload_start = patch.len() - payload.len();
+
+    for i in payload_start..patch.len() {
+        let mut corrupted = patch.clone();
+        corrupted[i] ^= 0x01;
+        assert!(
+            apply(&corrupted).is_err(),
+            "corruption at offset {i} went undetected"
+        );
+    }
+    for len in payload_start..patch.len() {
+        assert!(
+            apply(&patch[..len]).is_err(),
+            "truncation to {len} bytes went undetected"
+        );
+    }
+    let mut extended = patch.clone();
+    extended.push(0);
+    assert!(apply(&extended).is_err());
+
+    Ok(())
+}
+
+#[cfg(feature = "fec")]
+#[test]
+fn payload_checksum_with_fec() -> Result<(), Box<dyn Error>> {
+    use ina::FecConfig;
+
+    let old = b"Hello, world!";
+    let new = vec![0x5a; 20_000];
+    let old_blob = OldBlob::from_slice(old);
+    let mut patch = Vec::new();
+    let config = DiffConfig::new()
+        .payload_checksum(true)
+        .fec(Some(FecConfig::new()))
+        .clone();
+    ina::diff_with_config(&old_blob, &new, &mut patch, &config)?;
+
+    let mut reconstructed_new = Vec::new();
+    ina::patch(
+        io::Cursor::new(old),
+        patch.as_slice(),
+        &mut reconstructed_new,
+    )?;
+    assert_eq!(reconstructed_new, new);
+
+    Ok(())
+}
+
+#[cfg(all(feature = "index-mapped", feature = "mmap"))]
+#[test]
+fn diff_with_mapped_index() -> Result<(), Box<dyn Error>> {
+    use ina::{MappedFile, index::SuffixArray};
+
+    let old = b"The quick brown fox jumps over the lazy dog\0";
+    let new = b"The quick red fox leaps over the lazy cat";
+
+    let index_path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("quick-brown-fox.sa");
+    fs::write(&index_path, SuffixArray::new(old).suffix_bytes())?;
+    // SAFETY: The index file isn't modified while it's mapped
+    let index_map = unsafe { MappedFile::map(&File::open(&index_path)?)? };
+    let index = SuffixArray::from_suffix_bytes(old, index_map.as_slice())
+        .ok_or("mapped index is invalid")?;
+
+    let config = DiffConfig::new().compression_level(1).clone();
+    let mut patch = Vec::new();
+    let mut indexed_patch = Vec::new();
+    ina::diff_with_config(
+        &OldBlob::from_slice(&old[..old.len() - 1]),
+        new,
+        &mut patch,
+        &config,
+    )?;
+    ina::diff_with_index(&index, new, &mut indexed_patch, &config)?;
+    assert_eq!(indexed_patch, patch);
+
+    let mask_config = config
+        .clone()
+        .mask_ranges([0..4, 10..14], [])
+        .zero_masked(true)
+        .clone();
+    let err = ina::diff_with_index(&index, new, &mut Vec::new(), &mask_config).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+    Ok(())
+}
+
+#[test]
+fn seek_stats_of_moved_sections() -> Result<(), Box<dyn Error>> {
+    let (old, _) = common::binary_pair(0, 64 << 10);
+    // Swap the halves of the old blob so that applying the patch seeks forward, then backward
+    let half = old.len() / 2;
+    let new = [&old[half..], &old[..half]].concat();
+    let old_blob = OldBlob::from_slice(&old);
+
+    let mut patch = Vec::new();
+    let stats = ina::diff(&old_blob, &new, &mut patch)?;
+    let seeks = stats.seeks();
+    assert!(seeks.seeks() >= 2);
+    assert!(seeks.max_forward() >= half as u64 - 64);
+    assert!(seeks.max_backward() >= old.len() as u64 - 64);
+    assert_eq!(
+        seeks.forward().count() + seeks.backward().count(),
+        seeks.seeks()
+    );
+
+    Ok(())
+}
+
+#[test]
+fn max_backward_seek_bounds_seeks() -> Result<(), Box<dyn Error>> {
+    let (old, _) = common::binary_pair(1, 64 << 10);
+    // Reverse the order of the old blob's quarters so that every match lies before the last one
+    let quarters: Vec<_> = old.chunks(old.len().div_ceil(4)).rev().collect();
+    let new = quarters.concat();
+    let old_blob = OldBlob::from_slice(&old);
+
+    for max_backward_seek in [0, 4096] {
+        let mut config = DiffConfig::new();
+        config.max_backward_seek(Some(max_backward_seek));
+
+        let mut patch = Vec::new();
+        let stats = ina::diff_with_config(&old_blob, &new, &mut patch, &config)?;
+        assert!(stats.seeks().max_backward() <= max_backward_seek);
+        let mut reconstructed_new = Vec::new();
+        ina::patch(
+            io::Cursor::new(&old),
+            patch.as_slice(),
+            &mut reconstructed_new,
+        )?;
+        assert_eq!(reconstructed_new, new);
+
+        config.diff_window_len(16 << 10);
+        let mut patch = Vec::new();
+        let stats = ina::diff_windowed(
+            io::Cursor::new(&old),
+            io::Cursor::new(&new),
+            &mut patch,
+            &config,
+        )?;
+        assert!(stats.seeks().max_backward() <= max_backward_seek);
+        let mut reconstructed_new = Vec::new();
+        ina::patch(
+            io::Cursor::new(&old),
+            patch.as_slice(),
+            &mut reconstructed_new,
+        )?;
+        assert_eq!(reconstructed_new, new);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn compare_finds_shared_regions() -> Result<(), Box<dyn Error>> {
+    let (old, _) = common::binary_pair(3, 64 << 10);
+    let mut rng = common::Rng::new(3);
+    let inserted: Vec<u8> = (0..3000).map(|_| rng.between(0..256) as u8).collect();
+    // Insert unrelated data and drop the old blob's last quarter
+    let new = [&old[..16 << 10], &inserted, &old[16 << 10..48 << 10]].concat();
+
+    let comparison = ina::compare(&OldBlob::from_slice(&old), &new, &DiffConfig::new());
+    assert_eq!(comparison.old_len(), old.len() as u64);
+    assert_eq!(comparison.new_len(), new.len() as u64);
+    assert!(comparison.similarity() > 0.9);
+    for m in comparison.matches() {
+        assert!(m.similarity() > 0.0 && m.similarity() <= 1.0);
+        assert_eq!(
+            m.old_range().end - m.old_range().start,
+            m.new_range().end - m.new_range().start
+        );
+    }
+
+    // The matched and unmatched regions of the new blob partition it
+    let mut regions: Vec<_> = comparison
+        .matches()
+        .iter()
+        .map(|m| m.new_range())
+        .chain(comparison.new_unmatched())
+        .collect();
+    regions.sort_by_key(|range| range.start);
+    let mut end = 0;
+    for range in regions {
+        assert_eq!(range.start, end);
+        end = range.end;
+    }
+    assert_eq!(end, new.len() as u64);
+
+    // Most of the inserted data and the dropped quarter are unmatched
+    let unmatched_len = |ranges: Vec<std::ops::Range<u64>>| -> u64 {
+        ranges.iter().map(|range| range.end - range.start).sum()
+    };
+    assert!(unmatched_len(comparison.new_unmatched()) >= 2000);
+    assert!(unmatched_len(comparison.old_unmatched()) >= 15 << 10);
+
+    Ok(())
+}
+
+#[test]
+fn trivial_diffs_take_fast_paths() -> Result<(), Box<dyn Error>> {
+    let (old, changed) = common::binary_pair(17, 64 << 10);
+    let mut appended = old.clone();
+    appended.extend_from_slice(&changed[..4096]);
+
+    for (new, fast_path) in [
+        (&old, Some(FastPath::Identical)),
+        (&appended, Some(FastPath::Append)),
+        (&changed, None),
+    ] {
+        let mut patch = Vec::new();
+        let stats = ina::diff(&OldBlob::from_slice(&old), new, &mut patch)?;
+        assert_eq!(stats.fast_path(), fast_path);
+        if fast_path.is_some() {
+            assert_eq!(stats.control_records(), 1);
+        }
+
+        let mut reconstructed_new = Vec::new();
+        ina::patch(
+            io::Cursor::new(&old),
+            patch.as_slice(),
+            &mut reconstructed_new,
+        )?;
+        assert_eq!(&reconstructed_new, new);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn annotations_round_trip() -> Result<(), Box<dyn Error>> {
+    let old = OldBlob::from_slice(b"Hello, world!");
+    let new = b"Hello, patched world!";
+    let notes = "Release 2.0\n\n- Patched the world ✓\n";
+
+    let config = DiffConfig::new().annotation(Some(notes)).clone();
+    let patch = ina::diff_to_vec(&old, new, &config)?;
+    let metadata = ina::read_header(&mut patch.as_slice())?;
+    assert_eq!(metadata.annotation(), Some(notes));
+    let mut reconstructed_new = Vec::new();
+    ina::patch(
+        io::Cursor::new(old.as_bytes()),
+        patch.as_slice(),
+        &mut reconstructed_new,
+    )?;
+    assert_eq!(reconstructed_new, new);
+
+    let too_long = "a".repeat((1 << 20) + 1);
+    let config = DiffConfig::new().annotation(Some(&too_long)).clone();
+    let error = ina::diff_to_vec(&old, new, &config).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
 
     Ok(())
 }
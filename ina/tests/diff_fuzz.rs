@@ -0,0 +1,159 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Differential checks of the diff side against mutated inputs
+//!
+//! These complement the coverage-guided target in `fuzz/` with a fixed set of cases that runs with
+//! the rest of the tests. Each case mutates a generated old blob into a new one, diffs them with
+//! various configurations, and checks that applying the patch reproduces the new blob exactly.
+
+#![allow(missing_docs)]
+
+mod common;
+
+use std::{error::Error, io::Cursor};
+
+use common::Rng;
+use ina::{DiffConfig, OldBlob, TextMode};
+
+/// The number of generated cases
+const CASES: u64 = 200;
+
+/// Generates an old blob of one of several shapes which exercise different parts of the matcher
+fn generate_old(rng: &mut Rng) -> Vec<u8> {
+    let len = rng.between(0..4096);
+    match rng.below(6) {
+        // Incompressible data
+        0 => (0..len).map(|_| rng.next_u64() as u8).collect(),
+        // A single repeated byte, including the sentinel value
+        1 => vec![[0, 0xff][rng.below(2) as usize]; len],
+        // A short repeating pattern, which has many equally long matches
+        2 => {
+            let period = rng.between(1..17);
+            let pattern: Vec<u8> = (0..period).map(|_| rng.next_u64() as u8).collect();
+            pattern.iter().copied().cycle().take(len).collect()
+        }
+        // A small alphabet, which has many short matches
+        3 => (0..len).map(|_| b'a' + rng.below(3) as u8).collect(),
+        // Lines of text
+        4 => {
+            let mut old = Vec::with_capacity(len);
+            while old.len() < len {
+                old.extend_from_slice(format!("line {}\n", rng.below(64)).as_bytes());
+            }
+            old
+        }
+        _ => common::binary_pair(rng.next_u64(), len).0,
+    }
+}
+
+/// Mutates `old` into a new blob with a random number of edits
+fn mutate(rng: &mut Rng, old: &[u8]) -> Vec<u8> {
+    let mut new = old.to_vec();
+    for _ in 0..rng.below(8) {
+        let pos = rng.between(0..new.len() + 1);
+        let len = rng.between(0..256);
+        match rng.below(7) {
+            // Overwritten bytes
+            0 => {
+                let end = (pos + len).min(new.len());
+                new[pos..end]
+                    .iter_mut()
+                    .for_each(|b| *b = rng.next_u64() as u8);
+            }
+            // Inserted random bytes
+            1 => {
+                let inserted: Vec<u8> = (0..len).map(|_| rng.next_u64() as u8).collect();
+                new.splice(pos..pos, inserted);
+            }
+            // Removed bytes
+            2 => {
+                new.drain(pos..(pos + len).min(new.len()));
+            }
+            // A region of the old blob copied elsewhere
+            3 if !old.is_empty() => {
+                let start = rng.between(0..old.len());
+                let end = (start + len).min(old.len());
+                new.splice(pos..pos, old[start..end].iter().copied());
+            }
+            // A copy of the old blob appended to itself, which is periodic with its whole length
+            4 => new.extend_from_slice(old),
+            // Truncation
+            5 => new.truncate(pos),
+            // Bytes added to every byte of a region, like shifted displacements
+            _ => {
+                let shift = rng.between(1..256) as u8;
+                let end = (pos + len).min(new.len());
+                new[pos..end]
+                    .iter_mut()
+                    .for_each(|b| *b = b.wrapping_add(shift));
+            }
+        }
+    }
+
+    new
+}
+
+/// Returns the configurations each case is diffed with, along with whether they're supported by
+/// windowed diffs
+fn configs(rng: &mut Rng) -> Vec<(DiffConfig, bool)> {
+    let window_len = rng.between(1..2048) as u64;
+    let base = DiffConfig::new()
+        .compression_level(1)
+        .diff_window_len(window_len)
+        .clone();
+
+    vec![
+        (base.clone(), true),
+        (
+            base.clone().match_threshold(rng.between(1..32)).clone(),
+            true,
+        ),
+        (
+            base.clone()
+                .max_backward_seek(Some(rng.below(1024)))
+                .clone(),
+            true,
+        ),
+        (base.clone().text_mode(TextMode::Text).clone(), false),
+    ]
+}
+
+/// Applies `patch` to `old`
+fn apply(old: &[u8], patch: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut new = Vec::new();
+    ina::patch(Cursor::new(old), patch, &mut new)?;
+    Ok(new)
+}
+
+#[test]
+fn diff_round_trips_mutated_inputs() -> Result<(), Box<dyn Error>> {
+    for seed in 0..CASES {
+        let mut rng = Rng::new(seed);
+        let old = generate_old(&mut rng);
+        let new = mutate(&mut rng, &old);
+        let old_blob = OldBlob::from_slice(&old);
+
+        for (i, (config, windowed)) in configs(&mut rng).iter().enumerate() {
+            let mut patch = Vec::new();
+            ina::diff_with_config(&old_blob, &new, &mut patch, config)?;
+            assert!(
+                apply(&old, &patch)? == new,
+                "case {seed} with configuration {i} doesn't round-trip",
+            );
+
+            if !windowed {
+                continue;
+            }
+            let mut patch = Vec::new();
+            ina::diff_windowed(Cursor::new(&old), Cursor::new(&new), &mut patch, config)?;
+            assert!(
+                apply(&old, &patch)? == new,
+                "windowed case {seed} with configuration {i} doesn't round-trip",
+            );
+        }
+    }
+
+    Ok(())
+}
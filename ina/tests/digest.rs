@@ -0,0 +1,86 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+#![cfg(all(feature = "bundle", feature = "sha256", feature = "verify"))]
+#![allow(missing_docs)]
+
+use std::{error::Error, io::Cursor};
+
+use ina::{BundleReader, BundleWriter, DiffConfig, DigestAlgorithm, OldBlob, VerifyOptions};
+
+#[test]
+fn digest_algorithms_match_reference_digests() {
+    assert_eq!(
+        DigestAlgorithm::Sha256.hash(b"abc"),
+        [
+            0xba, 0x78, 0x16, 0xbf, 0x8f, 0x01, 0xcf, 0xea, 0x41, 0x41, 0x40, 0xde, 0x5d, 0xae,
+            0x22, 0x23, 0xb0, 0x03, 0x61, 0xa3, 0x96, 0x17, 0x7a, 0x9c, 0xb4, 0x10, 0xff, 0x61,
+            0xf2, 0x00, 0x15, 0xad,
+        ],
+    );
+    assert_eq!(
+        &DigestAlgorithm::Blake3.hash(b"abc"),
+        blake3::hash(b"abc").as_bytes(),
+    );
+
+    for algorithm in [DigestAlgorithm::Blake3, DigestAlgorithm::Sha256] {
+        assert_eq!(DigestAlgorithm::from_id(algorithm.id()), Some(algorithm));
+
+        let mut hasher = algorithm.hasher();
+        hasher.update(b"a");
+        hasher.update(b"bc");
+        assert_eq!(hasher.finalize(), algorithm.hash(b"abc"));
+        assert_eq!(hasher.algorithm(), algorithm);
+    }
+    assert_eq!(DigestAlgorithm::from_id(0), None);
+}
+
+#[test]
+fn verify_with_sha256() -> Result<(), Box<dyn Error>> {
+    let mut patch = Vec::new();
+    ina::diff(&OldBlob::from_slice(b"Hello"), b"Hero", &mut patch)?;
+
+    let expected = DigestAlgorithm::Sha256.hash(b"Hero");
+    let options = *VerifyOptions::new().digest_algorithm(DigestAlgorithm::Sha256);
+    assert!(ina::verify(
+        Cursor::new(b"Hello"),
+        patch.as_slice(),
+        &expected,
+        &options,
+    )?);
+    // The same digest doesn't verify as a BLAKE3 digest
+    assert!(!ina::verify(
+        Cursor::new(b"Hello"),
+        patch.as_slice(),
+        &expected,
+        &VerifyOptions::new(),
+    )?);
+
+    Ok(())
+}
+
+#[test]
+fn bundles_record_digest_algorithm() -> Result<(), Box<dyn Error>> {
+    for algorithm in [DigestAlgorithm::Blake3, DigestAlgorithm::Sha256] {
+        let mut writer = BundleWriter::with_digest_algorithm(Vec::new(), algorithm)?;
+        writer.add("app", Some(b"version 1"), b"version 2", &DiffConfig::new())?;
+        let bundle = writer.finish()?;
+
+        // BLAKE3 bundles remain readable by readers which predate the choice of algorithm
+        let major = u16::from_le_bytes([bundle[4], bundle[5]]);
+        let expected_major = if algorithm == DigestAlgorithm::Blake3 {
+            1
+        } else {
+            2
+        };
+        assert_eq!(major, expected_major);
+
+        let mut reader = BundleReader::new(bundle.as_slice())?;
+        assert_eq!(reader.digest_algorithm(), algorithm);
+        let entry = reader.next_entry()?.unwrap();
+        assert_eq!(entry.new_digest(), Some(&algorithm.hash(b"version 2")));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,36 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+#![allow(missing_docs)]
+
+use std::{error::Error, io};
+
+use ina::{OldBlob, Patcher};
+
+#[test]
+fn patcher_aligned_blocks() -> Result<(), Box<dyn Error>> {
+    let old: Vec<u8> = (0..10_000u32).map(|i| (i % 251) as u8).collect();
+    let mut new = old.clone();
+    new[5000..5100].fill(7);
+    let old_blob = OldBlob::from_slice(&old);
+    let mut patch = Vec::new();
+    ina::diff(&old_blob, &new, &mut patch)?;
+
+    let mut blocks =
+        Patcher::new(io::Cursor::new(&old), patch.as_slice())?.aligned_blocks(4096, 512);
+    let mut data = Vec::new();
+    let mut padded_lens = Vec::new();
+    while let Some(block) = blocks.next_block()? {
+        assert_eq!(block.padded().as_ptr() as usize % 512, 0);
+        assert_eq!(&block.padded()[..block.data().len()], block.data());
+        assert!(block.padded()[block.data().len()..].iter().all(|&b| b == 0));
+        data.extend_from_slice(block.data());
+        padded_lens.push(block.padded().len());
+    }
+    assert_eq!(padded_lens, [4096, 4096, 2048]);
+    assert_eq!(data, new);
+    assert!(blocks.next_block()?.is_none());
+
+    Ok(())
+}
@@ -0,0 +1,250 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+#![allow(missing_docs)]
+
+mod common;
+
+use std::{
+    error::Error,
+    future::Future,
+    io::{self, ErrorKind},
+    pin::pin,
+    sync::{
+        Arc, Condvar, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    task::{Context, Poll, Wake},
+    thread::{self, Thread},
+    time::Duration,
+};
+
+use ina::{DiffConfig, DiffEngine, DiffStats, EngineMetrics, EngineOptions, OldBlob, OldRef};
+
+fn old(seed: u32) -> OldBlob {
+    OldBlob::from_vec(common::periodic_blob(10_000, seed))
+}
+
+fn new(old: &OldBlob) -> Vec<u8> {
+    common::edited_blob(old.as_bytes(), 2000..2100)
+}
+
+/// Wakes the thread blocked on a future
+struct ThreadWaker(Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+/// Polls `future` on the current thread until it completes
+fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = pin!(future);
+    let waker = Arc::new(ThreadWaker(thread::current())).into();
+    let mut cx = Context::from_waker(&waker);
+    loop {
+        match future.as_mut().poll(&mut cx) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => thread::park(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct CountingMetrics {
+    submitted: AtomicU64,
+    finished: AtomicU64,
+    failed: AtomicU64,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    evictions: AtomicU64,
+    cache_len: AtomicU64,
+}
+
+impl EngineMetrics for CountingMetrics {
+    fn job_submitted(&self) {
+        self.submitted.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn job_finished(&self, _: Duration, result: Result<&DiffStats, &io::Error>) {
+        self.finished.fetch_add(1, Ordering::Relaxed);
+        if result.is_err() {
+            self.failed.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    fn index_cache_hit(&self) {
+        self.hits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn index_cache_miss(&self, _: Duration) {
+        self.misses.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn index_cache_eviction(&self, _: u64) {
+        self.evictions.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn index_cache_len(&self, len: u64) {
+        self.cache_len.store(len, Ordering::Relaxed);
+    }
+}
+
+#[test]
+fn jobs_produce_the_same_patches_as_diffing_directly() -> Result<(), Box<dyn Error>> {
+    let engine = DiffEngine::new(EngineOptions::new().threads(2))?;
+    let refs = [old(7), old(13)].map(OldRef::new);
+
+    let jobs: Vec<_> = (0..8)
+        .map(|i| {
+            let old = &refs[i % refs.len()];
+            (old, engine.submit(old, new(old.blob())))
+        })
+        .collect();
+    for (old, job) in jobs {
+        let result = block_on(job)?;
+        let new = new(old.blob());
+        assert_eq!(
+            result.patch(),
+            ina::diff_to_vec(old.blob(), &new, &DiffConfig::new())?,
+        );
+        assert_eq!(
+            ina::patch_to_vec(old.blob().as_bytes(), result.patch())?,
+            new
+        );
+        assert_eq!(result.stats().new_len(), new.len() as u64);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn jobs_use_their_own_config() -> Result<(), Box<dyn Error>> {
+    let config = DiffConfig::new().compression_level(1).clone();
+    let engine = DiffEngine::new(EngineOptions::new().threads(1).config(&config))?;
+    let old = OldRef::new(old(7));
+    let new = new(old.blob());
+
+    let result = engine.submit(&old, new.clone()).wait()?;
+    assert_eq!(result.patch(), ina::diff_to_vec(old.blob(), &new, &config)?);
+
+    let overridden = DiffConfig::new().compression_level(19).clone();
+    let result = engine
+        .submit_with_config(&old, new.clone(), &overridden)
+        .wait()?;
+    assert_eq!(
+        result.patch(),
+        ina::diff_to_vec(old.blob(), &new, &overridden)?
+    );
+
+    // The cached index can't reflect masked regions of the old blob being zeroed
+    let zeroed = DiffConfig::new()
+        .mask_ranges([0..100, 200..300], [])
+        .zero_masked(true)
+        .clone();
+    let error = engine
+        .submit_with_config(&old, new, &zeroed)
+        .wait()
+        .unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidInput);
+
+    Ok(())
+}
+
+#[test]
+fn least_recently_used_indexes_are_evicted() -> Result<(), Box<dyn Error>> {
+    let metrics = Arc::new(CountingMetrics::default());
+    // The old blobs and their indexes take 50,005 bytes each, so only one of them fits
+    let options = EngineOptions::new()
+        .threads(1)
+        .index_cache_limit(60_000)
+        .clone();
+    let engine = DiffEngine::with_metrics(&options, Arc::<CountingMetrics>::clone(&metrics))?;
+    let first = OldRef::new(old(7));
+    let second = OldRef::new(old(13));
+
+    let mut cached = Vec::new();
+    for old in [&first, &first, &second, &first] {
+        let result = engine.submit(old, new(old.blob())).wait()?;
+        cached.push(result.index_cached());
+    }
+
+    assert_eq!(cached, [false, true, false, false]);
+    assert_eq!(metrics.hits.load(Ordering::Relaxed), 1);
+    assert_eq!(metrics.misses.load(Ordering::Relaxed), 3);
+    assert_eq!(metrics.evictions.load(Ordering::Relaxed), 2);
+    assert_eq!(metrics.cache_len.load(Ordering::Relaxed), 50_005);
+    assert_eq!(engine.index_cache_len(), 50_005);
+
+    Ok(())
+}
+
+#[test]
+fn dropping_the_engine_finishes_submitted_jobs() -> Result<(), Box<dyn Error>> {
+    let metrics = Arc::new(CountingMetrics::default());
+    let engine = DiffEngine::with_metrics(
+        EngineOptions::new().threads(1),
+        Arc::<CountingMetrics>::clone(&metrics),
+    )?;
+    let old = OldRef::new(old(7));
+
+    let jobs: Vec<_> = (0..4)
+        .map(|_| engine.submit(&old, new(old.blob())))
+        .collect();
+    drop(engine);
+
+    for job in jobs {
+        assert!(job.is_finished());
+        job.wait()?;
+    }
+    assert_eq!(metrics.submitted.load(Ordering::Relaxed), 4);
+    assert_eq!(metrics.finished.load(Ordering::Relaxed), 4);
+    assert_eq!(metrics.failed.load(Ordering::Relaxed), 0);
+
+    Ok(())
+}
+
+/// Holds each job as it starts until another job has started too, giving up after a timeout
+#[derive(Default)]
+struct OverlapMetrics {
+    started: Mutex<u64>,
+    all_started: Condvar,
+    overlapped: AtomicU64,
+}
+
+impl EngineMetrics for OverlapMetrics {
+    fn job_started(&self, _: Duration) {
+        let mut started = self.started.lock().unwrap();
+        *started += 1;
+        self.all_started.notify_all();
+        let (started, _) = self
+            .all_started
+            .wait_timeout_while(started, Duration::from_secs(10), |started| *started < 2)
+            .unwrap();
+        if *started >= 2 {
+            self.overlapped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+#[test]
+fn jobs_run_concurrently() -> Result<(), Box<dyn Error>> {
+    let metrics = Arc::new(OverlapMetrics::default());
+    let engine = DiffEngine::with_metrics(
+        EngineOptions::new().threads(2),
+        Arc::<OverlapMetrics>::clone(&metrics),
+    )?;
+    let old = OldRef::new(old(7));
+
+    let first = engine.submit(&old, new(old.blob()));
+    let second = engine.submit(&old, new(old.blob()));
+    first.wait()?;
+    second.wait()?;
+
+    // Each job only saw the other one start if they ran at the same time
+    assert_eq!(metrics.overlapped.load(Ordering::Relaxed), 2);
+
+    Ok(())
+}
@@ -0,0 +1,129 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+#![allow(missing_docs)]
+
+mod common;
+
+use std::{error::Error, io::Cursor};
+
+use common::Rng;
+use ina::{DiffConfig, FecConfig, OldBlob};
+
+const BLOCK_SIZE: usize = 64;
+const DATA_BLOCKS: usize = 4;
+const PARITY_BLOCKS: usize = 2;
+/// The length of a block followed by its checksum
+const STORED_BLOCK_LEN: usize = BLOCK_SIZE + 4;
+
+/// Creates a patch from random data, whose payload can't be compressed, along with the new blob
+fn fec_patch(new_len: usize) -> Result<(Vec<u8>, Vec<u8>), Box<dyn Error>> {
+    let mut rng = Rng::new(new_len as u64);
+    let new: Vec<u8> = (0..new_len).map(|_| rng.next_u64() as u8).collect();
+
+    let mut fec = FecConfig::new();
+    fec.block_size(BLOCK_SIZE as u32)
+        .data_blocks(DATA_BLOCKS as u16)
+        .parity_blocks(PARITY_BLOCKS as u16);
+    let mut patch = Vec::new();
+    ina::diff_with_config(
+        &OldBlob::from_slice(b"old"),
+        &new,
+        &mut patch,
+        DiffConfig::new().fec(Some(fec)),
+    )?;
+
+    Ok((patch, new))
+}
+
+/// Returns the offsets of the groups of blocks of `patch`, whose payload follows its header, along
+/// with the number of data blocks in each group
+fn groups(patch: &[u8]) -> Result<Vec<(usize, usize)>, Box<dyn Error>> {
+    let mut payload = patch;
+    ina::read_header(&mut payload)?;
+    let start = patch.len() - payload.len();
+    assert_eq!(payload.len() % STORED_BLOCK_LEN, 0);
+
+    let mut blocks = payload.len() / STORED_BLOCK_LEN;
+    let mut groups = Vec::new();
+    let mut group_start = start;
+    while blocks > 0 {
+        let group_blocks = blocks.min(DATA_BLOCKS + PARITY_BLOCKS);
+        groups.push((group_start, group_blocks - PARITY_BLOCKS));
+        group_start += group_blocks * STORED_BLOCK_LEN;
+        blocks -= group_blocks;
+    }
+
+    Ok(groups)
+}
+
+/// Flips a byte inside block `block` of the group starting at `group_start`
+fn corrupt_block(patch: &mut [u8], group_start: usize, block: usize) {
+    patch[group_start + block * STORED_BLOCK_LEN + block * 7 % BLOCK_SIZE] ^= 0xff;
+}
+
+fn apply(patch: &[u8]) -> Result<Vec<u8>, ina::PatchError> {
+    let mut new = Vec::new();
+    ina::patch(Cursor::new(b"old"), patch, &mut new)?;
+
+    Ok(new)
+}
+
+#[test]
+fn corrupted_data_blocks_are_corrected() -> Result<(), Box<dyn Error>> {
+    let (mut patch, new) = fec_patch(900)?;
+    let groups = groups(&patch)?;
+    // The last group is short
+    assert!(groups.len() > 1);
+    assert!(groups.last().unwrap().1 < DATA_BLOCKS);
+
+    for &(group_start, data_blocks) in &groups {
+        for block in 0..PARITY_BLOCKS.min(data_blocks) {
+            corrupt_block(&mut patch, group_start, block);
+        }
+    }
+    assert_eq!(apply(&patch)?, new);
+
+    Ok(())
+}
+
+#[test]
+fn corrupted_blocks_of_any_kind_are_corrected() -> Result<(), Box<dyn Error>> {
+    // A single data block, which is followed by the parity blocks
+    let (clean_patch, new) = fec_patch(40)?;
+    let groups = groups(&clean_patch)?;
+    assert_eq!(groups.len(), 1);
+    let (group_start, data_blocks) = groups[0];
+    assert_eq!(data_blocks, 1);
+
+    for corrupted in [[0, 1], [0, 2], [1, 2]] {
+        let mut patch = clean_patch.clone();
+        for block in corrupted {
+            corrupt_block(&mut patch, group_start, block);
+        }
+        assert_eq!(apply(&patch)?, new, "{corrupted:?}");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn too_many_corrupted_blocks_are_rejected() -> Result<(), Box<dyn Error>> {
+    let (mut patch, _) = fec_patch(1000)?;
+    let groups = groups(&patch)?;
+
+    let (group_start, _) = groups[1];
+    for block in 0..=PARITY_BLOCKS {
+        corrupt_block(&mut patch, group_start, block);
+    }
+    let error = apply(&patch).unwrap_err();
+    assert!(
+        error
+            .to_string()
+            .contains("too many corrupted blocks to correct"),
+        "{error}",
+    );
+
+    Ok(())
+}
@@ -0,0 +1,97 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+#![allow(missing_docs)]
+
+use std::{
+    error::Error,
+    fs::File,
+    io::Cursor,
+    path::Path,
+    time::{Duration, UNIX_EPOCH},
+};
+
+use ina::{DiffConfig, FileMetadata, OldBlob};
+
+fn diff_with_metadata(metadata: FileMetadata) -> Result<Vec<u8>, Box<dyn Error>> {
+    let options = DiffConfig::new().file_metadata(Some(metadata)).clone();
+
+    Ok(ina::diff_to_vec(
+        &OldBlob::from_slice(b"Hello"),
+        b"Hero",
+        &options,
+    )?)
+}
+
+/// Applies `patch` to a file named `name`, restoring the file metadata recorded in it
+fn patch_to_file(name: &str, patch: &[u8]) -> Result<File, Box<dyn Error>> {
+    let path = Path::new(env!("CARGO_TARGET_TMPDIR")).join(name);
+    let mut new = File::create(&path)?;
+    ina::patch_with_hook(
+        Cursor::new(b"Hello"),
+        patch,
+        &mut new,
+        ina::restore_file_metadata,
+    )?;
+
+    Ok(File::open(&path)?)
+}
+
+#[test]
+fn mode_and_modified_time_round_trip() -> Result<(), Box<dyn Error>> {
+    let modified = UNIX_EPOCH + Duration::new(1_700_000_000, 123_456_789);
+    let metadata = FileMetadata::new(Some(0o751), Some(modified));
+    let patch = diff_with_metadata(metadata)?;
+    assert_eq!(
+        ina::read_header(&mut patch.as_slice())?.file_metadata(),
+        Some(metadata),
+    );
+
+    let new = patch_to_file("metadata_round_trip.new", &patch)?;
+    let new_metadata = new.metadata()?;
+    assert_eq!(new_metadata.modified()?, modified);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        assert_eq!(new_metadata.permissions().mode() & 0o7777, 0o751);
+    }
+    assert_eq!(
+        FileMetadata::from_metadata(&new_metadata).modified(),
+        Some(modified),
+    );
+
+    Ok(())
+}
+
+#[test]
+fn special_mode_bits_are_dropped() -> Result<(), Box<dyn Error>> {
+    // The setuid, setgid, and sticky bits aren't recorded
+    assert_eq!(FileMetadata::new(Some(0o7755), None).mode(), Some(0o755));
+
+    // Set the setuid bit in the mode recorded in a patch, which is a varint with tag 1
+    let mut patch = diff_with_metadata(FileMetadata::new(Some(0o755), None))?;
+    let field = [0x01, 0x02, 0xed, 0x03];
+    let pos = patch
+        .windows(field.len())
+        .position(|window| window == field)
+        .unwrap();
+    patch[pos + 3] = 0x13;
+
+    assert_eq!(
+        ina::read_header(&mut patch.as_slice())?
+            .file_metadata()
+            .and_then(|metadata| metadata.mode()),
+        Some(0o755),
+    );
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        let new = patch_to_file("metadata_setuid.new", &patch)?;
+        assert_eq!(new.metadata()?.permissions().mode() & 0o7777, 0o755);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,101 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+#![allow(missing_docs)]
+
+use std::{
+    error::Error,
+    fs,
+    io::Cursor,
+    path::Path,
+    time::{Duration, UNIX_EPOCH},
+};
+
+use ina::{DiffConfig, FileMetadata, FormatFeature, OldBlob, Patcher};
+
+const VECTORS: [&str; 4] = ["basic", "empty", "metadata", "seek"];
+
+fn read_vector(name: &str) -> Result<[Vec<u8>; 3], Box<dyn Error>> {
+    let golden_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("golden");
+
+    Ok([
+        fs::read(golden_dir.join(format!("{name}.old")))?,
+        fs::read(golden_dir.join(format!("{name}.ina")))?,
+        fs::read(golden_dir.join(format!("{name}.new")))?,
+    ])
+}
+
+#[test]
+fn apply_golden_patches() -> Result<(), Box<dyn Error>> {
+    for name in VECTORS {
+        let [old, patch, new] = read_vector(name)?;
+
+        let mut reconstructed_new = Vec::new();
+        ina::patch(Cursor::new(old), patch.as_slice(), &mut reconstructed_new)?;
+
+        assert_eq!(reconstructed_new, new, "golden vector {name}");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn golden_file_metadata() -> Result<(), Box<dyn Error>> {
+    let [old, patch, _] = read_vector("metadata")?;
+
+    let patcher = Patcher::new(Cursor::new(old), patch.as_slice())?;
+    let expected = FileMetadata::new(
+        Some(0o755),
+        Some(UNIX_EPOCH - Duration::new(1, 500_000_000)),
+    );
+    assert_eq!(patcher.metadata().file_metadata(), Some(expected));
+
+    let capabilities = patcher.metadata().format_capabilities();
+    assert_eq!(
+        capabilities.features().collect::<Vec<_>>(),
+        [FormatFeature::FileMetadata],
+    );
+    assert_eq!(capabilities.unknown_tags().collect::<Vec<_>>(), [1000]);
+
+    Ok(())
+}
+
+#[test]
+fn header_encoding() -> Result<(), Box<dyn Error>> {
+    let mut patch = Vec::new();
+    ina::diff_with_config(
+        &OldBlob::from_slice(b"Hello"),
+        b"Hero",
+        &mut patch,
+        DiffConfig::new().file_metadata(Some(FileMetadata::new(
+            Some(0o755),
+            Some(UNIX_EPOCH - Duration::new(1, 500_000_000)),
+        ))),
+    )?;
+
+    #[rustfmt::skip]
+    let expected = [
+        // Magic number
+        0x7c, 0x6c, 0x95, 0x5c,
+        // Major and minor version
+        0x01, 0x00, 0x02, 0x00,
+        // Extension area length
+        0x0c,
+        // File mode
+        0x01, 0x02, 0xed, 0x03,
+        // File modified time as zigzag-encoded seconds and nanoseconds
+        0x02, 0x06, 0x03, 0x80, 0xca, 0xb5, 0xee, 0x01,
+    ];
+    assert_eq!(patch[..expected.len()], expected);
+
+    Ok(())
+}
+
+#[cfg(feature = "selftest")]
+#[test]
+fn selftest_format() {
+    for (name, result) in ina::selftest::run_format() {
+        assert!(result.is_ok(), "{name}: {result:?}");
+    }
+}
@@ -0,0 +1,111 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+#![cfg(all(feature = "diff", feature = "patch"))]
+#![allow(missing_docs)]
+
+use std::error::Error;
+
+use ina::{BaseCheck, DiffConfig, OldBlob, PatchError};
+use integer_encoding::VarInt;
+
+const MAGIC: [u8; 4] = 0x5c956c7c_u32.to_le_bytes();
+
+/// Assembles a control stream of records adding 0 to `add_len` bytes of the old blob and seeking
+/// `seek` bytes
+fn controls(records: &[(u64, i64)]) -> Vec<u8> {
+    let mut controls = Vec::new();
+    for &(add_len, seek) in records {
+        controls.extend_from_slice(&add_len.encode_var_vec());
+        controls.resize(controls.len() + add_len as usize, 0);
+        controls.push(0);
+        controls.extend_from_slice(&seek.encode_var_vec());
+    }
+
+    controls
+}
+
+/// Assembles a patch from a header and an uncompressed control stream
+fn craft(header: &[u8], controls: &[u8]) -> Vec<u8> {
+    let mut patch = header.to_vec();
+    patch.extend_from_slice(&zstd::encode_all(controls, 3).unwrap());
+
+    patch
+}
+
+/// Applies `patch` to `old`, returning the error it fails with
+fn patch_error(old: &[u8], patch: &[u8]) -> PatchError {
+    let error = match ina::patch_to_vec(old, patch) {
+        Ok(new) => panic!("patch succeeded, producing {} bytes", new.len()),
+        Err(PatchError::Io(e)) => e,
+        Err(e) => return e,
+    };
+
+    match error.into_inner().map(|e| e.downcast::<PatchError>()) {
+        Some(Ok(e)) => *e,
+        e => panic!("patch failed with an unexpected error: {e:?}"),
+    }
+}
+
+#[test]
+fn seeks_before_start_are_rejected() {
+    let old = [1; 64];
+    let header = [MAGIC.as_slice(), &[1, 0, 1, 0, 0]].concat();
+
+    for (records, new_len) in [
+        (vec![(0, i64::MIN)], 0),
+        (vec![(0, -1)], 0),
+        (vec![(16, -17)], 16),
+        (vec![(16, -16), (16, 16), (8, i64::MIN)], 40),
+    ] {
+        let patch = craft(&header, &controls(&records));
+        let error = patch_error(&old, &patch);
+        assert!(
+            matches!(error, PatchError::SeekBeforeStart(len) if len == new_len),
+            "{records:?} failed with {error:?}",
+        );
+    }
+
+    // Seeking back to the start is fine
+    let patch = craft(&header, &controls(&[(16, -16), (16, 0)]));
+    assert_eq!(ina::patch_to_vec(&old, &patch).unwrap(), [1; 32]);
+}
+
+#[test]
+fn overflowing_seeks_are_rejected() {
+    let old = [1; 64];
+    let header = [MAGIC.as_slice(), &[1, 0, 1, 0, 0]].concat();
+
+    for (records, new_len) in [
+        (vec![(16, i64::MAX)], 16),
+        (vec![(0, i64::MAX), (0, 1)], 0),
+        (vec![(0, i64::MAX - 8), (0, 16)], 0),
+    ] {
+        let patch = craft(&header, &controls(&records));
+        let error = patch_error(&old, &patch);
+        assert!(
+            matches!(error, PatchError::SeekPastEnd(len) if len == new_len),
+            "{records:?} failed with {error:?}",
+        );
+    }
+}
+
+#[test]
+fn seeks_past_recorded_old_len_are_rejected() -> Result<(), Box<dyn Error>> {
+    let old: Vec<u8> = (0..4096u32).map(|i| (i * 7 % 251) as u8).collect();
+    let options = DiffConfig::new().base_check(BaseCheck::Full).clone();
+    let patch = ina::diff_to_vec(&OldBlob::from_slice(&old), &old, &options)?;
+    let mut data = patch.as_slice();
+    ina::read_header(&mut data)?;
+    let header = &patch[..patch.len() - data.len()];
+
+    // Seeking to the end of the old blob is fine, but not past it
+    let patch = craft(header, &controls(&[(16, 4080)]));
+    assert_eq!(ina::patch_to_vec(&old, &patch)?, old[..16]);
+    let patch = craft(header, &controls(&[(16, 4081)]));
+    let error = patch_error(&old, &patch);
+    assert!(matches!(error, PatchError::SeekPastEnd(16)), "{error}");
+
+    Ok(())
+}
@@ -0,0 +1,219 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+#![allow(missing_docs)]
+
+mod common;
+
+use std::{
+    error::Error,
+    io::{self, Cursor, ErrorKind, Read},
+};
+
+use common::Rng;
+use ina::{
+    DiffConfig, OldBlob, PatchError, Patcher,
+    test_util::{Fault, FaultyIo},
+};
+
+/// The kinds of errors injected, none of which the library retries on its own
+const FATAL_KINDS: [ErrorKind; 4] = [
+    ErrorKind::BrokenPipe,
+    ErrorKind::PermissionDenied,
+    ErrorKind::ConnectionReset,
+    ErrorKind::Other,
+];
+
+/// The kinds of transient errors a caller may retry after
+const TRANSIENT_KINDS: [ErrorKind; 3] = [
+    ErrorKind::Interrupted,
+    ErrorKind::WouldBlock,
+    ErrorKind::TimedOut,
+];
+
+/// Returns an old blob and a new blob which shares most of its data, moved around, so that
+/// patching it reads and seeks throughout the old blob
+fn blobs() -> (Vec<u8>, Vec<u8>) {
+    let mut rng = Rng::new(4246);
+    let old: Vec<u8> = (0..48 << 10).map(|_| rng.below(256) as u8).collect();
+    let mut new = old[24 << 10..].to_vec();
+    new.extend((0..2048).map(|_| rng.below(256) as u8));
+    new.extend_from_slice(&old[..24 << 10]);
+    for _ in 0..64 {
+        let pos = rng.below(new.len() as u64) as usize;
+        new[pos] ^= 0x55;
+    }
+
+    (old, new)
+}
+
+fn create_patch(old: &[u8], new: &[u8], options: &DiffConfig) -> Vec<u8> {
+    ina::diff_to_vec(&OldBlob::from_slice(old), new, options).unwrap()
+}
+
+/// Returns the kind of the I/O error underlying `error`, if any
+fn patch_error_kind(error: &PatchError) -> Option<ErrorKind> {
+    match error {
+        PatchError::Io(e) => Some(io_error_kind(e)),
+        PatchError::Header(e) => Some(e.io_error().kind()),
+        _ => None,
+    }
+}
+
+/// Returns the kind of `error`, or that of the I/O error underlying the `PatchError` it wraps
+fn io_error_kind(error: &io::Error) -> ErrorKind {
+    match error.get_ref().and_then(|e| e.downcast_ref::<PatchError>()) {
+        Some(e) => patch_error_kind(e).unwrap_or(error.kind()),
+        None => error.kind(),
+    }
+}
+
+/// Reads `patcher` to its end or first error, returning the bytes read so far and the error
+fn read_until_error<R: Read>(mut patcher: R) -> (Vec<u8>, Option<io::Error>) {
+    let mut new = Vec::new();
+    let mut buf = [0; 1000];
+    loop {
+        match patcher.read(&mut buf) {
+            Ok(0) => return (new, None),
+            Ok(read) => new.extend_from_slice(&buf[..read]),
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return (new, Some(e)),
+        }
+    }
+}
+
+#[test]
+fn old_read_faults_are_reported() {
+    let (old, new) = blobs();
+    let patch = create_patch(&old, &new, &DiffConfig::new());
+
+    for (i, offset) in (0..old.len() as u64).step_by(3001).enumerate() {
+        let kind = FATAL_KINDS[i % FATAL_KINDS.len()];
+        let faulty = FaultyIo::new(Cursor::new(&old)).fail_reads(Fault::at(offset, kind));
+        let patcher = Patcher::new(faulty, patch.as_slice()).unwrap();
+
+        let (produced, error) = read_until_error(patcher);
+        let error = error.unwrap_or_else(|| panic!("no error for fault at {offset}"));
+        assert_eq!(io_error_kind(&error), kind, "fault at {offset}");
+        assert!(
+            new.starts_with(&produced),
+            "corrupt output for fault at {offset}"
+        );
+    }
+}
+
+#[test]
+fn old_seek_faults_are_reported() {
+    let (old, new) = blobs();
+    let patch = create_patch(&old, &new, &DiffConfig::new());
+
+    // The patch seeks to the middle of the old blob and later back to its start
+    for (i, offset) in [0, 1, 4096, 24 << 10].into_iter().enumerate() {
+        let kind = FATAL_KINDS[i % FATAL_KINDS.len()];
+        let faulty = FaultyIo::new(Cursor::new(&old)).fail_seeks(Fault::at(offset, kind));
+        let error = match Patcher::new(faulty, patch.as_slice()) {
+            Ok(patcher) => read_until_error(patcher).1.map(|e| io_error_kind(&e)),
+            Err(e) => patch_error_kind(&e),
+        };
+
+        assert_eq!(error, Some(kind), "fault at {offset}");
+    }
+}
+
+#[test]
+fn patch_read_faults_are_reported() {
+    let (old, new) = blobs();
+    let options = DiffConfig::new().payload_checksum(true).clone();
+    let patch = create_patch(&old, &new, &options);
+
+    for (i, offset) in (0..patch.len() as u64).step_by(97).enumerate() {
+        let kind = FATAL_KINDS[i % FATAL_KINDS.len()];
+        let faulty = FaultyIo::new(patch.as_slice()).fail_reads(Fault::at(offset, kind));
+        let error = match Patcher::new(Cursor::new(&old), faulty) {
+            Ok(patcher) => {
+                let (produced, error) = read_until_error(patcher);
+                assert!(
+                    new.starts_with(&produced),
+                    "corrupt output for fault at {offset}"
+                );
+                error.map(|e| io_error_kind(&e))
+            }
+            Err(e) => patch_error_kind(&e),
+        };
+
+        assert_eq!(error, Some(kind), "fault at {offset}");
+    }
+}
+
+#[test]
+fn transient_faults_do_not_corrupt_output() {
+    let (old, new) = blobs();
+    let patch = create_patch(&old, &new, &DiffConfig::new());
+    let mut data = patch.as_slice();
+    ina::read_header(&mut data).unwrap();
+    let header_len = (patch.len() - data.len()) as u64;
+
+    for (i, offset) in (header_len..patch.len() as u64).step_by(89).enumerate() {
+        let kind = TRANSIENT_KINDS[i % TRANSIENT_KINDS.len()];
+        let faulty = FaultyIo::new(patch.as_slice()).fail_reads(Fault::at(offset, kind).once());
+        let mut patcher = Patcher::new(Cursor::new(&old), faulty).unwrap();
+
+        // Retry after the fault, which the patcher must resume from where it failed
+        let (mut produced, error) = read_until_error(&mut patcher);
+        if let Some(error) = error {
+            assert_eq!(io_error_kind(&error), kind, "fault at {offset}");
+            let (rest, error) = read_until_error(&mut patcher);
+            assert!(error.is_none(), "fault at {offset} persisted: {error:?}");
+            produced.extend_from_slice(&rest);
+        }
+
+        assert!(produced == new, "corrupt output for fault at {offset}");
+    }
+}
+
+#[test]
+fn new_write_faults_are_reported() {
+    let (old, new) = blobs();
+    let patch = create_patch(&old, &new, &DiffConfig::new());
+
+    for (i, offset) in (0..new.len() as u64).step_by(4999).enumerate() {
+        let kind = FATAL_KINDS[i % FATAL_KINDS.len()];
+        let mut faulty = FaultyIo::new(Vec::new()).fail_writes(Fault::at(offset, kind));
+        let error = ina::patch(Cursor::new(&old), patch.as_slice(), &mut faulty).unwrap_err();
+
+        assert_eq!(patch_error_kind(&error), Some(kind), "fault at {offset}");
+        assert_eq!(faulty.injected(), 1);
+        assert!(faulty.get_ref() == &new[..offset as usize]);
+    }
+}
+
+#[test]
+fn diff_write_faults_are_reported() -> Result<(), Box<dyn Error>> {
+    let (old, new) = blobs();
+    let old = OldBlob::from_vec(old);
+
+    for options in [
+        DiffConfig::new(),
+        DiffConfig::new().payload_checksum(true).clone(),
+    ] {
+        let patch = ina::diff_to_vec(&old, &new, &options)?;
+        for (i, offset) in (0..patch.len() as u64).step_by(101).enumerate() {
+            let kind = FATAL_KINDS[i % FATAL_KINDS.len()];
+            let mut faulty = FaultyIo::new(Vec::new()).fail_writes(Fault::at(offset, kind));
+            let error = ina::diff_with_config(&old, &new, &mut faulty, &options).unwrap_err();
+            assert_eq!(error.kind(), kind, "fault at {offset}");
+
+            // The patch is written unchanged once a transient fault has passed
+            let mut faulty = FaultyIo::new(Vec::new())
+                .fail_writes(Fault::at(offset, ErrorKind::Interrupted).once());
+            ina::diff_with_config(&old, &new, &mut faulty, &options)?;
+            assert!(
+                faulty.into_inner() == patch,
+                "corrupt patch for fault at {offset}"
+            );
+        }
+    }
+
+    Ok(())
+}
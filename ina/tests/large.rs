@@ -0,0 +1,167 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+#![cfg(all(feature = "diff", feature = "patch"))]
+#![allow(missing_docs)]
+
+use std::{
+    error::Error,
+    io::{self, Cursor, Read, Seek, SeekFrom},
+};
+
+use ina::{DiffConfig, Patcher, TextMode};
+use integer_encoding::VarInt;
+
+const MAGIC: [u8; 4] = 0x5c956c7c_u32.to_le_bytes();
+
+/// A blob larger than the address space of 32-bit targets whose bytes are computed from their
+/// positions
+struct Synthetic {
+    len: u64,
+    pos: u64,
+}
+
+impl Synthetic {
+    fn new(len: u64) -> Self {
+        Self { len, pos: 0 }
+    }
+
+    fn byte_at(pos: u64) -> u8 {
+        (pos % 251) as u8 ^ (pos >> 32) as u8
+    }
+}
+
+impl Read for Synthetic {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.len.saturating_sub(self.pos).min(buf.len() as u64) as usize;
+        for (i, byte) in buf[..len].iter_mut().enumerate() {
+            *byte = Self::byte_at(self.pos + i as u64);
+        }
+        self.pos += len as u64;
+
+        Ok(len)
+    }
+}
+
+impl Seek for Synthetic {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.pos = match pos {
+            SeekFrom::Start(pos) => pos,
+            SeekFrom::End(offset) => self.len.saturating_add_signed(offset),
+            SeekFrom::Current(offset) => self.pos.saturating_add_signed(offset),
+        };
+
+        Ok(self.pos)
+    }
+}
+
+/// Assembles a patch from an uncompressed control stream
+fn craft(controls: &[u8]) -> Vec<u8> {
+    let mut patch = MAGIC.to_vec();
+    patch.extend_from_slice(&[1, 0, 1, 0, 0]);
+    patch.extend_from_slice(&zstd::encode_all(controls, 3).unwrap());
+
+    patch
+}
+
+#[test]
+fn patches_seek_beyond_4_gib() -> Result<(), Box<dyn Error>> {
+    let target: i64 = 9 << 29;
+    // An empty record seeking past 4 GiB followed by a record adding 0 to 16 bytes
+    let mut controls = vec![0, 0];
+    controls.extend_from_slice(&i64::encode_var_vec(target));
+    controls.push(16);
+    controls.extend_from_slice(&[0; 16]);
+    controls.extend_from_slice(&[0, 0]);
+
+    let mut new = Vec::new();
+    ina::patch(
+        Synthetic::new(5 << 30),
+        craft(&controls).as_slice(),
+        &mut new,
+    )?;
+
+    let expected: Vec<_> = (0..16)
+        .map(|i| Synthetic::byte_at(target as u64 + i))
+        .collect();
+    assert_eq!(new, expected);
+
+    Ok(())
+}
+
+#[test]
+fn patches_records_longer_than_4_gib() -> Result<(), Box<dyn Error>> {
+    // A record adding to 5 GiB of the old blob, of which only the first bytes are present
+    let mut controls = u64::encode_var_vec(5 << 30);
+    controls.extend_from_slice(&[1; 4096]);
+
+    let patch = craft(&controls);
+    let mut patcher = Patcher::new(Synthetic::new(5 << 30), patch.as_slice())?;
+    let mut new = [0; 4096];
+    patcher.read_exact(&mut new)?;
+
+    for (pos, byte) in new.iter().enumerate() {
+        assert_eq!(*byte, Synthetic::byte_at(pos as u64).wrapping_add(1));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn windowed_diffs_round_trip() -> Result<(), Box<dyn Error>> {
+    let old: Vec<u8> = (0..200_000u32).map(|i| (i * 7 % 251) as u8).collect();
+    let mut new = old.clone();
+    new[1000..1100].fill(0);
+    new.drain(50_000..60_000);
+    new.extend_from_slice(&old[..20_000]);
+
+    let cases: [(&[u8], &[u8]); 5] = [
+        (&old, &new),
+        (&new, &old),
+        (&[], &new),
+        (&old, &[]),
+        (b"short", b"shorter"),
+    ];
+    for (old, new) in cases {
+        for window_len in [100, 4096, 65_536, 1 << 20] {
+            let mut patch = Vec::new();
+            let options = DiffConfig::new().diff_window_len(window_len).clone();
+            let stats =
+                ina::diff_windowed(Cursor::new(old), Cursor::new(new), &mut patch, &options)?;
+            assert_eq!(stats.old_len(), old.len() as u64);
+            assert_eq!(stats.new_len(), new.len() as u64);
+
+            let mut patched = Vec::new();
+            ina::patch(Cursor::new(old), patch.as_slice(), &mut patched)?;
+            assert!(patched == new, "window length {window_len}");
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn windowed_diffs_read_from_current_position() -> Result<(), Box<dyn Error>> {
+    let mut old = Cursor::new(b"ignored Hello, world!");
+    old.set_position(8);
+    let mut new = Cursor::new(b"ignored too Hello, there!");
+    new.set_position(12);
+
+    let mut patch = Vec::new();
+    ina::diff_windowed(old, new, &mut patch, &DiffConfig::new())?;
+
+    let mut patched = Vec::new();
+    ina::patch(
+        Cursor::new(b"Hello, world!"),
+        patch.as_slice(),
+        &mut patched,
+    )?;
+    assert_eq!(patched, b"Hello, there!");
+
+    let options = DiffConfig::new().text_mode(TextMode::Text).clone();
+    let result = ina::diff_windowed(Cursor::new([]), Cursor::new([]), &mut patch, &options);
+    assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidInput);
+
+    Ok(())
+}
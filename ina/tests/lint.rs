@@ -0,0 +1,140 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+#![cfg(feature = "lint")]
+#![allow(missing_docs)]
+
+use std::error::Error;
+
+use ina::{DiffConfig, LintReport, LintSeverity, OldBlob};
+
+const MAGIC: [u8; 4] = 0x5c956c7c_u32.to_le_bytes();
+
+/// Assembles a patch from a raw extension area and an uncompressed control stream
+fn craft(data_offset: &[u8], extension: &[u8], controls: &[u8]) -> Vec<u8> {
+    let mut patch = MAGIC.to_vec();
+    patch.extend_from_slice(&[1, 0, 1, 0]);
+    patch.extend_from_slice(data_offset);
+    patch.extend_from_slice(extension);
+    patch.extend_from_slice(&zstd::encode_all(controls, 3).unwrap());
+
+    patch
+}
+
+fn codes(report: &LintReport) -> Vec<(&str, LintSeverity)> {
+    report
+        .findings()
+        .iter()
+        .map(|finding| (finding.code(), finding.severity()))
+        .collect()
+}
+
+#[test]
+fn diff_output_is_conformant() -> Result<(), Box<dyn Error>> {
+    let old: Vec<u8> = (0..8192u32).map(|i| (i % 251) as u8).collect();
+    let mut new = old.clone();
+    new[100..300].fill(7);
+    new.extend_from_slice(b"appended");
+
+    for checksum in [false, true] {
+        let mut patch = Vec::new();
+        let config = DiffConfig::new().payload_checksum(checksum).clone();
+        ina::diff_with_config(&OldBlob::from_slice(&old), &new, &mut patch, &config)?;
+
+        let report = ina::lint(patch.as_slice())?;
+        assert!(report.findings().is_empty(), "{:?}", report.findings());
+        assert!(report.is_conformant());
+        assert_eq!(report.new_len(), new.len() as u64);
+        assert!(report.records() > 0);
+        assert_eq!(report.version().map(|v| v.major()), Some(1));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn header_problems_are_reported() -> Result<(), Box<dyn Error>> {
+    let report = ina::lint([0x42; 16].as_slice())?;
+    assert_eq!(codes(&report), [("bad-magic", LintSeverity::Error)]);
+
+    let report = ina::lint(&MAGIC[..])?;
+    assert_eq!(codes(&report), [("truncated-header", LintSeverity::Error)]);
+
+    // Tag 99 twice, each with one byte of value, behind a data offset encoded in two bytes
+    let extension = [99, 1, 0, 99, 1, 0];
+    let patch = craft(&[6 | 0x80, 0], &extension, &[0, 1, b'x', 0]);
+    let report = ina::lint(patch.as_slice())?;
+    assert_eq!(
+        codes(&report),
+        [
+            ("non-canonical-varint", LintSeverity::Error),
+            ("unknown-field", LintSeverity::Warning),
+            ("duplicate-field", LintSeverity::Error),
+            ("unknown-field", LintSeverity::Warning),
+        ],
+    );
+    assert_eq!(report.errors(), 2);
+    assert_eq!(report.warnings(), 2);
+
+    // A payload checksum field with the wrong CRC
+    let patch = craft(&[7], &[11, 5, 18, 0, 0, 0, 0], &[0, 1, b'x', 0]);
+    let report = ina::lint(patch.as_slice())?;
+    assert_eq!(codes(&report), [("checksum-mismatch", LintSeverity::Error)]);
+
+    Ok(())
+}
+
+#[test]
+fn control_stream_problems_are_reported() -> Result<(), Box<dyn Error>> {
+    let controls = [
+        // Add 1, copy 1, seek -3
+        1,
+        0,
+        1,
+        b'x',
+        5, //
+        // An empty record with an overlong seek of 2
+        0,
+        0,
+        4 | 0x80,
+        0, //
+        // Add 1 with an overlong length, copy 0, seek 0
+        0x81,
+        0,
+        0,
+        0,
+        0,
+    ];
+    let report = ina::lint(craft(&[0], &[], &controls).as_slice())?;
+    let records: Vec<_> = report
+        .findings()
+        .iter()
+        .map(|finding| (finding.code(), finding.record()))
+        .collect();
+    assert_eq!(
+        records,
+        [
+            ("seek-before-start", Some(0)),
+            ("non-canonical-varint", Some(1)),
+            ("empty-record", Some(1)),
+            ("non-canonical-varint", Some(2)),
+        ],
+    );
+    assert_eq!(report.records(), 3);
+    assert_eq!(report.new_len(), 3);
+    assert_eq!(report.data_len(), controls.len() as u64);
+
+    // The stream ends within the copy field
+    let report = ina::lint(craft(&[0], &[], &[0, 4, b'x']).as_slice())?;
+    assert_eq!(codes(&report), [("truncated-record", LintSeverity::Error)]);
+
+    // Compressed data is cut off
+    let mut patch = Vec::new();
+    ina::diff(&OldBlob::from_slice(&[0; 4095]), &[1; 4096], &mut patch)?;
+    patch.truncate(patch.len() - 4);
+    let report = ina::lint(patch.as_slice())?;
+    assert_eq!(codes(&report), [("corrupt-data", LintSeverity::Error)]);
+
+    Ok(())
+}
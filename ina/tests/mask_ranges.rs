@@ -0,0 +1,38 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+#![allow(missing_docs)]
+
+use std::{error::Error, io::Cursor};
+
+use ina::{DiffConfig, OldBlob};
+
+#[test]
+fn masked_regions_are_stored_verbatim() -> Result<(), Box<dyn Error>> {
+    let old: Vec<u8> = (0..4096u32).map(|i| (i * 31 % 251) as u8).collect();
+    let mut new = old.clone();
+    // Simulate a signature block which differs entirely between versions
+    new[1000..1256].iter_mut().for_each(|byte| *byte = !*byte);
+
+    let old_blob = OldBlob::from_slice(&old);
+
+    for zero_masked in [false, true] {
+        let mut config = DiffConfig::new();
+        // Overlapping and adjacent ranges which end exactly where matches do
+        config
+            .mask_ranges([1000..1200, 1100..1256], [900..1000, 1000..1256])
+            .zero_masked(zero_masked);
+
+        let mut patch = Vec::new();
+        let stats = ina::diff_with_config(&old_blob, &new, &mut patch, &config)?;
+        assert!(stats.copy_bytes() >= 356);
+        assert_eq!(stats.old_coverage().covered_len(), 4096 - 356);
+
+        let mut reconstructed_new = Vec::new();
+        ina::patch(Cursor::new(&old), patch.as_slice(), &mut reconstructed_new)?;
+        assert_eq!(reconstructed_new, new);
+    }
+
+    Ok(())
+}
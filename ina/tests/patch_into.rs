@@ -0,0 +1,90 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+#![allow(missing_docs)]
+
+use std::{
+    error::Error,
+    fs::{self, File},
+    io,
+    path::Path,
+};
+
+use ina::{DiffConfig, OldBlob};
+
+#[test]
+fn patch_into_region() -> Result<(), Box<dyn Error>> {
+    let old = b"Hello, world!";
+    let new = b"Hello, patched world!";
+    let old_blob = OldBlob::from_slice(old);
+    let mut patch = Vec::new();
+    ina::diff(&old_blob, new, &mut patch)?;
+
+    let container_path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("container.img");
+    fs::write(&container_path, [0xff; 64])?;
+    let container = File::options().write(true).open(&container_path)?;
+
+    let written = ina::patch_into(io::Cursor::new(old), patch.as_slice(), &container, 8)?;
+    assert_eq!(written, new.len() as u64);
+
+    let container = fs::read(&container_path)?;
+    assert_eq!(container.len(), 64);
+    assert_eq!(container[..8], [0xff; 8]);
+    assert_eq!(&container[8..8 + new.len()], new);
+    assert!(container[8 + new.len()..].iter().all(|&byte| byte == 0xff));
+
+    Ok(())
+}
+
+#[test]
+fn patch_to_existing_file() -> Result<(), Box<dyn Error>> {
+    let old = b"Hello, world!";
+    let new = b"Hello, patched world!";
+    let old_blob = OldBlob::from_slice(old);
+    let mut patch = Vec::new();
+    ina::diff_with_config(
+        &old_blob,
+        new,
+        &mut patch,
+        DiffConfig::new().record_new_len(true),
+    )?;
+    assert_eq!(
+        ina::read_header(&mut patch.as_slice())?.new_len(),
+        Some(new.len() as u64),
+    );
+
+    // Leftover contents of a longer file are truncated
+    let new_path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("patch_to_file.new");
+    fs::write(&new_path, [0xff; 64])?;
+    let new_file = File::options().write(true).open(&new_path)?;
+
+    let written = ina::patch_to_file(io::Cursor::new(old), patch.as_slice(), &new_file)?;
+    assert_eq!(written, new.len() as u64);
+    assert_eq!(fs::read(&new_path)?, new);
+
+    Ok(())
+}
+
+#[test]
+fn patch_to_file_caps_preallocation() -> Result<(), Box<dyn Error>> {
+    let old = [0x5a; 1024];
+    let new = vec![0; 1 << 20];
+    let patch = ina::diff_to_vec(
+        &OldBlob::from_slice(&old),
+        &new,
+        DiffConfig::new().record_new_len(true),
+    )?;
+
+    // Cut the patch off after its header, so that the file is left as it was preallocated
+    let mut payload = patch.as_slice();
+    ina::read_header(&mut payload)?;
+    let header = &patch[..patch.len() - payload.len()];
+
+    let new_path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("patch_to_file_capped.new");
+    let new_file = File::create(&new_path)?;
+    assert!(ina::patch_to_file(io::Cursor::new(old), header, &new_file).is_err());
+    assert_eq!(new_file.metadata()?.len(), 2 * old.len() as u64);
+
+    Ok(())
+}
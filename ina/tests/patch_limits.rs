@@ -0,0 +1,207 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+#![allow(missing_docs)]
+
+mod common;
+
+use std::{
+    error::Error,
+    io::{self, Cursor, ErrorKind, Read},
+    sync::mpsc::{self, Receiver},
+    time::Duration,
+};
+
+use ina::{
+    DiffConfig, OldBlob, PatchError, PatchLimits, PatchWriter, Patcher, SeekPolicy, SeekRule,
+    WatchdogReader,
+};
+
+fn apply(old: &[u8], patch: &[u8], limits: &PatchLimits) -> Result<Vec<u8>, PatchError> {
+    let mut patcher = Patcher::with_limits(Cursor::new(old), patch, limits)?;
+    let mut new = Vec::new();
+    patcher.read_to_end(&mut new)?;
+
+    Ok(new)
+}
+
+/// Returns the patch error wrapped by an error returned while reading from a `Patcher`
+fn limit_error(error: PatchError) -> Option<PatchError> {
+    match error {
+        PatchError::Io(e) if e.kind() == ErrorKind::InvalidData => e
+            .into_inner()
+            .and_then(|e| e.downcast::<PatchError>().ok())
+            .map(|e| *e),
+        _ => None,
+    }
+}
+
+#[test]
+fn expansion_limit_rejects_empty_records() -> Result<(), Box<dyn Error>> {
+    let old = b"old";
+    let mut writer = PatchWriter::new(Vec::new(), &DiffConfig::new())?;
+    for _ in 0..100_000 {
+        writer.write_record(&[], &[], 0)?;
+    }
+    writer.write_record(&[], b"new", 0)?;
+    let patch = writer.finish()?;
+
+    // The records compress well, so the patch is tiny
+    assert!(patch.len() < 1024);
+    assert_eq!(apply(old, &patch, &PatchLimits::new())?, b"new");
+
+    let error = apply(old, &patch, PatchLimits::new().max_expansion(2)).unwrap_err();
+    assert!(matches!(
+        limit_error(error),
+        Some(PatchError::ExpansionLimitExceeded(_)),
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn limits_accept_regular_patches() -> Result<(), Box<dyn Error>> {
+    let old: Vec<u8> = (0..1u32 << 18).map(|i| (i * 7 % 253) as u8).collect();
+    let mut new = old.clone();
+    new[1000..2000].fill(0);
+    new.splice(
+        5000..5000,
+        (0..1u32 << 12).map(|i| (i.wrapping_mul(0x9e37_79b9) >> 24) as u8),
+    );
+    let old_blob = OldBlob::from_slice(&old);
+
+    let mut patch = Vec::new();
+    ina::diff(&old_blob, &new, &mut patch)?;
+
+    let mut limits = PatchLimits::new();
+    limits.max_expansion(2).max_new_len(new.len() as u64);
+    assert_eq!(apply(&old, &patch, &limits)?, new);
+
+    let error = apply(&old, &patch, limits.max_new_len(new.len() as u64 - 1)).unwrap_err();
+    assert!(matches!(
+        limit_error(error),
+        Some(PatchError::NewLenLimitExceeded(limit)) if limit == new.len() as u64 - 1,
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn buffer_sizes() -> Result<(), Box<dyn Error>> {
+    let old: Vec<u8> = (0..1u32 << 18).map(|i| (i * 7 % 253) as u8).collect();
+    let mut new: Vec<u8> = old.iter().map(|b| b.wrapping_add(1)).collect();
+    new.extend_from_slice(&old[..1 << 16]);
+    let old_blob = OldBlob::from_slice(&old);
+
+    let mut patch = Vec::new();
+    ina::diff(&old_blob, &new, &mut patch)?;
+
+    // Sizes below the minimum are rounded up
+    for size in [1, 4 << 10, 256 << 10] {
+        let mut limits = PatchLimits::new();
+        limits.read_buffer_size(size).diff_buffer_size(size);
+        assert_eq!(apply(&old, &patch, &limits)?, new);
+    }
+
+    // Explicit buffers count toward the memory limit
+    let mut limits = PatchLimits::new();
+    limits.max_memory(32 << 20);
+    assert_eq!(apply(&old, &patch, &limits)?, new);
+    let error = apply(&old, &patch, limits.diff_buffer_size(32 << 20)).unwrap_err();
+    assert!(matches!(error, PatchError::MemoryLimitExceeded(_)));
+
+    Ok(())
+}
+
+#[test]
+fn seek_policy_rejects_seeks() -> Result<(), Box<dyn Error>> {
+    let (old, _) = common::binary_pair(2, 64 << 10);
+    // Reverse the order of the old blob's quarters so that applying the patch seeks backward
+    let quarters: Vec<_> = old.chunks(old.len().div_ceil(4)).rev().collect();
+    let new = quarters.concat();
+    let old_blob = OldBlob::from_slice(&old);
+    let apply = |patch: &[u8], policy: &SeekPolicy| -> io::Result<Vec<u8>> {
+        let mut patcher = Patcher::new(io::Cursor::new(&old), patch)
+            .map_err(io::Error::other)?
+            .seek_policy(*policy);
+        let mut reconstructed_new = Vec::new();
+        io::copy(&mut patcher, &mut reconstructed_new)?;
+        Ok(reconstructed_new)
+    };
+    let violation_of = |error: io::Error| match error.get_ref().and_then(|e| e.downcast_ref()) {
+        Some(PatchError::SeekViolation(violation)) => *violation,
+        _ => panic!("expected a seek violation, got {error:?}"),
+    };
+
+    let patch = ina::diff_to_vec(&old_blob, &new, &DiffConfig::new())?;
+    let error = apply(&patch, SeekPolicy::new().max_backward_seek(0)).unwrap_err();
+    let violation = violation_of(error);
+    assert_eq!(violation.rule(), SeekRule::MaxBackwardSeek(0));
+    assert!(violation.seek() < 0);
+    assert!(violation.new_offset() > 0 && violation.new_offset() < new.len() as u64);
+
+    let error = apply(&patch, SeekPolicy::new().window(4096)).unwrap_err();
+    assert_eq!(violation_of(error).rule(), SeekRule::Window(4096));
+
+    // Every quarter lies at most the whole old blob away
+    let policy = *SeekPolicy::new()
+        .max_backward_seek(old.len() as u64)
+        .window(old.len() as u64);
+    assert_eq!(apply(&patch, &policy)?, new);
+
+    // Patches diffed without backward seeks are accepted under the same constraint
+    let mut config = DiffConfig::new();
+    config.max_backward_seek(Some(0));
+    let patch = ina::diff_to_vec(&old_blob, &new, &config)?;
+    assert_eq!(apply(&patch, SeekPolicy::new().max_backward_seek(0))?, new);
+
+    Ok(())
+}
+
+/// A reader which provides the start of a patch and then blocks until its sender is dropped
+struct HangingReader {
+    start: io::Cursor<Vec<u8>>,
+    hang: Receiver<()>,
+}
+
+impl Read for HangingReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.start.read(buf)? {
+            0 => {
+                let _ = self.hang.recv();
+                Ok(0)
+            }
+            read => Ok(read),
+        }
+    }
+}
+
+#[test]
+fn watchdog_detects_stalls() -> Result<(), Box<dyn Error>> {
+    let (old, new) = common::binary_pair(9, 64 << 10);
+    let patch = ina::diff_to_vec(&OldBlob::from_slice(&old), &new, &DiffConfig::new())?;
+
+    let watched = WatchdogReader::new(io::Cursor::new(patch.clone()), Duration::from_secs(10))?;
+    let mut reconstructed_new = Vec::new();
+    ina::patch(io::Cursor::new(&old), watched, &mut reconstructed_new)?;
+    assert_eq!(reconstructed_new, new);
+
+    let (_sender, hang) = mpsc::channel();
+    let hanging = HangingReader {
+        start: io::Cursor::new(patch[..patch.len() / 2].to_vec()),
+        hang,
+    };
+    let watched = WatchdogReader::new(hanging, Duration::from_millis(100))?;
+    let result = ina::patch(io::Cursor::new(&old), watched, &mut io::sink());
+    let Err(PatchError::Io(e)) = result else {
+        panic!("stall went undetected: {result:?}");
+    };
+    assert_eq!(e.kind(), io::ErrorKind::TimedOut);
+    assert!(matches!(
+        e.get_ref().and_then(|e| e.downcast_ref()),
+        Some(PatchError::Stalled(_)),
+    ));
+
+    Ok(())
+}
@@ -0,0 +1,169 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+#![allow(missing_docs)]
+
+use ina::{Catalog, CatalogPatch, OldBlob};
+
+fn catalog() -> Catalog {
+    let mut catalog = Catalog::new();
+    catalog
+        .add_patch(CatalogPatch::new("v1", "v2", 100))
+        .add_patch(CatalogPatch::new("v2", "v3", 100))
+        .add_patch(CatalogPatch::new("v1", "v3", 250))
+        .add_patch(CatalogPatch::new("v3", "v4", 5_000))
+        .add_patch(CatalogPatch::new("v4", "v5", 10))
+        .add_full_file("v4", 1_000)
+        .add_full_file("v5", 1_010);
+    catalog
+}
+
+fn steps(catalog: &Catalog, from: &str, to: &str) -> Option<(Option<String>, Vec<String>, u64)> {
+    ina::plan_chain(catalog, from, to).map(|plan| {
+        let patches = plan
+            .patches()
+            .iter()
+            .map(|patch| format!("{}->{}", patch.old_id(), patch.new_id()))
+            .collect();
+        (plan.full_file().map(String::from), patches, plan.len())
+    })
+}
+
+#[test]
+fn plan_chain_picks_cheapest_route() {
+    let catalog = catalog();
+
+    // A chain of two patches is cheaper than the direct patch
+    assert_eq!(
+        steps(&catalog, "v1", "v3"),
+        Some((None, vec!["v1->v2".into(), "v2->v3".into()], 200)),
+    );
+    // The full file is cheaper than any chain
+    assert_eq!(
+        steps(&catalog, "v1", "v4"),
+        Some((Some("v4".into()), vec![], 1_000)),
+    );
+    // Ties are broken by the number of downloads
+    assert_eq!(
+        steps(&catalog, "v1", "v5"),
+        Some((Some("v5".into()), vec![], 1_010)),
+    );
+    // A full file of an intermediate version can start a chain
+    let mut catalog = catalog.clone();
+    catalog.add_full_file("v5", 2_000);
+    assert_eq!(
+        steps(&catalog, "v3", "v5"),
+        Some((Some("v4".into()), vec!["v4->v5".into()], 1_010)),
+    );
+}
+
+#[test]
+fn plan_chain_edge_cases() {
+    let catalog = catalog();
+
+    let plan = ina::plan_chain(&catalog, "v2", "v2").unwrap();
+    assert!(plan.is_empty());
+    assert_eq!(plan.len(), 0);
+
+    assert_eq!(steps(&catalog, "v3", "v1"), None);
+    assert_eq!(steps(&catalog, "unknown", "v3"), None);
+    assert_eq!(
+        steps(&catalog, "unknown", "v4"),
+        Some((Some("v4".into()), vec![], 1_000)),
+    );
+}
+
+#[test]
+fn catalog_queries() {
+    let mut catalog = catalog();
+    catalog.add_patch(CatalogPatch::new("v1", "v3", 200));
+
+    let new_ids: Vec<_> = catalog
+        .patches_from("v1")
+        .map(CatalogPatch::new_id)
+        .collect();
+    assert_eq!(new_ids, ["v2", "v3", "v3"]);
+    assert_eq!(catalog.patches_from("v5").count(), 0);
+
+    assert_eq!(
+        catalog.patch_between("v1", "v3"),
+        Some(&CatalogPatch::new("v1", "v3", 200)),
+    );
+    assert_eq!(catalog.patch_between("v1", "v4"), None);
+}
+
+#[cfg(all(feature = "diff", feature = "patch"))]
+#[test]
+fn catalog_from_dir() -> Result<(), Box<dyn std::error::Error>> {
+    use std::{fs, path::Path};
+
+    use ina::{DiffConfig, Provenance};
+
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("catalog");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("nested"))?;
+
+    let old = OldBlob::from_slice(b"Hello");
+    let write_patch = |name: &str, provenance: Option<Provenance>| {
+        let mut patch = Vec::new();
+        let mut config = DiffConfig::new();
+        config.provenance(provenance);
+        ina::diff_with_config(&old, b"Hero", &mut patch, &config)?;
+        fs::write(dir.join(name), &patch)?;
+        Ok::<_, Box<dyn std::error::Error>>(patch.len() as u64)
+    };
+    let ids = |old_id: &str, new_id: &str| {
+        Some(Provenance::new(
+            None,
+            None,
+            Some(old_id.into()),
+            Some(new_id.into()),
+        ))
+    };
+    let b_len = write_patch("b.ina", ids("v2", "v3"))?;
+    let a_len = write_patch("a.ina", ids("v1", "v2"))?;
+    write_patch("anonymous.ina", None)?;
+    fs::write(dir.join("notes.txt"), "not a patch")?;
+    fs::write(dir.join("empty"), "")?;
+
+    let catalog = Catalog::from_dir(&dir)?;
+    assert_eq!(
+        catalog.patches(),
+        [
+            CatalogPatch::new("v1", "v2", a_len).with_path(dir.join("a.ina")),
+            CatalogPatch::new("v2", "v3", b_len).with_path(dir.join("b.ina")),
+        ],
+    );
+    assert!(ina::plan_chain(&catalog, "v1", "v3").is_some());
+
+    Ok(())
+}
+
+#[cfg(all(feature = "diff", feature = "patch"))]
+#[test]
+fn catalog_patch_from_metadata() -> Result<(), Box<dyn std::error::Error>> {
+    use ina::{DiffConfig, Provenance};
+
+    let mut patch = Vec::new();
+    ina::diff(&OldBlob::from_slice(b"Hello"), b"Hero", &mut patch)?;
+    let metadata = ina::read_header(&mut patch.as_slice())?;
+    assert_eq!(CatalogPatch::from_metadata(&metadata, 10), None);
+
+    let mut config = DiffConfig::new();
+    config.provenance(Some(Provenance::new(
+        None,
+        None,
+        Some("v1".into()),
+        Some("v2".into()),
+    )));
+    let mut patch = Vec::new();
+    ina::diff_with_config(&OldBlob::from_slice(b"Hello"), b"Hero", &mut patch, &config)?;
+    let metadata = ina::read_header(&mut patch.as_slice())?;
+    assert_eq!(
+        CatalogPatch::from_metadata(&metadata, patch.len() as u64),
+        Some(CatalogPatch::new("v1", "v2", patch.len() as u64)),
+    );
+
+    Ok(())
+}
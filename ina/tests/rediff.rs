@@ -0,0 +1,93 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+#![cfg(all(feature = "diff", feature = "patch"))]
+#![allow(missing_docs)]
+
+use std::error::Error;
+
+use ina::{DiffConfig, OldBlob, RediffReason};
+
+fn blobs() -> (OldBlob, Vec<u8>) {
+    let old: Vec<u8> = (0..8192u32).map(|i| (i % 251) as u8).collect();
+    let mut new = old.clone();
+    new[1000..1100].fill(0);
+    new.extend_from_slice(b"appended data");
+
+    (OldBlob::from_vec(old), new)
+}
+
+#[test]
+fn settings_are_recorded() -> Result<(), Box<dyn Error>> {
+    let (old, new) = blobs();
+    let mut config = DiffConfig::new();
+    config
+        .compression_level(7)
+        .window_log(Some(20))
+        .match_threshold(12)
+        .record_settings(true);
+    let mut patch = Vec::new();
+    ina::diff_with_config(&old, &new, &mut patch, &config)?;
+
+    let settings = *ina::read_header(&mut patch.as_slice())?
+        .diff_settings()
+        .unwrap();
+    assert_eq!(settings.compression_level(), 7);
+    assert_eq!(settings.window_log(), Some(20));
+    assert_eq!(settings.match_threshold(), 12);
+    #[cfg(feature = "lint")]
+    assert!(ina::lint(patch.as_slice())?.findings().is_empty());
+
+    // Transcoding records the new compression settings and keeps the matcher settings
+    let mut transcoded = Vec::new();
+    ina::transcode(
+        patch.as_slice(),
+        &mut transcoded,
+        DiffConfig::new().compression_level(-3),
+    )?;
+    let settings = *ina::read_header(&mut transcoded.as_slice())?
+        .diff_settings()
+        .unwrap();
+    assert_eq!(settings.compression_level(), -3);
+    assert_eq!(settings.window_log(), None);
+    assert_eq!(settings.match_threshold(), 12);
+
+    // Settings aren't recorded by default
+    let mut patch = Vec::new();
+    ina::diff(&old, &new, &mut patch)?;
+    assert_eq!(
+        ina::read_header(&mut patch.as_slice())?.diff_settings(),
+        None
+    );
+
+    Ok(())
+}
+
+#[test]
+fn rediff_reasons() -> Result<(), Box<dyn Error>> {
+    let (old, new) = blobs();
+    let mut config = DiffConfig::new();
+    config.record_settings(true);
+    let mut patch = Vec::new();
+    ina::diff_with_config(&old, &new, &mut patch, &config)?;
+
+    let check = ina::check_rediff(&patch, &config, 0.01)?;
+    assert!(!check.is_recommended());
+    assert_eq!(check.patch_len(), patch.len() as u64);
+    assert_eq!(check.savings(), 0);
+    assert_eq!(check.settings().map(|s| s.match_threshold()), Some(8));
+
+    config.match_threshold(16);
+    let check = ina::check_rediff(&patch, &config, 0.01)?;
+    assert_eq!(check.reasons(), [RediffReason::MatcherSettingsChanged]);
+
+    // Patches which don't record their settings may have been matched with any settings
+    let mut patch = Vec::new();
+    ina::diff(&old, &new, &mut patch)?;
+    let check = ina::check_rediff(&patch, &DiffConfig::new(), 0.01)?;
+    assert_eq!(check.reasons(), [RediffReason::UnrecordedSettings]);
+    assert_eq!(check.settings(), None);
+
+    Ok(())
+}
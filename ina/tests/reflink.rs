@@ -0,0 +1,69 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+#![cfg(all(feature = "reflink", target_os = "linux"))]
+#![allow(missing_docs)]
+
+use std::{
+    error::Error,
+    fs::{self, File},
+    os::unix::fs::MetadataExt,
+    path::Path,
+};
+
+use ina::OldBlob;
+
+#[test]
+fn patch_reflink_matches_patch() -> Result<(), Box<dyn Error>> {
+    let old: Vec<u8> = (0..256 * 1024u32)
+        .map(|i| (i.wrapping_mul(0x9e3779b9) >> 24) as u8)
+        .collect();
+    // Change a few bytes in the middle and append data, leaving most blocks unchanged
+    let mut new = old.clone();
+    new[100_000..100_010].fill(0);
+    new.extend_from_slice(b"appended tail");
+
+    let old_blob = OldBlob::from_slice(&old);
+    let mut patch = Vec::new();
+    ina::diff(&old_blob, &new, &mut patch)?;
+
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR"));
+    let old_path = dir.join("reflink-old.bin");
+    let new_path = dir.join("reflink-new.bin");
+    fs::write(&old_path, &old)?;
+    // Stale contents of the new file are overwritten and truncated
+    fs::write(&new_path, vec![0xff; new.len() + 4096])?;
+
+    let old_file = File::open(&old_path)?;
+    let new_file = File::options().write(true).open(&new_path)?;
+    let stats = ina::patch_reflink(&old_file, patch.as_slice(), &new_file)?;
+
+    assert_eq!(fs::read(&new_path)?, new);
+    assert_eq!(stats.new_len(), new.len() as u64);
+    assert_eq!(stats.cloned_len() + stats.written_len(), stats.new_len());
+    // Only whole blocks are cloned, which is nothing if the filesystem doesn't support cloning
+    assert_eq!(stats.cloned_len() % new_file.metadata()?.blksize(), 0);
+
+    Ok(())
+}
+
+#[test]
+fn patch_reflink_rejects_short_old_file() -> Result<(), Box<dyn Error>> {
+    let mut patch = Vec::new();
+    ina::diff(
+        &OldBlob::from_slice(b"Hello, world!"),
+        b"Hello, patched world!",
+        &mut patch,
+    )?;
+
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR"));
+    let old_path = dir.join("reflink-short-old.bin");
+    fs::write(&old_path, b"Hello")?;
+
+    let old_file = File::open(&old_path)?;
+    let new_file = File::create(dir.join("reflink-short-new.bin"))?;
+    assert!(ina::patch_reflink(&old_file, patch.as_slice(), &new_file).is_err());
+
+    Ok(())
+}
@@ -0,0 +1,171 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+#![cfg(all(feature = "diff", feature = "patch"))]
+#![allow(missing_docs)]
+
+use std::{error::Error, io::Cursor};
+
+use ina::{DiffConfig, FormatFeature, OldBlob, PatchError, Patcher, format};
+use integer_encoding::VarInt;
+
+/// The tag of the requirements header field
+const TAG_REQUIREMENTS: u64 = 19;
+
+/// Assembles a patch whose header consists of a requirements field with the given value, followed
+/// by data which isn't a valid compressed stream, so that only failing on the header passes
+fn craft(requirements: &[u8]) -> Vec<u8> {
+    let mut extension = TAG_REQUIREMENTS.encode_var_vec();
+    extension.extend_from_slice(&requirements.len().encode_var_vec());
+    extension.extend_from_slice(requirements);
+
+    let mut patch = format::MAGIC.to_vec();
+    patch.extend_from_slice(&format::VERSION_MAJOR.to_le_bytes());
+    patch.extend_from_slice(&format::VERSION_MINOR.to_le_bytes());
+    patch.extend_from_slice(&extension.len().encode_var_vec());
+    patch.extend_from_slice(&extension);
+    patch.extend_from_slice(b"not compressed data");
+
+    patch
+}
+
+/// Encodes the value of a requirements field
+fn requirements(min_minor_version: u64, features: &[&str]) -> Vec<u8> {
+    let mut value = min_minor_version.encode_var_vec();
+    for feature in features {
+        value.extend_from_slice(&feature.len().encode_var_vec());
+        value.extend_from_slice(feature.as_bytes());
+    }
+
+    value
+}
+
+fn patch_error(patch: &[u8]) -> PatchError {
+    match Patcher::new(Cursor::new(b"old"), patch) {
+        Ok(_) => panic!("patch was accepted"),
+        Err(e) => e,
+    }
+}
+
+#[test]
+fn default_patches_record_no_requirements() -> Result<(), Box<dyn Error>> {
+    let patch = ina::diff_to_vec(&OldBlob::from_slice(b"Hello"), b"Hero", &DiffConfig::new())?;
+    let metadata = ina::read_header(&mut patch.as_slice())?;
+
+    assert_eq!(metadata.requirements(), None);
+    assert!(
+        !metadata
+            .format_capabilities()
+            .uses(FormatFeature::Requirements)
+    );
+
+    Ok(())
+}
+
+#[cfg(feature = "brotli")]
+#[test]
+fn codecs_are_required() -> Result<(), Box<dyn Error>> {
+    let config = DiffConfig::new().codec(ina::Codec::Brotli).clone();
+    let patch = ina::diff_to_vec(&OldBlob::from_slice(b"Hello"), b"Hero", &config)?;
+    let metadata = ina::read_header(&mut patch.as_slice())?;
+
+    let requirements = metadata
+        .requirements()
+        .expect("requirements aren't recorded");
+    assert_eq!(requirements.min_minor_version(), 1);
+    assert_eq!(
+        requirements.features().collect::<Vec<_>>(),
+        ["codec=brotli"]
+    );
+    assert_eq!(ina::patch_to_vec(b"Hello", &patch)?, b"Hero");
+
+    Ok(())
+}
+
+#[cfg(feature = "fec")]
+#[test]
+fn fec_is_required() -> Result<(), Box<dyn Error>> {
+    let config = DiffConfig::new()
+        .fec(Some(ina::FecConfig::default()))
+        .clone();
+    let patch = ina::diff_to_vec(&OldBlob::from_slice(b"Hello"), b"Hero", &config)?;
+    let metadata = ina::read_header(&mut patch.as_slice())?;
+
+    let requirements = metadata
+        .requirements()
+        .expect("requirements aren't recorded");
+    assert_eq!(requirements.features().collect::<Vec<_>>(), ["fec"]);
+    assert_eq!(ina::patch_to_vec(b"Hello", &patch)?, b"Hero");
+
+    Ok(())
+}
+
+#[test]
+fn unsupported_features_are_rejected() {
+    for (features, unsupported) in [
+        (vec!["frames"], "frames"),
+        (vec!["codec=lzma"], "codec=lzma"),
+        (
+            vec!["codec=zstd", "block-map", "frames", "codec=lzma"],
+            "frames",
+        ),
+    ] {
+        let error = patch_error(&craft(&requirements(1, &features)));
+        assert!(
+            matches!(&error, PatchError::UnsupportedFeature(name) if name == unsupported),
+            "{features:?} failed with {error:?}",
+        );
+    }
+}
+
+#[test]
+fn newer_minor_versions_are_rejected() {
+    let min_minor_version = format::VERSION_MINOR + 1;
+    let expected = format!("minor-version={min_minor_version}");
+    let error = patch_error(&craft(&requirements(min_minor_version.into(), &[])));
+
+    assert!(
+        matches!(&error, PatchError::UnsupportedFeature(name) if *name == expected),
+        "failed with {error:?}",
+    );
+    assert_eq!(
+        error.to_string(),
+        format!(
+            "unsupported feature: patch requires {expected}, which this patcher doesn't support"
+        ),
+    );
+}
+
+#[test]
+fn supported_requirements_are_accepted() -> Result<(), Box<dyn Error>> {
+    let features = ["codec=zstd", "block-map", "new-len"];
+    let metadata = ina::read_header(&mut craft(&requirements(1, &features)).as_slice())?;
+
+    let requirements = metadata
+        .requirements()
+        .expect("requirements aren't recorded");
+    assert_eq!(requirements.features().collect::<Vec<_>>(), features);
+
+    Ok(())
+}
+
+#[test]
+fn malformed_requirements_are_rejected() {
+    for value in [
+        vec![],
+        vec![0x80],
+        // Minimum minor version too large for a u16
+        (1u64 << 16).encode_var_vec(),
+        // Name longer than the field
+        vec![1, 5, b'f', b'e', b'c'],
+        // Name which isn't UTF-8
+        vec![1, 2, 0xff, 0xfe],
+    ] {
+        let error = patch_error(&craft(&value));
+        assert!(
+            matches!(error, PatchError::InvalidHeaderField(TAG_REQUIREMENTS)),
+            "{value:?} failed with {error:?}",
+        );
+    }
+}
@@ -0,0 +1,64 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+#![cfg(all(feature = "sandbox", target_os = "linux"))]
+#![allow(missing_docs)]
+
+use std::{
+    error::Error,
+    fs::{self, File},
+    io::{self, Cursor, ErrorKind},
+    path::Path,
+    thread,
+};
+
+use ina::{
+    OldBlob,
+    sandbox::{self, SandboxBuilder, SandboxStatus},
+};
+
+#[test]
+fn allowed_dirs_remain_accessible() -> Result<(), Box<dyn Error>> {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("sandbox");
+    let allowed = dir.join("allowed");
+    fs::create_dir_all(&allowed)?;
+    fs::write(dir.join("secret"), b"secret")?;
+
+    let mut patch = Vec::new();
+    ina::diff(&OldBlob::from_slice(b"Hello"), b"Hero", &mut patch)?;
+
+    // Landlock only restricts the thread enabling it, so the rest of the tests are unaffected
+    thread::scope(|scope| {
+        scope
+            .spawn(|| -> io::Result<()> {
+                let mut new = File::create(dir.join("new"))?;
+
+                if !SandboxBuilder::new()
+                    .allow_dir(&allowed)
+                    .enable_for_patching()
+                    .map_err(io::Error::other)?
+                {
+                    eprintln!("skipping: Landlock isn't supported by this kernel");
+                    return Ok(());
+                }
+                assert_eq!(sandbox::status(), SandboxStatus::FilesystemRestricted);
+
+                // Files opened beforehand and the allowed directory remain accessible
+                ina::patch(Cursor::new(b"Hello"), patch.as_slice(), &mut new)
+                    .map_err(io::Error::other)?;
+                fs::write(allowed.join("log"), b"patched")?;
+                assert_eq!(fs::read(allowed.join("log"))?, b"patched");
+
+                // Everything else isn't
+                let error = fs::read(dir.join("secret")).unwrap_err();
+                assert_eq!(error.kind(), ErrorKind::PermissionDenied);
+
+                Ok(())
+            })
+            .join()
+            .unwrap()
+    })?;
+
+    Ok(())
+}
@@ -0,0 +1,130 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+#![cfg(all(feature = "diff", feature = "patch"))]
+#![allow(missing_docs)]
+
+use std::{
+    error::Error,
+    io::{self, Cursor, ErrorKind},
+};
+
+use ina::{OldBlob, SEGMENT_HEADER_LEN, SegmentReader};
+
+fn patch_and_new() -> Result<(Vec<u8>, Vec<u8>), Box<dyn Error>> {
+    let old: Vec<u8> = (0..8192u32).map(|i| (i % 251) as u8).collect();
+    let mut new = old.clone();
+    new[1000..1100].fill(0);
+    new.extend_from_slice(b"appended data");
+
+    let mut patch = Vec::new();
+    ina::diff(&OldBlob::from_vec(old), &new, &mut patch)?;
+
+    Ok((patch, new))
+}
+
+fn join(segments: &[Vec<u8>]) -> io::Result<Vec<u8>> {
+    let mut joined = Vec::new();
+    ina::join_segments(segments.concat().as_slice(), &mut joined)?;
+
+    Ok(joined)
+}
+
+#[test]
+fn split_and_join_round_trip() -> Result<(), Box<dyn Error>> {
+    let (patch, _) = patch_and_new()?;
+
+    for max_segment_len in [
+        SEGMENT_HEADER_LEN + 1,
+        64,
+        100,
+        patch.len() + SEGMENT_HEADER_LEN,
+    ] {
+        let segments = ina::split_patch(&patch, max_segment_len)?;
+        assert!(segments.iter().all(|s| s.len() <= max_segment_len));
+        assert_eq!(join(&segments)?, patch);
+
+        let concatenated = segments.concat();
+        let mut reader = SegmentReader::new(concatenated.as_slice());
+        assert_eq!(reader.segment_count(), None);
+        io::copy(&mut reader, &mut io::sink())?;
+        assert_eq!(reader.segment_count(), Some(u32::try_from(segments.len())?));
+    }
+
+    // Empty blobs are split into a single empty segment
+    let segments = ina::split_patch(&[], 64)?;
+    assert_eq!(segments.len(), 1);
+    assert_eq!(join(&segments)?, b"");
+
+    Ok(())
+}
+
+#[test]
+fn patcher_reads_segments() -> Result<(), Box<dyn Error>> {
+    let old: Vec<u8> = (0..8192u32).map(|i| (i % 251) as u8).collect();
+    let (patch, new) = patch_and_new()?;
+    let segments = ina::split_patch(&patch, 100)?.concat();
+
+    let mut patched = Vec::new();
+    let reader = SegmentReader::new(segments.as_slice());
+    ina::patch(Cursor::new(&old), reader, &mut patched)?;
+    assert_eq!(patched, new);
+
+    Ok(())
+}
+
+#[test]
+fn invalid_segments_are_rejected() -> Result<(), Box<dyn Error>> {
+    let (patch, _) = patch_and_new()?;
+    let segments = ina::split_patch(&patch, SEGMENT_HEADER_LEN + 16)?;
+    assert!(segments.len() > 3);
+
+    // Missing segment
+    let mut missing = segments.clone();
+    missing.remove(2);
+    assert_eq!(join(&missing).unwrap_err().kind(), ErrorKind::InvalidData);
+
+    // Missing last segment
+    let truncated = &segments[..segments.len() - 1];
+    assert_eq!(join(truncated).unwrap_err().kind(), ErrorKind::InvalidData);
+
+    // Incomplete last segment
+    let mut incomplete = segments.concat();
+    incomplete.pop();
+    let error = ina::join_segments(incomplete.as_slice(), io::sink()).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+
+    // Reordered segments
+    let mut reordered = segments.clone();
+    reordered.swap(1, 2);
+    assert_eq!(join(&reordered).unwrap_err().kind(), ErrorKind::InvalidData);
+
+    // Corrupted payload and header
+    for offset in [SEGMENT_HEADER_LEN, 20] {
+        let mut corrupted = segments.clone();
+        corrupted[1][offset] ^= 1;
+        assert_eq!(join(&corrupted).unwrap_err().kind(), ErrorKind::InvalidData);
+    }
+
+    // Segment of another patch
+    let mut other_patch = patch.clone();
+    *other_patch.last_mut().unwrap() ^= 1;
+    let mut mixed = segments.clone();
+    mixed[1] = ina::split_patch(&other_patch, SEGMENT_HEADER_LEN + 16)?.swap_remove(1);
+    assert_eq!(join(&mixed).unwrap_err().kind(), ErrorKind::InvalidData);
+
+    // Not a segment
+    let error = ina::join_segments(patch.as_slice(), io::sink()).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+
+    Ok(())
+}
+
+#[test]
+fn segment_len_must_fit_header() {
+    for max_segment_len in [0, SEGMENT_HEADER_LEN] {
+        let error = ina::split_patch(b"patch", max_segment_len).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidInput);
+    }
+}
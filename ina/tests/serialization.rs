@@ -0,0 +1,95 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+#![allow(missing_docs)]
+
+mod common;
+
+use std::error::Error;
+
+use ina::{BaseCheck, DiffConfig, DiffStats, OldBlob, PatchLimits, PatchMetadata, PatchVersion};
+
+#[test]
+fn diff_config_round_trips() -> Result<(), Box<dyn Error>> {
+    let mut config = DiffConfig::new();
+    config
+        .compression_level(19)
+        .window_log(Some(24))
+        .anchors([(0x4000, 0x5200), (0, 0)])
+        .mask_ranges([7..18, 30..40], [7..18, 30..40])
+        .base_check(BaseCheck::Full)
+        .record_new_len(true);
+
+    let json = serde_json::to_string(&config)?;
+    assert_eq!(serde_json::from_str::<DiffConfig>(&json)?, config);
+
+    Ok(())
+}
+
+#[test]
+fn diff_config_is_normalized() -> Result<(), Box<dyn Error>> {
+    // Unset settings keep their defaults, and the rest are clamped and sorted like their setters do
+    let json = r#"{
+        "window_log": 99,
+        "match_threshold": 0,
+        "anchors": [[16, 32], [0, 0]],
+        "old_mask": { "ranges": [[8, 12], [0, 10]] },
+        "base_check": { "sampled": { "samples": 4, "sample_len": 64 } }
+    }"#;
+
+    let mut expected = DiffConfig::new();
+    expected
+        .window_log(Some(99))
+        .match_threshold(0)
+        .anchors([(16, 32), (0, 0)])
+        .mask_ranges([8..12, 0..10], [])
+        .base_check(BaseCheck::Sampled {
+            samples: 4,
+            sample_len: 64,
+        });
+    assert_eq!(serde_json::from_str::<DiffConfig>(json)?, expected);
+
+    Ok(())
+}
+
+#[test]
+fn patch_metadata_round_trips() -> Result<(), Box<dyn Error>> {
+    let (old, new) = common::binary_pair(7, 16 << 10);
+    let mut config = DiffConfig::new();
+    config.record_new_len(true).payload_checksum(true);
+    let patch = ina::diff_to_vec(&OldBlob::from_slice(&old), &new, &config)?;
+    let metadata = ina::read_header(&mut patch.as_slice())?;
+
+    let json = serde_json::to_value(&metadata)?;
+    assert_eq!(json["version"]["major"], metadata.version().major());
+    assert_eq!(json["new_len"], new.len());
+    assert_eq!(json["codec"], "zstd");
+    assert_eq!(serde_json::from_value::<PatchMetadata>(json)?, metadata);
+
+    Ok(())
+}
+
+#[test]
+fn unsupported_version_is_rejected() {
+    let result = serde_json::from_str::<PatchVersion>(r#"{ "major": 2, "minor": 0 }"#);
+
+    assert!(result.is_err(), "version 2.0 was accepted");
+}
+
+#[test]
+fn stats_and_limits_round_trip() -> Result<(), Box<dyn Error>> {
+    let (old, new) = common::binary_pair(8, 16 << 10);
+    let mut patch = Vec::new();
+    let stats = ina::diff(&OldBlob::from_slice(&old), &new, &mut patch)?;
+
+    let json = serde_json::to_string(&stats)?;
+    assert_eq!(serde_json::from_str::<DiffStats>(&json)?, stats);
+
+    let mut limits = PatchLimits::new();
+    limits.max_memory(64 << 20).max_expansion(100);
+    let json = serde_json::to_string(&limits)?;
+    assert_eq!(serde_json::from_str::<PatchLimits>(&json)?, limits);
+
+    Ok(())
+}
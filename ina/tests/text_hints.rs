@@ -0,0 +1,109 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+#![allow(missing_docs)]
+
+use std::{error::Error, io::Cursor, ops::Range};
+
+use ina::{DiffConfig, OldBlob, TextMode};
+
+fn config_file(lines: &[&str]) -> Vec<u8> {
+    lines
+        .iter()
+        .flat_map(|line| [line, "\n"])
+        .collect::<String>()
+        .into_bytes()
+}
+
+/// The old and new lines of each change recorded in a patch
+type Changes = Vec<(Range<u64>, Range<u64>)>;
+
+fn changes(patch: &[u8]) -> Result<Option<Changes>, Box<dyn Error>> {
+    let metadata = ina::read_header(&mut &*patch)?;
+
+    Ok(metadata.text_hints().map(|hints| {
+        assert!(!hints.is_truncated());
+        hints
+            .changes()
+            .iter()
+            .map(|change| (change.old_lines(), change.new_lines()))
+            .collect()
+    }))
+}
+
+#[test]
+fn text_hints_describe_changed_lines() -> Result<(), Box<dyn Error>> {
+    let mut old_lines: Vec<String> = (0..200u32)
+        .map(|i| format!("key_{i} = {:08x}", i.wrapping_mul(0x9e37_79b9)))
+        .collect();
+    let old = config_file(&old_lines.iter().map(String::as_str).collect::<Vec<_>>());
+    old_lines[20] = "key_20 = changed".into();
+    old_lines.insert(100, "inserted = true".into());
+    // Old line 179
+    old_lines.remove(180);
+    let new = config_file(&old_lines.iter().map(String::as_str).collect::<Vec<_>>());
+
+    let old_blob = OldBlob::from_slice(&old);
+
+    let mut patch = Vec::new();
+    ina::diff_with_config(
+        &old_blob,
+        &new,
+        &mut patch,
+        DiffConfig::new().text_mode(TextMode::Auto),
+    )?;
+    assert_eq!(
+        changes(&patch)?,
+        Some(vec![
+            (20..21, 20..21),
+            (100..100, 100..101),
+            (179..180, 180..180)
+        ]),
+    );
+
+    let mut reconstructed_new = Vec::new();
+    ina::patch(Cursor::new(&old), patch.as_slice(), &mut reconstructed_new)?;
+    assert_eq!(reconstructed_new, new);
+
+    // Transcoding carries the hints over
+    let mut transcoded = Vec::new();
+    ina::transcode(patch.as_slice(), &mut transcoded, &DiffConfig::new())?;
+    assert_eq!(changes(&transcoded)?, changes(&patch)?);
+
+    Ok(())
+}
+
+#[test]
+fn text_mode_auto_ignores_binary() -> Result<(), Box<dyn Error>> {
+    let old: Vec<u8> = (0..4096u32).map(|i| (i * 31 % 251) as u8).collect();
+    let mut new = old.clone();
+    new[1000..1100].fill(b'\n');
+    let old_blob = OldBlob::from_slice(&old);
+
+    let mut patch = Vec::new();
+    let mut config = DiffConfig::new();
+    ina::diff_with_config(
+        &old_blob,
+        &new,
+        &mut patch,
+        config.text_mode(TextMode::Auto),
+    )?;
+    assert_eq!(changes(&patch)?, None);
+
+    // Forcing text mode still produces a valid patch
+    let mut patch = Vec::new();
+    ina::diff_with_config(
+        &old_blob,
+        &new,
+        &mut patch,
+        config.text_mode(TextMode::Text),
+    )?;
+    assert!(changes(&patch)?.is_some());
+
+    let mut reconstructed_new = Vec::new();
+    ina::patch(Cursor::new(&old), patch.as_slice(), &mut reconstructed_new)?;
+    assert_eq!(reconstructed_new, new);
+
+    Ok(())
+}
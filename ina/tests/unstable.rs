@@ -0,0 +1,111 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+#![allow(missing_docs)]
+
+mod common;
+
+use std::{error::Error, io::ErrorKind};
+
+use ina::{
+    DiffConfig, OldBlob,
+    unstable::v0::{
+        BsdiffMatcher, ControlReader, Match, Matcher, diff_with_matcher, diff_with_matches,
+    },
+};
+
+/// Converts the control records of `patch` back into the matches it was encoded from
+fn matches_of(patch: &[u8]) -> Result<Vec<Match>, Box<dyn Error>> {
+    let mut matches = Vec::new();
+    let (mut old_pos, mut new_pos) = (0usize, 0usize);
+    for record in ControlReader::new(patch)? {
+        let record = record?;
+        let copy_end = new_pos + record.add().len() + record.copy().len();
+        matches.push(Match::new(old_pos, new_pos, record.add().len(), copy_end));
+        old_pos = old_pos
+            .wrapping_add(record.add().len())
+            .wrapping_add_signed(record.seek() as isize);
+        new_pos = copy_end;
+    }
+
+    Ok(matches)
+}
+
+#[test]
+fn control_records_round_trip_through_matches() -> Result<(), Box<dyn Error>> {
+    let (old, new) = common::binary_pair(3, 64 << 10);
+    let old = OldBlob::from_vec(old);
+    let patch = ina::diff_to_vec(&old, &new, &DiffConfig::new())?;
+
+    let matches = matches_of(&patch)?;
+    let options = DiffConfig::new().record_new_len(true).clone();
+    let mut rebuilt = Vec::new();
+    diff_with_matches(&old, &new, matches.clone(), &mut rebuilt, &options)?;
+
+    assert_eq!(ina::patch_to_vec(old.as_bytes(), &rebuilt)?, new);
+    assert_eq!(matches_of(&rebuilt)?, matches);
+    assert_eq!(
+        ina::read_header(&mut rebuilt.as_slice())?.new_len(),
+        Some(new.len() as u64),
+    );
+
+    Ok(())
+}
+
+#[test]
+fn matches_ending_early_are_rejected() -> Result<(), Box<dyn Error>> {
+    let old = OldBlob::from_slice(b"Hello");
+    let new = b"Hello, world!";
+
+    for matches in [vec![], vec![Match::new(0, 0, 5, 7)]] {
+        let error =
+            diff_with_matches(&old, new, matches, &mut Vec::new(), &DiffConfig::new()).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidInput);
+    }
+
+    // No matches are needed to cover an empty new blob
+    let mut patch = Vec::new();
+    diff_with_matches(&old, b"", [], &mut patch, &DiffConfig::new())?;
+    assert!(ina::patch_to_vec(old.as_bytes(), &patch)?.is_empty());
+
+    Ok(())
+}
+
+/// A matcher which stores all of the new blob verbatim
+struct VerbatimMatcher;
+
+impl Matcher for VerbatimMatcher {
+    fn find_matches(&mut self, _: &OldBlob, new: &[u8]) -> Vec<Match> {
+        vec![Match::new(0, 0, 0, new.len())]
+    }
+}
+
+#[test]
+fn matchers_produce_applicable_patches() -> Result<(), Box<dyn Error>> {
+    let (old, new) = common::binary_pair(4, 64 << 10);
+    let old = OldBlob::from_vec(old);
+
+    let mut bsdiff = Vec::new();
+    diff_with_matcher(
+        &old,
+        &new,
+        &mut BsdiffMatcher::default(),
+        &mut bsdiff,
+        &DiffConfig::new(),
+    )?;
+    let mut verbatim = Vec::new();
+    diff_with_matcher(
+        &old,
+        &new,
+        &mut VerbatimMatcher,
+        &mut verbatim,
+        &DiffConfig::new(),
+    )?;
+
+    assert_eq!(ina::patch_to_vec(old.as_bytes(), &bsdiff)?, new);
+    assert_eq!(ina::patch_to_vec(old.as_bytes(), &verbatim)?, new);
+    assert!(bsdiff.len() < verbatim.len());
+
+    Ok(())
+}
@@ -0,0 +1,89 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+#![cfg(all(feature = "diff", feature = "verify"))]
+#![allow(missing_docs)]
+
+mod common;
+
+use std::{
+    error::Error,
+    io::{self, Cursor, Read},
+};
+
+use common::Rng;
+use ina::{DiffConfig, DigestAlgorithm, OldBlob, VerifyOptions};
+
+/// A reader which counts the bytes read from it
+struct CountingReader<R> {
+    inner: R,
+    read: u64,
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.read += read as u64;
+
+        Ok(read)
+    }
+}
+
+#[test]
+fn mismatches_are_reported_in_constant_time() -> Result<(), Box<dyn Error>> {
+    let old = common::periodic_blob(100_000, 7);
+    let new = common::edited_blob(&old, 5000..5100);
+    let patch = ina::diff_to_vec(&OldBlob::from_slice(&old), &new, &DiffConfig::new())?;
+
+    let mut wrong = new.clone();
+    wrong[50_000] ^= 1;
+    for constant_time in [false, true] {
+        let mut options = VerifyOptions::new();
+        options.constant_time(constant_time);
+        let verify = |expected: &[u8]| {
+            let digest = DigestAlgorithm::Blake3.hash(expected);
+            ina::verify(Cursor::new(&old), patch.as_slice(), &digest, &options)
+        };
+
+        assert!(verify(&new)?, "{constant_time}");
+        assert!(!verify(&wrong)?, "{constant_time}");
+        assert!(!verify(&new[..new.len() - 1])?, "{constant_time}");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn constant_time_verification_reads_the_whole_patch() -> Result<(), Box<dyn Error>> {
+    // Random data can't be compressed, so the patch is much larger than the buffers it's read with
+    let mut rng = Rng::new(0);
+    let new: Vec<u8> = (0..1 << 20).map(|_| rng.next_u64() as u8).collect();
+    let mut patch = ina::diff_to_vec(&OldBlob::from_slice(b"old"), &new, &DiffConfig::new())?;
+
+    // Corrupt the start of the compressed data, so that patching fails right away
+    let mut payload = patch.as_slice();
+    ina::read_header(&mut payload)?;
+    let header_len = patch.len() - payload.len();
+    patch[header_len] ^= 0xff;
+
+    let digest = DigestAlgorithm::Blake3.hash(&new);
+    for constant_time in [false, true] {
+        let mut reader = CountingReader {
+            inner: patch.as_slice(),
+            read: 0,
+        };
+        let mut options = VerifyOptions::new();
+        options.constant_time(constant_time);
+        assert!(ina::verify(Cursor::new(b"old"), &mut reader, &digest, &options).is_err());
+
+        // Only constant-time verification consumes the patch regardless of where it fails
+        assert_eq!(
+            reader.read == patch.len() as u64,
+            constant_time,
+            "{constant_time}",
+        );
+    }
+
+    Ok(())
+}
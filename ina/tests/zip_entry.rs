@@ -0,0 +1,98 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+#![cfg(all(feature = "zip", feature = "diff"))]
+#![allow(missing_docs)]
+
+mod common;
+
+use std::{
+    error::Error,
+    fs,
+    io::{self, Cursor, Read, Write},
+    path::Path,
+};
+
+use ina::{OldBlob, ZipEntryReader};
+use zip::{CompressionMethod, ZipWriter, write::SimpleFileOptions};
+
+/// Creates a zip archive holding `patch` as `patches/app.ina` among other entries
+fn archive(patch: &[u8], options: SimpleFileOptions) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    writer.start_file("metadata.json", SimpleFileOptions::default())?;
+    writer.write_all(br#"{"version": 2}"#)?;
+    writer.start_file("patches/app.ina", options)?;
+    writer.write_all(patch)?;
+    writer.set_comment("OTA package");
+
+    Ok(writer.finish()?.into_inner())
+}
+
+fn apply<R: Read>(old: &[u8], patch: R) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut new = Vec::new();
+    ina::patch(Cursor::new(old), patch, &mut new)?;
+
+    Ok(new)
+}
+
+fn stored() -> SimpleFileOptions {
+    SimpleFileOptions::default().compression_method(CompressionMethod::Stored)
+}
+
+#[test]
+fn patch_from_stored_entry() -> Result<(), Box<dyn Error>> {
+    let (old, new) = common::binary_pair(7, 64 << 10);
+    let patch = ina::diff_to_vec(&OldBlob::from_slice(&old), &new, &Default::default())?;
+
+    for options in [stored(), stored().large_file(true)] {
+        let archive = archive(&patch, options)?;
+        let entry = ZipEntryReader::new(Cursor::new(&archive), "patches/app.ina")?;
+        assert_eq!(entry.len(), patch.len() as u64);
+        assert_eq!(apply(&old, entry)?, new);
+    }
+
+    let archive_path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("ota.zip");
+    fs::write(&archive_path, archive(&patch, stored())?)?;
+    let address = format!("{}:patches/app.ina", archive_path.display());
+    assert_eq!(apply(&old, ZipEntryReader::open(&address)?)?, new);
+
+    Ok(())
+}
+
+#[test]
+fn unreadable_entries_are_rejected() -> Result<(), Box<dyn Error>> {
+    let patch = ina::diff_to_vec(&OldBlob::from_slice(b"Hello"), b"Hero", &Default::default())?;
+    let archive = archive(&patch, stored())?;
+
+    let err = ZipEntryReader::new(Cursor::new(&archive), "patches/missing.ina").unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    let err = ZipEntryReader::new(Cursor::new(b"not a zip archive"), "app.ina").unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    let err = ZipEntryReader::open("ota.zip").unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+    let deflated = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    let archive = self::archive(&patch, deflated)?;
+    let err = ZipEntryReader::new(Cursor::new(&archive), "patches/app.ina").unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+
+    Ok(())
+}
+
+#[test]
+fn corrupted_entry_fails_crc() -> Result<(), Box<dyn Error>> {
+    let patch = b"entry contents standing in for a patch";
+    let mut archive = archive(patch, stored())?;
+    let pos = archive
+        .windows(patch.len())
+        .position(|window| window == patch)
+        .unwrap();
+    archive[pos] ^= 1;
+
+    let mut entry = ZipEntryReader::new(Cursor::new(&archive), "patches/app.ina")?;
+    let err = entry.read_to_end(&mut Vec::new()).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+    Ok(())
+}
@@ -11,6 +11,8 @@ use sufsort::SuffixArray;
 
 const DATA_PATH: &str = "benches/testdata/pizzachili-pitches.data";
 const CHUNK_SIZE: u64 = 512;
+const PATTERN_COUNT: usize = 1024;
+const PATTERN_LEN: usize = 32;
 
 fn construct(c: &mut Criterion) {
     let mut group = c.benchmark_group("construct");
@@ -60,5 +62,58 @@ fn construct(c: &mut Criterion) {
     group.finish();
 }
 
-criterion_group!(benches, construct);
+fn search(c: &mut Criterion) {
+    let mut group = c.benchmark_group("search");
+
+    let mut data = std::fs::read(DATA_PATH).unwrap();
+    data.push(0);
+    let sa = SuffixArray::new(&data);
+
+    // Search for substrings of the data, half of them with a mismatching tail, so that both full
+    // and partial matches are exercised
+    let patterns: Vec<Vec<u8>> = (0..PATTERN_COUNT)
+        .map(|i| {
+            let start = i * 7919 % (data.len() - PATTERN_LEN);
+            let mut pattern = data[start..start + PATTERN_LEN].to_vec();
+            if i % 2 == 1 {
+                pattern[PATTERN_LEN / 2] ^= 0xff;
+            }
+            pattern
+        })
+        .collect();
+
+    group.throughput(Throughput::Elements(PATTERN_COUNT as u64));
+    group.bench_function("contains", |b| {
+        b.iter(|| patterns.iter().filter(|p| sa.contains(p)).count());
+    });
+    group.bench_function("longest_match", |b| {
+        b.iter(|| {
+            patterns
+                .iter()
+                .map(|p| sa.longest_match(p).map_or(0, |m| m.len()))
+                .sum::<usize>()
+        });
+    });
+    let prefiltered = sa.clone().with_prefilter();
+    group.bench_function("longest_match_prefiltered", |b| {
+        b.iter(|| {
+            patterns
+                .iter()
+                .map(|p| prefiltered.longest_match(p).map_or(0, |m| m.len()))
+                .sum::<usize>()
+        });
+    });
+    group.bench_function("longest_match_many", |b| {
+        b.iter(|| {
+            sa.longest_match_many(&patterns)
+                .iter()
+                .map(|m| m.map_or(0, |m| m.len()))
+                .sum::<usize>()
+        });
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, construct, search);
 criterion_main!(benches);
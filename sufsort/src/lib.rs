@@ -50,6 +50,25 @@
 //! (this space excluding the sizes of the input data and output suffix array, which total 5*n*
 //! bytes). All searching operations run in *O*(*m* \* log(*n*)) time for patterns of length *m*.
 //!
+//! Data held in several buffers, e.g., reassembled from the network or allocated from a memory
+//! pool, can be indexed with [`SuffixArray::from_chunks()`], which copies it into a single buffer
+//! since both construction and searching access the data at arbitrary positions.
+//!
+//! # Features
+//!
+//! Functionality beyond suffix array construction and substring searching is gated behind the
+//! following feature flags:
+//!
+//! - `ranges` (enabled by default): searching by rank with custom comparators via
+//!   [`SuffixArray::search_by()`], [`SuffixArray::partition_point()`], and
+//!   [`SuffixArray::equal_range()`]
+//! - `lcp`: longest common prefix arrays via `SuffixArray::lcp()`
+//! - `bwt`: Burrows-Wheeler transforms via `SuffixArray::bwt()`
+//! - `owned`: storing and reusing the sorted suffixes independently of the data via
+//!   `SuffixArray::into_suffixes()` and `SuffixArray::from_suffixes()`
+//! - `mapped`: sharing the sorted suffixes between processes without copying them, e.g., through a
+//!   memory-mapped file, via `SuffixArray::suffix_bytes()` and `SuffixArray::from_suffix_bytes()`
+//!
 //! # Design considerations
 //!
 //! This library has a very strong focus on security, robustness, and speed. As such, it is:
@@ -63,10 +82,12 @@
 //! will freely take on additional features as needed.
 
 #![no_std]
+#![forbid(unsafe_code)]
 
 extern crate alloc;
 
 mod sacak;
+mod small;
 mod suffix_array;
 
 pub use suffix_array::{Substring, SuffixArray};
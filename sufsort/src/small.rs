@@ -0,0 +1,81 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use alloc::vec::Vec;
+
+/// The maximum length of data whose suffixes are sorted by comparison rather than with SACA-K.
+///
+/// SACA-K makes several passes over buckets for the whole alphabet regardless of the length of the
+/// data, which dominates its running time for short data. Comparison sorting has no such fixed
+/// cost, and its *O*(*n*² log *n*) worst case is negligible at this length. Benchmarks of random,
+/// repetitive, and uniform data put the break-even point between 128 and 256 bytes.
+pub(crate) const SMALL_LEN: usize = 128;
+
+/// Computes the suffix array of `data` by sorting its suffixes with direct comparisons.
+///
+/// The result is identical to that of [`sacak()`](crate::sacak::sacak), including the ordering of
+/// suffixes which begin with 0.
+///
+/// # Panics
+///
+/// Panics if `data` isn't empty and its last element is not 0.
+pub(crate) fn sort_suffixes(data: &[u8]) -> Vec<u32> {
+    if let Some(&last) = data.last() {
+        assert_eq!(last, 0, "last element in `data` must be 0");
+    }
+
+    // `data` is far shorter than u32::MAX bytes, so the suffix positions fit in a u32
+    let mut suffix_array: Vec<u32> = (0..data.len() as u32).collect();
+    suffix_array.sort_unstable_by_key(|&pos| &data[pos as usize..]);
+
+    suffix_array
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use super::*;
+    use crate::sacak::sacak;
+
+    #[test]
+    fn matches_sacak() {
+        let mut state = 0x2545_f491_u32;
+        let mut next = || {
+            // xorshift32
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state
+        };
+
+        for len in 0..=SMALL_LEN {
+            // Small alphabets produce long repeats, and an alphabet of 1 includes extra zeros
+            for alphabet in [1, 2, 4, 256] {
+                let mut data: Vec<u8> = (0..len).map(|_| (next() % alphabet) as u8).collect();
+                if let Some(last) = data.last_mut() {
+                    *last = 0;
+                }
+
+                assert_eq!(sort_suffixes(&data), sacak(&data), "data: {data:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn multiple_zeroes() {
+        let text = "Hello, \0world!\0";
+
+        assert_eq!(
+            sort_suffixes(text.as_bytes()),
+            vec![14, 7, 6, 13, 5, 0, 12, 1, 11, 2, 3, 4, 9, 10, 8],
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "last element in `data` must be 0")]
+    fn missing_sentinel() {
+        sort_suffixes(b"Hello");
+    }
+}
@@ -2,16 +2,27 @@
 //
 // SPDX-License-Identifier: Apache-2.0
 
-use alloc::vec::Vec;
-use core::{cmp::Ordering, ops::Deref};
+use alloc::borrow::Cow;
+use alloc::{vec, vec::Vec};
+use core::{
+    cmp::Ordering,
+    ops::{Deref, Range},
+};
 
-use crate::sacak;
+use crate::{sacak, small};
 
 /// A suffix array for a byte string.
+///
+/// The sorted suffixes are either owned by the suffix array or, if it was created with
+/// `SuffixArray::from_suffix_bytes()`, borrowed from elsewhere, such as a memory-mapped file.
+/// Cloning a suffix array with borrowed suffixes doesn't copy them.
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct SuffixArray<'a> {
     data: &'a [u8],
-    inner: Vec<u32>,
+    inner: Cow<'a, [u32]>,
+    /// The rank of the first suffix beginning with each pair of bytes, followed by the number of
+    /// suffixes, if the prefilter was built
+    buckets: Option<Vec<u32>>,
 }
 
 impl<'a> SuffixArray<'a> {
@@ -20,7 +31,9 @@ impl<'a> SuffixArray<'a> {
     /// Note that `data` MUST have a `0` appended to the end of the data you actually wish to sort
     /// for the algorithm to work properly.
     ///
-    /// This operation is *O*(*n*).
+    /// This operation is *O*(*n*). Suffixes of very short data, such as small configuration files,
+    /// are sorted by direct comparison instead, which avoids the fixed cost of the linear-time
+    /// algorithm and produces the same suffix array.
     ///
     /// # Panics
     ///
@@ -36,14 +49,486 @@ impl<'a> SuffixArray<'a> {
     /// ```
     #[must_use]
     pub fn new(data: &'a [u8]) -> Self {
-        let inner = sacak::sacak(data);
+        let suffixes = if data.len() <= small::SMALL_LEN {
+            small::sort_suffixes(data)
+        } else {
+            sacak::sacak(data)
+        };
+        let inner = Cow::Owned(suffixes);
+
+        Self {
+            data,
+            inner,
+            buckets: None,
+        }
+    }
+
+    /// Creates a new `SuffixArray` for the concatenation of `chunks`.
+    ///
+    /// Both construction and searching access the data at arbitrary positions, so the chunks are
+    /// copied into `buf` one after the other, followed by the sentinel, which must therefore not
+    /// be part of the chunks. `buf` is cleared first and reserves exactly the space needed, so
+    /// indexing chunked data takes one extra allocation the size of the data, but no more, and
+    /// the suffix array associates `buf` as its data. The chunks can be dropped afterward.
+    ///
+    /// This operation is *O*(*n*) like [`SuffixArray::new()`], which produces the same suffix
+    /// array for the concatenated data.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the total length of the chunks is at least `u32::MAX`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sufsort::SuffixArray;
+    ///
+    /// let chunks = [b"Hello, ".as_slice(), b"world!"];
+    /// let mut buf = Vec::new();
+    /// let sa = SuffixArray::from_chunks(chunks, &mut buf);
+    ///
+    /// assert!(sa.contains(b"o, w"));
+    /// assert_eq!(sa.data(), b"Hello, world!\0");
+    /// ```
+    #[must_use]
+    pub fn from_chunks<'c, I>(chunks: I, buf: &'a mut Vec<u8>) -> Self
+    where
+        I: IntoIterator<Item = &'c [u8]>,
+    {
+        let chunks: Vec<_> = chunks.into_iter().collect();
+        let len = chunks.iter().map(|chunk| chunk.len()).sum::<usize>() + 1;
+
+        buf.clear();
+        buf.reserve_exact(len);
+        for chunk in chunks {
+            buf.extend_from_slice(chunk);
+        }
+        buf.push(0);
+
+        Self::new(buf)
+    }
+
+    /// Builds a lookup table of the suffixes beginning with each pair of bytes, which
+    /// [`contains()`] and [`longest_match()`] use to narrow their binary search before comparing
+    /// any suffixes.
+    ///
+    /// Without the table, the first iterations of each search compare the pattern with suffixes
+    /// scattered throughout the associated data, which mostly miss the cache for large data. The
+    /// table replaces those iterations with two adjacent lookups, so it's worthwhile for data of
+    /// at least a few megabytes that is searched many times. Search results are unaffected.
+    ///
+    /// This operation is *O*(*n*) and allocates 256 KiB regardless of the length of the data.
+    ///
+    /// [`contains()`]: SuffixArray::contains
+    /// [`longest_match()`]: SuffixArray::longest_match
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sufsort::SuffixArray;
+    ///
+    /// let sa = SuffixArray::new(b"Hello, world!\0").with_prefilter();
+    /// assert!(sa.contains(b"world"));
+    /// assert_eq!(sa.longest_match(b"worth").as_deref(), Some(b"wor".as_ref()));
+    /// ```
+    #[must_use]
+    pub fn with_prefilter(mut self) -> Self {
+        // The only suffix shorter than two bytes is the sentinel, which sorts before every other
+        // suffix and so can be counted as beginning with two zeros
+        let mut buckets = vec![0; BUCKETS + 1];
+        for position in 0..self.data.len() {
+            buckets[bucket(&self.data[position..]) + 1] += 1;
+        }
+        for key in 1..buckets.len() {
+            buckets[key] += buckets[key - 1];
+        }
+        self.buckets = Some(buckets);
+
+        self
+    }
+
+    /// Returns the data associated with this suffix array.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sufsort::SuffixArray;
+    ///
+    /// let data = b"Hello, world!\0";
+    /// let sa = SuffixArray::new(data);
+    /// assert_eq!(sa.data(), data);
+    /// ```
+    #[must_use]
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// Returns the number of suffixes in the suffix array, including the sentinel suffix.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sufsort::SuffixArray;
+    ///
+    /// let sa = SuffixArray::new(b"banana\0");
+    /// assert_eq!(sa.len(), 7);
+    /// ```
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Returns `true` if the suffix array contains no suffixes.
+    ///
+    /// Because the associated data always contains a sentinel, this is always `false`.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Returns the starting positions of all suffixes of the associated data in sorted order.
+    ///
+    /// The index of a suffix in this slice is its rank, i.e., the number of suffixes which are
+    /// lexicographically smaller than it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sufsort::SuffixArray;
+    ///
+    /// let sa = SuffixArray::new(b"banana\0");
+    /// assert_eq!(sa.suffixes(), &[6, 5, 3, 1, 0, 4, 2]);
+    /// ```
+    #[must_use]
+    pub fn suffixes(&self) -> &[u32] {
+        &self.inner
+    }
+
+    /// Returns the suffix of the associated data with the given rank, or `None` if `rank` is out
+    /// of bounds.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sufsort::SuffixArray;
+    ///
+    /// let sa = SuffixArray::new(b"banana\0");
+    /// assert_eq!(sa.suffix(3), Some(b"anana\0".as_ref()));
+    /// assert_eq!(sa.suffix(7), None);
+    /// ```
+    #[must_use]
+    pub fn suffix(&self, rank: usize) -> Option<&'a [u8]> {
+        self.inner
+            .get(rank)
+            .map(|&position| &self.data[position as usize..])
+    }
+
+    /// Binary searches the sorted suffixes with a comparator function.
+    ///
+    /// The comparator is called with suffixes of the associated data and should return whether
+    /// each suffix is less than, equal to, or greater than the target. If a matching suffix is
+    /// found, its rank is returned in `Ok`. Otherwise, the rank at which a matching suffix could
+    /// be inserted while maintaining sorted order is returned in `Err`. See
+    /// [`slice::binary_search_by()`] for details.
+    ///
+    /// This operation calls `f` *O*(log(*n*)) times and never allocates.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sufsort::SuffixArray;
+    ///
+    /// let sa = SuffixArray::new(b"banana\0");
+    /// let rank = sa.search_by(|suffix| suffix.cmp(b"ana\0")).unwrap();
+    /// assert_eq!(sa.suffixes()[rank], 3);
+    /// ```
+    #[cfg(feature = "ranges")]
+    #[inline]
+    pub fn search_by<F>(&self, mut f: F) -> Result<usize, usize>
+    where
+        F: FnMut(&'a [u8]) -> Ordering,
+    {
+        self.inner
+            .binary_search_by(|&position| f(&self.data[position as usize..]))
+    }
+
+    /// Returns the rank of the first suffix for which `pred` returns `false`.
+    ///
+    /// The sorted suffixes must be partitioned by `pred`, i.e., `pred` must return `true` for
+    /// some prefix of the sorted suffixes and `false` for the rest. See
+    /// [`slice::partition_point()`] for details.
+    ///
+    /// This operation calls `pred` *O*(log(*n*)) times and never allocates.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sufsort::SuffixArray;
+    ///
+    /// let sa = SuffixArray::new(b"banana\0");
+    /// // The number of suffixes less than "b"
+    /// assert_eq!(sa.partition_point(|suffix| suffix < b"b".as_ref()), 4);
+    /// ```
+    #[cfg(feature = "ranges")]
+    #[inline]
+    pub fn partition_point<P>(&self, mut pred: P) -> usize
+    where
+        P: FnMut(&'a [u8]) -> bool,
+    {
+        self.inner
+            .partition_point(|&position| pred(&self.data[position as usize..]))
+    }
+
+    /// Returns the range of ranks of the suffixes which begin with `pattern`.
+    ///
+    /// The range is empty if `pattern` doesn't occur in the associated data. Otherwise, the
+    /// positions of every occurrence of `pattern` can be found by indexing [`suffixes()`] with the
+    /// range.
+    ///
+    /// This operation is *O*(*m* \* log(*n*)), where `m` is `pattern.len()`, and never
+    /// allocates.
+    ///
+    /// [`suffixes()`]: SuffixArray::suffixes
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sufsort::SuffixArray;
+    ///
+    /// let sa = SuffixArray::new(b"banana\0");
+    /// let ranks = sa.equal_range(b"an");
+    ///
+    /// let mut positions = sa.suffixes()[ranks].to_vec();
+    /// positions.sort();
+    /// assert_eq!(positions, [1, 3]);
+    /// ```
+    #[cfg(feature = "ranges")]
+    #[inline]
+    #[must_use]
+    pub fn equal_range(&self, pattern: &[u8]) -> Range<usize> {
+        let prefix = |suffix: &'a [u8]| &suffix[..suffix.len().min(pattern.len())];
+
+        let start = self.partition_point(|suffix| prefix(suffix) < pattern);
+        let end = self.partition_point(|suffix| prefix(suffix) <= pattern);
+
+        start..end
+    }
+
+    /// Returns the longest common prefix array of the suffix array.
+    ///
+    /// The element at each rank is the length of the longest common prefix of the suffix with
+    /// that rank and the suffix with the preceding rank. The element at rank 0 is always 0.
+    ///
+    /// This operation is *O*(*n*) and allocates 8*n* bytes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sufsort::SuffixArray;
+    ///
+    /// let sa = SuffixArray::new(b"banana\0");
+    /// // "ana\0" and "anana\0" share the prefix "ana"
+    /// assert_eq!(sa.lcp(), [0, 0, 1, 3, 0, 0, 2]);
+    /// ```
+    #[cfg(feature = "lcp")]
+    #[must_use]
+    pub fn lcp(&self) -> Vec<u32> {
+        let n = self.inner.len();
+
+        let mut ranks = vec![0; n];
+        for (rank, &position) in self.inner.iter().enumerate() {
+            ranks[position as usize] = rank;
+        }
+
+        // Kasai's algorithm: the common prefix of the suffix at each position with its predecessor
+        // is at most one shorter than that of the suffix at the previous position
+        let mut lcp = vec![0; n];
+        let mut len = 0;
+        for (position, &rank) in ranks.iter().enumerate() {
+            if rank == 0 {
+                len = 0;
+                continue;
+            }
+
+            let previous = self.inner[rank - 1] as usize;
+            len += common_prefix_len(&self.data[position + len..], &self.data[previous + len..]);
+            lcp[rank] = len as u32;
+            len = len.saturating_sub(1);
+        }
+
+        lcp
+    }
+
+    /// Returns the Burrows-Wheeler transform of the associated data.
+    ///
+    /// The element at each rank is the byte preceding the suffix with that rank, wrapping around
+    /// to the last byte of the data for the suffix beginning at position 0.
+    ///
+    /// This operation is *O*(*n*).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sufsort::SuffixArray;
+    ///
+    /// let sa = SuffixArray::new(b"banana\0");
+    /// assert_eq!(sa.bwt(), b"annb\0aa");
+    /// ```
+    #[cfg(feature = "bwt")]
+    #[must_use]
+    pub fn bwt(&self) -> Vec<u8> {
+        self.inner
+            .iter()
+            .map(|&position| match position {
+                0 => self.data[self.data.len() - 1],
+                _ => self.data[position as usize - 1],
+            })
+            .collect()
+    }
+
+    /// Consumes the suffix array, returning the starting positions of its suffixes in sorted order.
+    ///
+    /// The result can be stored and later passed to [`SuffixArray::from_suffixes()`] along with
+    /// the same data to avoid sorting the data again.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sufsort::SuffixArray;
+    ///
+    /// let suffixes = SuffixArray::new(b"banana\0").into_suffixes();
+    /// assert_eq!(suffixes, [6, 5, 3, 1, 0, 4, 2]);
+    /// ```
+    #[cfg(feature = "owned")]
+    #[must_use]
+    pub fn into_suffixes(self) -> Vec<u32> {
+        self.inner.into_owned()
+    }
+
+    /// Creates a `SuffixArray` for `data` from its sorted suffixes.
+    ///
+    /// `suffixes` must have been returned by [`SuffixArray::into_suffixes()`] for the same data.
+    /// Whether the suffixes are sorted isn't checked since doing so is expensive, so searching a
+    /// suffix array created from other suffixes returns unspecified results.
+    ///
+    /// This operation is *O*(*n*).
+    ///
+    /// Returns `None` if the last element in `data` is not 0, if `suffixes` has a different
+    /// length than `data`, or if any suffix begins outside of `data`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sufsort::SuffixArray;
+    ///
+    /// let data = b"banana\0";
+    /// let suffixes = SuffixArray::new(data).into_suffixes();
+    ///
+    /// let sa = SuffixArray::from_suffixes(data, suffixes).unwrap();
+    /// assert!(sa.contains(b"nan"));
+    /// ```
+    #[cfg(feature = "owned")]
+    #[must_use]
+    pub fn from_suffixes(data: &'a [u8], suffixes: Vec<u32>) -> Option<Self> {
+        valid_suffixes(data, &suffixes).then_some(Self {
+            data,
+            inner: Cow::Owned(suffixes),
+            buckets: None,
+        })
+    }
 
-        Self { data, inner }
+    /// Creates a `SuffixArray` for `data` which borrows its sorted suffixes from `suffixes`
+    /// without copying them.
+    ///
+    /// This behaves like [`SuffixArray::from_suffixes()`], except that `suffixes` can be shared,
+    /// e.g., by several threads searching the same data at once.
+    ///
+    /// This operation is *O*(*n*), but doesn't allocate.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sufsort::SuffixArray;
+    ///
+    /// let data = b"banana\0";
+    /// let suffixes = SuffixArray::new(data).into_suffixes();
+    ///
+    /// let sa = SuffixArray::from_suffix_slice(data, &suffixes).unwrap();
+    /// assert!(sa.contains(b"nan"));
+    /// ```
+    #[cfg(feature = "owned")]
+    #[must_use]
+    pub fn from_suffix_slice(data: &'a [u8], suffixes: &'a [u32]) -> Option<Self> {
+        valid_suffixes(data, suffixes).then_some(Self {
+            data,
+            inner: Cow::Borrowed(suffixes),
+            buckets: None,
+        })
+    }
+
+    /// Returns the sorted suffixes as bytes in native byte order.
+    ///
+    /// The result can be written to a file, which can later be memory-mapped and passed to
+    /// [`SuffixArray::from_suffix_bytes()`] along with the same data. This allows several
+    /// processes on the same machine to share a single copy of the suffix array.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sufsort::SuffixArray;
+    ///
+    /// let sa = SuffixArray::new(b"banana\0");
+    /// assert_eq!(sa.suffix_bytes().len(), 7 * 4);
+    /// ```
+    #[cfg(feature = "mapped")]
+    #[must_use]
+    pub fn suffix_bytes(&self) -> &[u8] {
+        bytemuck::cast_slice(&self.inner)
+    }
+
+    /// Creates a `SuffixArray` for `data` which borrows its sorted suffixes from `bytes` without
+    /// copying them.
+    ///
+    /// `bytes` must have been returned by [`SuffixArray::suffix_bytes()`] for the same data on a
+    /// machine with the same byte order, e.g., by writing it to a file which is then
+    /// memory-mapped. It must be aligned to 4 bytes, which memory mappings always are. As with
+    /// [`SuffixArray::from_suffixes()`], whether the suffixes are sorted isn't checked.
+    ///
+    /// This operation is *O*(*n*), but doesn't allocate.
+    ///
+    /// Returns `None` if `bytes` isn't aligned to 4 bytes, if the last element in `data` is not 0,
+    /// if `bytes` doesn't hold one suffix for each element of `data`, or if any suffix begins
+    /// outside of `data`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sufsort::SuffixArray;
+    ///
+    /// let data = b"banana\0";
+    /// let sa = SuffixArray::new(data);
+    ///
+    /// // In practice, the bytes would be read from a memory-mapped file
+    /// let borrowed = SuffixArray::from_suffix_bytes(data, sa.suffix_bytes()).unwrap();
+    /// assert!(borrowed.contains(b"nan"));
+    /// ```
+    #[cfg(feature = "mapped")]
+    #[must_use]
+    pub fn from_suffix_bytes(data: &'a [u8], bytes: &'a [u8]) -> Option<Self> {
+        let suffixes = bytemuck::try_cast_slice(bytes).ok()?;
+
+        valid_suffixes(data, suffixes).then_some(Self {
+            data,
+            inner: Cow::Borrowed(suffixes),
+            buckets: None,
+        })
     }
 
     /// Returns `true` if and only if `pattern` is contained in the associated data.
     ///
-    /// This operation is *O*(*m* \* log(*n*)), where `m` is `pattern.len()`.
+    /// This operation is *O*(*m* \* log(*n*)), where `m` is `pattern.len()`, and never
+    /// allocates. The search only covers the suffixes beginning with the same bytes as `pattern`
+    /// if the suffix array was created [with a prefilter](SuffixArray::with_prefilter).
     ///
     /// # Examples
     ///
@@ -54,15 +539,11 @@ impl<'a> SuffixArray<'a> {
     /// let sa = SuffixArray::new(data);
     /// assert!(sa.contains(b"world"));
     /// ```
+    #[inline]
     #[must_use]
     pub fn contains(&self, pattern: &[u8]) -> bool {
-        self.inner
-            .binary_search_by(|&suffix| {
-                self.data[suffix as usize..]
-                    .iter()
-                    .take(pattern.len())
-                    .cmp(pattern.iter())
-            })
+        self.inner[self.candidates(pattern)]
+            .binary_search_by(|&suffix| self.compare_prefix(suffix, pattern))
             .is_ok()
     }
 
@@ -70,7 +551,8 @@ impl<'a> SuffixArray<'a> {
     ///
     /// Returns `None` if no matching suffix is found.
     ///
-    /// This operation runs in *O*(*m* \* log(*n*)) time, where `m` is `pattern.len()`.
+    /// This operation runs in *O*(*m* \* log(*n*)) time, where `m` is `pattern.len()`, and never
+    /// allocates. As with [`contains()`](SuffixArray::contains), a prefilter narrows the search.
     ///
     /// # Examples
     ///
@@ -86,84 +568,192 @@ impl<'a> SuffixArray<'a> {
     /// assert_eq!(sa.longest_match(b"find").as_deref(), Some(b"fi".as_ref()));
     /// assert_eq!(sa.longest_match(b"Red fish\0 swim").as_deref(), Some(b"Red fish\0".as_ref()));
     /// ```
+    #[inline]
     #[must_use]
     pub fn longest_match(&self, pattern: &[u8]) -> Option<Substring<'_>> {
-        macro_rules! suffix {
-            ($i: expr) => {
-                &self.data[$i as usize..]
-            };
+        // Binary search our suffixes to find a match for `pattern`
+        let candidates = self.candidates(pattern);
+        let start = candidates.start;
+        match self.inner[candidates]
+            .binary_search_by(|&suffix| self.compare_prefix(suffix, pattern))
+        {
+            Ok(rank) => Some(self.substring(self.inner[start + rank] as usize, pattern.len())),
+            Err(sorted_pos) => self.partial_match(pattern, start + sorted_pos),
         }
+    }
 
-        macro_rules! len {
-            ($i: expr) => {
-                common_prefix_len(suffix!($i), pattern)
-            };
-        }
+    /// Returns the longest match of each of `patterns`, in the same order as `patterns`.
+    ///
+    /// The result for each pattern is as long as that of [`longest_match()`], although it may be
+    /// found at a different position if the pattern occurs more than once. The patterns are
+    /// searched for in sorted order, each search starting where the previous one ended, so that
+    /// consecutive searches touch nearby ranks of the suffix array instead of descending from the
+    /// middle of it every time. This makes the batch considerably cheaper than calling
+    /// [`longest_match()`] for each pattern when there are many patterns.
+    ///
+    /// Unlike the single-pattern searches, this operation allocates: it sorts `patterns.len()`
+    /// indices and returns one result per pattern.
+    ///
+    /// [`longest_match()`]: SuffixArray::longest_match
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sufsort::SuffixArray;
+    ///
+    /// let sa = SuffixArray::new(b"Red fish, blue fish\0");
+    /// let matches = sa.longest_match_many(&[b"blue whale".as_ref(), b"zebra", b"fishy"]);
+    ///
+    /// assert_eq!(matches[0].as_deref(), Some(b"blue ".as_ref()));
+    /// assert_eq!(matches[1], None);
+    /// assert_eq!(matches[2].as_deref(), Some(b"fish".as_ref()));
+    /// ```
+    #[must_use]
+    pub fn longest_match_many<P>(&self, patterns: &[P]) -> Vec<Option<Substring<'_>>>
+    where
+        P: AsRef<[u8]>,
+    {
+        let mut order: Vec<usize> = (0..patterns.len()).collect();
+        order.sort_unstable_by(|&a, &b| patterns[a].as_ref().cmp(patterns[b].as_ref()));
 
-        macro_rules! substring {
-            ($position: expr, $len: expr) => {
-                Substring {
-                    position: $position,
-                    data: &self.data[$position..($position + $len)],
+        let mut matches = vec![None; patterns.len()];
+        // The rank at which a pattern would be inserted never decreases as the patterns increase,
+        // so every search can start at the previous pattern's rank
+        let mut rank = 0;
+        for i in order {
+            let pattern = patterns[i].as_ref();
+            rank = self.lower_bound_from(pattern, rank);
+            matches[i] = match self.inner.get(rank) {
+                Some(&suffix) if self.compare_prefix(suffix, pattern) == Ordering::Equal => {
+                    Some(self.substring(suffix as usize, pattern.len()))
                 }
+                _ => self.partial_match(pattern, rank),
             };
         }
 
-        // Binary search our suffixes to find a match for `pattern`
-        let search_result = self
-            .inner
-            .binary_search_by(|&suffix_index| {
-                suffix!(suffix_index)
-                    .iter()
-                    .take(pattern.len())
-                    .cmp(pattern.iter())
-            })
-            .map(|i| self.inner[i] as usize);
-
-        match search_result {
-            Ok(position) => Some(substring!(position, len!(position))),
-            Err(sorted_pos) => {
-                // The full pattern wasn't found, meaning that either:
-                //
-                // 1. A partial match was found in a sorted suffix ot the left or right side of
-                //    `sorted_pos`.
-                // 2. No match was found whatsoever.
-                //
-                // Therefore, find the longest common prefix lengths between the pattern and the
-                // sorted suffixes to the left and right of our position to determine which one
-                // contains the longest match.
-                //
-                // The presence of the sentinel guarantees 1 <= `sorted_pos` <= data.len(), so the
-                // following subtractions should never underflow.
-                let left_lcp_len = len!(self.inner[sorted_pos - 1]);
-                let right_lcp_len = self.inner.get(sorted_pos).map_or(0, |p| len!(*p));
-
-                match left_lcp_len.cmp(&right_lcp_len) {
-                    Ordering::Less => {
-                        Some(substring!(self.inner[sorted_pos] as usize, right_lcp_len))
-                    }
-                    Ordering::Equal => {
-                        // It doesn't matter whether we use left_lcp_len or right_lcp_len here, so
-                        // choose left_lcp_len arbitrarily
-                        if left_lcp_len == 0 {
-                            None
-                        } else {
-                            Some(substring!(
-                                self.inner[sorted_pos - 1] as usize,
-                                left_lcp_len
-                            ))
-                        }
-                    }
-                    Ordering::Greater => Some(substring!(
-                        self.inner[sorted_pos - 1] as usize,
-                        left_lcp_len
-                    )),
+        matches
+    }
+
+    /// Returns the rank of the first suffix at or after `start` that doesn't sort before
+    /// `pattern`, assuming every suffix before `start` does.
+    ///
+    /// The search gallops away from `start` before bisecting, so it's cheaper the closer the
+    /// result is to `start`.
+    fn lower_bound_from(&self, pattern: &[u8], start: usize) -> usize {
+        let less = |rank: usize| self.compare_prefix(self.inner[rank], pattern) == Ordering::Less;
+
+        let mut low = start;
+        let mut high = start;
+        let mut step = 1;
+        while high < self.inner.len() && less(high) {
+            low = high + 1;
+            high = low.saturating_add(step);
+            step = step.saturating_mul(2);
+        }
+        let high = high.min(self.inner.len());
+
+        low + self.inner[low..high]
+            .partition_point(|&suffix| self.compare_prefix(suffix, pattern) == Ordering::Less)
+    }
+
+    /// Returns the range of ranks of the suffixes which may begin with `pattern`
+    ///
+    /// Every suffix before the range sorts before `pattern` and every suffix after it sorts after
+    /// `pattern`, so searching the range finds the same rank as searching every suffix.
+    #[inline]
+    fn candidates(&self, pattern: &[u8]) -> Range<usize> {
+        match (&self.buckets, pattern) {
+            (Some(buckets), [first]) => {
+                let key = usize::from(*first) << 8;
+                buckets[key] as usize..buckets[key + 0x100] as usize
+            }
+            (Some(buckets), [_, _, ..]) => {
+                let key = bucket(pattern);
+                buckets[key] as usize..buckets[key + 1] as usize
+            }
+            _ => 0..self.inner.len(),
+        }
+    }
+
+    /// Returns the longest match of `pattern` in the suffixes next to `sorted_pos`, the rank at
+    /// which `pattern` would be inserted if it doesn't occur in the associated data
+    fn partial_match(&self, pattern: &[u8], sorted_pos: usize) -> Option<Substring<'_>> {
+        let len = |suffix: u32| common_prefix_len(&self.data[suffix as usize..], pattern);
+
+        // The full pattern wasn't found, meaning that either:
+        //
+        // 1. A partial match was found in a sorted suffix ot the left or right side of
+        //    `sorted_pos`.
+        // 2. No match was found whatsoever.
+        //
+        // Therefore, find the longest common prefix lengths between the pattern and the
+        // sorted suffixes to the left and right of our position to determine which one
+        // contains the longest match.
+        //
+        // The presence of the sentinel guarantees 1 <= `sorted_pos` <= data.len(), so the
+        // following subtractions should never underflow.
+        let left_lcp_len = len(self.inner[sorted_pos - 1]);
+        let right_lcp_len = self.inner.get(sorted_pos).map_or(0, |&p| len(p));
+
+        match left_lcp_len.cmp(&right_lcp_len) {
+            Ordering::Less => Some(self.substring(self.inner[sorted_pos] as usize, right_lcp_len)),
+            Ordering::Equal => {
+                // It doesn't matter whether we use left_lcp_len or right_lcp_len here, so
+                // choose left_lcp_len arbitrarily
+                if left_lcp_len == 0 {
+                    None
+                } else {
+                    Some(self.substring(self.inner[sorted_pos - 1] as usize, left_lcp_len))
                 }
             }
+            Ordering::Greater => {
+                Some(self.substring(self.inner[sorted_pos - 1] as usize, left_lcp_len))
+            }
+        }
+    }
+
+    /// Compares the first `pattern.len()` bytes of the suffix at `suffix` with `pattern`
+    #[inline]
+    fn compare_prefix(&self, suffix: u32, pattern: &[u8]) -> Ordering {
+        self.data[suffix as usize..]
+            .iter()
+            .take(pattern.len())
+            .cmp(pattern.iter())
+    }
+
+    #[inline]
+    fn substring(&self, position: usize, len: usize) -> Substring<'_> {
+        Substring {
+            position,
+            data: &self.data[position..position + len],
         }
     }
 }
 
+/// Returns whether `suffixes` can be the sorted suffixes of `data` without sorting them
+#[cfg(any(feature = "mapped", feature = "owned"))]
+fn valid_suffixes(data: &[u8], suffixes: &[u32]) -> bool {
+    data.last() == Some(&0)
+        && suffixes.len() == data.len()
+        && suffixes
+            .iter()
+            .all(|&position| (position as usize) < data.len())
+}
+
+/// The number of buckets of a prefilter, one for each pair of bytes
+const BUCKETS: usize = 1 << 16;
+
+/// Returns the prefilter bucket of `suffix`, i.e., its first two bytes, where a missing second
+/// byte counts as 0
+#[inline]
+fn bucket(suffix: &[u8]) -> usize {
+    let first = usize::from(suffix[0]);
+    let second = suffix.get(1).copied().map_or(0, usize::from);
+
+    first << 8 | second
+}
+
+#[inline]
 fn common_prefix_len(a: &[u8], b: &[u8]) -> usize {
     a.iter().zip(b).take_while(|(x, y)| x == y).count()
 }
@@ -216,6 +806,9 @@ impl<'a> Deref for Substring<'a> {
 
 #[cfg(test)]
 mod tests {
+    #[cfg(feature = "owned")]
+    use alloc::vec;
+
     use super::*;
 
     #[test]
@@ -299,6 +892,84 @@ mod tests {
         assert_eq!(substring, None);
     }
 
+    #[cfg(feature = "ranges")]
+    #[test]
+    fn equal_range_multiple_matches() {
+        let data = b"abracadabra\0";
+        let sa = SuffixArray::new(data);
+        let ranks = sa.equal_range(b"abra");
+
+        let mut positions = sa.suffixes()[ranks].to_vec();
+        positions.sort_unstable();
+        assert_eq!(positions, [0, 7]);
+    }
+
+    #[cfg(feature = "ranges")]
+    #[test]
+    fn equal_range_no_matches() {
+        let data = b"abracadabra\0";
+        let sa = SuffixArray::new(data);
+
+        assert!(sa.equal_range(b"abc").is_empty());
+    }
+
+    #[cfg(feature = "ranges")]
+    #[test]
+    fn equal_range_empty_pattern() {
+        let data = b"abracadabra\0";
+        let sa = SuffixArray::new(data);
+
+        assert_eq!(sa.equal_range(b""), 0..sa.len());
+    }
+
+    #[test]
+    fn from_chunks_matches_contiguous_data() {
+        let data: Vec<u8> = (0..1000u32).map(|i| (i * 7 % 13) as u8 + b'a').collect();
+        let mut contiguous = data.clone();
+        contiguous.push(0);
+        let expected = SuffixArray::new(&contiguous);
+
+        for chunk_len in [1, 7, 128, 999, 1000] {
+            let mut buf = vec![0xff; 3];
+            let sa = SuffixArray::from_chunks(data.chunks(chunk_len), &mut buf);
+
+            assert_eq!(sa, expected, "chunks of {chunk_len} bytes");
+        }
+
+        let chunks = [b"".as_slice(), b"ban", b"", b"ana"];
+        let mut buf = Vec::new();
+        assert_eq!(
+            SuffixArray::from_chunks(chunks, &mut buf),
+            SuffixArray::new(b"banana\0"),
+        );
+        let mut buf = Vec::new();
+        assert_eq!(
+            SuffixArray::from_chunks([], &mut buf),
+            SuffixArray::new(b"\0"),
+        );
+    }
+
+    #[test]
+    fn suffixes_are_sorted() {
+        let data = b"The quick brown fox jumped over the lazy dog\0";
+        let sa = SuffixArray::new(data);
+
+        assert!(
+            (1..sa.len()).all(|rank| sa.suffix(rank - 1) < sa.suffix(rank)),
+            "suffixes are not in sorted order",
+        );
+    }
+
+    #[cfg(feature = "ranges")]
+    #[test]
+    fn search_by_finds_rank() {
+        let data = b"Hello, world!\0";
+        let sa = SuffixArray::new(data);
+        let rank = sa.search_by(|suffix| suffix.cmp(b"world!\0")).unwrap();
+
+        assert_eq!(sa.suffixes()[rank], 7);
+    }
+
     #[test]
     fn substring_match_longer_pattern() {
         let data = b"Red fish\0";
@@ -308,4 +979,158 @@ mod tests {
         assert_eq!(substring.position(), 4);
         assert_eq!(substring.deref(), b"fish\0");
     }
+
+    #[test]
+    fn longest_match_many_matches_longest_match() {
+        let data = b"The quick brown fox jumped over the lazy dog because the fox was quick\0";
+        let sa = SuffixArray::new(data);
+        let patterns: [&[u8]; 10] = [
+            b"fox",
+            b"quick",
+            b"the lazy cat",
+            b"zebra",
+            b"",
+            b"quick",
+            b"\0",
+            b"T",
+            b"dog\0x",
+            b"because",
+        ];
+
+        let matches = sa.longest_match_many(&patterns);
+        assert_eq!(matches.len(), patterns.len());
+        for (pattern, found) in patterns.iter().zip(matches) {
+            let expected = sa.longest_match(pattern);
+            assert_eq!(found.as_deref(), expected.as_deref(), "{pattern:?}");
+            if let Some(found) = found {
+                assert_eq!(
+                    &data[found.position()..found.position() + found.len()],
+                    found.deref(),
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn prefilter_matches_full_search() {
+        let data = b"abracadabra\0\0abra\0zz\xff\xff\xffa\0";
+        let sa = SuffixArray::new(data);
+        let prefiltered = sa.clone().with_prefilter();
+        let patterns: [&[u8]; 12] = [
+            b"",
+            b"\0",
+            b"\0\0",
+            b"\0\0\0",
+            b"a",
+            b"abra\0\0",
+            b"cad",
+            b"b",
+            b"z",
+            b"zzz",
+            b"\xff\xff",
+            b"\xffa\0x",
+        ];
+
+        for pattern in patterns {
+            assert_eq!(
+                prefiltered.contains(pattern),
+                sa.contains(pattern),
+                "{pattern:?}",
+            );
+            assert_eq!(
+                prefiltered.longest_match(pattern).as_deref(),
+                sa.longest_match(pattern).as_deref(),
+                "{pattern:?}",
+            );
+        }
+    }
+
+    #[test]
+    fn longest_match_many_no_patterns() {
+        let sa = SuffixArray::new(b"Hello, world!\0");
+
+        assert!(sa.longest_match_many::<&[u8]>(&[]).is_empty());
+    }
+
+    #[cfg(feature = "lcp")]
+    #[test]
+    fn lcp_matches_naive() {
+        let data = b"abracadabra\0\0abra\0";
+        let sa = SuffixArray::new(data);
+        let lcp = sa.lcp();
+
+        assert_eq!(lcp[0], 0);
+        for (rank, &len) in lcp.iter().enumerate().skip(1) {
+            let expected =
+                common_prefix_len(sa.suffix(rank - 1).unwrap(), sa.suffix(rank).unwrap());
+            assert_eq!(len as usize, expected, "wrong LCP at rank {rank}");
+        }
+    }
+
+    #[cfg(feature = "bwt")]
+    #[test]
+    fn bwt_is_permutation() {
+        let data = b"The quick brown fox jumped over the lazy dog\0";
+        let sa = SuffixArray::new(data);
+
+        let mut bwt = sa.bwt();
+        let mut sorted = data.to_vec();
+        bwt.sort_unstable();
+        sorted.sort_unstable();
+        assert_eq!(bwt, sorted);
+    }
+
+    #[cfg(feature = "owned")]
+    #[test]
+    fn from_suffixes_rejects_invalid() {
+        let data = b"banana\0";
+
+        assert_eq!(SuffixArray::from_suffixes(data, vec![0; 6]), None);
+        assert_eq!(SuffixArray::from_suffixes(data, vec![7; 7]), None);
+        assert_eq!(SuffixArray::from_suffixes(b"banana", vec![0; 6]), None);
+    }
+
+    #[cfg(feature = "owned")]
+    #[test]
+    fn from_suffix_slice_borrows() {
+        let data = b"The quick brown fox jumped over the lazy dog\0";
+        let suffixes = SuffixArray::new(data).into_suffixes();
+        let borrowed = SuffixArray::from_suffix_slice(data, &suffixes).unwrap();
+
+        assert_eq!(borrowed, SuffixArray::new(data));
+        assert!(matches!(borrowed.inner, Cow::Borrowed(_)));
+        assert_eq!(SuffixArray::from_suffix_slice(data, &suffixes[1..]), None);
+    }
+
+    #[cfg(feature = "mapped")]
+    #[test]
+    fn from_suffix_bytes_borrows() {
+        let data = b"The quick brown fox jumped over the lazy dog\0";
+        let sa = SuffixArray::new(data);
+        let borrowed = SuffixArray::from_suffix_bytes(data, sa.suffix_bytes()).unwrap();
+
+        assert_eq!(borrowed, sa);
+        assert!(matches!(borrowed.inner, Cow::Borrowed(_)));
+        assert_eq!(
+            borrowed.longest_match(b"lazy cat").unwrap().position(),
+            sa.longest_match(b"lazy cat").unwrap().position(),
+        );
+    }
+
+    #[cfg(feature = "mapped")]
+    #[test]
+    fn from_suffix_bytes_rejects_invalid() {
+        let data = b"banana\0";
+        let invalid = [7u32; 7];
+        let sa = SuffixArray::new(data);
+        let bytes = sa.suffix_bytes();
+
+        assert_eq!(SuffixArray::from_suffix_bytes(data, &bytes[4..]), None);
+        assert_eq!(SuffixArray::from_suffix_bytes(data, &bytes[1..25]), None);
+        assert_eq!(
+            SuffixArray::from_suffix_bytes(data, bytemuck::cast_slice(&invalid)),
+            None,
+        );
+        assert_eq!(SuffixArray::from_suffix_bytes(b"banana", &bytes[4..]), None);
+    }
 }
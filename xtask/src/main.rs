@@ -0,0 +1,222 @@
+// SPDX-FileCopyrightText: © 2026 Logan Magee
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Development tasks for this workspace, run via `cargo xtask <task>`
+//!
+//! Tasks:
+//!
+//! - `format-spec [--check]`: renders the description of the patch format to
+//!   `ina/format-spec.json`, or with `--check`, fails if the file is out of date
+//! - `safe-only`: checks that `ina` builds with the `safe-only` feature and every feature which
+//!   doesn't need unsafe code, and that it fails to build once unsafe code is added
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::{Command, ExitCode, Output},
+};
+
+use ina::{
+    Codec,
+    format::{self, CODECS, HEADER, RECORD_LAYOUT, TAGGED_FIELDS},
+};
+use serde_json::{Value, json};
+
+fn main() -> ExitCode {
+    let args: Vec<_> = env::args().skip(1).collect();
+    let args: Vec<_> = args.iter().map(String::as_str).collect();
+
+    match args.as_slice() {
+        ["format-spec"] => match fs::write(spec_path(), render_spec()) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                eprintln!("Failed to write '{}': {e}", spec_path().display());
+                ExitCode::FAILURE
+            }
+        },
+        ["format-spec", "--check"] => {
+            if fs::read_to_string(spec_path()).is_ok_and(|spec| spec == render_spec()) {
+                ExitCode::SUCCESS
+            } else {
+                eprintln!(
+                    "'{}' is out of date. Run `cargo xtask format-spec` to update it.",
+                    spec_path().display(),
+                );
+                ExitCode::FAILURE
+            }
+        }
+        ["safe-only"] => match check_safe_only() {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                eprintln!("{e}");
+                ExitCode::FAILURE
+            }
+        },
+        _ => {
+            eprintln!("Usage: cargo xtask <format-spec [--check] | safe-only>");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// The features of `ina` which rely on unsafe code
+const UNSAFE_FEATURES: [&str; 3] = ["mmap", "reflink", "sandbox"];
+
+/// Checks that `ina` forbids unsafe code with the `safe-only` feature
+///
+/// The features combined with `safe-only` are read from the manifest rather than listed here, so
+/// features added later are covered without having to remember to add them.
+fn check_safe_only() -> Result<(), String> {
+    let features = safe_features()?;
+
+    if !cargo_check(&features, false)?.status.success() {
+        return Err("`ina` doesn't build with the `safe-only` feature".to_owned());
+    }
+    for feature in UNSAFE_FEATURES {
+        expect_failure(
+            &cargo_check(&format!("safe-only,{feature}"), false)?,
+            "the `safe-only` feature can't be combined",
+            &format!("`ina` builds with both the `safe-only` and `{feature}` features"),
+        )?;
+    }
+    expect_failure(
+        &cargo_check(&features, true)?,
+        "unsafe_code",
+        "`ina` builds with unsafe code despite the `safe-only` feature",
+    )
+}
+
+/// Returns the comma-separated features of `ina` which don't rely on unsafe code, along with
+/// `safe-only`
+fn safe_features() -> Result<String, String> {
+    let output = Command::new(cargo())
+        .args(["metadata", "--format-version", "1", "--no-deps"])
+        .output()
+        .map_err(|e| format!("Failed to run `cargo metadata`: {e}"))?;
+    let metadata: Value = serde_json::from_slice(&output.stdout)
+        .map_err(|e| format!("Failed to parse the output of `cargo metadata`: {e}"))?;
+    let features = metadata["packages"]
+        .as_array()
+        .and_then(|packages| packages.iter().find(|package| package["name"] == "ina"))
+        .and_then(|ina| ina["features"].as_object())
+        .ok_or("The features of `ina` are missing from the output of `cargo metadata`")?;
+
+    // The default features are already listed individually
+    Ok(features
+        .keys()
+        .map(String::as_str)
+        .filter(|feature| *feature != "default" && !UNSAFE_FEATURES.contains(feature))
+        .collect::<Vec<_>>()
+        .join(","))
+}
+
+/// Runs `cargo check` on `ina` with only `features` enabled, optionally adding unsafe code
+///
+/// The checks get their own target directory, since the flags adding unsafe code would otherwise
+/// invalidate the regular build of every crate.
+fn cargo_check(features: &str, with_unsafe: bool) -> Result<Output, String> {
+    let mut command = Command::new(cargo());
+    command
+        .args([
+            "check",
+            "--package",
+            "ina",
+            "--no-default-features",
+            "--features",
+        ])
+        .arg(features)
+        .arg("--target-dir")
+        .arg(Path::new(env!("CARGO_MANIFEST_DIR")).join("../target/safe-only"));
+    if with_unsafe {
+        let flags = env::var("RUSTFLAGS").unwrap_or_default();
+        command.env("RUSTFLAGS", format!("{flags} --cfg ina_unsafe_probe"));
+    }
+
+    command
+        .output()
+        .map_err(|e| format!("Failed to run `cargo check`: {e}"))
+}
+
+/// Returns an error with `message` unless `output` is of a failed build whose errors mention
+/// `expected`
+fn expect_failure(output: &Output, expected: &str, message: &str) -> Result<(), String> {
+    if !output.status.success() && String::from_utf8_lossy(&output.stderr).contains(expected) {
+        Ok(())
+    } else {
+        Err(message.to_owned())
+    }
+}
+
+/// Returns the Cargo executable this task was run with
+fn cargo() -> String {
+    env::var("CARGO").unwrap_or_else(|_| "cargo".to_owned())
+}
+
+/// Returns the path of the rendered format description
+fn spec_path() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("../ina/format-spec.json")
+}
+
+/// Renders the description of the patch format as pretty-printed JSON
+fn render_spec() -> String {
+    let mut spec = serde_json::to_string_pretty(&spec()).expect("JSON values always serialize");
+    spec.push('\n');
+
+    spec
+}
+
+/// Describes the patch format from the constants the `ina` crate reads and writes patches with
+fn spec() -> Value {
+    let header: Vec<_> = HEADER
+        .iter()
+        .map(|field| json!({ "part": field.to_string(), "layout": field.layout() }))
+        .collect();
+    let tagged_fields: Vec<_> = TAGGED_FIELDS
+        .iter()
+        .map(|field| {
+            json!({
+                "tag": field.tag(),
+                "name": field.name(),
+                "feature": field.feature().to_string(),
+                "layout": field.layout(),
+            })
+        })
+        .collect();
+    let codecs: Vec<_> = CODECS
+        .iter()
+        .map(|codec| {
+            json!({
+                "id": codec.id(),
+                "name": codec.to_string(),
+                "default": *codec == Codec::default(),
+            })
+        })
+        .collect();
+
+    json!({
+        "magic": format::MAGIC.iter().map(|byte| format!("{byte:02x}")).collect::<String>(),
+        "mime_type": format::MIME_TYPE,
+        "version": { "major": format::VERSION_MAJOR, "minor": format::VERSION_MINOR },
+        "header": header,
+        "tagged_field": ina::HeaderField::ExtensionField.layout(),
+        "tagged_fields": tagged_fields,
+        "record": RECORD_LAYOUT,
+        "codecs": codecs,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    #[test]
+    fn format_spec_is_up_to_date() {
+        let spec = fs::read_to_string(super::spec_path()).unwrap_or_default();
+
+        assert!(
+            spec == super::render_spec(),
+            "ina/format-spec.json is out of date. Run `cargo xtask format-spec` to update it.",
+        );
+    }
+}